@@ -0,0 +1,412 @@
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+
+use crate::fields::Fr;
+
+// Grumpkin's group order is BN254's base field modulus; its base field is Fr.
+const ORDER: &str = "21888242871839275222246405745257275088696311157297823662689037894645226208583";
+const GENERATOR_Y: &str = "17631683881184975370165255887551781615748388533673675138860";
+
+/// The number of points on the curve, which scalars are taken modulo.
+pub fn order() -> BigUint {
+    BigUint::parse_bytes(ORDER.as_bytes(), 10).expect("valid order")
+}
+
+/// Whether `y` is in the lower half of the field. Compressed points and
+/// address points keep this as their sign.
+pub fn is_positive(y: &Fr) -> bool {
+    y.0 <= (Fr::modulus() - 1u32) >> 1
+}
+
+/// A point on Grumpkin (y² = x³ − 17 over Fr). The point at infinity has no
+/// affine form; functions that can reach it return `Option`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AffinePoint {
+    pub x: Fr,
+    pub y: Fr,
+}
+
+impl AffinePoint {
+    pub fn generator() -> Self {
+        AffinePoint {
+            x: Fr::from(1u8),
+            y: Fr(BigUint::parse_bytes(GENERATOR_Y.as_bytes(), 10).expect("valid generator")),
+        }
+    }
+
+    pub fn new(x: Fr, y: Fr) -> Result<Self, String> {
+        let point = AffinePoint { x, y };
+        if !point.is_on_curve() {
+            return Err(format!(
+                "({}, {}) is not on Grumpkin",
+                point.x.to_hex(),
+                point.y.to_hex()
+            ));
+        }
+        Ok(point)
+    }
+
+    pub fn is_on_curve(&self) -> bool {
+        let p = Fr::modulus();
+        let (x, y) = (&self.x.0, &self.y.0);
+        x < &p && y < &p && (y * y) % &p == curve_rhs(x, &p)
+    }
+
+    /// The point with this x and a positive y, if there is one.
+    pub fn from_x(x: &Fr) -> Option<Self> {
+        let p = Fr::modulus();
+        if x.0 >= p {
+            return None;
+        }
+        let y = Fr(sqrt(&curve_rhs(&x.0, &p), &p)?);
+        let y = if is_positive(&y) { y } else { Fr(&p - y.0) };
+        Some(AffinePoint { x: x.clone(), y })
+    }
+
+    pub fn neg(&self) -> Self {
+        AffinePoint {
+            x: self.x.clone(),
+            y: Fr((Fr::modulus() - &self.y.0) % Fr::modulus()),
+        }
+    }
+
+    pub fn to_projective(&self) -> ProjectivePoint {
+        ProjectivePoint {
+            x: self.x.0.clone(),
+            y: self.y.0.clone(),
+            z: BigUint::one(),
+        }
+    }
+
+    /// x, big-endian, with the top bit set when y is positive (aztec.js'
+    /// `Point.toCompressedBuffer`).
+    pub fn compress(&self) -> [u8; 32] {
+        let mut compressed = self.x.to_be_bytes();
+        if is_positive(&self.y) {
+            compressed[0] |= 0x80;
+        }
+        compressed
+    }
+
+    pub fn decompress(bytes: &[u8]) -> Result<Self, String> {
+        let bytes: [u8; 32] = bytes
+            .try_into()
+            .map_err(|_| format!("Compressed point needs 32 bytes, got {}", bytes.len()))?;
+        let mut x = bytes;
+        x[0] &= 0x7f;
+        let x = Fr::from_be_bytes(&x)?;
+        let point =
+            Self::from_x(&x).ok_or_else(|| format!("No Grumpkin point has x = {}", x.to_hex()))?;
+        Ok(if bytes[0] & 0x80 != 0 {
+            point
+        } else {
+            point.neg()
+        })
+    }
+}
+
+/// x then y, 32 big-endian bytes each (aztec.js' `Point.toBuffer`); the
+/// point at infinity is all zeros.
+pub fn serialize(point: Option<&AffinePoint>) -> [u8; 64] {
+    let mut bytes = [0u8; 64];
+    if let Some(point) = point {
+        bytes[..32].copy_from_slice(&point.x.to_be_bytes());
+        bytes[32..].copy_from_slice(&point.y.to_be_bytes());
+    }
+    bytes
+}
+
+pub fn deserialize(bytes: &[u8]) -> Result<Option<AffinePoint>, String> {
+    if bytes.len() != 64 {
+        return Err(format!("Point needs 64 bytes, got {}", bytes.len()));
+    }
+    if bytes.iter().all(|b| *b == 0) {
+        return Ok(None);
+    }
+    let x = Fr::from_be_bytes(&bytes[..32])?;
+    let y = Fr::from_be_bytes(&bytes[32..])?;
+    AffinePoint::new(x, y).map(Some)
+}
+
+/// Jacobian coordinates: (X, Y, Z) is (X/Z², Y/Z³), and Z = 0 is the point
+/// at infinity. Additions skip the inversion that affine ones pay for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectivePoint {
+    pub x: BigUint,
+    pub y: BigUint,
+    pub z: BigUint,
+}
+
+impl ProjectivePoint {
+    pub fn infinity() -> Self {
+        ProjectivePoint {
+            x: BigUint::one(),
+            y: BigUint::one(),
+            z: BigUint::zero(),
+        }
+    }
+
+    pub fn is_infinity(&self) -> bool {
+        self.z.is_zero()
+    }
+
+    pub fn double(&self) -> Self {
+        if self.is_infinity() || self.y.is_zero() {
+            return Self::infinity();
+        }
+        let p = Fr::modulus();
+        let yy = &self.y * &self.y % &p;
+        let s = BigUint::from(4u32) * &self.x * &yy % &p;
+        let m = BigUint::from(3u32) * &self.x * &self.x % &p;
+        let x = sub(&(&m * &m), &(BigUint::from(2u32) * &s), &p);
+        let y = sub(
+            &(&m * sub(&s, &x, &p)),
+            &(BigUint::from(8u32) * &yy * &yy),
+            &p,
+        );
+        let z = BigUint::from(2u32) * &self.y * &self.z % &p;
+        ProjectivePoint { x, y, z }
+    }
+
+    pub fn add(&self, other: &Self) -> Self {
+        if self.is_infinity() {
+            return other.clone();
+        }
+        if other.is_infinity() {
+            return self.clone();
+        }
+        let p = Fr::modulus();
+        let z1z1 = &self.z * &self.z % &p;
+        let z2z2 = &other.z * &other.z % &p;
+        let u1 = &self.x * &z2z2 % &p;
+        let u2 = &other.x * &z1z1 % &p;
+        let s1 = &self.y * &z2z2 * &other.z % &p;
+        let s2 = &other.y * &z1z1 * &self.z % &p;
+        let h = sub(&u2, &u1, &p);
+        let r = sub(&s2, &s1, &p);
+        if h.is_zero() {
+            return if r.is_zero() {
+                self.double()
+            } else {
+                Self::infinity()
+            };
+        }
+        let hh = &h * &h % &p;
+        let hhh = &hh * &h % &p;
+        let v = &u1 * &hh % &p;
+        let x = sub(&sub(&(&r * &r), &hhh, &p), &(BigUint::from(2u32) * &v), &p);
+        let y = sub(&(&r * sub(&v, &x, &p)), &(&s1 * &hhh), &p);
+        let z = &self.z * &other.z * &h % &p;
+        ProjectivePoint { x, y, z }
+    }
+
+    /// Double-and-add, most significant bit first.
+    pub fn mul(&self, scalar: &BigUint) -> Self {
+        let mut result = Self::infinity();
+        for i in (0..scalar.bits()).rev() {
+            result = result.double();
+            if scalar.bit(i) {
+                result = result.add(self);
+            }
+        }
+        result
+    }
+
+    pub fn to_affine(&self) -> Option<AffinePoint> {
+        if self.is_infinity() {
+            return None;
+        }
+        let p = Fr::modulus();
+        let z_inv = inverse(&self.z, &p);
+        let z_inv2 = &z_inv * &z_inv % &p;
+        Some(AffinePoint {
+            x: Fr(&self.x * &z_inv2 % &p),
+            y: Fr(&self.y * &z_inv2 * &z_inv % &p),
+        })
+    }
+}
+
+/// Affine addition; `None` is the point at infinity.
+pub fn add(a: Option<&AffinePoint>, b: Option<&AffinePoint>) -> Option<AffinePoint> {
+    let (a, b) = match (a, b) {
+        (None, b) => return b.cloned(),
+        (a, None) => return a.cloned(),
+        (Some(a), Some(b)) => (a, b),
+    };
+    let p = Fr::modulus();
+    let (ax, ay, bx, by) = (&a.x.0, &a.y.0, &b.x.0, &b.y.0);
+    let lambda = if ax == bx {
+        if ((ay + by) % &p).is_zero() {
+            return None;
+        }
+        BigUint::from(3u32) * ax * ax * inverse(&(BigUint::from(2u32) * ay), &p)
+    } else {
+        sub(by, ay, &p) * inverse(&sub(bx, ax, &p), &p)
+    } % &p;
+    let x = sub(&sub(&(&lambda * &lambda), ax, &p), bx, &p);
+    let y = sub(&(lambda * sub(ax, &x, &p)), ay, &p);
+    Some(AffinePoint { x: Fr(x), y: Fr(y) })
+}
+
+/// `scalar · point`, computed in Jacobian coordinates.
+pub fn mul(point: &AffinePoint, scalar: &BigUint) -> Option<AffinePoint> {
+    point.to_projective().mul(scalar).to_affine()
+}
+
+fn curve_rhs(x: &BigUint, p: &BigUint) -> BigUint {
+    (x * x * x + p - 17u32) % p
+}
+
+fn sub(a: &BigUint, b: &BigUint, p: &BigUint) -> BigUint {
+    (a % p + p - b % p) % p
+}
+
+fn inverse(a: &BigUint, p: &BigUint) -> BigUint {
+    a.modpow(&(p - 2u32), p)
+}
+
+/// Tonelli-Shanks.
+fn sqrt(a: &BigUint, p: &BigUint) -> Option<BigUint> {
+    if a.is_zero() {
+        return Some(BigUint::zero());
+    }
+    let one = BigUint::one();
+    let half = (p - 1u32) >> 1;
+    if a.modpow(&half, p) != one {
+        return None;
+    }
+    let mut q = p - 1u32;
+    let mut s = 0u32;
+    while !q.bit(0) {
+        q >>= 1;
+        s += 1;
+    }
+    let mut z = BigUint::from(2u32);
+    while z.modpow(&half, p) == one {
+        z += 1u32;
+    }
+    let mut m = s;
+    let mut c = z.modpow(&q, p);
+    let mut t = a.modpow(&q, p);
+    let mut r = a.modpow(&((&q + 1u32) >> 1), p);
+    while t != one {
+        let mut i = 0;
+        let mut t2 = t.clone();
+        while t2 != one {
+            t2 = &t2 * &t2 % p;
+            i += 1;
+        }
+        let b = c.modpow(&(BigUint::one() << (m - i - 1)), p);
+        m = i;
+        c = &b * &b % p;
+        t = t * &c % p;
+        r = r * b % p;
+    }
+    Some(r)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scalar(n: u64) -> BigUint {
+        BigUint::from(n)
+    }
+
+    #[test]
+    fn test_generator_and_order() {
+        let g = AffinePoint::generator();
+        assert!(g.is_on_curve());
+        assert!(is_positive(&g.y));
+        assert_eq!(AffinePoint::from_x(&Fr::from(1u8)), Some(g.clone()));
+        assert_eq!(mul(&g, &order()), None);
+        assert_eq!(mul(&g, &(order() - 1u32)), Some(g.neg()));
+        assert_eq!(mul(&g, &(order() + 1u32)), Some(g.clone()));
+    }
+
+    fn point(x: &str, y: &str) -> AffinePoint {
+        AffinePoint::new(Fr::try_from(x).unwrap(), Fr::try_from(y).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_known_multiples_and_compression() {
+        let g = AffinePoint::generator();
+        // Noir's `embedded_curve_add` test: G + G.
+        let two = point(
+            "0x06ce1b0827aafa85ddeb49cdaa36306d19a74caa311e13d46d8bc688cdbffffe",
+            "0x1c122f81a3a14964909ede0ba2a6855fc93faf6fa1a788bf467be7e7a43f80ac",
+        );
+        assert_eq!(mul(&g, &scalar(2)), Some(two.clone()));
+        let k = BigUint::parse_bytes(
+            b"0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef",
+            16,
+        )
+        .unwrap();
+        let big = point(
+            "0x1e80aee3834e1297b47c859a4a26339b5c7c4c4096342b360e2b7ba402d1873e",
+            "0x0e3faf018f2e20f0811785d35bd8491f32e42833ede83ca1a5f3e1975ce7e25e",
+        );
+        assert_eq!(mul(&g, &k), Some(big.clone()));
+
+        // 2G's y is in the upper half, so its sign bit stays clear.
+        assert!(!is_positive(&two.y));
+        assert_eq!(
+            hex::encode(two.compress()),
+            "06ce1b0827aafa85ddeb49cdaa36306d19a74caa311e13d46d8bc688cdbffffe"
+        );
+        assert!(is_positive(&big.y));
+        assert_eq!(
+            hex::encode(big.compress()),
+            "9e80aee3834e1297b47c859a4a26339b5c7c4c4096342b360e2b7ba402d1873e"
+        );
+        assert_eq!(AffinePoint::decompress(&big.compress()), Ok(big));
+    }
+
+    #[test]
+    fn test_projective_matches_affine() {
+        let g = AffinePoint::generator();
+        let mut affine = None;
+        for n in 1..=20u64 {
+            affine = add(affine.as_ref(), Some(&g));
+            assert_eq!(mul(&g, &scalar(n)), affine, "{}·G", n);
+        }
+        assert_eq!(
+            mul(&g, &scalar(5)),
+            add(mul(&g, &scalar(2)).as_ref(), mul(&g, &scalar(3)).as_ref())
+        );
+
+        let a = mul(&g, &scalar(0xdeadbeef)).unwrap().to_projective();
+        let b = a.double().add(&a);
+        assert_eq!(b.to_affine(), mul(&g, &scalar(3 * 0xdeadbeef)));
+        assert!(a
+            .add(&a.to_affine().unwrap().neg().to_projective())
+            .is_infinity());
+        assert_eq!(ProjectivePoint::infinity().add(&a), a);
+    }
+
+    #[test]
+    fn test_compression_and_serialization() {
+        let g = AffinePoint::generator();
+        let mut expected = [0u8; 32];
+        expected[31] = 1;
+        assert_eq!(g.neg().compress(), expected);
+        expected[0] = 0x80;
+        assert_eq!(g.compress(), expected);
+
+        let point = mul(&g, &scalar(123_456_789)).unwrap();
+        for point in [point.clone(), point.neg()] {
+            assert_eq!(
+                AffinePoint::decompress(&point.compress()),
+                Ok(point.clone())
+            );
+            assert_eq!(deserialize(&serialize(Some(&point))), Ok(Some(point)));
+        }
+        assert_eq!(deserialize(&serialize(None)), Ok(None));
+
+        assert!(AffinePoint::decompress(&[0u8; 32]).is_err());
+        assert!(AffinePoint::decompress(&[0u8; 31]).is_err());
+        let mut off_curve = serialize(Some(&g));
+        off_curve[63] ^= 1;
+        assert!(deserialize(&off_curve).is_err());
+    }
+}
@@ -0,0 +1,1724 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use num_bigint::{BigInt, BigUint, Sign};
+use serde_json::Value;
+use sha3::{Digest, Keccak256};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use crate::fields::{AztecAddress, Fr};
+
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ContractArtifact {
+    pub name: String,
+    pub functions: Vec<FunctionArtifact>,
+    #[serde(rename = "nonDispatchPublicFunctions")]
+    pub non_dispatch_public_functions: Vec<FunctionAbi>,
+    #[serde(rename = "storageLayout")]
+    pub storage_layout: HashMap<String, FieldLayout>,
+    pub notes: HashMap<String, ContractNote>,
+    #[serde(rename = "fileMap")]
+    pub file_map: DebugFileMap,
+    #[serde(default)]
+    pub outputs: Outputs,
+    #[serde(skip)]
+    selectors: OnceLock<SelectorMap>,
+}
+
+impl ContractArtifact {
+    /// Selectors of every function, hashed on first use.
+    pub fn selectors(&self) -> &SelectorMap {
+        self.selectors.get_or_init(|| SelectorMap::new(self))
+    }
+
+    pub fn function_by_selector(&self, selector: &FunctionSelector) -> Option<&FunctionArtifact> {
+        self.selectors()
+            .index_of(selector)
+            .map(|i| &self.functions[i])
+    }
+
+    /// Event structs the contract emits, from `outputs.structs.events`.
+    pub fn events(&self) -> &[AbiType] {
+        self.outputs.structs.get("events").map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
+/// Function selectors of one artifact, looked up either way: selector to
+/// function (for decoding call stacks and logs) and name to selector.
+#[derive(Debug, Clone, Default)]
+pub struct SelectorMap {
+    by_selector: HashMap<FunctionSelector, (String, Option<usize>)>,
+    by_name: HashMap<String, FunctionSelector>,
+}
+
+impl SelectorMap {
+    /// Covers `functions` and `nonDispatchPublicFunctions`; only the former
+    /// resolve to a `FunctionArtifact`.
+    pub fn new(artifact: &ContractArtifact) -> Self {
+        let mut map = SelectorMap::default();
+        let functions = artifact.functions.iter().enumerate().map(|(i, f)| (&f.name, &f.parameters, Some(i)));
+        let non_dispatch = artifact.non_dispatch_public_functions.iter().map(|f| (&f.name, &f.parameters, None));
+        for (name, parameters, index) in functions.chain(non_dispatch) {
+            let selector = FunctionSelector::from_name_and_parameters(name, parameters);
+            map.by_name.entry(name.clone()).or_insert_with(|| selector.clone());
+            map.by_selector.entry(selector).or_insert_with(|| (name.clone(), index));
+        }
+        map
+    }
+
+    pub fn name_of(&self, selector: &FunctionSelector) -> Option<&str> {
+        self.by_selector.get(selector).map(|(name, _)| name.as_str())
+    }
+
+    pub fn selector_of(&self, name: &str) -> Option<&FunctionSelector> {
+        self.by_name.get(name)
+    }
+
+    /// Position of the function in `ContractArtifact::functions`.
+    pub fn index_of(&self, selector: &FunctionSelector) -> Option<usize> {
+        self.by_selector.get(selector).and_then(|(_, index)| *index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_selector.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_selector.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FunctionArtifact {
+    pub name: String,
+    pub parameters: Vec<AbiParameter>,
+    pub bytecode: String,
+    #[serde(rename = "verificationKey")]
+    pub verification_key: Option<String>,
+    #[serde(rename = "debugSymbols")]
+    pub debug_symbols: String,
+    pub debug: Option<FunctionDebugMetadata>,
+    #[serde(rename = "functionType")]
+    pub function_type: String,
+    #[serde(rename = "isStatic", default)]
+    pub is_static: bool,
+    #[serde(rename = "isInitializer", default)]
+    pub is_initializer: bool,
+    #[serde(rename = "returnTypes", default)]
+    pub return_types: Vec<AbiType>,
+}
+
+impl FunctionArtifact {
+    pub fn to_abi(&self) -> FunctionAbi {
+        FunctionAbi {
+            name: self.name.clone(),
+            function_type: self.function_type.clone(),
+            isInternal: false,
+            isStatic: self.is_static,
+            isInitializer: self.is_initializer,
+            parameters: self.parameters.clone(),
+            return_types: self.return_types.clone(),
+            errorTypes: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FunctionDebugMetadata {}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AbiParameter {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub abi_type: AbiType,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FieldLayout {
+    pub slot: String,
+    /// The storage type, e.g. `PublicMutable<Field>`, when the artifact has it.
+    #[serde(rename = "type", default)]
+    pub typ: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ContractNote {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub typ: String,
+    pub fields: Vec<NoteField>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NoteField {
+    pub name: String,
+    pub index: usize,
+    pub nullable: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DebugFileMap(pub HashMap<String, DebugFile>);
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DebugFile {
+    pub source: String,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FunctionSelector(pub String);
+
+impl FunctionSelector {
+    pub fn from_name_and_parameters(name: &str, params: &[AbiParameter]) -> Self {
+        let signature = format!(
+            "{}({})",
+            name,
+            params.iter()
+                .map(|p| p.abi_type.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+        Self::from_signature(&signature)
+    }
+
+    /// The selector of a signature spelled out, like aztec.js'
+    /// `FunctionSelector.fromSignature('transfer_in_public((Field),(Field),u128,Field)')`.
+    pub fn from_signature(signature: &str) -> Self {
+        let mut hasher = Keccak256::new();
+        hasher.update(signature.as_bytes());
+        let hash = hasher.finalize();
+        FunctionSelector(hex::encode(&hash[..4]))
+    }
+
+    /// Parses `0x27e740b2`, `27E740B2` or a shortened `0x2aa8`.
+    pub fn from_hex(s: &str) -> Result<Self, String> {
+        let digits = s.trim_start_matches("0x").to_lowercase();
+        if digits.is_empty() || digits.len() > 8 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("Invalid function selector '{}'", s));
+        }
+        Ok(FunctionSelector(format!("{:0>8}", digits)))
+    }
+}
+
+impl Serialize for FunctionSelector {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("0x{:0>8}", self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for FunctionSelector {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        FunctionSelector::from_hex(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+pub fn get_function_artifact<'a>(
+    artifact: &'a ContractArtifact,
+    name_or_selector: &str,
+) -> Result<&'a FunctionArtifact, String> {
+    if let Some(f) = artifact.functions.iter().find(|f| f.name == name_or_selector) {
+        return Ok(f);
+    }
+
+    FunctionSelector::from_hex(name_or_selector)
+        .ok()
+        .and_then(|selector| artifact.function_by_selector(&selector))
+        .ok_or_else(|| format!("Unknown function '{}'.", name_or_selector))
+}
+
+pub fn load_contract_artifact<P: AsRef<Path>>(path: P) -> Result<ContractArtifact, Box<dyn std::error::Error>> {
+    let contents = fs::read_to_string(path)?;
+    Ok(parse_contract_artifact(&contents)?)
+}
+
+pub fn parse_contract_artifact(json: &str) -> Result<ContractArtifact, String> {
+    let artifact: ContractArtifact =
+        serde_json::from_str(json).map_err(|e| format!("Invalid contract artifact: {}", e))?;
+    artifact.selectors();
+    Ok(artifact)
+}
+
+#[derive(Debug, Clone)]
+pub struct FunctionParameter {
+    pub name: String,
+    pub param_type: AbiType,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[allow(non_snake_case)]
+pub struct FunctionAbi {
+    pub name: String,
+    #[serde(rename = "functionType")]
+    pub function_type: String,
+    pub isInternal: bool,
+    pub isStatic: bool,
+    pub isInitializer: bool,
+    pub parameters: Vec<AbiParameter>,
+    #[serde(rename = "returnTypes")]
+    pub return_types: Vec<AbiType>,
+    pub errorTypes: Option<Value>,
+}
+
+/// One call argument. `Json` goes through the same conversions as before;
+/// the typed variants skip JSON, so large values keep their precision.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArgValue {
+    Fr(Fr),
+    Json(Value),
+    Address(AztecAddress),
+    Bool(bool),
+    U128(u128),
+    I128(i128),
+    Str(String),
+}
+
+impl From<Value> for ArgValue {
+    fn from(v: Value) -> Self {
+        ArgValue::Json(v)
+    }
+}
+
+impl From<Fr> for ArgValue {
+    fn from(v: Fr) -> Self {
+        ArgValue::Fr(v)
+    }
+}
+
+impl From<AztecAddress> for ArgValue {
+    fn from(v: AztecAddress) -> Self {
+        ArgValue::Address(v)
+    }
+}
+
+impl From<bool> for ArgValue {
+    fn from(v: bool) -> Self {
+        ArgValue::Bool(v)
+    }
+}
+
+impl From<u128> for ArgValue {
+    fn from(v: u128) -> Self {
+        ArgValue::U128(v)
+    }
+}
+
+impl From<u64> for ArgValue {
+    fn from(v: u64) -> Self {
+        ArgValue::U128(v.into())
+    }
+}
+
+impl From<i128> for ArgValue {
+    fn from(v: i128) -> Self {
+        ArgValue::I128(v)
+    }
+}
+
+impl From<i64> for ArgValue {
+    fn from(v: i64) -> Self {
+        ArgValue::I128(v.into())
+    }
+}
+
+impl From<String> for ArgValue {
+    fn from(v: String) -> Self {
+        ArgValue::Str(v)
+    }
+}
+
+impl From<&str> for ArgValue {
+    fn from(v: &str) -> Self {
+        ArgValue::Str(v.to_string())
+    }
+}
+
+pub struct ArgumentEncoder {
+    abi: FunctionAbi,
+    args: Vec<ArgValue>,
+    parallel_min_fields: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind")]
+pub enum AbiType {
+    #[serde(rename = "field")]
+    Field,
+    #[serde(rename = "boolean")]
+    Boolean,
+    #[serde(rename = "array")]
+    Array { r#type: Box<AbiType>, length: usize },
+    #[serde(rename = "string")]
+    String { length: usize },
+    #[serde(rename = "struct")]
+    Struct { fields: Vec<AbiStructField>, path: String },
+    #[serde(rename = "integer")]
+    Integer { sign: String, width: usize },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AbiStructField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: AbiType,
+}
+
+impl AbiType {
+    /// Number of field elements this type occupies once flattened.
+    pub fn flattened_size(&self) -> usize {
+        match self {
+            AbiType::Field | AbiType::Boolean | AbiType::Integer { .. } => 1,
+            AbiType::Array { r#type, length } => r#type.flattened_size().saturating_mul(*length),
+            AbiType::String { length } => *length,
+            AbiType::Struct { fields, .. } => fields
+                .iter()
+                .fold(0, |size, f| size.saturating_add(f.field_type.flattened_size())),
+        }
+    }
+}
+
+impl fmt::Display for AbiType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AbiType::Field => write!(f, "field"),
+            AbiType::Boolean => write!(f, "bool"),
+            AbiType::Array { r#type, length } => write!(f, "{}[{}]", r#type, length),
+            AbiType::String { length } => write!(f, "string[{}]", length),
+            AbiType::Struct { .. } => write!(f, "struct"),
+            AbiType::Integer { sign, width } => {
+                write!(f, "{}{}", if sign == "unsigned" { "u" } else { "i" }, width)
+            }
+        }
+    }
+}
+
+/// Upper bound on the fields a call's arguments may flatten to; keeps a
+/// malformed ABI (say, a `string` of length 2^60) from exhausting memory.
+pub const MAX_ENCODED_FIELDS: usize = 1 << 16;
+
+/// Top-level arrays flattening to at least this many fields have their
+/// elements encoded in parallel.
+pub const PARALLEL_MIN_FIELDS: usize = 4096;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Outputs {
+    #[serde(default)]
+    pub structs: HashMap<String, Vec<AbiType>>,
+    #[serde(default)]
+    pub globals: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StructField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: AbiType,
+}
+
+impl ArgumentEncoder {
+    pub fn new<A: Into<ArgValue>>(abi: FunctionAbi, args: Vec<A>) -> Self {
+        Self {
+            abi,
+            args: args.into_iter().map(Into::into).collect(),
+            parallel_min_fields: PARALLEL_MIN_FIELDS,
+        }
+    }
+
+    /// Arrays smaller than this are encoded on the calling thread;
+    /// `usize::MAX` turns the parallel path off.
+    pub fn with_parallel_min_fields(mut self, fields: usize) -> Self {
+        self.parallel_min_fields = fields;
+        self
+    }
+
+    /// Number of fields `encode` produces.
+    pub fn flattened_size(&self) -> usize {
+        self.abi
+            .parameters
+            .iter()
+            .fold(0usize, |size, p| size.saturating_add(p.abi_type.flattened_size()))
+    }
+
+    pub fn encode(&mut self) -> Result<Vec<Fr>, String> {
+        let mut out = Vec::with_capacity(self.flattened_size().min(MAX_ENCODED_FIELDS));
+        self.encode_into(&mut out)?;
+        Ok(out)
+    }
+
+    /// Appends the arguments' fields to `out`, so callers batching many
+    /// calls can reuse one buffer. On error `out` may hold a partial call.
+    pub fn encode_into(&mut self, out: &mut Vec<Fr>) -> Result<(), String> {
+        let size = self.flattened_size();
+        let parameters = std::mem::take(&mut self.abi.parameters);
+        let args = std::mem::take(&mut self.args);
+
+        if args.len() != parameters.len() {
+            return Err(format!(
+                "Function '{}' expects {} arguments, got {}.",
+                self.abi.name,
+                parameters.len(),
+                args.len()
+            ));
+        }
+
+        if size > MAX_ENCODED_FIELDS {
+            return Err(format!(
+                "Function '{}' arguments flatten to {} fields, more than the {} allowed.",
+                self.abi.name, size, MAX_ENCODED_FIELDS
+            ));
+        }
+        out.reserve(size);
+
+        for (param, arg) in parameters.iter().zip(&args) {
+            match (&param.abi_type, arg) {
+                (AbiType::Array { r#type, length }, ArgValue::Json(Value::Array(elements)))
+                    if param.abi_type.flattened_size() >= self.parallel_min_fields
+                        && elements.len() == *length =>
+                {
+                    encode_elements_parallel(out, r#type, elements, &param.name)?
+                }
+                _ => encode_arg_value(out, &param.abi_type, arg, &param.name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// Elements are independent, so each is encoded into its own buffer and the
+// buffers appended in order; the first failing element's error wins.
+fn encode_elements_parallel(out: &mut Vec<Fr>, element_type: &AbiType, elements: &[Value], name: &str) -> Result<(), String> {
+    #[cfg(feature = "parallel")]
+    let elements = elements.par_iter();
+    #[cfg(not(feature = "parallel"))]
+    let elements = elements.iter();
+    let encoded: Vec<Result<Vec<Fr>, String>> = elements
+        .enumerate()
+        .map(|(i, element)| {
+            let mut fields = Vec::with_capacity(element_type.flattened_size());
+            encode_argument(&mut fields, element_type, element, Some(&format!("{}[{}]", name, i)))?;
+            Ok(fields)
+        })
+        .collect();
+    for fields in encoded {
+        out.extend(fields?);
+    }
+    Ok(())
+}
+
+fn encode_arg_value(out: &mut Vec<Fr>, abi_type: &AbiType, arg: &ArgValue, name: &str) -> Result<(), String> {
+    match arg {
+        ArgValue::Json(value) => return encode_argument(out, abi_type, value, Some(name)),
+        ArgValue::Str(s) => return encode_argument(out, abi_type, &Value::String(s.clone()), Some(name)),
+        _ => {}
+    }
+
+    // A single field fits a field, an integer, a bool, or a struct
+    // wrapping exactly one of those (such as `AztecAddress { inner }`).
+    let mut target = abi_type;
+    while let AbiType::Struct { fields, .. } = target {
+        match fields.as_slice() {
+            [field] => target = &field.field_type,
+            _ => return Err(format!("Cannot pass {:?} as the struct {}", arg, name)),
+        }
+    }
+    let value = match (target, arg) {
+        (AbiType::Field, ArgValue::Fr(value) | ArgValue::Address(AztecAddress(value))) => value.clone(),
+        (AbiType::Field | AbiType::Boolean, ArgValue::Bool(b)) => Fr::from(*b),
+        (AbiType::Field, ArgValue::U128(n)) => Fr::from(*n),
+        (AbiType::Field, ArgValue::I128(n)) => {
+            Fr::from(u128::try_from(*n).map_err(|_| format!("{} is negative for {}", n, name))?)
+        }
+        (AbiType::Integer { width, .. }, ArgValue::Fr(value)) => {
+            if value.0.bits() > *width as u64 {
+                return Err(format!("{} does not fit in {} for {}", value.0, target, name));
+            }
+            value.clone()
+        }
+        (AbiType::Integer { sign, width }, ArgValue::U128(n)) => integer_field(sign, *width, BigInt::from(*n), name)?,
+        (AbiType::Integer { sign, width }, ArgValue::I128(n)) => integer_field(sign, *width, BigInt::from(*n), name)?,
+        _ => return Err(format!("Cannot pass {:?} as {} for {}", arg, target, name)),
+    };
+    out.push(value);
+    Ok(())
+}
+
+fn encode_argument(out: &mut Vec<Fr>, abi_type: &AbiType, arg: &Value, name: Option<&str>) -> Result<(), String> {
+    match abi_type {
+        AbiType::Field => {
+            if arg.is_number() {
+                let num = arg.as_u64().ok_or("Invalid number")?;
+                out.push(Fr(BigUint::from(num)));
+            } else if let Some(s) = arg.as_str() {
+                out.push(Fr::try_from_str(s)?);
+            } else if let Some(b) = arg.as_bool() {
+                out.push(Fr::from(b));
+            } else {
+                return Err(format!("Unsupported Field arg: {:?}", arg));
+            }
+        }
+        AbiType::Boolean => {
+            let b = arg
+                .as_bool()
+                .ok_or_else(|| format!("Expected boolean for {}", name.unwrap_or("unknown")))?;
+            out.push(Fr::from(b));
+        }
+        AbiType::Array { r#type, length } => {
+            let arr = arg.as_array().ok_or("Expected array")?;
+            if arr.len() != *length {
+                return Err(format!("Array length mismatch for {}", name.unwrap_or("unknown")));
+            }
+    
+            for (i, elem) in arr.iter().enumerate() {
+                encode_argument(out, r#type, elem, Some(&format!("{}[{}]", name.unwrap_or("arr"), i)))?;
+            }
+        }
+        AbiType::String { length } => {
+            let string = arg.as_str().ok_or("Expected string")?;
+            let chars = string.chars().chain(std::iter::repeat('\0'));
+            out.extend(chars.take(*length).map(|char| Fr::from_u8(char as u8)));
+        }
+        AbiType::Struct { fields, path } => {
+            if let Some(fields) = well_known_struct(path, fields, arg, name.unwrap_or("unknown"))? {
+                out.extend(fields);
+                return Ok(());
+            }
+            let obj = arg.as_object().ok_or("Expected object for struct")?;
+            for field in fields {
+                let field_val = obj.get(&field.name).ok_or("Missing struct field")?;
+                encode_argument(out, &field.field_type, field_val, Some(&field.name))?;
+            }
+        }
+        AbiType::Integer { sign, width } => {
+            let name = name.unwrap_or("unknown");
+            let value = if let Some(s) = arg.as_str() {
+                parse_integer(s)?
+            } else if let Some(n) = arg.as_u64() {
+                BigInt::from(n)
+            } else if let Some(n) = arg.as_i64() {
+                BigInt::from(n)
+            } else {
+                // Floats included: a JSON number past 2^53 has already
+                // lost precision, so wide values must come as strings.
+                return Err(format!("Unsupported integer input for {}: {}", name, arg));
+            };
+            out.push(integer_field(sign, *width, value, name)?);
+        }
+    }
+    Ok(())
+}
+
+/// Protocol and standard library types that artifacts spell as structs but
+/// users write as one value: a hex or decimal string for `AztecAddress` and
+/// `EthAddress` (both `{ inner: Field }`), `[x, y]` or `0x` followed by x and
+/// y (64 bytes) for an `EmbeddedCurvePoint`, an integer for a `U128 { lo, hi }`
+/// and a plain array, up to its capacity, for a `BoundedVec { storage, len }`.
+/// `None` for other structs and for objects, which encode field by field as
+/// usual.
+fn well_known_struct(path: &str, members: &[AbiStructField], arg: &Value, name: &str) -> Result<Option<Vec<Fr>>, String> {
+    if arg.is_object() {
+        return Ok(None);
+    }
+    let field = |value: &Value| match value {
+        Value::String(s) => Fr::try_from(s.as_str()),
+        Value::Number(n) => n.as_u64().map(Fr::from).ok_or_else(|| format!("Invalid field {} for {}", n, name)),
+        _ => Err(format!("Expected a field for {}, got {}", name, value)),
+    };
+    let fields = match path.rsplit("::").next().unwrap_or(path) {
+        "AztecAddress" => vec![field(arg)?],
+        "EthAddress" => {
+            let address = field(arg)?;
+            if address.0.bits() > 160 {
+                return Err(format!("{} is too wide for an EthAddress ({})", address.to_hex(), name));
+            }
+            vec![address]
+        }
+        "EmbeddedCurvePoint" | "Point" => {
+            let (x, y) = match arg {
+                Value::Array(coordinates) if coordinates.len() == 2 => (field(&coordinates[0])?, field(&coordinates[1])?),
+                Value::String(s) => {
+                    let hex = s.strip_prefix("0x").unwrap_or(s);
+                    let bytes = hex::decode(hex).map_err(|e| format!("Invalid point for {}: {}", name, e))?;
+                    if bytes.len() != 64 {
+                        return Err(format!("Expected 64 bytes of x and y for {}, got {}", name, bytes.len()));
+                    }
+                    (Fr::from_be_bytes(&bytes[..32])?, Fr::from_be_bytes(&bytes[32..])?)
+                }
+                _ => return Err(format!("Expected [x, y] or a hex point for {}", name)),
+            };
+            // Only the point at infinity sets `is_infinite`, and it has no
+            // coordinates to write this way.
+            vec![x, y, Fr::zero()]
+        }
+        "U128" => {
+            let value = match arg {
+                Value::String(s) => parse_integer(s)?,
+                Value::Number(n) => n.as_u64().map(BigInt::from).ok_or_else(|| format!("Invalid U128 {} for {}", n, name))?,
+                _ => return Err(format!("Expected an integer for the U128 {}", name)),
+            };
+            let value = integer_field("unsigned", 128, value, name)?;
+            let limb = BigUint::from(u64::MAX);
+            vec![Fr(&value.0 & &limb), Fr(value.0 >> 64u32)]
+        }
+        "BoundedVec" => {
+            let Value::Array(elements) = arg else {
+                return Err(format!("Expected an array for the BoundedVec {}", name));
+            };
+            let (Some(AbiType::Array { r#type, length }), Some(len_type)) = (
+                members.iter().find(|m| m.name == "storage").map(|m| &m.field_type),
+                members.iter().find(|m| m.name == "len").map(|m| &m.field_type),
+            ) else {
+                return Ok(None);
+            };
+            if elements.len() > *length {
+                return Err(format!("{} has {} elements, more than its capacity of {}", name, elements.len(), length));
+            }
+            let capacity = r#type.flattened_size().saturating_mul(*length);
+            let mut fields = Vec::with_capacity(capacity.saturating_add(1));
+            for (i, element) in elements.iter().enumerate() {
+                encode_argument(&mut fields, r#type, element, Some(&format!("{}[{}]", name, i)))?;
+            }
+            // Unused slots are zeroed, as `BoundedVec::new` leaves them.
+            fields.resize(capacity, Fr::zero());
+            encode_argument(&mut fields, len_type, &Value::from(elements.len()), Some(name))?;
+            fields
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(fields))
+}
+
+/// Decimal or `0x` hex, with an optional leading `-`.
+fn parse_integer(s: &str) -> Result<BigInt, String> {
+    let trimmed = s.trim();
+    let (negative, digits) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed),
+    };
+    let magnitude = match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        Some(hex) => BigUint::parse_bytes(hex.as_bytes(), 16),
+        None => BigUint::parse_bytes(digits.as_bytes(), 10),
+    }
+    .ok_or_else(|| format!("Invalid integer '{}'", s))?;
+    Ok(BigInt::from_biguint(if negative { Sign::Minus } else { Sign::Plus }, magnitude))
+}
+
+/// Range-checks `value` against a Noir integer type and returns its field.
+/// Signed values are stored as two's complement over `width` bits, so -1 as
+/// an `i64` is 2^64 - 1, matching aztec.js.
+fn integer_field(sign: &str, width: usize, value: BigInt, name: &str) -> Result<Fr, String> {
+    let one = || BigInt::from(1u8);
+    let (min, max) = if sign == "unsigned" {
+        (BigInt::from(0u8), (one() << width) - 1u8)
+    } else {
+        let half = one() << width.saturating_sub(1);
+        (-half.clone(), half - 1u8)
+    };
+    if value < min || value > max {
+        let typ = AbiType::Integer { sign: sign.to_string(), width };
+        return Err(format!("{} is out of range for {} ({})", value, typ, name));
+    }
+    let value = if value.sign() == Sign::Minus { value + (one() << width) } else { value };
+    Ok(Fr(value.to_biguint().expect("non-negative after two's complement")))
+}
+
+pub fn encode_arguments<A: Into<ArgValue>>(abi: FunctionAbi, args: Vec<A>) -> Result<Vec<Fr>, String> {
+    ArgumentEncoder::new(abi, args).encode()
+}
+
+/// Inverse of `encode_arguments`: rebuilds one JSON value per parameter from
+/// the flattened fields. Fields and integers come back as decimal strings
+/// (negative for signed integers), strings without their trailing NUL padding.
+pub fn decode_arguments(parameters: &[AbiParameter], fields: &[Fr]) -> Result<Vec<Value>, String> {
+    let mut fields = fields.iter();
+    let values = parameters
+        .iter()
+        .map(|p| decode_argument(&p.abi_type, &mut fields))
+        .collect::<Result<Vec<_>, _>>()?;
+    if fields.next().is_some() {
+        return Err("More fields than the parameters use".to_string());
+    }
+    Ok(values)
+}
+
+/// `decode_arguments` for a function's `return_types`: one value per type.
+pub fn decode_return_values(return_types: &[AbiType], fields: &[Fr]) -> Result<Vec<Value>, String> {
+    let mut fields = fields.iter();
+    let values = return_types
+        .iter()
+        .map(|typ| decode_argument(typ, &mut fields))
+        .collect::<Result<Vec<_>, _>>()?;
+    if fields.next().is_some() {
+        return Err("More return fields than the return types use".to_string());
+    }
+    Ok(values)
+}
+
+fn decode_argument<'a>(abi_type: &AbiType, fields: &mut impl Iterator<Item = &'a Fr>) -> Result<Value, String> {
+    let mut next = || fields.next().ok_or_else(|| "Not enough fields for the parameters".to_string());
+    match abi_type {
+        AbiType::Integer { sign, width } if sign != "unsigned" => {
+            let value = BigInt::from(next()?.0.clone());
+            let half = BigInt::from(1u8) << width.saturating_sub(1);
+            let value = if value >= half { value - (half << 1) } else { value };
+            Ok(Value::String(value.to_string()))
+        }
+        AbiType::Field | AbiType::Integer { .. } => Ok(Value::String(next()?.0.to_string())),
+        AbiType::Boolean => match next()?.to_u64() {
+            Some(0) => Ok(Value::Bool(false)),
+            Some(1) => Ok(Value::Bool(true)),
+            _ => Err("Boolean field is neither 0 nor 1".to_string()),
+        },
+        AbiType::String { length } => {
+            let mut bytes = Vec::new();
+            for _ in 0..*length {
+                let byte = next()?.to_u64().filter(|b| *b <= u8::MAX as u64).ok_or("String field is not a byte")?;
+                bytes.push(byte as u8);
+            }
+            while bytes.last() == Some(&0) {
+                bytes.pop();
+            }
+            String::from_utf8(bytes).map(Value::String).map_err(|e| e.to_string())
+        }
+        AbiType::Array { r#type, length } => (0..*length)
+            .map(|_| decode_argument(r#type, fields))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array),
+        AbiType::Struct { fields: members, .. } => members
+            .iter()
+            .map(|m| Ok((m.name.clone(), decode_argument(&m.field_type, fields)?)))
+            .collect::<Result<serde_json::Map<_, _>, String>>()
+            .map(Value::Object),
+    }
+}
+
+/// The selector public logs of `event` end in: the first four bytes of the
+/// keccak hash of `Name(types)`, `Name` being the last segment of the struct's
+/// path. `None` for anything but a struct.
+pub fn event_selector(event: &AbiType) -> Option<Fr> {
+    let AbiType::Struct { fields, path } = event else {
+        return None;
+    };
+    let name = path.rsplit("::").next().unwrap_or(path);
+    let types = fields.iter().map(|f| f.field_type.to_string()).collect::<Vec<_>>().join(",");
+    let hash = Keccak256::digest(format!("{}({})", name, types).as_bytes());
+    Some(Fr::from(u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]])))
+}
+
+/// Decodes a public log emitted as `event`: the serialized struct followed by
+/// its selector, then zero padding. `None` when the log is some other event.
+pub fn decode_event(event: &AbiType, log: &[Fr]) -> Option<Result<Value, String>> {
+    let emitted = log.len() - log.iter().rev().take_while(|f| f.is_zero()).count();
+    let (selector, fields) = log[..emitted].split_last()?;
+    if event_selector(event).as_ref() != Some(selector) || fields.len() != event.flattened_size() {
+        return None;
+    }
+    Some(decode_argument(event, &mut fields.iter()))
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn dummy_contract_artifact(functions: Vec<FunctionArtifact>) -> ContractArtifact {
+        ContractArtifact {
+            name: "TestContract".to_string(),
+            functions,
+            non_dispatch_public_functions: vec![],
+            storage_layout: HashMap::new(),
+            notes: HashMap::new(),
+            file_map: DebugFileMap(HashMap::new()),
+            outputs: Outputs::default(),
+            selectors: OnceLock::new(),
+        }
+    }
+
+    fn dummy_function_artifact(name: &str, parameters: Vec<AbiParameter>) -> FunctionArtifact {
+        FunctionArtifact {
+            name: name.to_string(),
+            parameters,
+            bytecode: "".to_string(),
+            verification_key: None,
+            debug_symbols: "".to_string(),
+            debug: None,
+            function_type: "private".to_string(),
+            is_static: false,
+            is_initializer: false,
+            return_types: vec![],
+        }
+    }
+
+    #[test]
+    fn test_encode_single_field_argument() {
+        let abi = FunctionAbi {
+            name: "set_value".to_string(),
+            function_type: "private".to_string(),
+            isInternal: false,
+            isStatic: false,
+            isInitializer: false,
+            parameters: vec![AbiParameter {
+                name: "value".to_string(),
+                abi_type: AbiType::Field,
+            }],
+            return_types: vec![],
+            errorTypes: None,
+        };
+
+        let args = vec![json!(42)];
+        let encoded = encode_arguments(abi, args).unwrap();
+        assert_eq!(encoded[0], Fr::from_u8(42));
+    }
+
+    #[test]
+    fn test_encode_boolean_argument() {
+        let abi = FunctionAbi {
+            name: "toggle".to_string(),
+            function_type: "private".to_string(),
+            isInternal: false,
+            isStatic: false,
+            isInitializer: false,
+            parameters: vec![AbiParameter {
+                name: "flag".to_string(),
+                abi_type: AbiType::Boolean,
+            }],
+            return_types: vec![],
+            errorTypes: None,
+        };
+
+        let args = vec![json!(true)];
+        let encoded = encode_arguments(abi, args).unwrap();
+        assert_eq!(encoded[0], Fr::from_u8(1));
+    }
+
+    #[test]
+    fn test_encode_array_argument() {
+        let abi = FunctionAbi {
+            name: "fill_array".to_string(),
+            function_type: "private".to_string(),
+            isInternal: false,
+            isStatic: false,
+            isInitializer: false,
+            parameters: vec![AbiParameter {
+                name: "arr".to_string(),
+                abi_type: AbiType::Array {
+                    r#type: Box::new(AbiType::Field),
+                    length: 3,
+                },
+            }],
+            return_types: vec![],
+            errorTypes: None,
+        };
+
+        let args = vec![json!([1, 2, 3])];
+        let encoded = encode_arguments(abi, args).unwrap();
+        assert_eq!(encoded[0], Fr::from_u8(1));
+        assert_eq!(encoded[1], Fr::from_u8(2));
+        assert_eq!(encoded[2], Fr::from_u8(3));
+    }
+
+    #[test]
+    fn test_encode_string_argument() {
+        let abi = FunctionAbi {
+            name: "set_name".to_string(),
+            function_type: "private".to_string(),
+            isInternal: false,
+            isStatic: false,
+            isInitializer: false,
+            parameters: vec![AbiParameter {
+                name: "name".to_string(),
+                abi_type: AbiType::String { length: 5 },
+            }],
+            return_types: vec![],
+            errorTypes: None,
+        };
+
+        let args = vec![json!("Rust")];
+        let encoded = encode_arguments(abi, args).unwrap();
+        assert_eq!(encoded[0], Fr::from_u8(b'R'));
+        assert_eq!(encoded[1], Fr::from_u8(b'u'));
+        assert_eq!(encoded[2], Fr::from_u8(b's'));
+        assert_eq!(encoded[3], Fr::from_u8(b't'));
+        assert_eq!(encoded[4], Fr::from_u8(0)); // Padding
+    }
+
+    #[test]
+    fn test_encode_integer_argument() {
+        let abi = FunctionAbi {
+            name: "set_int".to_string(),
+            function_type: "private".to_string(),
+            isInternal: false,
+            isStatic: false,
+            isInitializer: false,
+            parameters: vec![AbiParameter {
+                name: "int_val".to_string(),
+                abi_type: AbiType::Integer {
+                    sign: "unsigned".to_string(),
+                    width: 32,
+                },
+            }],
+            return_types: vec![],
+            errorTypes: None,
+        };
+
+        let args = vec![json!("123456789")];
+        let encoded = encode_arguments(abi, args).unwrap();
+        assert_eq!(encoded[0], Fr::from(123456789u64));
+    }
+
+    #[test]
+    fn test_encode_single_field_u64() {
+        let abi = FunctionAbi {
+            name: "test_fn".to_string(),
+            function_type: "public".to_string(),
+            isInternal: false,
+            isStatic: false,
+            isInitializer: false,
+            parameters: vec![AbiParameter {
+                name: "value".to_string(),
+                abi_type: AbiType::Field,
+            }],
+            return_types: vec![],
+            errorTypes: None,
+        };
+        let args = vec![json!(42)];
+        let encoded = encode_arguments(abi, args).unwrap();
+        assert_eq!(encoded.len(), 1);
+        assert_eq!(encoded[0], Fr::from_u8(42));
+    }
+
+    #[test]
+    fn test_set_just_field_encoding() {
+        let abi = FunctionAbi {
+            name: "set_just_field".to_string(),
+            function_type: "public".to_string(),
+            isInternal: false,
+            isStatic: false,
+            isInitializer: false,
+            parameters: vec![AbiParameter {
+                name: "value".to_string(),
+                abi_type: AbiType::Field,
+            }],
+            return_types: vec![],
+            errorTypes: None,
+        };
+
+        let args = vec![json!(123456789u64)];
+
+        let encoded = encode_arguments(abi, args).expect("Encoding should succeed");
+
+        assert_eq!(encoded.len(), 1);
+        assert_eq!(encoded[0], Fr(BigUint::from(123456789u64)));
+    }
+
+    #[test]
+    fn test_encode_boolean() {
+        let abi = FunctionAbi {
+            name: "test_bool".to_string(),
+            function_type: "public".to_string(),
+            isInternal: false,
+            isStatic: false,
+            isInitializer: false,
+            parameters: vec![AbiParameter {
+                name: "flag".to_string(),
+                abi_type: AbiType::Boolean,
+            }],
+            return_types: vec![],
+            errorTypes: None,
+        };
+        let args = vec![json!(true)];
+        let encoded = encode_arguments(abi, args).unwrap();
+        assert_eq!(encoded.len(), 1);
+        assert_eq!(encoded[0], Fr::from_u8(1));
+    }
+
+    #[test]
+    fn test_function_selector_from_name_and_parameters() {
+        let params = vec![
+            AbiParameter {
+                name: "value".to_string(),
+                abi_type: AbiType::Field,
+            },
+        ];
+        let selector = FunctionSelector::from_name_and_parameters("set_just_field", &params);
+        assert_eq!(selector.0.len(), 8);
+    }
+
+    #[test]
+    fn test_get_function_artifact_by_name() {
+        let func = FunctionArtifact {
+            name: "set_just_field".to_string(),
+            parameters: vec![AbiParameter {
+                name: "value".to_string(),
+                abi_type: AbiType::Field,
+            }],
+            bytecode: "".to_string(),
+            verification_key: None,
+            debug_symbols: String::new(),
+            debug: None,
+            function_type: "public".to_string(),
+            is_static: false,
+            is_initializer: false,
+            return_types: vec![],
+        };
+
+        let artifact = ContractArtifact {
+            name: "MyContract".to_string(),
+            functions: vec![func.clone()],
+            non_dispatch_public_functions: vec![],
+            storage_layout: Default::default(),
+            notes: Default::default(),
+            file_map: DebugFileMap(Default::default()),
+            outputs: Outputs::default(),
+            selectors: OnceLock::new(),
+        };
+
+        let resolved = get_function_artifact(&artifact, "set_just_field").unwrap();
+        assert_eq!(resolved.name, "set_just_field");
+    }
+
+    #[test]
+    fn test_get_function_artifact_by_selector() {
+        let func = FunctionArtifact {
+            name: "set_just_field".to_string(),
+            parameters: vec![AbiParameter {
+                name: "value".to_string(),
+                abi_type: AbiType::Field,
+            }],
+            bytecode: "".to_string(),
+            verification_key: None,
+            debug_symbols: String::new(),
+            debug: None,
+            function_type: "public".to_string(),
+            is_static: false,
+            is_initializer: false,
+            return_types: vec![],
+        };
+
+        let artifact = ContractArtifact {
+            name: "MyContract".to_string(),
+            functions: vec![func.clone()],
+            non_dispatch_public_functions: vec![],
+            storage_layout: Default::default(),
+            notes: Default::default(),
+            file_map: DebugFileMap(Default::default()),
+            outputs: Outputs::default(),
+            selectors: OnceLock::new(),
+        };
+
+        let selector = FunctionSelector::from_name_and_parameters(&func.name, &func.parameters);
+        let resolved = get_function_artifact(&artifact, &selector.0).unwrap();
+        assert_eq!(resolved.name, "set_just_field");
+    }
+
+    #[test]
+    fn test_nested_struct_encoding() {
+        let abi = FunctionAbi {
+            name: "nested_struct".to_string(),
+            function_type: "public".to_string(),
+            isInternal: false,
+            isStatic: false,
+            isInitializer: false,
+            parameters: vec![AbiParameter {
+                name: "nested".to_string(),
+                abi_type: AbiType::Struct {
+                    path: "MyContract::nested".to_string(),
+                    fields: vec![
+                        AbiStructField {
+                            name: "a".to_string(),
+                            field_type: AbiType::Field,
+                        },
+                        AbiStructField {
+                            name: "b".to_string(),
+                            field_type: AbiType::Struct {
+                                path: "MyContract::nested.b".to_string(),
+                                fields: vec![
+                                    AbiStructField {
+                                        name: "x".to_string(),
+                                        field_type: AbiType::Field,
+                                    },
+                                    AbiStructField {
+                                        name: "y".to_string(),
+                                        field_type: AbiType::Field,
+                                    },
+                                ]
+                            },
+                        },
+                    ],
+                },
+            }],
+            return_types: vec![],
+            errorTypes: None,
+        };
+
+        let args = vec![json!({
+            "a": 123123,
+            "b": {
+                "x": 456456,
+                "y": 789789
+            }
+        })];
+
+        let encoded = encode_arguments(abi, args).unwrap();
+        assert_eq!(encoded[0], Fr::from_u64(123123));
+        assert_eq!(encoded[1], Fr::from_u64(456456));
+        assert_eq!(encoded[2], Fr::from_u64(789789));
+    }
+
+    #[test]
+    fn test_integer_in_struct() {
+        let abi = FunctionAbi {
+            name: "integer_struct".to_string(),
+            function_type: "public".to_string(),
+            isInternal: false,
+            isStatic: false,
+            isInitializer: false,
+            parameters: vec![AbiParameter {
+                name: "value".to_string(),
+                abi_type: AbiType::Struct {
+                    path: "MyContract::intStruct".to_string(),
+                    fields: vec![AbiStructField {
+                        name: "int".to_string(),
+                        field_type: AbiType::Integer {
+                            sign: "unsigned".to_string(),
+                            width: 64,
+                        },
+                    }],
+                },
+            }],
+            return_types: vec![],
+            errorTypes: None,
+        };
+
+        let args = vec![json!({ "int": "9876543210" })];
+        let encoded = encode_arguments(abi, args).unwrap();
+        assert_eq!(encoded[0], Fr::from(9876543210u64));
+    }
+
+    #[test]
+    fn test_string_encoding() {
+        let abi = FunctionAbi {
+            name: "set_name".to_string(),
+            function_type: "public".to_string(),
+            isInternal: false,
+            isStatic: false,
+            isInitializer: false,
+            parameters: vec![AbiParameter {
+                name: "name".to_string(),
+                abi_type: AbiType::String { length: 5 },
+            }],
+            return_types: vec![],
+            errorTypes: None,
+        };
+
+        let args = vec![json!("Bob")];
+        let encoded = encode_arguments(abi, args).unwrap();
+        assert_eq!(encoded[0], Fr::from_u8(b'B'));
+        assert_eq!(encoded[1], Fr::from_u8(b'o'));
+        assert_eq!(encoded[2], Fr::from_u8(b'b'));
+        assert_eq!(encoded[3], Fr::from_u8(0));
+        assert_eq!(encoded[4], Fr::from_u8(0));
+    }
+
+    #[test]
+    fn test_array_of_structs() {
+        let abi = FunctionAbi {
+            name: "update_points".to_string(),
+            function_type: "public".to_string(),
+            isInternal: false,
+            isStatic: false,
+            isInitializer: false,
+            parameters: vec![AbiParameter {
+                name: "points".to_string(),
+                abi_type: AbiType::Array {
+                    r#type: Box::new(AbiType::Struct {
+                        path: "MyContract::Point".to_string(),
+                        fields: vec![
+                            AbiStructField {
+                                name: "x".to_string(),
+                                field_type: AbiType::Field,
+                            },
+                            AbiStructField {
+                                name: "y".to_string(),
+                                field_type: AbiType::Field,
+                            },
+                        ],
+                    }),
+                    length: 2,
+                },
+            }],
+            return_types: vec![],
+            errorTypes: None,
+        };
+
+        let args = vec![json!([
+            { "x": 10, "y": 20 },
+            { "x": 30, "y": 40 }
+        ])];
+
+        let encoded = encode_arguments(abi, args).unwrap();
+        assert_eq!(encoded[0], Fr::from_u64(10));
+        assert_eq!(encoded[1], Fr::from_u64(20));
+        assert_eq!(encoded[2], Fr::from_u64(30));
+        assert_eq!(encoded[3], Fr::from_u64(40));
+    }
+
+    fn feed_batch_abi(length: usize) -> FunctionAbi {
+        FunctionAbi {
+            name: "update_feeds".to_string(),
+            function_type: "public".to_string(),
+            isInternal: false,
+            isStatic: false,
+            isInitializer: false,
+            parameters: vec![
+                AbiParameter {
+                    name: "round".to_string(),
+                    abi_type: AbiType::Field,
+                },
+                AbiParameter {
+                    name: "feeds".to_string(),
+                    abi_type: AbiType::Array {
+                        r#type: Box::new(AbiType::Struct {
+                            path: "Feeds::Update".to_string(),
+                            fields: vec![
+                                AbiStructField {
+                                    name: "id".to_string(),
+                                    field_type: AbiType::Field,
+                                },
+                                AbiStructField {
+                                    name: "price".to_string(),
+                                    field_type: AbiType::Integer {
+                                        sign: "unsigned".to_string(),
+                                        width: 64,
+                                    },
+                                },
+                            ],
+                        }),
+                        length,
+                    },
+                },
+            ],
+            return_types: vec![],
+            errorTypes: None,
+        }
+    }
+
+    #[test]
+    fn test_parallel_encoding_matches_sequential() {
+        let feeds: Vec<Value> = (0..300)
+            .map(|i| json!({ "id": i, "price": (i * 1000).to_string() }))
+            .collect();
+        let args = vec![json!(7), json!(feeds)];
+
+        let sequential = ArgumentEncoder::new(feed_batch_abi(300), args.clone())
+            .with_parallel_min_fields(usize::MAX)
+            .encode()
+            .unwrap();
+        let mut encoder = ArgumentEncoder::new(feed_batch_abi(300), args.clone()).with_parallel_min_fields(2);
+        assert_eq!(encoder.flattened_size(), 601);
+        let mut out = vec![Fr::from_u64(1)];
+        encoder.encode_into(&mut out).unwrap();
+        assert_eq!(out[0], Fr::from_u64(1));
+        assert_eq!(out[1..], sequential[..]);
+        assert_eq!(sequential[600], Fr::from_u64(299_000));
+
+        let mut bad = feeds.clone();
+        bad[250]["price"] = json!("-1");
+        bad[20]["price"] = json!("-2");
+        let err = ArgumentEncoder::new(feed_batch_abi(300), vec![json!(7), json!(bad)])
+            .with_parallel_min_fields(2)
+            .encode()
+            .unwrap_err();
+        assert!(err.contains("-2"), "{}", err);
+    }
+
+    #[test]
+    fn test_function_selector_mixed_params() {
+        let abi_params = vec![
+            AbiParameter {
+                name: "flag".to_string(),
+                abi_type: AbiType::Boolean,
+            },
+            AbiParameter {
+                name: "value".to_string(),
+                abi_type: AbiType::Field,
+            },
+        ];
+
+        let selector = FunctionSelector::from_name_and_parameters("do_action", &abi_params);
+        assert_eq!(selector.0.len(), 8);
+    }
+
+    #[test]
+    fn test_encode_struct() {
+        let abi = FunctionAbi {
+            name: "test_struct".to_string(),
+            function_type: "public".to_string(),
+            isInternal: false,
+            isStatic: false,
+            isInitializer: false,
+            parameters: vec![AbiParameter {
+                name: "data".to_string(),
+                abi_type: AbiType::Struct {
+                    path: "MyContract::Data".to_string(),
+                    fields: vec![
+                        AbiStructField {
+                            name: "a".to_string(),
+                            field_type: AbiType::Field,
+                        },
+                        AbiStructField {
+                            name: "b".to_string(),
+                            field_type: AbiType::Boolean,
+                        },
+                    ],
+                },
+            }],
+            return_types: vec![],
+            errorTypes: None,
+        };
+        let args = vec![json!({ "a": 7, "b": false })];
+        let encoded = encode_arguments(abi, args).unwrap();
+        assert_eq!(encoded.len(), 2);
+        assert_eq!(encoded[0], Fr::from_u8(7));
+        assert_eq!(encoded[1], Fr::from_u8(0));
+    }
+
+    #[test]
+    fn test_encode_integer_from_string() {
+        let abi = FunctionAbi {
+            name: "test_integer".to_string(),
+            function_type: "public".to_string(),
+            isInternal: false,
+            isStatic: false,
+            isInitializer: false,
+            parameters: vec![AbiParameter {
+                name: "int_val".to_string(),
+                abi_type: AbiType::Integer {
+                    sign: "unsigned".to_string(),
+                    width: 64,
+                },
+            }],
+            return_types: vec![],
+            errorTypes: None,
+        };
+        let mut narrow = abi.clone();
+        narrow.parameters[0].abi_type = AbiType::Integer {
+            sign: "unsigned".to_string(),
+            width: 32,
+        };
+        let args = vec![json!("12345678901234567890")];
+        let encoded = encode_arguments(abi, args.clone()).unwrap();
+        assert_eq!(encoded.len(), 1);
+        assert_eq!(encoded[0], Fr::from(12345678901234567890u64));
+        assert!(encode_arguments(narrow, args).is_err());
+    }
+
+    #[test]
+    fn test_get_function_artifact_unknown_function() {
+        let artifact = dummy_contract_artifact(vec![dummy_function_artifact(
+            "set_just_field",
+            vec![AbiParameter {
+                name: "value".to_string(),
+                abi_type: AbiType::Field,
+            }],
+        )]);
+
+        let err = get_function_artifact(&artifact, "set_feeds").unwrap_err();
+        assert_eq!(err, "Unknown function 'set_feeds'.");
+    }
+
+    #[test]
+    fn test_encode_integer_number_above_u8() {
+        let abi = FunctionAbi {
+            name: "set_int".to_string(),
+            function_type: "public".to_string(),
+            isInternal: false,
+            isStatic: false,
+            isInitializer: false,
+            parameters: vec![AbiParameter {
+                name: "int_val".to_string(),
+                abi_type: AbiType::Integer {
+                    sign: "unsigned".to_string(),
+                    width: 64,
+                },
+            }],
+            return_types: vec![],
+            errorTypes: None,
+        };
+
+        let encoded = encode_arguments(abi, vec![json!(70_000)]).unwrap();
+        assert_eq!(encoded[0], Fr::from(70_000u32));
+    }
+
+    #[test]
+    fn test_malformed_arguments_are_errors() {
+        let abi = |abi_type: AbiType| FunctionAbi {
+            name: "malformed".to_string(),
+            function_type: "public".to_string(),
+            isInternal: false,
+            isStatic: false,
+            isInitializer: false,
+            parameters: vec![AbiParameter {
+                name: "value".to_string(),
+                abi_type,
+            }],
+            return_types: vec![],
+            errorTypes: None,
+        };
+
+        assert!(encode_arguments(abi(AbiType::Boolean), vec![json!("yes")]).is_err());
+        assert!(encode_arguments(abi(AbiType::Field), vec![json!(Fr::modulus().to_string())]).is_err());
+        let huge = AbiType::Array {
+            r#type: Box::new(AbiType::String { length: usize::MAX }),
+            length: usize::MAX,
+        };
+        assert_eq!(huge.flattened_size(), usize::MAX);
+        let err = encode_arguments(abi(huge), vec![json!([])]).unwrap_err();
+        assert!(err.contains("more than"), "{}", err);
+    }
+
+    #[test]
+    fn test_encode_typed_arg_values() {
+        let abi = |abi_type: AbiType| FunctionAbi {
+            name: "typed".to_string(),
+            function_type: "public".to_string(),
+            isInternal: false,
+            isStatic: false,
+            isInitializer: false,
+            parameters: vec![AbiParameter {
+                name: "value".to_string(),
+                abi_type,
+            }],
+            return_types: vec![],
+            errorTypes: None,
+        };
+        let u128_type = || AbiType::Integer { sign: "unsigned".to_string(), width: 128 };
+        let address_type = AbiType::Struct {
+            path: "aztec::protocol_types::address::AztecAddress".to_string(),
+            fields: vec![AbiStructField {
+                name: "inner".to_string(),
+                field_type: AbiType::Field,
+            }],
+        };
+
+        let big = (1u128 << 53) + 1;
+        assert_eq!(encode_arguments(abi(u128_type()), vec![big]).unwrap(), vec![Fr::from(big)]);
+        let address = AztecAddress(Fr::from(0xabu8));
+        assert_eq!(encode_arguments(abi(address_type), vec![address]).unwrap(), vec![Fr::from(0xabu8)]);
+        assert_eq!(encode_arguments(abi(AbiType::Field), vec![true]).unwrap(), vec![Fr::from(1u8)]);
+
+        let narrow = AbiType::Integer { sign: "unsigned".to_string(), width: 8 };
+        let err = encode_arguments(abi(narrow), vec![256u128]).unwrap_err();
+        assert!(err.contains("out of range"), "{}", err);
+        assert!(encode_arguments(abi(AbiType::Boolean), vec![Fr::from(1u8)]).is_err());
+        assert_eq!(encode_arguments(abi(u128_type()), vec!["42"]).unwrap(), vec![Fr::from(42u8)]);
+    }
+
+    #[test]
+    fn test_encode_well_known_structs_from_scalars() {
+        let abi = |path: &str, fields: &[&str]| FunctionAbi {
+            name: "well_known".to_string(),
+            function_type: "public".to_string(),
+            isInternal: false,
+            isStatic: false,
+            isInitializer: false,
+            parameters: vec![AbiParameter {
+                name: "value".to_string(),
+                abi_type: AbiType::Struct {
+                    path: path.to_string(),
+                    fields: fields
+                        .iter()
+                        .map(|name| AbiStructField {
+                            name: name.to_string(),
+                            field_type: if *name == "is_infinite" { AbiType::Boolean } else { AbiType::Field },
+                        })
+                        .collect(),
+                },
+            }],
+            return_types: vec![],
+            errorTypes: None,
+        };
+        let address = || abi("aztec::protocol_types::address::aztec_address::AztecAddress", &["inner"]);
+        let eth = || abi("aztec::protocol_types::address::eth_address::EthAddress", &["inner"]);
+        let point = || abi("std::embedded_curve_ops::EmbeddedCurvePoint", &["x", "y", "is_infinite"]);
+
+        assert_eq!(encode_arguments(address(), vec![json!("0xab")]).unwrap(), vec![Fr::from(0xabu8)]);
+        assert_eq!(encode_arguments(address(), vec![json!({ "inner": "171" })]).unwrap(), vec![Fr::from(0xabu8)]);
+        let eth_address = format!("0x{}", "ff".repeat(20));
+        assert_eq!(encode_arguments(eth(), vec![json!(eth_address)]).unwrap().len(), 1);
+        assert!(encode_arguments(eth(), vec![json!(format!("0x01{}", "00".repeat(20)))]).is_err());
+
+        let expected = vec![Fr::from(1u8), Fr::from(2u8), Fr::zero()];
+        assert_eq!(encode_arguments(point(), vec![json!(["0x1", 2])]).unwrap(), expected);
+        let hex = format!("0x{}01{}02", "00".repeat(31), "00".repeat(31));
+        assert_eq!(encode_arguments(point(), vec![json!(hex)]).unwrap(), expected);
+        assert!(encode_arguments(point(), vec![json!("0x0102")]).is_err());
+
+        // Other structs still need an object.
+        assert!(encode_arguments(abi("MyContract::Wrapper", &["inner"]), vec![json!("0xab")]).is_err());
+
+        let u128 = || abi("std::uint128::U128", &["lo", "hi"]);
+        let value = (3u128 << 64) + 5;
+        assert_eq!(encode_arguments(u128(), vec![json!(value.to_string())]).unwrap(), vec![Fr::from(5u8), Fr::from(3u8)]);
+        assert_eq!(encode_arguments(u128(), vec![json!(7)]).unwrap(), vec![Fr::from(7u8), Fr::zero()]);
+        assert!(encode_arguments(u128(), vec![json!(format!("0x1{}", "0".repeat(32)))]).is_err());
+    }
+
+    #[test]
+    fn test_encode_bounded_vec_from_array() {
+        let abi = FunctionAbi {
+            name: "bounded".to_string(),
+            function_type: "public".to_string(),
+            isInternal: false,
+            isStatic: false,
+            isInitializer: false,
+            parameters: vec![AbiParameter {
+                name: "values".to_string(),
+                abi_type: AbiType::Struct {
+                    path: "std::collections::bounded_vec::BoundedVec".to_string(),
+                    fields: vec![
+                        AbiStructField {
+                            name: "storage".to_string(),
+                            field_type: AbiType::Array { r#type: Box::new(AbiType::Field), length: 3 },
+                        },
+                        AbiStructField {
+                            name: "len".to_string(),
+                            field_type: AbiType::Integer { sign: "unsigned".to_string(), width: 32 },
+                        },
+                    ],
+                },
+            }],
+            return_types: vec![],
+            errorTypes: None,
+        };
+
+        let encoded = encode_arguments(abi.clone(), vec![json!(["7", 8])]).unwrap();
+        assert_eq!(encoded, vec![Fr::from(7u8), Fr::from(8u8), Fr::zero(), Fr::from(2u8)]);
+        let explicit = json!({ "storage": ["7", "8", "0"], "len": 2 });
+        assert_eq!(encode_arguments(abi.clone(), vec![explicit]).unwrap(), encoded);
+        let err = encode_arguments(abi.clone(), vec![json!([1, 2, 3, 4])]).unwrap_err();
+        assert!(err.contains("capacity of 3"), "{}", err);
+        assert_eq!(encode_arguments(abi, vec![json!([])]).unwrap(), vec![Fr::zero(); 4]);
+    }
+
+    #[test]
+    fn test_encode_integers_at_boundary_widths() {
+        let encode = |sign: &str, width: usize, arg: ArgValue| {
+            let abi = FunctionAbi {
+                name: "wide".to_string(),
+                function_type: "public".to_string(),
+                isInternal: false,
+                isStatic: false,
+                isInitializer: false,
+                parameters: vec![AbiParameter {
+                    name: "value".to_string(),
+                    abi_type: AbiType::Integer { sign: sign.to_string(), width },
+                }],
+                return_types: vec![],
+                errorTypes: None,
+            };
+            encode_arguments(abi, vec![arg]).map(|fields| fields[0].clone())
+        };
+        let pow2 = |bits: usize| BigUint::from(1u8) << bits;
+
+        assert_eq!(encode("unsigned", 64, u64::MAX.into()), Ok(Fr::from(u64::MAX)));
+        assert_eq!(encode("unsigned", 64, json!(u64::MAX).into()), Ok(Fr::from(u64::MAX)));
+        assert!(encode("unsigned", 64, (u64::MAX as u128 + 1).into()).is_err());
+        assert_eq!(encode("unsigned", 128, u128::MAX.into()), Ok(Fr::from(u128::MAX)));
+        assert_eq!(
+            encode("unsigned", 128, json!(u128::MAX.to_string()).into()),
+            Ok(Fr::from(u128::MAX))
+        );
+        assert_eq!(
+            encode("unsigned", 128, json!(format!("0x{:x}", u128::MAX)).into()),
+            Ok(Fr::from(u128::MAX))
+        );
+        assert!(encode("unsigned", 128, json!(pow2(128).to_string()).into()).is_err());
+
+        // Negative values are two's complement over the type's width.
+        assert_eq!(encode("signed", 64, i64::MIN.into()), Ok(Fr(pow2(63))));
+        assert_eq!(encode("signed", 64, json!(i64::MIN).into()), Ok(Fr(pow2(63))));
+        assert_eq!(encode("signed", 64, json!("-1").into()), Ok(Fr(pow2(64) - 1u8)));
+        assert_eq!(encode("signed", 64, i64::MAX.into()), Ok(Fr::from(i64::MAX as u64)));
+        assert!(encode("signed", 64, (i64::MIN as i128 - 1).into()).is_err());
+        assert!(encode("signed", 64, (1u128 << 63).into()).is_err());
+        assert_eq!(encode("signed", 128, i128::MIN.into()), Ok(Fr(pow2(127))));
+
+        let i64_type = AbiType::Integer { sign: "signed".to_string(), width: 64 };
+        let params = vec![AbiParameter { name: "value".to_string(), abi_type: i64_type }];
+        assert_eq!(decode_arguments(&params, &[Fr(pow2(63))]), Ok(vec![json!(i64::MIN.to_string())]));
+
+        assert!(encode("unsigned", 8, json!(-1).into()).is_err());
+        assert!(encode("unsigned", 64, json!(1.5).into()).is_err());
+        assert!(encode("unsigned", 64, json!("12ab").into()).is_err());
+    }
+
+    #[test]
+    fn test_decode_arguments_rejects_wrong_field_counts() {
+        let params = vec![AbiParameter {
+            name: "value".to_string(),
+            abi_type: AbiType::String { length: 2 },
+        }];
+        assert_eq!(
+            decode_arguments(&params, &[Fr::from(b'h'), Fr::from(0u8)]).unwrap(),
+            vec![json!("h")]
+        );
+        assert!(decode_arguments(&params, &[Fr::from(b'h')]).is_err());
+        assert!(decode_arguments(&params, &vec![Fr::from(1u8); 3]).is_err());
+    }
+
+    #[test]
+    fn test_decode_event_checks_the_selector() {
+        let event: AbiType = serde_json::from_value(json!({
+            "kind": "struct",
+            "path": "Main::PriceUpdated",
+            "fields": [
+                { "name": "feed_id", "type": { "kind": "field" } },
+                { "name": "price", "type": { "kind": "integer", "sign": "unsigned", "width": 128 } },
+            ],
+        }))
+        .unwrap();
+        let selector = event_selector(&event).unwrap();
+        let hash = Keccak256::digest(b"PriceUpdated(field,u128)");
+        assert_eq!(selector, Fr::from(u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]])));
+
+        let log = [Fr::from(1u8), Fr::from(0u8), selector.clone(), Fr::zero(), Fr::zero()];
+        assert_eq!(decode_event(&event, &log).unwrap().unwrap(), json!({ "feed_id": "1", "price": "0" }));
+        assert_eq!(decode_event(&event, &[Fr::from(1u8), Fr::from(7u8), Fr::from(9u8)]), None);
+        assert_eq!(decode_event(&event, &[Fr::from(1u8), selector]), None);
+        assert_eq!(decode_event(&event, &[]), None);
+    }
+
+    #[test]
+    fn test_selector_map_lookups() {
+        let field = |name: &str| AbiParameter {
+            name: name.to_string(),
+            abi_type: AbiType::Field,
+        };
+        let mut artifact = dummy_contract_artifact(vec![
+            dummy_function_artifact("constructor", vec![]),
+            dummy_function_artifact("set_just_field", vec![field("value")]),
+        ]);
+        let mut get = dummy_function_artifact("get_just_field", vec![]).to_abi();
+        get.function_type = "public".to_string();
+        artifact.non_dispatch_public_functions.push(get);
+
+        let set = FunctionSelector::from_name_and_parameters("set_just_field", &[field("value")]);
+        let selectors = artifact.selectors();
+        assert_eq!(selectors.len(), 3);
+        assert_eq!(selectors.name_of(&set), Some("set_just_field"));
+        assert_eq!(selectors.selector_of("set_just_field"), Some(&set));
+        assert_eq!(artifact.function_by_selector(&set).unwrap().name, "set_just_field");
+
+        let get = FunctionSelector::from_name_and_parameters("get_just_field", &[]);
+        assert_eq!(selectors.name_of(&get), Some("get_just_field"));
+        assert!(artifact.function_by_selector(&get).is_none());
+        assert_eq!(get_function_artifact(&artifact, &format!("0x{}", set.0)).unwrap().name, "set_just_field");
+        assert!(selectors.name_of(&FunctionSelector("00000000".to_string())).is_none());
+    }
+}
@@ -0,0 +1,254 @@
+use num_bigint::BigUint;
+use num_traits::{ToPrimitive, Zero};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use zeroize::Zeroize;
+
+/// Order of the BN254 scalar field, which every Aztec field element lives in.
+const MODULUS: &str =
+    "21888242871839275222246405745257275088548364400416034343698204186575808495617";
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fr(pub BigUint);
+
+impl Fr {
+    pub fn modulus() -> BigUint {
+        BigUint::parse_bytes(MODULUS.as_bytes(), 10).expect("valid modulus")
+    }
+
+    pub fn zero() -> Self {
+        Fr(BigUint::zero())
+    }
+
+    pub fn from_u8(v: u8) -> Self {
+        Fr(BigUint::from(v))
+    }
+
+    /// Parses a decimal string; use `Fr::try_from` to also accept hex.
+    pub fn try_from_str(s: &str) -> Result<Self, String> {
+        let value = BigUint::parse_bytes(s.trim().as_bytes(), 10)
+            .ok_or_else(|| format!("Invalid decimal field '{}'", s))?;
+        Self::checked(value)
+    }
+
+    pub fn from_biguint(b: BigUint) -> Self {
+        Fr(b)
+    }
+
+    pub fn from_u64(v: u64) -> Self {
+        Fr(BigUint::from(v))
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    pub fn to_u64(&self) -> Option<u64> {
+        self.0.to_u64()
+    }
+
+    pub fn to_u128(&self) -> Option<u128> {
+        self.0.to_u128()
+    }
+
+    /// Big-endian, left-padded to 32 bytes. Anything above 256 bits (only
+    /// reachable by building `Fr` from a raw `BigUint`) is truncated.
+    pub fn to_be_bytes(&self) -> [u8; 32] {
+        let bytes = self.0.to_bytes_be();
+        let bytes = &bytes[bytes.len().saturating_sub(32)..];
+        let mut out = [0u8; 32];
+        out[32 - bytes.len()..].copy_from_slice(bytes);
+        out
+    }
+
+    pub fn from_be_bytes(bytes: &[u8]) -> Result<Self, String> {
+        Self::checked(BigUint::from_bytes_be(bytes))
+    }
+
+    /// `0x` followed by 64 lowercase hex digits, the way aztec.js prints fields.
+    pub fn to_hex(&self) -> String {
+        format!("0x{}", hex::encode(self.to_be_bytes()))
+    }
+
+    fn checked(value: BigUint) -> Result<Self, String> {
+        if value >= Self::modulus() {
+            return Err(format!("Value {} is not below the field modulus", value));
+        }
+        Ok(Fr(value))
+    }
+}
+
+macro_rules! impl_from_unsigned {
+    ($($t:ty),*) => {
+        $(
+            impl From<$t> for Fr {
+                fn from(v: $t) -> Self {
+                    Fr(BigUint::from(v))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_unsigned!(u8, u16, u32, u64, u128);
+
+impl From<bool> for Fr {
+    fn from(v: bool) -> Self {
+        Fr::from(v as u8)
+    }
+}
+
+/// Accepts decimal (`"123"`) and `0x`-prefixed hex (`"0x7b"`) strings.
+impl TryFrom<&str> for Fr {
+    type Error = String;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        let s = s.trim();
+        let parsed = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+            Some(hex) => BigUint::parse_bytes(hex.as_bytes(), 16),
+            None => BigUint::parse_bytes(s.as_bytes(), 10),
+        };
+        let value = parsed.ok_or_else(|| format!("Invalid field string '{}'", s))?;
+        Self::checked(value)
+    }
+}
+
+impl Serialize for Fr {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_hex())
+    }
+}
+
+/// Accepts hex or decimal strings as well as plain JSON numbers.
+impl<'de> Deserialize<'de> for Fr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Value::deserialize(deserializer)? {
+            Value::String(s) => Fr::try_from(s.as_str()).map_err(serde::de::Error::custom),
+            Value::Number(n) => n
+                .as_u64()
+                .map(Fr::from)
+                .ok_or_else(|| serde::de::Error::custom(format!("Invalid field number {}", n))),
+            other => Err(serde::de::Error::custom(format!(
+                "Invalid field value {}",
+                other
+            ))),
+        }
+    }
+}
+
+/// An Aztec address. It is a single field element; contracts take it as the
+/// one-field `AztecAddress { inner }` struct.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AztecAddress(pub Fr);
+
+impl TryFrom<&str> for AztecAddress {
+    type Error = String;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        Fr::try_from(s)
+            .map(AztecAddress)
+            .map_err(|e| format!("Invalid address '{}': {}", s, e))
+    }
+}
+
+impl AztecAddress {
+    /// Rejects what can't be a deployed contract or account: zero, and
+    /// values that aren't field elements.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.0.is_zero() {
+            return Err("Invalid address: zero".to_string());
+        }
+        if self.0 .0 >= Fr::modulus() {
+            return Err(format!(
+                "Invalid address {:#x}: not a field element",
+                self.0 .0
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for AztecAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0.to_hex())
+    }
+}
+
+/// num-bigint has no `Zeroize`: writing as many zero digits as the value
+/// holds overwrites its buffer in place, before it is freed.
+impl Zeroize for Fr {
+    fn zeroize(&mut self) {
+        let digits = self.0.bits().div_ceil(32) as usize;
+        self.0.assign_from_slice(&vec![0; digits]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_unsigned_integers() {
+        assert_eq!(Fr::from(300u16), Fr::from_u64(300));
+        assert_eq!(Fr::from(u128::MAX).to_u128(), Some(u128::MAX));
+        assert_eq!(Fr::from(u128::MAX).to_u64(), None);
+        assert_eq!(Fr::from(true), Fr::from_u8(1));
+    }
+
+    #[test]
+    fn test_try_from_decimal_and_hex() {
+        assert_eq!(Fr::try_from("255").unwrap(), Fr::from(255u8));
+        assert_eq!(Fr::try_from("0xff").unwrap(), Fr::from(255u8));
+        assert!(Fr::try_from("0xzz").is_err());
+        assert!(Fr::try_from(MODULUS).is_err());
+    }
+
+    #[test]
+    fn test_try_from_str_rejects_bad_input() {
+        assert_eq!(Fr::try_from_str(" 42 ").unwrap(), Fr::from(42u8));
+        assert!(Fr::try_from_str("0x2a").is_err());
+        assert!(Fr::try_from_str("-1").is_err());
+        assert!(Fr::try_from_str("").is_err());
+        assert!(Fr::try_from_str(MODULUS).is_err());
+    }
+
+    #[test]
+    fn test_be_bytes_round_trip() {
+        let value = Fr::from(0x0102_0304u32);
+        let bytes = value.to_be_bytes();
+        assert_eq!(&bytes[28..], &[1, 2, 3, 4]);
+        assert_eq!(Fr::from_be_bytes(&bytes).unwrap(), value);
+        assert!(Fr::from_be_bytes(&[0xff; 32]).is_err());
+    }
+
+    #[test]
+    fn test_ordering_and_zero() {
+        assert!(Fr::zero().is_zero());
+        assert!(Fr::from(1u8) < Fr::from(2u8));
+        assert_eq!(Fr::from(7u64).max(Fr::from(3u64)), Fr::from(7u64));
+    }
+
+    #[test]
+    fn test_serde_uses_padded_hex() {
+        let encoded = serde_json::to_string(&Fr::from(0x2aa8u32)).unwrap();
+        assert_eq!(encoded, format!("\"0x{:0>64}\"", "2aa8"));
+        assert_eq!(
+            serde_json::from_str::<Fr>("\"0x2AA8\"").unwrap(),
+            Fr::from(0x2aa8u32)
+        );
+        assert_eq!(
+            serde_json::from_str::<Fr>("10920").unwrap(),
+            Fr::from(0x2aa8u32)
+        );
+    }
+
+    #[test]
+    fn test_zeroize_clears_the_value() {
+        let mut secret =
+            Fr::try_from("0x0101010101010101010101010101010101010101010101010101010101010101")
+                .unwrap();
+        secret.zeroize();
+        assert!(secret.is_zero());
+    }
+}
@@ -0,0 +1,10 @@
+//! Field elements, ABI encoding, function selectors and curve arithmetic,
+//! free of the async and networking stack the `sequencer` crate brings.
+
+pub mod curves;
+pub mod encoder;
+pub mod fields;
+pub mod poseidon2;
+pub mod tx_request;
+#[cfg(feature = "wasm")]
+pub mod wasm;
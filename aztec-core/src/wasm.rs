@@ -0,0 +1,202 @@
+use js_sys::{Array, Function};
+use serde_json::Value;
+use std::cell::RefCell;
+use wasm_bindgen::prelude::*;
+
+use crate::encoder::{
+    encode_arguments as encode_fields, get_function_artifact, parse_contract_artifact,
+    AbiParameter, FunctionSelector,
+};
+use crate::fields::Fr;
+use crate::poseidon2::{Bn254Poseidon2, Poseidon2};
+use crate::tx_request::{NodeInfo, TxExecutionRequest};
+
+/// `encodeArguments(artifactJson, functionName, argsJson)`: the call's
+/// arguments as `0x` field strings, in aztec.js' `encodeArguments` order.
+#[wasm_bindgen(js_name = encodeArguments)]
+pub fn encode_arguments(
+    artifact: &str,
+    function: &str,
+    args: &str,
+) -> Result<Vec<String>, JsError> {
+    let fields = encode(artifact, function, args).map_err(|e| JsError::new(&e))?;
+    Ok(fields.iter().map(Fr::to_hex).collect())
+}
+
+/// `functionSelector(name, parametersJson)`, as aztec.js'
+/// `FunctionSelector.fromNameAndParameters(...).toString()`.
+#[wasm_bindgen(js_name = functionSelector)]
+pub fn function_selector(name: &str, parameters: &str) -> Result<String, JsError> {
+    selector(name, parameters).map_err(|e| JsError::new(&e))
+}
+
+/// `buildTxRequest(artifactJson, contractAddress, functionName, argsJson,
+/// nodeInfoJson, poseidon2)`: the `TxExecutionRequest` calling the contract
+/// directly, as its canonical JSON string. Hashes with the native Poseidon2
+/// unless `poseidon2(inputs, separator)` is given: that takes `0x` field
+/// strings and returns one, hashing without a separator when `separator` is
+/// undefined; aztec.js' `poseidon2HashWithSeparator` fits.
+#[wasm_bindgen(js_name = buildTxRequest)]
+pub fn build_tx_request(
+    artifact: &str,
+    contract: &str,
+    function: &str,
+    args: &str,
+    node_info: &str,
+    poseidon2: Option<Function>,
+) -> Result<String, JsError> {
+    let Some(poseidon2) = &poseidon2 else {
+        return build(
+            artifact,
+            contract,
+            function,
+            args,
+            node_info,
+            &Bn254Poseidon2,
+        )
+        .map(|request| request.to_canonical_string())
+        .map_err(|e| JsError::new(&e));
+    };
+    let hasher = JsPoseidon2 {
+        hash: poseidon2,
+        error: RefCell::new(None),
+    };
+    let request = build(artifact, contract, function, args, node_info, &hasher);
+    match (request, hasher.error.into_inner()) {
+        (_, Some(e)) | (Err(e), None) => Err(JsError::new(&e)),
+        (Ok(request), None) => Ok(request.to_canonical_string()),
+    }
+}
+
+fn encode(artifact: &str, function: &str, args: &str) -> Result<Vec<Fr>, String> {
+    let artifact = parse_contract_artifact(artifact)?;
+    let function = get_function_artifact(&artifact, function)?;
+    encode_fields(function.to_abi(), parse_args(args)?)
+}
+
+fn parse_args(args: &str) -> Result<Vec<Value>, String> {
+    serde_json::from_str(args).map_err(|e| format!("Invalid arguments: {}", e))
+}
+
+fn selector(name: &str, parameters: &str) -> Result<String, String> {
+    let parameters: Vec<AbiParameter> =
+        serde_json::from_str(parameters).map_err(|e| format!("Invalid parameters: {}", e))?;
+    Ok(format!(
+        "0x{}",
+        FunctionSelector::from_name_and_parameters(name, &parameters).0
+    ))
+}
+
+fn build<H: Poseidon2>(
+    artifact: &str,
+    contract: &str,
+    function: &str,
+    args: &str,
+    node_info: &str,
+    hasher: &H,
+) -> Result<TxExecutionRequest, String> {
+    let artifact = parse_contract_artifact(artifact)?;
+    let function = get_function_artifact(&artifact, function)?;
+    let selector = FunctionSelector::from_name_and_parameters(&function.name, &function.parameters);
+    let args = encode_fields(function.to_abi(), parse_args(args)?)?;
+    let contract = Fr::try_from(contract)?;
+    let node_info: NodeInfo =
+        serde_json::from_str(node_info).map_err(|e| format!("Invalid node info: {}", e))?;
+    Ok(TxExecutionRequest::direct_call(
+        contract, selector, args, &node_info, hasher,
+    ))
+}
+
+// A JS hash function as `Poseidon2`. The trait can't fail, so the first
+// error is kept and reported once the request is built.
+struct JsPoseidon2<'a> {
+    hash: &'a Function,
+    error: RefCell<Option<String>>,
+}
+
+impl JsPoseidon2<'_> {
+    fn call(&self, inputs: &[Fr], separator: JsValue) -> Fr {
+        let inputs: Array = inputs.iter().map(|f| JsValue::from(f.to_hex())).collect();
+        let hash = self
+            .hash
+            .call2(&JsValue::NULL, &inputs, &separator)
+            .map_err(|e| format!("poseidon2 threw: {:?}", e))
+            .and_then(|hash| {
+                hash.as_string()
+                    .ok_or_else(|| "poseidon2 must return a hex string".to_string())
+            })
+            .and_then(|hash| Fr::try_from(hash.as_str()));
+        hash.unwrap_or_else(|e| {
+            self.error.borrow_mut().get_or_insert(e);
+            Fr::zero()
+        })
+    }
+}
+
+impl Poseidon2 for JsPoseidon2<'_> {
+    fn hash_with_separator(&self, inputs: &[Fr], separator: u32) -> Fr {
+        self.call(inputs, JsValue::from(separator))
+    }
+
+    fn hash(&self, inputs: &[Fr]) -> Fr {
+        self.call(inputs, JsValue::UNDEFINED)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn artifact() -> String {
+        json!({
+            "name": "Feed",
+            "functions": [{
+                "name": "set_just_field",
+                "parameters": [{ "name": "value", "type": { "kind": "field" } }],
+                "bytecode": "",
+                "debugSymbols": "",
+                "functionType": "public"
+            }],
+            "nonDispatchPublicFunctions": [],
+            "storageLayout": {},
+            "notes": {},
+            "fileMap": {}
+        })
+        .to_string()
+    }
+
+    struct Sum;
+
+    impl Poseidon2 for Sum {
+        fn hash_with_separator(&self, inputs: &[Fr], separator: u32) -> Fr {
+            let sum: u128 = inputs.iter().map(|f| f.to_u128().unwrap()).sum();
+            Fr::from(sum + separator as u128)
+        }
+
+        fn hash(&self, inputs: &[Fr]) -> Fr {
+            self.hash_with_separator(inputs, 0)
+        }
+    }
+
+    #[test]
+    fn test_bindings_match_the_native_encoding() {
+        let artifact = &artifact();
+        let fields = encode(artifact, "set_just_field", "[42]").unwrap();
+        assert_eq!(fields, [Fr::from(42u8)]);
+        assert!(encode(artifact, "set_just_field", "[]").is_err());
+
+        let parameters = json!([{ "name": "value", "type": { "kind": "field" } }]).to_string();
+        let expected = selector("set_just_field", &parameters).unwrap();
+        let parsed = parse_contract_artifact(artifact).unwrap();
+        let native = parsed.selectors().selector_of("set_just_field").unwrap();
+        assert_eq!(expected, format!("0x{}", native.0));
+
+        let node_info = json!({ "l1ChainId": 31337, "rollupVersion": 1 }).to_string();
+        let request = build(artifact, "0x0a", "set_just_field", "[42]", &node_info, &Sum).unwrap();
+        assert_eq!(request.origin, Fr::from(0x0au8));
+        assert_eq!(&request.function_selector, native);
+        assert_eq!(request.first_call_args_hash, Fr::from(42u8 + 44));
+        assert_eq!(request.tx_context.chain_id, Fr::from(31337u64));
+    }
+}
@@ -1,57 +1,85 @@
-use futures_util::{SinkExt, StreamExt};
+use client::repl;
+use client::ws_client::WsClient;
+use sequencer::bridge::protocol::{BridgeRequest, CallRequest, Framing, Subscribe};
+use sequencer::fields::Fr;
+use sequencer::watcher::WatchTarget;
 use serde_json::json;
-use tokio::net::TcpStream;
-use tokio::time::{sleep, Duration};
-use tokio_tungstenite::connect_async;
-use tungstenite::protocol::Message;
-use url::Url; // Importing futures utils
+use tokio::time::Duration;
+use url::Url;
+
+const DEMO_CONTRACT: &str = "0x12d8f70092c1d4b2bf3ddd60af8e47c1a10d90f3f31fe4c874d4b91f58442ede";
+/// `just_field` is the second storage slot of `Main`.
+const JUST_FIELD_SLOT: u8 = 2;
 
 #[tokio::main]
-async fn main() {
-    let url = Url::parse("ws://localhost:3002").unwrap();
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let url = Url::parse("ws://localhost:3002")?;
+
+    let framing = match std::env::var("WS_FRAMING").as_deref() {
+        Ok("cbor") => Framing::Cbor,
+        _ => Framing::Json,
+    };
 
-    match connect_async(url).await {
-        Ok((mut socket, _)) => {
-            println!(" Connected to WebSocket server");
+    let contract = std::env::var("DEFAULT_CONTRACT").unwrap_or_else(|_| DEMO_CONTRACT.to_string());
 
-            // Send "set" action with value 214
-            let set_request = json!({ "action": "set", "value": 214 }).to_string();
-            socket.send(Message::Text(set_request)).await.unwrap();
-            println!("Sent set request");
+    match WsClient::connect(&url, framing).await {
+        Ok(mut client) => {
+            println!(
+                " Connected to WebSocket server ({:?} framing)",
+                client.framing()
+            );
 
-            // Wait for confirmation
-            if let Some(Ok(Message::Text(response))) = socket.next().await {
-                println!(" Response: {}", response);
+            // `client repl`: interactive commands instead of the fixed demo.
+            if std::env::args().nth(1).as_deref() == Some("repl") {
+                return repl::run(client, contract, Fr::from(JUST_FIELD_SLOT)).await;
             }
 
-            // Delay for contract update (simulate waiting for transaction)
-            sleep(Duration::from_secs(1)).await;
+            // Watch `just_field`: its change prompts `set_and_wait` to check
+            // the receipt without waiting for the next poll.
+            let subscribe = BridgeRequest::Subscribe(Subscribe::Target {
+                target: WatchTarget::PublicStorage {
+                    contract,
+                    slot: Fr::from(JUST_FIELD_SLOT),
+                },
+            });
+            if let Err(e) = client.request(&subscribe).await {
+                eprintln!(" Subscribe request failed: {}", e);
+            }
+
+            // Set 214 and wait for its tx to be mined before reading it back.
+            println!("Sending set request");
+            match client
+                .set_and_wait(json!(214), Duration::from_secs(60))
+                .await
+            {
+                Ok(confirmed) => {
+                    println!(
+                        " Tx {} mined in block {}",
+                        confirmed.tx_hash, confirmed.receipt["blockNumber"]
+                    );
+                    for change in confirmed.changes {
+                        println!(
+                            " just_field changed in block {}: {} -> {}",
+                            change.block, change.previous, change.current
+                        );
+                    }
+                }
+                Err(e) => eprintln!(" Set request failed: {}", e),
+            }
 
             // Send "get" action to retrieve the value
-            let get_request = json!({ "action": "get" }).to_string();
-            socket.send(Message::Text(get_request)).await.unwrap();
+            let get_request = BridgeRequest::Get(CallRequest::default());
             println!("Sent get request");
 
             // Wait for value response
-            if let Some(Ok(Message::Text(response))) = socket.next().await {
-                println!("Retrieved Value: {}", response);
+            match client.call(&get_request).await {
+                Ok(response) => println!("Retrieved Value: {:?}", response),
+                Err(e) => eprintln!(" Get request failed: {}", e),
             }
         }
         Err(e) => {
             eprintln!("WebSocket connection failed: {}", e);
         }
     }
-}
-
-// while let Some(msg) = socket.next().await {
-//     match msg {
-//         Ok(Message::Text(text)) => println!(" WebSocket Response: {}", text),
-//         Ok(_) => println!("Received non-text WebSocket message"),
-//         Err(e) => println!(" Error receiving WebSocket message: {}", e),
-//     }
-// }
-
-#[cfg(test)]
-mod tests {
-    use super::*;
+    Ok(())
 }
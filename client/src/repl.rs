@@ -0,0 +1,310 @@
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use sequencer::bridge::protocol::{
+    BridgeRequest, BridgeResponse, CallRequest, EventSubscription, ReceiptRequest, Subscribe,
+};
+use sequencer::bridge::sign_set;
+use sequencer::fields::Fr;
+use sequencer::watcher::WatchTarget;
+use serde_json::Value;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+
+use crate::ws_client::{ClientEvent, WsClient};
+
+const HELP: &str = "\
+commands:
+  set <value>        send a set; the value is read as JSON, else as a string
+  get [--refresh]    read the value (--refresh skips the bridge's cache)
+  subscribe [slot]   watch a public storage slot of the contract (default: just_field)
+  events <event>     receive the contract's events of that name, decoded
+  status             connection details and the receipt of the last set
+  help               this text
+  quit               leave";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Set(Value),
+    Get { refresh: bool },
+    Subscribe(Option<Fr>),
+    Events(String),
+    Status,
+    Help,
+    Quit,
+}
+
+impl Command {
+    /// `Ok(None)` for a blank line.
+    pub fn parse(line: &str) -> Result<Option<Command>, String> {
+        let line = line.trim();
+        let (word, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let command = match word {
+            "" => return Ok(None),
+            "set" if rest.is_empty() => return Err("usage: set <value>".to_string()),
+            "set" => Command::Set(
+                serde_json::from_str(rest).unwrap_or_else(|_| Value::String(rest.to_string())),
+            ),
+            "get" => match rest {
+                "" => Command::Get { refresh: false },
+                "--refresh" => Command::Get { refresh: true },
+                _ => return Err("usage: get [--refresh]".to_string()),
+            },
+            "subscribe" if rest.is_empty() => Command::Subscribe(None),
+            "subscribe" => Command::Subscribe(Some(Fr::try_from(rest)?)),
+            "events" if rest.is_empty() => return Err("usage: events <event>".to_string()),
+            "events" => Command::Events(rest.to_string()),
+            "status" => Command::Status,
+            "help" | "?" => Command::Help,
+            "quit" | "exit" => Command::Quit,
+            other => return Err(format!("unknown command `{}` (try `help`)", other)),
+        };
+        Ok(Some(command))
+    }
+}
+
+/// One line per response, the way the REPL prints it.
+pub fn format_response(response: &BridgeResponse) -> String {
+    if let Some(error) = &response.error {
+        return match &response.code {
+            Some(code) => format!("error ({:?}): {}", code, error),
+            None => format!("error: {}", error),
+        };
+    }
+    if let Some(approval) = &response.approval {
+        return format!(
+            "awaiting approvals {}/{} (id {})",
+            approval.approvals, approval.threshold, approval.id
+        );
+    }
+    if let Some(tx_hash) = &response.tx_hash {
+        return format!("sent tx {}", tx_hash);
+    }
+    if let Some(effects) = &response.dry_run {
+        return format!(
+            "dry run: {} public write(s), {} note hash(es), {} nullifier(s)",
+            effects.public_data_writes.len(),
+            effects.note_hashes.len(),
+            effects.nullifiers.len()
+        );
+    }
+    match &response.value {
+        Some(value) if response.stale == Some(true) => format!("value {} (stale)", value),
+        Some(value) => format!("value {}", value),
+        None if response.success => "ok".to_string(),
+        None => "failed".to_string(),
+    }
+}
+
+/// A `set` carrying `value`, signed when `BRIDGE_OPERATOR_KEY` is set (bridges
+/// with `BRIDGE_AUTH_OPERATORS` only take signed sets).
+pub fn set_request(value: Value) -> BridgeRequest {
+    let mut call = CallRequest {
+        value: Some(value),
+        ..Default::default()
+    };
+    if let Ok(key) = std::env::var("BRIDGE_OPERATOR_KEY") {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        match sign_set(&call, &key, now.as_millis() as u64, now.as_secs() + 60) {
+            Ok(auth) => call.auth = Some(auth),
+            Err(e) => eprintln!(" Cannot sign set request: {}", e),
+        }
+    }
+    BridgeRequest::Set(call)
+}
+
+/// Reads commands until `quit` or end of input, printing responses and any
+/// pushed changes as they arrive. `default_slot` is what a bare `subscribe`
+/// watches on `contract`.
+pub async fn run(
+    mut client: WsClient,
+    contract: String,
+    default_slot: Fr,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut lines = read_lines()?;
+    let mut last_tx: Option<String> = None;
+    let mut subscriptions = 0;
+    println!("Type `help` for commands.");
+
+    loop {
+        let line = tokio::select! {
+            line = lines.recv() => match line {
+                Some(line) => line,
+                None => break,
+            },
+            event = client.next_event() => {
+                match event {
+                    Some(ClientEvent::ValueChanged(change)) => println!(
+                        "changed in block {}: {} -> {}",
+                        change.block, change.previous, change.current
+                    ),
+                    Some(ClientEvent::ContractEvent(event)) => println!(
+                        "{} in block {}: {}",
+                        event.event, event.block, event.payload
+                    ),
+                    Some(ClientEvent::ConnectionLost(reason)) => {
+                        println!("connection lost: {}", reason);
+                        break;
+                    }
+                    None => break,
+                }
+                continue;
+            }
+        };
+
+        let command = match Command::parse(&line) {
+            Ok(Some(command)) => command,
+            Ok(None) => continue,
+            Err(e) => {
+                println!("{}", e);
+                continue;
+            }
+        };
+        let request = match command {
+            Command::Quit => break,
+            Command::Help => {
+                println!("{}", HELP);
+                continue;
+            }
+            Command::Status => {
+                println!("framing: {:?}", client.framing());
+                println!(
+                    "protocol: v{} {:?}",
+                    client.protocol().version,
+                    client.protocol().features
+                );
+                println!("contract: {}", contract);
+                println!("subscriptions: {}", subscriptions);
+                match &last_tx {
+                    Some(tx_hash) => BridgeRequest::Receipt(ReceiptRequest {
+                        tx_hash: tx_hash.clone(),
+                    }),
+                    None => {
+                        println!("no set sent yet");
+                        continue;
+                    }
+                }
+            }
+            Command::Set(value) => set_request(value),
+            Command::Get { refresh } => BridgeRequest::Get(CallRequest {
+                force_refresh: refresh,
+                ..Default::default()
+            }),
+            Command::Subscribe(slot) => {
+                subscriptions += 1;
+                BridgeRequest::Subscribe(Subscribe::Target {
+                    target: WatchTarget::PublicStorage {
+                        contract: contract.clone(),
+                        slot: slot.unwrap_or_else(|| default_slot.clone()),
+                    },
+                })
+            }
+            Command::Events(event) => {
+                subscriptions += 1;
+                BridgeRequest::Subscribe(Subscribe::Events {
+                    events: EventSubscription {
+                        contract: contract.clone(),
+                        event,
+                    },
+                })
+            }
+        };
+
+        match client.request(&request).await {
+            Ok(response) => {
+                if let Some(tx_hash) = &response.tx_hash {
+                    last_tx = Some(tx_hash.clone());
+                }
+                println!("{}", format_response(&response));
+            }
+            Err(e) => println!("request failed: {}", e),
+        }
+    }
+    Ok(())
+}
+
+/// rustyline blocks, so it gets its own thread; lines come back over a
+/// channel that closes on end of input.
+fn read_lines() -> Result<mpsc::UnboundedReceiver<String>, Box<dyn std::error::Error>> {
+    let mut editor = DefaultEditor::new()?;
+    let (lines_tx, lines) = mpsc::unbounded_channel();
+    thread::spawn(move || loop {
+        match editor.readline("bridge> ") {
+            Ok(line) => {
+                let _ = editor.add_history_entry(line.as_str());
+                if lines_tx.send(line).is_err() {
+                    break;
+                }
+            }
+            Err(ReadlineError::Interrupted) => continue,
+            Err(_) => break,
+        }
+    });
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sequencer::bridge::protocol::{ApprovalStatus, ErrorCode};
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(Command::parse("  "), Ok(None));
+        assert_eq!(Command::parse("set 42"), Ok(Some(Command::Set(json!(42)))));
+        assert_eq!(
+            Command::parse("set hello world"),
+            Ok(Some(Command::Set(json!("hello world"))))
+        );
+        assert_eq!(
+            Command::parse("get --refresh"),
+            Ok(Some(Command::Get { refresh: true }))
+        );
+        assert_eq!(
+            Command::parse("subscribe 0x02"),
+            Ok(Some(Command::Subscribe(Some(Fr::from(2u8)))))
+        );
+        assert_eq!(
+            Command::parse("events PriceUpdated"),
+            Ok(Some(Command::Events("PriceUpdated".to_string())))
+        );
+        assert_eq!(Command::parse("exit"), Ok(Some(Command::Quit)));
+        assert!(Command::parse("set").is_err());
+        assert!(Command::parse("subscribe nope").is_err());
+        assert!(Command::parse("frobnicate").is_err());
+    }
+
+    #[test]
+    fn test_format_response() {
+        let response = |value: Value| -> BridgeResponse { serde_json::from_value(value).unwrap() };
+        assert_eq!(
+            format_response(&response(json!({ "success": true, "txHash": "0xab" }))),
+            "sent tx 0xab"
+        );
+        assert_eq!(
+            format_response(&response(
+                json!({ "success": true, "value": "214", "stale": true })
+            )),
+            "value \"214\" (stale)"
+        );
+
+        let mut failed = response(json!({ "success": false, "error": "no such contract" }));
+        failed.code = Some(ErrorCode::NotFound);
+        assert_eq!(
+            format_response(&failed),
+            "error (NotFound): no such contract"
+        );
+
+        let mut pending = response(json!({ "success": true }));
+        pending.approval = Some(ApprovalStatus {
+            id: "7".to_string(),
+            approvals: 1,
+            threshold: 2,
+        });
+        assert_eq!(format_response(&pending), "awaiting approvals 1/2 (id 7)");
+    }
+}
@@ -0,0 +1,611 @@
+use futures_util::{SinkExt, StreamExt};
+use sequencer::bridge::protocol::{
+    BridgeEvent, BridgeRequest, BridgeResponse, ContractEvent, ErrorCode, ErrorResponse, Feature,
+    Framing, Hello, Negotiated, ReceiptRequest,
+};
+use sequencer::watcher::ValueChange;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::fmt;
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::{interval, timeout, Duration, Instant, MissedTickBehavior};
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use tungstenite::protocol::Message;
+use url::Url;
+
+use crate::repl::set_request;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+type Reply = oneshot::Sender<Result<BridgeResponse, String>>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClientEvent {
+    /// A subscribed target changed on chain.
+    ValueChanged(ValueChange),
+    /// A subscribed contract emitted an event.
+    ContractEvent(ContractEvent),
+    ConnectionLost(String),
+}
+
+/// Why `WsClient::call` failed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RequestError {
+    /// The request never got an answer: the connection is closed or broke.
+    Connection(String),
+    /// The bridge answered with a failure.
+    Bridge(ErrorResponse),
+    /// The tx was not confirmed within this long; it may still land.
+    Timeout(Duration),
+    /// The set went through the bridge but no tx landed: it was not sent
+    /// (dry run, awaiting approvals), or it reverted or was dropped.
+    Unconfirmed(String),
+}
+
+impl RequestError {
+    /// `None` for connection failures and codes this build doesn't know.
+    pub fn code(&self) -> Option<ErrorCode> {
+        match self {
+            RequestError::Bridge(failure) => failure.error_code(),
+            _ => None,
+        }
+    }
+
+    /// Whether sending the request again (after reconnecting, for
+    /// connection failures) may succeed.
+    pub fn retryable(&self) -> bool {
+        match self {
+            RequestError::Connection(_) => true,
+            RequestError::Bridge(failure) => failure.retryable,
+            RequestError::Timeout(_) | RequestError::Unconfirmed(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RequestError::Connection(e) => write!(f, "Connection failed: {}", e),
+            RequestError::Bridge(failure) => write!(f, "Bridge refused the request: {}", failure),
+            RequestError::Timeout(limit) => write!(f, "Tx not confirmed within {:?}", limit),
+            RequestError::Unconfirmed(e) => write!(f, "Tx not confirmed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RequestError {}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HeartbeatConfig {
+    /// How often a Ping is sent.
+    pub interval: Duration,
+    /// The connection counts as lost when nothing (Pong or data) arrived for this long.
+    pub timeout: Duration,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        HeartbeatConfig {
+            interval: Duration::from_secs(15),
+            timeout: Duration::from_secs(45),
+        }
+    }
+}
+
+/// What `set_and_wait` saw: the tx, its final receipt, and the changes
+/// pushed while it waited.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetConfirmation {
+    pub tx_hash: String,
+    pub receipt: Value,
+    pub changes: Vec<ValueChange>,
+}
+
+pub struct WsClient {
+    requests: mpsc::UnboundedSender<(BridgeRequest, Reply)>,
+    events: mpsc::UnboundedReceiver<ClientEvent>,
+    framing: Framing,
+    protocol: Negotiated,
+    receipt_poll: Duration,
+}
+
+impl WsClient {
+    pub async fn connect(url: &Url, framing: Framing) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::connect_with_heartbeat(url, framing, HeartbeatConfig::default()).await
+    }
+
+    /// Connects and says `hello`: negotiates the protocol revision, features
+    /// and `framing` with the bridge, falling back to whatever it agreed to.
+    /// Fails when the bridge finds no revision both sides speak.
+    pub async fn connect_with_heartbeat(
+        url: &Url,
+        framing: Framing,
+        heartbeat: HeartbeatConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let (mut socket, _) = connect_async(url.as_str()).await?;
+
+        let mut features = vec![Feature::Subscriptions, Feature::Auth];
+        if framing != Framing::Json {
+            features.insert(0, Feature::BinaryFraming);
+        }
+        let hello = BridgeRequest::Hello(Hello::current(vec![framing, Framing::Json], features));
+        socket.send(encode(Framing::Json, &hello)?).await?;
+        let welcome = loop {
+            match socket.next().await {
+                Some(Ok(message)) => match decode(Framing::Json, message) {
+                    Some(welcome) => break welcome?,
+                    None => continue,
+                },
+                Some(Err(e)) => return Err(e.into()),
+                None => return Err("Connection closed during hello".into()),
+            }
+        };
+        if let Some(failure) = welcome.error_response() {
+            return Err(format!("Bridge refused hello: {}", failure).into());
+        }
+        let negotiated = welcome.framing.unwrap_or_default();
+        let protocol = welcome.protocol.unwrap_or_else(Negotiated::legacy);
+
+        let (requests, commands) = mpsc::unbounded_channel();
+        let (events_tx, events) = mpsc::unbounded_channel();
+        tokio::spawn(drive(socket, negotiated, heartbeat, commands, events_tx));
+
+        Ok(WsClient {
+            requests,
+            events,
+            framing: negotiated,
+            protocol,
+            receipt_poll: Duration::from_secs(2),
+        })
+    }
+
+    /// How often `set_and_wait` checks the receipt when no change arrives.
+    pub fn with_receipt_poll(mut self, poll: Duration) -> Self {
+        self.receipt_poll = poll;
+        self
+    }
+
+    pub fn framing(&self) -> Framing {
+        self.framing
+    }
+
+    /// The revision and features agreed on in `hello`.
+    pub fn protocol(&self) -> &Negotiated {
+        &self.protocol
+    }
+
+    pub async fn request(
+        &self,
+        request: &BridgeRequest,
+    ) -> Result<BridgeResponse, Box<dyn std::error::Error>> {
+        let (reply, response) = oneshot::channel();
+        self.requests
+            .send((request.clone(), reply))
+            .map_err(|_| "Connection is closed")?;
+
+        Ok(response.await.map_err(|_| "Connection is closed")??)
+    }
+
+    /// Like `request`, but a failure response comes back as
+    /// `RequestError::Bridge` instead of an unsuccessful `BridgeResponse`.
+    pub async fn call(&self, request: &BridgeRequest) -> Result<BridgeResponse, RequestError> {
+        let response = self
+            .request(request)
+            .await
+            .map_err(|e| RequestError::Connection(e.to_string()))?;
+        match response.error_response() {
+            Some(failure) => Err(RequestError::Bridge(failure)),
+            None => Ok(response),
+        }
+    }
+
+    /// Next connection event; `None` once the connection task has stopped.
+    pub async fn next_event(&mut self) -> Option<ClientEvent> {
+        self.events.recv().await
+    }
+
+    /// Sends a `set` of `value` and waits until its tx is mined. The receipt
+    /// is checked whenever a subscribed value changes, and every
+    /// `with_receipt_poll` interval otherwise. Changes pushed meanwhile are
+    /// returned rather than left for `next_event`.
+    pub async fn set_and_wait(
+        &mut self,
+        value: Value,
+        limit: Duration,
+    ) -> Result<SetConfirmation, RequestError> {
+        let response = self.call(&set_request(value)).await?;
+        let Some(tx_hash) = response.tx_hash else {
+            return Err(RequestError::Unconfirmed(
+                "the bridge did not send it (dry run or awaiting approvals)".to_string(),
+            ));
+        };
+        timeout(limit, self.wait_for_receipt(tx_hash))
+            .await
+            .map_err(|_| RequestError::Timeout(limit))?
+    }
+
+    async fn wait_for_receipt(&mut self, tx_hash: String) -> Result<SetConfirmation, RequestError> {
+        let mut changes = Vec::new();
+        let mut ticker = interval(self.receipt_poll);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                event = self.events.recv() => match event {
+                    Some(ClientEvent::ValueChanged(change)) => changes.push(change),
+                    Some(ClientEvent::ContractEvent(_)) => {}
+                    Some(ClientEvent::ConnectionLost(e)) => return Err(RequestError::Connection(e)),
+                    None => return Err(RequestError::Connection("Connection is closed".to_string())),
+                },
+                _ = ticker.tick() => {}
+            }
+
+            let request = BridgeRequest::Receipt(ReceiptRequest {
+                tx_hash: tx_hash.clone(),
+            });
+            let receipt = self.call(&request).await?.value.unwrap_or_default();
+            match receipt["status"].as_str() {
+                Some("pending") => continue,
+                Some("success") => {
+                    return Ok(SetConfirmation {
+                        tx_hash,
+                        receipt,
+                        changes,
+                    })
+                }
+                status => {
+                    return Err(RequestError::Unconfirmed(format!(
+                        "tx {} ended with status {}: {}",
+                        tx_hash,
+                        status.unwrap_or("unknown"),
+                        receipt["error"]
+                    )))
+                }
+            }
+        }
+    }
+}
+
+fn encode(framing: Framing, request: &BridgeRequest) -> Result<Message, String> {
+    let encoded = framing.encode(request)?;
+    if framing.is_binary() {
+        Ok(Message::Binary(encoded))
+    } else {
+        String::from_utf8(encoded)
+            .map(Message::Text)
+            .map_err(|e| e.to_string())
+    }
+}
+
+fn decode(framing: Framing, message: Message) -> Option<Result<BridgeResponse, String>> {
+    match message {
+        Message::Text(text) => Some(Framing::Json.decode(text.as_bytes())),
+        Message::Binary(bytes) => Some(framing.decode(&bytes)),
+        _ => None,
+    }
+}
+
+// Owns the socket: forwards requests, matches responses to callers in FIFO
+// order (the bridge answers each connection sequentially) and keeps the
+// heartbeat going.
+async fn drive(
+    mut socket: Socket,
+    framing: Framing,
+    heartbeat: HeartbeatConfig,
+    mut commands: mpsc::UnboundedReceiver<(BridgeRequest, Reply)>,
+    events: mpsc::UnboundedSender<ClientEvent>,
+) {
+    let mut pending: VecDeque<Reply> = VecDeque::new();
+    let mut last_seen = Instant::now();
+    let mut ticker = interval(heartbeat.interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    let reason = loop {
+        tokio::select! {
+            command = commands.recv() => {
+                let Some((request, reply)) = command else {
+                    let _ = socket.close(None).await;
+                    return;
+                };
+                match encode(framing, &request) {
+                    Ok(message) => {
+                        if let Err(e) = socket.send(message).await {
+                            let _ = reply.send(Err(e.to_string()));
+                            break e.to_string();
+                        }
+                        pending.push_back(reply);
+                    }
+                    Err(e) => {
+                        let _ = reply.send(Err(e));
+                    }
+                }
+            }
+            message = socket.next() => {
+                last_seen = Instant::now();
+                match message {
+                    Some(Ok(Message::Close(_))) | None => break "Connection closed by server".to_string(),
+                    Some(Err(e)) => break e.to_string(),
+                    Some(Ok(message)) => match decode(framing, message) {
+                        // Pushes answer no request, so they must not consume a reply.
+                        Some(Ok(BridgeResponse { event: Some(BridgeEvent::ValueChanged(change)), .. })) => {
+                            let _ = events.send(ClientEvent::ValueChanged(change));
+                        }
+                        Some(Ok(BridgeResponse { event: Some(BridgeEvent::ContractEvent(event)), .. })) => {
+                            let _ = events.send(ClientEvent::ContractEvent(event));
+                        }
+                        Some(response) => {
+                            if let Some(reply) = pending.pop_front() {
+                                let _ = reply.send(response);
+                            }
+                        }
+                        None => {}
+                    },
+                }
+            }
+            _ = ticker.tick() => {
+                if last_seen.elapsed() > heartbeat.timeout {
+                    break format!("No pong within {:?}", heartbeat.timeout);
+                }
+                if let Err(e) = socket.send(Message::Ping(Vec::new())).await {
+                    break e.to_string();
+                }
+            }
+        }
+    };
+
+    for reply in pending {
+        let _ = reply.send(Err(reason.clone()));
+    }
+    let _ = events.send(ClientEvent::ConnectionLost(reason));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sequencer::bridge::protocol::{CallRequest, Subscribe, VERSION};
+    use sequencer::bridge::{serve, Bridge, BridgeConfig, RequestLimits, SendQueueConfig};
+    use sequencer::fields::Fr;
+    use sequencer::testing::{fixtures, test_wallet, MockPxe};
+    use sequencer::watcher::WatchTarget;
+    use serde_json::json;
+    use std::sync::Arc;
+    use tokio::net::TcpListener;
+    use tokio::time::{sleep, timeout};
+
+    async fn start_bridge(idle_timeout: Duration) -> (Url, MockPxe, Arc<Bridge>) {
+        start_bridge_with(idle_timeout, |_| {}).await
+    }
+
+    async fn start_bridge_with(
+        idle_timeout: Duration,
+        configure: impl FnOnce(&mut BridgeConfig),
+    ) -> (Url, MockPxe, Arc<Bridge>) {
+        let mock = MockPxe::start().await.unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("ws://{}", listener.local_addr().unwrap())).unwrap();
+
+        let mut config = BridgeConfig {
+            listen_addr: url.to_string(),
+            rest_addr: None,
+            grpc_addr: None,
+            health_addr: None,
+            ready_timeout: Duration::from_secs(1),
+            artifact_dir: std::env::temp_dir(),
+            artifact_reload_interval: Duration::from_secs(60),
+            default_contract: None,
+            sender: "0x01".to_string(),
+            idle_timeout,
+            cache_ttl: Duration::from_secs(10),
+            watch_interval: Duration::from_secs(60),
+            state_path: None,
+            approvals: None,
+            auth: None,
+            idempotency_ttl: Duration::from_secs(60),
+            limits: RequestLimits::default(),
+            send_queue: SendQueueConfig::default(),
+        };
+        configure(&mut config);
+        let pxe = mock.client().with_wallet(test_wallet("0x01"));
+        let bridge = Arc::new(Bridge::new(config, pxe));
+        tokio::spawn(serve(listener, bridge.clone()));
+
+        (url, mock, bridge)
+    }
+
+    fn unknown_contract_get() -> BridgeRequest {
+        BridgeRequest::Get(CallRequest {
+            contract: Some("0xdead".to_string()),
+            ..Default::default()
+        })
+    }
+
+    fn fast_heartbeat() -> HeartbeatConfig {
+        HeartbeatConfig {
+            interval: Duration::from_millis(50),
+            timeout: Duration::from_millis(150),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_negotiates_cbor_and_round_trips() {
+        let (url, _mock, _bridge) = start_bridge(Duration::from_secs(60)).await;
+        let client = WsClient::connect(&url, Framing::Cbor).await.unwrap();
+        assert_eq!(client.framing(), Framing::Cbor);
+        assert_eq!(client.protocol().version, VERSION);
+        assert!(client.protocol().has(Feature::Subscriptions));
+        assert!(!client.protocol().has(Feature::Auth));
+
+        let response = client.request(&unknown_contract_get()).await.unwrap();
+        assert!(!response.success);
+        assert!(response.error.unwrap().contains("0xdead"));
+    }
+
+    #[tokio::test]
+    async fn test_call_returns_typed_errors() {
+        let (url, _mock, _bridge) = start_bridge(Duration::from_secs(60)).await;
+        let client = WsClient::connect(&url, Framing::Json).await.unwrap();
+
+        let err = client.call(&unknown_contract_get()).await.unwrap_err();
+        assert_eq!(err.code(), Some(ErrorCode::NotFound));
+        assert!(!err.retryable());
+        let RequestError::Bridge(failure) = err else {
+            panic!("expected a bridge error");
+        };
+        assert_eq!(failure.code, 404);
+        assert!(failure.message.contains("0xdead"));
+
+        let subscribe = BridgeRequest::Subscribe(Subscribe::Target {
+            target: WatchTarget::PublicStorage {
+                contract: "0x12".to_string(),
+                slot: Fr::from(2u8),
+            },
+        });
+        assert!(client.call(&subscribe).await.unwrap().success);
+    }
+
+    #[tokio::test]
+    async fn test_json_client_skips_hello() {
+        let (url, _mock, _bridge) = start_bridge(Duration::from_secs(60)).await;
+        let client = WsClient::connect(&url, Framing::Json).await.unwrap();
+
+        let response = client.request(&unknown_contract_get()).await.unwrap();
+        assert!(!response.success);
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_keeps_idle_connection_open() {
+        let (url, _mock, _bridge) = start_bridge(Duration::from_millis(200)).await;
+        let client = WsClient::connect_with_heartbeat(&url, Framing::Json, fast_heartbeat())
+            .await
+            .unwrap();
+
+        sleep(Duration::from_millis(500)).await;
+        assert!(client.request(&unknown_contract_get()).await.is_ok());
+    }
+
+    // A bare server that only answers the hello.
+    async fn accept_with_hello(listener: &TcpListener) -> WebSocketStream<TcpStream> {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+        let _ = socket.next().await;
+        let welcome = BridgeResponse::welcome(Framing::Json, Negotiated::legacy());
+        let welcome = serde_json::to_string(&welcome).unwrap();
+        socket.send(Message::Text(welcome)).await.unwrap();
+        socket
+    }
+
+    #[tokio::test]
+    async fn test_reports_connection_lost_when_server_goes_silent() {
+        // Accepts the handshake and then never reads, so pings go unanswered.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("ws://{}", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move {
+            let _socket = accept_with_hello(&listener).await;
+            sleep(Duration::from_secs(5)).await;
+        });
+
+        let mut client = WsClient::connect_with_heartbeat(&url, Framing::Json, fast_heartbeat())
+            .await
+            .unwrap();
+        let event = timeout(Duration::from_secs(2), client.next_event())
+            .await
+            .unwrap();
+
+        assert!(
+            matches!(event, Some(ClientEvent::ConnectionLost(reason)) if reason.contains("No pong"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pending_requests_fail_when_connection_drops() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("ws://{}", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move {
+            let mut socket = accept_with_hello(&listener).await;
+            let _ = socket.next().await;
+            socket.close(None).await.unwrap();
+        });
+
+        let mut client = WsClient::connect(&url, Framing::Json).await.unwrap();
+        assert!(client.request(&unknown_contract_get()).await.is_err());
+        assert!(matches!(
+            client.next_event().await,
+            Some(ClientEvent::ConnectionLost(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_value_changes_arrive_as_events_between_replies() {
+        let (url, mock, bridge) = start_bridge(Duration::from_secs(60)).await;
+        mock.respond("pxe_getBlockNumber", json!(1));
+        mock.respond("pxe_getBlockNumber", json!(2));
+        mock.respond("pxe_getPublicStorageAt", json!("0x2bc"));
+        mock.respond("pxe_getPublicStorageAt", json!("0xd6"));
+
+        let mut client = WsClient::connect(&url, Framing::Cbor).await.unwrap();
+        let target = WatchTarget::PublicStorage {
+            contract: "0x12".to_string(),
+            slot: Fr::from(2u8),
+        };
+        let ack = client
+            .request(&BridgeRequest::Subscribe(Subscribe::Target {
+                target: target.clone(),
+            }))
+            .await
+            .unwrap();
+        assert!(ack.success);
+
+        bridge.watcher().poll().await.unwrap();
+        bridge.watcher().poll().await.unwrap();
+
+        let event = timeout(Duration::from_secs(2), client.next_event())
+            .await
+            .unwrap();
+        let Some(ClientEvent::ValueChanged(change)) = event else {
+            panic!("expected a value change, got {:?}", event);
+        };
+        assert_eq!(change.target, target);
+        assert_eq!(change.current, json!(Fr::from(214u8)));
+
+        // The push didn't eat the reply slot of the next request.
+        let response = client.request(&unknown_contract_get()).await.unwrap();
+        assert!(response.error.unwrap().contains("0xdead"));
+    }
+
+    #[tokio::test]
+    async fn test_set_and_wait_returns_once_the_receipt_lands() {
+        let dir = std::env::temp_dir().join(format!("client-artifacts-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("0x12.json"), fixtures().artifact_json).unwrap();
+        let (url, mock, _bridge) = start_bridge_with(Duration::from_secs(60), |config| {
+            config.artifact_dir = dir.clone();
+            config.default_contract = Some("0x12".to_string());
+        })
+        .await;
+        fixtures().serve_send(&mock);
+        mock.respond("pxe_getTxReceipt", json!({ "status": "pending" }));
+        let mut client = WsClient::connect(&url, Framing::Json)
+            .await
+            .unwrap()
+            .with_receipt_poll(Duration::from_millis(20));
+
+        let err = client
+            .set_and_wait(json!(214), Duration::from_millis(100))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RequestError::Timeout(_)), "{:?}", err);
+
+        // One more pending poll, then mined.
+        mock.respond(
+            "pxe_getTxReceipt",
+            json!({ "status": "success", "blockNumber": 5 }),
+        );
+        let confirmed = client
+            .set_and_wait(json!(214), Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(confirmed.tx_hash, fixtures().tx_hash);
+        assert_eq!(confirmed.receipt["blockNumber"], 5);
+        assert!(confirmed.changes.is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
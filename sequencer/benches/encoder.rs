@@ -0,0 +1,87 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use sequencer::encoder::{AbiParameter, AbiStructField, AbiType, ArgumentEncoder, FunctionAbi};
+use sequencer::fields::Fr;
+use serde_json::{json, Value};
+
+// A batch feed update: `update_feeds(round, [Update { id, price }; n])`.
+fn feed_batch(length: usize) -> (FunctionAbi, Vec<Value>) {
+    let update = AbiType::Struct {
+        path: "Feeds::Update".to_string(),
+        fields: vec![
+            AbiStructField {
+                name: "id".to_string(),
+                field_type: AbiType::Field,
+            },
+            AbiStructField {
+                name: "price".to_string(),
+                field_type: AbiType::Integer {
+                    sign: "unsigned".to_string(),
+                    width: 128,
+                },
+            },
+        ],
+    };
+    let abi = FunctionAbi {
+        name: "update_feeds".to_string(),
+        function_type: "public".to_string(),
+        isInternal: false,
+        isStatic: false,
+        isInitializer: false,
+        parameters: vec![
+            AbiParameter {
+                name: "round".to_string(),
+                abi_type: AbiType::Field,
+            },
+            AbiParameter {
+                name: "feeds".to_string(),
+                abi_type: AbiType::Array {
+                    r#type: Box::new(update),
+                    length,
+                },
+            },
+        ],
+        return_types: vec![],
+        errorTypes: None,
+    };
+    let feeds: Vec<Value> = (0..length)
+        .map(|i| json!({ "id": i.to_string(), "price": (i as u128 * 1_000_000_007).to_string() }))
+        .collect();
+    (abi, vec![json!(1), json!(feeds)])
+}
+
+fn encode(c: &mut Criterion) {
+    let mut group = c.benchmark_group("encode_feed_batch");
+    for length in [64, 1024, 16384] {
+        let (abi, args) = feed_batch(length);
+        group.bench_with_input(BenchmarkId::new("sequential", length), &length, |b, _| {
+            b.iter(|| {
+                ArgumentEncoder::new(abi.clone(), args.clone())
+                    .with_parallel_min_fields(usize::MAX)
+                    .encode()
+                    .unwrap()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("parallel", length), &length, |b, _| {
+            b.iter(|| {
+                ArgumentEncoder::new(abi.clone(), args.clone())
+                    .with_parallel_min_fields(0)
+                    .encode()
+                    .unwrap()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("into_buffer", length), &length, |b, _| {
+            let mut out: Vec<Fr> = Vec::with_capacity(2 * length + 1);
+            b.iter(|| {
+                out.clear();
+                ArgumentEncoder::new(abi.clone(), args.clone())
+                    .encode_into(&mut out)
+                    .unwrap();
+                black_box(out.len())
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, encode);
+criterion_main!(benches);
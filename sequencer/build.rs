@@ -0,0 +1,15 @@
+use std::path::PathBuf;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Use the vendored protoc so builds don't depend on a system install.
+    let mut config = prost_build::Config::new();
+    config.protoc_executable(protoc_bin_vendored::protoc_bin_path()?);
+
+    let include = protoc_bin_vendored::include_path()?;
+    tonic_prost_build::configure().compile_with_config(
+        config,
+        &[PathBuf::from("proto/bridge.proto")],
+        &[PathBuf::from("proto"), include],
+    )?;
+    Ok(())
+}
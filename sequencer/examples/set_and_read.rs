@@ -0,0 +1,115 @@
+//! Deploys the `Main` contract (or finds it deployed), bumps `just_field` by
+//! one through `set_just_field`, waits for the receipt and reads the value
+//! back with `get_just_field`. Needs a running sandbox:
+//!
+//!     cargo run --example set_and_read
+//!
+//! `PXE_URL`, `SENDER_ADDRESS` and `CONTRACT_ADDRESS` override the sandbox
+//! defaults; `ARTIFACT` points at another build of the contract. The send is
+//! signed by the `ACCOUNT_*` wallet (see `WalletConfig::from_env`). With
+//! `RPC_TRACE=<path>` the `set_just_field` send's PXE exchanges are saved
+//! there as a trace for `testing::ReplayHarness`; the replay test reads
+//! `traces/send_set_just_field.jsonl`.
+
+use sequencer::aztec_rpc_client::setup_sandbox;
+use sequencer::contract::{public_return_values, Contract, SimulateOptions};
+use sequencer::deploy::{DeployMethod, DeployOutcome, OnExisting};
+use sequencer::encoder::{load_contract_artifact, ArgValue};
+use sequencer::fields::Fr;
+use sequencer::testing::RpcRecorder;
+use sequencer::tx_request::DEFAULT_ORIGIN;
+use sequencer::wallet::WalletConfig;
+use std::env;
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Where the sandbox's `deploy.ts` puts `Main` (zero salt, sandbox account).
+const MAIN_ADDRESS: &str = "0x12d8f70092c1d4b2bf3ddd60af8e47c1a10d90f3f31fe4c874d4b91f58442ede";
+const RECEIPT_POLLS: u32 = 60;
+const RECEIPT_POLL_DELAY: Duration = Duration::from_secs(2);
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut pxe = setup_sandbox().await?;
+    if let Some(wallet) = WalletConfig::from_env()? {
+        pxe = pxe.with_wallet(Arc::from(wallet.into_wallet(None)?));
+    }
+    let sender = env::var("SENDER_ADDRESS").unwrap_or_else(|_| DEFAULT_ORIGIN.to_string());
+    let address = env::var("CONTRACT_ADDRESS").unwrap_or_else(|_| MAIN_ADDRESS.to_string());
+    let artifact_path = env::var("ARTIFACT").unwrap_or_else(|_| "contract-Main.json".to_string());
+    let artifact = Arc::new(load_contract_artifact(&artifact_path)?);
+
+    // Address derivation isn't native yet, so the address is given up front.
+    let deployment = DeployMethod::new(&pxe, sender.as_str(), &artifact, vec![])
+        .at(address.as_str())
+        .on_existing(OnExisting::Skip)
+        .send()
+        .await?;
+    match deployment {
+        DeployOutcome::Deployed { address, tx_hash } => {
+            println!("Deploying {} in tx {}", address, tx_hash);
+            let receipt = pxe
+                .wait_for_tx(&tx_hash, RECEIPT_POLLS, RECEIPT_POLL_DELAY)
+                .await?;
+            mined(&receipt)?;
+        }
+        DeployOutcome::AlreadyDeployed { address } => {
+            println!("{} is already deployed", address)
+        }
+    }
+
+    let contract = Contract::at(&pxe, sender.clone(), address.clone(), artifact.clone());
+    let read = || async {
+        let simulation = contract
+            .method("get_just_field", Vec::<ArgValue>::new())?
+            .simulate(SimulateOptions::default())
+            .await?;
+        public_return_values(&simulation)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Box::<dyn std::error::Error>::from("get_just_field returned nothing"))
+    };
+
+    let before = read().await?;
+    let next = Fr::from_biguint(before.0.clone() + 1u8);
+    println!("just_field is {}, setting {}", before.0, next.0);
+
+    let recorder = RpcRecorder::new();
+    // Ids restart at 1, as they do for the client a replay sends from.
+    let sending = pxe
+        .clone()
+        .with_request_ids(Arc::new(AtomicU64::new(1)))
+        .with_recorder(recorder.clone());
+    let tx_hash = Contract::at(&sending, sender, address, artifact)
+        .method("set_just_field", vec![next.clone()])?
+        .send()
+        .await?;
+    if let Ok(path) = env::var("RPC_TRACE") {
+        recorder.trace().save(&path)?;
+        println!("Saved the send's trace to {}", path);
+    }
+    let receipt = pxe
+        .wait_for_tx(&tx_hash, RECEIPT_POLLS, RECEIPT_POLL_DELAY)
+        .await?;
+    mined(&receipt)?;
+    println!("Mined in block {}", receipt["blockNumber"]);
+
+    let after = read().await?;
+    if after != next {
+        return Err(format!("Read back {} after setting {}", after.0, next.0).into());
+    }
+    println!("just_field is now {}", after.0);
+    Ok(())
+}
+
+fn mined(receipt: &serde_json::Value) -> Result<(), Box<dyn std::error::Error>> {
+    match receipt["status"].as_str() {
+        Some("success") => Ok(()),
+        status => Err(format!(
+            "Tx {} ended as {:?}: {}",
+            receipt["txHash"], status, receipt["error"]
+        )
+        .into()),
+    }
+}
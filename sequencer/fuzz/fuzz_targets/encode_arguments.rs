@@ -0,0 +1,16 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sequencer::encoder::{decode_arguments, encode_arguments, FunctionAbi};
+use serde_json::Value;
+
+// Input: JSON `[<function abi>, [<args>...]]`.
+fuzz_target!(|data: &[u8]| {
+    let Ok((abi, args)) = serde_json::from_slice::<(FunctionAbi, Vec<Value>)>(data) else {
+        return;
+    };
+    let parameters = abi.parameters.clone();
+    if let Ok(fields) = encode_arguments(abi, args) {
+        let _ = decode_arguments(&parameters, &fields);
+    }
+});
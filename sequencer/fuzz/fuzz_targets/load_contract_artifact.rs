@@ -0,0 +1,21 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sequencer::encoder::{get_function_artifact, parse_contract_artifact, FunctionSelector};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(json) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Ok(artifact) = parse_contract_artifact(json) else {
+        return;
+    };
+    for function in &artifact.functions {
+        let selector =
+            FunctionSelector::from_name_and_parameters(&function.name, &function.parameters);
+        let _ = get_function_artifact(&artifact, &selector.0);
+        for parameter in &function.to_abi().parameters {
+            let _ = parameter.abi_type.flattened_size();
+        }
+    }
+});
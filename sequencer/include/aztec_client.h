@@ -0,0 +1,39 @@
+/* C API of the sequencer's RPC client (`--features ffi`). Requests and
+ * responses are JSON; every returned string is released with
+ * aztec_string_free. See src/ffi.rs for the request shapes. */
+#ifndef AZTEC_CLIENT_H
+#define AZTEC_CLIENT_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct AztecClient AztecClient;
+
+/* config is a JSON object:
+ *   "url"           PXE JSON-RPC endpoint.
+ *   "namespace"?    RPC method prefix, e.g. "pxe".
+ *   "from"?         account calls are made from when a request names none;
+ *                   defaults to the sandbox account DEFAULT_ORIGIN.
+ *   "account_kind"? "schnorr" (default) or "ecdsa", as ACCOUNT_KIND.
+ *   "secret_key"?   signs txs from "from"; without it every send fails for
+ *                   want of a wallet. Wiped from memory once read.
+ * NULL on failure, with *error (when not NULL) set to a message to release
+ * with aztec_string_free. */
+AztecClient *aztec_client_new(const char *config, char **error);
+void aztec_client_free(AztecClient *client);
+
+/* {"contract", "artifact", "function", "args"?, "send"?, "from"?}
+ * -> {"result": ...} or {"error": "..."} */
+char *aztec_call_function(const AztecClient *client, const char *request);
+
+/* -> {"result": receipt} or {"error": "..."} */
+char *aztec_get_receipt(const AztecClient *client, const char *tx_hash);
+
+void aztec_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
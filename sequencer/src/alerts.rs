@@ -0,0 +1,182 @@
+use serde::Serialize;
+use serde_json::json;
+use std::env;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::feed_policy::StalenessAlert;
+
+/// Something an operator should hear about.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Alert {
+    /// A send failed in simulation: the tx would revert.
+    #[serde(rename_all = "camelCase")]
+    TxReverted {
+        contract: String,
+        function: String,
+        message: String,
+    },
+    /// The PXE (or node) could not be reached for `method`.
+    PxeUnreachable { method: String, error: String },
+    /// The fee budget refused a send.
+    FeeBudgetExceeded { reason: String },
+    /// A feed's on-chain value outlived its staleness window.
+    StaleFeed(StalenessAlert),
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Alert::TxReverted {
+                contract,
+                function,
+                message,
+            } => write!(f, "Tx to {}.{} reverts: {}", contract, function, message),
+            Alert::PxeUnreachable { method, error } => {
+                write!(f, "PXE unreachable for {}: {}", method, error)
+            }
+            Alert::FeeBudgetExceeded { reason } => {
+                write!(f, "Fee budget refused a send: {}", reason)
+            }
+            Alert::StaleFeed(stale) => write!(f, "{}", stale),
+        }
+    }
+}
+
+/// Where alerts go. Sinks are called inline, so they should hand slow work
+/// (like HTTP) off rather than block.
+pub trait AlertSink: fmt::Debug + Send + Sync {
+    fn alert(&self, alert: &Alert);
+}
+
+/// Prints each alert as an `ALERT:` line.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdoutAlerts;
+
+impl AlertSink for StdoutAlerts {
+    fn alert(&self, alert: &Alert) {
+        println!("ALERT: {}", alert);
+    }
+}
+
+/// Logs each alert as a `tracing` warning.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogAlerts;
+
+impl AlertSink for LogAlerts {
+    fn alert(&self, alert: &Alert) {
+        tracing::warn!(alert = %serde_json::to_string(alert).unwrap_or_default(), "{}", alert);
+    }
+}
+
+/// POSTs each alert as JSON to `url`, in the background; delivery failures
+/// are logged. The body is the alert's fields plus a `text` line, which is
+/// what Slack incoming webhooks show. Needs a tokio runtime.
+#[derive(Debug, Clone)]
+pub struct WebhookAlerts {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl WebhookAlerts {
+    pub fn new(url: impl Into<String>) -> Self {
+        WebhookAlerts {
+            url: url.into(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    fn body(alert: &Alert) -> serde_json::Value {
+        let mut body = json!(alert);
+        body["text"] = json!(alert.to_string());
+        body
+    }
+}
+
+impl AlertSink for WebhookAlerts {
+    fn alert(&self, alert: &Alert) {
+        let request = self.client.post(&self.url).json(&Self::body(alert));
+        let url = self.url.clone();
+        tokio::spawn(async move {
+            if let Err(e) = request.send().await.and_then(|r| r.error_for_status()) {
+                tracing::warn!("Could not deliver alert to {}: {}", url, e);
+            }
+        });
+    }
+}
+
+/// Sends every alert to each of its sinks.
+#[derive(Debug, Clone, Default)]
+pub struct AlertSinks(pub Vec<Arc<dyn AlertSink>>);
+
+impl AlertSink for AlertSinks {
+    fn alert(&self, alert: &Alert) {
+        for sink in &self.0 {
+            sink.alert(alert);
+        }
+    }
+}
+
+/// Stdout alerts, plus a webhook when `ALERT_WEBHOOK_URL` is set.
+pub fn from_env() -> Arc<dyn AlertSink> {
+    let mut sinks: Vec<Arc<dyn AlertSink>> = vec![Arc::new(StdoutAlerts)];
+    if let Ok(url) = env::var("ALERT_WEBHOOK_URL") {
+        sinks.push(Arc::new(WebhookAlerts::new(url)));
+    }
+    Arc::new(AlertSinks(sinks))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::fields::Fr;
+    use crate::testing::MockPxe;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    /// Keeps every alert, for assertions.
+    #[derive(Debug, Default)]
+    pub(crate) struct Collect(pub(crate) Mutex<Vec<Alert>>);
+
+    impl AlertSink for Collect {
+        fn alert(&self, alert: &Alert) {
+            self.0.lock().unwrap().push(alert.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_webhook_posts_alert_json() {
+        let hook = MockPxe::start().await.unwrap();
+        let collect = Arc::new(Collect::default());
+        let sinks = AlertSinks(vec![
+            Arc::new(WebhookAlerts::new(hook.url())),
+            collect.clone(),
+        ]);
+        let stale = Alert::StaleFeed(StalenessAlert {
+            feed_id: Fr::from(3u8),
+            updated_at: 100,
+            age_secs: 11,
+            staleness_secs: 10,
+        });
+        sinks.alert(&stale);
+        sinks.alert(&Alert::FeeBudgetExceeded {
+            reason: "daily cap".to_string(),
+        });
+
+        for _ in 0..100 {
+            if hook.requests().len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let mut posted = hook.requests();
+        posted.sort_by_key(|body| body["kind"].to_string());
+        assert_eq!(posted[0]["kind"], "feeBudgetExceeded");
+        assert_eq!(posted[0]["text"], "Fee budget refused a send: daily cap");
+        assert_eq!(posted[1]["kind"], "staleFeed");
+        assert_eq!(posted[1]["feedId"], json!(Fr::from(3u8)));
+        assert_eq!(posted[1]["ageSecs"], 11);
+        assert_eq!(collect.0.lock().unwrap()[0], stale);
+    }
+}
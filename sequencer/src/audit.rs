@@ -0,0 +1,226 @@
+use rusqlite::{params, Connection, OpenFlags};
+use serde::Serialize;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use crate::fields::Fr;
+
+/// One feed update as mined: which feed got which value, when, in which tx,
+/// for what fee, and whether the tx went through.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditRecord {
+    /// The block's timestamp (unix seconds); `None` for blocks indexed
+    /// before timestamps were kept.
+    pub timestamp: Option<u64>,
+    pub block: u64,
+    pub feed_id: Fr,
+    pub value: Fr,
+    pub tx_hash: String,
+    pub fee: Fr,
+    /// `success` or `reverted`.
+    pub status: String,
+    /// Known for txs passed to `Indexer::record_sender`.
+    pub sender: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AuditFormat {
+    Csv,
+    /// One JSON object per line.
+    #[default]
+    JsonLines,
+}
+
+impl FromStr for AuditFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "csv" => Ok(AuditFormat::Csv),
+            "jsonl" | "json-lines" => Ok(AuditFormat::JsonLines),
+            other => Err(format!(
+                "Unknown audit format '{}': use csv or jsonl",
+                other
+            )),
+        }
+    }
+}
+
+const CSV_HEADER: &str = "timestamp,block,feed_id,value,tx_hash,fee,status,sender";
+
+/// Read-only access to an `Indexer` database for audit reports, so exports
+/// need neither a node nor a lock on the running indexer.
+pub struct AuditLog {
+    db: Connection,
+}
+
+impl AuditLog {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let db = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|e| format!("Cannot open {}: {}", path, e))?;
+        Ok(AuditLog { db })
+    }
+
+    /// Every indexed feed write, oldest first, limited to blocks with
+    /// timestamps within `from..=to` when either bound is given.
+    pub fn records(&self, from: Option<u64>, to: Option<u64>) -> Result<Vec<AuditRecord>, String> {
+        let mut statement = self
+            .db
+            .prepare(
+                "SELECT block_times.timestamp, feed_writes.block, feed_id, value,
+                        feed_writes.tx_hash, fee, reverted, sender
+                 FROM feed_writes
+                 JOIN txs ON txs.tx_hash = feed_writes.tx_hash
+                 LEFT JOIN block_times ON block_times.number = feed_writes.block
+                 LEFT JOIN tx_senders ON tx_senders.tx_hash = feed_writes.tx_hash
+                 WHERE (?1 IS NULL OR block_times.timestamp >= ?1)
+                   AND (?2 IS NULL OR block_times.timestamp <= ?2)
+                 ORDER BY feed_writes.block, feed_writes.rowid",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = statement
+            .query_map(params![from, to], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get(4)?,
+                    row.get::<_, String>(5)?,
+                    row.get::<_, bool>(6)?,
+                    row.get(7)?,
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+        rows.map(|row| {
+            let (timestamp, block, feed_id, value, tx_hash, fee, reverted, sender) =
+                row.map_err(|e| e.to_string())?;
+            Ok(AuditRecord {
+                timestamp,
+                block,
+                feed_id: Fr::try_from(feed_id.as_str())?,
+                value: Fr::try_from(value.as_str())?,
+                tx_hash,
+                fee: Fr::try_from(fee.as_str())?,
+                status: if reverted { "reverted" } else { "success" }.to_string(),
+                sender,
+            })
+        })
+        .collect()
+    }
+}
+
+/// Writes `records` to `out`; CSV starts with a header row.
+pub fn export(
+    records: &[AuditRecord],
+    format: AuditFormat,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    match format {
+        AuditFormat::JsonLines => {
+            for record in records {
+                serde_json::to_writer(&mut *out, record)?;
+                writeln!(out)?;
+            }
+        }
+        AuditFormat::Csv => {
+            writeln!(out, "{}", CSV_HEADER)?;
+            // Every column is a number, hex string or fixed word, so nothing
+            // needs quoting.
+            for r in records {
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{},{},{}",
+                    r.timestamp.map(|t| t.to_string()).unwrap_or_default(),
+                    r.block,
+                    r.feed_id.to_hex(),
+                    r.value.to_hex(),
+                    r.tx_hash,
+                    r.fee.to_hex(),
+                    r.status,
+                    r.sender.as_deref().unwrap_or_default()
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aztec_rpc_client::AztecRpcClient;
+    use crate::block::fixtures::{block_json, tx_effect_json};
+    use crate::block::L2Block;
+    use crate::indexer::{Indexer, IndexerConfig};
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    fn block(number: u64, timestamp: u64, tx_hash: &str, value: u8) -> L2Block {
+        let mut effect = tx_effect_json();
+        effect["txHash"] = json!(tx_hash);
+        effect["publicDataWrites"] =
+            json!([{ "leafSlot": "0x51", "value": format!("{:#x}", value) }]);
+        let mut block = block_json(number, vec![effect]);
+        block["header"]["globalVariables"]["timestamp"] = json!(format!("{:#x}", timestamp));
+        serde_json::from_value(block).unwrap()
+    }
+
+    #[test]
+    fn test_exports_indexed_feed_writes() {
+        let path = std::env::temp_dir().join(format!("audit-{}.sqlite", std::process::id()));
+        let path = path.to_str().unwrap();
+        let config = IndexerConfig {
+            feed_slots: HashMap::from([(Fr::from(1u8), Fr::from(0x51u8))]),
+            ..IndexerConfig::default()
+        };
+        let node = AztecRpcClient::new("http://127.0.0.1:1", None);
+        let indexer = Indexer::open(node, config, Duration::from_secs(1), path).unwrap();
+        indexer.index_block(&block(1, 1_000, "0xaa", 42)).unwrap();
+        indexer.index_block(&block(2, 2_000, "0xbb", 43)).unwrap();
+        indexer.record_sender("0xaa", "0x0b").unwrap();
+
+        let log = AuditLog::open(path).unwrap();
+        assert_eq!(log.records(None, None).unwrap().len(), 2);
+        let records = log.records(Some(1_500), None).unwrap();
+        assert_eq!(
+            records,
+            [AuditRecord {
+                timestamp: Some(2_000),
+                block: 2,
+                feed_id: Fr::from(1u8),
+                value: Fr::from(43u8),
+                tx_hash: "0xbb".to_string(),
+                fee: Fr::from(0x0c8du64),
+                status: "success".to_string(),
+                sender: None,
+            }]
+        );
+        let first = log.records(None, Some(1_000)).unwrap();
+        assert_eq!(first[0].sender.as_deref(), Some("0x0b"));
+
+        let mut csv = vec![];
+        export(&first, AuditFormat::Csv, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(
+            lines[1],
+            format!(
+                "1000,1,{},{},0xaa,{},success,0x0b",
+                Fr::from(1u8).to_hex(),
+                Fr::from(42u8).to_hex(),
+                Fr::from(0x0c8du64).to_hex()
+            )
+        );
+        let mut jsonl = vec![];
+        export(&records, AuditFormat::JsonLines, &mut jsonl).unwrap();
+        let line: serde_json::Value = serde_json::from_slice(&jsonl).unwrap();
+        assert_eq!(line["txHash"], "0xbb");
+        assert_eq!(line["sender"], serde_json::Value::Null);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
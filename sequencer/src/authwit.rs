@@ -0,0 +1,212 @@
+use serde_json::json;
+
+use crate::aztec_rpc_client::AztecRpcClient;
+use crate::curves::grumpkin::AffinePoint;
+use crate::fields::Fr;
+use crate::notes::{generator_index, Poseidon2};
+use crate::signing::{Schnorr, SchnorrChallenge, SchnorrKeyPair, SchnorrSignature};
+use crate::tx_request::{AuthWitness, HashedValues, NodeInfo};
+use crate::wallet::FunctionCall;
+
+/// Where aztec-nr's `SchnorrAccount` keeps its `PublicKeyNote`.
+pub const SIGNING_PUBLIC_KEY_SLOT: u8 = 1;
+
+/// Signs `request_hash` into the witness a Schnorr account checks.
+pub fn create_auth_witness<H: SchnorrChallenge>(
+    schnorr: &Schnorr<H>,
+    key: &SchnorrKeyPair,
+    request_hash: &Fr,
+) -> AuthWitness {
+    AuthWitness {
+        request_hash: request_hash.clone(),
+        witness: schnorr.sign(key, request_hash).to_witness(),
+    }
+}
+
+/// The hash an auth witness signs to let `caller` make `call` on the
+/// account's behalf, on this chain and rollup version (aztec.js'
+/// `computeAuthWitMessageHash`).
+pub fn message_hash(
+    caller: &Fr,
+    call: &FunctionCall,
+    node_info: &NodeInfo,
+    hasher: &dyn Poseidon2,
+) -> Result<Fr, String> {
+    let args = HashedValues::from_args(hasher, call.args.clone());
+    let inner = hasher.hash_with_separator(
+        &[caller.clone(), call.selector_field()?, args.hash],
+        generator_index::AUTHWIT_INNER,
+    );
+    Ok(hasher.hash_with_separator(
+        &[
+            call.to.clone(),
+            Fr::from(node_info.l1_chain_id),
+            Fr::from(node_info.rollup_version),
+            inner,
+        ],
+        generator_index::AUTHWIT_OUTER,
+    ))
+}
+
+/// Checks auth witnesses the way a Schnorr account contract would, so bad
+/// delegations are refused before a simulation is spent on them.
+pub struct AuthWitVerifier<H> {
+    schnorr: Schnorr<H>,
+}
+
+impl<H: SchnorrChallenge> AuthWitVerifier<H> {
+    pub fn new(hasher: H) -> Self {
+        AuthWitVerifier {
+            schnorr: Schnorr::new(hasher),
+        }
+    }
+
+    /// Verifies `witness` against the hash it claims to authorize.
+    pub fn verify_witness(
+        &self,
+        witness: &AuthWitness,
+        public_key: &AffinePoint,
+    ) -> Result<(), String> {
+        let signature = SchnorrSignature::from_witness(&witness.witness)?;
+        self.schnorr
+            .verify(public_key, &witness.request_hash, &signature)
+    }
+
+    /// Verifies `witness` against the signing key `account` keeps in its
+    /// `PublicKeyNote`.
+    pub async fn verify_for_account(
+        &self,
+        pxe: &AztecRpcClient,
+        account: &str,
+        witness: &AuthWitness,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let public_key = signing_public_key(pxe, account).await?;
+        Ok(self.verify_witness(witness, &public_key)?)
+    }
+}
+
+/// Reads a Schnorr account's signing key from its `PublicKeyNote`. The key
+/// is not one of the account's address keys, so the PXE must hold the
+/// account's notes.
+pub async fn signing_public_key(
+    pxe: &AztecRpcClient,
+    account: &str,
+) -> Result<AffinePoint, Box<dyn std::error::Error>> {
+    let notes = pxe
+        .get_notes(json!({
+            "contractAddress": account,
+            "storageSlot": Fr::from(SIGNING_PUBLIC_KEY_SLOT),
+            "scopes": [account],
+        }))
+        .await?;
+    let items = &notes[0]["note"]["items"];
+    if items.is_null() {
+        return Err(format!("No signing key note for account {}", account).into());
+    }
+    Ok(AffinePoint::new(
+        serde_json::from_value(items[0].clone())?,
+        serde_json::from_value(items[1].clone())?,
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::PedersenBlake2s;
+    use crate::testing::MockPxe;
+    use num_bigint::BigUint;
+
+    fn keypair(secret: u64) -> SchnorrKeyPair {
+        SchnorrKeyPair::from_secret(&BigUint::from(secret)).unwrap()
+    }
+
+    #[test]
+    fn test_verifies_schnorr_witnesses() {
+        let verifier = AuthWitVerifier::new(PedersenBlake2s);
+        let key = keypair(0xabcdef);
+        let witness =
+            create_auth_witness(&Schnorr::new(PedersenBlake2s), &key, &Fr::from(0x1234u64));
+        assert_eq!(witness.witness.len(), 64);
+        assert_eq!(verifier.verify_witness(&witness, key.public_key()), Ok(()));
+
+        let other = AuthWitness {
+            request_hash: Fr::from(0x1235u64),
+            ..witness.clone()
+        };
+        assert!(verifier.verify_witness(&other, key.public_key()).is_err());
+        assert!(verifier
+            .verify_witness(&witness, keypair(7).public_key())
+            .is_err());
+
+        let mut tampered = witness.clone();
+        tampered.witness[0] = Fr::from(256u32);
+        assert!(verifier
+            .verify_witness(&tampered, key.public_key())
+            .is_err());
+        tampered.witness.pop();
+        assert!(verifier
+            .verify_witness(&tampered, key.public_key())
+            .is_err());
+
+        let off_curve = AffinePoint {
+            x: key.public_key().x.clone(),
+            y: Fr::from(1u8),
+        };
+        let err = verifier.verify_witness(&witness, &off_curve).unwrap_err();
+        assert!(err.contains("not on Grumpkin"), "{}", err);
+    }
+
+    #[test]
+    fn test_message_hash_is_computed_like_aztec_js() {
+        use crate::encoder::FunctionSelector;
+        use crate::notes::Bn254Poseidon2;
+
+        let call = FunctionCall {
+            to: Fr::from(0x0au8),
+            selector: FunctionSelector::from_hex("0x17f12888").unwrap(),
+            args: vec![Fr::from(1u8), Fr::from(2u8)],
+            is_public: true,
+            is_static: false,
+        };
+        let node_info = NodeInfo {
+            node_version: String::new(),
+            l1_chain_id: 31337,
+            rollup_version: 0xb2da7e95,
+        };
+        // poseidon2([46, to, chain, version, poseidon2([45, caller,
+        // selector, poseidon2([44, ...args])])]); the args are hashed as
+        // args even for a public call.
+        let hash = message_hash(&Fr::from(0x0bu8), &call, &node_info, &Bn254Poseidon2).unwrap();
+        assert_eq!(
+            hash,
+            Fr::try_from("0x235ed8c435897b45921d72dade48eb1faa238b93db9c80cf3287fb6f76af087c")
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reads_signing_key_from_account_note() {
+        let key = keypair(0x42);
+        let public_key = key.public_key();
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond(
+            "pxe_getNotes",
+            json!([{ "note": { "items": [public_key.x.to_hex(), public_key.y.to_hex()] } }]),
+        );
+        let pxe = AztecRpcClient::new(mock.url(), Some("pxe".to_string()));
+        let witness = create_auth_witness(&Schnorr::new(PedersenBlake2s), &key, &Fr::from(99u8));
+
+        AuthWitVerifier::new(PedersenBlake2s)
+            .verify_for_account(&pxe, "0x0a", &witness)
+            .await
+            .unwrap();
+        let filter = &mock.requests()[0]["params"][0];
+        assert_eq!(filter["contractAddress"], "0x0a");
+        assert_eq!(filter["storageSlot"], json!(Fr::from(1u8)));
+
+        let empty = MockPxe::start().await.unwrap();
+        empty.respond("pxe_getNotes", json!([]));
+        let pxe = AztecRpcClient::new(empty.url(), Some("pxe".to_string()));
+        assert!(signing_public_key(&pxe, "0x0a").await.is_err());
+    }
+}
@@ -1,28 +1,335 @@
-use num_bigint::BigUint;
+use futures_util::stream::{self, StreamExt, TryStreamExt};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use serde::Deserialize;
 use serde_json::{json, Value};
 use std::env;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::time::sleep;
+use tracing::Instrument;
+
+use crate::alerts::{Alert, AlertSink};
+use crate::block::{IndexedTxEffect, L2Block};
+use crate::contract::ConfirmSend;
+use crate::error::AztecError;
+use crate::fees::FeeBudget;
+use crate::fields::{AztecAddress, Fr};
+use crate::gas::GasProfiler;
+use crate::journal::TxJournal;
+use crate::layers::{HttpTransport, Layer, RecordLayer, RpcService};
+use crate::networks::Networks;
+use crate::private_logs::PrivateLog;
+use crate::senders::SenderPool;
+use crate::simulation_cache::SimulationCache;
+use crate::testing::RpcRecorder;
+use crate::timings::CallTimings;
+use crate::tx_request::NodeInfo;
+#[cfg(unix)]
+use crate::unix_transport::UnixTransport;
+use crate::version::{version_from_info, PayloadProfile, ProtocolVersion};
+use crate::wallet::{AccountWallet, Wallets};
 
 #[derive(Debug, Deserialize)]
 pub struct RpcResponse<T> {
+    #[serde(default)]
     pub jsonrpc: String,
-    pub id: u32,
+    /// Echoes the request's id; `null` only on errors for requests the
+    /// server couldn't read.
+    #[serde(default)]
+    pub id: Value,
+    /// `None` only when the field is missing; a `null` result is handed to
+    /// `T`, so methods returning optional values can decode it.
+    #[serde(
+        default = "Option::default",
+        deserialize_with = "present",
+        bound(deserialize = "T: Deserialize<'de>")
+    )]
     pub result: Option<T>,
     pub error: Option<serde_json::Value>,
 }
 
-#[derive(Debug)]
+fn present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Clone)]
 pub struct AztecRpcClient {
+    namespace: Option<String>,
+    transport: Arc<dyn RpcService>,
+    version: Option<ProtocolVersion>,
+    profile: PayloadProfile,
+    dry_run: bool,
+    fee_budget: Option<Arc<FeeBudget>>,
+    gas_profiler: Option<Arc<GasProfiler>>,
+    tx_journal: Option<Arc<TxJournal>>,
+    simulation_cache: Option<Arc<SimulationCache>>,
+    sender_pool: Option<Arc<SenderPool>>,
+    call_timings: Option<Arc<CallTimings>>,
+    send_confirmation: Option<Arc<dyn ConfirmSend>>,
+    wallets: Wallets,
+    request_ids: Arc<AtomicU64>,
+    alert_sink: Option<Arc<dyn AlertSink>>,
+}
+
+/// Methods that spend fees or change chain state; refused in dry-run mode.
+pub(crate) const SUBMITTING_METHODS: [&str; 2] = ["proveTx", "sendTx"];
+
+/// `getContractMetadata` calls `get_contracts_metadata` keeps in flight.
+const METADATA_CONCURRENCY: usize = 8;
+
+/// How the HTTP client talks to the PXE. The defaults are reqwest's.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HttpClientConfig {
+    /// Idle connections kept open per host.
+    pub pool_max_idle_per_host: Option<usize>,
+    /// How long an idle pooled connection is kept.
+    pub pool_idle_timeout: Option<Duration>,
+    /// TCP keep-alive probe interval.
+    pub tcp_keepalive: Option<Duration>,
+    /// Speak HTTP/2 without negotiating it first, for PXEs behind an h2c
+    /// proxy.
+    pub http2_prior_knowledge: bool,
+    /// Every request goes through this proxy.
+    pub proxy: Option<String>,
+    /// Sent with every request, e.g. an API key for a hosted PXE. Values
+    /// are marked sensitive so they stay out of debug output.
+    pub headers: Vec<(String, String)>,
+}
+
+impl HttpClientConfig {
+    /// `headers` is `Name: value` pairs separated by `;`.
+    pub fn parse(
+        pool_max_idle: Option<&str>,
+        idle_timeout_secs: Option<&str>,
+        keepalive_secs: Option<&str>,
+        http2: Option<&str>,
+        proxy: Option<&str>,
+        headers: Option<&str>,
+    ) -> Result<Self, String> {
+        let secs = |value: Option<&str>, what: &str| -> Result<Option<Duration>, String> {
+            value
+                .map(|v| {
+                    v.trim()
+                        .parse()
+                        .map(Duration::from_secs)
+                        .map_err(|_| format!("Invalid {}: {}", what, v))
+                })
+                .transpose()
+        };
+        let headers = headers
+            .unwrap_or_default()
+            .split(';')
+            .filter(|header| !header.trim().is_empty())
+            .map(|header| {
+                let (name, value) = header
+                    .split_once(':')
+                    .ok_or_else(|| format!("Header must be `Name: value`: {}", header.trim()))?;
+                Ok((name.trim().to_string(), value.trim().to_string()))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(HttpClientConfig {
+            pool_max_idle_per_host: pool_max_idle
+                .map(|n| {
+                    n.trim()
+                        .parse()
+                        .map_err(|_| format!("Invalid pool size: {}", n))
+                })
+                .transpose()?,
+            pool_idle_timeout: secs(idle_timeout_secs, "idle timeout")?,
+            tcp_keepalive: secs(keepalive_secs, "keep-alive interval")?,
+            http2_prior_knowledge: matches!(http2.map(str::trim), Some("1" | "true")),
+            proxy: proxy.map(|p| p.trim().to_string()),
+            headers,
+        })
+    }
+
+    /// Reads `PXE_HTTP_POOL_MAX_IDLE`, `PXE_HTTP_IDLE_TIMEOUT_SECS`,
+    /// `PXE_HTTP_KEEPALIVE_SECS`, `PXE_HTTP2` (`true` for prior knowledge),
+    /// `PXE_HTTP_PROXY` and `PXE_HTTP_HEADERS`; `None` when none is set.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let names = [
+            "PXE_HTTP_POOL_MAX_IDLE",
+            "PXE_HTTP_IDLE_TIMEOUT_SECS",
+            "PXE_HTTP_KEEPALIVE_SECS",
+            "PXE_HTTP2",
+            "PXE_HTTP_PROXY",
+            "PXE_HTTP_HEADERS",
+        ];
+        let values: Vec<Option<String>> = names.iter().map(|name| env::var(name).ok()).collect();
+        if values.iter().all(Option::is_none) {
+            return Ok(None);
+        }
+        Self::parse(
+            values[0].as_deref(),
+            values[1].as_deref(),
+            values[2].as_deref(),
+            values[3].as_deref(),
+            values[4].as_deref(),
+            values[5].as_deref(),
+        )
+        .map(Some)
+    }
+
+    pub fn build(&self) -> Result<reqwest::Client, String> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| format!("Invalid header name: {}", name))?;
+            let mut value = HeaderValue::from_str(value)
+                .map_err(|_| format!("Invalid value for header {}", name))?;
+            value.set_sensitive(true);
+            headers.insert(name, value);
+        }
+        let mut builder = reqwest::Client::builder().default_headers(headers);
+        if let Some(interval) = self.tcp_keepalive {
+            builder = builder.tcp_keepalive(interval);
+        }
+        if let Some(max_idle) = self.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max_idle);
+        }
+        if let Some(timeout) = self.pool_idle_timeout {
+            builder = builder.pool_idle_timeout(timeout);
+        }
+        if self.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        if let Some(proxy) = &self.proxy {
+            let proxy = reqwest::Proxy::all(proxy)
+                .map_err(|e| format!("Invalid proxy {}: {}", proxy, e))?;
+            builder = builder.proxy(proxy);
+        }
+        builder
+            .build()
+            .map_err(|e| format!("Cannot build HTTP client: {}", e))
+    }
+}
+
+/// Builds an `AztecRpcClient` with a configured HTTP client; see
+/// `AztecRpcClient::builder`.
+#[derive(Debug, Clone)]
+pub struct AztecRpcClientBuilder {
     host: String,
     namespace: Option<String>,
-    client: reqwest::Client,
+    http: HttpClientConfig,
+    transport: Option<Arc<dyn RpcService>>,
+    layers: Vec<Arc<dyn Layer>>,
+}
+
+impl AztecRpcClientBuilder {
+    pub fn namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Replaces every HTTP setting made so far.
+    pub fn http_config(mut self, http: HttpClientConfig) -> Self {
+        self.http = http;
+        self
+    }
+
+    pub fn pool_max_idle_per_host(mut self, max_idle: usize) -> Self {
+        self.http.pool_max_idle_per_host = Some(max_idle);
+        self
+    }
+
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.http.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    pub fn tcp_keepalive(mut self, interval: Duration) -> Self {
+        self.http.tcp_keepalive = Some(interval);
+        self
+    }
+
+    pub fn http2_prior_knowledge(mut self, enabled: bool) -> Self {
+        self.http.http2_prior_knowledge = enabled;
+        self
+    }
+
+    pub fn proxy(mut self, url: impl Into<String>) -> Self {
+        self.http.proxy = Some(url.into());
+        self
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.http.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sends calls over `transport` (e.g. a `WsTransport`) instead of HTTP;
+    /// the HTTP settings are then unused.
+    pub fn transport(mut self, transport: impl RpcService + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Wraps the transport in `layer`. Layers added first see each call
+    /// first, as with tower's `ServiceBuilder`: `.layer(metrics).layer(retry)`
+    /// counts a retried call once.
+    pub fn layer(mut self, layer: impl Layer + 'static) -> Self {
+        self.layers.push(Arc::new(layer));
+        self
+    }
+
+    pub fn build(self) -> Result<AztecRpcClient, String> {
+        let mut transport: Arc<dyn RpcService> = match self.transport {
+            Some(transport) => transport,
+            None => transport_for(&self.host, self.http.build()?),
+        };
+        for layer in self.layers.iter().rev() {
+            transport = layer.layer(transport);
+        }
+        let mut client = AztecRpcClient::new(self.host, self.namespace);
+        client.transport = transport;
+        Ok(client)
+    }
+}
+
+/// `host`'s transport: a Unix socket for `unix://` URLs, HTTP otherwise.
+fn transport_for(host: &str, client: reqwest::Client) -> Arc<dyn RpcService> {
+    #[cfg(unix)]
+    if let Some(unix) = UnixTransport::from_url(host) {
+        return Arc::new(unix);
+    }
+    Arc::new(HttpTransport::new(host, client))
+}
+
+/// A reply must be JSON-RPC 2.0 and carry the request's id; errors may
+/// carry a `null` id instead, as servers send for unreadable requests.
+fn check_envelope<T>(response: &RpcResponse<T>, id: u64, method: &str) -> Result<(), AztecError> {
+    if response.jsonrpc != "2.0" {
+        return Err(AztecError::Protocol(format!(
+            "{} reply has jsonrpc {:?}, expected \"2.0\"",
+            method, response.jsonrpc
+        )));
+    }
+    let null_error = response.id.is_null() && response.error.is_some();
+    if response.id != json!(id) && !null_error {
+        return Err(AztecError::Protocol(format!(
+            "{} reply has id {}, expected {}",
+            method, response.id, id
+        )));
+    }
+    Ok(())
 }
 
 pub async fn setup_sandbox() -> Result<AztecRpcClient, Box<dyn std::error::Error>> {
     let pxe_url = env::var("PXE_URL").unwrap_or_else(|_| "http://localhost:8080".to_string());
-    let pxe = AztecRpcClient::new(pxe_url, Some("pxe".to_string()));
+    connect(pxe_url).await
+}
+
+// Waits for the PXE at `pxe_url` to come up and negotiates its version.
+async fn connect(pxe_url: String) -> Result<AztecRpcClient, Box<dyn std::error::Error>> {
+    let mut pxe = AztecRpcClient::builder(pxe_url)
+        .namespace("pxe")
+        .http_config(HttpClientConfig::from_env()?.unwrap_or_default())
+        .build()?;
 
     wait_for_pxe(
         || async {
@@ -34,6 +341,9 @@ pub async fn setup_sandbox() -> Result<AztecRpcClient, Box<dyn std::error::Error
     )
     .await?;
 
+    let version = pxe.negotiate_version().await?;
+    println!("PXE version {} ({:?} payloads)", version, pxe.profile());
+
     Ok(pxe)
 }
 
@@ -66,246 +376,642 @@ where
 }
 
 impl AztecRpcClient {
+    /// For tuning the HTTP client (pooling, keep-alive, HTTP/2, a proxy or
+    /// extra headers), using another transport, or adding layers. `new`
+    /// uses reqwest's defaults. The HTTP settings don't apply to `unix://`
+    /// hosts.
+    pub fn builder(host: impl Into<String>) -> AztecRpcClientBuilder {
+        AztecRpcClientBuilder {
+            host: host.into(),
+            namespace: None,
+            http: HttpClientConfig::default(),
+            transport: None,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Connects to the PXE of a network profile (see `Networks::from_env`)
+    /// and checks that it is on the chain the profile expects.
+    pub async fn for_network(name: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let profile = Networks::from_env()?.get(name)?;
+        let pxe = connect(profile.pxe_url.clone()).await?;
+        profile.check(&pxe.get_node_info().await?)?;
+        Ok(pxe)
+    }
+
+    /// `host` is an HTTP URL, or `unix:///path` for a PXE on a local socket.
     pub fn new(host: impl Into<String>, namespace: Option<String>) -> Self {
         AztecRpcClient {
-            host: host.into(),
             namespace,
-            client: reqwest::Client::new(),
+            transport: transport_for(&host.into(), reqwest::Client::new()),
+            version: None,
+            profile: PayloadProfile::default(),
+            dry_run: false,
+            fee_budget: None,
+            gas_profiler: None,
+            tx_journal: None,
+            simulation_cache: None,
+            sender_pool: None,
+            call_timings: None,
+            send_confirmation: None,
+            wallets: Wallets::default(),
+            request_ids: Arc::new(AtomicU64::new(1)),
+            alert_sink: None,
+        }
+    }
+
+    /// Wraps the transport, as layered so far, in `layer`.
+    pub fn with_layer(mut self, layer: impl Layer) -> Self {
+        self.transport = layer.layer(self.transport);
+        self
+    }
+
+    pub fn with_recorder(self, recorder: RpcRecorder) -> Self {
+        self.with_layer(RecordLayer::new(recorder))
+    }
+
+    /// Pins the payload shape instead of detecting it with `negotiate_version`.
+    pub fn with_profile(mut self, profile: PayloadProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// In dry-run mode, sends are simulated only (see
+    /// `ContractFunctionInteraction::send`) and `proveTx`/`sendTx` are refused.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Sends must fit the budget; receipts fetched through this client
+    /// settle its estimates.
+    pub fn with_fee_budget(mut self, budget: Arc<FeeBudget>) -> Self {
+        self.fee_budget = Some(budget);
+        self
+    }
+
+    pub fn fee_budget(&self) -> Option<&Arc<FeeBudget>> {
+        self.fee_budget.as_ref()
+    }
+
+    /// Sends use the profiler's gas limits once it has them; receipts
+    /// fetched through this client feed it.
+    pub fn with_gas_profiler(mut self, profiler: Arc<GasProfiler>) -> Self {
+        self.gas_profiler = Some(profiler);
+        self
+    }
+
+    pub fn gas_profiler(&self) -> Option<&Arc<GasProfiler>> {
+        self.gas_profiler.as_ref()
+    }
+
+    /// Proven txs are journaled until their receipt is final, so that
+    /// `TxJournal::recover` can finish them after a crash; receipts fetched
+    /// through this client clear them.
+    pub fn with_tx_journal(mut self, journal: Arc<TxJournal>) -> Self {
+        self.tx_journal = Some(journal);
+        self
+    }
+
+    pub fn tx_journal(&self) -> Option<&Arc<TxJournal>> {
+        self.tx_journal.as_ref()
+    }
+
+    /// `simulate` answers repeated calls from `cache` within a block.
+    pub fn with_simulation_cache(mut self, cache: Arc<SimulationCache>) -> Self {
+        self.simulation_cache = Some(cache);
+        self
+    }
+
+    pub fn simulation_cache(&self) -> Option<&Arc<SimulationCache>> {
+        self.simulation_cache.as_ref()
+    }
+
+    /// Sends go out from the pool's accounts instead of the interaction's
+    /// `from`; receipts fetched through this client free their slots.
+    pub fn with_sender_pool(mut self, pool: Arc<SenderPool>) -> Self {
+        self.sender_pool = Some(pool);
+        self
+    }
+
+    pub fn sender_pool(&self) -> Option<&Arc<SenderPool>> {
+        self.sender_pool.as_ref()
+    }
+
+    /// Interactions time their `simulateTx`, `proveTx` and `sendTx` calls
+    /// into `timings`.
+    pub fn with_call_timings(mut self, timings: Arc<CallTimings>) -> Self {
+        self.call_timings = Some(timings);
+        self
+    }
+
+    pub fn call_timings(&self) -> Option<&Arc<CallTimings>> {
+        self.call_timings.as_ref()
+    }
+
+    /// Every send is previewed and must be confirmed before it is proven.
+    pub fn with_send_confirmation(mut self, confirmation: Arc<dyn ConfirmSend>) -> Self {
+        self.send_confirmation = Some(confirmation);
+        self
+    }
+
+    pub fn send_confirmation(&self) -> Option<&Arc<dyn ConfirmSend>> {
+        self.send_confirmation.as_ref()
+    }
+
+    /// Signs the entrypoint payloads of txs sent from `wallet`'s account;
+    /// call once per account, e.g. for each in a sender pool.
+    pub fn with_wallet(mut self, wallet: Arc<dyn AccountWallet>) -> Self {
+        self.wallets.insert(wallet);
+        self
+    }
+
+    pub fn wallet(&self, address: &str) -> Option<&Arc<dyn AccountWallet>> {
+        self.wallets.get(address)
+    }
+
+    /// Request ids are taken from `ids`, counting up. Clients sharing one
+    /// counter never reuse each other's ids, which keeps recorded traces and
+    /// proxy logs unambiguous.
+    pub fn with_request_ids(mut self, ids: Arc<AtomicU64>) -> Self {
+        self.request_ids = ids;
+        self
+    }
+
+    /// Unreachable PXEs, reverting sends and fee budget refusals through
+    /// this client raise alerts on `sink`.
+    pub fn with_alert_sink(mut self, sink: Arc<dyn AlertSink>) -> Self {
+        self.alert_sink = Some(sink);
+        self
+    }
+
+    pub fn alert_sink(&self) -> Option<&Arc<dyn AlertSink>> {
+        self.alert_sink.as_ref()
+    }
+
+    pub(crate) fn alert(&self, alert: Alert) {
+        if let Some(sink) = &self.alert_sink {
+            sink.alert(&alert);
         }
     }
 
+    pub fn version(&self) -> Option<ProtocolVersion> {
+        self.version
+    }
+
+    pub fn profile(&self) -> PayloadProfile {
+        self.profile
+    }
+
+    /// Asks the PXE which aztec-packages release it runs (`getPXEInfo`, then
+    /// `getNodeInfo` for releases without it) and picks the matching payload
+    /// profile. Fails on releases outside the compatibility matrix.
+    pub async fn negotiate_version(
+        &mut self,
+    ) -> Result<ProtocolVersion, Box<dyn std::error::Error>> {
+        let mut reported = None;
+        for method in ["getPXEInfo", "getNodeInfo"] {
+            if let Ok(info) = self.request::<Value>(method, vec![]).await {
+                reported = version_from_info(&info).map(str::to_string);
+                if reported.is_some() {
+                    break;
+                }
+            }
+        }
+
+        let reported = reported.ok_or("PXE did not report an aztec-packages version")?;
+        let version = ProtocolVersion::parse(&reported)?;
+        self.profile = PayloadProfile::for_version(version)?;
+        self.version = Some(version);
+        Ok(version)
+    }
+
     pub async fn request<T: for<'de> serde::Deserialize<'de> + std::fmt::Debug>(
         &self,
         method: &str,
         params: Vec<serde_json::Value>,
-    ) -> Result<T, Box<dyn std::error::Error>> {
+    ) -> Result<T, AztecError> {
+        if self.dry_run && SUBMITTING_METHODS.contains(&method) {
+            return Err(AztecError::DryRun(method.to_string()));
+        }
+
         let full_method = if let Some(ns) = &self.namespace {
             format!("{}_{}", ns, method)
         } else {
             method.to_string()
         };
 
+        let id = self.request_ids.fetch_add(1, Ordering::Relaxed);
         let payload = json!({
             "jsonrpc": "2.0",
-            "id": 1,
+            "id": id,
             "method": full_method,
             "params": params,
         });
 
-        let client = &self.client;
-        let response = client.post(&self.host).json(&payload).send().await?;
-        let text = response.text().await?;
-
-        // println!("RPC raw response: {}", text);
+        let span = tracing::info_span!("pxe.call", method = %full_method, id);
+        let response = match self.transport.call(payload).instrument(span).await {
+            Ok(response) => response,
+            Err(e) => {
+                if let AztecError::Transport(error) = &e {
+                    self.alert(Alert::PxeUnreachable {
+                        method: full_method,
+                        error: error.clone(),
+                    });
+                }
+                return Err(e);
+            }
+        };
 
-        let rpc_response: RpcResponse<T> = serde_json::from_str(&text)?;
+        let rpc_response: RpcResponse<T> = serde_json::from_value(response)?;
+        check_envelope(&rpc_response, id, &full_method)?;
 
-        if let Some(err) = rpc_response.error {
-            return Err(format!("PXE returned error: {}", err).into());
+        if let Some(error) = rpc_response.error {
+            return Err(AztecError::Rpc {
+                method: full_method,
+                error,
+            });
         }
 
         rpc_response
             .result
-            .ok_or("Missing `result` field in RPC response".into())
+            .ok_or_else(|| AztecError::Transport("Missing `result` field in RPC response".into()))
+    }
+
+    /// What offline tx building needs to know about the network.
+    pub async fn get_node_info(&self) -> Result<NodeInfo, AztecError> {
+        self.request("getNodeInfo", vec![]).await
     }
 
-    pub async fn get_block_number(&self) -> Result<u64, Box<dyn std::error::Error>> {
+    pub async fn get_block_number(&self) -> Result<u64, AztecError> {
         self.request("getBlockNumber", vec![]).await
     }
 
-    pub async fn get_contracts(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-        self.request("getContracts", vec![]).await
+    /// `None` for blocks the node doesn't have yet.
+    pub async fn get_block(&self, number: u64) -> Result<Option<L2Block>, AztecError> {
+        self.request("getBlock", vec![json!(number)]).await
     }
 
-    pub async fn get_contract_metadata(&self) -> Result<Value, Box<dyn std::error::Error>> {
-        let value: Value = self
-            .request(
-                "getContractMetadata",
-                vec![json!(
-                    "0x12d8f70092c1d4b2bf3ddd60af8e47c1a10d90f3f31fe4c874d4b91f58442ede"
-                )],
-            )
-            .await?;
-
-        if let Some(contract_instance) = value.get("contractInstance").and_then(|v| v.as_object()) {
-            println!("Contract address: {}", contract_instance["address"]);
-            println!(
-                "Contract class ID: {}",
-                contract_instance["currentContractClassId"]
-            );
-        } else {
-            println!("Could not extract contractInstance.");
+    /// `None` until the tx is mined.
+    pub async fn get_tx_effect(
+        &self,
+        tx_hash: &str,
+    ) -> Result<Option<IndexedTxEffect>, AztecError> {
+        self.request("getTxEffect", vec![json!(tx_hash)]).await
+    }
+
+    pub async fn get_public_storage_at(&self, contract: &str, slot: &Fr) -> Result<Fr, AztecError> {
+        self.request("getPublicStorageAt", vec![json!(contract), json!(slot)])
+            .await
+    }
+
+    pub async fn get_notes(&self, filter: Value) -> Result<Value, AztecError> {
+        self.request("getNotes", vec![filter]).await
+    }
+
+    /// Node method: up to `limit` private logs from block `from` onwards,
+    /// for `IncomingNoteDecryptor` to scan.
+    pub async fn get_private_logs(
+        &self,
+        from: u64,
+        limit: u64,
+    ) -> Result<Vec<PrivateLog>, AztecError> {
+        self.request("getPrivateLogs", vec![json!(from), json!(limit)])
+            .await
+    }
+
+    /// `{ txHash, status, error, blockNumber, .. }`; unknown hashes come back
+    /// with status `dropped` rather than as an error.
+    pub async fn get_tx_receipt(&self, tx_hash: &str) -> Result<Value, AztecError> {
+        let receipt = self.request("getTxReceipt", vec![json!(tx_hash)]).await?;
+        if let Some(budget) = &self.fee_budget {
+            budget.settle(&receipt).map_err(AztecError::State)?;
+        }
+        if let Some(profiler) = &self.gas_profiler {
+            profiler.record(&receipt).map_err(AztecError::State)?;
+        }
+        if let Some(journal) = &self.tx_journal {
+            journal.settle(&receipt).map_err(AztecError::State)?;
         }
+        if let Some(pool) = &self.sender_pool {
+            pool.settle(&receipt);
+        }
+        Ok(receipt)
+    }
 
-        Ok(value)
+    /// Gives up on a sent tx: the tx journal stops resubmitting it and the
+    /// sender pool frees its account's slot. Aztec can't replace a tx, so one
+    /// the PXE already holds may still be mined; its receipt then settles
+    /// the fee budget as usual.
+    pub fn cancel_tx(&self, tx_hash: &str) -> Result<(), AztecError> {
+        if let Some(journal) = &self.tx_journal {
+            journal.cancel(tx_hash).map_err(AztecError::State)?;
+        }
+        if let Some(pool) = &self.sender_pool {
+            pool.cancel(tx_hash);
+        }
+        Ok(())
     }
 
-    pub async fn send_tx_set_feeds(
+    /// Polls the receipt until the tx leaves `pending`, and returns it
+    /// whatever the final status (`success`, a revert, or `dropped`).
+    pub async fn wait_for_tx(
         &self,
-        _from_address: &str,
-        _to_contract_address: &str,
-        _function_name: &str,
-        _args: Vec<Value>,
+        tx_hash: &str,
+        max_attempts: u32,
+        delay: Duration,
     ) -> Result<Value, Box<dyn std::error::Error>> {
-        let _gas_limits_da_gas = 1000000000;
-        let _gas_limits_l2_gas = 1000000000;
-
-        let _teardown_limits_da_gas = 6000000;
-        let _teardown_limits_l2_gas = 6000000;
-
-        let tx_execution_request = json!({
-          "jsonrpc": "2.0",
-          "id": 9,
-          "method": "pxe_simulateTx",
-          "params": [
-            {
-              "origin": "0x154307e2c5e6b146106ad12642a7a1abef01990b0bc68b21c0de67267a705344",
-              "functionSelector": "0x27e740b2",
-              "firstCallArgsHash": "0x11f1fc3d3ffa64fccd5dc340dd3991395969b30b08306a563e42e2085138abda",
-              "txContext": {
-                "gasSettings": {
-                  "gasLimits": { "daGas": 1000000000, "l2Gas": 1000000000 },
-                  "teardownGasLimits": { "daGas": 6000000, "l2Gas": 6000000 },
-                  "maxFeesPerGas": {
-                    "feePerDaGas": "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "feePerL2Gas": "0x0000000000000000000000000000000000000000000000000000000000002aa8"
-                  },
-                  "maxPriorityFeesPerGas": {
-                    "feePerDaGas": "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "feePerL2Gas": "0x0000000000000000000000000000000000000000000000000000000000000000"
-                  }
-                },
-                "chainId": "0x0000000000000000000000000000000000000000000000000000000000007a69",
-                "version": "0x00000000000000000000000000000000000000000000000000000000b2da7e95"
-              },
-              "argsOfCalls": [
-                {
-                  "values": [
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000"
-                  ],
-                  "hash": "0x2ff90a8a1f6c3253957f7864dfdf12ec0eef9006c26cdf58dab6a170b5b7dd1c"
-                },
-                {
-                  "values": [
-                    "0x0000000000000000000000000000000000000000000000000000000017f12888"
-                  ],
-                  "hash": "0x0825a9b29181eef01b503945a4268c1d9f7714782fd4d8383a9c6257066df693"
-                },
-                {
-                  "values": [
-                    "0x0000000000000000000000000000000000000000000000000000000000000000"
-                  ],
-                  "hash": "0x2032c19437941846a704c8b191e823c8074b38114a03a22e93020ef6f7688b4d"
-                },
-                {
-                  "values": [
-                    "0x0000000000000000000000000000000000000000000000000000000000000000"
-                  ],
-                  "hash": "0x2032c19437941846a704c8b191e823c8074b38114a03a22e93020ef6f7688b4d"
-                },
-                {
-                  "values": [
-                    "0x0000000000000000000000000000000000000000000000000000000000000000"
-                  ],
-                  "hash": "0x2032c19437941846a704c8b191e823c8074b38114a03a22e93020ef6f7688b4d"
-                },
-                {
-                  "values": [
-                    "0x0000000000000000000000000000000000000000000000000000000000000000"
-                  ],
-                  "hash": "0x2032c19437941846a704c8b191e823c8074b38114a03a22e93020ef6f7688b4d"
-                },
-                {
-                  "values": [
-                    "0x2ff90a8a1f6c3253957f7864dfdf12ec0eef9006c26cdf58dab6a170b5b7dd1c",
-                    "0x0000000000000000000000000000000000000000000000000000000000c02957",
-                    "0x0000000000000000000000000000000000000000000000000000000000000002",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x0825a9b29181eef01b503945a4268c1d9f7714782fd4d8383a9c6257066df693",
-                    "0x0000000000000000000000000000000000000000000000000000000017f12888",
-                    "0x044b9be988489338e14b0ab349a6d6b5e47b329b0fd2cc9a0a373ba2ddd676b2",
-                    "0x0000000000000000000000000000000000000000000000000000000000000001",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x2032c19437941846a704c8b191e823c8074b38114a03a22e93020ef6f7688b4d",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x0000000000000000000000000000000000000000000000000000000000000001",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x2032c19437941846a704c8b191e823c8074b38114a03a22e93020ef6f7688b4d",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x0000000000000000000000000000000000000000000000000000000000000001",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x084691ec849079122dbf0b59d4831ca107e46d444270f9fe80355efc37ec5a74",
-                    "0x2032c19437941846a704c8b191e823c8074b38114a03a22e93020ef6f7688b4d",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x0000000000000000000000000000000000000000000000000000000000000001",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x2032c19437941846a704c8b191e823c8074b38114a03a22e93020ef6f7688b4d",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x0000000000000000000000000000000000000000000000000000000000000001",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000",
-                    "0x2c1dbbf61cd800fc996d6bf52dd4acb34e659a2d09946dc5e9721ca3b97a067d",
-                    "0x0000000000000000000000000000000000000000000000000000000000000001",
-                    "0x0000000000000000000000000000000000000000000000000000000000000000"
-                  ],
-                  "hash": "0x11f1fc3d3ffa64fccd5dc340dd3991395969b30b08306a563e42e2085138abda"
-                }
-              ],
-              "authWitnesses": [
-                "0x239041351450551a45e86e62eadc39d99960e37b07c7ef9b2a08de24f860efc500000040000000000000000000000000000000000000000000000000000000000000002e000000000000000000000000000000000000000000000000000000000000008d000000000000000000000000000000000000000000000000000000000000007e000000000000000000000000000000000000000000000000000000000000003e00000000000000000000000000000000000000000000000000000000000000f1000000000000000000000000000000000000000000000000000000000000008700000000000000000000000000000000000000000000000000000000000000cd00000000000000000000000000000000000000000000000000000000000000a200000000000000000000000000000000000000000000000000000000000000cc000000000000000000000000000000000000000000000000000000000000003900000000000000000000000000000000000000000000000000000000000000bc00000000000000000000000000000000000000000000000000000000000000c0000000000000000000000000000000000000000000000000000000000000003c00000000000000000000000000000000000000000000000000000000000000e300000000000000000000000000000000000000000000000000000000000000b600000000000000000000000000000000000000000000000000000000000000ae00000000000000000000000000000000000000000000000000000000000000140000000000000000000000000000000000000000000000000000000000000065000000000000000000000000000000000000000000000000000000000000002400000000000000000000000000000000000000000000000000000000000000b800000000000000000000000000000000000000000000000000000000000000fc000000000000000000000000000000000000000000000000000000000000006d000000000000000000000000000000000000000000000000000000000000000a00000000000000000000000000000000000000000000000000000000000000af00000000000000000000000000000000000000000000000000000000000000ab00000000000000000000000000000000000000000000000000000000000000940000000000000000000000000000000000000000000000000000000000000053000000000000000000000000000000000000000000000000000000000000008b00000000000000000000000000000000000000000000000000000000000000a40000000000000000000000000000000000000000000000000000000000000013000000000000000000000000000000000000000000000000000000000000005b0000000000000000000000000000000000000000000000000000000000000036000000000000000000000000000000000000000000000000000000000000003400000000000000000000000000000000000000000000000000000000000000ab00000000000000000000000000000000000000000000000000000000000000dc00000000000000000000000000000000000000000000000000000000000000a5000000000000000000000000000000000000000000000000000000000000000400000000000000000000000000000000000000000000000000000000000000a500000000000000000000000000000000000000000000000000000000000000f4000000000000000000000000000000000000000000000000000000000000007d00000000000000000000000000000000000000000000000000000000000000c000000000000000000000000000000000000000000000000000000000000000b100000000000000000000000000000000000000000000000000000000000000d90000000000000000000000000000000000000000000000000000000000000056000000000000000000000000000000000000000000000000000000000000009d00000000000000000000000000000000000000000000000000000000000000ea000000000000000000000000000000000000000000000000000000000000000f00000000000000000000000000000000000000000000000000000000000000ed00000000000000000000000000000000000000000000000000000000000000d60000000000000000000000000000000000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000005b000000000000000000000000000000000000000000000000000000000000005e00000000000000000000000000000000000000000000000000000000000000a2000000000000000000000000000000000000000000000000000000000000004200000000000000000000000000000000000000000000000000000000000000f0000000000000000000000000000000000000000000000000000000000000003800000000000000000000000000000000000000000000000000000000000000b500000000000000000000000000000000000000000000000000000000000000bc0000000000000000000000000000000000000000000000000000000000000008000000000000000000000000000000000000000000000000000000000000005c000000000000000000000000000000000000000000000000000000000000005200000000000000000000000000000000000000000000000000000000000000b900000000000000000000000000000000000000000000000000000000000000d10000000000000000000000000000000000000000000000000000000000000097"
-              ],
-              "capsules": []
-            },
-            true,
-            null,
-            true,
-            null
-          ]
-        });
-        // let _payload = json!({
-        //     "txRequest": tx_execution_request,
-        //     "simulatePublic": true,
-        //     "msgSender": "0x154307e2c5e6b146106ad12642a7a1abef01990b0bc68b21c0de67267a705344",
-        //     "skipTxValidation": false,
-        //     "skipFeeEnforcement": false,
-        //     "scopes": [],
-        // });
-        println!("goes to here");
-
-        // println!(
-        //     "tx_execution_request: {:?} ",
-        //     tx_execution_request["params"].as_array().unwrap().to_vec()
-        // );
-        // encoder.encode_argument(&my_abi_type, &my_arg_json, Some("arg0"))?;
-
-        println!("");
-
-        let result: Value = self
-            .request(
-                "simulateTx",
-                tx_execution_request["params"].as_array().unwrap().to_vec(),
-            )
-            .await?;
-
-        Ok(result)
+        let span = tracing::info_span!(
+            "tx.wait",
+            tx_hash,
+            attempts = tracing::field::Empty,
+            status = tracing::field::Empty,
+        );
+        for attempt in 1..=max_attempts {
+            span.record("attempts", attempt);
+            let receipt = self
+                .get_tx_receipt(tx_hash)
+                .instrument(span.clone())
+                .await?;
+            if receipt["status"] != "pending" {
+                span.record("status", receipt["status"].as_str().unwrap_or_default());
+                return Ok(receipt);
+            }
+            if attempt < max_attempts {
+                sleep(delay).instrument(span.clone()).await;
+            }
+        }
+        Err(format!(
+            "Tx {} still pending after {} attempts",
+            tx_hash, max_attempts
+        )
+        .into())
+    }
+
+    /// The artifact of a contract class, if the PXE has it registered.
+    pub async fn get_contract_artifact(&self, class_id: &Fr) -> Result<Value, AztecError> {
+        self.request("getContractArtifact", vec![json!(class_id)])
+            .await
+    }
+
+    /// Bare addresses; `contracts::ContractLister` adds their metadata.
+    pub async fn get_contracts(&self) -> Result<Vec<String>, AztecError> {
+        self.request("getContracts", vec![]).await
+    }
+
+    // Unchecked: callers validate `address` first.
+    async fn contract_metadata(&self, address: &str) -> Result<Value, AztecError> {
+        self.request("getContractMetadata", vec![json!(address)])
+            .await
+    }
+
+    /// `{ contractInstance, isContractInitialized, isContractPublished }`;
+    /// `contractInstance` is null when the PXE doesn't know the address.
+    /// The address is checked, so nothing that isn't one ends up in the
+    /// request.
+    pub async fn get_contract_metadata(&self, address: &AztecAddress) -> Result<Value, AztecError> {
+        address.validate().map_err(AztecError::Encoding)?;
+        self.contract_metadata(&address.to_string()).await
+    }
+
+    /// Metadata of each address, in order, a few requests at a time. Every
+    /// address is checked before any request goes out.
+    pub async fn get_contracts_metadata(
+        &self,
+        addresses: &[AztecAddress],
+    ) -> Result<Vec<Value>, AztecError> {
+        for address in addresses {
+            address.validate().map_err(AztecError::Encoding)?;
+        }
+        stream::iter(addresses)
+            .map(|address| async move { self.contract_metadata(&address.to_string()).await })
+            .buffered(METADATA_CONCURRENCY)
+            .try_collect()
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::tests::Collect;
+    use crate::testing::MockPxe;
+
+    #[tokio::test]
+    async fn test_builder_sends_headers_through_the_proxy() {
+        let proxy = MockPxe::start().await.unwrap();
+        proxy.respond("pxe_getBlockNumber", json!(7));
+        let pxe = AztecRpcClient::builder("http://pxe.invalid:8080")
+            .namespace("pxe")
+            .proxy(proxy.url())
+            .header("X-Api-Key", "secret")
+            .pool_max_idle_per_host(4)
+            .tcp_keepalive(Duration::from_secs(30))
+            .build()
+            .unwrap();
+
+        assert_eq!(pxe.get_block_number().await.unwrap(), 7);
+        let head = &proxy.request_heads()[0];
+        assert!(
+            head.starts_with("post http://pxe.invalid:8080/ "),
+            "{}",
+            head
+        );
+        assert!(head.contains("x-api-key: secret"), "{}", head);
+
+        let bad = AztecRpcClient::builder("http://localhost:0").header("bad header", "x");
+        assert!(bad.build().is_err());
+    }
+
+    #[test]
+    fn test_http_config_parsing() {
+        let config = HttpClientConfig::parse(
+            Some("16"),
+            Some("90"),
+            None,
+            Some("true"),
+            Some("http://proxy:3128"),
+            Some("X-Api-Key: abc:def; X-Org: feeds"),
+        )
+        .unwrap();
+        assert_eq!(config.pool_max_idle_per_host, Some(16));
+        assert_eq!(config.pool_idle_timeout, Some(Duration::from_secs(90)));
+        assert_eq!(config.tcp_keepalive, None);
+        assert!(config.http2_prior_knowledge);
+        assert_eq!(
+            config.headers,
+            [
+                ("X-Api-Key".to_string(), "abc:def".to_string()),
+                ("X-Org".to_string(), "feeds".to_string()),
+            ]
+        );
+        assert!(config.build().is_ok());
+
+        assert!(HttpClientConfig::parse(Some("many"), None, None, None, None, None).is_err());
+        assert!(HttpClientConfig::parse(None, None, None, None, None, Some("no-colon")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_contract_metadata_checks_addresses() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond(
+            "pxe_getContractMetadata",
+            json!({ "isContractInitialized": true }),
+        );
+        let pxe = AztecRpcClient::new(mock.url(), Some("pxe".to_string()));
+        let address = |n: u8| AztecAddress(Fr::from(n));
+
+        let zero = pxe.get_contract_metadata(&address(0)).await.unwrap_err();
+        assert!(matches!(zero, AztecError::Encoding(_)), "{:?}", zero);
+        let unreduced = AztecAddress(Fr::from_biguint(Fr::modulus()));
+        let batch = [address(1), unreduced];
+        assert!(pxe.get_contracts_metadata(&batch).await.is_err());
+        assert!(mock.requests().is_empty());
+
+        let metadata = pxe
+            .get_contracts_metadata(&[address(10), address(11)])
+            .await
+            .unwrap();
+        assert_eq!(metadata.len(), 2);
+        assert_eq!(metadata[1]["isContractInitialized"], true);
+        let mut params: Vec<String> = mock
+            .requests()
+            .iter()
+            .map(|r| r["params"].to_string())
+            .collect();
+        params.sort();
+        assert_eq!(
+            params,
+            [
+                json!([address(10)]).to_string(),
+                json!([address(11)]).to_string()
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_request_failures_are_typed_errors() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond_with_envelope(
+            "pxe_getBlockNumber",
+            json!({ "jsonrpc": "2.0", "id": 1, "error": { "code": -32601, "message": "nope" } }),
+        );
+        mock.respond_with_envelope("pxe_getContracts", json!({ "jsonrpc": "2.0", "id": 1 }));
+        mock.respond("pxe_getTxReceipt", json!("not a receipt"));
+        let pxe = AztecRpcClient::new(mock.url(), Some("pxe".to_string()));
+
+        let err = pxe.get_block_number().await.unwrap_err();
+        assert!(
+            matches!(&err, AztecError::Rpc { method, .. } if method == "pxe_getBlockNumber"),
+            "{:?}",
+            err
+        );
+        assert!(matches!(
+            pxe.get_contracts().await.unwrap_err(),
+            AztecError::Transport(_)
+        ));
+        assert!(matches!(
+            pxe.request::<u64>("getTxReceipt", vec![])
+                .await
+                .unwrap_err(),
+            AztecError::Transport(_)
+        ));
+
+        let alerts = Arc::new(Collect::default());
+        let offline =
+            AztecRpcClient::new("http://127.0.0.1:1", None).with_alert_sink(alerts.clone());
+        assert!(matches!(
+            offline.get_block_number().await.unwrap_err(),
+            AztecError::Transport(_)
+        ));
+        assert!(matches!(
+            &alerts.0.lock().unwrap()[..],
+            [Alert::PxeUnreachable { method, .. }] if method == "getBlockNumber"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_requests_carry_increasing_ids() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond("pxe_getBlockNumber", json!(7));
+        let ids = Arc::new(AtomicU64::new(40));
+        let pxe =
+            AztecRpcClient::new(mock.url(), Some("pxe".to_string())).with_request_ids(ids.clone());
+        let other = AztecRpcClient::new(mock.url(), Some("pxe".to_string())).with_request_ids(ids);
+
+        pxe.get_block_number().await.unwrap();
+        other.get_block_number().await.unwrap();
+        pxe.clone().get_block_number().await.unwrap();
+        let sent: Vec<Value> = mock.requests().iter().map(|r| r["id"].clone()).collect();
+        assert_eq!(sent, [json!(40), json!(41), json!(42)]);
+
+        let reply =
+            |envelope: Value| -> RpcResponse<u64> { serde_json::from_value(envelope).unwrap() };
+        let ok = reply(json!({ "jsonrpc": "2.0", "id": 3, "result": 1 }));
+        assert!(check_envelope(&ok, 3, "pxe_m").is_ok());
+        let err = check_envelope(&ok, 4, "pxe_m").unwrap_err();
+        assert!(
+            matches!(&err, AztecError::Protocol(e) if e.contains("id 3, expected 4")),
+            "{}",
+            err
+        );
+        let version = reply(json!({ "jsonrpc": "1.0", "id": 3, "result": 1 }));
+        assert!(matches!(
+            check_envelope(&version, 3, "pxe_m"),
+            Err(AztecError::Protocol(_))
+        ));
+        let missing = reply(json!({ "id": 3, "result": 1 }));
+        assert!(check_envelope(&missing, 3, "pxe_m").is_err());
+        let unread = reply(json!({ "jsonrpc": "2.0", "id": null, "error": { "code": -32700 } }));
+        assert!(check_envelope(&unread, 3, "pxe_m").is_ok());
+        let anonymous = reply(json!({ "jsonrpc": "2.0", "id": null, "result": 1 }));
+        assert!(check_envelope(&anonymous, 3, "pxe_m").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_tx_polls_until_mined() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond(
+            "pxe_getTxReceipt",
+            json!({ "txHash": "0xab", "status": "pending" }),
+        );
+        let pxe = AztecRpcClient::new(mock.url(), Some("pxe".to_string()));
+
+        let err = pxe
+            .wait_for_tx("0xab", 2, Duration::from_millis(1))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("still pending"), "{}", err);
+        assert_eq!(mock.requests().len(), 2);
+
+        mock.respond(
+            "pxe_getTxReceipt",
+            json!({ "txHash": "0xab", "status": "success" }),
+        );
+        let receipt = pxe
+            .wait_for_tx("0xab", 5, Duration::from_millis(1))
+            .await
+            .unwrap();
+        assert_eq!(receipt["status"], "success");
+    }
+
+    #[tokio::test]
+    async fn test_null_results_decode_as_none() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond("node_getBlock", Value::Null);
+        mock.respond("node_getTxEffect", Value::Null);
+        mock.respond("node_getBlockNumber", Value::Null);
+        let node = AztecRpcClient::new(mock.url(), Some("node".to_string()));
+
+        assert_eq!(node.get_block(99).await.unwrap(), None);
+        assert_eq!(node.get_tx_effect("0x01").await.unwrap(), None);
+        assert!(matches!(
+            node.get_block_number().await.unwrap_err(),
+            AztecError::Transport(_)
+        ));
     }
 }
@@ -0,0 +1,262 @@
+use serde::{Deserialize, Serialize};
+
+use crate::fields::Fr;
+use crate::private_logs::PrivateLog;
+use crate::tx_request::GasFees;
+
+/// An L2 block as the node returns it from `getBlock`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct L2Block {
+    /// The archive tree after this block was added to it.
+    pub archive: AppendOnlyTreeSnapshot,
+    pub header: BlockHeader,
+    pub body: BlockBody,
+}
+
+impl L2Block {
+    pub fn number(&self) -> Option<u64> {
+        self.header.global_variables.block_number.to_u64()
+    }
+
+    pub fn tx_effects(&self) -> &[TxEffect] {
+        &self.body.tx_effects
+    }
+
+    pub fn tx_effect(&self, tx_hash: &str) -> Option<&TxEffect> {
+        self.body
+            .tx_effects
+            .iter()
+            .find(|effect| effect.tx_hash.eq_ignore_ascii_case(tx_hash))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppendOnlyTreeSnapshot {
+    pub root: Fr,
+    pub next_available_leaf_index: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockHeader {
+    pub last_archive: AppendOnlyTreeSnapshot,
+    pub content_commitment: ContentCommitment,
+    pub state: StateReference,
+    pub global_variables: GlobalVariables,
+    pub total_fees: Fr,
+    pub total_mana_used: Fr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentCommitment {
+    pub num_txs: Fr,
+    pub blobs_hash: Fr,
+    pub in_hash: Fr,
+    pub out_hash: Fr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateReference {
+    pub l1_to_l2_message_tree: AppendOnlyTreeSnapshot,
+    pub partial: PartialStateReference,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartialStateReference {
+    pub note_hash_tree: AppendOnlyTreeSnapshot,
+    pub nullifier_tree: AppendOnlyTreeSnapshot,
+    pub public_data_tree: AppendOnlyTreeSnapshot,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GlobalVariables {
+    pub chain_id: Fr,
+    pub version: Fr,
+    pub block_number: Fr,
+    pub slot_number: Fr,
+    pub timestamp: Fr,
+    /// L1 address the sequencer's rewards go to.
+    pub coinbase: String,
+    pub fee_recipient: Fr,
+    pub gas_fees: GasFees,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockBody {
+    pub tx_effects: Vec<TxEffect>,
+}
+
+/// Everything a mined tx changed. Log lists hold the logs themselves; nodes
+/// that still report contract class log hashes fill `contract_class_logs_hashes`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxEffect {
+    /// 0 when the tx succeeded; otherwise which phase reverted.
+    pub revert_code: u8,
+    pub tx_hash: String,
+    pub transaction_fee: Fr,
+    pub note_hashes: Vec<Fr>,
+    pub nullifiers: Vec<Fr>,
+    pub l2_to_l1_msgs: Vec<Fr>,
+    pub public_data_writes: Vec<PublicDataWrite>,
+    pub private_logs: Vec<PrivateLog>,
+    pub public_logs: Vec<PublicLog>,
+    pub contract_class_logs: Vec<ContractClassLog>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contract_class_logs_hashes: Vec<LogHash>,
+}
+
+impl TxEffect {
+    pub fn reverted(&self) -> bool {
+        self.revert_code != 0
+    }
+}
+
+/// `getTxEffect`'s result: the effect and where it was mined.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedTxEffect {
+    pub l2_block_number: u64,
+    pub l2_block_hash: String,
+    #[serde(default)]
+    pub tx_index_in_block: Option<u64>,
+    pub data: TxEffect,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicDataWrite {
+    /// The storage slot siloed with the contract address.
+    pub leaf_slot: Fr,
+    pub value: Fr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicLog {
+    pub contract_address: Fr,
+    pub fields: Vec<Fr>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractClassLog {
+    pub contract_address: Fr,
+    pub fields: Vec<Fr>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogHash {
+    pub value: Fr,
+    /// Length of the hashed log in fields.
+    pub length: u64,
+}
+
+/// JSON shaped like the node's responses, for tests here and elsewhere.
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use serde_json::{json, Value};
+
+    pub(crate) fn tx_effect_json() -> Value {
+        json!({
+            "revertCode": 0,
+            "txHash": "0x1a2b",
+            "transactionFee": "0x0c8d",
+            "noteHashes": ["0x05"],
+            "nullifiers": ["0x06", "0x07"],
+            "l2ToL1Msgs": [],
+            "publicDataWrites": [{ "leafSlot": "0x0a", "value": "0x2a" }],
+            "privateLogs": [{ "fields": ["0x01", "0x02"], "emittedLength": 2 }],
+            "publicLogs": [{ "contractAddress": "0x0c", "fields": ["0x03"] }],
+            "contractClassLogs": [],
+        })
+    }
+
+    fn snapshot(root: u8, next: u64) -> Value {
+        json!({ "root": format!("0x{:02x}", root), "nextAvailableLeafIndex": next })
+    }
+
+    pub(crate) fn block_json(number: u64, tx_effects: Vec<Value>) -> Value {
+        json!({
+            "archive": snapshot(1, 8),
+            "header": {
+                "lastArchive": snapshot(2, 7),
+                "contentCommitment": { "numTxs": "0x01", "blobsHash": "0x0b", "inHash": "0x00", "outHash": "0x00" },
+                "state": {
+                    "l1ToL2MessageTree": snapshot(3, 16),
+                    "partial": {
+                        "noteHashTree": snapshot(4, 64),
+                        "nullifierTree": snapshot(5, 128),
+                        "publicDataTree": snapshot(6, 256),
+                    },
+                },
+                "globalVariables": {
+                    "chainId": "0x7a69",
+                    "version": "0x01",
+                    "blockNumber": number,
+                    "slotNumber": "0x10",
+                    "timestamp": "0x6553f100",
+                    "coinbase": "0x0000000000000000000000000000000000000000",
+                    "feeRecipient": "0x00",
+                    "gasFees": { "feePerDaGas": "0x00", "feePerL2Gas": "0x0a" },
+                },
+                "totalFees": "0x0c8d",
+                "totalManaUsed": "0x0141",
+            },
+            "body": { "txEffects": tx_effects },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixtures::{block_json, tx_effect_json};
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_deserializes_block() {
+        let block: L2Block = serde_json::from_value(block_json(7, vec![tx_effect_json()])).unwrap();
+
+        assert_eq!(block.number(), Some(7));
+        assert_eq!(
+            block
+                .header
+                .state
+                .partial
+                .nullifier_tree
+                .next_available_leaf_index,
+            128
+        );
+        let effect = block.tx_effect("0x1A2B").unwrap();
+        assert!(!effect.reverted());
+        assert_eq!(
+            effect.public_data_writes,
+            vec![PublicDataWrite {
+                leaf_slot: Fr::from(10u8),
+                value: Fr::from(42u8),
+            }]
+        );
+        assert_eq!(effect.private_logs[0].fields.len(), 2);
+        assert!(effect.contract_class_logs_hashes.is_empty());
+    }
+
+    #[test]
+    fn test_rejects_malformed_effects() {
+        let mut effect = tx_effect_json();
+        effect["noteHashes"] = json!(["not a field"]);
+        assert!(serde_json::from_value::<TxEffect>(effect).is_err());
+
+        let mut effect = tx_effect_json();
+        effect.as_object_mut().unwrap().remove("nullifiers");
+        assert!(serde_json::from_value::<TxEffect>(effect).is_err());
+    }
+}
@@ -0,0 +1,355 @@
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use super::keys::{normalize_key, parse_operators, verify};
+use super::protocol::{ApprovalStatus, CallRequest};
+use crate::state::StateStore;
+
+const PENDING_PREFIX: &str = "approvals/pending/";
+const NONCE_KEY: &str = "approvals/nonce";
+
+/// N-of-M operator sign-off required before a `set` is sent. Operators are
+/// secp256k1 public keys (SEC1 hex); each signs the 32-byte proposal id.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApprovalPolicy {
+    pub threshold: usize,
+    pub operators: Vec<String>,
+}
+
+impl ApprovalPolicy {
+    /// `operators` is comma-separated; `threshold` defaults to all of them.
+    pub fn parse(operators: &str, threshold: Option<&str>) -> Result<Self, String> {
+        let operators = parse_operators(operators)?;
+        let threshold = match threshold {
+            Some(t) => t
+                .parse()
+                .map_err(|_| format!("Invalid approval threshold '{}'", t))?,
+            None => operators.len(),
+        };
+
+        if threshold == 0 || threshold > operators.len() {
+            return Err(format!(
+                "Approval threshold must be between 1 and {}, got {}",
+                operators.len(),
+                threshold
+            ));
+        }
+        Ok(ApprovalPolicy {
+            threshold,
+            operators,
+        })
+    }
+}
+
+/// A `set` waiting for signatures. `approvals` maps operator key to signature.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingApproval {
+    pub id: String,
+    pub call: CallRequest,
+    pub approvals: BTreeMap<String, String>,
+    #[serde(default)]
+    pub state: ApprovalState,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalState {
+    #[default]
+    Pending,
+    /// Threshold met and the call handed out to be sent; further approvals
+    /// are refused so it is sent once.
+    Executing,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApprovalProgress {
+    Pending(ApprovalStatus),
+    /// Threshold met; the call can be sent. It is marked `Executing` until
+    /// `Approvals::complete`, or `Approvals::release` after a failed send so
+    /// it can be retried by re-approving.
+    Ready(CallRequest),
+}
+
+/// Pending approvals, kept in the `StateStore` so they survive restarts.
+pub struct Approvals {
+    policy: ApprovalPolicy,
+    store: Arc<StateStore>,
+    // Serializes the read-modify-writes of the nonce and pending approvals.
+    lock: Mutex<()>,
+}
+
+impl Approvals {
+    pub fn new(policy: ApprovalPolicy, store: Arc<StateStore>) -> Self {
+        Approvals {
+            policy,
+            store,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Parks `call` and returns its id: `keccak256(call json || nonce)`, so
+    /// operators can check what they sign from the call alone.
+    pub fn propose(&self, call: CallRequest) -> Result<ApprovalStatus, String> {
+        let _guard = self.lock.lock().unwrap();
+        let nonce = self.store.get::<u64>(NONCE_KEY)?.unwrap_or(0);
+        self.store.put(NONCE_KEY, &(nonce + 1))?;
+
+        let pending = PendingApproval {
+            id: proposal_id(&call, nonce),
+            call,
+            approvals: BTreeMap::new(),
+            state: ApprovalState::Pending,
+        };
+        self.store
+            .put(&format!("{}{}", PENDING_PREFIX, pending.id), &pending)?;
+        Ok(self.status(&pending))
+    }
+
+    pub fn approve(
+        &self,
+        id: &str,
+        operator: &str,
+        signature: &str,
+    ) -> Result<ApprovalProgress, String> {
+        let operator = normalize_key(operator)?;
+        if !self.policy.operators.contains(&operator) {
+            return Err(format!("{} is not an approval operator", operator));
+        }
+        let digest = hex::decode(id.trim_start_matches("0x"))
+            .map_err(|_| format!("Invalid approval id {}", id))?;
+        verify(&digest, &operator, signature)?;
+
+        let _guard = self.lock.lock().unwrap();
+        let key = format!("{}{}", PENDING_PREFIX, id);
+        let mut pending: PendingApproval = self
+            .store
+            .get(&key)?
+            .ok_or_else(|| format!("Unknown approval {}", id))?;
+        if pending.state == ApprovalState::Executing {
+            return Err(format!("Approval {} is already being sent", id));
+        }
+
+        pending.approvals.insert(operator, signature.to_string());
+        let ready = pending.approvals.len() >= self.policy.threshold;
+        if ready {
+            pending.state = ApprovalState::Executing;
+        }
+        self.store.put(&key, &pending)?;
+
+        if ready {
+            Ok(ApprovalProgress::Ready(pending.call))
+        } else {
+            Ok(ApprovalProgress::Pending(self.status(&pending)))
+        }
+    }
+
+    /// Drops an approval whose call has been sent.
+    pub fn complete(&self, id: &str) -> Result<(), String> {
+        let _guard = self.lock.lock().unwrap();
+        self.store.remove(&format!("{}{}", PENDING_PREFIX, id))
+    }
+
+    /// Puts an approval whose send failed back to pending, keeping its
+    /// signatures, so the next approval sends it again.
+    pub fn release(&self, id: &str) -> Result<(), String> {
+        let _guard = self.lock.lock().unwrap();
+        let key = format!("{}{}", PENDING_PREFIX, id);
+        if let Some(mut pending) = self.store.get::<PendingApproval>(&key)? {
+            pending.state = ApprovalState::Pending;
+            self.store.put(&key, &pending)?;
+        }
+        Ok(())
+    }
+
+    pub fn pending(&self) -> Result<Vec<PendingApproval>, String> {
+        self.store
+            .keys(PENDING_PREFIX)
+            .iter()
+            .filter_map(|key| self.store.get(key).transpose())
+            .collect()
+    }
+
+    fn status(&self, pending: &PendingApproval) -> ApprovalStatus {
+        ApprovalStatus {
+            id: pending.id.clone(),
+            approvals: pending.approvals.len(),
+            threshold: self.policy.threshold,
+        }
+    }
+}
+
+fn proposal_id(call: &CallRequest, nonce: u64) -> String {
+    let mut hasher = Keccak256::new();
+    hasher.update(serde_json::to_vec(call).expect("call request serializes"));
+    hasher.update(nonce.to_be_bytes());
+    format!("0x{}", hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use k256::ecdsa::signature::hazmat::PrehashSigner;
+    use k256::ecdsa::{Signature, SigningKey};
+    use serde_json::json;
+
+    pub(crate) fn operator(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32].into()).unwrap()
+    }
+
+    pub(crate) fn public_key(key: &SigningKey) -> String {
+        format!(
+            "0x{}",
+            hex::encode(key.verifying_key().to_encoded_point(true).as_bytes())
+        )
+    }
+
+    pub(crate) fn sign(key: &SigningKey, id: &str) -> String {
+        let digest = hex::decode(id.trim_start_matches("0x")).unwrap();
+        let signature: Signature = key.sign_prehash(&digest).unwrap();
+        format!("0x{}", hex::encode(signature.to_bytes()))
+    }
+
+    fn two_of_three() -> ApprovalPolicy {
+        let keys: Vec<String> = (1..=3).map(|i| public_key(&operator(i))).collect();
+        ApprovalPolicy::parse(&keys.join(","), Some("2")).unwrap()
+    }
+
+    fn call() -> CallRequest {
+        CallRequest {
+            value: Some(json!(214)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_policy_validation() {
+        let key = public_key(&operator(1));
+        assert_eq!(ApprovalPolicy::parse(&key, None).unwrap().threshold, 1);
+        assert!(ApprovalPolicy::parse(&key, Some("2")).is_err());
+        assert!(ApprovalPolicy::parse(&key, Some("0")).is_err());
+        assert!(ApprovalPolicy::parse("0x1234", None).is_err());
+    }
+
+    #[test]
+    fn test_two_of_three_flow() {
+        let approvals = Approvals::new(two_of_three(), Arc::new(StateStore::in_memory()));
+        let status = approvals.propose(call()).unwrap();
+        assert_eq!(status.approvals, 0);
+
+        let first = approvals
+            .approve(
+                &status.id,
+                &public_key(&operator(1)),
+                &sign(&operator(1), &status.id),
+            )
+            .unwrap();
+        assert!(matches!(
+            first,
+            ApprovalProgress::Pending(ApprovalStatus { approvals: 1, .. })
+        ));
+
+        // Approving twice doesn't count twice.
+        let again = approvals
+            .approve(
+                &status.id,
+                &public_key(&operator(1)),
+                &sign(&operator(1), &status.id),
+            )
+            .unwrap();
+        assert!(matches!(again, ApprovalProgress::Pending(_)));
+
+        let second = approvals
+            .approve(
+                &status.id,
+                &public_key(&operator(3)),
+                &sign(&operator(3), &status.id),
+            )
+            .unwrap();
+        assert_eq!(second, ApprovalProgress::Ready(call()));
+        assert_eq!(
+            approvals.pending().unwrap()[0].state,
+            ApprovalState::Executing
+        );
+
+        // Once handed out, it isn't handed out again until released.
+        let late = approvals.approve(
+            &status.id,
+            &public_key(&operator(2)),
+            &sign(&operator(2), &status.id),
+        );
+        assert!(late.unwrap_err().contains("already being sent"));
+        approvals.release(&status.id).unwrap();
+        let retry = approvals
+            .approve(
+                &status.id,
+                &public_key(&operator(2)),
+                &sign(&operator(2), &status.id),
+            )
+            .unwrap();
+        assert_eq!(retry, ApprovalProgress::Ready(call()));
+
+        approvals.complete(&status.id).unwrap();
+        assert!(approvals.pending().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_rejects_strangers_and_forged_signatures() {
+        let approvals = Approvals::new(two_of_three(), Arc::new(StateStore::in_memory()));
+        let status = approvals.propose(call()).unwrap();
+
+        let stranger = approvals.approve(
+            &status.id,
+            &public_key(&operator(9)),
+            &sign(&operator(9), &status.id),
+        );
+        assert!(stranger.unwrap_err().contains("not an approval operator"));
+
+        let forged = approvals.approve(
+            &status.id,
+            &public_key(&operator(1)),
+            &sign(&operator(2), &status.id),
+        );
+        assert!(forged.unwrap_err().contains("Invalid signature"));
+        assert!(approvals.pending().unwrap()[0].approvals.is_empty());
+    }
+
+    #[test]
+    fn test_same_call_gets_a_fresh_id_each_time() {
+        let approvals = Approvals::new(two_of_three(), Arc::new(StateStore::in_memory()));
+        let first = approvals.propose(call()).unwrap();
+        let second = approvals.propose(call()).unwrap();
+        assert_ne!(first.id, second.id);
+        assert_eq!(approvals.pending().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_concurrent_approvals_release_the_call_once() {
+        let keys: Vec<String> = (1..=8).map(|i| public_key(&operator(i))).collect();
+        let policy = ApprovalPolicy::parse(&keys.join(","), Some("4")).unwrap();
+        let approvals = Arc::new(Approvals::new(policy, Arc::new(StateStore::in_memory())));
+        let id = approvals.propose(call()).unwrap().id;
+
+        let handles: Vec<_> = (1..=8)
+            .map(|i| {
+                let approvals = approvals.clone();
+                let id = id.clone();
+                std::thread::spawn(move || {
+                    approvals.approve(&id, &public_key(&operator(i)), &sign(&operator(i), &id))
+                })
+            })
+            .collect();
+        let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+        let ready = results
+            .iter()
+            .filter(|r| matches!(r, Ok(ApprovalProgress::Ready(_))))
+            .count();
+        assert_eq!(ready, 1);
+        // No signature is lost: every approval before the call went out counted.
+        let pending = &approvals.pending().unwrap()[0];
+        assert_eq!(pending.approvals.len(), 4);
+        assert_eq!(pending.state, ApprovalState::Executing);
+    }
+}
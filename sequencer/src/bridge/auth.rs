@@ -0,0 +1,336 @@
+use serde_json::json;
+use sha3::{Digest, Keccak256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use super::keys::{normalize_key, parse_operators, sign, verify};
+use super::protocol::{CallRequest, SetAuth};
+use crate::state::StateStore;
+
+const NONCE_PREFIX: &str = "auth/nonce/";
+
+/// Operators allowed to `set`, as secp256k1 public keys (SEC1 hex). Every
+/// `set` must carry a `SetAuth` from one of them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthPolicy {
+    pub operators: Vec<String>,
+    /// What each operator may call, keyed by its normalized key. `None`
+    /// lets every operator call anything; with a list, operators missing
+    /// from it may call nothing.
+    pub access: Option<HashMap<String, Vec<Grant>>>,
+}
+
+/// One entry in an operator's access list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Grant {
+    /// Every contract and function.
+    Any,
+    /// Every function of the contract.
+    Contract(String),
+    /// One function of the contract.
+    Function { contract: String, function: String },
+}
+
+impl Grant {
+    fn parse(grant: &str) -> Grant {
+        match grant.split_once('.') {
+            _ if grant == "*" => Grant::Any,
+            Some((contract, function)) => Grant::Function {
+                contract: contract.to_lowercase(),
+                function: function.to_string(),
+            },
+            None => Grant::Contract(grant.to_lowercase()),
+        }
+    }
+
+    fn allows(&self, contract: &str, function: &str) -> bool {
+        match self {
+            Grant::Any => true,
+            Grant::Contract(c) => c.eq_ignore_ascii_case(contract),
+            Grant::Function {
+                contract: c,
+                function: f,
+            } => c.eq_ignore_ascii_case(contract) && f == function,
+        }
+    }
+}
+
+impl AuthPolicy {
+    /// `operators` is comma-separated.
+    pub fn parse(operators: &str) -> Result<Self, String> {
+        let operators = parse_operators(operators)?;
+        if operators.is_empty() {
+            return Err("Auth needs at least one operator key".to_string());
+        }
+        Ok(AuthPolicy {
+            operators,
+            access: None,
+        })
+    }
+
+    /// Restricts operators to what `access` grants them. It is
+    /// comma-separated `<operator key>=<grants>`, the grants `|`-separated
+    /// and each `*`, `<contract>` or `<contract>.<function>`.
+    pub fn with_access(mut self, access: &str) -> Result<Self, String> {
+        let mut grants = HashMap::new();
+        for entry in access.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (operator, entry_grants) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("Access entry '{}' needs <operator>=<grants>", entry))?;
+            let operator = normalize_key(operator)?;
+            if !self.operators.contains(&operator) {
+                return Err(format!("{} has access but is not an operator", operator));
+            }
+            grants
+                .entry(operator)
+                .or_insert_with(Vec::new)
+                .extend(entry_grants.split('|').map(str::trim).map(Grant::parse));
+        }
+        self.access = Some(grants);
+        Ok(self)
+    }
+
+    /// Whether `operator` (normalized) may call `function` on `contract`.
+    pub fn may_call(&self, operator: &str, contract: &str, function: &str) -> bool {
+        let Some(access) = &self.access else {
+            return true;
+        };
+        access
+            .get(operator)
+            .is_some_and(|grants| grants.iter().any(|g| g.allows(contract, function)))
+    }
+}
+
+/// What a `SetAuth` signs: keccak256 of the compact JSON array
+/// `["set", <call without auth>, nonce, expiry]`.
+pub fn digest(call: &CallRequest, nonce: u64, expiry: u64) -> [u8; 32] {
+    let call = CallRequest {
+        auth: None,
+        ..call.clone()
+    };
+    let message = json!(["set", call, nonce, expiry]);
+    Keccak256::digest(serde_json::to_vec(&message).expect("call request serializes")).into()
+}
+
+/// Signs `call` with an operator's hex secret key.
+pub fn sign_set(
+    call: &CallRequest,
+    secret_key: &str,
+    nonce: u64,
+    expiry: u64,
+) -> Result<SetAuth, String> {
+    let (operator, signature) = sign(&digest(call, nonce, expiry), secret_key)?;
+    Ok(SetAuth {
+        operator,
+        nonce,
+        expiry,
+        signature,
+    })
+}
+
+/// Verifies signed `set`s. Each operator's nonces must strictly increase; the
+/// last one seen is kept in the `StateStore` so replays fail across restarts.
+pub struct Authenticator {
+    policy: AuthPolicy,
+    store: Arc<StateStore>,
+    // Serializes the nonce check-and-bump.
+    lock: Mutex<()>,
+}
+
+impl Authenticator {
+    pub fn new(policy: AuthPolicy, store: Arc<StateStore>) -> Self {
+        Authenticator {
+            policy,
+            store,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Accepts `call` at time `now` (unix seconds), consumes its nonce and
+    /// returns the operator who signed it. `target` is the contract and
+    /// function the call resolves to; the operator's access to it is checked
+    /// before the nonce is consumed, so a refused call doesn't burn it.
+    pub fn check(
+        &self,
+        call: &CallRequest,
+        target: Option<(&str, &str)>,
+        now: u64,
+    ) -> Result<String, String> {
+        let operator = self.authorize(call, target, now)?;
+        let nonce = call.auth.as_ref().map_or(0, |auth| auth.nonce);
+
+        let _guard = self.lock.lock().unwrap();
+        let key = format!("{}{}", NONCE_PREFIX, operator);
+        if let Some(last) = self.store.get::<u64>(&key)? {
+            if nonce <= last {
+                return Err(format!("Nonce {} already used (last was {})", nonce, last));
+            }
+        }
+        self.store.put(&key, &nonce)?;
+        Ok(operator)
+    }
+
+    /// What `check` verifies short of the nonce: the signature, its expiry
+    /// and the operator's access to `target`. Nothing is consumed.
+    pub fn authorize(
+        &self,
+        call: &CallRequest,
+        target: Option<(&str, &str)>,
+        now: u64,
+    ) -> Result<String, String> {
+        let auth = call
+            .auth
+            .as_ref()
+            .ok_or("This bridge only accepts signed `set` requests.")?;
+        let operator = normalize_key(&auth.operator)?;
+        if !self.policy.operators.contains(&operator) {
+            return Err(format!("{} is not an authorized operator", operator));
+        }
+        if auth.expiry < now {
+            return Err(format!("Signature expired at {}", auth.expiry));
+        }
+        verify(
+            &digest(call, auth.nonce, auth.expiry),
+            &operator,
+            &auth.signature,
+        )?;
+        if let Some((contract, function)) = target {
+            if !self.policy.may_call(&operator, contract, function) {
+                return Err(format!(
+                    "{} may not call {} on {}",
+                    operator, function, contract
+                ));
+            }
+        }
+        Ok(operator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::approvals::tests::{operator, public_key};
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn secret(seed: u8) -> String {
+        hex::encode(operator(seed).to_bytes())
+    }
+
+    fn authenticator() -> Authenticator {
+        let policy = AuthPolicy::parse(&public_key(&operator(1))).unwrap();
+        Authenticator::new(policy, Arc::new(StateStore::in_memory()))
+    }
+
+    fn signed(value: u64, seed: u8, nonce: u64, expiry: u64) -> CallRequest {
+        let mut call = CallRequest {
+            value: Some(json!(value)),
+            ..Default::default()
+        };
+        call.auth = Some(sign_set(&call, &secret(seed), nonce, expiry).unwrap());
+        call
+    }
+
+    #[test]
+    fn test_accepts_each_nonce_once() {
+        let auth = authenticator();
+        auth.check(&signed(214, 1, 1, NOW + 60), None, NOW).unwrap();
+
+        let replay = auth
+            .check(&signed(214, 1, 1, NOW + 60), None, NOW)
+            .unwrap_err();
+        assert!(replay.contains("already used"), "{}", replay);
+        let older = auth
+            .check(&signed(5, 1, 0, NOW + 60), None, NOW)
+            .unwrap_err();
+        assert!(older.contains("already used"), "{}", older);
+
+        auth.check(&signed(5, 1, 2, NOW + 60), None, NOW).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_bad_requests() {
+        let auth = authenticator();
+        let unsigned = CallRequest::default();
+        assert!(auth.check(&unsigned, None, NOW).is_err());
+
+        let stranger = auth
+            .check(&signed(214, 2, 1, NOW + 60), None, NOW)
+            .unwrap_err();
+        assert!(stranger.contains("not an authorized operator"));
+
+        let expired = auth
+            .check(&signed(214, 1, 1, NOW - 1), None, NOW)
+            .unwrap_err();
+        assert!(expired.contains("expired"));
+
+        let mut tampered = signed(214, 1, 1, NOW + 60);
+        tampered.value = Some(json!(215));
+        let tampered = auth.check(&tampered, None, NOW).unwrap_err();
+        assert!(tampered.contains("Invalid signature"));
+        // None of the failures consumed the nonce.
+        auth.check(&signed(214, 1, 1, NOW + 60), None, NOW).unwrap();
+
+        let policy = AuthPolicy::parse(&public_key(&operator(1)))
+            .unwrap()
+            .with_access(&format!("{}=0x0a.set_just_field", public_key(&operator(1))))
+            .unwrap();
+        let auth = Authenticator::new(policy, Arc::new(StateStore::in_memory()));
+        let denied = auth
+            .check(
+                &signed(214, 1, 1, NOW + 60),
+                Some(("0x0a", "set_field_in_map")),
+                NOW,
+            )
+            .unwrap_err();
+        assert!(denied.contains("may not call"));
+
+        // Nor does a call the operator may not make.
+        auth.check(
+            &signed(214, 1, 1, NOW + 60),
+            Some(("0x0a", "set_just_field")),
+            NOW,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_access_list_limits_operators() {
+        let (one, two) = (public_key(&operator(1)), public_key(&operator(2)));
+        let policy = AuthPolicy::parse(&format!("{},{}", one, two)).unwrap();
+        assert!(policy.may_call(&one, "0x0a", "anything"));
+
+        let access = format!("{}=0x0A.set_just_field|0x0b, {}=*", one, two);
+        let policy = policy.with_access(&access).unwrap();
+        let one = normalize_key(&one).unwrap();
+        let two = normalize_key(&two).unwrap();
+        assert!(policy.may_call(&one, "0x0a", "set_just_field"));
+        assert!(!policy.may_call(&one, "0x0a", "set_field_in_map"));
+        assert!(policy.may_call(&one, "0x0B", "set_field_in_map"));
+        assert!(policy.may_call(&two, "0x0c", "set_just_field"));
+
+        let only_two = AuthPolicy::parse(&two).unwrap();
+        let stranger = public_key(&operator(3));
+        assert!(only_two
+            .clone()
+            .with_access(&format!("{}=*", stranger))
+            .unwrap_err()
+            .contains("not an operator"));
+        let only_two = only_two.with_access(&format!("{}=0x0a", two)).unwrap();
+        assert!(!only_two.may_call(&normalize_key(&stranger).unwrap(), "0x0a", "f"));
+    }
+
+    #[test]
+    fn test_nonces_are_kept_in_the_store() {
+        let store = Arc::new(StateStore::in_memory());
+        let policy = AuthPolicy::parse(&public_key(&operator(1))).unwrap();
+        Authenticator::new(policy.clone(), store.clone())
+            .check(&signed(214, 1, 7, NOW + 60), None, NOW)
+            .unwrap();
+
+        let restarted = Authenticator::new(policy, store);
+        assert!(restarted
+            .check(&signed(214, 1, 7, NOW + 60), None, NOW)
+            .is_err());
+    }
+}
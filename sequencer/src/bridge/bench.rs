@@ -0,0 +1,401 @@
+use futures_util::{SinkExt, StreamExt};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::time::{sleep_until, timeout};
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+
+use super::protocol::{BridgeRequest, BridgeResponse, CallRequest};
+
+/// What `sequencer bench ws` sends: `requests` `get`s and `set`s, spread over
+/// `connections` WebSocket connections that each wait for an answer before
+/// sending again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchConfig {
+    pub url: String,
+    pub connections: usize,
+    pub requests: usize,
+    /// Share of the requests that are `set`s, in percent.
+    pub set_percent: u8,
+    /// Requests per second over all connections; `None` sends each as soon
+    /// as its connection is free.
+    pub rate: Option<f64>,
+    /// How long to wait for an answer before giving up on the connection.
+    pub timeout: Duration,
+    pub get: CallRequest,
+    pub set: CallRequest,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        let addr = env::var("BRIDGE_ADDR").unwrap_or_else(|_| "127.0.0.1:3002".to_string());
+        BenchConfig {
+            url: format!("ws://{}", addr),
+            connections: 10,
+            requests: 1_000,
+            set_percent: 0,
+            rate: None,
+            timeout: Duration::from_secs(30),
+            get: CallRequest::default(),
+            set: CallRequest::default(),
+        }
+    }
+}
+
+impl BenchConfig {
+    /// `[url] [--connections N] [--requests M] [--sets PERCENT] [--rate
+    /// PER_SECOND] [--timeout SECONDS] [--contract ADDRESS] [--get FUNCTION]
+    /// [--set FUNCTION] [--value JSON]`. The url defaults to the bridge at
+    /// `BRIDGE_ADDR`; requests without a contract or function go to the
+    /// bridge's defaults.
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut config = BenchConfig::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("--") {
+                config.url = arg.clone();
+                continue;
+            }
+            let value = args
+                .next()
+                .ok_or_else(|| format!("{} needs a value", arg))?;
+            let number = || {
+                value
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid {}: {}", arg, value))
+            };
+            match arg.as_str() {
+                "--connections" => config.connections = number()?,
+                "--requests" => config.requests = number()?,
+                "--sets" => {
+                    config.set_percent = value
+                        .parse()
+                        .ok()
+                        .filter(|p| *p <= 100)
+                        .ok_or_else(|| format!("Invalid --sets: {} (0 to 100)", value))?
+                }
+                "--rate" => {
+                    let rate: f64 = value
+                        .parse()
+                        .ok()
+                        .filter(|r: &f64| *r > 0.0 && r.is_finite())
+                        .ok_or_else(|| format!("Invalid --rate: {}", value))?;
+                    config.rate = Some(rate);
+                }
+                "--timeout" => config.timeout = Duration::from_secs(number()? as u64),
+                "--contract" => {
+                    config.get.contract = Some(value.clone());
+                    config.set.contract = Some(value.clone());
+                }
+                "--get" => config.get.function = Some(value.clone()),
+                "--set" => config.set.function = Some(value.clone()),
+                "--value" => {
+                    let value = serde_json::from_str(value)
+                        .unwrap_or_else(|_| Value::String(value.clone()));
+                    config.set.value = Some(value);
+                }
+                _ => return Err(format!("Unknown bench option {}", arg)),
+            }
+        }
+        if config.connections == 0 {
+            return Err("--connections must be at least 1".to_string());
+        }
+        Ok(config)
+    }
+
+    // Spreads the sets evenly: `set_percent` of every hundred requests.
+    fn is_set(&self, index: usize) -> bool {
+        let percent = self.set_percent as usize;
+        index * percent / 100 != (index + 1) * percent / 100
+    }
+
+    /// Runs the load and reports what came back. Connections that fail or
+    /// time out are dropped, and their other requests go to the rest.
+    pub async fn run(self) -> BenchReport {
+        let config = Arc::new(self);
+        let next = Arc::new(AtomicUsize::new(0));
+        let start = Instant::now();
+        let workers: Vec<_> = (0..config.connections)
+            .map(|_| tokio::spawn(worker(config.clone(), next.clone(), start)))
+            .collect();
+        let mut report = BenchReport::default();
+        for worker in workers {
+            let outcomes = worker.await.unwrap_or_else(|e| {
+                vec![Outcome {
+                    action: Action::Get,
+                    result: Err(format!("panicked: {}", e)),
+                }]
+            });
+            for outcome in outcomes {
+                report.record(outcome);
+            }
+        }
+        report.elapsed = start.elapsed();
+        for stats in [&mut report.get, &mut report.set] {
+            stats.latencies.sort();
+        }
+        report
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Get,
+    Set,
+}
+
+// One request's latency, or what went wrong: the response's error code, or
+// `timeout`/`connection`/`connect` when no response came.
+struct Outcome {
+    action: Action,
+    result: Result<Duration, String>,
+}
+
+async fn worker(config: Arc<BenchConfig>, next: Arc<AtomicUsize>, start: Instant) -> Vec<Outcome> {
+    let mut outcomes = vec![];
+    let mut socket = match connect_async(&config.url).await {
+        Ok((socket, _)) => socket,
+        Err(_) => {
+            // Counted once against the request it would have sent first.
+            if next.fetch_add(1, Ordering::SeqCst) < config.requests {
+                outcomes.push(Outcome {
+                    action: Action::Get,
+                    result: Err("connect".to_string()),
+                });
+            }
+            return outcomes;
+        }
+    };
+    loop {
+        let index = next.fetch_add(1, Ordering::SeqCst);
+        if index >= config.requests {
+            break;
+        }
+        if let Some(rate) = config.rate {
+            let due = start + Duration::from_secs_f64(index as f64 / rate);
+            sleep_until(due.into()).await;
+        }
+        let (action, request) = if config.is_set(index) {
+            (Action::Set, BridgeRequest::Set(config.set.clone()))
+        } else {
+            (Action::Get, BridgeRequest::Get(config.get.clone()))
+        };
+        let text = serde_json::to_string(&request).expect("requests serialize");
+        let sent = Instant::now();
+        if socket.send(Message::Text(text)).await.is_err() {
+            outcomes.push(Outcome {
+                action,
+                result: Err("connection".to_string()),
+            });
+            break;
+        }
+        let result = loop {
+            let remaining = config.timeout.saturating_sub(sent.elapsed());
+            match timeout(remaining, socket.next()).await {
+                Err(_) => break Err("timeout".to_string()),
+                Ok(None) | Ok(Some(Err(_))) => break Err("connection".to_string()),
+                Ok(Some(Ok(Message::Text(text)))) => {
+                    let Ok(response) = serde_json::from_str::<BridgeResponse>(&text) else {
+                        break Err("malformed".to_string());
+                    };
+                    // Subscription pushes answer no request.
+                    if response.event.is_some() {
+                        continue;
+                    }
+                    if response.success {
+                        break Ok(sent.elapsed());
+                    }
+                    let code = response
+                        .code
+                        .and_then(|code| serde_json::to_value(code).ok())
+                        .and_then(|code| code.as_str().map(str::to_string));
+                    break Err(code.unwrap_or_else(|| "failed".to_string()));
+                }
+                Ok(Some(Ok(_))) => continue,
+            }
+        };
+        let lost = matches!(&result, Err(e) if e == "timeout" || e == "connection");
+        outcomes.push(Outcome { action, result });
+        if lost {
+            break;
+        }
+    }
+    outcomes
+}
+
+/// One action's answered requests and their latencies, fastest first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ActionStats {
+    pub requests: usize,
+    pub errors: usize,
+    pub latencies: Vec<Duration>,
+}
+
+impl ActionStats {
+    /// The nearest-rank `p`th percentile of the successful requests.
+    pub fn percentile(&self, p: usize) -> Option<Duration> {
+        if self.latencies.is_empty() {
+            return None;
+        }
+        let rank = (self.latencies.len() * p).div_ceil(100).max(1);
+        Some(self.latencies[rank - 1])
+    }
+
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            return 0.0;
+        }
+        self.errors as f64 / self.requests as f64
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BenchReport {
+    pub elapsed: Duration,
+    pub get: ActionStats,
+    pub set: ActionStats,
+    /// Failed requests by error code, or by `timeout`, `connection` or
+    /// `connect` when the bridge never answered.
+    pub errors: BTreeMap<String, usize>,
+}
+
+impl BenchReport {
+    fn record(&mut self, outcome: Outcome) {
+        let stats = match outcome.action {
+            Action::Get => &mut self.get,
+            Action::Set => &mut self.set,
+        };
+        stats.requests += 1;
+        match outcome.result {
+            Ok(latency) => stats.latencies.push(latency),
+            Err(error) => {
+                stats.errors += 1;
+                *self.errors.entry(error).or_default() += 1;
+            }
+        }
+    }
+
+    pub fn requests(&self) -> usize {
+        self.get.requests + self.set.requests
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |d: Option<Duration>| d.map_or("-".to_string(), |d| d.as_millis().to_string());
+        writeln!(
+            f,
+            "{} requests in {:.1}s ({:.1}/s)",
+            self.requests(),
+            self.elapsed.as_secs_f64(),
+            self.requests() as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+        )?;
+        writeln!(
+            f,
+            "action\trequests\terrors\terror %\tp50 ms\tp90 ms\tp99 ms\tmax ms"
+        )?;
+        for (name, stats) in [("get", &self.get), ("set", &self.set)] {
+            if stats.requests == 0 {
+                continue;
+            }
+            writeln!(
+                f,
+                "{}\t{}\t{}\t{:.1}\t{}\t{}\t{}\t{}",
+                name,
+                stats.requests,
+                stats.errors,
+                stats.error_rate() * 100.0,
+                ms(stats.percentile(50)),
+                ms(stats.percentile(90)),
+                ms(stats.percentile(99)),
+                ms(stats.latencies.last().copied())
+            )?;
+        }
+        for (error, count) in &self.errors {
+            writeln!(f, "  {}: {}", error, count)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::protocol::ErrorCode;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::accept_async;
+
+    // Answers `get`s, refuses `set`s, and pushes an event before each answer.
+    async fn fake_bridge() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut socket = accept_async(stream).await.unwrap();
+                    while let Some(Ok(Message::Text(text))) = socket.next().await {
+                        let request: BridgeRequest = serde_json::from_str(&text).unwrap();
+                        let push = serde_json::json!({ "success": true, "event": {
+                            "type": "valueChanged",
+                            "target": { "kind": "publicStorage", "contract": "0x12", "slot": "0x2" },
+                            "block": 1, "previous": null, "current": null,
+                        }});
+                        socket.send(Message::Text(push.to_string())).await.unwrap();
+                        let response = match request {
+                            BridgeRequest::Get(_) => BridgeResponse::value(Value::from(7), false),
+                            _ => BridgeResponse::failed(ErrorCode::Unauthorized, "no"),
+                        };
+                        let text = serde_json::to_string(&response).unwrap();
+                        socket.send(Message::Text(text)).await.unwrap();
+                    }
+                });
+            }
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_reports_latencies_and_errors_per_action() {
+        let url = fake_bridge().await;
+        let args: Vec<String> = [
+            &url,
+            "--connections",
+            "4",
+            "--requests",
+            "40",
+            "--sets",
+            "25",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let report = BenchConfig::parse(&args).unwrap().run().await;
+
+        assert_eq!(report.requests(), 40);
+        assert_eq!((report.get.requests, report.get.errors), (30, 0));
+        assert_eq!(report.get.latencies.len(), 30);
+        assert!(report.get.percentile(50) <= report.get.percentile(99));
+        assert_eq!((report.set.requests, report.set.errors), (10, 10));
+        assert_eq!(report.set.error_rate(), 1.0);
+        assert_eq!(
+            report.errors,
+            BTreeMap::from([("unauthorized".to_string(), 10)])
+        );
+        assert!(report.to_string().contains("get\t30\t0\t0.0\t"));
+
+        // Nothing listens there.
+        let mut config = BenchConfig::parse(&["ws://127.0.0.1:1".to_string()]).unwrap();
+        config.requests = 5;
+        config.connections = 2;
+        let report = config.run().await;
+        assert_eq!(report.errors, BTreeMap::from([("connect".to_string(), 2)]));
+
+        assert!(BenchConfig::parse(&["--sets".to_string(), "101".to_string()]).is_err());
+        assert!(BenchConfig::parse(&["--connections".to_string(), "0".to_string()]).is_err());
+    }
+}
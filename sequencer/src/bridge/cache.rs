@@ -0,0 +1,135 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub contract: String,
+    pub function: String,
+    pub args: String,
+}
+
+impl CacheKey {
+    pub fn new(contract: &str, function: &str, args: &[Value]) -> Self {
+        CacheKey {
+            contract: contract.to_lowercase(),
+            function: function.to_string(),
+            args: serde_json::to_string(args).unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    value: Value,
+    fetched_at: Instant,
+    invalidated: bool,
+    refreshing: bool,
+}
+
+/// Last confirmed result of every `get` the bridge has answered. Entries are
+/// served immediately and refreshed in the background; an entry is stale when
+/// it is older than the TTL or a `set` hit the same contract since it was read.
+#[derive(Debug)]
+pub struct ValueCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<CacheKey, CacheEntry>>,
+}
+
+impl ValueCache {
+    pub fn new(ttl: Duration) -> Self {
+        ValueCache {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the cached value and whether it is stale.
+    pub fn get(&self, key: &CacheKey) -> Option<(Value, bool)> {
+        let entries = self.entries.lock().expect("cache lock poisoned");
+        entries.get(key).map(|entry| {
+            let stale = entry.invalidated || entry.fetched_at.elapsed() > self.ttl;
+            (entry.value.clone(), stale)
+        })
+    }
+
+    pub fn store(&self, key: CacheKey, value: Value) {
+        self.entries.lock().expect("cache lock poisoned").insert(
+            key,
+            CacheEntry {
+                value,
+                fetched_at: Instant::now(),
+                invalidated: false,
+                refreshing: false,
+            },
+        );
+    }
+
+    /// Marks a background refresh as started; `false` if one is already running.
+    pub fn begin_refresh(&self, key: &CacheKey) -> bool {
+        let mut entries = self.entries.lock().expect("cache lock poisoned");
+        match entries.get_mut(key) {
+            Some(entry) if !entry.refreshing => {
+                entry.refreshing = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn end_refresh(&self, key: &CacheKey) {
+        if let Some(entry) = self
+            .entries
+            .lock()
+            .expect("cache lock poisoned")
+            .get_mut(key)
+        {
+            entry.refreshing = false;
+        }
+    }
+
+    pub fn invalidate_contract(&self, contract: &str) {
+        let contract = contract.to_lowercase();
+        for (key, entry) in self.entries.lock().expect("cache lock poisoned").iter_mut() {
+            if key.contract == contract {
+                entry.invalidated = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_entries_go_stale_after_ttl_or_invalidation() {
+        let cache = ValueCache::new(Duration::from_secs(60));
+        let key = CacheKey::new("0xAB", "get_just_field", &[]);
+        cache.store(key.clone(), json!(1));
+        assert_eq!(cache.get(&key), Some((json!(1), false)));
+
+        cache.invalidate_contract("0xab");
+        assert_eq!(cache.get(&key), Some((json!(1), true)));
+
+        let expired = ValueCache::new(Duration::ZERO);
+        expired.store(key.clone(), json!(2));
+        std::thread::sleep(Duration::from_millis(1));
+        assert_eq!(expired.get(&key), Some((json!(2), true)));
+    }
+
+    #[test]
+    fn test_only_one_refresh_at_a_time() {
+        let cache = ValueCache::new(Duration::from_secs(60));
+        let key = CacheKey::new("0x01", "get_just_field", &[json!(1)]);
+        assert!(!cache.begin_refresh(&key));
+
+        cache.store(key.clone(), json!(1));
+        assert!(cache.begin_refresh(&key));
+        assert!(!cache.begin_refresh(&key));
+        cache.end_refresh(&key);
+        assert!(cache.begin_refresh(&key));
+    }
+}
@@ -0,0 +1,485 @@
+use prost_types::value::Kind;
+use serde_json::{json, Map, Value};
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::{Request, Response, Status};
+
+use super::protocol::{
+    BridgeRequest, BridgeResponse, CallRequest, ErrorCode, ReceiptRequest, SetAuth, Subscribe,
+};
+use super::server::{Bridge, Subscriptions};
+use crate::fields::Fr;
+use crate::watcher::{self, WatchTarget};
+
+/// Types and stubs generated from `proto/bridge.proto`.
+pub mod pb {
+    tonic::include_proto!("sequencer.bridge.v1");
+}
+
+use pb::bridge_server::{Bridge as BridgeService, BridgeServer};
+
+/// The gRPC service; every RPC is translated into a `BridgeRequest` and run
+/// through `Bridge::handle`, like WebSocket and REST requests. A `traceparent`
+/// in the metadata continues the caller's trace.
+pub struct GrpcBridge {
+    bridge: Arc<Bridge>,
+}
+
+impl GrpcBridge {
+    pub fn new(bridge: Arc<Bridge>) -> Self {
+        GrpcBridge { bridge }
+    }
+
+    async fn handle(
+        &self,
+        request: BridgeRequest,
+        traceparent: Option<String>,
+    ) -> Result<BridgeResponse, Status> {
+        self.handle_for(request, None, traceparent).await
+    }
+
+    async fn handle_for(
+        &self,
+        request: BridgeRequest,
+        subscriptions: Option<&mut Subscriptions>,
+        traceparent: Option<String>,
+    ) -> Result<BridgeResponse, Status> {
+        let response = self
+            .bridge
+            .handle_for(request, subscriptions, traceparent.as_deref())
+            .await;
+        if response.success {
+            return Ok(response);
+        }
+        let message = response.error.unwrap_or_default();
+        Err(match response.code {
+            Some(ErrorCode::NotFound) => Status::not_found(message),
+            Some(ErrorCode::Upstream | ErrorCode::PxeUnavailable) => Status::unavailable(message),
+            Some(ErrorCode::TxReverted) => Status::failed_precondition(message),
+            Some(ErrorCode::Unauthorized) => Status::unauthenticated(message),
+            Some(ErrorCode::PayloadTooLarge) => Status::resource_exhausted(message),
+            Some(ErrorCode::InvalidRequest | ErrorCode::Encoding) | None => {
+                Status::invalid_argument(message)
+            }
+        })
+    }
+}
+
+pub async fn serve(
+    listener: TcpListener,
+    bridge: Arc<Bridge>,
+) -> Result<(), tonic::transport::Error> {
+    let max_message = bridge.config().limits.max_frame_bytes;
+    let service = BridgeServer::new(GrpcBridge::new(bridge)).max_decoding_message_size(max_message);
+    tonic::transport::Server::builder()
+        .add_service(service)
+        .serve_with_incoming(TcpListenerStream::new(listener))
+        .await
+}
+
+#[tonic::async_trait]
+impl BridgeService for GrpcBridge {
+    async fn call(
+        &self,
+        request: Request<pb::CallRequest>,
+    ) -> Result<Response<pb::CallReply>, Status> {
+        let traceparent = traceparent(&request);
+        let request = request.into_inner();
+        let call = CallRequest {
+            contract: non_empty(request.contract),
+            function: non_empty(request.function),
+            args: Some(request.args.into_iter().map(to_json).collect()),
+            auth: request.auth.map(|auth| SetAuth {
+                operator: auth.operator,
+                nonce: auth.nonce,
+                expiry: auth.expiry,
+                signature: auth.signature,
+            }),
+            ..Default::default()
+        };
+
+        let response = self.handle(BridgeRequest::Set(call), traceparent).await?;
+        let outcome = match (response.tx_hash, response.approval) {
+            (Some(tx_hash), _) => pb::call_reply::Outcome::TxHash(tx_hash),
+            (None, _) if response.dry_run.is_some() => {
+                let effects = serde_json::to_value(response.dry_run).expect("effects serialize");
+                pb::call_reply::Outcome::DryRun(from_json(effects))
+            }
+            (None, Some(status)) => pb::call_reply::Outcome::Approval(pb::ApprovalStatus {
+                id: status.id,
+                approvals: status.approvals as u32,
+                threshold: status.threshold as u32,
+            }),
+            (None, None) => return Err(Status::internal("Bridge returned no tx hash")),
+        };
+        Ok(Response::new(pb::CallReply {
+            outcome: Some(outcome),
+        }))
+    }
+
+    async fn simulate(
+        &self,
+        request: Request<pb::SimulateRequest>,
+    ) -> Result<Response<pb::SimulateReply>, Status> {
+        let traceparent = traceparent(&request);
+        let request = request.into_inner();
+        let call = CallRequest {
+            contract: non_empty(request.contract),
+            function: non_empty(request.function),
+            args: Some(request.args.into_iter().map(to_json).collect()),
+            force_refresh: request.force_refresh,
+            ..Default::default()
+        };
+
+        let response = self.handle(BridgeRequest::Get(call), traceparent).await?;
+        Ok(Response::new(pb::SimulateReply {
+            value: response.value.map(from_json),
+            stale: response.stale.unwrap_or(false),
+        }))
+    }
+
+    async fn get_receipt(
+        &self,
+        request: Request<pb::GetReceiptRequest>,
+    ) -> Result<Response<pb::TxReceipt>, Status> {
+        let traceparent = traceparent(&request);
+        let tx_hash = request.into_inner().tx_hash;
+        let response = self
+            .handle(
+                BridgeRequest::Receipt(ReceiptRequest { tx_hash }),
+                traceparent,
+            )
+            .await?;
+
+        let receipt = response.value.unwrap_or_default();
+        let text = |field: &str| receipt[field].as_str().unwrap_or_default().to_string();
+        Ok(Response::new(pb::TxReceipt {
+            tx_hash: text("txHash"),
+            status: text("status"),
+            error: text("error"),
+            block_number: receipt["blockNumber"].as_u64(),
+            block_hash: text("blockHash"),
+        }))
+    }
+
+    type SubscribeBlocksStream = ReceiverStream<Result<pb::BlockUpdate, Status>>;
+
+    async fn subscribe_blocks(
+        &self,
+        request: Request<pb::SubscribeBlocksRequest>,
+    ) -> Result<Response<Self::SubscribeBlocksStream>, Status> {
+        let targets = request
+            .into_inner()
+            .targets
+            .into_iter()
+            .map(WatchTarget::try_from)
+            .collect::<Result<HashSet<_>, _>>()
+            .map_err(Status::invalid_argument)?;
+        let mut subscriptions = Subscriptions::default();
+        for target in targets {
+            let subscribe = BridgeRequest::Subscribe(Subscribe::Target { target });
+            if let Err(status) = self
+                .handle_for(subscribe, Some(&mut subscriptions), None)
+                .await
+            {
+                subscriptions.release(self.bridge.watcher());
+                return Err(status);
+            }
+        }
+
+        let bridge = self.bridge.clone();
+        let mut blocks = bridge.watcher().subscribe_blocks();
+        let (sender, receiver) = mpsc::channel(16);
+        tokio::spawn(async move {
+            let targets = subscriptions.targets();
+            loop {
+                let update = match blocks.recv().await {
+                    Ok(block) => Ok(pb::BlockUpdate {
+                        number: block.number,
+                        changes: block
+                            .changes
+                            .into_iter()
+                            .filter(|change| targets.contains(&change.target))
+                            .map(pb::ValueChange::from)
+                            .collect(),
+                    }),
+                    Err(RecvError::Lagged(missed)) => Err(Status::data_loss(format!(
+                        "Subscriber fell behind and missed {} blocks",
+                        missed
+                    ))),
+                    Err(RecvError::Closed) => break,
+                };
+                // The client hung up.
+                if sender.send(update).await.is_err() {
+                    break;
+                }
+            }
+            subscriptions.release(bridge.watcher());
+        });
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+}
+
+fn traceparent<T>(request: &Request<T>) -> Option<String> {
+    request
+        .metadata()
+        .get("traceparent")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+fn non_empty(field: String) -> Option<String> {
+    Some(field).filter(|field| !field.is_empty())
+}
+
+impl TryFrom<pb::WatchTarget> for WatchTarget {
+    type Error = String;
+
+    fn try_from(target: pb::WatchTarget) -> Result<Self, String> {
+        match target.kind {
+            Some(pb::watch_target::Kind::PublicStorage(storage)) => {
+                Ok(WatchTarget::PublicStorage {
+                    contract: storage.contract,
+                    slot: Fr::try_from(storage.slot.as_str())?,
+                })
+            }
+            Some(pb::watch_target::Kind::Notes(notes)) => Ok(WatchTarget::Notes {
+                contract: notes.contract,
+                storage_slot: notes
+                    .storage_slot
+                    .as_deref()
+                    .map(Fr::try_from)
+                    .transpose()?,
+                scopes: notes.scopes,
+            }),
+            None => Err("Watch target has no kind".to_string()),
+        }
+    }
+}
+
+impl From<WatchTarget> for pb::WatchTarget {
+    fn from(target: WatchTarget) -> Self {
+        let kind = match target {
+            WatchTarget::PublicStorage { contract, slot } => {
+                pb::watch_target::Kind::PublicStorage(pb::PublicStorage {
+                    contract,
+                    slot: slot.to_hex(),
+                })
+            }
+            WatchTarget::Notes {
+                contract,
+                storage_slot,
+                scopes,
+            } => pb::watch_target::Kind::Notes(pb::Notes {
+                contract,
+                storage_slot: storage_slot.map(|slot| slot.to_hex()),
+                scopes,
+            }),
+        };
+        pb::WatchTarget { kind: Some(kind) }
+    }
+}
+
+impl From<watcher::ValueChange> for pb::ValueChange {
+    fn from(change: watcher::ValueChange) -> Self {
+        pb::ValueChange {
+            target: Some(change.target.into()),
+            previous: Some(from_json(change.previous)),
+            current: Some(from_json(change.current)),
+        }
+    }
+}
+
+/// `google.protobuf.Value` numbers are doubles, so whole numbers come back as
+/// integers; anything past 2^53 should be sent as a string.
+fn to_json(value: prost_types::Value) -> Value {
+    match value.kind {
+        None | Some(Kind::NullValue(_)) => Value::Null,
+        Some(Kind::NumberValue(n)) if n.fract() == 0.0 && n.abs() < (1u64 << 53) as f64 => {
+            json!(n as i64)
+        }
+        Some(Kind::NumberValue(n)) => json!(n),
+        Some(Kind::StringValue(s)) => Value::String(s),
+        Some(Kind::BoolValue(b)) => Value::Bool(b),
+        Some(Kind::StructValue(s)) => Value::Object(
+            s.fields
+                .into_iter()
+                .map(|(key, value)| (key, to_json(value)))
+                .collect::<Map<_, _>>(),
+        ),
+        Some(Kind::ListValue(list)) => Value::Array(list.values.into_iter().map(to_json).collect()),
+    }
+}
+
+fn from_json(value: Value) -> prost_types::Value {
+    let kind = match value {
+        Value::Null => Kind::NullValue(0),
+        Value::Bool(b) => Kind::BoolValue(b),
+        Value::Number(n) => Kind::NumberValue(n.as_f64().unwrap_or_default()),
+        Value::String(s) => Kind::StringValue(s),
+        Value::Array(values) => Kind::ListValue(prost_types::ListValue {
+            values: values.into_iter().map(from_json).collect(),
+        }),
+        Value::Object(fields) => Kind::StructValue(prost_types::Struct {
+            fields: fields
+                .into_iter()
+                .map(|(key, value)| (key, from_json(value)))
+                .collect(),
+        }),
+    };
+    prost_types::Value { kind: Some(kind) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::server::tests::{bridge_with_mock, CONTRACT};
+    use crate::testing::MockPxe;
+    use pb::bridge_client::BridgeClient;
+    use std::time::Duration;
+    use tokio::time::timeout;
+    use tonic::transport::Channel;
+    use tonic::Code;
+
+    async fn start() -> (BridgeClient<Channel>, Arc<Bridge>, MockPxe) {
+        let (bridge, mock) = bridge_with_mock(|_| {}).await;
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, bridge.clone()));
+        (BridgeClient::connect(url).await.unwrap(), bridge, mock)
+    }
+
+    #[tokio::test]
+    async fn test_call_and_simulate() {
+        let (mut client, _bridge, mock) = start().await;
+        mock.respond("pxe_simulateTx", json!({ "privateExecutionResult": {} }));
+        mock.respond("pxe_simulateTx", json!({ "v": 1 }));
+
+        let sent = client
+            .call(pb::CallRequest {
+                contract: CONTRACT.to_string(),
+                function: String::new(),
+                args: vec![from_json(json!(214))],
+                auth: None,
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(
+            sent.outcome,
+            Some(pb::call_reply::Outcome::TxHash("0xfeed".to_string()))
+        );
+
+        let simulated = client
+            .simulate(pb::SimulateRequest {
+                contract: CONTRACT.to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(simulated.value.map(to_json), Some(json!({ "v": 1 })));
+        assert!(!simulated.stale);
+    }
+
+    #[tokio::test]
+    async fn test_errors_map_to_status_codes() {
+        let (mut client, _bridge, _mock) = start().await;
+
+        let unknown = client
+            .simulate(pb::SimulateRequest {
+                contract: "0xdead".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(unknown.code(), Code::NotFound);
+
+        let no_contract = client.call(pb::CallRequest::default()).await.unwrap_err();
+        assert_eq!(no_contract.code(), Code::InvalidArgument);
+
+        // Nothing queued on the mock, so the PXE call fails.
+        let receipt = client
+            .get_receipt(pb::GetReceiptRequest {
+                tx_hash: "0x01".to_string(),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(receipt.code(), Code::Unavailable);
+    }
+
+    #[tokio::test]
+    async fn test_receipt_fields() {
+        let (mut client, _bridge, mock) = start().await;
+        mock.respond(
+            "pxe_getTxReceipt",
+            json!({ "txHash": "0x01", "status": "success", "error": "", "blockNumber": 7 }),
+        );
+
+        let receipt = client
+            .get_receipt(pb::GetReceiptRequest {
+                tx_hash: "0x01".to_string(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(receipt.status, "success");
+        assert_eq!(receipt.block_number, Some(7));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_blocks_streams_changes_for_requested_targets() {
+        let (mut client, bridge, mock) = start().await;
+        mock.respond("pxe_getBlockNumber", json!(1));
+        mock.respond("pxe_getBlockNumber", json!(2));
+        mock.respond("pxe_getPublicStorageAt", json!("0x2bc"));
+        mock.respond("pxe_getPublicStorageAt", json!("0xd6"));
+
+        let target = WatchTarget::PublicStorage {
+            contract: CONTRACT.to_string(),
+            slot: Fr::from(2u8),
+        };
+        let mut stream = client
+            .subscribe_blocks(pb::SubscribeBlocksRequest {
+                targets: vec![target.clone().into()],
+            })
+            .await
+            .unwrap()
+            .into_inner();
+
+        bridge.watcher().poll().await.unwrap();
+        bridge.watcher().poll().await.unwrap();
+
+        let first = timeout(Duration::from_secs(2), stream.message())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!((first.number, first.changes.len()), (1, 0));
+
+        let second = timeout(Duration::from_secs(2), stream.message())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(second.number, 2);
+        assert_eq!(
+            second.changes,
+            vec![pb::ValueChange {
+                target: Some(target.into()),
+                previous: Some(from_json(json!(Fr::from(700u16)))),
+                current: Some(from_json(json!(Fr::from(214u8)))),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_json_values_round_trip() {
+        let value = json!({ "feed_id": "7", "price": 1000, "flags": [true, null, 1.5] });
+        assert_eq!(to_json(from_json(value.clone())), value);
+    }
+}
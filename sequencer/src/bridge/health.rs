@@ -0,0 +1,140 @@
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::Serialize;
+use serde_json::json;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+use super::server::Bridge;
+
+/// The outcome of one readiness check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Check {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Check {
+    pub fn ok() -> Self {
+        Check {
+            ok: true,
+            error: None,
+        }
+    }
+
+    pub fn failed(error: impl Into<String>) -> Self {
+        Check {
+            ok: false,
+            error: Some(error.into()),
+        }
+    }
+}
+
+/// What `Bridge::readiness` found; the bridge is ready when every check is.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Readiness {
+    pub ready: bool,
+    pub pxe: Check,
+    pub state_store: Check,
+    pub artifacts: Check,
+}
+
+impl Readiness {
+    pub fn new(pxe: Check, state_store: Check, artifacts: Check) -> Self {
+        Readiness {
+            ready: pxe.ok && state_store.ok && artifacts.ok,
+            pxe,
+            state_store,
+            artifacts,
+        }
+    }
+}
+
+/// `GET /healthz` answers 200 while the process serves at all; `GET
+/// /readyz` answers 200 when `Bridge::readiness` passes and 503 otherwise,
+/// with the checks in the body either way. `GET /metrics` serves the call
+/// timings (when they are kept) and the WebSocket send queues' drop counts
+/// in the Prometheus text format.
+pub fn routes() -> Router<Arc<Bridge>> {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/metrics", get(metrics))
+}
+
+/// Serves only the probes, apart from the REST API.
+pub async fn serve(listener: TcpListener, bridge: Arc<Bridge>) -> std::io::Result<()> {
+    axum::serve(listener, routes().with_state(bridge)).await
+}
+
+async fn healthz() -> Response {
+    Json(json!({ "status": "ok" })).into_response()
+}
+
+async fn readyz(State(bridge): State<Arc<Bridge>>) -> Response {
+    let readiness = bridge.readiness().await;
+    let status = if readiness.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(readiness)).into_response()
+}
+
+async fn metrics(State(bridge): State<Arc<Bridge>>) -> Response {
+    let mut text = match bridge.call_timings().map(|timings| timings.prometheus()) {
+        Some(Ok(text)) => text,
+        Some(Err(e)) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+        None => String::new(),
+    };
+    text.push_str(&bridge.outbound_stats().prometheus());
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], text).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::server::tests::bridge_with_mock;
+    use serde_json::Value;
+
+    #[tokio::test]
+    async fn test_readyz_reports_each_check() {
+        let (bridge, mock) = bridge_with_mock(|_| {}).await;
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, bridge.clone()));
+
+        let health = reqwest::get(format!("{}/healthz", url)).await.unwrap();
+        assert_eq!(health.status().as_u16(), 200);
+        let metrics = reqwest::get(format!("{}/metrics", url)).await.unwrap();
+        assert_eq!(metrics.status().as_u16(), 200);
+        let metrics = metrics.text().await.unwrap();
+        assert!(metrics.starts_with("# HELP bridge_dropped_notifications_total"));
+        assert!(metrics.contains("\nbridge_dropped_notifications_total 0\n"));
+
+        // Nothing queued on the mock, so the PXE call fails, and the
+        // artifact directory hasn't been scanned.
+        let response = reqwest::get(format!("{}/readyz", url)).await.unwrap();
+        assert_eq!(response.status().as_u16(), 503);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["ready"], false);
+        assert_eq!(body["pxe"]["ok"], false);
+        assert_eq!(body["stateStore"], json!({ "ok": true }));
+        assert!(body["artifacts"]["error"]
+            .as_str()
+            .unwrap()
+            .contains("not read yet"));
+
+        bridge.registry().reload().unwrap();
+        mock.respond("pxe_getBlockNumber", json!(7));
+        let response = reqwest::get(format!("{}/readyz", url)).await.unwrap();
+        assert_eq!(response.status().as_u16(), 200);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["ready"], true);
+    }
+}
@@ -0,0 +1,288 @@
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::protocol::{BridgeResponse, CallRequest, ErrorCode};
+use crate::state::StateStore;
+
+const KEY_PREFIX: &str = "idempotency/";
+const MAX_KEY_LEN: usize = 128;
+/// How long a claim whose request never finished (say, the bridge died
+/// mid-send) holds its key before a retry may take it over. `hold` renews
+/// it while the request is in flight.
+const CLAIM_LEASE: Duration = Duration::from_secs(300);
+/// Expired keys are swept at most this often; in between, `claim` ignores
+/// them where it finds them.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A claimed key: the request it was claimed for and, once that finished
+/// successfully, what the bridge answered.
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    request: CallRequest,
+    /// When the key was claimed, or the claim last renewed.
+    claimed_at: u64,
+    #[serde(default)]
+    response: Option<BridgeResponse>,
+}
+
+/// What to do with a `set` carrying an idempotency key.
+#[derive(Debug, PartialEq)]
+pub enum Claim {
+    /// First time the key is seen: handle the request, then `complete` it.
+    New,
+    /// The request was already handled; answer with this again.
+    Replay(Box<BridgeResponse>),
+}
+
+/// Remembers which `set`s were already handled, by the client's
+/// `idempotency_key`, so a retry after a timeout returns the original tx
+/// instead of sending a second one. Keys live in the `StateStore` for `ttl`.
+pub struct IdempotencyKeys {
+    store: Arc<StateStore>,
+    ttl: Duration,
+    // Serializes claims so two retries can't both see a fresh key, and holds
+    // when expired keys were last pruned.
+    lock: Mutex<u64>,
+}
+
+impl IdempotencyKeys {
+    pub fn new(store: Arc<StateStore>, ttl: Duration) -> Self {
+        IdempotencyKeys {
+            store,
+            ttl,
+            lock: Mutex::new(0),
+        }
+    }
+
+    /// Claims `key` for `call` at `now` (unix seconds). A replay must be the
+    /// same request, signature included; one arriving while the first is
+    /// still being handled is refused as retryable, until the first's claim
+    /// lease runs out.
+    pub fn claim(
+        &self,
+        key: &str,
+        call: &CallRequest,
+        now: u64,
+    ) -> Result<Claim, (ErrorCode, String)> {
+        if key.is_empty() || key.len() > MAX_KEY_LEN {
+            return Err((
+                ErrorCode::InvalidRequest,
+                format!("Idempotency keys must be 1 to {} bytes", MAX_KEY_LEN),
+            ));
+        }
+        let internal = |e: String| (ErrorCode::Upstream, e);
+        let mut pruned_at = self.lock.lock().unwrap();
+        if *pruned_at + PRUNE_INTERVAL.as_secs() <= now {
+            self.prune(now).map_err(internal)?;
+            *pruned_at = now;
+        }
+
+        let store_key = format!("{}{}", KEY_PREFIX, key);
+        let entry = self.store.get::<Entry>(&store_key).map_err(internal)?;
+        if let Some(entry) = entry.filter(|entry| !self.expired(entry, now)) {
+            if &entry.request != call {
+                return Err((
+                    ErrorCode::InvalidRequest,
+                    format!("Idempotency key {} was used for a different request", key),
+                ));
+            }
+            return match entry.response {
+                Some(response) => Ok(Claim::Replay(Box::new(response))),
+                None if entry.claimed_at + CLAIM_LEASE.as_secs() <= now => {
+                    self.claim_for(&store_key, call, now).map_err(internal)
+                }
+                None => Err((
+                    ErrorCode::Upstream,
+                    format!(
+                        "The request with idempotency key {} is still in progress",
+                        key
+                    ),
+                )),
+            };
+        }
+
+        self.claim_for(&store_key, call, now).map_err(internal)
+    }
+
+    fn claim_for(&self, store_key: &str, call: &CallRequest, now: u64) -> Result<Claim, String> {
+        let entry = Entry {
+            request: call.clone(),
+            claimed_at: now,
+            response: None,
+        };
+        self.store.put(store_key, &entry)?;
+        Ok(Claim::New)
+    }
+
+    /// Moves the lease on `key`'s unfinished claim to start at `now`.
+    pub fn renew(&self, key: &str, now: u64) -> Result<(), String> {
+        let _guard = self.lock.lock().unwrap();
+        let store_key = format!("{}{}", KEY_PREFIX, key);
+        let Some(mut entry) = self.store.get::<Entry>(&store_key)? else {
+            return Ok(());
+        };
+        if entry.response.is_some() {
+            return Ok(());
+        }
+        entry.claimed_at = now;
+        self.store.put(&store_key, &entry)
+    }
+
+    /// Renews `key`'s claim every third of its lease for as long as it is
+    /// polled, reading the time from `now`. Polled alongside the request, so
+    /// one that outlives a lease doesn't lose its key to a retry.
+    pub async fn hold(&self, key: &str, now: impl Fn() -> u64) {
+        let mut renewals = tokio::time::interval(CLAIM_LEASE / 3);
+        // The first tick is immediate, and the claim was just taken.
+        renewals.tick().await;
+        loop {
+            renewals.tick().await;
+            if let Err(e) = self.renew(key, now()) {
+                tracing::warn!("Could not renew idempotency key {}: {}", key, e);
+            }
+        }
+    }
+
+    /// Records how the request claimed under `key` ended. Failures release
+    /// the key so the client can retry.
+    pub fn complete(&self, key: &str, response: &BridgeResponse) -> Result<(), String> {
+        let _guard = self.lock.lock().unwrap();
+        let store_key = format!("{}{}", KEY_PREFIX, key);
+        if !response.success {
+            return self.store.remove(&store_key);
+        }
+        let Some(mut entry) = self.store.get::<Entry>(&store_key)? else {
+            return Ok(());
+        };
+        entry.response = Some(response.clone());
+        self.store.put(&store_key, &entry)
+    }
+
+    fn prune(&self, now: u64) -> Result<(), String> {
+        let expired: Vec<String> = self
+            .store
+            .keys(KEY_PREFIX)
+            .into_iter()
+            .filter(|store_key| match self.store.get::<Entry>(store_key) {
+                Ok(Some(entry)) => self.expired(&entry, now),
+                Ok(None) => false,
+                // Unreadable entries (e.g. from an older build) just go.
+                Err(_) => true,
+            })
+            .collect();
+        self.store.remove_all(&expired)
+    }
+
+    // Unfinished claims last at least their lease, however short `ttl` is.
+    fn expired(&self, entry: &Entry, now: u64) -> bool {
+        let kept_for = match entry.response {
+            Some(_) => self.ttl,
+            None => self.ttl.max(CLAIM_LEASE),
+        };
+        entry.claimed_at + kept_for.as_secs() <= now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn call(value: u64) -> CallRequest {
+        CallRequest {
+            value: Some(json!(value)),
+            idempotency_key: Some("retry-1".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_replays_return_the_first_response_until_expiry() {
+        let store = Arc::new(StateStore::in_memory());
+        let keys = IdempotencyKeys::new(store.clone(), Duration::from_secs(60));
+
+        assert_eq!(keys.claim("retry-1", &call(214), NOW).unwrap(), Claim::New);
+        let (code, e) = keys.claim("retry-1", &call(214), NOW).unwrap_err();
+        assert_eq!(code, ErrorCode::Upstream);
+        assert!(e.contains("in progress"), "{}", e);
+
+        let sent = BridgeResponse::sent("0xfeed".to_string());
+        keys.complete("retry-1", &sent).unwrap();
+        assert_eq!(
+            keys.claim("retry-1", &call(214), NOW + 30).unwrap(),
+            Claim::Replay(Box::new(sent))
+        );
+        let (code, _) = keys.claim("retry-1", &call(5), NOW + 30).unwrap_err();
+        assert_eq!(code, ErrorCode::InvalidRequest);
+
+        assert_eq!(
+            keys.claim("retry-1", &call(5), NOW + 60).unwrap(),
+            Claim::New
+        );
+        assert_eq!(store.keys(KEY_PREFIX), ["idempotency/retry-1"]);
+        assert!(keys.claim("", &call(5), NOW).is_err());
+    }
+
+    #[test]
+    fn test_abandoned_claims_lapse_after_the_lease() {
+        let keys =
+            IdempotencyKeys::new(Arc::new(StateStore::in_memory()), Duration::from_secs(3600));
+        keys.claim("retry-1", &call(214), NOW).unwrap();
+        let lease = CLAIM_LEASE.as_secs();
+        assert!(keys.claim("retry-1", &call(214), NOW + lease - 1).is_err());
+        assert_eq!(
+            keys.claim("retry-1", &call(214), NOW + lease).unwrap(),
+            Claim::New
+        );
+        // The takeover holds a fresh lease.
+        assert!(keys.claim("retry-1", &call(214), NOW + lease + 1).is_err());
+    }
+
+    #[test]
+    fn test_renewed_claims_outlive_the_lease() {
+        let keys = IdempotencyKeys::new(Arc::new(StateStore::in_memory()), Duration::from_secs(60));
+        keys.claim("retry-1", &call(214), NOW).unwrap();
+        let lease = CLAIM_LEASE.as_secs();
+        keys.renew("retry-1", NOW + lease - 1).unwrap();
+        assert!(keys.claim("retry-1", &call(214), NOW + lease).is_err());
+        assert!(keys
+            .claim("retry-1", &call(214), NOW + 2 * lease - 2)
+            .is_err());
+        assert_eq!(
+            keys.claim("retry-1", &call(214), NOW + 2 * lease - 1)
+                .unwrap(),
+            Claim::New
+        );
+    }
+
+    #[test]
+    fn test_expired_keys_are_pruned_at_intervals() {
+        let store = Arc::new(StateStore::in_memory());
+        let keys = IdempotencyKeys::new(store.clone(), Duration::from_secs(30));
+        let sent = BridgeResponse::sent("0xfeed".to_string());
+        keys.claim("a", &call(1), NOW).unwrap();
+        keys.complete("a", &sent).unwrap();
+
+        // Expired but not yet swept: still stored, no longer replayed.
+        keys.claim("b", &call(2), NOW + 40).unwrap();
+        assert_eq!(store.keys(KEY_PREFIX), ["idempotency/a", "idempotency/b"]);
+        assert_eq!(keys.claim("a", &call(3), NOW + 40).unwrap(), Claim::New);
+        keys.complete("a", &sent).unwrap();
+
+        keys.claim("c", &call(4), NOW + PRUNE_INTERVAL.as_secs() + 40)
+            .unwrap();
+        assert_eq!(store.keys(KEY_PREFIX), ["idempotency/b", "idempotency/c"]);
+    }
+
+    #[test]
+    fn test_failures_release_the_key() {
+        let keys = IdempotencyKeys::new(Arc::new(StateStore::in_memory()), Duration::from_secs(60));
+        keys.claim("retry-1", &call(214), NOW).unwrap();
+        let failed = BridgeResponse::failed(ErrorCode::PxeUnavailable, "down");
+        keys.complete("retry-1", &failed).unwrap();
+        assert_eq!(keys.claim("retry-1", &call(214), NOW).unwrap(), Claim::New);
+    }
+}
@@ -0,0 +1,64 @@
+use k256::ecdsa::signature::hazmat::{PrehashSigner, PrehashVerifier};
+use k256::ecdsa::{Signature, SigningKey, VerifyingKey};
+
+use crate::secret::Zeroizing;
+
+/// Parses a secp256k1 public key (SEC1 hex, compressed or not) and returns it
+/// in compressed `0x` form, so the same operator always compares equal.
+pub(crate) fn normalize_key(key: &str) -> Result<String, String> {
+    let bytes = hex::decode(key.trim_start_matches("0x"))
+        .map_err(|e| format!("Invalid operator key '{}': {}", key, e))?;
+    let key = VerifyingKey::from_sec1_bytes(&bytes)
+        .map_err(|_| format!("Invalid operator key '{}'", key))?;
+    Ok(format!(
+        "0x{}",
+        hex::encode(key.to_encoded_point(true).as_bytes())
+    ))
+}
+
+/// Parses a comma-separated operator list.
+pub(crate) fn parse_operators(operators: &str) -> Result<Vec<String>, String> {
+    operators
+        .split(',')
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .map(normalize_key)
+        .collect()
+}
+
+/// Checks an ECDSA `signature` (64-byte hex) by `operator` over a 32-byte
+/// prehashed `digest`.
+pub(crate) fn verify(digest: &[u8], operator: &str, signature: &str) -> Result<(), String> {
+    let invalid = || format!("Invalid signature from {}", operator);
+    let key = VerifyingKey::from_sec1_bytes(
+        &hex::decode(operator.trim_start_matches("0x")).map_err(|_| invalid())?,
+    )
+    .map_err(|_| invalid())?;
+    let signature = Signature::from_slice(
+        &hex::decode(signature.trim_start_matches("0x")).map_err(|_| invalid())?,
+    )
+    .map_err(|_| invalid())?;
+
+    key.verify_prehash(digest, &signature)
+        .map_err(|_| invalid())
+}
+
+/// Signs `digest` with a hex secret key; returns `(public key, signature)`.
+pub(crate) fn sign(digest: &[u8], secret_key: &str) -> Result<(String, String), String> {
+    let bytes = Zeroizing::new(
+        hex::decode(secret_key.trim_start_matches("0x"))
+            .map_err(|e| format!("Invalid secret key: {}", e))?,
+    );
+    let key = SigningKey::from_slice(&bytes).map_err(|_| "Invalid secret key".to_string())?;
+    let signature: Signature = key
+        .sign_prehash(digest)
+        .map_err(|e| format!("Cannot sign: {}", e))?;
+
+    Ok((
+        format!(
+            "0x{}",
+            hex::encode(key.verifying_key().to_encoded_point(true).as_bytes())
+        ),
+        format!("0x{}", hex::encode(signature.to_bytes())),
+    ))
+}
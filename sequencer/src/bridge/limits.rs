@@ -0,0 +1,224 @@
+use serde_json::Value;
+use std::env;
+
+use super::protocol::ErrorCode;
+use crate::encoder::{AbiParameter, AbiType};
+
+/// Bounds on what a client may send, checked before anything is parsed
+/// into a request or encoded, so crafted inputs can't tie the bridge up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestLimits {
+    /// Largest WebSocket message, REST body or gRPC message, in bytes.
+    pub max_frame_bytes: usize,
+    /// Deepest nesting of arrays and objects in a request or its arguments.
+    pub max_depth: usize,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        RequestLimits {
+            max_frame_bytes: 64 * 1024,
+            max_depth: 32,
+        }
+    }
+}
+
+impl RequestLimits {
+    /// `BRIDGE_MAX_FRAME_BYTES` and `BRIDGE_MAX_DEPTH`, each defaulting.
+    pub fn from_env() -> Result<Self, String> {
+        let defaults = RequestLimits::default();
+        let read = |name: &str, default: usize| match env::var(name) {
+            Ok(value) => value
+                .parse()
+                .map_err(|_| format!("{} must be a number, got '{}'", name, value)),
+            Err(_) => Ok(default),
+        };
+        Ok(RequestLimits {
+            max_frame_bytes: read("BRIDGE_MAX_FRAME_BYTES", defaults.max_frame_bytes)?,
+            max_depth: read("BRIDGE_MAX_DEPTH", defaults.max_depth)?,
+        })
+    }
+
+    /// Checks a raw frame's size and, for JSON, its nesting, without
+    /// parsing it.
+    pub fn check_frame(&self, bytes: &[u8], json: bool) -> Result<(), (ErrorCode, String)> {
+        if bytes.len() > self.max_frame_bytes {
+            return Err((
+                ErrorCode::PayloadTooLarge,
+                format!(
+                    "Request is {} bytes; the limit is {}.",
+                    bytes.len(),
+                    self.max_frame_bytes
+                ),
+            ));
+        }
+        if json && json_depth(bytes) > self.max_depth {
+            return Err(self.too_deep());
+        }
+        Ok(())
+    }
+
+    /// Checks call arguments against the function's parameters: no more
+    /// arguments than parameters, no array or string longer than its ABI
+    /// type, and nothing nested past `max_depth`.
+    pub fn check_args(
+        &self,
+        parameters: &[AbiParameter],
+        args: &[Value],
+    ) -> Result<(), (ErrorCode, String)> {
+        if args.iter().any(|arg| value_depth(arg) > self.max_depth) {
+            return Err(self.too_deep());
+        }
+        if args.len() > parameters.len() {
+            return Err((
+                ErrorCode::Encoding,
+                format!(
+                    "Function takes {} arguments, got {}.",
+                    parameters.len(),
+                    args.len()
+                ),
+            ));
+        }
+        for (parameter, arg) in parameters.iter().zip(args) {
+            check_length(&parameter.name, &parameter.abi_type, arg)
+                .map_err(|e| (ErrorCode::Encoding, e))?;
+        }
+        Ok(())
+    }
+
+    fn too_deep(&self) -> (ErrorCode, String) {
+        (
+            ErrorCode::InvalidRequest,
+            format!("Request nests deeper than {} levels.", self.max_depth),
+        )
+    }
+}
+
+// The deepest bracket nesting in `bytes`, skipping brackets inside strings.
+fn json_depth(bytes: &[u8]) -> usize {
+    let (mut depth, mut deepest) = (0usize, 0);
+    let (mut in_string, mut escaped) = (false, false);
+    for &byte in bytes {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'[' | b'{' => {
+                depth += 1;
+                deepest = deepest.max(depth);
+            }
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    deepest
+}
+
+fn value_depth(value: &Value) -> usize {
+    // Iterative so the check itself can't overflow the stack.
+    let mut deepest = 0;
+    let mut stack = vec![(value, 0)];
+    while let Some((value, depth)) = stack.pop() {
+        deepest = deepest.max(depth);
+        match value {
+            Value::Array(items) => stack.extend(items.iter().map(|v| (v, depth + 1))),
+            Value::Object(fields) => stack.extend(fields.values().map(|v| (v, depth + 1))),
+            _ => {}
+        }
+    }
+    deepest
+}
+
+fn check_length(name: &str, abi_type: &AbiType, value: &Value) -> Result<(), String> {
+    match (abi_type, value) {
+        (AbiType::Array { r#type, length }, Value::Array(items)) => {
+            if items.len() > *length {
+                return Err(format!(
+                    "'{}' takes at most {} elements, got {}.",
+                    name,
+                    length,
+                    items.len()
+                ));
+            }
+            items
+                .iter()
+                .try_for_each(|item| check_length(name, r#type, item))
+        }
+        (AbiType::String { length }, Value::String(s)) if s.len() > *length => Err(format!(
+            "'{}' takes at most {} bytes, got {}.",
+            name,
+            length,
+            s.len()
+        )),
+        (AbiType::Struct { fields, .. }, Value::Object(values)) => {
+            fields
+                .iter()
+                .try_for_each(|field| match values.get(&field.name) {
+                    Some(value) => check_length(&field.name, &field.field_type, value),
+                    None => Ok(()),
+                })
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_limits_reject_before_encoding() {
+        let limits = RequestLimits {
+            max_frame_bytes: 64,
+            max_depth: 3,
+        };
+        assert!(limits.check_frame(br#"{"a": [[1]]}"#, true).is_ok());
+        assert_eq!(
+            limits
+                .check_frame(br#"{"a": [[["]]]]"]]]}"#, true)
+                .unwrap_err()
+                .0,
+            ErrorCode::InvalidRequest
+        );
+        assert!(limits.check_frame(b"[[[[", false).is_ok());
+        assert_eq!(
+            limits.check_frame(&[b' '; 65], true).unwrap_err().0,
+            ErrorCode::PayloadTooLarge
+        );
+
+        let parameters: Vec<AbiParameter> = serde_json::from_value(json!([
+            { "name": "values", "type": { "kind": "array", "length": 2, "type": { "kind": "field" } } },
+            { "name": "label", "type": { "kind": "string", "length": 4 } },
+        ]))
+        .unwrap();
+        assert!(limits
+            .check_args(&parameters, &[json!([1, 2]), json!("abcd")])
+            .is_ok());
+        let (code, error) = limits
+            .check_args(&parameters, &[json!([1, 2, 3])])
+            .unwrap_err();
+        assert_eq!(code, ErrorCode::Encoding);
+        assert_eq!(error, "'values' takes at most 2 elements, got 3.");
+        assert!(limits
+            .check_args(&parameters, &[json!([]), json!("abcde")])
+            .is_err());
+        assert!(limits
+            .check_args(&parameters, &[json!(1), json!(2), json!(3)])
+            .is_err());
+        assert_eq!(
+            limits
+                .check_args(&parameters, &[json!([[[[1]]]])])
+                .unwrap_err()
+                .0,
+            ErrorCode::InvalidRequest
+        );
+    }
+}
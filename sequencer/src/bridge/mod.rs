@@ -0,0 +1,23 @@
+mod approvals;
+mod auth;
+mod bench;
+mod cache;
+pub mod grpc;
+mod health;
+mod idempotency;
+mod keys;
+mod limits;
+mod outbound;
+pub mod protocol;
+mod registry;
+mod rest;
+mod server;
+
+pub use approvals::{ApprovalPolicy, PendingApproval};
+pub use auth::{sign_set, AuthPolicy, Grant};
+pub use bench::{ActionStats, BenchConfig, BenchReport};
+pub use health::{Check, Readiness};
+pub use limits::RequestLimits;
+pub use outbound::{OutboundStats, OverflowPolicy, SendQueueConfig};
+pub use registry::{ArtifactRegistry, ClassIdOf, ClassMismatch, OnUpgrade, ReloadReport};
+pub use server::{run, serve, Bridge, BridgeConfig};
@@ -0,0 +1,323 @@
+use futures_util::{Sink, SinkExt};
+use std::collections::VecDeque;
+use std::env;
+use std::fmt::Write as _;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+use tokio_tungstenite::tungstenite::Message;
+
+/// What a connection does when a push finds its send queue full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Drop the oldest queued push to make room; the client misses it.
+    #[default]
+    DropOldest,
+    /// Close the connection; the client reconnects and resubscribes.
+    Disconnect,
+}
+
+impl FromStr for OverflowPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "drop-oldest" => Ok(OverflowPolicy::DropOldest),
+            "disconnect" => Ok(OverflowPolicy::Disconnect),
+            _ => Err(format!(
+                "Overflow policy must be 'drop-oldest' or 'disconnect', got '{}'",
+                s
+            )),
+        }
+    }
+}
+
+/// Bounds on what the bridge buffers for a client that reads slower than
+/// pushes arrive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SendQueueConfig {
+    /// Most frames waiting to be written to one connection.
+    pub capacity: usize,
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for SendQueueConfig {
+    fn default() -> Self {
+        SendQueueConfig {
+            capacity: 256,
+            overflow: OverflowPolicy::default(),
+        }
+    }
+}
+
+impl SendQueueConfig {
+    /// `BRIDGE_SEND_QUEUE` and `BRIDGE_SEND_OVERFLOW`, each defaulting.
+    pub fn from_env() -> Result<Self, String> {
+        let defaults = SendQueueConfig::default();
+        let capacity = match env::var("BRIDGE_SEND_QUEUE") {
+            Ok(value) => match value.parse() {
+                Ok(capacity) if capacity > 0 => capacity,
+                _ => {
+                    return Err(format!(
+                        "BRIDGE_SEND_QUEUE must be a positive number, got '{}'",
+                        value
+                    ))
+                }
+            },
+            Err(_) => defaults.capacity,
+        };
+        let overflow = match env::var("BRIDGE_SEND_OVERFLOW") {
+            Ok(value) => value.parse()?,
+            Err(_) => defaults.overflow,
+        };
+        Ok(SendQueueConfig { capacity, overflow })
+    }
+}
+
+/// Pushes dropped and connections closed because a client read too slowly,
+/// across all connections.
+#[derive(Debug, Default)]
+pub struct OutboundStats {
+    dropped: AtomicU64,
+    disconnects: AtomicU64,
+}
+
+impl OutboundStats {
+    /// Notifications a client never got: pushed out of a full queue, or
+    /// missed while the connection lagged behind the block watcher.
+    pub fn dropped_notifications(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    pub fn slow_disconnects(&self) -> u64 {
+        self.disconnects.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn record_dropped(&self, count: u64) {
+        self.dropped.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// The counters in the Prometheus text format.
+    pub fn prometheus(&self) -> String {
+        let mut out = String::new();
+        for (name, help, value) in [
+            (
+                "bridge_dropped_notifications_total",
+                "Pushes a slow WebSocket client never got.",
+                self.dropped_notifications(),
+            ),
+            (
+                "bridge_slow_disconnects_total",
+                "WebSocket connections closed for falling behind on pushes.",
+                self.slow_disconnects(),
+            ),
+        ] {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            let _ = writeln!(out, "{} {}", name, value);
+        }
+        out
+    }
+}
+
+/// Something for a connection's writer to do.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Frame {
+    Message(Message),
+    /// Flush whatever the socket queued on its own, like pongs.
+    Flush,
+}
+
+/// The queue was closed, by the connection or because the client fell
+/// too far behind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Closed;
+
+#[derive(Debug, Default)]
+struct State {
+    /// Each frame, and whether it is a push (and so may be dropped).
+    frames: VecDeque<(Frame, bool)>,
+    closed: bool,
+    overflowed: bool,
+}
+
+/// One connection's bounded send queue, drained by a writer task so that a
+/// client that stops reading can't make the bridge buffer without limit.
+/// Responses wait for room, so a client that doesn't read its answers stops
+/// being read; pushes never wait, and overflow by `OverflowPolicy`.
+#[derive(Debug)]
+pub(crate) struct SendQueue {
+    config: SendQueueConfig,
+    stats: Arc<OutboundStats>,
+    state: Mutex<State>,
+    queued: Notify,
+    freed: Notify,
+}
+
+impl SendQueue {
+    pub fn new(config: SendQueueConfig, stats: Arc<OutboundStats>) -> Self {
+        SendQueue {
+            config,
+            stats,
+            state: Mutex::new(State::default()),
+            queued: Notify::new(),
+            freed: Notify::new(),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("send queue lock poisoned")
+    }
+
+    /// Queues `frame` once there is room for it.
+    pub async fn respond(&self, frame: Frame) -> Result<(), Closed> {
+        loop {
+            let freed = self.freed.notified();
+            {
+                let mut state = self.state();
+                if state.closed {
+                    return Err(Closed);
+                }
+                if state.frames.len() < self.config.capacity {
+                    state.frames.push_back((frame, false));
+                    self.queued.notify_one();
+                    return Ok(());
+                }
+            }
+            freed.await;
+        }
+    }
+
+    /// Queues a push without waiting. A full queue drops its oldest push
+    /// (or this one, when it holds only responses), or closes under
+    /// `OverflowPolicy::Disconnect`.
+    pub fn push(&self, message: Message) -> Result<(), Closed> {
+        let mut state = self.state();
+        if state.closed {
+            return Err(Closed);
+        }
+        if state.frames.len() >= self.config.capacity {
+            if self.config.overflow == OverflowPolicy::Disconnect {
+                state.frames.clear();
+                state.closed = true;
+                state.overflowed = true;
+                self.stats.disconnects.fetch_add(1, Ordering::Relaxed);
+                self.queued.notify_one();
+                self.freed.notify_one();
+                return Err(Closed);
+            }
+            self.stats.record_dropped(1);
+            match state.frames.iter().position(|(_, push)| *push) {
+                Some(oldest) => {
+                    state.frames.remove(oldest);
+                }
+                None => return Ok(()),
+            }
+        }
+        state.frames.push_back((Frame::Message(message), true));
+        self.queued.notify_one();
+        Ok(())
+    }
+
+    /// Stops taking frames; the writer still sends what is queued.
+    pub fn close(&self) {
+        self.state().closed = true;
+        self.queued.notify_one();
+        self.freed.notify_one();
+    }
+
+    /// Whether the queue closed because a push overflowed it.
+    pub fn overflowed(&self) -> bool {
+        self.state().overflowed
+    }
+
+    /// The next frame to write, or `None` once closed and drained.
+    async fn next(&self) -> Option<Frame> {
+        loop {
+            let queued = self.queued.notified();
+            {
+                let mut state = self.state();
+                if let Some((frame, _)) = state.frames.pop_front() {
+                    self.freed.notify_one();
+                    return Some(frame);
+                }
+                if state.closed {
+                    return None;
+                }
+            }
+            queued.await;
+        }
+    }
+
+    /// Writes frames to `sink` until the queue is closed and drained, or
+    /// the sink fails (which closes the queue).
+    pub async fn drain_into<S>(self: Arc<Self>, mut sink: S) -> Result<(), S::Error>
+    where
+        S: Sink<Message> + Unpin,
+    {
+        while let Some(frame) = self.next().await {
+            let written = match frame {
+                Frame::Message(message) => sink.send(message).await,
+                Frame::Flush => sink.flush().await,
+            };
+            if let Err(e) = written {
+                self.close();
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(n: u32) -> Message {
+        Message::Text(n.to_string())
+    }
+
+    async fn drain(queue: SendQueue) -> Vec<Message> {
+        let queue = Arc::new(queue);
+        queue.close();
+        let mut sent = vec![];
+        queue.drain_into(&mut sent).await.unwrap();
+        sent
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_drops_or_disconnects_by_policy() {
+        let stats = Arc::new(OutboundStats::default());
+        let config = SendQueueConfig {
+            capacity: 3,
+            overflow: OverflowPolicy::DropOldest,
+        };
+        let queue = SendQueue::new(config, stats.clone());
+        queue.push(text(1)).unwrap();
+        queue.respond(Frame::Message(text(2))).await.unwrap();
+        queue.push(text(3)).unwrap();
+        queue.push(text(4)).unwrap();
+        // Responses are never dropped, only older pushes.
+        assert_eq!(drain(queue).await, vec![text(2), text(3), text(4)]);
+        assert_eq!(stats.dropped_notifications(), 1);
+
+        let config = SendQueueConfig {
+            capacity: 2,
+            overflow: OverflowPolicy::Disconnect,
+        };
+        let queue = SendQueue::new(config, stats.clone());
+        queue.push(text(1)).unwrap();
+        queue.push(text(2)).unwrap();
+        assert_eq!(queue.push(text(3)), Err(Closed));
+        assert!(queue.overflowed());
+        assert_eq!(queue.respond(Frame::Flush).await, Err(Closed));
+        assert_eq!(stats.slow_disconnects(), 1);
+        assert!(stats
+            .prometheus()
+            .contains("bridge_slow_disconnects_total 1\n"));
+
+        assert_eq!("disconnect".parse(), Ok(OverflowPolicy::Disconnect));
+        assert!("drop-newest".parse::<OverflowPolicy>().is_err());
+    }
+}
@@ -0,0 +1,830 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::contract::TxEffects;
+use crate::watcher::{ValueChange, WatchTarget};
+
+/// The bridge protocol revision. Revision 1 is everything before `hello`
+/// carried a version; clients that send none are treated as revision 1.
+/// `tests/protocol_golden.rs` fails until this is bumped when a message's
+/// wire format changes.
+pub const VERSION: u32 = 2;
+/// The oldest revision this bridge still serves.
+pub const MIN_VERSION: u32 = 1;
+
+/// Optional parts of the protocol, agreed on in the `hello` exchange.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    /// CBOR binary frames (see `Framing`).
+    BinaryFraming,
+    /// `subscribe` and the pushes that follow it.
+    Subscriptions,
+    /// Signed `set` requests (see `SetAuth`).
+    Auth,
+}
+
+/// Wire encoding for bridge messages. Connections start out as JSON text
+/// frames; a `hello` can switch both directions to CBOR binary frames.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Framing {
+    #[default]
+    Json,
+    Cbor,
+}
+
+impl Framing {
+    /// The server supports every framing, so the client's first preference wins.
+    pub fn negotiate(offered: &[Framing]) -> Framing {
+        offered.first().copied().unwrap_or_default()
+    }
+
+    pub fn is_binary(&self) -> bool {
+        matches!(self, Framing::Cbor)
+    }
+
+    pub fn encode<T: Serialize>(&self, message: &T) -> Result<Vec<u8>, String> {
+        match self {
+            Framing::Json => serde_json::to_vec(message).map_err(|e| e.to_string()),
+            Framing::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::ser::into_writer(message, &mut bytes).map_err(|e| e.to_string())?;
+                Ok(bytes)
+            }
+        }
+    }
+
+    pub fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, String> {
+        match self {
+            Framing::Json => serde_json::from_slice(bytes).map_err(|e| e.to_string()),
+            Framing::Cbor => ciborium::de::from_reader(bytes).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// Messages accepted by the bridge. Older clients only send
+/// `{"action": "set", "value": ..}` / `{"action": "get"}`; every routing field
+/// is optional and falls back to the bridge's default contract.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum BridgeRequest {
+    Hello(Hello),
+    Set(CallRequest),
+    Get(CallRequest),
+    Subscribe(Subscribe),
+    Approve(Approve),
+    Storage(StorageRequest),
+    Receipt(ReceiptRequest),
+    Interface(InterfaceRequest),
+}
+
+impl BridgeRequest {
+    /// The `action` tag the request was sent with.
+    pub fn action(&self) -> &'static str {
+        match self {
+            BridgeRequest::Hello(_) => "hello",
+            BridgeRequest::Set(_) => "set",
+            BridgeRequest::Get(_) => "get",
+            BridgeRequest::Subscribe(_) => "subscribe",
+            BridgeRequest::Approve(_) => "approve",
+            BridgeRequest::Storage(_) => "storage",
+            BridgeRequest::Receipt(_) => "receipt",
+            BridgeRequest::Interface(_) => "interface",
+        }
+    }
+}
+
+/// A W3C `traceparent` any request may carry next to its own fields, so its
+/// spans join the caller's trace. Kept out of the requests themselves so
+/// retries with a new trace still match their idempotency key.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct TraceContext {
+    #[serde(default)]
+    pub traceparent: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Hello {
+    /// Framings the client can speak, most preferred first.
+    #[serde(default)]
+    pub framing: Vec<Framing>,
+    /// The newest revision the client speaks; `None` for revision 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
+    /// The oldest revision the client can still work with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_version: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<Feature>,
+}
+
+impl Hello {
+    /// A hello offering this build's revision and `features`.
+    pub fn current(framing: Vec<Framing>, features: Vec<Feature>) -> Self {
+        Hello {
+            framing,
+            version: Some(VERSION),
+            min_version: None,
+            features,
+        }
+    }
+
+    /// Settles on the newest revision both sides speak and the offered
+    /// features the bridge `supports`. Fails when no revision fits or the
+    /// client lacks a feature the bridge `requires`. Revision 1 clients
+    /// predate features and get everything the bridge supports.
+    pub fn negotiate(
+        &self,
+        supports: &[Feature],
+        requires: &[Feature],
+    ) -> Result<(Framing, Negotiated), String> {
+        let offered = self.version.unwrap_or(1);
+        let version = offered.min(VERSION);
+        let oldest = self.min_version.unwrap_or(0).max(MIN_VERSION);
+        if version < oldest {
+            return Err(format!(
+                "Bridge speaks protocol {}..={}, client needs {}..={}",
+                MIN_VERSION,
+                VERSION,
+                self.min_version.unwrap_or(offered),
+                offered
+            ));
+        }
+
+        if version < 2 {
+            let negotiated = Negotiated {
+                version,
+                features: supports.to_vec(),
+            };
+            return Ok((Framing::negotiate(&self.framing), negotiated));
+        }
+        if let Some(missing) = requires.iter().find(|f| !self.features.contains(f)) {
+            return Err(format!("Bridge requires the {:?} feature", missing));
+        }
+        let features: Vec<Feature> = supports
+            .iter()
+            .filter(|f| self.features.contains(f))
+            .copied()
+            .collect();
+        let framing = if features.contains(&Feature::BinaryFraming) {
+            Framing::negotiate(&self.framing)
+        } else {
+            Framing::Json
+        };
+        Ok((framing, Negotiated { version, features }))
+    }
+}
+
+/// The revision and features a `hello` settled on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Negotiated {
+    pub version: u32,
+    pub features: Vec<Feature>,
+}
+
+impl Negotiated {
+    /// What a bridge that never answered with a revision supports.
+    pub fn legacy() -> Self {
+        Negotiated {
+            version: 1,
+            features: vec![
+                Feature::BinaryFraming,
+                Feature::Subscriptions,
+                Feature::Auth,
+            ],
+        }
+    }
+
+    pub fn has(&self, feature: Feature) -> bool {
+        self.features.contains(&feature)
+    }
+}
+
+/// Asks for a push whenever `target` changes on chain, or for each of a
+/// contract's `events` in new blocks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Subscribe {
+    Target { target: WatchTarget },
+    Events { events: EventSubscription },
+}
+
+/// A contract's events of one kind. `event` is the struct's name in the
+/// artifact, with or without its module path (`PriceUpdated` or
+/// `Main::PriceUpdated`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct EventSubscription {
+    pub contract: String,
+    pub event: String,
+}
+
+/// An operator's signature over a pending `set` (see `ApprovalPolicy`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Approve {
+    pub id: String,
+    pub operator: String,
+    pub signature: String,
+}
+
+/// Reads a public storage variable by name, using the artifact's storage
+/// layout to find its slot.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StorageRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contract: Option<String>,
+    pub variable: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReceiptRequest {
+    pub tx_hash: String,
+}
+
+/// Describes a contract's functions, storage, notes and events (see
+/// `ArtifactReport`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InterfaceRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contract: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApprovalStatus {
+    pub id: String,
+    pub approvals: usize,
+    pub threshold: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CallRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contract: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub function: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub args: Option<Vec<Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+    /// For `get`: skip the bridge's cache and read from the PXE.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub force_refresh: bool,
+    /// For `set`, when the bridge requires signed requests (see `AuthPolicy`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<SetAuth>,
+    /// For `set`: retries carrying the same key get the first attempt's
+    /// response instead of sending another tx.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idempotency_key: Option<String>,
+}
+
+/// An operator's signature over a `set`, its nonce and its expiry (unix
+/// seconds). Built with `sign_set`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SetAuth {
+    pub operator: String,
+    pub nonce: u64,
+    pub expiry: u64,
+    pub signature: String,
+}
+
+impl CallRequest {
+    /// Explicit `args` win; otherwise a bare `value` becomes the single argument.
+    pub fn resolved_args(&self) -> Vec<Value> {
+        match (&self.args, &self.value) {
+            (Some(args), _) => args.clone(),
+            (None, Some(value)) => vec![value.clone()],
+            (None, None) => vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BridgeResponse {
+    pub success: bool,
+    #[serde(rename = "txHash", default, skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<String>,
+    /// Set instead of `tx_hash` when the bridge runs in dry-run mode: what the
+    /// `set` would have changed had it been sent.
+    #[serde(rename = "dryRun", default, skip_serializing_if = "Option::is_none")]
+    pub dry_run: Option<TxEffects>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+    /// Set on `get` responses; `true` when the value came from the cache and
+    /// may no longer match the chain.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stale: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// What kind of failure `error` is; always set alongside it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<ErrorCode>,
+    /// The failure in full; bridges before it only send `error` and `code`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<ErrorResponse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub framing: Option<Framing>,
+    /// Set on the answer to a `hello`; bridges before revision 2 leave it out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<Negotiated>,
+    /// Set when a `set` (or an `approve`) still needs operator signatures.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval: Option<ApprovalStatus>,
+    /// Set on messages the bridge pushes unprompted; these don't answer any
+    /// request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<BridgeEvent>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// The request itself is malformed.
+    InvalidRequest,
+    /// The arguments don't fit the function's ABI.
+    Encoding,
+    /// The contract, function or storage variable doesn't exist.
+    NotFound,
+    /// The PXE rejected or failed the call.
+    Upstream,
+    /// The PXE could not be reached.
+    PxeUnavailable,
+    /// Simulating the tx hit a revert or failed assertion.
+    TxReverted,
+    /// A `set` without a valid operator signature.
+    Unauthorized,
+    /// The request is larger than the bridge accepts (see `RequestLimits`).
+    PayloadTooLarge,
+}
+
+impl ErrorCode {
+    const ALL: [ErrorCode; 8] = [
+        ErrorCode::InvalidRequest,
+        ErrorCode::Encoding,
+        ErrorCode::NotFound,
+        ErrorCode::Upstream,
+        ErrorCode::PxeUnavailable,
+        ErrorCode::TxReverted,
+        ErrorCode::Unauthorized,
+        ErrorCode::PayloadTooLarge,
+    ];
+
+    /// The stable numeric code, borrowed from the closest HTTP status.
+    pub fn number(self) -> u16 {
+        match self {
+            ErrorCode::InvalidRequest => 400,
+            ErrorCode::Unauthorized => 401,
+            ErrorCode::NotFound => 404,
+            ErrorCode::TxReverted => 409,
+            ErrorCode::PayloadTooLarge => 413,
+            ErrorCode::Encoding => 422,
+            ErrorCode::Upstream => 502,
+            ErrorCode::PxeUnavailable => 503,
+        }
+    }
+
+    pub fn from_number(number: u16) -> Option<ErrorCode> {
+        ErrorCode::ALL
+            .into_iter()
+            .find(|code| code.number() == number)
+    }
+
+    /// Whether sending the same request again may succeed.
+    pub fn retryable(self) -> bool {
+        matches!(self, ErrorCode::Upstream | ErrorCode::PxeUnavailable)
+    }
+}
+
+/// A failed request: `code` is an `ErrorCode` number, `details` whatever
+/// the bridge knows beyond the message (e.g. the PXE's JSON-RPC error).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub code: u16,
+    pub message: String,
+    pub retryable: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+}
+
+impl ErrorResponse {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        ErrorResponse {
+            code: code.number(),
+            message: message.into(),
+            retryable: code.retryable(),
+            details: None,
+        }
+    }
+
+    /// `None` for codes this build doesn't know.
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        ErrorCode::from_number(self.code)
+    }
+}
+
+impl std::fmt::Display for ErrorResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (code {})", self.message, self.code)
+    }
+}
+
+impl std::error::Error for ErrorResponse {}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum BridgeEvent {
+    ValueChanged(ValueChange),
+    ContractEvent(ContractEvent),
+}
+
+/// An event a subscribed contract emitted, decoded with its artifact's ABI.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractEvent {
+    pub contract: String,
+    /// The event struct's path in the artifact.
+    pub event: String,
+    pub block: u64,
+    pub tx_hash: String,
+    /// The event's fields by name, decoded like `get` return values.
+    pub payload: Value,
+}
+
+impl BridgeResponse {
+    pub fn sent(tx_hash: String) -> Self {
+        BridgeResponse {
+            success: true,
+            tx_hash: Some(tx_hash),
+            ..Default::default()
+        }
+    }
+
+    pub fn would_send(effects: TxEffects) -> Self {
+        BridgeResponse {
+            success: true,
+            dry_run: Some(effects),
+            ..Default::default()
+        }
+    }
+
+    pub fn value(value: Value, stale: bool) -> Self {
+        BridgeResponse {
+            success: true,
+            value: Some(value),
+            stale: Some(stale),
+            ..Default::default()
+        }
+    }
+
+    pub fn ok() -> Self {
+        BridgeResponse {
+            success: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn awaiting_approval(status: ApprovalStatus) -> Self {
+        BridgeResponse {
+            success: true,
+            approval: Some(status),
+            ..Default::default()
+        }
+    }
+
+    pub fn push(event: BridgeEvent) -> Self {
+        BridgeResponse {
+            success: true,
+            event: Some(event),
+            ..Default::default()
+        }
+    }
+
+    pub fn welcome(framing: Framing, protocol: Negotiated) -> Self {
+        BridgeResponse {
+            success: true,
+            framing: Some(framing),
+            protocol: Some(protocol),
+            ..Default::default()
+        }
+    }
+
+    pub fn error(error: impl Into<String>) -> Self {
+        BridgeResponse::failed(ErrorCode::InvalidRequest, error)
+    }
+
+    pub fn failed(code: ErrorCode, error: impl Into<String>) -> Self {
+        let error = error.into();
+        BridgeResponse {
+            success: false,
+            failure: Some(ErrorResponse::new(code, error.clone())),
+            error: Some(error),
+            code: Some(code),
+            ..Default::default()
+        }
+    }
+
+    pub fn failed_with_details(code: ErrorCode, error: impl Into<String>, details: Value) -> Self {
+        let mut response = BridgeResponse::failed(code, error);
+        if let Some(failure) = &mut response.failure {
+            failure.details = Some(details);
+        }
+        response
+    }
+
+    /// The failure of an unsuccessful response, rebuilt from `error` and
+    /// `code` when the bridge didn't send one.
+    pub fn error_response(&self) -> Option<ErrorResponse> {
+        if self.success {
+            return None;
+        }
+        self.failure.clone().or_else(|| {
+            let code = self.code.unwrap_or(ErrorCode::InvalidRequest);
+            Some(ErrorResponse::new(
+                code,
+                self.error.clone().unwrap_or_default(),
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fields::Fr;
+    use serde_json::json;
+
+    #[test]
+    fn test_legacy_set_message() {
+        let request: BridgeRequest =
+            serde_json::from_value(json!({ "action": "set", "value": 214 })).unwrap();
+        let BridgeRequest::Set(call) = request else {
+            panic!("expected set");
+        };
+        assert_eq!(call.contract, None);
+        assert_eq!(call.resolved_args(), vec![json!(214)]);
+    }
+
+    #[test]
+    fn test_routed_get_message() {
+        let request: BridgeRequest = serde_json::from_value(json!({
+            "action": "get",
+            "contract": "0x12",
+            "function": "read_field_in_map",
+            "args": [1],
+        }))
+        .unwrap();
+
+        assert_eq!(
+            request,
+            BridgeRequest::Get(CallRequest {
+                contract: Some("0x12".to_string()),
+                function: Some("read_field_in_map".to_string()),
+                args: Some(vec![json!(1)]),
+                value: None,
+                force_refresh: false,
+                auth: None,
+                idempotency_key: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_response_omits_empty_fields() {
+        let encoded = serde_json::to_value(BridgeResponse::sent("0xabc".to_string())).unwrap();
+        assert_eq!(encoded, json!({ "success": true, "txHash": "0xabc" }));
+    }
+
+    #[test]
+    fn test_failures_carry_an_error_response() {
+        let response = BridgeResponse::failed_with_details(
+            ErrorCode::PxeUnavailable,
+            "connection refused",
+            json!({ "url": "http://localhost:8080" }),
+        );
+        assert_eq!(
+            serde_json::to_value(&response).unwrap(),
+            json!({
+                "success": false,
+                "error": "connection refused",
+                "code": "pxe_unavailable",
+                "failure": {
+                    "code": 503,
+                    "message": "connection refused",
+                    "retryable": true,
+                    "details": { "url": "http://localhost:8080" },
+                },
+            })
+        );
+        let failure = response.error_response().unwrap();
+        assert_eq!(failure.error_code(), Some(ErrorCode::PxeUnavailable));
+
+        // Older bridges send only `error` and `code`.
+        let legacy: BridgeResponse = serde_json::from_value(
+            json!({ "success": false, "error": "no such contract", "code": "not_found" }),
+        )
+        .unwrap();
+        assert_eq!(
+            legacy.error_response(),
+            Some(ErrorResponse::new(ErrorCode::NotFound, "no such contract"))
+        );
+        assert_eq!(BridgeResponse::ok().error_response(), None);
+
+        for code in ErrorCode::ALL {
+            assert_eq!(ErrorCode::from_number(code.number()), Some(code));
+        }
+    }
+
+    fn storage_target() -> WatchTarget {
+        WatchTarget::PublicStorage {
+            contract: "0x12".to_string(),
+            slot: Fr::from(2u8),
+        }
+    }
+
+    #[test]
+    fn test_subscribe_message() {
+        let request: BridgeRequest = serde_json::from_value(json!({
+            "action": "subscribe",
+            "target": { "kind": "publicStorage", "contract": "0x12", "slot": "0x2" },
+        }))
+        .unwrap();
+        assert_eq!(
+            request,
+            BridgeRequest::Subscribe(Subscribe::Target {
+                target: storage_target()
+            })
+        );
+
+        let request: BridgeRequest = serde_json::from_value(json!({
+            "action": "subscribe",
+            "events": { "contract": "0x12", "event": "PriceUpdated" },
+        }))
+        .unwrap();
+        let BridgeRequest::Subscribe(Subscribe::Events { events }) = request else {
+            panic!("not an events subscription: {:?}", request);
+        };
+        assert_eq!(events.event, "PriceUpdated");
+    }
+
+    fn round_trip<T: Serialize + DeserializeOwned>(framing: Framing, message: &T) -> T {
+        framing.decode(&framing.encode(message).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_requests_round_trip_through_every_framing() {
+        let requests = vec![
+            BridgeRequest::Hello(Hello {
+                framing: vec![Framing::Cbor, Framing::Json],
+                ..Default::default()
+            }),
+            BridgeRequest::Hello(Hello::current(
+                vec![Framing::Cbor],
+                vec![Feature::BinaryFraming, Feature::Auth],
+            )),
+            BridgeRequest::Set(CallRequest {
+                contract: Some("0x12".to_string()),
+                function: Some("set_feeds".to_string()),
+                args: Some(vec![
+                    json!((0..64).collect::<Vec<u64>>()),
+                    json!("123456789012345678901234567890"),
+                ]),
+                value: None,
+                force_refresh: false,
+                auth: None,
+                idempotency_key: Some("retry-7".to_string()),
+            }),
+            BridgeRequest::Get(CallRequest {
+                force_refresh: true,
+                ..Default::default()
+            }),
+            BridgeRequest::Subscribe(Subscribe::Target {
+                target: storage_target(),
+            }),
+            BridgeRequest::Subscribe(Subscribe::Events {
+                events: EventSubscription {
+                    contract: "0x12".to_string(),
+                    event: "PriceUpdated".to_string(),
+                },
+            }),
+            BridgeRequest::Approve(Approve {
+                id: "0x01".to_string(),
+                operator: "0x02".to_string(),
+                signature: "0x03".to_string(),
+            }),
+            BridgeRequest::Storage(StorageRequest {
+                contract: None,
+                variable: "just_field".to_string(),
+            }),
+            BridgeRequest::Receipt(ReceiptRequest {
+                tx_hash: "0x04".to_string(),
+            }),
+        ];
+
+        for framing in [Framing::Json, Framing::Cbor] {
+            for request in &requests {
+                assert_eq!(&round_trip(framing, request), request);
+            }
+        }
+    }
+
+    #[test]
+    fn test_responses_round_trip_through_every_framing() {
+        let responses = vec![
+            BridgeResponse::sent("0xabc".to_string()),
+            BridgeResponse::value(json!({ "values": ["0x01", true, null] }), true),
+            BridgeResponse::would_send(TxEffects {
+                nullifiers: vec![Fr::from(1u8)],
+                ..Default::default()
+            }),
+            BridgeResponse::error("boom"),
+            BridgeResponse::failed(ErrorCode::NotFound, "gone"),
+            BridgeResponse::welcome(Framing::Cbor, Negotiated::legacy()),
+            BridgeResponse::awaiting_approval(ApprovalStatus {
+                id: "0x01".to_string(),
+                approvals: 1,
+                threshold: 2,
+            }),
+            BridgeResponse::push(BridgeEvent::ValueChanged(ValueChange {
+                target: storage_target(),
+                block: 7,
+                previous: json!(Fr::from(700u16)),
+                current: json!(Fr::from(214u8)),
+            })),
+            BridgeResponse::push(BridgeEvent::ContractEvent(ContractEvent {
+                contract: "0x12".to_string(),
+                event: "Main::PriceUpdated".to_string(),
+                block: 8,
+                tx_hash: "0x05".to_string(),
+                payload: json!({ "feed_id": "1", "price": "214" }),
+            })),
+        ];
+
+        for framing in [Framing::Json, Framing::Cbor] {
+            for response in &responses {
+                assert_eq!(&round_trip(framing, response), response);
+            }
+        }
+    }
+
+    #[test]
+    fn test_cbor_is_smaller_for_large_argument_arrays() {
+        let request = BridgeRequest::Set(CallRequest {
+            args: Some(vec![json!((0..64)
+                .map(|i| i * 1_000_000u64)
+                .collect::<Vec<_>>())]),
+            ..Default::default()
+        });
+
+        let json_len = Framing::Json.encode(&request).unwrap().len();
+        let cbor_len = Framing::Cbor.encode(&request).unwrap().len();
+        assert!(
+            cbor_len < json_len,
+            "cbor {} >= json {}",
+            cbor_len,
+            json_len
+        );
+    }
+
+    #[test]
+    fn test_hello_negotiates_version_and_features() {
+        let all = [
+            Feature::BinaryFraming,
+            Feature::Subscriptions,
+            Feature::Auth,
+        ];
+
+        // Revision 1 clients send no version and keep every feature.
+        let legacy: Hello = serde_json::from_value(json!({ "framing": ["cbor"] })).unwrap();
+        let (framing, negotiated) = legacy.negotiate(&all, &[Feature::Auth]).unwrap();
+        assert_eq!(framing, Framing::Cbor);
+        assert_eq!(negotiated.version, 1);
+        assert_eq!(negotiated.features, all);
+
+        let hello = Hello::current(vec![Framing::Cbor], vec![Feature::Subscriptions]);
+        let (framing, negotiated) = hello.negotiate(&all, &[]).unwrap();
+        assert_eq!(framing, Framing::Json, "binary framing was not offered");
+        assert_eq!(negotiated.version, VERSION);
+        assert_eq!(negotiated.features, [Feature::Subscriptions]);
+        let err = hello.negotiate(&all, &[Feature::Auth]).unwrap_err();
+        assert!(err.contains("Auth"), "{}", err);
+
+        // Newer clients are downgraded, unless they can't go that low.
+        let newer = Hello {
+            version: Some(VERSION + 1),
+            ..hello.clone()
+        };
+        assert_eq!(newer.negotiate(&all, &[]).unwrap().1.version, VERSION);
+        let strict = Hello {
+            min_version: Some(VERSION + 1),
+            ..newer
+        };
+        assert!(strict.negotiate(&all, &[]).is_err());
+    }
+
+    #[test]
+    fn test_negotiate_defaults_to_json() {
+        assert_eq!(Framing::negotiate(&[]), Framing::Json);
+        assert_eq!(Framing::negotiate(&[Framing::Cbor]), Framing::Cbor);
+    }
+}
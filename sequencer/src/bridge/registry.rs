@@ -0,0 +1,460 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::encoder::{load_contract_artifact, ContractArtifact};
+use crate::fields::{AztecAddress, Fr};
+use crate::pxe_api::PxeApi;
+
+// An artifact and the file state it was loaded from. `mismatch` is set by
+// `check_class` and dropped with the entry when the file is reloaded.
+// `fetched` ones came from the PXE rather than a file, so `reload` keeps
+// them until a file for the key shows up.
+#[derive(Debug)]
+struct Entry {
+    artifact: Arc<ContractArtifact>,
+    stamp: Option<(SystemTime, u64)>,
+    mismatch: Option<ClassMismatch>,
+    fetched: bool,
+}
+
+/// Computes an artifact's contract class id, as aztec.js'
+/// `getContractClassFromArtifact` does. That hashes the artifact and the
+/// verification keys of its private functions, which this crate doesn't
+/// compute, so callers supply it.
+pub trait ClassIdOf: fmt::Debug + Send + Sync {
+    fn class_id(&self, artifact: &ContractArtifact) -> Result<Fr, String>;
+}
+
+/// What to do with a contract whose on-chain class no longer matches its
+/// artifact, e.g. after an upgrade.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnUpgrade {
+    /// Log a warning and keep serving the artifact.
+    #[default]
+    Warn,
+    /// Fail `resolve` for the contract until its artifact file changes.
+    Refuse,
+}
+
+/// A contract whose `currentContractClassId` isn't its artifact's class.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClassMismatch {
+    pub address: String,
+    pub on_chain: Fr,
+    pub local: Fr,
+}
+
+impl fmt::Display for ClassMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Contract {} is now class {}, but its artifact is class {}",
+            self.address,
+            self.on_chain.to_hex(),
+            self.local.to_hex()
+        )
+    }
+}
+
+/// What one `reload` changed, by file key.
+#[derive(Debug, Default, PartialEq)]
+pub struct ReloadReport {
+    pub loaded: Vec<String>,
+    pub removed: Vec<String>,
+    /// Files that failed to load; whatever was loaded before stays served.
+    pub failed: Vec<(String, String)>,
+}
+
+/// Contract artifacts from `<dir>/<key>.json`, where the key is the
+/// contract's address or class id. Artifacts load on first use or on
+/// `reload`, which also picks up files that changed or went away, so a
+/// recompiled contract is served without restarting the bridge.
+#[derive(Debug)]
+pub struct ArtifactRegistry {
+    dir: PathBuf,
+    cache: Mutex<HashMap<String, Entry>>,
+    scanned: AtomicBool,
+    class_ids: Option<Arc<dyn ClassIdOf>>,
+    on_upgrade: OnUpgrade,
+}
+
+impl ArtifactRegistry {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        ArtifactRegistry {
+            dir: dir.into(),
+            cache: Mutex::new(HashMap::new()),
+            scanned: AtomicBool::new(false),
+            class_ids: None,
+            on_upgrade: OnUpgrade::default(),
+        }
+    }
+
+    /// Lets `check_class` compare on-chain class ids with the artifacts'.
+    pub fn with_class_ids(mut self, class_ids: Arc<dyn ClassIdOf>, on_upgrade: OnUpgrade) -> Self {
+        self.class_ids = Some(class_ids);
+        self.on_upgrade = on_upgrade;
+        self
+    }
+
+    pub fn resolve(&self, address: &str) -> Result<Arc<ContractArtifact>, String> {
+        let key = address.to_lowercase();
+        if !is_hex(&key) {
+            return Err(format!("Invalid contract address '{}'.", address));
+        }
+
+        if let Some(entry) = self.lock().get(&key) {
+            return match &entry.mismatch {
+                Some(mismatch) if self.on_upgrade == OnUpgrade::Refuse => Err(format!(
+                    "{}; update its artifact to call it again.",
+                    mismatch
+                )),
+                _ => Ok(entry.artifact.clone()),
+            };
+        }
+
+        let path = self.dir.join(format!("{}.json", key));
+        let entry = load(&path).map_err(|e| {
+            format!(
+                "No artifact for contract {} ({}): {}",
+                address,
+                path.display(),
+                e
+            )
+        })?;
+        let artifact = entry.artifact.clone();
+        self.lock().insert(key, entry);
+        Ok(artifact)
+    }
+
+    /// A loaded artifact by address or class id (`0x…`), else by contract
+    /// name. Names only match artifacts a `reload` or `resolve` has seen.
+    pub fn get(&self, key: &str) -> Option<Arc<ContractArtifact>> {
+        if is_hex(&key.to_lowercase()) {
+            return self.resolve(key).ok();
+        }
+        let cache = self.lock();
+        let mut named: Vec<_> = cache
+            .iter()
+            .filter(|(_, entry)| entry.artifact.name == key)
+            .collect();
+        named.sort_by(|a, b| a.0.cmp(b.0));
+        named.first().map(|(_, entry)| entry.artifact.clone())
+    }
+
+    /// Loads new and changed `*.json` files and forgets deleted ones.
+    pub fn reload(&self) -> Result<ReloadReport, String> {
+        let read_dir = fs::read_dir(&self.dir)
+            .map_err(|e| format!("Cannot read {}: {}", self.dir.display(), e))?;
+        let mut files = HashMap::new();
+        for dir_entry in read_dir.flatten() {
+            let path = dir_entry.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                    files.insert(stem.to_lowercase(), path);
+                }
+            }
+        }
+
+        self.scanned.store(true, Ordering::Relaxed);
+        let mut report = ReloadReport::default();
+        let stamps: HashMap<String, _> = self
+            .lock()
+            .iter()
+            .map(|(key, entry)| (key.clone(), entry.stamp))
+            .collect();
+        for (key, path) in &files {
+            if stamps
+                .get(key)
+                .is_some_and(|stamp| *stamp == stamp_of(path))
+            {
+                continue;
+            }
+            match load(path) {
+                Ok(entry) => {
+                    self.lock().insert(key.clone(), entry);
+                    report.loaded.push(key.clone());
+                }
+                Err(e) => report.failed.push((key.clone(), e.to_string())),
+            }
+        }
+        self.lock().retain(|key, entry| {
+            let keep = entry.fetched || files.contains_key(key);
+            if !keep {
+                report.removed.push(key.clone());
+            }
+            keep
+        });
+
+        report.loaded.sort();
+        report.removed.sort();
+        report.failed.sort();
+        Ok(report)
+    }
+
+    /// Whether a `reload` has read the directory yet.
+    pub fn is_loaded(&self) -> bool {
+        self.scanned.load(Ordering::Relaxed)
+    }
+
+    /// `resolve`, falling back to the artifact the PXE has registered for
+    /// the contract's current class, so contracts without a local file can
+    /// still be called. A fetched artifact is kept like a loaded one.
+    pub async fn resolve_or_fetch<P: PxeApi + ?Sized>(
+        &self,
+        pxe: &P,
+        address: &str,
+    ) -> Result<Arc<ContractArtifact>, String> {
+        let key = address.to_lowercase();
+        let local = self.resolve(address);
+        if local.is_ok() || !is_hex(&key) || self.lock().contains_key(&key) {
+            return local;
+        }
+        let fetched = async {
+            let class_id = current_class_id(pxe, address)
+                .await?
+                .ok_or("the PXE has no instance there")?;
+            let artifact = pxe
+                .get_contract_artifact(&class_id)
+                .await
+                .map_err(|e| e.to_string())?;
+            if artifact.is_null() {
+                return Err(format!(
+                    "the PXE has no artifact for class {}",
+                    class_id.to_hex()
+                ));
+            }
+            serde_json::from_value::<ContractArtifact>(artifact).map_err(|e| e.to_string())
+        };
+        let artifact = match fetched.await {
+            Ok(artifact) => Arc::new(artifact),
+            Err(e) => return Err(format!("{}; nor from the PXE: {}", local.unwrap_err(), e)),
+        };
+        artifact.selectors();
+        self.lock().insert(
+            key,
+            Entry {
+                artifact: artifact.clone(),
+                stamp: None,
+                mismatch: None,
+                fetched: true,
+            },
+        );
+        Ok(artifact)
+    }
+
+    /// Compares the contract's `currentContractClassId` with the class id
+    /// of its artifact, warning on a mismatch (and, with `OnUpgrade::Refuse`,
+    /// failing `resolve` for it until the file is updated). `None` when they
+    /// match, when the PXE has no instance there, or without `with_class_ids`.
+    pub async fn check_class<P: PxeApi + ?Sized>(
+        &self,
+        pxe: &P,
+        address: &str,
+    ) -> Result<Option<ClassMismatch>, String> {
+        let Some(class_ids) = &self.class_ids else {
+            return Ok(None);
+        };
+        let key = address.to_lowercase();
+        // Not `resolve`, which refuses a contract already found stale.
+        let loaded = self.lock().get(&key).map(|entry| entry.artifact.clone());
+        let artifact = match loaded {
+            Some(artifact) => artifact,
+            None => self.resolve(address)?,
+        };
+        let Some(on_chain) = current_class_id(pxe, address).await? else {
+            return Ok(None);
+        };
+
+        let local = class_ids.class_id(&artifact)?;
+        let mismatch = (on_chain != local).then(|| ClassMismatch {
+            address: address.to_string(),
+            on_chain,
+            local,
+        });
+        if let Some(mismatch) = &mismatch {
+            tracing::warn!("{}", mismatch);
+        }
+        if let Some(entry) = self.lock().get_mut(&key) {
+            if Arc::ptr_eq(&entry.artifact, &artifact) {
+                entry.mismatch = mismatch.clone();
+            }
+        }
+        Ok(mismatch)
+    }
+
+    /// Reloads the directory every `interval`, logging what changed.
+    pub async fn watch(self: Arc<Self>, interval: Duration) {
+        loop {
+            match self.reload() {
+                Ok(report) => {
+                    for key in &report.loaded {
+                        tracing::info!("Loaded artifact {}", key);
+                    }
+                    for key in &report.removed {
+                        tracing::info!("Dropped artifact {}", key);
+                    }
+                    for (key, e) in &report.failed {
+                        tracing::warn!("Cannot load artifact {}: {}", key, e);
+                    }
+                }
+                Err(e) => tracing::error!("Artifact reload failed: {}", e),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Entry>> {
+        self.cache.lock().expect("registry lock poisoned")
+    }
+}
+
+// `None` when the PXE has no instance at `address`.
+async fn current_class_id<P: PxeApi + ?Sized>(
+    pxe: &P,
+    address: &str,
+) -> Result<Option<Fr>, String> {
+    let address = AztecAddress::try_from(address)?;
+    let metadata = pxe
+        .get_contract_metadata(&address)
+        .await
+        .map_err(|e| e.to_string())?;
+    metadata["contractInstance"]["currentContractClassId"]
+        .as_str()
+        .map(Fr::try_from)
+        .transpose()
+}
+
+fn is_hex(key: &str) -> bool {
+    key.starts_with("0x") && key[2..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+// Taken before reading, so a write racing the load shows up next time.
+fn stamp_of(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+fn load(path: &Path) -> Result<Entry, Box<dyn std::error::Error>> {
+    let stamp = stamp_of(path);
+    let artifact = Arc::new(load_contract_artifact(path)?);
+    Ok(Entry {
+        artifact,
+        stamp,
+        mismatch: None,
+        fetched: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aztec_rpc_client::AztecRpcClient;
+    use crate::testing::MockPxe;
+    use serde_json::json;
+
+    fn write(dir: &Path, key: &str, name: &str, functions: &[&str]) {
+        let functions: Vec<_> = functions
+            .iter()
+            .map(|name| {
+                json!({
+                    "name": name,
+                    "parameters": [],
+                    "bytecode": "",
+                    "debugSymbols": "",
+                    "functionType": "public",
+                })
+            })
+            .collect();
+        let artifact = json!({
+            "name": name,
+            "functions": functions,
+            "nonDispatchPublicFunctions": [],
+            "storageLayout": {},
+            "notes": {},
+            "fileMap": {},
+        });
+        fs::write(dir.join(format!("{}.json", key)), artifact.to_string()).unwrap();
+    }
+
+    #[test]
+    fn test_reload_picks_up_changes() {
+        let dir = std::env::temp_dir().join(format!("registry-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        write(&dir, "0x0a", "Main", &["get"]);
+        write(&dir, "0x0b", "Token", &["mint"]);
+        let registry = ArtifactRegistry::new(&dir);
+
+        assert!(registry.get("Token").is_none());
+        let report = registry.reload().unwrap();
+        assert_eq!(report.loaded, ["0x0a", "0x0b"]);
+        assert_eq!(registry.get("Token").unwrap().functions.len(), 1);
+        assert_eq!(registry.reload().unwrap(), ReloadReport::default());
+
+        // A half-written file keeps the old artifact until it parses.
+        fs::write(dir.join("0x0a.json"), "{").unwrap();
+        let report = registry.reload().unwrap();
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(registry.resolve("0x0A").unwrap().functions.len(), 1);
+
+        write(&dir, "0x0a", "Main", &["get", "set"]);
+        fs::remove_file(dir.join("0x0b.json")).unwrap();
+        let report = registry.reload().unwrap();
+        assert_eq!(report.loaded, ["0x0a"]);
+        assert_eq!(report.removed, ["0x0b"]);
+        assert_eq!(registry.get("Main").unwrap().functions.len(), 2);
+        assert!(registry.get("Token").is_none());
+        assert!(registry.resolve("0x0b").is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Stands in for the real hash: the number of functions.
+    #[derive(Debug)]
+    struct FunctionCount;
+
+    impl ClassIdOf for FunctionCount {
+        fn class_id(&self, artifact: &ContractArtifact) -> Result<Fr, String> {
+            Ok(Fr::from(artifact.functions.len() as u64))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upgraded_class_refuses_until_artifact_updates() {
+        let dir = std::env::temp_dir().join(format!("registry-class-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        write(&dir, "0x0a", "Main", &["get"]);
+        let registry =
+            ArtifactRegistry::new(&dir).with_class_ids(Arc::new(FunctionCount), OnUpgrade::Refuse);
+        let mock = MockPxe::start().await.unwrap();
+        let pxe = AztecRpcClient::new(mock.url(), Some("pxe".to_string()));
+        let metadata = |class_id: u8| json!({ "contractInstance": { "currentContractClassId": Fr::from(class_id).to_hex() } });
+
+        // Upgraded on chain, after the first check, to a class with two
+        // functions.
+        mock.respond("pxe_getContractMetadata", metadata(1));
+        mock.respond("pxe_getContractMetadata", metadata(2));
+        assert_eq!(registry.check_class(&pxe, "0x0a").await.unwrap(), None);
+
+        let mismatch = registry.check_class(&pxe, "0x0a").await.unwrap().unwrap();
+        assert_eq!(
+            (mismatch.on_chain, mismatch.local),
+            (Fr::from(2u8), Fr::from(1u8))
+        );
+        assert!(registry
+            .resolve("0x0a")
+            .unwrap_err()
+            .contains("update its artifact"));
+
+        write(&dir, "0x0a", "Main", &["get", "set"]);
+        assert_eq!(registry.reload().unwrap().loaded, ["0x0a"]);
+        assert_eq!(registry.check_class(&pxe, "0x0a").await.unwrap(), None);
+        assert_eq!(registry.resolve("0x0a").unwrap().functions.len(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,393 @@
+use axum::body::Bytes;
+use axum::extract::rejection::BytesRejection;
+use axum::extract::DefaultBodyLimit;
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+use super::health;
+use super::protocol::{
+    BridgeRequest, BridgeResponse, CallRequest, ErrorCode, InterfaceRequest, ReceiptRequest,
+    StorageRequest,
+};
+use super::server::Bridge;
+
+/// Body of `POST /contracts/{address}/call`: the fields of a WebSocket
+/// `set`/`get`, minus the contract, which comes from the path. Calls are sent
+/// as transactions unless `simulate` is set. An `Idempotency-Key` header
+/// stands in for `idempotency_key`.
+#[derive(Debug, Default, Deserialize)]
+struct CallBody {
+    #[serde(flatten)]
+    call: CallRequest,
+    #[serde(default)]
+    simulate: bool,
+}
+
+/// HTTP routes over the same `Bridge::handle` the WebSocket protocol uses.
+/// Successful responses carry the `BridgeResponse` JSON; failures are
+/// `application/problem+json` bodies. Bodies over the bridge's
+/// `RequestLimits` are refused unread. A `traceparent` header continues the
+/// caller's trace. The health probes are served too.
+pub fn router(bridge: Arc<Bridge>) -> Router {
+    let max_body = bridge.config().limits.max_frame_bytes;
+    Router::new()
+        .route("/contracts/{address}/call", post(call))
+        .route("/contracts/{address}/storage/{variable}", get(storage))
+        .route("/contracts/{address}/interface", get(interface))
+        .route("/txs/{hash}/receipt", get(receipt))
+        .merge(health::routes())
+        .fallback(|| async {
+            problem(StatusCode::NOT_FOUND, ErrorCode::NotFound, "No such route.")
+        })
+        .method_not_allowed_fallback(|| async {
+            problem(
+                StatusCode::METHOD_NOT_ALLOWED,
+                ErrorCode::InvalidRequest,
+                "Method not allowed on this route.",
+            )
+        })
+        .layer(DefaultBodyLimit::max(max_body))
+        .with_state(bridge)
+}
+
+pub async fn serve(listener: TcpListener, bridge: Arc<Bridge>) -> std::io::Result<()> {
+    axum::serve(listener, router(bridge)).await
+}
+
+async fn call(
+    State(bridge): State<Arc<Bridge>>,
+    Path(address): Path<String>,
+    headers: HeaderMap,
+    body: Result<Bytes, BytesRejection>,
+) -> Response {
+    // Read and parsed by hand so a bad body gets a problem response, not
+    // axum's plain-text rejection.
+    let body = match body {
+        Ok(body) => body,
+        Err(rejection) if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+            return problem(
+                rejection.status(),
+                ErrorCode::PayloadTooLarge,
+                &rejection.body_text(),
+            )
+        }
+        Err(rejection) => {
+            return problem(
+                rejection.status(),
+                ErrorCode::InvalidRequest,
+                &rejection.body_text(),
+            )
+        }
+    };
+    if let Err((code, e)) = bridge.config().limits.check_frame(&body, true) {
+        return respond(BridgeResponse::failed(code, e));
+    }
+    let body = if body.is_empty() {
+        CallBody::default()
+    } else {
+        match serde_json::from_slice::<CallBody>(&body) {
+            Ok(body) => body,
+            Err(e) => {
+                return problem(
+                    StatusCode::BAD_REQUEST,
+                    ErrorCode::InvalidRequest,
+                    &format!("Invalid request: {}", e),
+                )
+            }
+        }
+    };
+
+    let idempotency_key = body.call.idempotency_key.clone().or_else(|| {
+        headers
+            .get("idempotency-key")
+            .and_then(|key| key.to_str().ok())
+            .map(str::to_string)
+    });
+    let call = CallRequest {
+        contract: Some(address),
+        idempotency_key,
+        ..body.call
+    };
+    let request = if body.simulate {
+        BridgeRequest::Get(call)
+    } else {
+        BridgeRequest::Set(call)
+    };
+    handle(&bridge, request, &headers).await
+}
+
+async fn storage(
+    State(bridge): State<Arc<Bridge>>,
+    Path((address, variable)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Response {
+    let request = BridgeRequest::Storage(StorageRequest {
+        contract: Some(address),
+        variable,
+    });
+    handle(&bridge, request, &headers).await
+}
+
+async fn interface(
+    State(bridge): State<Arc<Bridge>>,
+    Path(address): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let request = BridgeRequest::Interface(InterfaceRequest {
+        contract: Some(address),
+    });
+    handle(&bridge, request, &headers).await
+}
+
+async fn receipt(
+    State(bridge): State<Arc<Bridge>>,
+    Path(tx_hash): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    let request = BridgeRequest::Receipt(ReceiptRequest { tx_hash });
+    handle(&bridge, request, &headers).await
+}
+
+async fn handle(bridge: &Arc<Bridge>, request: BridgeRequest, headers: &HeaderMap) -> Response {
+    let traceparent = headers
+        .get("traceparent")
+        .and_then(|value| value.to_str().ok());
+    respond(bridge.handle_traced(request, traceparent).await)
+}
+
+fn respond(response: BridgeResponse) -> Response {
+    if response.success {
+        let status = match response.approval {
+            Some(_) => StatusCode::ACCEPTED,
+            None => StatusCode::OK,
+        };
+        return (status, Json(response)).into_response();
+    }
+
+    // Error code numbers are HTTP statuses.
+    let code = response.code.unwrap_or(ErrorCode::InvalidRequest);
+    let status = StatusCode::from_u16(code.number()).unwrap_or(StatusCode::BAD_GATEWAY);
+    problem(status, code, response.error.as_deref().unwrap_or_default())
+}
+
+/// RFC 7807 problem details, the error shape OpenAPI specs describe.
+fn problem(status: StatusCode, code: ErrorCode, detail: &str) -> Response {
+    let body = json!({
+        "type": "about:blank",
+        "title": status.canonical_reason().unwrap_or_default(),
+        "status": status.as_u16(),
+        "detail": detail,
+        "code": code,
+        "retryable": code.retryable(),
+    });
+    (
+        status,
+        [(header::CONTENT_TYPE, "application/problem+json")],
+        body.to_string(),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bridge::server::tests::{bridge_with_mock, CONTRACT};
+    use crate::fields::Fr;
+    use crate::testing::MockPxe;
+    use serde_json::Value;
+
+    async fn start() -> (String, MockPxe) {
+        let (bridge, mock) = bridge_with_mock(|_| {}).await;
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, bridge));
+        (url, mock)
+    }
+
+    async fn body(response: reqwest::Response) -> (StatusCode, Value) {
+        let status = StatusCode::from_u16(response.status().as_u16()).unwrap();
+        (status, response.json().await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_reads_storage_variable_by_name() {
+        let (url, mock) = start().await;
+        mock.respond("pxe_getPublicStorageAt", json!(Fr::from(214u8)));
+
+        let response = reqwest::get(format!("{}/contracts/{}/storage/just_field", url, CONTRACT))
+            .await
+            .unwrap();
+        let (status, body) = body(response).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["value"], json!(Fr::from(214u8)));
+        assert_eq!(
+            mock.requests()[0]["params"],
+            json!([CONTRACT, Fr::from(2u8)])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_serves_contract_interface() {
+        let (url, mock) = start().await;
+
+        let response = reqwest::get(format!("{}/contracts/{}/interface", url, CONTRACT))
+            .await
+            .unwrap();
+        let (status, body) = body(response).await;
+
+        assert_eq!(status, StatusCode::OK);
+        let interface = &body["value"];
+        assert_eq!(interface["name"], "Main");
+        let get = interface["functions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|f| f["name"] == "get_just_field")
+            .unwrap();
+        assert_eq!(get["is_static"], true);
+        assert_eq!(get["return_types"], json!(["field"]));
+        assert_eq!(interface["storage"][1]["type"], "PublicMutable<Field>");
+        assert!(mock.requests().is_empty());
+
+        let response = reqwest::get(format!("{}/contracts/0x01/interface", url))
+            .await
+            .unwrap();
+        assert_eq!(response.status().as_u16(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_call_sends_or_simulates() {
+        let (url, mock) = start().await;
+        mock.respond("pxe_simulateTx", json!({ "privateExecutionResult": {} }));
+        mock.respond("pxe_simulateTx", json!({ "v": 1 }));
+        let client = reqwest::Client::new();
+        let endpoint = format!("{}/contracts/{}/call", url, CONTRACT);
+
+        let sent = client
+            .post(&endpoint)
+            .json(&json!({ "value": 214 }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            body(sent).await,
+            (
+                StatusCode::OK,
+                json!({ "success": true, "txHash": "0xfeed" })
+            )
+        );
+
+        for _ in 0..2 {
+            let retried = client
+                .post(&endpoint)
+                .header("Idempotency-Key", "retry-1")
+                .json(&json!({ "value": 5 }))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(body(retried).await.0, StatusCode::OK);
+        }
+        let sends = mock
+            .requests()
+            .iter()
+            .filter(|r| r["method"] == "pxe_sendTx")
+            .count();
+        assert_eq!(sends, 2);
+
+        let simulated = client
+            .post(&endpoint)
+            .json(&json!({ "simulate": true }))
+            .send()
+            .await
+            .unwrap();
+        let (status, simulated) = body(simulated).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(simulated["value"], json!({ "v": 1 }));
+    }
+
+    #[tokio::test]
+    async fn test_errors_are_problem_details() {
+        let (url, _mock) = start().await;
+        let client = reqwest::Client::new();
+
+        let missing = client
+            .get(format!("{}/contracts/{}/storage/nope", url, CONTRACT))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(
+            missing.headers()[header::CONTENT_TYPE],
+            "application/problem+json"
+        );
+        let (status, problem) = body(missing).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(problem["status"], json!(404));
+        assert_eq!(problem["code"], json!("not_found"));
+        assert!(problem["detail"].as_str().unwrap().contains("'nope'"));
+
+        let malformed = client
+            .post(format!("{}/contracts/{}/call", url, CONTRACT))
+            .body("{")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(body(malformed).await.0, StatusCode::BAD_REQUEST);
+
+        let oversized = client
+            .post(format!("{}/contracts/{}/call", url, CONTRACT))
+            .body(format!("{{\"value\": \"{}\"}}", "1".repeat(70_000)))
+            .send()
+            .await
+            .unwrap();
+        let (status, problem) = body(oversized).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(problem["code"], json!("payload_too_large"));
+        let nested = client
+            .post(format!("{}/contracts/{}/call", url, CONTRACT))
+            .body(format!(
+                "{{\"value\": {}1{}}}",
+                "[".repeat(100),
+                "]".repeat(100)
+            ))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(body(nested).await.0, StatusCode::BAD_REQUEST);
+
+        // The mock PXE has no receipt queued, so the RPC call fails.
+        let receipt = client
+            .get(format!("{}/txs/0x01/receipt", url))
+            .send()
+            .await
+            .unwrap();
+        let (status, problem) = body(receipt).await;
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert_eq!(problem["code"], json!("upstream"));
+        assert_eq!(problem["retryable"], json!(true));
+    }
+
+    #[tokio::test]
+    async fn test_receipt_is_fetched_from_pxe() {
+        let (url, mock) = start().await;
+        mock.respond(
+            "pxe_getTxReceipt",
+            json!({ "txHash": "0x01", "status": "success", "blockNumber": 7 }),
+        );
+
+        let response = reqwest::get(format!("{}/txs/0x01/receipt", url))
+            .await
+            .unwrap();
+        let (status, body) = body(response).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["value"]["blockNumber"], json!(7));
+        assert_eq!(mock.requests()[0]["params"], json!(["0x01"]));
+    }
+}
@@ -0,0 +1,1925 @@
+use futures_util::future::{self, BoxFuture, FutureExt};
+use futures_util::stream::SplitStream;
+use futures_util::StreamExt;
+use serde_json::json;
+use std::collections::HashSet;
+use std::env;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use tokio::time::{sleep_until, Instant};
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{accept_async_with_config, WebSocketStream};
+use tracing::Instrument;
+
+use super::approvals::{ApprovalPolicy, ApprovalProgress, Approvals, PendingApproval};
+use super::auth::{AuthPolicy, Authenticator};
+use super::cache::{CacheKey, ValueCache};
+use super::health::{Check, Readiness};
+use super::idempotency::{Claim, IdempotencyKeys};
+use super::limits::RequestLimits;
+use super::outbound::{Frame, OutboundStats, SendQueue, SendQueueConfig};
+use super::protocol::{
+    Approve, BridgeEvent, BridgeRequest, BridgeResponse, CallRequest, ContractEvent, ErrorCode,
+    EventSubscription, Feature, Framing, InterfaceRequest, Negotiated, ReceiptRequest,
+    StorageRequest, Subscribe, TraceContext,
+};
+use super::registry::ArtifactRegistry;
+use super::{grpc, health, rest};
+use crate::contract::{ContractFunctionInteraction, SimulateOptions};
+use crate::encoder::{decode_event, get_function_artifact, AbiType, ContractArtifact};
+use crate::error::AztecError;
+use crate::fields::Fr;
+use crate::inspect::ArtifactReport;
+use crate::pxe_api::PxeApi;
+use crate::state::StateStore;
+use crate::supervisor::{RestartPolicy, Supervisor};
+use crate::timings::CallTimings;
+use crate::tx_request::DEFAULT_ORIGIN;
+use crate::watcher::{BlockWatcher, ContractLog, WatchTarget};
+
+const DEFAULT_SET_FUNCTION: &str = "set_just_field";
+const DEFAULT_GET_FUNCTION: &str = "get_just_field";
+
+#[derive(Debug, Clone)]
+pub struct BridgeConfig {
+    pub listen_addr: String,
+    /// Where `run` also serves the REST API; off when unset.
+    pub rest_addr: Option<String>,
+    /// Where `run` also serves the gRPC API (`proto/bridge.proto`); off when unset.
+    pub grpc_addr: Option<String>,
+    /// Where `run` serves just `/healthz` and `/readyz`, for when the REST
+    /// API (which has them too) is off or public.
+    pub health_addr: Option<String>,
+    /// How long `/readyz` waits for the PXE to answer.
+    pub ready_timeout: Duration,
+    pub artifact_dir: PathBuf,
+    /// How often `run` rescans `artifact_dir` for new or changed artifacts.
+    pub artifact_reload_interval: Duration,
+    pub default_contract: Option<String>,
+    pub sender: String,
+    /// Connections that send nothing (not even a ping) for this long are closed.
+    pub idle_timeout: Duration,
+    /// Cached `get` results older than this are reported as stale.
+    pub cache_ttl: Duration,
+    /// How often the block watcher behind `subscribe` polls the PXE.
+    pub watch_interval: Duration,
+    /// `StateStore` file opened by `run`; state is memory-only without it.
+    pub state_path: Option<PathBuf>,
+    /// When set, `set` requests wait for operator approvals before sending.
+    pub approvals: Option<ApprovalPolicy>,
+    /// When set, `set` requests must be signed by one of these operators.
+    pub auth: Option<AuthPolicy>,
+    /// How long a `set`'s idempotency key is remembered.
+    pub idempotency_ttl: Duration,
+    /// Size and nesting bounds on incoming requests.
+    pub limits: RequestLimits,
+    /// Bound on each connection's outgoing frames, and what happens to
+    /// pushes past it.
+    pub send_queue: SendQueueConfig,
+}
+
+impl BridgeConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let approvals = match env::var("BRIDGE_APPROVAL_OPERATORS") {
+            Ok(operators) => Some(ApprovalPolicy::parse(
+                &operators,
+                env::var("BRIDGE_APPROVAL_THRESHOLD").ok().as_deref(),
+            )?),
+            Err(_) => None,
+        };
+        let auth = match env::var("BRIDGE_AUTH_OPERATORS") {
+            Ok(operators) => {
+                let policy = AuthPolicy::parse(&operators)?;
+                Some(match env::var("BRIDGE_AUTH_ACCESS") {
+                    Ok(access) => policy.with_access(&access)?,
+                    Err(_) => policy,
+                })
+            }
+            Err(_) => None,
+        };
+
+        Ok(BridgeConfig {
+            listen_addr: env::var("BRIDGE_ADDR").unwrap_or_else(|_| "127.0.0.1:3002".to_string()),
+            rest_addr: env::var("BRIDGE_REST_ADDR").ok(),
+            grpc_addr: env::var("BRIDGE_GRPC_ADDR").ok(),
+            health_addr: env::var("BRIDGE_HEALTH_ADDR").ok(),
+            ready_timeout: Duration::from_millis(env_u64("BRIDGE_READY_TIMEOUT_MS", 1_000)),
+            artifact_dir: env::var("ARTIFACT_DIR")
+                .unwrap_or_else(|_| "artifacts".to_string())
+                .into(),
+            artifact_reload_interval: Duration::from_secs(env_u64("ARTIFACT_RELOAD_SECS", 2)),
+            default_contract: env::var("DEFAULT_CONTRACT").ok(),
+            sender: env::var("SENDER_ADDRESS").unwrap_or_else(|_| DEFAULT_ORIGIN.to_string()),
+            idle_timeout: Duration::from_secs(env_u64("BRIDGE_IDLE_TIMEOUT_SECS", 60)),
+            cache_ttl: Duration::from_secs(env_u64("BRIDGE_CACHE_TTL_SECS", 10)),
+            watch_interval: Duration::from_secs(env_u64("BRIDGE_WATCH_INTERVAL_SECS", 2)),
+            state_path: env::var("BRIDGE_STATE_PATH").ok().map(PathBuf::from),
+            approvals,
+            auth,
+            idempotency_ttl: Duration::from_secs(env_u64("BRIDGE_IDEMPOTENCY_TTL_SECS", 86_400)),
+            limits: RequestLimits::from_env()?,
+            send_queue: SendQueueConfig::from_env()?,
+        })
+    }
+}
+
+fn env_u64(name: &str, default: u64) -> u64 {
+    env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Routes bridge requests to contract calls through the PXE, or anything
+/// else that implements `PxeApi`.
+pub struct Bridge {
+    config: BridgeConfig,
+    pxe: Arc<dyn PxeApi>,
+    registry: Arc<ArtifactRegistry>,
+    cache: ValueCache,
+    watcher: Arc<BlockWatcher>,
+    approvals: Option<Approvals>,
+    auth: Option<Authenticator>,
+    idempotency: IdempotencyKeys,
+    store: Arc<StateStore>,
+    outbound: Arc<OutboundStats>,
+}
+
+impl Bridge {
+    pub fn new(config: BridgeConfig, pxe: impl PxeApi + 'static) -> Self {
+        let pxe: Arc<dyn PxeApi> = Arc::new(pxe);
+        let registry = Arc::new(ArtifactRegistry::new(config.artifact_dir.clone()));
+        let cache = ValueCache::new(config.cache_ttl);
+        let watcher = Arc::new(BlockWatcher::new(pxe.clone(), config.watch_interval));
+        let store = Arc::new(StateStore::in_memory());
+        let idempotency = IdempotencyKeys::new(store.clone(), config.idempotency_ttl);
+        let bridge = Bridge {
+            config,
+            pxe,
+            registry,
+            cache,
+            watcher,
+            approvals: None,
+            auth: None,
+            idempotency,
+            store: store.clone(),
+            outbound: Arc::new(OutboundStats::default()),
+        };
+        bridge.with_store(store)
+    }
+
+    /// Keeps pending approvals, used nonces and idempotency keys in `store`
+    /// instead of memory.
+    pub fn with_store(mut self, store: Arc<StateStore>) -> Self {
+        self.idempotency = IdempotencyKeys::new(store.clone(), self.config.idempotency_ttl);
+        self.approvals = self
+            .config
+            .approvals
+            .clone()
+            .map(|policy| Approvals::new(policy, store.clone()));
+        self.auth = self
+            .config
+            .auth
+            .clone()
+            .map(|policy| Authenticator::new(policy, store.clone()));
+        self.store = store;
+        self
+    }
+
+    pub fn config(&self) -> &BridgeConfig {
+        &self.config
+    }
+
+    /// Approvals still waiting for signatures (empty when approvals are off).
+    pub fn pending_approvals(&self) -> Result<Vec<PendingApproval>, String> {
+        match &self.approvals {
+            Some(approvals) => approvals.pending(),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Not rescanning its directory until `run` starts it.
+    pub fn registry(&self) -> &Arc<ArtifactRegistry> {
+        &self.registry
+    }
+
+    /// The PXE's call timings, when it keeps them.
+    pub fn call_timings(&self) -> Option<&Arc<CallTimings>> {
+        self.pxe.call_timings()
+    }
+
+    /// Pushes WebSocket connections dropped for reading too slowly.
+    pub fn outbound_stats(&self) -> &Arc<OutboundStats> {
+        &self.outbound
+    }
+
+    /// Not polling until `run` starts it (or a test calls `poll`).
+    pub fn watcher(&self) -> &Arc<BlockWatcher> {
+        &self.watcher
+    }
+
+    /// Whether the bridge can serve calls: the PXE answers within
+    /// `ready_timeout`, the state store takes writes, and the artifact
+    /// directory has been read.
+    pub async fn readiness(&self) -> Readiness {
+        let timeout = self.config.ready_timeout;
+        let pxe = match tokio::time::timeout(timeout, self.pxe.get_block_number()).await {
+            Ok(Ok(_)) => Check::ok(),
+            Ok(Err(e)) => Check::failed(e.to_string()),
+            Err(_) => Check::failed(format!("No answer within {:?}", timeout)),
+        };
+        let state_store = self
+            .store
+            .check_writable()
+            .map_or_else(Check::failed, |_| Check::ok());
+        let artifacts = if self.registry.is_loaded() {
+            Check::ok()
+        } else {
+            Check::failed(format!(
+                "{} not read yet",
+                self.config.artifact_dir.display()
+            ))
+        };
+        Readiness::new(pxe, state_store, artifacts)
+    }
+
+    pub async fn handle_text(self: &Arc<Self>, text: &str) -> BridgeResponse {
+        if let Err((code, e)) = self.config.limits.check_frame(text.as_bytes(), true) {
+            return BridgeResponse::failed(code, e);
+        }
+        match serde_json::from_str::<BridgeRequest>(text) {
+            Ok(request) => {
+                let trace: TraceContext = serde_json::from_str(text).unwrap_or_default();
+                self.handle_traced(request, trace.traceparent.as_deref())
+                    .await
+            }
+            Err(e) => BridgeResponse::error(format!("Invalid request: {}", e)),
+        }
+    }
+
+    pub async fn handle(self: &Arc<Self>, request: BridgeRequest) -> BridgeResponse {
+        self.handle_traced(request, None).await
+    }
+
+    /// `handle` in a `bridge.request` span, the root of the spans for the
+    /// RPC calls it makes. With the `otel` feature the span continues the
+    /// trace in `traceparent`, when given.
+    pub async fn handle_traced(
+        self: &Arc<Self>,
+        request: BridgeRequest,
+        traceparent: Option<&str>,
+    ) -> BridgeResponse {
+        self.handle_for(request, None, traceparent).await
+    }
+
+    /// `handle_traced` for a subscriber that can be pushed to: its
+    /// `subscribe`s are added to `subscriptions`. Without one they are
+    /// refused.
+    #[cfg_attr(not(feature = "otel"), allow(unused_variables))]
+    pub async fn handle_for(
+        self: &Arc<Self>,
+        request: BridgeRequest,
+        subscriptions: Option<&mut Subscriptions>,
+        traceparent: Option<&str>,
+    ) -> BridgeResponse {
+        let span = tracing::info_span!(
+            "bridge.request",
+            action = request.action(),
+            success = tracing::field::Empty,
+            tx_hash = tracing::field::Empty,
+        );
+        #[cfg(feature = "otel")]
+        if let Some(traceparent) = traceparent {
+            crate::telemetry::set_parent(&span, traceparent);
+        }
+        let response = self
+            .dispatch(request, subscriptions)
+            .instrument(span.clone())
+            .await;
+        span.record("success", response.success);
+        if let Some(tx_hash) = &response.tx_hash {
+            span.record("tx_hash", tx_hash.as_str());
+        }
+        response
+    }
+
+    async fn dispatch(
+        self: &Arc<Self>,
+        request: BridgeRequest,
+        subscriptions: Option<&mut Subscriptions>,
+    ) -> BridgeResponse {
+        match request {
+            BridgeRequest::Hello(hello) => {
+                // Signed sets are only on offer, and then mandatory, when
+                // the bridge checks them.
+                let auth: &[Feature] = match self.auth {
+                    Some(_) => &[Feature::Auth],
+                    None => &[],
+                };
+                let supports = [&[Feature::BinaryFraming, Feature::Subscriptions], auth].concat();
+                match hello.negotiate(&supports, auth) {
+                    Ok((framing, protocol)) => BridgeResponse::welcome(framing, protocol),
+                    Err(e) => BridgeResponse::error(e),
+                }
+            }
+            BridgeRequest::Set(call) => match call.idempotency_key.clone() {
+                Some(key) => self.set_once(&key, call).await,
+                None => self.submit(call).await,
+            },
+            BridgeRequest::Get(call) => self.get(call).await,
+            BridgeRequest::Subscribe(subscribe) => match subscriptions {
+                Some(subscriptions) => self.subscribe(subscriptions, subscribe).await,
+                None => BridgeResponse::error("Subscriptions need a connection to push to."),
+            },
+            BridgeRequest::Approve(approve) => self.approve(approve).await,
+            BridgeRequest::Storage(storage) => self.storage(storage).await,
+            BridgeRequest::Receipt(receipt) => self.receipt(receipt).await,
+            BridgeRequest::Interface(request) => self.interface(request).await,
+        }
+    }
+
+    // The signature is checked before the key is claimed, so only operators
+    // can claim keys, but the nonce is only consumed in `submit`: a retry
+    // reuses the first attempt's, and gets its recorded response.
+    async fn set_once(&self, key: &str, call: CallRequest) -> BridgeResponse {
+        if let Some(auth) = &self.auth {
+            if let Err(e) = auth.authorize(&call, self.target(&call), unix_now()) {
+                return BridgeResponse::failed(ErrorCode::Unauthorized, e);
+            }
+        }
+        match self.idempotency.claim(key, &call, unix_now()) {
+            Ok(Claim::New) => {}
+            Ok(Claim::Replay(response)) => return *response,
+            Err((code, e)) => return BridgeResponse::failed(code, e),
+        }
+        let response = tokio::select! {
+            response = self.submit(call) => response,
+            () = self.idempotency.hold(key, unix_now) => unreachable!("claims are held until dropped"),
+        };
+        if let Err(e) = self.idempotency.complete(key, &response) {
+            tracing::error!("Could not record idempotency key {}: {}", key, e);
+        }
+        response
+    }
+
+    async fn submit(&self, call: CallRequest) -> BridgeResponse {
+        if let Err(e) = self.authenticate(&call) {
+            return BridgeResponse::failed(ErrorCode::Unauthorized, e);
+        }
+        match &self.approvals {
+            Some(approvals) => self.propose(approvals, call).await,
+            None => self.set(call).await,
+        }
+    }
+
+    fn authenticate(&self, call: &CallRequest) -> Result<(), String> {
+        let Some(auth) = &self.auth else {
+            return Ok(());
+        };
+        auth.check(call, self.target(call), unix_now()).map(|_| ())
+    }
+
+    // Access is checked on the contract and function the call resolves to;
+    // a call with neither a contract nor a default fails later anyway.
+    fn target<'c>(&'c self, call: &'c CallRequest) -> Option<(&'c str, &'c str)> {
+        let contract = call
+            .contract
+            .as_deref()
+            .or(self.config.default_contract.as_deref())?;
+        let function = call.function.as_deref().unwrap_or(DEFAULT_SET_FUNCTION);
+        Some((contract, function))
+    }
+
+    async fn set(&self, call: CallRequest) -> BridgeResponse {
+        let interaction = match self.interaction(&call, DEFAULT_SET_FUNCTION).await {
+            Ok(interaction) => interaction,
+            Err((code, e)) => return BridgeResponse::failed(code, e),
+        };
+        // Nothing changes on chain, so cached values stay fresh.
+        if self.pxe.dry_run() {
+            return match interaction.dry_run().await {
+                Ok(effects) => BridgeResponse::would_send(effects),
+                Err(e) => upstream_failure(&*e),
+            };
+        }
+        match interaction.send().await {
+            Ok(tx_hash) => {
+                self.cache
+                    .invalidate_contract(interaction.contract_address());
+                BridgeResponse::sent(tx_hash)
+            }
+            Err(e) => upstream_failure(&*e),
+        }
+    }
+
+    // Bad calls are rejected up front so operators never sign them.
+    async fn propose(&self, approvals: &Approvals, call: CallRequest) -> BridgeResponse {
+        let checked = self
+            .interaction(&call, DEFAULT_SET_FUNCTION)
+            .await
+            .and_then(|interaction| {
+                interaction
+                    .encode_args()
+                    .map_err(|e| (ErrorCode::Encoding, e))
+            });
+        if let Err((code, e)) = checked {
+            return BridgeResponse::failed(code, e);
+        }
+
+        match approvals.propose(call) {
+            Ok(status) => BridgeResponse::awaiting_approval(status),
+            Err(e) => BridgeResponse::error(e),
+        }
+    }
+
+    async fn approve(&self, approve: Approve) -> BridgeResponse {
+        let Some(approvals) = &self.approvals else {
+            return BridgeResponse::error("Approvals are not enabled on this bridge.");
+        };
+
+        match approvals.approve(&approve.id, &approve.operator, &approve.signature) {
+            Ok(ApprovalProgress::Pending(status)) => BridgeResponse::awaiting_approval(status),
+            Ok(ApprovalProgress::Ready(call)) => {
+                let response = self.set(call).await;
+                let settled = if response.success {
+                    approvals.complete(&approve.id)
+                } else {
+                    approvals.release(&approve.id)
+                };
+                if let Err(e) = settled {
+                    tracing::error!("Could not settle approval {}: {}", approve.id, e);
+                }
+                response
+            }
+            Err(e) => BridgeResponse::error(e),
+        }
+    }
+
+    async fn get(self: &Arc<Self>, call: CallRequest) -> BridgeResponse {
+        let interaction = match self.interaction(&call, DEFAULT_GET_FUNCTION).await {
+            Ok(interaction) => interaction,
+            Err((code, e)) => return BridgeResponse::failed(code, e),
+        };
+        let key = CacheKey::new(
+            interaction.contract_address(),
+            call.function.as_deref().unwrap_or(DEFAULT_GET_FUNCTION),
+            &call.resolved_args(),
+        );
+
+        if !call.force_refresh {
+            if let Some((value, stale)) = self.cache.get(&key) {
+                if self.cache.begin_refresh(&key) {
+                    let bridge = self.clone();
+                    tokio::spawn(async move { bridge.refresh(call, key).await });
+                }
+                return BridgeResponse::value(value, stale);
+            }
+        }
+
+        match interaction.simulate(SimulateOptions::default()).await {
+            Ok(value) => {
+                self.cache.store(key, value.clone());
+                BridgeResponse::value(value, false)
+            }
+            Err(e) => upstream_failure(&*e),
+        }
+    }
+
+    async fn storage(&self, request: StorageRequest) -> BridgeResponse {
+        let (contract, artifact) = match self.resolve(request.contract.as_deref()).await {
+            Ok(resolved) => resolved,
+            Err((code, e)) => return BridgeResponse::failed(code, e),
+        };
+        let slot = match artifact.storage_layout.get(&request.variable) {
+            Some(layout) => match Fr::try_from(layout.slot.as_str()) {
+                Ok(slot) => slot,
+                Err(e) => return BridgeResponse::error(e),
+            },
+            None => {
+                return BridgeResponse::failed(
+                    ErrorCode::NotFound,
+                    format!(
+                        "Contract {} has no storage variable '{}'.",
+                        contract, request.variable
+                    ),
+                )
+            }
+        };
+
+        match self.pxe.get_public_storage_at(&contract, &slot).await {
+            Ok(value) => BridgeResponse::value(json!(value), false),
+            Err(e) => upstream_failure(&e),
+        }
+    }
+
+    async fn receipt(&self, request: ReceiptRequest) -> BridgeResponse {
+        match self.pxe.get_tx_receipt(&request.tx_hash).await {
+            Ok(receipt) => BridgeResponse::value(receipt, false),
+            Err(e) => upstream_failure(&e),
+        }
+    }
+
+    async fn interface(&self, request: InterfaceRequest) -> BridgeResponse {
+        match self.resolve(request.contract.as_deref()).await {
+            Ok((_, artifact)) => {
+                BridgeResponse::value(json!(ArtifactReport::from_artifact(&artifact)), false)
+            }
+            Err((code, e)) => BridgeResponse::failed(code, e),
+        }
+    }
+
+    async fn refresh(&self, call: CallRequest, key: CacheKey) {
+        let result = match self.interaction(&call, DEFAULT_GET_FUNCTION).await {
+            Ok(interaction) => interaction
+                .simulate(SimulateOptions::default())
+                .await
+                .map_err(|e| e.to_string()),
+            Err((_, e)) => Err(e),
+        };
+
+        match result {
+            Ok(value) => self.cache.store(key, value),
+            Err(e) => {
+                tracing::warn!(
+                    "Background refresh of {}.{} failed: {}",
+                    key.contract,
+                    key.function,
+                    e
+                );
+                self.cache.end_refresh(&key);
+            }
+        }
+    }
+
+    /// The named contract (or the default one) and its artifact, from
+    /// `artifact_dir` or else the PXE.
+    async fn resolve(
+        &self,
+        contract: Option<&str>,
+    ) -> Result<(String, Arc<ContractArtifact>), (ErrorCode, String)> {
+        let contract = contract
+            .or(self.config.default_contract.as_deref())
+            .ok_or_else(|| {
+                (
+                    ErrorCode::InvalidRequest,
+                    "No contract given and no default contract configured.".to_string(),
+                )
+            })?;
+        let artifact = self
+            .registry
+            .resolve_or_fetch(&*self.pxe, contract)
+            .await
+            .map_err(|e| (ErrorCode::NotFound, e))?;
+        Ok((contract.to_string(), artifact))
+    }
+
+    async fn subscribe(
+        &self,
+        subscriptions: &mut Subscriptions,
+        subscribe: Subscribe,
+    ) -> BridgeResponse {
+        match subscribe {
+            Subscribe::Target { target } => {
+                if subscriptions.targets.insert(target.clone()) {
+                    self.watcher.watch(target);
+                }
+            }
+            Subscribe::Events { events } => {
+                if !subscriptions
+                    .events
+                    .iter()
+                    .any(|f| f.subscription == events)
+                {
+                    match self.event_filter(&events).await {
+                        Ok(filter) => {
+                            self.watcher.watch_logs(filter.address.clone());
+                            subscriptions.events.push(filter);
+                        }
+                        Err((code, e)) => return BridgeResponse::failed(code, e),
+                    }
+                }
+            }
+        }
+        BridgeResponse::ok()
+    }
+
+    async fn event_filter(
+        &self,
+        events: &EventSubscription,
+    ) -> Result<EventFilter, (ErrorCode, String)> {
+        let (contract, artifact) = self.resolve(Some(&events.contract)).await?;
+        let address =
+            Fr::try_from(contract.as_str()).map_err(|e| (ErrorCode::InvalidRequest, e))?;
+        let abi = artifact
+            .events()
+            .iter()
+            .find(|abi| match abi {
+                AbiType::Struct { path, .. } => {
+                    *path == events.event || path.rsplit("::").next() == Some(&events.event)
+                }
+                _ => false,
+            })
+            .cloned()
+            .ok_or_else(|| {
+                let e = format!("{} has no event '{}'", artifact.name, events.event);
+                (ErrorCode::NotFound, e)
+            })?;
+        Ok(EventFilter {
+            subscription: events.clone(),
+            address,
+            abi,
+        })
+    }
+
+    async fn interaction(
+        &self,
+        call: &CallRequest,
+        default_function: &str,
+    ) -> Result<ContractFunctionInteraction<'_, dyn PxeApi>, (ErrorCode, String)> {
+        let (contract, artifact) = self.resolve(call.contract.as_deref()).await?;
+        let function = get_function_artifact(
+            &artifact,
+            call.function.as_deref().unwrap_or(default_function),
+        )
+        .map_err(|e| (ErrorCode::NotFound, e))?;
+        let args = call.resolved_args();
+        self.config.limits.check_args(&function.parameters, &args)?;
+
+        Ok(ContractFunctionInteraction::new(
+            &*self.pxe,
+            self.config.sender.clone(),
+            contract,
+            function.to_abi(),
+            args,
+        ))
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("clock is after 1970")
+        .as_secs()
+}
+
+/// Sorts a failed PXE call into what the client can act on: arguments that
+/// don't encode, a PXE that can't be reached, a tx that reverts in
+/// simulation, or anything else the PXE refused.
+fn upstream_failure(error: &(dyn std::error::Error + 'static)) -> BridgeResponse {
+    let message = error.to_string();
+    match error.downcast_ref::<AztecError>() {
+        Some(AztecError::Encoding(_)) => BridgeResponse::failed(ErrorCode::Encoding, message),
+        Some(AztecError::Transport(_) | AztecError::Protocol(_) | AztecError::Timeout(_)) => {
+            BridgeResponse::failed(ErrorCode::PxeUnavailable, message)
+        }
+        Some(AztecError::Rpc { error, .. }) => {
+            let reason = error["message"].as_str().unwrap_or_default().to_lowercase();
+            let code = if reason.contains("revert") || reason.contains("assertion failed") {
+                ErrorCode::TxReverted
+            } else {
+                ErrorCode::Upstream
+            };
+            BridgeResponse::failed_with_details(code, message, error.clone())
+        }
+        Some(AztecError::Simulation { error, .. }) => {
+            BridgeResponse::failed_with_details(ErrorCode::TxReverted, message, error.clone())
+        }
+        _ => BridgeResponse::failed(ErrorCode::Upstream, message),
+    }
+}
+
+/// Serves the bridge until Ctrl-C, or until a task fails for good. Its
+/// servers, block watcher and artifact reloader run under `supervisor`,
+/// alongside whatever the caller already added to it.
+pub async fn run(
+    config: BridgeConfig,
+    pxe: impl PxeApi + 'static,
+    mut supervisor: Supervisor,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let store = match &config.state_path {
+        Some(path) => StateStore::open(path)?,
+        None => StateStore::in_memory(),
+    };
+    let bridge = Arc::new(Bridge::new(config, pxe).with_store(Arc::new(store)));
+    let pending = bridge.pending_approvals()?;
+    if !pending.is_empty() {
+        println!("{} set requests are waiting for approval", pending.len());
+    }
+
+    let watcher = bridge.watcher().clone();
+    supervisor.add("Block watcher", RestartPolicy::default(), move || {
+        let watcher = watcher.clone();
+        async move {
+            watcher.run().await;
+            Ok(())
+        }
+    });
+    let registry = bridge.registry().clone();
+    let interval = bridge.config().artifact_reload_interval;
+    supervisor.add("Artifact reloader", RestartPolicy::default(), move || {
+        let registry = registry.clone();
+        async move {
+            registry.watch(interval).await;
+            Ok(())
+        }
+    });
+
+    let listener = TcpListener::bind(&bridge.config().listen_addr).await?;
+    println!("Bridge listening on ws://{}", listener.local_addr()?);
+    add_server(&mut supervisor, "Bridge", listener, &bridge, serve)?;
+    if let Some(addr) = bridge.config().rest_addr.clone() {
+        let rest_listener = TcpListener::bind(&addr).await?;
+        println!(
+            "REST API listening on http://{}",
+            rest_listener.local_addr()?
+        );
+        add_server(
+            &mut supervisor,
+            "REST API",
+            rest_listener,
+            &bridge,
+            rest::serve,
+        )?;
+    }
+    if let Some(addr) = bridge.config().health_addr.clone() {
+        let health_listener = TcpListener::bind(&addr).await?;
+        println!(
+            "Health probes listening on http://{}",
+            health_listener.local_addr()?
+        );
+        add_server(
+            &mut supervisor,
+            "Health probes",
+            health_listener,
+            &bridge,
+            health::serve,
+        )?;
+    }
+    if let Some(addr) = bridge.config().grpc_addr.clone() {
+        let grpc_listener = TcpListener::bind(&addr).await?;
+        println!("gRPC API listening on {}", grpc_listener.local_addr()?);
+        add_server(
+            &mut supervisor,
+            "gRPC API",
+            grpc_listener,
+            &bridge,
+            grpc::serve,
+        )?;
+    }
+
+    let shutdown = async {
+        let _ = tokio::signal::ctrl_c().await;
+        println!("Shutting down");
+    };
+    Ok(supervisor.run(shutdown).await?)
+}
+
+// Supervises a server on `listener`, bound up front so a bad address fails
+// at startup. Restarts bind its address again.
+fn add_server<F, Fut, E>(
+    supervisor: &mut Supervisor,
+    name: &str,
+    listener: TcpListener,
+    bridge: &Arc<Bridge>,
+    serve: F,
+) -> std::io::Result<()>
+where
+    F: Fn(TcpListener, Arc<Bridge>) -> Fut + Copy + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), E>> + Send + 'static,
+    E: std::fmt::Display,
+{
+    let addr = listener.local_addr()?;
+    let first = Mutex::new(Some(listener));
+    let bridge = bridge.clone();
+    supervisor.add(name, RestartPolicy::default(), move || {
+        let first = first.lock().expect("listener lock poisoned").take();
+        let bridge = bridge.clone();
+        async move {
+            let listener = match first {
+                Some(listener) => listener,
+                None => TcpListener::bind(addr).await.map_err(|e| e.to_string())?,
+            };
+            serve(listener, bridge).await.map_err(|e| e.to_string())
+        }
+    });
+    Ok(())
+}
+
+pub async fn serve(listener: TcpListener, bridge: Arc<Bridge>) -> std::io::Result<()> {
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let bridge = bridge.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(bridge, stream).await {
+                tracing::error!("Connection {} closed with error: {}", peer, e);
+            }
+        });
+    }
+}
+
+/// An events `subscribe`, resolved against the contract's artifact.
+#[derive(Debug, Clone)]
+struct EventFilter {
+    subscription: EventSubscription,
+    address: Fr,
+    abi: AbiType,
+}
+
+impl EventFilter {
+    /// `None` unless `log` is this contract emitting this event.
+    fn decode(&self, log: &ContractLog) -> Option<Result<ContractEvent, String>> {
+        let AbiType::Struct { path, .. } = &self.abi else {
+            return None;
+        };
+        if log.contract != self.address {
+            return None;
+        }
+        let payload = decode_event(&self.abi, &log.fields)?;
+        Some(payload.map(|payload| ContractEvent {
+            contract: self.subscription.contract.clone(),
+            event: path.clone(),
+            block: log.block,
+            tx_hash: log.tx_hash.clone(),
+            payload,
+        }))
+    }
+}
+
+/// What one subscriber (a WebSocket connection, a gRPC stream) is pushed.
+/// The block watcher counts who watches what, so the subscriber has to
+/// `release` these when it goes away.
+#[derive(Debug, Default)]
+pub struct Subscriptions {
+    targets: HashSet<WatchTarget>,
+    events: Vec<EventFilter>,
+}
+
+impl Subscriptions {
+    pub fn targets(&self) -> &HashSet<WatchTarget> {
+        &self.targets
+    }
+
+    pub fn release(&mut self, watcher: &BlockWatcher) {
+        for target in self.targets.drain() {
+            watcher.unwatch(&target);
+        }
+        for filter in self.events.drain(..) {
+            watcher.unwatch_logs(&filter.address);
+        }
+    }
+}
+
+/// State kept for the lifetime of one WebSocket connection.
+#[derive(Debug, Default)]
+struct Session {
+    framing: Framing,
+    requests: u64,
+    subscriptions: Subscriptions,
+    /// `None` until a `hello` settles it; clients that never send one get
+    /// revision 1 behaviour.
+    protocol: Option<Negotiated>,
+    /// Set when a `hello` found no common protocol.
+    refused: bool,
+}
+
+impl Session {
+    // `hello` and `subscribe` change the session, so they are handled here,
+    // in turn, and come back answered. Everything else is spawned, and its
+    // answer comes back as the task's.
+    async fn handle(
+        &mut self,
+        bridge: &Arc<Bridge>,
+        request: BridgeRequest,
+        trace: TraceContext,
+    ) -> BoxFuture<'static, BridgeResponse> {
+        self.requests += 1;
+        let hello = match request {
+            BridgeRequest::Hello(_) => true,
+            BridgeRequest::Subscribe(_) => false,
+            request => {
+                let bridge = bridge.clone();
+                let task = tokio::spawn(async move {
+                    bridge
+                        .handle_for(request, None, trace.traceparent.as_deref())
+                        .await
+                });
+                return async move {
+                    task.await.unwrap_or_else(|e| {
+                        tracing::error!("Request task failed: {}", e);
+                        BridgeResponse::error("Request failed")
+                    })
+                }
+                .boxed();
+            }
+        };
+        let response = self.update(bridge, request, trace).await;
+        self.refused = hello && !response.success;
+        future::ready(response).boxed()
+    }
+
+    async fn update(
+        &mut self,
+        bridge: &Arc<Bridge>,
+        request: BridgeRequest,
+        trace: TraceContext,
+    ) -> BridgeResponse {
+        if matches!(request, BridgeRequest::Subscribe(_))
+            && self
+                .protocol
+                .as_ref()
+                .is_some_and(|p| !p.has(Feature::Subscriptions))
+        {
+            return BridgeResponse::error("Subscriptions were not negotiated in hello");
+        }
+        let response = bridge
+            .handle_for(
+                request,
+                Some(&mut self.subscriptions),
+                trace.traceparent.as_deref(),
+            )
+            .await;
+        if let Some(protocol) = &response.protocol {
+            self.protocol = Some(protocol.clone());
+        }
+        if let Some(negotiated) = response.framing {
+            self.framing = negotiated;
+        }
+        response
+    }
+}
+
+async fn handle_connection(
+    bridge: Arc<Bridge>,
+    stream: TcpStream,
+) -> Result<(), tokio_tungstenite::tungstenite::Error> {
+    // Oversized messages are refused as they arrive, before they are
+    // buffered whole; `check_frame` then only has nesting left to catch.
+    let max_bytes = bridge.config().limits.max_frame_bytes;
+    let ws_config = WebSocketConfig {
+        max_message_size: Some(max_bytes),
+        max_frame_size: Some(max_bytes),
+        ..WebSocketConfig::default()
+    };
+    let (sink, mut socket) = accept_async_with_config(stream, Some(ws_config))
+        .await?
+        .split();
+    let queue = Arc::new(SendQueue::new(
+        bridge.config().send_queue,
+        bridge.outbound_stats().clone(),
+    ));
+    let mut writer = tokio::spawn(queue.clone().drain_into(sink));
+    let mut session = Session::default();
+
+    let result = serve_connection(&bridge, &mut socket, &queue, &mut session).await;
+    session.subscriptions.release(bridge.watcher());
+    let mut written = Ok(());
+    if queue.overflowed() {
+        // The writer may be stuck on a client that stopped reading.
+        tracing::warn!(
+            "Closing connection that fell {} frames behind",
+            bridge.config().send_queue.capacity
+        );
+        writer.abort();
+    } else {
+        // Let the writer send what's queued, but not wait on a stalled
+        // client forever.
+        queue.close();
+        let idle_timeout = bridge.config().idle_timeout;
+        match tokio::time::timeout(idle_timeout, &mut writer).await {
+            Ok(Ok(result)) => written = result,
+            Ok(Err(_)) => {}
+            Err(_) => writer.abort(),
+        }
+    }
+    result?;
+    written?;
+    tracing::info!("Connection closed after {} requests", session.requests);
+    Ok(())
+}
+
+/// A request's answer on its way, and the framing it goes out in.
+type Answer = (Framing, BoxFuture<'static, BridgeResponse>);
+
+// Reads requests and queues their responses, and the session's pushes,
+// until the client leaves, idles out or the queue closes. Requests run on
+// tasks of their own, so a slow one holds up neither pings nor pushes.
+async fn serve_connection(
+    bridge: &Arc<Bridge>,
+    socket: &mut SplitStream<WebSocketStream<TcpStream>>,
+    queue: &Arc<SendQueue>,
+    session: &mut Session,
+) -> Result<(), tokio_tungstenite::tungstenite::Error> {
+    let mut changes = bridge.watcher().subscribe();
+    let mut logs = bridge.watcher().subscribe_logs();
+    let idle_timeout = bridge.config().idle_timeout;
+    let mut idle_deadline = Instant::now() + idle_timeout;
+    let stats = bridge.outbound_stats();
+    // At most a queue's worth of requests in flight; past that, reading
+    // waits for the oldest to be answered.
+    let (answers, in_flight) = mpsc::channel(bridge.config().send_queue.capacity.max(1));
+    let responder = tokio::spawn(respond_in_order(in_flight, queue.clone()));
+    let mut close = false;
+
+    loop {
+        // Pushes don't count as activity; only the client can keep the
+        // connection alive.
+        let message = tokio::select! {
+            message = socket.next() => match message {
+                Some(message) => message?,
+                None => break,
+            },
+            change = changes.recv() => {
+                let pushed = match change {
+                    Ok(change) if session.subscriptions.targets.contains(&change.target) => {
+                        let push = BridgeResponse::push(BridgeEvent::ValueChanged(change));
+                        queue.push(encode(session.framing, &push))
+                    }
+                    Ok(_) => Ok(()),
+                    Err(RecvError::Lagged(missed)) => {
+                        tracing::warn!("Connection fell behind and missed {} value changes", missed);
+                        stats.record_dropped(missed);
+                        Ok(())
+                    }
+                    // The bridge owns the watcher, so the sender outlives us.
+                    Err(RecvError::Closed) => unreachable!("block watcher dropped"),
+                };
+                if pushed.is_err() {
+                    break;
+                }
+                continue;
+            }
+            log = logs.recv() => {
+                let mut pushes = vec![];
+                match log {
+                    Ok(log) => {
+                        for filter in &session.subscriptions.events {
+                            match filter.decode(&log) {
+                                Some(Ok(event)) => pushes.push(BridgeResponse::push(BridgeEvent::ContractEvent(event))),
+                                Some(Err(e)) => tracing::warn!("Could not decode event in {}: {}", log.tx_hash, e),
+                                None => {}
+                            }
+                        }
+                    }
+                    Err(RecvError::Lagged(missed)) => {
+                        tracing::warn!("Connection fell behind and missed {} contract logs", missed);
+                        stats.record_dropped(missed);
+                    }
+                    Err(RecvError::Closed) => unreachable!("block watcher dropped"),
+                }
+                if pushes.iter().any(|push| queue.push(encode(session.framing, push)).is_err()) {
+                    break;
+                }
+                continue;
+            }
+            _ = sleep_until(idle_deadline) => {
+                tracing::info!("Closing connection idle for {:?}", idle_timeout);
+                close = true;
+                break;
+            }
+        };
+        idle_deadline = Instant::now() + idle_timeout;
+
+        // Text frames are always JSON so that plain clients keep working after
+        // another framing has been negotiated.
+        let (bytes, framing) = match &message {
+            Message::Text(text) => (text.as_bytes(), Framing::Json),
+            Message::Binary(bytes) => (bytes.as_slice(), session.framing),
+            Message::Close(_) => break,
+            // tungstenite queues the pong itself; flush so it goes out now.
+            Message::Ping(_) => {
+                if queue.respond(Frame::Flush).await.is_err() {
+                    break;
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        let decoded = bridge
+            .config()
+            .limits
+            .check_frame(bytes, !framing.is_binary())
+            .and_then(|()| {
+                framing
+                    .decode::<BridgeRequest>(bytes)
+                    .map_err(|e| (ErrorCode::InvalidRequest, format!("Invalid request: {}", e)))
+            });
+
+        let trace = framing.decode(bytes).unwrap_or_default();
+        // A welcome still goes out in the old framing; the switch applies to
+        // everything after it.
+        let framing = session.framing;
+        let answer = match decoded {
+            Ok(request) => session.handle(bridge, request, trace).await,
+            Err((code, e)) => future::ready(BridgeResponse::failed(code, e)).boxed(),
+        };
+        if answers.send((framing, answer)).await.is_err() {
+            break;
+        }
+        if session.refused {
+            // No common protocol: carrying on would only garble later messages.
+            close = true;
+            break;
+        }
+    }
+    // Requests still in flight are answered before the connection closes.
+    drop(answers);
+    let _ = responder.await;
+    if close {
+        let _ = queue.respond(Frame::Message(Message::Close(None))).await;
+    }
+    Ok(())
+}
+
+// Clients match responses to requests by order, so answers are queued in
+// the order their requests came in, however long each takes.
+async fn respond_in_order(mut in_flight: mpsc::Receiver<Answer>, queue: Arc<SendQueue>) {
+    while let Some((framing, answer)) = in_flight.recv().await {
+        let response = answer.await;
+        if queue
+            .respond(Frame::Message(encode(framing, &response)))
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+fn encode(framing: Framing, response: &BridgeResponse) -> Message {
+    let encoded = framing
+        .encode(response)
+        .expect("bridge response serializes");
+    if framing.is_binary() {
+        Message::Binary(encoded)
+    } else {
+        Message::Text(String::from_utf8(encoded).expect("json is utf-8"))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::aztec_rpc_client::AztecRpcClient;
+    use crate::testing::{fixtures, test_wallet, MockPxe};
+    use futures_util::SinkExt;
+    use std::fs;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::time::timeout;
+
+    pub(crate) const CONTRACT: &str =
+        "0x12d8f70092c1d4b2bf3ddd60af8e47c1a10d90f3f31fe4c874d4b91f58442ede";
+
+    fn write_artifact(dir: &std::path::Path) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(
+            dir.join(format!("{}.json", CONTRACT)),
+            fixtures().artifact_json,
+        )
+        .unwrap();
+    }
+
+    static NEXT_DIR: AtomicUsize = AtomicUsize::new(0);
+
+    pub(crate) async fn bridge_with_mock(
+        configure: impl FnOnce(&mut BridgeConfig),
+    ) -> (Arc<Bridge>, MockPxe) {
+        let dir = std::env::temp_dir().join(format!(
+            "bridge-artifacts-{}-{}",
+            std::process::id(),
+            NEXT_DIR.fetch_add(1, Ordering::SeqCst)
+        ));
+        write_artifact(&dir);
+
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond(
+            "pxe_proveTx",
+            json!({ "publicInputs": {}, "clientIvcProof": "0x00" }),
+        );
+        mock.respond("pxe_sendTx", json!("0xfeed"));
+
+        let mut config = BridgeConfig {
+            listen_addr: "127.0.0.1:0".to_string(),
+            rest_addr: None,
+            grpc_addr: None,
+            health_addr: None,
+            ready_timeout: Duration::from_secs(1),
+            artifact_dir: dir,
+            artifact_reload_interval: Duration::from_secs(60),
+            default_contract: None,
+            sender: DEFAULT_ORIGIN.to_string(),
+            idle_timeout: Duration::from_secs(60),
+            cache_ttl: Duration::from_secs(60),
+            watch_interval: Duration::from_secs(60),
+            state_path: None,
+            approvals: None,
+            auth: None,
+            idempotency_ttl: Duration::from_secs(60),
+            limits: RequestLimits::default(),
+            send_queue: SendQueueConfig::default(),
+        };
+        configure(&mut config);
+        let pxe = mock.client();
+        (Arc::new(Bridge::new(config, pxe)), mock)
+    }
+
+    fn get_request(force_refresh: bool) -> String {
+        json!({ "action": "get", "contract": CONTRACT, "force_refresh": force_refresh }).to_string()
+    }
+
+    fn simulate_calls(mock: &MockPxe) -> usize {
+        mock.requests()
+            .iter()
+            .filter(|r| r["method"] == "pxe_simulateTx")
+            .count()
+    }
+
+    #[tokio::test]
+    async fn test_routes_set_to_named_contract_and_function() {
+        let (bridge, mock) = bridge_with_mock(|_| {}).await;
+        mock.respond("pxe_simulateTx", json!({ "privateExecutionResult": {} }));
+        let response = bridge
+            .handle_text(
+                &json!({
+                    "action": "set",
+                    "contract": CONTRACT,
+                    "function": "set_field_in_map",
+                    "args": [1, 2],
+                })
+                .to_string(),
+            )
+            .await;
+
+        assert_eq!(response, BridgeResponse::sent("0xfeed".to_string()));
+        assert_eq!(mock.requests().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_retried_set_with_idempotency_key_sends_once() {
+        let (bridge, mock) = bridge_with_mock(|_| {}).await;
+        mock.respond("pxe_simulateTx", json!({ "privateExecutionResult": {} }));
+        let set = |value: u64| {
+            json!({ "action": "set", "contract": CONTRACT, "value": value, "idempotency_key": "k1" })
+                .to_string()
+        };
+
+        let first = bridge.handle_text(&set(214)).await;
+        assert_eq!(first, BridgeResponse::sent("0xfeed".to_string()));
+        mock.respond("pxe_sendTx", json!("0xbeef"));
+        assert_eq!(bridge.handle_text(&set(214)).await, first);
+        let sends = |mock: &MockPxe| {
+            mock.requests()
+                .iter()
+                .filter(|r| r["method"] == "pxe_sendTx")
+                .count()
+        };
+        assert_eq!(sends(&mock), 1);
+
+        let reused = bridge.handle_text(&set(5)).await;
+        assert_eq!(reused.code, Some(ErrorCode::InvalidRequest));
+        assert_eq!(sends(&mock), 1);
+    }
+
+    #[tokio::test]
+    async fn test_only_signed_sets_claim_idempotency_keys() {
+        use crate::bridge::approvals::tests::{operator, public_key};
+        use crate::bridge::auth::sign_set;
+
+        let key = public_key(&operator(1));
+        let (bridge, mock) =
+            bridge_with_mock(|config| config.auth = Some(AuthPolicy::parse(&key).unwrap())).await;
+        mock.respond("pxe_simulateTx", json!({ "privateExecutionResult": {} }));
+
+        let mut call = CallRequest {
+            contract: Some(CONTRACT.to_string()),
+            value: Some(json!(5)),
+            idempotency_key: Some("k1".to_string()),
+            ..Default::default()
+        };
+        let squatter = bridge.handle(BridgeRequest::Set(call.clone())).await;
+        assert_eq!(squatter.code, Some(ErrorCode::Unauthorized));
+
+        call.value = Some(json!(214));
+        let expiry = unix_now() + 60;
+        let secret = hex::encode(operator(1).to_bytes());
+        call.auth = Some(sign_set(&call, &secret, 1, expiry).unwrap());
+        let first = bridge.handle(BridgeRequest::Set(call.clone())).await;
+        assert_eq!(first, BridgeResponse::sent("0xfeed".to_string()));
+        // The retry carries the used nonce and gets the recorded response.
+        assert_eq!(bridge.handle(BridgeRequest::Set(call)).await, first);
+        assert_eq!(simulate_calls(&mock), 1);
+    }
+
+    #[tokio::test]
+    async fn test_legacy_set_uses_default_contract() {
+        let (bridge, mock) =
+            bridge_with_mock(|config| config.default_contract = Some(CONTRACT.to_string())).await;
+        mock.respond("pxe_simulateTx", json!({ "privateExecutionResult": {} }));
+        let response = bridge.handle_text(r#"{"action":"set","value":214}"#).await;
+        assert!(response.success, "{:?}", response.error);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_wrong_arity_before_calling_pxe() {
+        let (bridge, mock) = bridge_with_mock(|_| {}).await;
+        let response = bridge
+            .handle_text(&json!({ "action": "set", "contract": CONTRACT, "function": "set_field_in_map", "args": [1] }).to_string())
+            .await;
+
+        assert!(!response.success);
+        assert!(mock.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failures_map_to_stable_codes() {
+        let (bridge, mock) = bridge_with_mock(|_| {}).await;
+        let set = |args: serde_json::Value| {
+            json!({ "action": "set", "contract": CONTRACT, "function": "set_field_in_map", "args": args })
+                .to_string()
+        };
+
+        let response = bridge.handle_text(&set(json!([1]))).await;
+        assert_eq!(response.code, Some(ErrorCode::Encoding));
+
+        let revert = json!({ "code": -32000, "message": "Assertion failed: not an admin" });
+        mock.respond_with_envelope(
+            "pxe_simulateTx",
+            json!({ "jsonrpc": "2.0", "id": 1, "error": revert }),
+        );
+        let failure = bridge
+            .handle_text(&set(json!([1, 2])))
+            .await
+            .failure
+            .unwrap();
+        assert_eq!(failure.error_code(), Some(ErrorCode::TxReverted));
+        assert!(!failure.retryable);
+        assert_eq!(failure.details, Some(revert));
+
+        let offline = Bridge::new(
+            bridge.config().clone(),
+            AztecRpcClient::new("http://127.0.0.1:1", Some("pxe".to_string()))
+                .with_wallet(test_wallet(DEFAULT_ORIGIN)),
+        );
+        let failure = Arc::new(offline)
+            .handle_text(&set(json!([1, 2])))
+            .await
+            .failure
+            .unwrap();
+        assert_eq!(failure.code, 503);
+        assert!(failure.retryable);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_contract_is_an_error() {
+        let (bridge, _mock) = bridge_with_mock(|_| {}).await;
+        let response = bridge
+            .handle_text(r#"{"action":"get","contract":"0xdead"}"#)
+            .await;
+        assert!(response
+            .error
+            .unwrap()
+            .contains("No artifact for contract 0xdead"));
+    }
+
+    #[tokio::test]
+    async fn test_artifact_comes_from_pxe_without_a_local_file() {
+        let (bridge, mock) = bridge_with_mock(|_| {}).await;
+        let class_id = "0x0c1a55";
+        mock.respond(
+            "pxe_getContractMetadata",
+            json!({ "contractInstance": { "currentContractClassId": class_id } }),
+        );
+        let artifact: serde_json::Value = serde_json::from_str(fixtures().artifact_json).unwrap();
+        mock.respond("pxe_getContractArtifact", artifact);
+        mock.respond("pxe_simulateTx", fixtures().simulate_get.clone());
+
+        let get = r#"{"action":"get","contract":"0xbeef","function":"get_just_field"}"#;
+        let response = bridge.handle_text(get).await;
+        assert!(response.success, "{:?}", response.error);
+        let interface = bridge
+            .handle_text(r#"{"action":"interface","contract":"0xbeef"}"#)
+            .await;
+        assert!(interface.success, "{:?}", interface.error);
+
+        // Fetched once, and kept when the directory is rescanned.
+        bridge.registry().reload().unwrap();
+        assert!(bridge.registry().resolve("0xbeef").is_ok());
+        let fetches: Vec<_> = mock
+            .requests()
+            .into_iter()
+            .filter(|r| r["method"] == "pxe_getContractArtifact")
+            .collect();
+        assert_eq!(fetches.len(), 1);
+        assert_eq!(
+            fetches[0]["params"],
+            json!([Fr::try_from(class_id).unwrap()])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_idle_connections_are_reaped() {
+        let (bridge, _mock) =
+            bridge_with_mock(|config| config.idle_timeout = Duration::from_millis(100)).await;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, bridge));
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr))
+            .await
+            .unwrap();
+        let closed = timeout(Duration::from_secs(2), async {
+            loop {
+                match socket.next().await {
+                    Some(Ok(Message::Close(_))) | None | Some(Err(_)) => return,
+                    Some(Ok(_)) => continue,
+                }
+            }
+        })
+        .await;
+
+        assert!(closed.is_ok(), "idle connection was not closed");
+    }
+
+    #[tokio::test]
+    async fn test_oversized_messages_close_the_connection() {
+        let (bridge, mock) = bridge_with_mock(|config| config.limits.max_frame_bytes = 1024).await;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, bridge));
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr))
+            .await
+            .unwrap();
+        let oversized = json!({ "action": "get", "contract": "x".repeat(2048) }).to_string();
+        // The server may drop the connection before the write completes.
+        let _ = socket.send(Message::Text(oversized)).await;
+        let closed = timeout(Duration::from_secs(2), async {
+            match socket.next().await {
+                Some(Ok(Message::Close(_))) | None | Some(Err(_)) => {}
+                Some(Ok(message)) => panic!("expected a close, got {:?}", message),
+            }
+        })
+        .await;
+
+        assert!(
+            closed.is_ok(),
+            "oversized message did not close the connection"
+        );
+        assert!(mock.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_pings_are_answered_while_a_request_is_in_flight() {
+        let (bridge, mock) = bridge_with_mock(|_| {}).await;
+        mock.respond("pxe_getTxReceipt", json!({ "status": "success" }));
+        mock.delay("pxe_getTxReceipt", Duration::from_secs(2));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve(listener, bridge));
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr))
+            .await
+            .unwrap();
+        let receipt = json!({ "action": "receipt", "tx_hash": "0x01" });
+        socket
+            .send(Message::Text(receipt.to_string()))
+            .await
+            .unwrap();
+        socket
+            .send(Message::Text("not json".to_string()))
+            .await
+            .unwrap();
+        socket.send(Message::Ping(b"alive".to_vec())).await.unwrap();
+
+        let pong = timeout(Duration::from_millis(500), socket.next())
+            .await
+            .expect("no pong while the receipt was in flight");
+        assert!(matches!(pong, Some(Ok(Message::Pong(_)))));
+
+        // Answers still go out in the order their requests came in.
+        let mut answers = vec![];
+        while answers.len() < 2 {
+            match timeout(Duration::from_secs(5), socket.next())
+                .await
+                .unwrap()
+            {
+                Some(Ok(Message::Text(text))) => {
+                    answers.push(serde_json::from_str::<BridgeResponse>(&text).unwrap())
+                }
+                other => panic!("expected an answer, got {:?}", other),
+            }
+        }
+        assert_eq!(
+            answers[0],
+            BridgeResponse::value(json!({ "status": "success" }), false)
+        );
+        assert_eq!(answers[1].code, Some(ErrorCode::InvalidRequest));
+    }
+
+    #[tokio::test]
+    async fn test_get_is_served_from_cache_and_refreshed_in_background() {
+        let (bridge, mock) = bridge_with_mock(|_| {}).await;
+        mock.respond("pxe_simulateTx", json!({ "v": 1 }));
+        mock.respond("pxe_simulateTx", json!({ "v": 2 }));
+
+        let first = bridge.handle_text(&get_request(false)).await;
+        assert_eq!(first, BridgeResponse::value(json!({ "v": 1 }), false));
+
+        let cached = bridge.handle_text(&get_request(false)).await;
+        assert_eq!(cached, BridgeResponse::value(json!({ "v": 1 }), false));
+
+        timeout(Duration::from_secs(2), async {
+            while bridge
+                .cache
+                .get(&CacheKey::new(CONTRACT, DEFAULT_GET_FUNCTION, &[]))
+                .map(|(v, _)| v)
+                != Some(json!({ "v": 2 }))
+            {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("background refresh never landed");
+
+        let refreshed = bridge.handle_text(&get_request(false)).await;
+        assert_eq!(refreshed.value, Some(json!({ "v": 2 })));
+    }
+
+    #[tokio::test]
+    async fn test_force_refresh_reads_from_pxe() {
+        let (bridge, mock) = bridge_with_mock(|_| {}).await;
+        mock.respond("pxe_simulateTx", json!({ "v": 1 }));
+
+        bridge.handle_text(&get_request(false)).await;
+        let forced = bridge.handle_text(&get_request(true)).await;
+
+        assert_eq!(forced.stale, Some(false));
+        assert_eq!(simulate_calls(&mock), 2);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_set_reports_effects_without_sending() {
+        let (bridge, mock) = bridge_with_mock(|_| {}).await;
+        let bridge = Bridge::new(bridge.config().clone(), mock.client().with_dry_run(true));
+        mock.respond(
+            "pxe_simulateTx",
+            json!({ "publicOutput": { "txEffect": { "nullifiers": ["0x01"] } } }),
+        );
+
+        let response = Arc::new(bridge)
+            .handle_text(&json!({ "action": "set", "contract": CONTRACT, "value": 5 }).to_string())
+            .await;
+        assert!(response.tx_hash.is_none());
+        assert_eq!(response.dry_run.unwrap().nullifiers.len(), 1);
+        assert_eq!(mock.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_set_marks_cached_values_stale() {
+        let (bridge, mock) = bridge_with_mock(|_| {}).await;
+        mock.respond("pxe_simulateTx", json!({ "privateExecutionResult": {} }));
+
+        bridge.handle_text(&get_request(false)).await;
+        let set = bridge
+            .handle_text(&json!({ "action": "set", "contract": CONTRACT, "value": 5 }).to_string())
+            .await;
+        assert!(set.success);
+
+        let cached = bridge.handle_text(&get_request(false)).await;
+        assert_eq!(cached.stale, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_pushes_changes_only_to_subscribed_connections() {
+        let (bridge, mock) = bridge_with_mock(|_| {}).await;
+        mock.respond("pxe_getBlockNumber", json!(1));
+        mock.respond("pxe_getBlockNumber", json!(2));
+        mock.respond("pxe_getPublicStorageAt", json!("0x2bc"));
+        mock.respond("pxe_getPublicStorageAt", json!("0xd6"));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, bridge.clone()));
+
+        let target = json!({ "kind": "publicStorage", "contract": CONTRACT, "slot": "0x2" });
+        let (mut subscriber, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        let (mut bystander, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        subscriber
+            .send(Message::Text(
+                json!({ "action": "subscribe", "target": target }).to_string(),
+            ))
+            .await
+            .unwrap();
+        let ack = subscriber.next().await.unwrap().unwrap();
+        assert_eq!(ack, Message::Text(r#"{"success":true}"#.to_string()));
+
+        bridge.watcher().poll().await.unwrap();
+        bridge.watcher().poll().await.unwrap();
+
+        let push = timeout(Duration::from_secs(2), subscriber.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let push: BridgeResponse = serde_json::from_str(push.to_text().unwrap()).unwrap();
+        let Some(BridgeEvent::ValueChanged(change)) = push.event else {
+            panic!("expected a value change, got {:?}", push);
+        };
+        assert_eq!(change.block, 2);
+        assert_eq!(change.current, json!(crate::fields::Fr::from(214u8)));
+
+        assert!(timeout(Duration::from_millis(200), bystander.next())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_subscriptions_end_with_the_connection() {
+        let (bridge, _mock) = bridge_with_mock(|_| {}).await;
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, bridge.clone()));
+
+        let target = WatchTarget::PublicStorage {
+            contract: CONTRACT.to_string(),
+            slot: Fr::from(2u8),
+        };
+        let subscribe = json!({ "action": "subscribe", "target": target });
+        let (mut first, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        let (mut second, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        assert!(exchange(&mut first, subscribe.clone()).await.success);
+        assert!(exchange(&mut first, subscribe.clone()).await.success);
+        assert!(exchange(&mut second, subscribe).await.success);
+        assert!(
+            !bridge
+                .handle_text(&json!({ "action": "subscribe", "target": target }).to_string())
+                .await
+                .success
+        );
+
+        let closed = |socket: WebSocketStream<_>| async move {
+            let mut socket = socket;
+            socket.close(None).await.unwrap();
+            while socket.next().await.is_some() {}
+        };
+        closed(first).await;
+        // The first connection's subscription goes; the second's remains.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(bridge.watcher().is_watching(&target));
+
+        closed(second).await;
+        timeout(Duration::from_secs(2), async {
+            while bridge.watcher().is_watching(&target) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pushes_decoded_events_of_subscribed_contracts() {
+        let (bridge, mock) = bridge_with_mock(|_| {}).await;
+        let mut artifact: serde_json::Value =
+            serde_json::from_str(fixtures().artifact_json).unwrap();
+        let event = json!({ "kind": "struct", "path": "Main::PriceUpdated", "fields": [
+            { "name": "feed_id", "type": { "kind": "field" } },
+            { "name": "price", "type": { "kind": "integer", "sign": "unsigned", "width": 128 } },
+        ] });
+        artifact["outputs"] = json!({ "structs": { "events": [event.clone()] }, "globals": {} });
+        let path = bridge
+            .config()
+            .artifact_dir
+            .join(format!("{}.json", CONTRACT));
+        fs::write(path, artifact.to_string()).unwrap();
+
+        let selector = crate::encoder::event_selector(&serde_json::from_value(event).unwrap());
+        let mut effect = crate::block::fixtures::tx_effect_json();
+        effect["publicLogs"] = json!([
+            { "contractAddress": "0x0c", "fields": ["0x01", "0xd6", selector] },
+            { "contractAddress": CONTRACT, "fields": ["0x01", "0xd6", selector, "0x00"] },
+        ]);
+        mock.respond("pxe_getBlockNumber", json!(1));
+        mock.respond("pxe_getBlockNumber", json!(2));
+        mock.respond(
+            "pxe_getBlock",
+            crate::block::fixtures::block_json(2, vec![effect]),
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, bridge.clone()));
+        let (mut socket, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        let subscribe = |event: &str| json!({ "action": "subscribe", "events": { "contract": CONTRACT, "event": event } });
+        let missing = exchange(&mut socket, subscribe("Missing")).await;
+        assert_eq!(missing.code, Some(ErrorCode::NotFound));
+        assert!(
+            exchange(&mut socket, subscribe("PriceUpdated"))
+                .await
+                .success
+        );
+
+        bridge.watcher().poll().await.unwrap();
+        bridge.watcher().poll().await.unwrap();
+
+        let push = timeout(Duration::from_secs(2), socket.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let push: BridgeResponse = serde_json::from_str(push.to_text().unwrap()).unwrap();
+        let Some(BridgeEvent::ContractEvent(event)) = push.event else {
+            panic!("expected a contract event, got {:?}", push);
+        };
+        assert_eq!(event.event, "Main::PriceUpdated");
+        assert_eq!((event.block, event.tx_hash.as_str()), (2, "0x1a2b"));
+        assert_eq!(event.payload, json!({ "feed_id": "1", "price": "214" }));
+        assert!(timeout(Duration::from_millis(200), socket.next())
+            .await
+            .is_err());
+    }
+
+    async fn exchange(
+        socket: &mut WebSocketStream<tokio_tungstenite::MaybeTlsStream<TcpStream>>,
+        request: serde_json::Value,
+    ) -> BridgeResponse {
+        socket
+            .send(Message::Text(request.to_string()))
+            .await
+            .unwrap();
+        let reply = socket.next().await.unwrap().unwrap();
+        serde_json::from_str(reply.to_text().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_hello_gates_features_and_rejects_incompatible_clients() {
+        let (bridge, _mock) = bridge_with_mock(|_| {}).await;
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, bridge));
+
+        let subscribe = json!({
+            "action": "subscribe",
+            "target": { "kind": "publicStorage", "contract": CONTRACT, "slot": "0x2" },
+        });
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        let welcome = exchange(
+            &mut socket,
+            json!({ "action": "hello", "version": 2, "features": ["auth"] }),
+        )
+        .await;
+        assert_eq!(
+            welcome.protocol,
+            Some(Negotiated {
+                version: 2,
+                features: vec![]
+            })
+        );
+        let refused = exchange(&mut socket, subscribe.clone()).await;
+        assert!(refused.error.unwrap().contains("not negotiated"));
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        let welcome = exchange(
+            &mut socket,
+            json!({ "action": "hello", "version": 9, "features": ["subscriptions"] }),
+        )
+        .await;
+        assert_eq!(welcome.protocol.unwrap().version, 2);
+        assert!(exchange(&mut socket, subscribe).await.success);
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+        let rejected = exchange(
+            &mut socket,
+            json!({ "action": "hello", "version": 9, "min_version": 9 }),
+        )
+        .await;
+        assert!(!rejected.success);
+        let closed = timeout(Duration::from_secs(2), socket.next())
+            .await
+            .unwrap();
+        assert!(matches!(closed, Some(Ok(Message::Close(_))) | None));
+    }
+
+    #[tokio::test]
+    async fn test_set_waits_for_operator_approvals() {
+        use crate::bridge::approvals::tests::{operator, public_key, sign};
+
+        let operators: Vec<String> = (1..=3).map(|i| public_key(&operator(i))).collect();
+        let (bridge, mock) = bridge_with_mock(|config| {
+            config.approvals = Some(ApprovalPolicy::parse(&operators.join(","), Some("2")).unwrap())
+        })
+        .await;
+        mock.respond("pxe_simulateTx", json!({ "privateExecutionResult": {} }));
+
+        let set = json!({ "action": "set", "contract": CONTRACT, "value": 214 }).to_string();
+        let proposed = bridge.handle_text(&set).await;
+        let status = proposed.approval.expect("set should wait for approval");
+        assert_eq!((status.approvals, status.threshold), (0, 2));
+        assert!(mock.requests().is_empty());
+
+        let approve = |seed: u8| {
+            json!({
+                "action": "approve",
+                "id": status.id,
+                "operator": public_key(&operator(seed)),
+                "signature": sign(&operator(seed), &status.id),
+            })
+            .to_string()
+        };
+        let first = bridge.handle_text(&approve(2)).await;
+        assert_eq!(first.approval.unwrap().approvals, 1);
+        assert!(mock.requests().is_empty());
+
+        let second = bridge.handle_text(&approve(3)).await;
+        assert_eq!(second, BridgeResponse::sent("0xfeed".to_string()));
+        assert_eq!(simulate_calls(&mock), 1);
+        assert!(bridge.pending_approvals().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_set_requires_operator_signature() {
+        use crate::bridge::approvals::tests::{operator, public_key};
+        use crate::bridge::auth::sign_set;
+
+        let key = public_key(&operator(1));
+        let access = format!("{}={}.set_just_field", key, CONTRACT);
+        let (bridge, mock) = bridge_with_mock(|config| {
+            config.auth = Some(
+                AuthPolicy::parse(&key)
+                    .unwrap()
+                    .with_access(&access)
+                    .unwrap(),
+            )
+        })
+        .await;
+        mock.respond("pxe_simulateTx", json!({ "privateExecutionResult": {} }));
+
+        let mut call = CallRequest {
+            contract: Some(CONTRACT.to_string()),
+            value: Some(json!(214)),
+            ..Default::default()
+        };
+        let unsigned = bridge.handle(BridgeRequest::Set(call.clone())).await;
+        assert_eq!(unsigned.code, Some(ErrorCode::Unauthorized));
+        assert!(mock.requests().is_empty());
+
+        let expiry = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+            + 60;
+        let secret = hex::encode(operator(1).to_bytes());
+        call.auth = Some(sign_set(&call, &secret, 1, expiry).unwrap());
+        let signed = bridge.handle(BridgeRequest::Set(call.clone())).await;
+        assert_eq!(signed, BridgeResponse::sent("0xfeed".to_string()));
+
+        let replayed = bridge.handle(BridgeRequest::Set(call.clone())).await;
+        assert_eq!(replayed.code, Some(ErrorCode::Unauthorized));
+
+        call.function = Some("set_field_in_map".to_string());
+        call.args = Some(vec![json!(1), json!(2)]);
+        call.auth = Some(sign_set(&call, &secret, 2, expiry).unwrap());
+        let denied = bridge.handle(BridgeRequest::Set(call.clone())).await;
+        assert_eq!(denied.code, Some(ErrorCode::Unauthorized));
+        assert!(denied
+            .error
+            .unwrap()
+            .contains("may not call set_field_in_map"));
+        assert_eq!(simulate_calls(&mock), 1);
+
+        // The refused call didn't use up nonce 2.
+        call.function = None;
+        call.args = None;
+        call.auth = Some(sign_set(&call, &secret, 2, expiry).unwrap());
+        let allowed = bridge.handle(BridgeRequest::Set(call)).await;
+        assert_eq!(allowed, BridgeResponse::sent("0xfeed".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_set_is_rejected_before_approval() {
+        let key = crate::bridge::approvals::tests::public_key(
+            &crate::bridge::approvals::tests::operator(1),
+        );
+        let (bridge, _mock) = bridge_with_mock(|config| {
+            config.approvals = Some(ApprovalPolicy::parse(&key, None).unwrap())
+        })
+        .await;
+
+        let response = bridge
+            .handle_text(
+                &json!({ "action": "set", "contract": CONTRACT, "args": [1, 2] }).to_string(),
+            )
+            .await;
+        assert!(!response.success);
+        assert!(bridge.pending_approvals().unwrap().is_empty());
+    }
+}
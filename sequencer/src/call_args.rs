@@ -0,0 +1,281 @@
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+use crate::encoder::{encode_arguments, AbiParameter, AbiType, FunctionAbi};
+use crate::fields::Fr;
+use crate::fixed_point::FixedPoint;
+
+/// Reads one argument as typed at a prompt. Fields and integers take
+/// decimal or `0x` hex, booleans `true`/`false` (or `1`/`0`, `y`/`n`),
+/// strings are taken as is, and arrays and structs as JSON (or, for an
+/// address, point or `U128`, a bare number). The value is checked against
+/// the parameter's type before it is returned.
+pub fn parse_arg(parameter: &AbiParameter, input: &str) -> Result<Value, String> {
+    let input = input.trim();
+    let value = match &parameter.abi_type {
+        AbiType::Field | AbiType::Integer { .. } => decimal(input)?,
+        AbiType::Boolean => match input.to_ascii_lowercase().as_str() {
+            "true" | "1" | "y" | "yes" => Value::Bool(true),
+            "false" | "0" | "n" | "no" => Value::Bool(false),
+            _ => return Err(format!("Expected true or false, got '{}'", input)),
+        },
+        AbiType::String { .. } => Value::String(input.to_string()),
+        AbiType::Array { .. } => {
+            serde_json::from_str(input).map_err(|e| format!("Expected JSON: {}", e))?
+        }
+        // Addresses, points and `U128`s also take a bare number.
+        AbiType::Struct { .. } if !input.starts_with(['{', '[']) => {
+            Value::String(input.to_string())
+        }
+        AbiType::Struct { .. } => {
+            serde_json::from_str(input).map_err(|e| format!("Expected JSON: {}", e))?
+        }
+    };
+    check_arg(parameter, &value)?;
+    Ok(value)
+}
+
+// The encoder reads numbers in decimal, so hex is converted here.
+fn decimal(input: &str) -> Result<Value, String> {
+    if input.starts_with("0x") || input.starts_with("0X") {
+        Ok(Value::String(Fr::try_from(input)?.0.to_string()))
+    } else {
+        Ok(Value::String(input.to_string()))
+    }
+}
+
+/// Whether `value` encodes as `parameter`.
+pub fn check_arg(parameter: &AbiParameter, value: &Value) -> Result<(), String> {
+    let abi = FunctionAbi {
+        name: String::new(),
+        function_type: String::new(),
+        isInternal: false,
+        isStatic: false,
+        isInitializer: false,
+        parameters: vec![parameter.clone()],
+        return_types: vec![],
+        errorTypes: None,
+    };
+    encode_arguments(abi, vec![value.clone()]).map(|_| ())
+}
+
+/// Asks for each parameter in turn, as `name (type): `, until `ask` gives
+/// an answer that parses; `rejected` hears why each bad answer was refused.
+pub fn prompt_args<E>(
+    parameters: &[AbiParameter],
+    mut ask: impl FnMut(&str) -> Result<String, E>,
+    mut rejected: impl FnMut(&str),
+) -> Result<Vec<Value>, E> {
+    let mut args = Vec::with_capacity(parameters.len());
+    for parameter in parameters {
+        let label = format!("{} ({}): ", parameter.name, parameter.abi_type);
+        loop {
+            match parse_arg(parameter, &ask(&label)?) {
+                Ok(value) => break args.push(value),
+                Err(e) => rejected(&e),
+            }
+        }
+    }
+    Ok(args)
+}
+
+/// Arguments from a JSON file: an array in parameter order, or an object
+/// keyed by parameter name. Each is checked against its parameter, and
+/// hex numbers are accepted as at the prompt.
+pub fn load_args_file<P: AsRef<Path>>(
+    path: P,
+    parameters: &[AbiParameter],
+) -> Result<Vec<Value>, String> {
+    let path = path.as_ref();
+    let contents =
+        fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let json: Value = serde_json::from_str(&contents)
+        .map_err(|e| format!("Invalid JSON in {}: {}", path.display(), e))?;
+    let args = match json {
+        Value::Array(args) => args,
+        Value::Object(mut named) => parameters
+            .iter()
+            .map(|p| {
+                named
+                    .remove(&p.name)
+                    .ok_or_else(|| format!("{} has no `{}`", path.display(), p.name))
+            })
+            .collect::<Result<_, _>>()?,
+        _ => return Err(format!("{} must hold an array or object", path.display())),
+    };
+    if args.len() != parameters.len() {
+        return Err(format!(
+            "Expected {} arguments, {} has {}",
+            parameters.len(),
+            path.display(),
+            args.len()
+        ));
+    }
+    parameters
+        .iter()
+        .zip(args)
+        .map(|(parameter, value)| {
+            let value = match (&parameter.abi_type, value) {
+                (AbiType::Field | AbiType::Integer { .. }, Value::String(s)) => decimal(&s)?,
+                (_, value) => value,
+            };
+            check_arg(parameter, &value).map_err(|e| format!("{}: {}", parameter.name, e))?;
+            Ok(value)
+        })
+        .collect()
+}
+
+/// Scales the numbers passed for the parameters or struct fields named in
+/// `names` by `fixed`, so a price can be given as `12.5` rather than as
+/// its scaled integer. Arrays of such numbers are scaled element by element.
+pub fn scale_fixed_args(
+    parameters: &[AbiParameter],
+    args: Vec<Value>,
+    names: &[String],
+    fixed: &FixedPoint,
+) -> Result<Vec<Value>, String> {
+    parameters
+        .iter()
+        .zip(args)
+        .map(|(parameter, value)| {
+            scale_fixed(&parameter.name, &parameter.abi_type, value, names, fixed)
+        })
+        .collect()
+}
+
+fn scale_fixed(
+    name: &str,
+    abi_type: &AbiType,
+    value: Value,
+    names: &[String],
+    fixed: &FixedPoint,
+) -> Result<Value, String> {
+    let scaled = |decimal: &str| {
+        fixed
+            .parse(decimal)
+            .map(|scaled| Value::String(scaled.to_string()))
+            .map_err(|e| format!("{}: {}", name, e))
+    };
+    match (abi_type, value) {
+        (AbiType::Field | AbiType::Integer { .. }, Value::String(s))
+            if names.iter().any(|n| n == name) =>
+        {
+            scaled(&s)
+        }
+        (AbiType::Field | AbiType::Integer { .. }, Value::Number(n))
+            if names.iter().any(|n| n == name) =>
+        {
+            scaled(&n.to_string())
+        }
+        (AbiType::Array { r#type, .. }, Value::Array(items)) => items
+            .into_iter()
+            .map(|item| scale_fixed(name, r#type, item, names, fixed))
+            .collect::<Result<_, _>>()
+            .map(Value::Array),
+        (AbiType::Struct { fields, .. }, Value::Object(mut values)) => {
+            for field in fields {
+                if let Some(value) = values.remove(&field.name) {
+                    let value = scale_fixed(&field.name, &field.field_type, value, names, fixed)?;
+                    values.insert(field.name.clone(), value);
+                }
+            }
+            Ok(Value::Object(values))
+        }
+        (_, value) => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoder::get_function_artifact;
+    use crate::testing::fixtures;
+    use serde_json::json;
+    use std::collections::VecDeque;
+
+    fn parameters_of(function: &str) -> Vec<AbiParameter> {
+        get_function_artifact(&fixtures().artifact, function)
+            .unwrap()
+            .parameters
+            .clone()
+    }
+
+    #[test]
+    fn test_prompts_until_each_argument_parses() {
+        let parameters = parameters_of("set_field_in_map");
+        let mut answers = VecDeque::from(["0x1", "not a number", "42"]);
+        let mut labels = vec![];
+        let mut rejections = vec![];
+        let args = prompt_args(
+            &parameters,
+            |label| {
+                labels.push(label.to_string());
+                answers.pop_front().map(str::to_string).ok_or(())
+            },
+            |e| rejections.push(e.to_string()),
+        )
+        .unwrap();
+
+        assert_eq!(args, [json!("1"), json!("42")]);
+        assert_eq!(labels[0], format!("{} (field): ", parameters[0].name));
+        assert_eq!(labels.len(), 3);
+        assert_eq!(rejections.len(), 1);
+
+        let feeds = &parameters_of("set_feeds")[0];
+        assert!(parse_arg(feeds, "[1, 2]").is_err());
+        let entry = json!({ "feed_id": "1", "price": "10", "timestamp": 5 });
+        let valid = parse_arg(feeds, &json!([entry, entry, entry]).to_string()).unwrap();
+        assert_eq!(valid[0]["price"], "10");
+    }
+
+    #[test]
+    fn test_args_file_by_position_or_name() {
+        let parameters = parameters_of("set_field_in_map");
+        let dir = std::env::temp_dir();
+        let positional = dir.join(format!("args-positional-{}.json", std::process::id()));
+        let named = dir.join(format!("args-named-{}.json", std::process::id()));
+        fs::write(&positional, r#"["0x1", 42]"#).unwrap();
+        let by_name = json!({ parameters[1].name.clone(): 42, parameters[0].name.clone(): "0x1" });
+        fs::write(&named, by_name.to_string()).unwrap();
+
+        let expected = [json!("1"), json!(42)];
+        assert_eq!(load_args_file(&positional, &parameters).unwrap(), expected);
+        assert_eq!(load_args_file(&named, &parameters).unwrap(), expected);
+
+        fs::write(&positional, r#"["0x1"]"#).unwrap();
+        assert!(load_args_file(&positional, &parameters).is_err());
+        fs::write(&positional, r#"["0x1", true, 3]"#).unwrap();
+        assert!(load_args_file(&positional, &parameters).is_err());
+        fs::remove_file(&positional).unwrap();
+        fs::remove_file(&named).unwrap();
+    }
+
+    #[test]
+    fn test_scales_named_fixed_point_args() {
+        let fixed = FixedPoint::new(2).unwrap();
+        let names = ["price".to_string()];
+        let feeds = parameters_of("set_feeds");
+        let entry = |price: Value| json!({ "feed_id": "1", "price": price, "timestamp": 5 });
+        let args = vec![json!([
+            entry(json!("12.5")),
+            entry(json!(0.125)),
+            entry(json!(3))
+        ])];
+        let scaled = scale_fixed_args(&feeds, args, &names, &fixed).unwrap();
+        assert_eq!(
+            scaled,
+            [json!([
+                entry(json!("1250")),
+                entry(json!("13")),
+                entry(json!("300"))
+            ])]
+        );
+        assert_eq!(scaled[0][0]["feed_id"], "1");
+        check_arg(&feeds[0], &scaled[0]).unwrap();
+
+        let bad = vec![json!([entry(json!("-1"))])];
+        let error = scale_fixed_args(&feeds, bad, &names, &fixed).unwrap_err();
+        assert!(error.starts_with("price: Negative"), "{}", error);
+    }
+}
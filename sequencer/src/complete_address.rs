@@ -0,0 +1,383 @@
+use num_bigint::BigUint;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::json;
+use sha2::{Digest, Sha512};
+use std::fmt;
+
+use crate::curves::grumpkin::{self, AffinePoint};
+use crate::fields::Fr;
+use crate::notes::{generator_index, Poseidon2};
+use crate::pxe_api::{decode, PxeApi};
+use crate::secret::Zeroizing;
+
+/// An account's four master public keys, derived from its secret key the
+/// way aztec.js' `deriveKeys` does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicKeys {
+    pub master_nullifier: AffinePoint,
+    pub master_incoming_viewing: AffinePoint,
+    pub master_outgoing_viewing: AffinePoint,
+    pub master_tagging: AffinePoint,
+}
+
+impl PublicKeys {
+    pub fn derive(secret_key: &Fr) -> Result<Self, String> {
+        let public_key = |separator| {
+            grumpkin::mul(
+                &AffinePoint::generator(),
+                &sha512_to_scalar(secret_key, separator).0,
+            )
+            .ok_or_else(|| "Secret key derives a key at infinity".to_string())
+        };
+        Ok(PublicKeys {
+            master_nullifier: public_key(generator_index::NSK_M)?,
+            master_incoming_viewing: public_key(generator_index::IVSK_M)?,
+            master_outgoing_viewing: public_key(generator_index::OVSK_M)?,
+            master_tagging: public_key(generator_index::TSK_M)?,
+        })
+    }
+
+    fn points(&self) -> [&AffinePoint; 4] {
+        [
+            &self.master_nullifier,
+            &self.master_incoming_viewing,
+            &self.master_outgoing_viewing,
+            &self.master_tagging,
+        ]
+    }
+
+    /// `x, y, is_infinite` per key.
+    pub fn to_fields(&self) -> Vec<Fr> {
+        self.points()
+            .into_iter()
+            .flat_map(|p| [p.x.clone(), p.y.clone(), Fr::zero()])
+            .collect()
+    }
+
+    /// Each key's `x, y`, as the PXE serializes `PublicKeys`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.points()
+            .into_iter()
+            .flat_map(|p| [p.x.to_be_bytes(), p.y.to_be_bytes()])
+            .flatten()
+            .collect()
+    }
+
+    pub fn hash(&self, poseidon2: &impl Poseidon2) -> Fr {
+        poseidon2.hash_with_separator(&self.to_fields(), generator_index::PUBLIC_KEYS_HASH)
+    }
+}
+
+/// The keys of a contract that has none (aztec.js' `PublicKeys.default()`),
+/// which is what deployments use unless given others.
+impl Default for PublicKeys {
+    fn default() -> Self {
+        let point = |x, y| {
+            AffinePoint::new(Fr::try_from(x).unwrap(), Fr::try_from(y).unwrap())
+                .expect("default public keys are on the curve")
+        };
+        PublicKeys {
+            master_nullifier: point(
+                "0x01498945581e0eb9f8427ad6021184c700ef091d570892c437d12c7d90364bbd",
+                "0x170ae506787c5c43d6ca9255d571c10fa9ffa9d141666e290c347c5c9ab7e344",
+            ),
+            master_incoming_viewing: point(
+                "0x00c044b05b6ca83b9c2dbae79cc1135155956a64e136819136e9947fe5e5866c",
+                "0x1c1f0ca244c7cd46b682552bff8ae77dea40b966a71de076ec3b7678f2bdb151",
+            ),
+            master_outgoing_viewing: point(
+                "0x1b00316144359e9a3ec8e49c1cdb7eeb0cedd190dfd9dc90eea5115aa779e287",
+                "0x080ffc74d7a8b0bccb88ac11f45874172f3847eb8b92654aaa58a3d2b8dc7833",
+            ),
+            master_tagging: point(
+                "0x019c111f36ad3fc1d9b7a7a14344314d2864b94f030594cd67f753ef774a1efb",
+                "0x2039907fe37f08d10739255141bb066c506a12f7d1e8dfec21abc58494705b6f",
+            ),
+        }
+    }
+}
+
+// `sha512ToGrumpkinScalar([secret_key, separator])`: the separator is
+// serialized as a big-endian u32.
+// The master secret keys are cleared once their public keys are derived.
+fn sha512_to_scalar(secret_key: &Fr, separator: u32) -> Zeroizing<Fr> {
+    let secret_bytes = Zeroizing::new(secret_key.to_be_bytes());
+    let mut hasher = Sha512::new();
+    hasher.update(secret_bytes.as_slice());
+    hasher.update(separator.to_be_bytes());
+    let digest = Zeroizing::new(Fr(BigUint::from_bytes_be(&hasher.finalize())));
+    Zeroizing::new(Fr(&digest.0 % grumpkin::order()))
+}
+
+/// An address with what it was derived from: `address` is the x coordinate
+/// of `preaddress * G + ivpk_m`, where `preaddress` hashes the public keys
+/// with the partial address. The PXE sends it as the hex of `address`, the
+/// keys' points and `partial_address`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompleteAddress {
+    pub address: Fr,
+    pub public_keys: PublicKeys,
+    pub partial_address: Fr,
+}
+
+const ENCODED_LEN: usize = 32 + 4 * 64 + 32;
+
+impl CompleteAddress {
+    pub fn compute(
+        public_keys: PublicKeys,
+        partial_address: Fr,
+        poseidon2: &impl Poseidon2,
+    ) -> Result<Self, String> {
+        let preaddress = poseidon2.hash_with_separator(
+            &[public_keys.hash(poseidon2), partial_address.clone()],
+            generator_index::CONTRACT_ADDRESS_V1,
+        );
+        let point = grumpkin::add(
+            grumpkin::mul(&AffinePoint::generator(), &preaddress.0).as_ref(),
+            Some(&public_keys.master_incoming_viewing),
+        )
+        .ok_or_else(|| "Address point is at infinity".to_string())?;
+        Ok(CompleteAddress {
+            address: point.x,
+            public_keys,
+            partial_address,
+        })
+    }
+
+    /// Checks that `address` is what the keys and partial address derive.
+    pub fn verify(&self, poseidon2: &impl Poseidon2) -> Result<(), Box<dyn std::error::Error>> {
+        let derived = CompleteAddress::compute(
+            self.public_keys.clone(),
+            self.partial_address.clone(),
+            poseidon2,
+        )?;
+        if derived.address != self.address {
+            return Err(AddressMismatch::Derived {
+                configured: self.address.clone(),
+                derived: derived.address,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(ENCODED_LEN);
+        bytes.extend(self.address.to_be_bytes());
+        bytes.extend(self.public_keys.to_bytes());
+        bytes.extend(self.partial_address.to_be_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() != ENCODED_LEN {
+            return Err(format!(
+                "Complete address needs {} bytes, got {}",
+                ENCODED_LEN,
+                bytes.len()
+            ));
+        }
+        let fields = bytes
+            .chunks(32)
+            .map(Fr::from_be_bytes)
+            .collect::<Result<Vec<_>, _>>()?;
+        let point = |i: usize| AffinePoint::new(fields[i].clone(), fields[i + 1].clone());
+        Ok(CompleteAddress {
+            address: fields[0].clone(),
+            public_keys: PublicKeys {
+                master_nullifier: point(1)?,
+                master_incoming_viewing: point(3)?,
+                master_outgoing_viewing: point(5)?,
+                master_tagging: point(7)?,
+            },
+            partial_address: fields[9].clone(),
+        })
+    }
+}
+
+impl Serialize for CompleteAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("0x{}", hex::encode(self.to_bytes())))
+    }
+}
+
+impl<'de> Deserialize<'de> for CompleteAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let bytes = hex::decode(s.trim_start_matches("0x")).map_err(serde::de::Error::custom)?;
+        CompleteAddress::from_bytes(&bytes).map_err(serde::de::Error::custom)
+    }
+}
+
+/// The wallet's origin address doesn't belong to its keys. Sending from it
+/// anyway only fails later, in simulation, without saying why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressMismatch {
+    /// The keys and partial address derive `derived`, not the configured
+    /// address.
+    Derived { configured: Fr, derived: Fr },
+    /// The PXE has the address registered with other keys or another
+    /// partial address.
+    Registered { address: Fr },
+}
+
+impl fmt::Display for AddressMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressMismatch::Derived {
+                configured,
+                derived,
+            } => write!(
+                f,
+                "Account keys and partial address derive {}, not the configured address {}",
+                derived.to_hex(),
+                configured.to_hex()
+            ),
+            AddressMismatch::Registered { address } => write!(
+                f,
+                "The PXE has {} registered with other keys or another partial address",
+                address.to_hex()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AddressMismatch {}
+
+/// Makes sure the PXE knows the account at `address` under the keys
+/// `secret_key` derives, registering it with `partial_address` if it doesn't.
+/// Fails with `AddressMismatch` when the PXE's account, or the one it
+/// registers, isn't that.
+pub async fn ensure_registered<P: PxeApi + ?Sized>(
+    pxe: &P,
+    address: &Fr,
+    secret_key: &Fr,
+    partial_address: &Fr,
+) -> Result<CompleteAddress, Box<dyn std::error::Error>> {
+    let public_keys = PublicKeys::derive(secret_key)?;
+    let registered: Vec<CompleteAddress> =
+        decode(pxe.call("getRegisteredAccounts", vec![]).await?)?;
+    let account = match registered.into_iter().find(|a| a.address == *address) {
+        Some(account) => account,
+        None => {
+            let params = vec![json!(secret_key), json!(partial_address)];
+            let account: CompleteAddress = decode(pxe.call("registerAccount", params).await?)?;
+            if account.address != *address {
+                return Err(AddressMismatch::Derived {
+                    configured: address.clone(),
+                    derived: account.address,
+                }
+                .into());
+            }
+            account
+        }
+    };
+    if account.public_keys != public_keys || account.partial_address != *partial_address {
+        return Err(AddressMismatch::Registered {
+            address: address.clone(),
+        }
+        .into());
+    }
+    Ok(account)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aztec_rpc_client::AztecRpcClient;
+    use crate::notes::Bn254Poseidon2;
+    use crate::testing::MockPxe;
+
+    fn fr(hex: &str) -> Fr {
+        Fr::try_from(hex).unwrap()
+    }
+
+    #[test]
+    fn test_derives_keys_and_address() {
+        // aztec-nr's `compute_public_keys_hash` test keys.
+        let point = |x: u8, y: u8| AffinePoint {
+            x: Fr::from(x),
+            y: Fr::from(y),
+        };
+        let keys = PublicKeys {
+            master_nullifier: point(1, 2),
+            master_incoming_viewing: point(3, 4),
+            master_outgoing_viewing: point(5, 6),
+            master_tagging: point(7, 8),
+        };
+        assert_eq!(
+            keys.hash(&Bn254Poseidon2),
+            fr("0x0fecd9a32db731fec1fded1b9ff957a1625c069245a3613a2538bd527068b0ad")
+        );
+
+        let keys = PublicKeys::derive(&Fr::from(0x2au8)).unwrap();
+        assert_eq!(
+            keys.master_incoming_viewing,
+            AffinePoint {
+                x: fr("0x20476ffd1e826dd8f9352bb72b86628840464b017944de119ae2365c0c1c8938"),
+                y: fr("0x132504262036f26f1a7b02ee6e8b47ffd1aff4a8fbf7262665879cb48aec3880"),
+            }
+        );
+        assert_eq!(
+            keys.hash(&Bn254Poseidon2),
+            fr("0x0e3d80e2a2ef640740177bbbbcd40b6664cc4deb87d095d171107e2a1d80a80f")
+        );
+        let account = CompleteAddress::compute(keys, Fr::from(0x0bu8), &Bn254Poseidon2).unwrap();
+        assert_eq!(
+            account.address,
+            fr("0x1258cf06f124d60eca733947c6f10def87131a9718351d14798e2032030e78b5")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_registers_missing_accounts_and_refuses_mismatches() {
+        let secret_key = Fr::from(0x2au8);
+        let partial_address = Fr::from(0x0bu8);
+        let keys = PublicKeys::derive(&secret_key).unwrap();
+        let account =
+            CompleteAddress::compute(keys, partial_address.clone(), &Bn254Poseidon2).unwrap();
+        account.verify(&Bn254Poseidon2).unwrap();
+        let encoded = serde_json::to_value(&account).unwrap();
+        assert_eq!(
+            serde_json::from_value::<CompleteAddress>(encoded.clone()).unwrap(),
+            account
+        );
+
+        let mock = MockPxe::start().await.unwrap();
+        let pxe = AztecRpcClient::new(mock.url(), Some("pxe".to_string()));
+        mock.respond("pxe_getRegisteredAccounts", json!([]));
+        mock.respond("pxe_registerAccount", encoded.clone());
+        let registered = ensure_registered(&pxe, &account.address, &secret_key, &partial_address)
+            .await
+            .unwrap();
+        assert_eq!(registered, account);
+
+        // Already registered, but from another partial address.
+        mock.respond("pxe_getRegisteredAccounts", json!([encoded]));
+        let e = ensure_registered(&pxe, &account.address, &secret_key, &Fr::from(1u8))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            e.downcast_ref::<AddressMismatch>(),
+            Some(&AddressMismatch::Registered {
+                address: account.address.clone()
+            })
+        );
+
+        // The keys belong to another address than the one configured.
+        mock.respond("pxe_getRegisteredAccounts", json!([]));
+        mock.respond("pxe_registerAccount", json!(account));
+        let e = ensure_registered(&pxe, &Fr::from(7u8), &secret_key, &partial_address)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            e.downcast_ref::<AddressMismatch>(),
+            Some(AddressMismatch::Derived { derived, .. }) if *derived == account.address
+        ));
+
+        let mut wrong = account.clone();
+        wrong.address = Fr::from(7u8);
+        assert!(wrong.verify(&Bn254Poseidon2).is_err());
+        assert!(CompleteAddress::from_bytes(&[0; 32]).is_err());
+    }
+}
@@ -0,0 +1,1674 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+
+use crate::alerts::Alert;
+use crate::aztec_rpc_client::AztecRpcClient;
+use crate::block::PublicDataWrite;
+use crate::encoder::{
+    decode_return_values, encode_arguments, get_function_artifact, AbiParameter, AbiType, ArgValue,
+    ContractArtifact, FunctionAbi, FunctionSelector,
+};
+use crate::error::AztecError;
+use crate::fees::{max_fee, FeeBudgetError};
+use crate::fields::Fr;
+use crate::inspect::ArtifactReport;
+use crate::journal::JournalEntry;
+use crate::notes::{Bn254Poseidon2, Poseidon2};
+use crate::pxe_api::{decode, PxeApi};
+use crate::simulation_cache::SimulationKey;
+use crate::simulation_error::SimulationError;
+use crate::timings::Stage;
+use crate::tx_request::{Gas, GasSettings, NodeInfo, TxExecutionRequest};
+use crate::wallet::{EntrypointOptions, FeeOptions, FunctionCall};
+
+/// What `send` returns in dry-run mode, where nothing is sent.
+pub const DRY_RUN_TX_HASH: &str =
+    "0x0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Knobs for `simulateTx`. The defaults are what `simulate`, `prove` and
+/// `send` have always sent: public simulation on, tx validation skipped, the
+/// PXE's own sender and fee-enforcement defaults, every registered scope.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulateOptions {
+    pub simulate_public: bool,
+    pub skip_tx_validation: bool,
+    pub skip_fee_enforcement: Option<bool>,
+    /// Simulate as this account instead of the interaction's `from`.
+    pub msg_sender_override: Option<String>,
+    /// Restrict note access to these accounts.
+    pub scopes: Option<Vec<String>>,
+    /// Go to the PXE even when a `SimulationCache` has the result.
+    pub bypass_cache: bool,
+}
+
+impl Default for SimulateOptions {
+    fn default() -> Self {
+        SimulateOptions {
+            simulate_public: true,
+            skip_tx_validation: true,
+            skip_fee_enforcement: None,
+            msg_sender_override: None,
+            scopes: None,
+            bypass_cache: false,
+        }
+    }
+}
+
+impl SimulateOptions {
+    /// Sender and scopes must be addresses; catch typos before the PXE does.
+    pub fn validate(&self) -> Result<(), String> {
+        let addresses = self
+            .msg_sender_override
+            .iter()
+            .chain(self.scopes.iter().flatten());
+        for address in addresses {
+            Fr::try_from(address.as_str())
+                .map_err(|e| format!("Invalid address '{}': {}", address, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Limits for `send_with`. Each step's timeout is cut short by
+/// `overall_deadline`, and `cancel` aborts whichever step is running.
+/// `sendTx` itself is never interrupted: once it is on the wire the tx may
+/// be out, so both are only checked before it starts.
+#[derive(Debug, Clone, Default)]
+pub struct CallOptions {
+    pub simulate_timeout: Option<Duration>,
+    pub prove_timeout: Option<Duration>,
+    pub overall_deadline: Option<Instant>,
+    pub cancel: CancellationToken,
+}
+
+impl CallOptions {
+    /// Runs `step`, failing it with `Timeout(method)` or `Cancelled`.
+    async fn run<T, E: From<AztecError>>(
+        &self,
+        method: &str,
+        timeout: Option<Duration>,
+        step: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, E> {
+        let deadline = match (timeout.map(|t| Instant::now() + t), self.overall_deadline) {
+            (Some(step), Some(overall)) => Some(step.min(overall)),
+            (step, overall) => step.or(overall),
+        };
+        let limited = async {
+            match deadline {
+                Some(deadline) => tokio::time::timeout_at(deadline, step)
+                    .await
+                    .map_err(|_| AztecError::Timeout(method.to_string()))?,
+                None => step.await,
+            }
+        };
+        tokio::select! {
+            biased;
+            _ = self.cancel.cancelled() => Err(AztecError::Cancelled.into()),
+            result = limited => result,
+        }
+    }
+
+    /// Fails if the call was cancelled or is past its deadline already.
+    fn check(&self, method: &str) -> Result<(), AztecError> {
+        if self.cancel.is_cancelled() {
+            return Err(AztecError::Cancelled);
+        }
+        match self.overall_deadline {
+            Some(deadline) if Instant::now() >= deadline => {
+                Err(AztecError::Timeout(method.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A deployed contract seen through its artifact, like aztec.js' `Contract`:
+/// `contract.method("set_just_field", args)?.send()`.
+pub struct Contract<'a, P: ?Sized = AztecRpcClient> {
+    pxe: &'a P,
+    from: String,
+    address: String,
+    artifact: Arc<ContractArtifact>,
+}
+
+// By hand: deriving would require `P: Clone`.
+impl<P: ?Sized> Clone for Contract<'_, P> {
+    fn clone(&self) -> Self {
+        Contract {
+            pxe: self.pxe,
+            from: self.from.clone(),
+            address: self.address.clone(),
+            artifact: self.artifact.clone(),
+        }
+    }
+}
+
+impl<'a, P: PxeApi + ?Sized> Contract<'a, P> {
+    pub fn at(
+        pxe: &'a P,
+        from: impl Into<String>,
+        address: impl Into<String>,
+        artifact: Arc<ContractArtifact>,
+    ) -> Self {
+        Contract {
+            pxe,
+            from: from.into(),
+            address: address.into(),
+            artifact,
+        }
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    pub fn artifact(&self) -> &ContractArtifact {
+        &self.artifact
+    }
+
+    /// Functions, storage, notes and events the artifact declares.
+    pub fn interface(&self) -> ArtifactReport {
+        ArtifactReport::from_artifact(&self.artifact)
+    }
+
+    /// Looks the function up by name or selector. `args` can be JSON values
+    /// or anything else that converts into an `ArgValue`.
+    pub fn method<A: Into<ArgValue>>(
+        &self,
+        name: &str,
+        args: Vec<A>,
+    ) -> Result<ContractFunctionInteraction<'a, P>, String> {
+        let function = get_function_artifact(&self.artifact, name)?;
+        let mut interaction = ContractFunctionInteraction::new(
+            self.pxe,
+            self.from.clone(),
+            self.address.clone(),
+            function.to_abi(),
+            args,
+        );
+        interaction.artifact = Some(self.artifact.clone());
+        Ok(interaction)
+    }
+
+    /// `method` with arguments given by parameter name instead of position:
+    /// `contract.call("set_field_in_map")?.arg("key", 1).arg("value", 214).build()`.
+    pub fn call(&self, name: &str) -> Result<CallBuilder<'a, P>, String> {
+        let function = get_function_artifact(&self.artifact, name)?;
+        Ok(CallBuilder {
+            contract: self.clone(),
+            function: function.name.clone(),
+            parameters: function.parameters.clone(),
+            args: Vec::new(),
+        })
+    }
+}
+
+/// Arguments for one function, matched to its parameters by name. `build`
+/// puts them in ABI order, once every parameter has exactly one.
+pub struct CallBuilder<'a, P: ?Sized = AztecRpcClient> {
+    contract: Contract<'a, P>,
+    function: String,
+    parameters: Vec<AbiParameter>,
+    args: Vec<(String, ArgValue)>,
+}
+
+impl<'a, P: PxeApi + ?Sized> CallBuilder<'a, P> {
+    pub fn arg(mut self, name: impl Into<String>, value: impl Into<ArgValue>) -> Self {
+        self.args.push((name.into(), value.into()));
+        self
+    }
+
+    /// The arguments in parameter order.
+    pub fn positional(&self) -> Result<Vec<ArgValue>, String> {
+        if let Some((name, _)) = self
+            .args
+            .iter()
+            .find(|(name, _)| !self.parameters.iter().any(|p| p.name == *name))
+        {
+            return Err(format!("{} has no parameter `{}`", self.function, name));
+        }
+        let mut missing = vec![];
+        let mut positional = vec![];
+        for parameter in &self.parameters {
+            let mut given = self.args.iter().filter(|(name, _)| *name == parameter.name);
+            match (given.next(), given.next()) {
+                (Some((_, value)), None) => positional.push(value.clone()),
+                (Some(_), Some(_)) => {
+                    return Err(format!("`{}` is given more than once", parameter.name))
+                }
+                (None, _) => missing.push(parameter.name.as_str()),
+            }
+        }
+        if !missing.is_empty() {
+            return Err(format!(
+                "{} is missing {}",
+                self.function,
+                missing
+                    .iter()
+                    .map(|name| format!("`{}`", name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        Ok(positional)
+    }
+
+    pub fn build(&self) -> Result<ContractFunctionInteraction<'a, P>, String> {
+        self.contract.method(&self.function, self.positional()?)
+    }
+}
+
+/// A call to a single contract function, mirroring aztec.js'
+/// `ContractFunctionInteraction`: `simulate` → `prove` → `send`.
+pub struct ContractFunctionInteraction<'a, P: ?Sized = AztecRpcClient> {
+    pxe: &'a P,
+    from: String,
+    contract_address: String,
+    artifact: Option<Arc<ContractArtifact>>,
+    function: FunctionAbi,
+    args: Vec<ArgValue>,
+    preceding: Vec<FunctionCall>,
+    nonces: Option<(Fr, Fr)>,
+}
+
+impl<'a, P: PxeApi + ?Sized> ContractFunctionInteraction<'a, P> {
+    pub fn new<A: Into<ArgValue>>(
+        pxe: &'a P,
+        from: impl Into<String>,
+        contract_address: impl Into<String>,
+        function: FunctionAbi,
+        args: Vec<A>,
+    ) -> Self {
+        ContractFunctionInteraction {
+            pxe,
+            from: from.into(),
+            contract_address: contract_address.into(),
+            artifact: None,
+            function,
+            args: args.into_iter().map(Into::into).collect(),
+            preceding: vec![],
+            nonces: None,
+        }
+    }
+
+    /// Calls the account makes before this one in the same tx, as a
+    /// deployment publishes its instance before the constructor runs.
+    pub fn with_preceding(mut self, calls: Vec<FunctionCall>) -> Self {
+        self.preceding = calls;
+        self
+    }
+
+    /// Fixes the app and fee payload nonces, which are otherwise random
+    /// per tx, so a recorded send is rebuilt byte for byte (see
+    /// `wallet::entrypoint_nonces`).
+    pub fn with_nonces(mut self, app: Fr, fee: Fr) -> Self {
+        self.nonces = Some((app, fee));
+        self
+    }
+
+    pub fn contract_address(&self) -> &str {
+        &self.contract_address
+    }
+
+    pub fn parameters(&self) -> &[AbiParameter] {
+        &self.function.parameters
+    }
+
+    pub fn selector(&self) -> FunctionSelector {
+        FunctionSelector::from_name_and_parameters(&self.function.name, &self.function.parameters)
+    }
+
+    pub fn encode_args(&self) -> Result<Vec<Fr>, String> {
+        encode_arguments(self.function.clone(), self.args.clone())
+    }
+
+    /// The tx request for this call from `from`'s account: the call wrapped
+    /// in the account's entrypoint, its payloads signed by the client's
+    /// wallet for `from`. Chain id and rollup version are the node's; gas
+    /// limits are the profiler's, once it has seen enough calls.
+    pub async fn create(&self) -> Result<Value, AztecError> {
+        self.create_as(&self.from).await
+    }
+
+    async fn create_as(&self, origin: &str) -> Result<Value, AztecError> {
+        let mut calls = self.preceding.clone();
+        calls.push(self.request()?);
+        let wallet = self.pxe.wallet(origin).ok_or_else(|| {
+            AztecError::Account(format!("No wallet signs for account {}", origin))
+        })?;
+        let node_info = self.pxe.get_node_info().await?;
+        let mut gas_settings = GasSettings::default();
+        if let Some(profiler) = self.pxe.gas_profiler() {
+            let suggested = profiler
+                .suggest(
+                    &self.contract_address,
+                    &self.selector(),
+                    gas_settings.gas_limits,
+                )
+                .map_err(AztecError::State)?;
+            if let Some(limits) = suggested {
+                gas_settings.gas_limits = limits;
+            }
+        }
+        let (nonce, fee_nonce) = self.nonces.clone().unzip();
+        let options = EntrypointOptions {
+            fee: FeeOptions {
+                gas_settings,
+                nonce: fee_nonce,
+                ..FeeOptions::default()
+            },
+            nonce,
+            ..EntrypointOptions::default()
+        };
+        let request = wallet
+            .create_tx_execution_request(calls, options, &node_info, &Bn254Poseidon2)
+            .map_err(AztecError::Account)?;
+        Ok(request.to_json())
+    }
+
+    /// The tx request for this call, built without the PXE: for signing on
+    /// an air-gapped machine, or for tests. The contract is called directly
+    /// (aztec.js' `DefaultEntrypoint`) rather than through `from`'s account,
+    /// so there is no entrypoint payload or auth witness. Chain id and rollup
+    /// version come from `node_info`; the args are hashed with `hasher`.
+    pub fn build<H: Poseidon2>(
+        &self,
+        node_info: &NodeInfo,
+        hasher: &H,
+    ) -> Result<TxExecutionRequest, AztecError> {
+        let args = self.encode_args().map_err(AztecError::Encoding)?;
+        let contract =
+            Fr::try_from(self.contract_address.as_str()).map_err(AztecError::Encoding)?;
+        Ok(TxExecutionRequest::direct_call(
+            contract,
+            self.selector(),
+            args,
+            node_info,
+            hasher,
+        ))
+    }
+
+    /// This call as one of the `FunctionCall`s an account's entrypoint
+    /// makes, for `AccountWallet::create_tx_execution_request`.
+    pub fn request(&self) -> Result<FunctionCall, AztecError> {
+        let args = self.encode_args().map_err(AztecError::Encoding)?;
+        let to = Fr::try_from(self.contract_address.as_str()).map_err(AztecError::Encoding)?;
+        Ok(FunctionCall {
+            to,
+            selector: self.selector(),
+            args,
+            is_public: self.function.function_type == "public",
+            is_static: self.function.isStatic,
+        })
+    }
+
+    /// Simulates the tx and renders what `send` would submit, for a human
+    /// to check before it goes out.
+    pub async fn describe(&self) -> Result<TxPreview, Box<dyn std::error::Error>> {
+        let tx_request = self.create().await?;
+        let simulation = self
+            .simulate_request(tx_request.clone(), &SimulateOptions::default())
+            .await?;
+        Ok(self.preview(&tx_request, &simulation)?)
+    }
+
+    fn preview(&self, tx_request: &Value, simulation: &Value) -> Result<TxPreview, String> {
+        let request = TxExecutionRequest::from_json(tx_request.clone())?;
+        let args = self
+            .function
+            .parameters
+            .iter()
+            .zip(&self.args)
+            .map(|(parameter, arg)| PreviewArg {
+                name: parameter.name.clone(),
+                abi_type: parameter.abi_type.to_string(),
+                value: render_arg(arg),
+            })
+            .collect();
+        Ok(TxPreview {
+            contract_name: self.artifact.as_ref().map(|a| a.name.clone()),
+            contract_address: self.contract_address.clone(),
+            function: self.function.name.clone(),
+            selector: self.selector().0,
+            args,
+            from: request.origin.to_hex(),
+            // No fee payment method is supported yet: the account pays.
+            fee_payer: request.origin.to_hex(),
+            gas_settings: request.tx_context.gas_settings,
+            estimated_fee: estimate_fee(tx_request, simulation)?,
+        })
+    }
+
+    pub async fn simulate(
+        &self,
+        options: SimulateOptions,
+    ) -> Result<Value, Box<dyn std::error::Error>> {
+        options.validate()?;
+        let Some(cache) = self
+            .pxe
+            .simulation_cache()
+            .filter(|_| !options.bypass_cache)
+        else {
+            let tx_request = self.create().await?;
+            return Ok(self.simulate_request(tx_request, &options).await?);
+        };
+        let block = match cache.block() {
+            Some(block) => block,
+            None => self.pxe.get_block_number().await?,
+        };
+        let request = json!([
+            self.from,
+            self.encode_args()?,
+            options.simulate_public,
+            options.skip_tx_validation,
+            options.skip_fee_enforcement,
+            options.msg_sender_override,
+            options.scopes,
+        ]);
+        let key = SimulationKey::new(&self.contract_address, self.selector(), &request, block);
+        // Looked up before the tx request is built: a hit needs nothing
+        // from the PXE but, without a watcher, the block number.
+        if let Some(cached) = cache.get(&key) {
+            return Ok(cached);
+        }
+        let tx_request = self.create().await?;
+        let simulation = self.simulate_request(tx_request, &options).await?;
+        cache.insert(key, simulation.clone());
+        Ok(simulation)
+    }
+
+    /// `simulate`, keeping the function's ABI so the result can decode what
+    /// it returned.
+    pub async fn simulate_result(
+        &self,
+        options: SimulateOptions,
+    ) -> Result<SimulationResult, Box<dyn std::error::Error>> {
+        let raw = self.simulate(options).await?;
+        Ok(SimulationResult::new(raw, &self.function))
+    }
+
+    pub async fn prove(&self) -> Result<Value, Box<dyn std::error::Error>> {
+        let tx_request = self.create().await?;
+        let simulation = self
+            .simulate_request(tx_request.clone(), &SimulateOptions::default())
+            .await?;
+        Ok(self.prove_simulated(tx_request, &simulation).await?)
+    }
+
+    /// In dry-run mode (`AztecRpcClient::with_dry_run`) this only simulates,
+    /// logs the effects and returns `DRY_RUN_TX_HASH`. With a fee budget
+    /// (`AztecRpcClient::with_fee_budget`) the tx's worst-case fee is reserved
+    /// first, and the send refused if that would break a limit. With a
+    /// sender pool (`AztecRpcClient::with_sender_pool`) the tx goes out from
+    /// the pool's least busy account rather than `from`. With a send
+    /// confirmation (`AztecRpcClient::with_send_confirmation`) the `describe`
+    /// preview must be accepted before anything is proven.
+    pub async fn send(&self) -> Result<String, Box<dyn std::error::Error>> {
+        self.send_with(CallOptions::default()).await
+    }
+
+    /// `send` with timeouts and cancellation. A send that stops early,
+    /// including between `proveTx` and `sendTx`, gives back its fee budget
+    /// and sender pool reservations.
+    pub async fn send_with(
+        &self,
+        options: CallOptions,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        if self.pxe.dry_run() {
+            options
+                .run("simulateTx", options.simulate_timeout, self.dry_run())
+                .await?;
+            return Ok(DRY_RUN_TX_HASH.to_string());
+        }
+
+        let Some(pool) = self.pxe.sender_pool() else {
+            return self.send_as(&self.from, &options).await;
+        };
+        let (id, sender) = pool.reserve(&self.contract_address)?;
+        let sent = self.send_as(&sender, &options).await;
+        match &sent {
+            Ok(tx_hash) => pool.assign(id, tx_hash),
+            Err(_) => pool.release(id),
+        }
+        sent
+    }
+
+    // One `tx.send` span over the simulate, prove and send calls.
+    async fn send_as(
+        &self,
+        origin: &str,
+        options: &CallOptions,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let span = tracing::info_span!(
+            "tx.send",
+            contract = %self.contract_address,
+            function = %self.function.name,
+            from = origin,
+            tx_hash = tracing::field::Empty,
+        );
+        let sent = self
+            .submit_as(origin, options)
+            .instrument(span.clone())
+            .await;
+        if let Ok(tx_hash) = &sent {
+            span.record("tx_hash", tx_hash.as_str());
+        }
+        sent
+    }
+
+    async fn submit_as(
+        &self,
+        origin: &str,
+        options: &CallOptions,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let tx_request = self.create_as(origin).await?;
+        let simulation = options
+            .run(
+                "simulateTx",
+                options.simulate_timeout,
+                self.simulate_request(tx_request.clone(), &SimulateOptions::default()),
+            )
+            .await
+            .inspect_err(|e| self.alert_revert(e))?;
+        if let Some(confirmation) = self.pxe.send_confirmation() {
+            if !confirmation.confirm(&self.preview(&tx_request, &simulation)?) {
+                return Err(AztecError::Cancelled.into());
+            }
+        }
+        let reservation = match self.pxe.fee_budget() {
+            Some(budget) => {
+                let fee = estimate_fee(&tx_request, &simulation)?;
+                let id = budget.reserve(fee, unix_now()).inspect_err(|e| {
+                    if !matches!(e, FeeBudgetError::Store(_)) {
+                        self.pxe.alert(Alert::FeeBudgetExceeded {
+                            reason: e.to_string(),
+                        });
+                    }
+                })?;
+                Some((budget, id))
+            }
+            None => None,
+        };
+
+        let sent: Result<String, AztecError> = async {
+            let proving_result = options
+                .run(
+                    "proveTx",
+                    options.prove_timeout,
+                    self.prove_simulated(tx_request, &simulation),
+                )
+                .await?;
+            options.check("sendTx")?;
+            let tx = tx_from_proving_result(&proving_result);
+            let Some(journal) = self.pxe.tx_journal() else {
+                return decode(
+                    self.timed(Stage::Send, self.pxe.call("sendTx", vec![tx]))
+                        .await?,
+                );
+            };
+            let entry = JournalEntry {
+                contract: self.contract_address.clone(),
+                function: self.function.name.clone(),
+                from: origin.to_string(),
+                tx: tx.clone(),
+                tx_hash: None,
+                cancelled: false,
+            };
+            let id = journal.record(&entry).map_err(AztecError::State)?;
+            // Only a refusal is known not to have reached the chain; after a
+            // transport error the entry stays for `TxJournal::recover`.
+            let sent = self
+                .timed(Stage::Send, self.pxe.call("sendTx", vec![tx]))
+                .await;
+            match &sent {
+                Ok(_) | Err(AztecError::Transport(_)) | Err(AztecError::Timeout(_)) => {}
+                Err(_) => journal.discard(&id).map_err(AztecError::State)?,
+            }
+            let tx_hash: String = decode(sent?)?;
+            journal.sent(&id, &tx_hash).map_err(AztecError::State)?;
+            Ok(tx_hash)
+        }
+        .await;
+
+        if let Some((budget, id)) = reservation {
+            match &sent {
+                Ok(tx_hash) => budget.assign(id, tx_hash)?,
+                Err(_) => budget.release(id)?,
+            }
+        }
+        if let (Ok(tx_hash), Some(profiler)) = (&sent, self.pxe.gas_profiler()) {
+            let gas_used = TxEffects::from_simulation(&simulation)?.gas_used;
+            if let Some(gas) = gas_used {
+                profiler.track(tx_hash, &self.contract_address, &self.selector(), gas)?;
+            }
+        }
+        Ok(sent?)
+    }
+
+    /// Simulates the tx `send` would submit and reports what it would change.
+    pub async fn dry_run(&self) -> Result<TxEffects, Box<dyn std::error::Error>> {
+        let tx_request = self.create().await?;
+        let simulation = self
+            .simulate_request(tx_request.clone(), &SimulateOptions::default())
+            .await?;
+        print!("{}", self.preview(&tx_request, &simulation)?);
+        let effects = TxEffects::from_simulation(&simulation)?;
+        println!(
+            "Dry run {}.{}: {} storage writes, {} note hashes, {} nullifiers, gas {:?}",
+            self.contract_address,
+            self.function.name,
+            effects.public_data_writes.len(),
+            effects.note_hashes.len(),
+            effects.nullifiers.len(),
+            effects.gas_used
+        );
+        for write in &effects.public_data_writes {
+            println!("  {} <- {}", write.leaf_slot.to_hex(), write.value.to_hex());
+        }
+        Ok(effects)
+    }
+
+    async fn prove_simulated(
+        &self,
+        tx_request: Value,
+        simulation: &Value,
+    ) -> Result<Value, AztecError> {
+        let params = vec![
+            self.pxe.profile().tx_request(tx_request),
+            simulation["privateExecutionResult"].clone(),
+        ];
+        self.timed(Stage::Prove, self.pxe.call("proveTx", params))
+            .await
+    }
+
+    async fn simulate_request(
+        &self,
+        tx_request: Value,
+        options: &SimulateOptions,
+    ) -> Result<Value, AztecError> {
+        let params = self.pxe.profile().simulate_params(tx_request, options);
+        self.timed(Stage::Simulate, self.pxe.call("simulateTx", params))
+            .await
+            .map_err(|e| self.resolve_failure(e))
+    }
+
+    // Successful calls only: a failure's duration says little about the
+    // function.
+    async fn timed<T>(
+        &self,
+        stage: Stage,
+        call: impl Future<Output = Result<T, AztecError>>,
+    ) -> Result<T, AztecError> {
+        let Some(timings) = self.pxe.call_timings() else {
+            return call.await;
+        };
+        let started = Instant::now();
+        let result = call.await;
+        if result.is_ok() {
+            let elapsed = started.elapsed();
+            let function = &self.function.name;
+            if let Err(e) = timings.observe(
+                &self.contract_address,
+                &self.selector(),
+                function,
+                stage,
+                elapsed,
+            ) {
+                println!("Could not record {} time of {}: {}", stage, function, e);
+            }
+        }
+        result
+    }
+
+    fn alert_revert(&self, error: &AztecError) {
+        let message = match error {
+            AztecError::Simulation { failure, .. } => failure.message.clone(),
+            AztecError::Rpc { error, .. } => {
+                let message = error["message"].as_str().unwrap_or_default();
+                let reason = message.to_lowercase();
+                if !reason.contains("revert") && !reason.contains("assertion failed") {
+                    return;
+                }
+                message.to_string()
+            }
+            _ => return,
+        };
+        self.pxe.alert(Alert::TxReverted {
+            contract: self.contract_address.clone(),
+            function: self.function.name.clone(),
+            message,
+        });
+    }
+
+    // Assertion failures come back as opcode offsets; with the artifact at
+    // hand they can point at the Noir source instead.
+    fn resolve_failure(&self, error: AztecError) -> AztecError {
+        let (AztecError::Rpc { error: rpc, .. }, Some(artifact)) = (&error, &self.artifact) else {
+            return error;
+        };
+        let failure = SimulationError::from_rpc_error(rpc, |address| {
+            address
+                .eq_ignore_ascii_case(&self.contract_address)
+                .then(|| artifact.clone())
+        });
+        match failure {
+            Some(failure) => AztecError::Simulation {
+                error: rpc.clone(),
+                failure,
+            },
+            None => error,
+        }
+    }
+}
+
+/// Asked before a send is proven; `false` stops it (`AztecError::Cancelled`).
+pub trait ConfirmSend: fmt::Debug + Send + Sync {
+    fn confirm(&self, preview: &TxPreview) -> bool;
+}
+
+/// What a send would submit, as `describe` reports it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxPreview {
+    pub contract_name: Option<String>,
+    pub contract_address: String,
+    pub function: String,
+    pub selector: String,
+    pub args: Vec<PreviewArg>,
+    pub from: String,
+    pub fee_payer: String,
+    pub gas_settings: GasSettings,
+    /// Worst case, as `estimate_fee` works it out.
+    pub estimated_fee: u128,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewArg {
+    pub name: String,
+    pub abi_type: String,
+    pub value: String,
+}
+
+impl fmt::Display for TxPreview {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let contract = self.contract_name.as_deref().unwrap_or("contract");
+        writeln!(
+            f,
+            "{}.{} at {} (selector 0x{})",
+            contract, self.function, self.contract_address, self.selector
+        )?;
+        writeln!(f, "  from:          {}", self.from)?;
+        for arg in &self.args {
+            writeln!(f, "  {}: {} = {}", arg.name, arg.abi_type, arg.value)?;
+        }
+        let settings = &self.gas_settings;
+        writeln!(
+            f,
+            "  gas limits:    {} DA, {} L2 (teardown {} DA, {} L2)",
+            settings.gas_limits.da_gas,
+            settings.gas_limits.l2_gas,
+            settings.teardown_gas_limits.da_gas,
+            settings.teardown_gas_limits.l2_gas
+        )?;
+        writeln!(
+            f,
+            "  max fees:      {} per DA gas, {} per L2 gas",
+            settings.max_fees_per_gas.fee_per_da_gas.0, settings.max_fees_per_gas.fee_per_l2_gas.0
+        )?;
+        writeln!(f, "  fee payer:     {}", self.fee_payer)?;
+        writeln!(f, "  estimated fee: {}", self.estimated_fee)
+    }
+}
+
+fn render_arg(arg: &ArgValue) -> String {
+    match arg {
+        ArgValue::Fr(value) => value.to_hex(),
+        ArgValue::Json(value) => value.to_string(),
+        ArgValue::Address(address) => address.to_string(),
+        ArgValue::Bool(value) => value.to_string(),
+        ArgValue::U128(value) => value.to_string(),
+        ArgValue::I128(value) => value.to_string(),
+        ArgValue::Str(value) => format!("{:?}", value),
+    }
+}
+
+/// The decoded `publicOutput` of a `simulateTx` result: the tx effect the
+/// sequencer would include, and the gas it used.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxEffects {
+    pub public_data_writes: Vec<PublicDataWrite>,
+    pub note_hashes: Vec<Fr>,
+    pub nullifiers: Vec<Fr>,
+    pub gas_used: Option<Gas>,
+}
+
+impl TxEffects {
+    pub fn from_simulation(simulation: &Value) -> Result<Self, String> {
+        let output = &simulation["publicOutput"];
+        let effect = &output["txEffect"];
+        Ok(TxEffects {
+            public_data_writes: effect_list(effect, "publicDataWrites")?,
+            note_hashes: effect_list(effect, "noteHashes")?,
+            nullifiers: effect_list(effect, "nullifiers")?,
+            gas_used: match &output["gasUsed"]["totalGas"] {
+                Value::Null => None,
+                gas => Some(
+                    serde_json::from_value(gas.clone())
+                        .map_err(|e| format!("Unexpected gas in simulation output: {}", e))?,
+                ),
+            },
+        })
+    }
+}
+
+fn effect_list<T: DeserializeOwned>(effect: &Value, key: &str) -> Result<Vec<T>, String> {
+    match &effect[key] {
+        Value::Null => Ok(vec![]),
+        value => serde_json::from_value(value.clone())
+            .map_err(|e| format!("Unexpected `{}` in simulation output: {}", key, e)),
+    }
+}
+
+/// Worst-case fee of a simulated tx: the gas it used (its limits when the
+/// simulation doesn't say) at the request's max fees per gas.
+pub fn estimate_fee(tx_request: &Value, simulation: &Value) -> Result<u128, String> {
+    let settings = TxExecutionRequest::from_json(tx_request.clone())?
+        .tx_context
+        .gas_settings;
+    let gas = TxEffects::from_simulation(simulation)?
+        .gas_used
+        .unwrap_or(settings.gas_limits);
+    Ok(max_fee(gas, &settings.max_fees_per_gas))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Return values of the first public call in a `simulateTx` result
+/// (`TxSimulationResult.getPublicReturnValues()[0].values`).
+pub fn public_return_values(simulation: &Value) -> Result<Vec<Fr>, String> {
+    match &simulation["publicOutput"]["publicReturnValues"][0]["values"] {
+        Value::Null => Ok(vec![]),
+        Value::Array(values) => values
+            .iter()
+            .map(|v| Fr::try_from(v.as_str().unwrap_or_default()))
+            .collect(),
+        other => Err(format!("Unexpected public return values: {}", other)),
+    }
+}
+
+/// Return values of the private entrypoint in a `simulateTx` result. Calls
+/// go to the contract directly, so that is the called function.
+pub fn private_return_values(simulation: &Value) -> Result<Vec<Fr>, String> {
+    match &simulation["privateExecutionResult"]["entrypoint"]["returnValues"] {
+        Value::Null => Ok(vec![]),
+        Value::Array(values) => values
+            .iter()
+            .map(|v| Fr::try_from(v.as_str().unwrap_or_default()))
+            .collect(),
+        other => Err(format!("Unexpected private return values: {}", other)),
+    }
+}
+
+/// A `simulateTx` result and the ABI of the function it simulated.
+#[derive(Debug, Clone)]
+pub struct SimulationResult {
+    pub raw: Value,
+    function_type: String,
+    return_types: Vec<AbiType>,
+}
+
+impl SimulationResult {
+    pub fn new(raw: Value, function: &FunctionAbi) -> Self {
+        SimulationResult {
+            raw,
+            function_type: function.function_type.clone(),
+            return_types: function.return_types.clone(),
+        }
+    }
+
+    /// What the function returned, as fields: from its public call for a
+    /// public function, else from the private execution.
+    pub fn return_fields(&self) -> Result<Vec<Fr>, String> {
+        match self.function_type.as_str() {
+            "public" => public_return_values(&self.raw),
+            _ => private_return_values(&self.raw),
+        }
+    }
+
+    /// The return values decoded by the function's `returnTypes`, one per
+    /// type, the way `decode_arguments` renders arguments.
+    pub fn returns(&self) -> Result<Vec<Value>, String> {
+        decode_return_values(&self.return_types, &self.return_fields()?)
+    }
+}
+
+// Equivalent of `TxProvingResult.toTx()`.
+fn tx_from_proving_result(proving_result: &Value) -> Value {
+    let or_empty = |key: &str| match &proving_result[key] {
+        Value::Null => json!([]),
+        value => value.clone(),
+    };
+
+    json!({
+        "data": proving_result["publicInputs"],
+        "clientIvcProof": proving_result["clientIvcProof"],
+        "contractClassLogPreimages": or_empty("contractClassLogPreimages"),
+        "publicFunctionCalldata": or_empty("publicFunctionCalldata"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::tests::Collect;
+    use crate::fees::{FeeBudget, FeeLimits};
+    use crate::gas::{GasProfiler, GasProfilerConfig};
+    use crate::journal::TxJournal;
+    use crate::pxe_api::OfflinePxe;
+    use crate::senders::{SenderPool, SenderPoolConfig};
+    use crate::state::StateStore;
+    use crate::testing::{fixtures, test_wallet, MockPxe};
+    use crate::timings::CallTimings;
+    use crate::tx_request::DEFAULT_ORIGIN;
+
+    const OTHER_ACCOUNT: &str =
+        "0x0a2b3c4d5e6f708192a3b4c5d6e7f8091a2b3c4d5e6f708192a3b4c5d6e7f801";
+
+    fn set_just_field_abi() -> FunctionAbi {
+        get_function_artifact(&fixtures().artifact, "set_just_field")
+            .unwrap()
+            .to_abi()
+    }
+
+    #[tokio::test]
+    async fn test_simulate_passes_sender_override_and_scopes() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond("pxe_simulateTx", json!({ "privateExecutionResult": {} }));
+        let pxe = mock.client();
+
+        let options = SimulateOptions {
+            skip_tx_validation: false,
+            skip_fee_enforcement: Some(true),
+            msg_sender_override: Some(OTHER_ACCOUNT.to_string()),
+            scopes: Some(vec![DEFAULT_ORIGIN.to_string()]),
+            ..SimulateOptions::default()
+        };
+        ContractFunctionInteraction::new(
+            &pxe,
+            DEFAULT_ORIGIN,
+            DEFAULT_ORIGIN,
+            set_just_field_abi(),
+            vec![json!(1)],
+        )
+        .simulate(options)
+        .await
+        .unwrap();
+
+        let params = mock.requests()[1]["params"].clone();
+        assert_eq!(
+            params.as_array().unwrap()[1..],
+            [
+                json!(true),
+                json!(OTHER_ACCOUNT),
+                json!(false),
+                json!(true),
+                json!([DEFAULT_ORIGIN]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_simulate_rejects_bad_scope_before_calling_pxe() {
+        let mock = MockPxe::start().await.unwrap();
+        let pxe = mock.client();
+
+        let options = SimulateOptions {
+            scopes: Some(vec!["not-an-address".to_string()]),
+            ..SimulateOptions::default()
+        };
+        let err = ContractFunctionInteraction::new(
+            &pxe,
+            DEFAULT_ORIGIN,
+            DEFAULT_ORIGIN,
+            set_just_field_abi(),
+            vec![json!(1)],
+        )
+        .simulate(options)
+        .await
+        .unwrap_err();
+
+        assert!(err.to_string().contains("not-an-address"), "{}", err);
+        assert!(mock.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_send_only_simulates() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond(
+            "pxe_simulateTx",
+            json!({
+                "privateExecutionResult": {},
+                "publicOutput": {
+                    "txEffect": {
+                        "publicDataWrites": [{ "leafSlot": "0x2a", "value": "0xd6" }],
+                        "noteHashes": [],
+                        "nullifiers": ["0x01"],
+                    },
+                    "gasUsed": { "totalGas": { "daGas": 1024, "l2Gas": 30000 } },
+                },
+            }),
+        );
+        let pxe = mock.client().with_dry_run(true);
+        let interaction = ContractFunctionInteraction::new(
+            &pxe,
+            DEFAULT_ORIGIN,
+            DEFAULT_ORIGIN,
+            set_just_field_abi(),
+            vec![json!(214)],
+        );
+
+        assert_eq!(interaction.send().await.unwrap(), DRY_RUN_TX_HASH);
+        let methods: Vec<Value> = mock
+            .requests()
+            .iter()
+            .map(|r| r["method"].clone())
+            .collect();
+        assert_eq!(
+            methods,
+            vec![json!("pxe_getNodeInfo"), json!("pxe_simulateTx")]
+        );
+
+        let refused = pxe.request::<Value>("sendTx", vec![]).await.unwrap_err();
+        assert!(refused.to_string().contains("dry-run"), "{}", refused);
+        assert_eq!(mock.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_send_stays_within_fee_budget() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond(
+            "pxe_simulateTx",
+            json!({
+                "privateExecutionResult": {},
+                "publicOutput": { "gasUsed": { "totalGas": { "daGas": 0, "l2Gas": 1000 } } },
+            }),
+        );
+        mock.respond("pxe_proveTx", json!({}));
+        mock.respond("pxe_sendTx", json!("0xabc"));
+        mock.respond(
+            "pxe_getTxReceipt",
+            json!({ "txHash": "0xabc", "status": "success", "transactionFee": "0x64" }),
+        );
+        // The recorded request pays up to 0x2aa8 per unit of L2 gas.
+        let worst_case = 1000 * 0x2aa8;
+        let budget = Arc::new(FeeBudget::new(
+            FeeLimits {
+                max_tx_fee: Some(worst_case),
+                hourly_cap: Some(worst_case + 100),
+                daily_cap: None,
+            },
+            Arc::new(StateStore::in_memory()),
+        ));
+        let alerts = Arc::new(Collect::default());
+        let pxe = mock
+            .client()
+            .with_fee_budget(budget.clone())
+            .with_alert_sink(alerts.clone());
+        let interaction = ContractFunctionInteraction::new(
+            &pxe,
+            DEFAULT_ORIGIN,
+            DEFAULT_ORIGIN,
+            set_just_field_abi(),
+            vec![json!(214)],
+        );
+
+        assert_eq!(interaction.send().await.unwrap(), "0xabc");
+        let refused = interaction.send().await.unwrap_err();
+        assert!(refused.to_string().contains("hourly cap"), "{}", refused);
+        assert_eq!(budget.refusals(), 1);
+        assert!(matches!(
+            &alerts.0.lock().unwrap()[..],
+            [Alert::FeeBudgetExceeded { reason }] if reason.contains("hourly cap")
+        ));
+        assert_eq!(mock.requests().len(), 6);
+
+        // Once the receipt shows the actual fee there is room again.
+        pxe.get_tx_receipt("0xabc").await.unwrap();
+        assert_eq!(budget.spent_since(0).unwrap(), 100);
+        interaction.send().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_send_learns_gas_limits_from_receipts() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond(
+            "pxe_simulateTx",
+            json!({
+                "privateExecutionResult": {},
+                "publicOutput": { "gasUsed": { "totalGas": { "daGas": 100, "l2Gas": 1000 } } },
+            }),
+        );
+        mock.respond("pxe_proveTx", json!({}));
+        mock.respond("pxe_sendTx", json!("0xabc"));
+        mock.respond(
+            "pxe_getTxReceipt",
+            json!({ "txHash": "0xabc", "status": "success" }),
+        );
+        let config = GasProfilerConfig {
+            min_samples: 1,
+            ..Default::default()
+        };
+        let profiler = Arc::new(GasProfiler::new(config, Arc::new(StateStore::in_memory())));
+        let pxe = mock.client().with_gas_profiler(profiler);
+        let interaction = ContractFunctionInteraction::new(
+            &pxe,
+            DEFAULT_ORIGIN,
+            DEFAULT_ORIGIN,
+            set_just_field_abi(),
+            vec![json!(214)],
+        );
+        let gas_limits = |request: Value| request["txContext"]["gasSettings"]["gasLimits"].clone();
+
+        // The recorded limits until a receipt confirms the first call.
+        let recorded = json!({ "daGas": 1_000_000_000u64, "l2Gas": 1_000_000_000u64 });
+        assert_eq!(gas_limits(interaction.create().await.unwrap()), recorded);
+        interaction.send().await.unwrap();
+        assert_eq!(gas_limits(interaction.create().await.unwrap()), recorded);
+
+        pxe.get_tx_receipt("0xabc").await.unwrap();
+        assert_eq!(
+            gas_limits(interaction.create().await.unwrap()),
+            json!({ "daGas": 120, "l2Gas": 1200 })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_times_each_stage() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond("pxe_simulateTx", json!({ "privateExecutionResult": {} }));
+        mock.respond("pxe_proveTx", json!({}));
+        // Refused, so not timed.
+        mock.respond_with_envelope(
+            "pxe_sendTx",
+            json!({ "jsonrpc": "2.0", "id": 1, "error": { "code": -32000, "message": "nope" } }),
+        );
+        let timings = Arc::new(CallTimings::new(Arc::new(StateStore::in_memory())));
+        let pxe = mock.client().with_call_timings(timings.clone());
+        let interaction = ContractFunctionInteraction::new(
+            &pxe,
+            DEFAULT_ORIGIN,
+            DEFAULT_ORIGIN,
+            set_just_field_abi(),
+            vec![json!(214)],
+        );
+        assert!(interaction.send().await.is_err());
+
+        let stages: Vec<_> = timings
+            .snapshot()
+            .unwrap()
+            .into_iter()
+            .map(|timing| (timing.function, timing.stage, timing.histogram.count))
+            .collect();
+        assert_eq!(stages.len(), 2);
+        assert!(stages.contains(&("set_just_field".to_string(), Stage::Prove, 1)));
+        assert!(stages.contains(&("set_just_field".to_string(), Stage::Simulate, 1)));
+    }
+
+    #[tokio::test]
+    async fn test_send_rotates_through_sender_pool() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond("pxe_simulateTx", json!({ "privateExecutionResult": {} }));
+        mock.respond("pxe_proveTx", json!({}));
+        mock.respond("pxe_sendTx", json!("0xabc"));
+        let pool = Arc::new(SenderPool::new(
+            SenderPoolConfig::parse("0x0a,0x0b", Some("1")).unwrap(),
+        ));
+        let pxe = mock
+            .client()
+            .with_wallet(test_wallet("0x0a"))
+            .with_wallet(test_wallet("0x0b"))
+            .with_sender_pool(pool.clone());
+        let interaction = ContractFunctionInteraction::new(
+            &pxe,
+            DEFAULT_ORIGIN,
+            DEFAULT_ORIGIN,
+            set_just_field_abi(),
+            vec![json!(214)],
+        );
+
+        interaction.send().await.unwrap();
+        interaction.send().await.unwrap();
+        let busy = interaction.send().await.unwrap_err();
+        assert!(busy.to_string().contains("pending"), "{}", busy);
+        let origins: Vec<Value> = mock
+            .requests()
+            .iter()
+            .filter(|r| r["method"] == "pxe_simulateTx")
+            .map(|r| r["params"][0]["origin"].clone())
+            .collect();
+        assert_eq!(
+            origins,
+            vec![
+                json!(Fr::from(10u8).to_hex()),
+                json!(Fr::from(11u8).to_hex())
+            ]
+        );
+        assert_eq!(pool.pending("0x0a"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_send_journals_proven_tx_until_receipt() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond("pxe_simulateTx", json!({ "privateExecutionResult": {} }));
+        mock.respond("pxe_proveTx", json!({ "tx": { "data": 1 } }));
+        mock.respond_with_envelope(
+            "pxe_sendTx",
+            json!({ "jsonrpc": "2.0", "id": 1, "error": { "message": "Invalid tx" } }),
+        );
+        mock.respond("pxe_sendTx", json!("0xabc"));
+        mock.respond(
+            "pxe_getTxReceipt",
+            json!({ "txHash": "0xabc", "status": "success" }),
+        );
+        let journal = Arc::new(TxJournal::new(Arc::new(StateStore::in_memory())));
+        let pxe = mock.client().with_tx_journal(journal.clone());
+        let interaction = ContractFunctionInteraction::new(
+            &pxe,
+            DEFAULT_ORIGIN,
+            DEFAULT_ORIGIN,
+            set_just_field_abi(),
+            vec![json!(214)],
+        );
+
+        assert!(interaction.send().await.is_err());
+        assert!(journal.entries().unwrap().is_empty());
+        interaction.send().await.unwrap();
+        let entries = journal.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].1.function, "set_just_field");
+        assert_eq!(entries[0].1.tx_hash.as_deref(), Some("0xabc"));
+        assert_eq!(entries[0].1.tx, mock.requests()[7]["params"][0]);
+
+        pxe.get_tx_receipt("0xabc").await.unwrap();
+        assert!(journal.entries().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_send_gives_up_on_slow_prover_and_cancellation() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond("pxe_simulateTx", json!({ "privateExecutionResult": {} }));
+        mock.respond("pxe_proveTx", json!({}));
+        mock.respond("pxe_sendTx", json!("0xabc"));
+        mock.delay("pxe_proveTx", Duration::from_secs(5));
+        let pool = Arc::new(SenderPool::new(
+            SenderPoolConfig::parse("0x0a", Some("1")).unwrap(),
+        ));
+        let pxe = mock
+            .client()
+            .with_wallet(test_wallet("0x0a"))
+            .with_sender_pool(pool.clone());
+        let interaction = ContractFunctionInteraction::new(
+            &pxe,
+            DEFAULT_ORIGIN,
+            DEFAULT_ORIGIN,
+            set_just_field_abi(),
+            vec![json!(214)],
+        );
+
+        let options = CallOptions {
+            prove_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+        let err = interaction.send_with(options).await.unwrap_err();
+        assert!(
+            matches!(err.downcast_ref(), Some(AztecError::Timeout(m)) if m == "proveTx"),
+            "{}",
+            err
+        );
+        assert_eq!(pool.pending("0x0a"), 0);
+
+        let options = CallOptions::default();
+        let cancel = options.cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            cancel.cancel();
+        });
+        let err = interaction.send_with(options).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(AztecError::Cancelled)));
+
+        let options = CallOptions {
+            overall_deadline: Some(Instant::now()),
+            ..Default::default()
+        };
+        let err = interaction.send_with(options).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(AztecError::Timeout(_))));
+
+        assert_eq!(pool.pending("0x0a"), 0);
+        assert!(mock.requests().iter().all(|r| r["method"] != "pxe_sendTx"));
+    }
+
+    #[derive(Debug, Default)]
+    struct Decline(std::sync::Mutex<Vec<TxPreview>>);
+
+    impl ConfirmSend for Decline {
+        fn confirm(&self, preview: &TxPreview) -> bool {
+            self.0.lock().unwrap().push(preview.clone());
+            false
+        }
+    }
+
+    #[tokio::test]
+    async fn test_describe_previews_and_confirmation_can_decline() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond(
+            "pxe_simulateTx",
+            json!({
+                "privateExecutionResult": {},
+                "publicOutput": { "gasUsed": { "totalGas": { "daGas": 10, "l2Gas": 20 } } },
+            }),
+        );
+        let decline = Arc::new(Decline::default());
+        let pxe = mock.client().with_send_confirmation(decline.clone());
+        let interaction = ContractFunctionInteraction::new(
+            &pxe,
+            DEFAULT_ORIGIN,
+            OTHER_ACCOUNT,
+            set_just_field_abi(),
+            vec![json!(214)],
+        );
+
+        let preview = interaction.describe().await.unwrap();
+        assert_eq!(preview.function, "set_just_field");
+        assert_eq!(preview.contract_address, OTHER_ACCOUNT);
+        assert_eq!(preview.args.len(), 1);
+        assert_eq!(preview.args[0].name, "value");
+        assert_eq!(preview.args[0].value, "214");
+        assert_eq!(preview.fee_payer, preview.from);
+        let fees = &preview.gas_settings.max_fees_per_gas;
+        let gas = Gas {
+            da_gas: 10,
+            l2_gas: 20,
+        };
+        assert_eq!(preview.estimated_fee, max_fee(gas, fees));
+        let rendered = preview.to_string();
+        assert!(rendered.contains("set_just_field at"), "{}", rendered);
+        assert!(rendered.contains("value: field = 214"), "{}", rendered);
+
+        let err = interaction.send().await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(AztecError::Cancelled)));
+        assert_eq!(decline.0.lock().unwrap().as_slice(), [preview]);
+        assert!(mock.requests().iter().all(|r| r["method"] != "pxe_proveTx"));
+    }
+
+    #[test]
+    fn test_tx_effects_from_simulation() {
+        let effects = TxEffects::from_simulation(&json!({
+            "publicOutput": {
+                "txEffect": { "publicDataWrites": [{ "leafSlot": "0x2a", "value": "0xd6" }] },
+                "gasUsed": { "totalGas": { "daGas": 1024, "l2Gas": 30000 } },
+            },
+        }))
+        .unwrap();
+
+        assert_eq!(
+            effects,
+            TxEffects {
+                public_data_writes: vec![PublicDataWrite {
+                    leaf_slot: Fr::from(42u8),
+                    value: Fr::from(214u8),
+                }],
+                note_hashes: vec![],
+                nullifiers: vec![],
+                gas_used: Some(Gas {
+                    da_gas: 1024,
+                    l2_gas: 30000,
+                }),
+            }
+        );
+        assert!(TxEffects::from_simulation(&json!({}))
+            .unwrap()
+            .gas_used
+            .is_none());
+    }
+
+    #[test]
+    fn test_call_builder_matches_arguments_by_name() {
+        let pxe = OfflinePxe;
+        let contract = Contract::at(
+            &pxe,
+            DEFAULT_ORIGIN,
+            OTHER_ACCOUNT,
+            fixtures().artifact.clone(),
+        );
+        let interaction = contract
+            .method("set_field_in_map", Vec::<Value>::new())
+            .unwrap();
+        let [key, value] = [0, 1].map(|i| &interaction.parameters()[i].name);
+
+        let by_name = contract
+            .call("set_field_in_map")
+            .unwrap()
+            .arg(value.as_str(), 214u64)
+            .arg(key.as_str(), json!(1))
+            .build()
+            .unwrap();
+        let by_position = contract
+            .method("set_field_in_map", vec![json!(1), json!(214)])
+            .unwrap();
+        assert_eq!(by_name.encode_args(), by_position.encode_args());
+
+        let call = || contract.call("set_field_in_map").unwrap();
+        let err = call().arg(key.as_str(), 1u64).build().err().unwrap();
+        assert_eq!(err, format!("set_field_in_map is missing `{}`", value));
+        let err = call()
+            .arg(key.as_str(), 1u64)
+            .arg(key.as_str(), 2u64)
+            .positional();
+        assert!(err.unwrap_err().contains("more than once"));
+        let err = call().arg("owner", 1u64).positional().unwrap_err();
+        assert_eq!(err, "set_field_in_map has no parameter `owner`");
+    }
+
+    #[test]
+    fn test_simulation_result_decodes_returns() {
+        let getter = get_function_artifact(&fixtures().artifact, "get_just_field")
+            .unwrap()
+            .to_abi();
+        let public = SimulationResult::new(fixtures().simulate_get.clone(), &getter);
+        assert_eq!(public.returns().unwrap(), [json!("700")]);
+
+        let private = FunctionAbi {
+            function_type: "private".to_string(),
+            return_types: vec![
+                AbiType::Field,
+                AbiType::Array {
+                    r#type: Box::new(AbiType::Boolean),
+                    length: 2,
+                },
+            ],
+            ..getter
+        };
+        let simulation = json!({
+            "privateExecutionResult": {
+                "entrypoint": { "returnValues": ["0x2a", "0x1", "0x0"], "nestedExecutions": [] },
+            },
+        });
+        let result = SimulationResult::new(simulation, &private);
+        assert_eq!(
+            result.returns().unwrap(),
+            [json!("42"), json!([true, false])]
+        );
+
+        let short =
+            json!({ "privateExecutionResult": { "entrypoint": { "returnValues": ["0x2a"] } } });
+        assert!(SimulationResult::new(short, &private).returns().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_simulation_failure_points_at_noir_source() {
+        use crate::debug_info::tests::{set_debug_symbols, SOURCE};
+        use crate::encoder::parse_contract_artifact;
+
+        let artifact = json!({
+            "name": "Main",
+            "functions": [{
+                "name": "set_just_field",
+                "parameters": [{ "name": "value", "type": { "kind": "field" } }],
+                "bytecode": "",
+                "debugSymbols": set_debug_symbols(),
+                "functionType": "public",
+            }],
+            "nonDispatchPublicFunctions": [],
+            "storageLayout": {},
+            "notes": {},
+            "fileMap": { "3": { "path": "src/main.nr", "source": SOURCE } },
+        });
+        let artifact = Arc::new(parse_contract_artifact(&artifact.to_string()).unwrap());
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond_with_envelope(
+            "pxe_simulateTx",
+            json!({ "jsonrpc": "2.0", "id": 1, "error": {
+                "code": -32000,
+                "message": "Assertion failed: zero",
+                "data": {
+                    "originalMessage": "Assertion failed: zero",
+                    "functionErrorStack": [{
+                        "contractAddress": OTHER_ACCOUNT,
+                        "functionName": "set_just_field",
+                    }],
+                    "noirErrorStack": ["0.7"],
+                },
+            } }),
+        );
+        let alerts = Arc::new(Collect::default());
+        let pxe = mock.client().with_alert_sink(alerts.clone());
+        let contract = Contract::at(&pxe, DEFAULT_ORIGIN, OTHER_ACCOUNT, artifact);
+        let interaction = contract.method("set_just_field", vec![json!(0)]).unwrap();
+
+        let err = interaction
+            .simulate(SimulateOptions::default())
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Assertion failed: zero at src/main.nr:2:5");
+        let Some(AztecError::Simulation { error, failure }) = err.downcast_ref() else {
+            panic!("{:?}", err);
+        };
+        assert_eq!(error["code"], -32000);
+        assert_eq!(failure.frames[0].contract, "Main");
+
+        // Only sends alert; reads that revert are the caller's business.
+        assert!(alerts.0.lock().unwrap().is_empty());
+        interaction.send().await.unwrap_err();
+        assert_eq!(
+            *alerts.0.lock().unwrap(),
+            [Alert::TxReverted {
+                contract: OTHER_ACCOUNT.to_string(),
+                function: "set_just_field".to_string(),
+                message: "Assertion failed: zero".to_string(),
+            }]
+        );
+    }
+
+    /// Sums the inputs plus the separator, standing in for Poseidon2.
+    struct Sum;
+
+    impl Poseidon2 for Sum {
+        fn hash_with_separator(&self, inputs: &[Fr], separator: u32) -> Fr {
+            let sum: u128 = inputs.iter().map(|f| f.to_u128().unwrap()).sum();
+            Fr::from(sum + separator as u128)
+        }
+
+        fn hash(&self, inputs: &[Fr]) -> Fr {
+            self.hash_with_separator(inputs, 0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_build_needs_no_pxe() {
+        let contract = Contract::at(
+            &OfflinePxe,
+            DEFAULT_ORIGIN,
+            OTHER_ACCOUNT,
+            fixtures().artifact.clone(),
+        );
+        let info = NodeInfo {
+            node_version: "0.87.2".to_string(),
+            l1_chain_id: 31337,
+            rollup_version: 7,
+        };
+        let set = contract.method("set_just_field", vec![json!(214)]).unwrap();
+
+        let request = set.build(&info, &Sum).unwrap();
+        assert_eq!(request.origin, Fr::try_from(OTHER_ACCOUNT).unwrap());
+        assert_eq!(request.function_selector, set.selector());
+        assert_eq!(request.args_of_calls[0].values, [Fr::from(214u8)]);
+        assert_eq!(request.first_call_args_hash, Fr::from(214u128 + 44));
+        assert_eq!(request.tx_context.chain_id, Fr::from(31337u64));
+        assert_eq!(request.tx_context.version, Fr::from(7u8));
+        let serialized = request.to_canonical_string();
+        assert_eq!(
+            set.build(&info, &Sum).unwrap().to_canonical_string(),
+            serialized
+        );
+        assert_eq!(
+            TxExecutionRequest::from_json(serde_json::from_str(&serialized).unwrap()).unwrap(),
+            request
+        );
+
+        let get = contract
+            .method("get_just_field", Vec::<Value>::new())
+            .unwrap();
+        assert_eq!(
+            get.build(&info, &Sum).unwrap().first_call_args_hash,
+            Fr::zero()
+        );
+        assert!(set.send().await.is_err());
+    }
+
+    #[test]
+    fn test_interface_reflects_fixture_artifact() {
+        let pxe = AztecRpcClient::new("http://localhost:0", None);
+        let contract = Contract::at(
+            &pxe,
+            DEFAULT_ORIGIN,
+            OTHER_ACCOUNT,
+            fixtures().artifact.clone(),
+        );
+        let interface = contract.interface();
+
+        let get = interface
+            .functions
+            .iter()
+            .find(|f| f.name == "get_just_field")
+            .unwrap();
+        assert!(get.is_static);
+        assert_eq!(get.return_types, vec!["field"]);
+        let set = contract.method("set_just_field", vec![json!(1)]).unwrap();
+        let set_report = interface
+            .functions
+            .iter()
+            .find(|f| f.name == "set_just_field")
+            .unwrap();
+        assert_eq!(set_report.selector, format!("0x{}", set.selector().0));
+        assert!(interface.functions[0].is_initializer);
+        assert_eq!(
+            interface.storage[1].typ.as_deref(),
+            Some("PublicMutable<Field>")
+        );
+    }
+}
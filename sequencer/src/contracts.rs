@@ -0,0 +1,207 @@
+use futures_util::stream::{self, StreamExt, TryStreamExt};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::aztec_rpc_client::AztecRpcClient;
+use crate::bridge::ArtifactRegistry;
+use crate::error::AztecError;
+use crate::fields::{AztecAddress, Fr};
+
+/// One contract the PXE knows, with what `getContractMetadata` says about it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContractSummary {
+    pub address: String,
+    /// `None` when the PXE has no instance for the address.
+    pub class_id: Option<Fr>,
+    pub initialized: bool,
+    pub published: bool,
+    /// Name of the registry's artifact for the address or class, if any.
+    pub artifact: Option<String>,
+}
+
+impl ContractSummary {
+    fn from_metadata(address: String, metadata: &Value) -> Self {
+        let class_id = metadata["contractInstance"]["currentContractClassId"]
+            .as_str()
+            .and_then(|id| Fr::try_from(id).ok());
+        ContractSummary {
+            address,
+            class_id,
+            initialized: metadata["isContractInitialized"].as_bool().unwrap_or(false),
+            published: metadata["isContractPublished"].as_bool().unwrap_or(false),
+            artifact: None,
+        }
+    }
+}
+
+/// Which contracts `ContractLister::list` returns: those of `class_id`, if
+/// set, then `limit` of them from `offset`, in the PXE's order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContractQuery {
+    pub class_id: Option<Fr>,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+impl ContractQuery {
+    fn page<T>(&self, items: Vec<T>) -> Vec<T> {
+        let items = items.into_iter().skip(self.offset);
+        match self.limit {
+            Some(limit) => items.take(limit).collect(),
+            None => items.collect(),
+        }
+    }
+}
+
+/// `getContracts`, with each address's metadata fetched alongside.
+pub struct ContractLister<'a> {
+    pxe: &'a AztecRpcClient,
+    registry: Option<&'a ArtifactRegistry>,
+    concurrency: usize,
+}
+
+impl<'a> ContractLister<'a> {
+    pub fn new(pxe: &'a AztecRpcClient) -> Self {
+        ContractLister {
+            pxe,
+            registry: None,
+            concurrency: 8,
+        }
+    }
+
+    /// Names summaries after the artifacts the registry has for them.
+    pub fn with_registry(mut self, registry: &'a ArtifactRegistry) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// How many `getContractMetadata` calls `list` keeps in flight.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Without a class filter only the requested page is looked up; with
+    /// one, every contract is, since the filter needs each class id.
+    pub async fn list(&self, query: &ContractQuery) -> Result<Vec<ContractSummary>, AztecError> {
+        let mut addresses = self.pxe.get_contracts().await?;
+        if query.class_id.is_none() {
+            addresses = query.page(addresses);
+        }
+
+        let summaries: Vec<ContractSummary> = stream::iter(addresses)
+            .map(|address| async move {
+                let checked =
+                    AztecAddress::try_from(address.as_str()).map_err(AztecError::Encoding)?;
+                let metadata = self.pxe.get_contract_metadata(&checked).await?;
+                Ok::<_, AztecError>(ContractSummary::from_metadata(address, &metadata))
+            })
+            .buffered(self.concurrency)
+            .try_collect()
+            .await?;
+
+        let mut summaries = match &query.class_id {
+            Some(class_id) => query.page(
+                summaries
+                    .into_iter()
+                    .filter(|s| s.class_id.as_ref() == Some(class_id))
+                    .collect(),
+            ),
+            None => summaries,
+        };
+        if let Some(registry) = self.registry {
+            for summary in &mut summaries {
+                summary.artifact = self.artifact_name(registry, summary);
+            }
+        }
+        Ok(summaries)
+    }
+
+    fn artifact_name(
+        &self,
+        registry: &ArtifactRegistry,
+        summary: &ContractSummary,
+    ) -> Option<String> {
+        registry
+            .get(&summary.address)
+            .or_else(|| registry.get(&summary.class_id.as_ref()?.to_hex()))
+            .map(|artifact| artifact.name.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{fixtures, MockPxe};
+    use serde_json::json;
+    use std::fs;
+
+    fn metadata(class_id: u8, initialized: bool) -> Value {
+        json!({
+            "contractInstance": { "currentContractClassId": Fr::from(class_id).to_hex() },
+            "isContractInitialized": initialized,
+            "isContractPublished": true,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_lists_filters_and_pages_contracts() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond("pxe_getContracts", json!(["0x0a", "0x0b", "0x0c"]));
+        mock.respond("pxe_getContractMetadata", metadata(1, true));
+        mock.respond("pxe_getContractMetadata", metadata(2, false));
+        mock.respond("pxe_getContractMetadata", metadata(1, false));
+        let pxe = AztecRpcClient::new(mock.url(), Some("pxe".to_string()));
+
+        let dir = std::env::temp_dir().join(format!("contracts-registry-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("0x0a.json"), fixtures().artifact_json).unwrap();
+        let registry = ArtifactRegistry::new(&dir);
+        let lister = ContractLister::new(&pxe)
+            .with_registry(&registry)
+            .with_concurrency(1);
+
+        let ones = lister
+            .list(&ContractQuery {
+                class_id: Some(Fr::from(1u8)),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            ones,
+            [
+                ContractSummary {
+                    address: "0x0a".to_string(),
+                    class_id: Some(Fr::from(1u8)),
+                    initialized: true,
+                    published: true,
+                    artifact: Some("Main".to_string()),
+                },
+                ContractSummary {
+                    address: "0x0c".to_string(),
+                    class_id: Some(Fr::from(1u8)),
+                    initialized: false,
+                    published: true,
+                    artifact: None,
+                },
+            ]
+        );
+
+        // Pages without a filter only look up the page.
+        let before = mock.requests().len();
+        let page = lister
+            .list(&ContractQuery {
+                offset: 1,
+                limit: Some(1),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].address, "0x0b");
+        assert_eq!(mock.requests().len(), before + 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
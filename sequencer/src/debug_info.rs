@@ -0,0 +1,176 @@
+use base64::Engine;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::encoder::{ContractArtifact, DebugFileMap, FunctionArtifact};
+
+/// A position in a contract's Noir source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub path: String,
+    pub line: usize,
+    pub column: usize,
+    /// The first line of the spanned source.
+    pub text: String,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.path, self.line, self.column)
+    }
+}
+
+/// A function's decoded `debugSymbols`: where each opcode came from. Noir
+/// keys ACIR opcodes by index ("3", or "3.12" inside a Brillig call) and
+/// Brillig opcodes by function id, then program counter.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DebugInfo {
+    #[serde(default)]
+    locations: HashMap<String, Vec<Location>>,
+    #[serde(default)]
+    brillig_locations: HashMap<String, HashMap<String, Vec<Location>>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Location {
+    span: Span,
+    file: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Span {
+    start: usize,
+    end: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProgramDebugInfo {
+    debug_infos: Vec<DebugInfo>,
+}
+
+impl DebugInfo {
+    /// Decodes `debugSymbols`: base64 of the raw-deflated JSON nargo
+    /// writes, or that JSON as is.
+    pub fn decode(debug_symbols: &str) -> Result<Self, String> {
+        let json = if debug_symbols.trim_start().starts_with('{') {
+            debug_symbols.as_bytes().to_vec()
+        } else {
+            let compressed = base64::engine::general_purpose::STANDARD
+                .decode(debug_symbols.trim())
+                .map_err(|e| format!("Debug symbols are not base64: {}", e))?;
+            miniz_oxide::inflate::decompress_to_vec(&compressed)
+                .map_err(|e| format!("Cannot inflate debug symbols: {:?}", e))?
+        };
+        let program: ProgramDebugInfo =
+            serde_json::from_slice(&json).map_err(|e| format!("Invalid debug symbols: {}", e))?;
+        Ok(program.debug_infos.into_iter().next().unwrap_or_default())
+    }
+
+    /// The Noir call stack of `opcode`, outermost first. Unknown opcodes and
+    /// files missing from `file_map` resolve to nothing.
+    pub fn resolve(&self, opcode: &str, file_map: &DebugFileMap) -> Vec<SourceLocation> {
+        self.lookup(opcode.trim())
+            .into_iter()
+            .flatten()
+            .filter_map(|location| {
+                let file = file_map.0.get(&location.file.to_string())?;
+                let before = file.source.get(..location.span.start)?;
+                let text = file.source.get(location.span.start..location.span.end)?;
+                Some(SourceLocation {
+                    path: file.path.clone(),
+                    line: before.matches('\n').count() + 1,
+                    column: before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1,
+                    text: text.lines().next().unwrap_or_default().trim().to_string(),
+                })
+            })
+            .collect()
+    }
+
+    fn lookup(&self, opcode: &str) -> Option<&Vec<Location>> {
+        if let Some(locations) = self.locations.get(opcode) {
+            return Some(locations);
+        }
+        let pc = opcode.rsplit('.').next()?;
+        let mut ids: Vec<_> = self.brillig_locations.keys().collect();
+        ids.sort();
+        ids.into_iter()
+            .find_map(|id| self.brillig_locations[id].get(pc))
+    }
+}
+
+/// Resolves a failing opcode stack of `function` against `artifact`'s
+/// sources; empty when the function carries no usable debug symbols.
+pub fn source_locations(
+    artifact: &ContractArtifact,
+    function: &FunctionArtifact,
+    opcodes: &[String],
+) -> Vec<SourceLocation> {
+    let Ok(debug) = DebugInfo::decode(&function.debug_symbols) else {
+        return vec![];
+    };
+    opcodes
+        .iter()
+        .flat_map(|opcode| debug.resolve(opcode, &artifact.file_map))
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::encoder::DebugFile;
+    use serde_json::{json, Value};
+
+    pub(crate) const SOURCE: &str =
+        "fn set(value: Field) {\n    assert(value != 0, \"zero\");\n}\n";
+
+    /// `debugSymbols` as nargo writes them.
+    pub(crate) fn encode(debug: &Value) -> String {
+        let compressed = miniz_oxide::deflate::compress_to_vec(debug.to_string().as_bytes(), 6);
+        base64::engine::general_purpose::STANDARD.encode(compressed)
+    }
+
+    /// Brillig pc 7 is the assertion in `set`, called from its signature.
+    pub(crate) fn set_debug_symbols() -> String {
+        encode(&json!({ "debug_infos": [{
+            "locations": {},
+            "brillig_locations": { "0": { "7": [
+                { "span": { "start": 0, "end": 20 }, "file": 3 },
+                { "span": { "start": 27, "end": 53 }, "file": 3 },
+            ] } },
+        }] }))
+    }
+
+    fn file_map() -> DebugFileMap {
+        DebugFileMap(HashMap::from([(
+            "3".to_string(),
+            DebugFile {
+                source: SOURCE.to_string(),
+                path: "src/main.nr".to_string(),
+            },
+        )]))
+    }
+
+    #[test]
+    fn test_resolves_opcodes_to_source_lines() {
+        let debug = DebugInfo::decode(&set_debug_symbols()).unwrap();
+        let locations = debug.resolve("0.7", &file_map());
+        assert_eq!(locations.len(), 2);
+        assert_eq!(locations[1].to_string(), "src/main.nr:2:5");
+        assert_eq!(locations[1].text, "assert(value != 0, \"zero\")");
+        assert_eq!(debug.resolve("7", &file_map()), locations);
+        assert!(debug.resolve("8", &file_map()).is_empty());
+
+        let plain = json!({ "debug_infos": [{
+            "locations": { "2": [{ "span": { "start": 55, "end": 56 }, "file": 3 }] },
+        }] });
+        let debug = DebugInfo::decode(&plain.to_string()).unwrap();
+        assert_eq!(
+            debug.resolve("2", &file_map())[0].to_string(),
+            "src/main.nr:3:1"
+        );
+        assert!(debug.resolve("2", &DebugFileMap(HashMap::new())).is_empty());
+
+        assert!(DebugInfo::decode("not base64!").is_err());
+    }
+}
@@ -0,0 +1,646 @@
+use serde_json::{json, Value};
+use std::fmt;
+
+use crate::aztec_rpc_client::AztecRpcClient;
+use crate::complete_address::{CompleteAddress, PublicKeys};
+use crate::contract::ContractFunctionInteraction;
+use crate::encoder::{get_function_artifact, ContractArtifact, FunctionSelector};
+use crate::fields::{AztecAddress, Fr};
+use crate::notes::{generator_index, Bn254Poseidon2, Poseidon2};
+use crate::pxe_api::PxeApi;
+use crate::tx_request::HashedValues;
+use crate::wallet::FunctionCall;
+
+/// The protocol contract that publishes contract instances (aztec.js'
+/// `ProtocolContractAddress.ContractInstanceDeployer`).
+pub const CONTRACT_INSTANCE_DEPLOYER: &str =
+    "0x0000000000000000000000000000000000000000000000000000000000000002";
+
+// Its `deploy(salt, contract_class_id, initialization_hash, public_keys,
+// universal_deploy)`, with the struct arguments flattened as Noir does.
+const DEPLOY_SIGNATURE: &str = "deploy(Field,(Field),Field,(((Field,Field,bool)),((Field,Field,bool)),((Field,Field,bool)),((Field,Field,bool))),bool)";
+
+/// What a contract instance is made from, as aztec.js'
+/// `getContractInstanceFromDeployParams` assembles it. The address commits
+/// to all of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractInstance {
+    pub salt: Fr,
+    pub deployer: Fr,
+    pub class_id: Fr,
+    pub initialization_hash: Fr,
+    pub public_keys: PublicKeys,
+}
+
+impl ContractInstance {
+    pub fn salted_initialization_hash(&self, poseidon2: &impl Poseidon2) -> Fr {
+        poseidon2.hash_with_separator(
+            &[
+                self.salt.clone(),
+                self.initialization_hash.clone(),
+                self.deployer.clone(),
+            ],
+            generator_index::PARTIAL_ADDRESS,
+        )
+    }
+
+    pub fn partial_address(&self, poseidon2: &impl Poseidon2) -> Fr {
+        poseidon2.hash_with_separator(
+            &[
+                self.class_id.clone(),
+                self.salted_initialization_hash(poseidon2),
+            ],
+            generator_index::PARTIAL_ADDRESS,
+        )
+    }
+
+    pub fn address(&self, poseidon2: &impl Poseidon2) -> Result<Fr, String> {
+        let partial_address = self.partial_address(poseidon2);
+        CompleteAddress::compute(self.public_keys.clone(), partial_address, poseidon2)
+            .map(|complete| complete.address)
+    }
+
+    /// The instance as the PXE's `registerContract` takes it.
+    pub fn to_json(&self, address: &Fr) -> Value {
+        json!({
+            "version": 1,
+            "salt": self.salt.to_hex(),
+            "deployer": self.deployer.to_hex(),
+            "currentContractClassId": self.class_id.to_hex(),
+            "originalContractClassId": self.class_id.to_hex(),
+            "initializationHash": self.initialization_hash.to_hex(),
+            "publicKeys": format!("0x{}", hex::encode(self.public_keys.to_bytes())),
+            "address": address.to_hex(),
+        })
+    }
+
+    /// The instance deployer's `deploy` call that publishes this instance,
+    /// so the sequencer can run its public functions.
+    pub fn publication_call(&self) -> FunctionCall {
+        let mut args = vec![
+            self.salt.clone(),
+            self.class_id.clone(),
+            self.initialization_hash.clone(),
+        ];
+        args.extend(self.public_keys.to_fields());
+        args.push(Fr::from(u8::from(self.deployer == Fr::zero())));
+        FunctionCall {
+            to: Fr::try_from(CONTRACT_INSTANCE_DEPLOYER).expect("valid deployer address"),
+            selector: FunctionSelector::from_signature(DEPLOY_SIGNATURE),
+            args,
+            is_public: false,
+            is_static: false,
+        }
+    }
+}
+
+/// What an instance commits its initializer call to (aztec.js'
+/// `computeInitializationHash`): the selector and the hash of its args.
+pub fn initialization_hash(
+    initializer: &FunctionCall,
+    poseidon2: &impl Poseidon2,
+) -> Result<Fr, String> {
+    let args = HashedValues::from_args(poseidon2, initializer.args.clone());
+    Ok(poseidon2.hash_with_separator(
+        &[initializer.selector_field()?, args.hash],
+        generator_index::CONSTRUCTOR,
+    ))
+}
+
+/// What `DeployMethod::send` does when the address is already taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnExisting {
+    /// Return `DeployOutcome::AlreadyDeployed` without sending anything.
+    Skip,
+    /// Fail with `DeployError::AlreadyDeployed`.
+    #[default]
+    Fail,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeployOutcome {
+    Deployed { address: String, tx_hash: String },
+    AlreadyDeployed { address: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeployError {
+    AlreadyDeployed {
+        address: String,
+    },
+    /// The PXE knows an instance at the address, but it was deployed with
+    /// different parameters, so the precomputed address is wrong.
+    InstanceMismatch {
+        address: String,
+        field: &'static str,
+        expected: String,
+        found: String,
+    },
+}
+
+impl fmt::Display for DeployError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeployError::AlreadyDeployed { address } => {
+                write!(f, "Contract already deployed at {}", address)
+            }
+            DeployError::InstanceMismatch {
+                address,
+                field,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Instance at {} has {} {}, expected {}",
+                address, field, found, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DeployError {}
+
+/// Deploys a contract at a deterministic address: the same artifact,
+/// constructor args, salt and deployer always give the same instance.
+///
+/// The address also commits to the artifact's contract class id, which
+/// hashes the artifact and its private functions' verification keys. This
+/// crate doesn't compute that, so it is passed to `with_class_id` (aztec.js'
+/// `getContractClassFromArtifact`). With it, the address is derived and
+/// `send` registers the instance with the PXE and publishes it in the
+/// constructor's tx. The class itself must already be published and its
+/// artifact registered with the PXE: publishing a class isn't done here.
+///
+/// Without a class id the address has to be passed to `at`, and `send` only
+/// calls the initializer there.
+pub struct DeployMethod<'a, P: ?Sized = AztecRpcClient> {
+    pxe: &'a P,
+    from: String,
+    artifact: &'a ContractArtifact,
+    args: Vec<Value>,
+    initializer: String,
+    salt: Fr,
+    universal: bool,
+    class_id: Option<Fr>,
+    public_keys: PublicKeys,
+    publish_instance: bool,
+    address: Option<String>,
+    on_existing: OnExisting,
+}
+
+impl<'a, P: PxeApi + ?Sized> DeployMethod<'a, P> {
+    pub fn new(
+        pxe: &'a P,
+        from: impl Into<String>,
+        artifact: &'a ContractArtifact,
+        args: Vec<Value>,
+    ) -> Self {
+        DeployMethod {
+            pxe,
+            from: from.into(),
+            artifact,
+            args,
+            initializer: "constructor".to_string(),
+            salt: Fr::zero(),
+            universal: false,
+            class_id: None,
+            public_keys: PublicKeys::default(),
+            publish_instance: true,
+            address: None,
+            on_existing: OnExisting::default(),
+        }
+    }
+
+    pub fn with_salt(mut self, salt: Fr) -> Self {
+        self.salt = salt;
+        self
+    }
+
+    /// Deploys with a zero deployer so the address doesn't depend on `from`.
+    pub fn universal(mut self) -> Self {
+        self.universal = true;
+        self
+    }
+
+    pub fn with_initializer(mut self, name: impl Into<String>) -> Self {
+        self.initializer = name.into();
+        self
+    }
+
+    pub fn with_class_id(mut self, class_id: Fr) -> Self {
+        self.class_id = Some(class_id);
+        self
+    }
+
+    /// The keys the instance is addressed to; a contract has none by default.
+    pub fn with_public_keys(mut self, public_keys: PublicKeys) -> Self {
+        self.public_keys = public_keys;
+        self
+    }
+
+    /// Registers the instance with the PXE without publishing it, for
+    /// contracts with only private functions.
+    pub fn skip_instance_publication(mut self) -> Self {
+        self.publish_instance = false;
+        self
+    }
+
+    /// Where the contract is expected. With a class id, `address` fails if
+    /// this isn't the derived address.
+    pub fn at(mut self, address: impl Into<String>) -> Self {
+        self.address = Some(address.into());
+        self
+    }
+
+    pub fn on_existing(mut self, on_existing: OnExisting) -> Self {
+        self.on_existing = on_existing;
+        self
+    }
+
+    pub fn salt(&self) -> &Fr {
+        &self.salt
+    }
+
+    pub fn deployer(&self) -> Result<Fr, String> {
+        if self.universal {
+            Ok(Fr::zero())
+        } else {
+            Fr::try_from(self.from.as_str())
+        }
+    }
+
+    /// The instance this deploys, once the class id is known.
+    pub fn instance(&self) -> Result<Option<ContractInstance>, String> {
+        let Some(class_id) = &self.class_id else {
+            return Ok(None);
+        };
+        // The initializer's target doesn't enter the hash.
+        let initializer = self
+            .initializer_call(Fr::zero().to_hex())?
+            .request()
+            .map_err(|e| e.to_string())?;
+        Ok(Some(ContractInstance {
+            salt: self.salt.clone(),
+            deployer: self.deployer()?,
+            class_id: class_id.clone(),
+            initialization_hash: initialization_hash(&initializer, &Bn254Poseidon2)?,
+            public_keys: self.public_keys.clone(),
+        }))
+    }
+
+    /// The derived address when the class id is known, else the one given to
+    /// `at`.
+    pub fn address(&self) -> Result<String, String> {
+        let Some(instance) = self.instance()? else {
+            return self.address.clone().ok_or_else(|| {
+                "Deployment address must be derived with `with_class_id` or set with `at`"
+                    .to_string()
+            });
+        };
+        let derived = instance.address(&Bn254Poseidon2)?;
+        match &self.address {
+            Some(expected) if Fr::try_from(expected.as_str()).ok() != Some(derived.clone()) => {
+                Err(format!(
+                    "Deployment address {} is not the derived {}",
+                    expected,
+                    derived.to_hex()
+                ))
+            }
+            _ => Ok(derived.to_hex()),
+        }
+    }
+
+    /// Whether the contract at the address is already initialized or
+    /// published. Errors if the PXE's instance there doesn't match this salt
+    /// and deployer.
+    pub async fn is_deployed(&self) -> Result<bool, Box<dyn std::error::Error>> {
+        let address = self.address()?;
+        let metadata = self
+            .pxe
+            .get_contract_metadata(&AztecAddress::try_from(address.as_str())?)
+            .await?;
+
+        let instance = &metadata["contractInstance"];
+        if !instance.is_null() {
+            self.check_instance(&address, instance)?;
+        }
+
+        let initialized = metadata["isContractInitialized"].as_bool().unwrap_or(false);
+        let published = metadata["isContractPublished"].as_bool().unwrap_or(false);
+        Ok(initialized || published)
+    }
+
+    pub async fn send(&self) -> Result<DeployOutcome, Box<dyn std::error::Error>> {
+        let address = self.address()?;
+        if self.is_deployed().await? {
+            return match self.on_existing {
+                OnExisting::Skip => Ok(DeployOutcome::AlreadyDeployed { address }),
+                OnExisting::Fail => Err(DeployError::AlreadyDeployed { address }.into()),
+            };
+        }
+
+        let mut preceding = vec![];
+        if let Some(instance) = self.instance()? {
+            let instance_json = instance.to_json(&Fr::try_from(address.as_str())?);
+            self.pxe.register_contract_instance(instance_json).await?;
+            if self.publish_instance {
+                preceding.push(instance.publication_call());
+            }
+        }
+        let tx_hash = self
+            .initializer_call(address.clone())?
+            .with_preceding(preceding)
+            .send()
+            .await?;
+
+        Ok(DeployOutcome::Deployed { address, tx_hash })
+    }
+
+    fn initializer_call(
+        &self,
+        address: String,
+    ) -> Result<ContractFunctionInteraction<'a, P>, String> {
+        let initializer = get_function_artifact(self.artifact, &self.initializer)?.to_abi();
+        Ok(ContractFunctionInteraction::new(
+            self.pxe,
+            self.from.clone(),
+            address,
+            initializer,
+            self.args.clone(),
+        ))
+    }
+
+    fn check_instance(
+        &self,
+        address: &str,
+        instance: &Value,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let expected = [("salt", self.salt.clone()), ("deployer", self.deployer()?)];
+        for (field, expected) in expected {
+            let found = instance[field].as_str().unwrap_or_default();
+            if Fr::try_from(found).ok() != Some(expected.clone()) {
+                return Err(DeployError::InstanceMismatch {
+                    address: address.to_string(),
+                    field,
+                    expected: expected.to_hex(),
+                    found: found.to_string(),
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockPxe;
+    use crate::tx_request::{TxExecutionRequest, DEFAULT_ORIGIN};
+    use serde_json::json;
+
+    const ADDRESS: &str = "0x12d8f70092c1d4b2bf3ddd60af8e47c1a10d90f3f31fe4c874d4b91f58442ede";
+
+    fn artifact() -> ContractArtifact {
+        serde_json::from_value(json!({
+            "name": "Main",
+            "functions": [{
+                "name": "constructor",
+                "parameters": [],
+                "bytecode": "",
+                "debugSymbols": "",
+                "functionType": "public",
+            }],
+            "nonDispatchPublicFunctions": [],
+            "storageLayout": {},
+            "notes": {},
+            "fileMap": {},
+        }))
+        .unwrap()
+    }
+
+    fn class_id() -> Fr {
+        Fr::from(0xc1a55u64)
+    }
+
+    async fn mock_with_instance(instance: Value) -> (MockPxe, AztecRpcClient) {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond(
+            "pxe_getContractMetadata",
+            json!({
+                "contractInstance": instance,
+                "isContractInitialized": !instance.is_null(),
+                "isContractPublished": false,
+            }),
+        );
+        let pxe = mock.client();
+        (mock, pxe)
+    }
+
+    fn existing_instance(salt: u64) -> Value {
+        json!({ "address": ADDRESS, "salt": Fr::from(salt).to_hex(), "deployer": Fr::zero().to_hex() })
+    }
+
+    #[tokio::test]
+    async fn test_deploys_when_address_is_free() {
+        let (mock, pxe) = mock_with_instance(Value::Null).await;
+        mock.respond("pxe_simulateTx", json!({ "privateExecutionResult": {} }));
+        mock.respond(
+            "pxe_proveTx",
+            json!({ "publicInputs": {}, "clientIvcProof": "0x" }),
+        );
+        mock.respond("pxe_sendTx", json!("0x01"));
+
+        let artifact = artifact();
+        let outcome = DeployMethod::new(&pxe, DEFAULT_ORIGIN, &artifact, vec![])
+            .with_salt(Fr::from(7u64))
+            .universal()
+            .at(ADDRESS)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            outcome,
+            DeployOutcome::Deployed {
+                address: ADDRESS.to_string(),
+                tx_hash: "0x01".to_string()
+            }
+        );
+        assert_eq!(mock.requests()[0]["params"], json!([ADDRESS]));
+    }
+
+    #[test]
+    fn test_address_commits_to_every_deploy_param() {
+        let pxe = AztecRpcClient::new("http://localhost:0", None);
+        let artifact = artifact();
+        let method = || {
+            DeployMethod::new(&pxe, DEFAULT_ORIGIN, &artifact, vec![])
+                .with_salt(Fr::from(7u64))
+                .with_class_id(class_id())
+        };
+
+        let instance = method().instance().unwrap().unwrap();
+        let address = method().address().unwrap();
+        assert_eq!(address, instance.address(&Bn254Poseidon2).unwrap().to_hex());
+        let initializer = method()
+            .initializer_call(address.clone())
+            .unwrap()
+            .request()
+            .unwrap();
+        assert_eq!(
+            instance.initialization_hash,
+            initialization_hash(&initializer, &Bn254Poseidon2).unwrap()
+        );
+        assert_eq!(instance.public_keys, PublicKeys::default());
+
+        let others = [
+            method().with_salt(Fr::from(8u64)),
+            method().with_class_id(Fr::from(0xc1a56u64)),
+            method().universal(),
+            method().with_public_keys(PublicKeys::derive(&Fr::from(1u8)).unwrap()),
+        ];
+        for other in others {
+            assert_ne!(other.address().ok(), Some(address.clone()));
+        }
+
+        assert_eq!(method().at(address.clone()).address().unwrap(), address);
+        assert!(method().at(ADDRESS).address().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_publishes_instance_before_constructor() {
+        let (mock, pxe) = mock_with_instance(Value::Null).await;
+        mock.respond("pxe_registerContract", Value::Null);
+        mock.respond("pxe_simulateTx", json!({ "privateExecutionResult": {} }));
+        mock.respond(
+            "pxe_proveTx",
+            json!({ "publicInputs": {}, "clientIvcProof": "0x" }),
+        );
+        mock.respond("pxe_sendTx", json!("0x01"));
+
+        let artifact = artifact();
+        let method = DeployMethod::new(&pxe, DEFAULT_ORIGIN, &artifact, vec![])
+            .with_salt(Fr::from(7u64))
+            .with_class_id(class_id());
+        let address = method.address().unwrap();
+        let instance = method.instance().unwrap().unwrap();
+        assert_eq!(
+            method.send().await.unwrap(),
+            DeployOutcome::Deployed {
+                address: address.clone(),
+                tx_hash: "0x01".to_string()
+            }
+        );
+
+        let requests = mock.requests();
+        assert_eq!(requests[0]["params"], json!([address]));
+        assert_eq!(requests[1]["method"], "pxe_registerContract");
+        let registered = &requests[1]["params"][0]["instance"];
+        assert_eq!(registered["address"], json!(address));
+        assert_eq!(
+            registered["currentContractClassId"],
+            json!(class_id().to_hex())
+        );
+        assert_eq!(
+            registered["deployer"],
+            json!(Fr::try_from(DEFAULT_ORIGIN).unwrap().to_hex())
+        );
+
+        let simulated = requests
+            .iter()
+            .find(|r| r["method"] == "pxe_simulateTx")
+            .unwrap();
+        let tx_request = TxExecutionRequest::from_json(simulated["params"][0].clone()).unwrap();
+        let publication = instance.publication_call();
+        assert_eq!(publication.args.len(), 16);
+        assert_eq!(publication.args[15], Fr::zero());
+        assert!(tx_request
+            .args_of_calls
+            .contains(&publication.hashed_args(&Bn254Poseidon2).unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_skips_existing_instance_with_same_salt() {
+        let (mock, pxe) = mock_with_instance(existing_instance(7)).await;
+
+        let artifact = artifact();
+        let outcome = DeployMethod::new(&pxe, DEFAULT_ORIGIN, &artifact, vec![])
+            .with_salt(Fr::from(7u64))
+            .universal()
+            .at(ADDRESS)
+            .on_existing(OnExisting::Skip)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            outcome,
+            DeployOutcome::AlreadyDeployed {
+                address: ADDRESS.to_string()
+            }
+        );
+        assert_eq!(mock.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fails_on_existing_instance_by_default() {
+        let (_mock, pxe) = mock_with_instance(existing_instance(7)).await;
+
+        let artifact = artifact();
+        let err = DeployMethod::new(&pxe, DEFAULT_ORIGIN, &artifact, vec![])
+            .with_salt(Fr::from(7u64))
+            .universal()
+            .at(ADDRESS)
+            .send()
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            err.downcast_ref::<DeployError>(),
+            Some(&DeployError::AlreadyDeployed {
+                address: ADDRESS.to_string()
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_address_computed_from_other_salt() {
+        let (_mock, pxe) = mock_with_instance(existing_instance(8)).await;
+
+        let artifact = artifact();
+        let err = DeployMethod::new(&pxe, DEFAULT_ORIGIN, &artifact, vec![])
+            .with_salt(Fr::from(7u64))
+            .universal()
+            .at(ADDRESS)
+            .on_existing(OnExisting::Skip)
+            .send()
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<DeployError>(),
+            Some(DeployError::InstanceMismatch { field: "salt", .. })
+        ));
+    }
+
+    #[test]
+    fn test_deployer_is_sender_unless_universal() {
+        let pxe = AztecRpcClient::new("http://localhost:0", None);
+        let artifact = artifact();
+        let method = DeployMethod::new(&pxe, DEFAULT_ORIGIN, &artifact, vec![]);
+        assert_eq!(
+            method.deployer().unwrap(),
+            Fr::try_from(DEFAULT_ORIGIN).unwrap()
+        );
+        assert!(method.address().is_err());
+        let universal = method.universal();
+        assert_eq!(universal.deployer().unwrap(), Fr::zero());
+        let instance = universal
+            .with_class_id(class_id())
+            .instance()
+            .unwrap()
+            .unwrap();
+        assert_eq!(instance.publication_call().args[15], Fr::from(1u8));
+    }
+}
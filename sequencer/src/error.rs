@@ -0,0 +1,71 @@
+use serde_json::Value;
+use std::fmt;
+
+use crate::simulation_error::SimulationError;
+
+/// Errors from talking to the PXE. Anything returning `Box<dyn Error>` can
+/// downcast to this to tell bad input from an unreachable or failing PXE.
+#[derive(Debug)]
+pub enum AztecError {
+    /// Arguments or payloads that don't fit the ABI or wire format.
+    Encoding(String),
+    /// The PXE could not be reached, or did not answer with JSON-RPC.
+    Transport(String),
+    /// The PXE answered, but not with a valid reply to the request: wrong
+    /// `jsonrpc` version or an id that doesn't match.
+    Protocol(String),
+    /// The PXE answered `method` with a JSON-RPC error.
+    Rpc { method: String, error: Value },
+    /// `simulateTx` failed, resolved against the contract's artifact; `error`
+    /// is the JSON-RPC error as the PXE sent it.
+    Simulation {
+        error: Value,
+        failure: SimulationError,
+    },
+    /// `method` submits a tx and the client is in dry-run mode.
+    DryRun(String),
+    /// Local state the client keeps (e.g. the fee budget) failed.
+    State(String),
+    /// There is no wallet for the sending account, or it could not sign.
+    Account(String),
+    /// `method` ran past its timeout or the call's deadline.
+    Timeout(String),
+    /// The caller cancelled the call.
+    Cancelled,
+}
+
+impl fmt::Display for AztecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AztecError::Encoding(e) => write!(f, "{}", e),
+            AztecError::Transport(e) => write!(f, "PXE transport error: {}", e),
+            AztecError::Protocol(e) => write!(f, "PXE protocol error: {}", e),
+            AztecError::Rpc { error, .. } => write!(f, "PXE returned error: {}", error),
+            AztecError::Simulation { failure, .. } => match failure.location() {
+                Some(location) => write!(f, "{} at {}", failure.message, location),
+                None => write!(f, "{}", failure.message),
+            },
+            AztecError::DryRun(method) => {
+                write!(f, "Refusing to call {} in dry-run mode", method)
+            }
+            AztecError::State(e) => write!(f, "{}", e),
+            AztecError::Account(e) => write!(f, "{}", e),
+            AztecError::Timeout(method) => write!(f, "PXE did not finish {} in time", method),
+            AztecError::Cancelled => write!(f, "Cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for AztecError {}
+
+impl From<reqwest::Error> for AztecError {
+    fn from(e: reqwest::Error) -> Self {
+        AztecError::Transport(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for AztecError {
+    fn from(e: serde_json::Error) -> Self {
+        AztecError::Transport(e.to_string())
+    }
+}
@@ -0,0 +1,239 @@
+use crate::authwit;
+use crate::encoder::FunctionSelector;
+use crate::fees::max_fee;
+use crate::fields::Fr;
+use crate::notes::Poseidon2;
+use crate::tx_request::{Gas, GasSettings, NodeInfo};
+use crate::wallet::{random_nonce, AccountWallet, FeeOptions, FunctionCall};
+
+/// The protocol contract public auth witnesses are kept in.
+pub const AUTH_REGISTRY_ADDRESS: u8 = 1;
+
+/// How a tx's fee is paid (aztec.js' `FeePaymentMethod`s).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FeePaymentMethod {
+    /// The account pays in fee juice from its own balance.
+    FeeJuice,
+    /// The fee paying contract at `fpc_address` pays, and the account
+    /// refunds it the max fee in `asset` from its private balance. Whatever
+    /// is left unspent comes back in the FPC's teardown.
+    PrivateFpc { fpc_address: Fr, asset: Fr },
+    /// Same, from the account's public `asset` balance, authorized in the
+    /// auth registry rather than by an auth witness.
+    PublicFpc { fpc_address: Fr, asset: Fr },
+}
+
+impl FeePaymentMethod {
+    pub fn fee_payer(&self, sender: &Fr) -> Fr {
+        match self {
+            FeePaymentMethod::FeeJuice => sender.clone(),
+            FeePaymentMethod::PrivateFpc { fpc_address, .. }
+            | FeePaymentMethod::PublicFpc { fpc_address, .. } => fpc_address.clone(),
+        }
+    }
+
+    /// `settings` with both gas limits padded by `padding_percent`. FPCs
+    /// refund in teardown, so they get at least the default teardown limits;
+    /// fee juice payments have no teardown to pay for.
+    pub fn gas_settings(&self, mut settings: GasSettings, padding_percent: u64) -> GasSettings {
+        settings.gas_limits = padded(settings.gas_limits, padding_percent);
+        settings.teardown_gas_limits = match self {
+            FeePaymentMethod::FeeJuice => Gas {
+                da_gas: 0,
+                l2_gas: 0,
+            },
+            _ => {
+                let default = GasSettings::default().teardown_gas_limits;
+                let teardown = settings.teardown_gas_limits;
+                padded(
+                    Gas {
+                        da_gas: teardown.da_gas.max(default.da_gas),
+                        l2_gas: teardown.l2_gas.max(default.l2_gas),
+                    },
+                    padding_percent,
+                )
+            }
+        };
+        settings
+    }
+
+    /// The fee payload for `wallet`'s tx: the FPC's entrypoint call, with
+    /// the auth witness (or, for public FPCs, the auth registry call) that
+    /// lets it take the max fee `gas_settings` allows.
+    pub fn fee_options(
+        &self,
+        wallet: &dyn AccountWallet,
+        gas_settings: GasSettings,
+        node_info: &NodeInfo,
+        hasher: &dyn Poseidon2,
+    ) -> Result<FeeOptions, String> {
+        let (fpc_address, asset, public) = match self {
+            FeePaymentMethod::FeeJuice => {
+                return Ok(FeeOptions {
+                    gas_settings,
+                    ..FeeOptions::default()
+                })
+            }
+            FeePaymentMethod::PrivateFpc { fpc_address, asset } => (fpc_address, asset, false),
+            FeePaymentMethod::PublicFpc { fpc_address, asset } => (fpc_address, asset, true),
+        };
+        let sender = Fr::try_from(wallet.address())
+            .map_err(|e| format!("Invalid account address: {}", e))?;
+        let max_fee = Fr::from(max_fee(
+            gas_settings.gas_limits,
+            &gas_settings.max_fees_per_gas,
+        ));
+        let nonce = random_nonce()?;
+        let call = |to: &Fr, signature: &str, args: Vec<Fr>| FunctionCall {
+            to: to.clone(),
+            selector: FunctionSelector::from_signature(signature),
+            args,
+            is_public: public,
+            is_static: false,
+        };
+        let transfer_args = vec![sender, fpc_address.clone(), max_fee.clone(), nonce.clone()];
+        let fee_args = vec![max_fee, nonce];
+
+        let mut options = FeeOptions {
+            gas_settings,
+            calls: vec![],
+            is_fee_payer: false,
+            auth_witnesses: vec![],
+            nonce: None,
+        };
+        if public {
+            let transfer = call(
+                asset,
+                "transfer_in_public((Field),(Field),u128,Field)",
+                transfer_args,
+            );
+            let message_hash = authwit::message_hash(fpc_address, &transfer, node_info, hasher)?;
+            options.calls = vec![
+                call(
+                    &Fr::from(AUTH_REGISTRY_ADDRESS),
+                    "set_authorized(Field,bool)",
+                    vec![message_hash, Fr::from(true)],
+                ),
+                call(fpc_address, "fee_entrypoint_public(u128,Field)", fee_args),
+            ];
+        } else {
+            let transfer = call(
+                asset,
+                "transfer_to_public((Field),(Field),u128,Field)",
+                transfer_args,
+            );
+            let message_hash = authwit::message_hash(fpc_address, &transfer, node_info, hasher)?;
+            options.auth_witnesses = vec![wallet.create_auth_witness(&message_hash)?];
+            options.calls = vec![call(
+                fpc_address,
+                "fee_entrypoint_private(u128,Field)",
+                fee_args,
+            )];
+        }
+        Ok(options)
+    }
+}
+
+/// `gas` plus `percent` of it, so estimates survive small changes in state.
+pub fn padded(gas: Gas, percent: u64) -> Gas {
+    let pad = |gas: u64| gas.saturating_add(gas.saturating_mul(percent) / 100);
+    Gas {
+        da_gas: pad(gas.da_gas),
+        l2_gas: pad(gas.l2_gas),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::Bn254Poseidon2;
+    use crate::wallet::{EcdsaAccountWallet, EntrypointOptions};
+
+    const SECRET: &str = "0x0101010101010101010101010101010101010101010101010101010101010101";
+
+    #[test]
+    fn test_fpcs_pay_through_the_fee_payload() {
+        let wallet = EcdsaAccountWallet::new("0x0a", SECRET).unwrap();
+        let node_info = NodeInfo {
+            node_version: String::new(),
+            l1_chain_id: 31337,
+            rollup_version: 1,
+        };
+        let fpc_address = Fr::from(0xfcu8);
+        let asset = Fr::from(0xa5u8);
+
+        let juice = FeePaymentMethod::FeeJuice;
+        let settings = juice.gas_settings(GasSettings::default(), 10);
+        assert_eq!(settings.gas_limits.l2_gas, 1_100_000_000);
+        assert_eq!(settings.teardown_gas_limits.l2_gas, 0);
+        let options = juice
+            .fee_options(&wallet, settings, &node_info, &Bn254Poseidon2)
+            .unwrap();
+        assert!(options.is_fee_payer && options.calls.is_empty());
+
+        let private = FeePaymentMethod::PrivateFpc {
+            fpc_address: fpc_address.clone(),
+            asset: asset.clone(),
+        };
+        assert_eq!(private.fee_payer(&Fr::from(0x0au8)), fpc_address);
+        let settings = private.gas_settings(GasSettings::default(), 10);
+        assert_eq!(settings.teardown_gas_limits.l2_gas, 6_600_000);
+        let options = private
+            .fee_options(&wallet, settings.clone(), &node_info, &Bn254Poseidon2)
+            .unwrap();
+        assert!(!options.is_fee_payer);
+        let [fee_call] = options.calls.as_slice() else {
+            panic!("expected one fee call, got {:?}", options.calls);
+        };
+        assert_eq!(fee_call.to, fpc_address);
+        assert!(!fee_call.is_public);
+        let max_fee = Fr::from(max_fee(settings.gas_limits, &settings.max_fees_per_gas));
+        assert_eq!(fee_call.args[0], max_fee);
+        let transfer = FunctionCall {
+            to: asset.clone(),
+            selector: FunctionSelector::from_signature(
+                "transfer_to_public((Field),(Field),u128,Field)",
+            ),
+            args: vec![
+                Fr::from(0x0au8),
+                fpc_address.clone(),
+                max_fee,
+                fee_call.args[1].clone(),
+            ],
+            is_public: false,
+            is_static: false,
+        };
+        let message_hash =
+            authwit::message_hash(&fpc_address, &transfer, &node_info, &Bn254Poseidon2);
+        assert_eq!(
+            options.auth_witnesses[0].request_hash,
+            message_hash.unwrap()
+        );
+
+        // The FPC's witness rides along with the entrypoint's own.
+        let request = wallet
+            .create_tx_execution_request(
+                vec![],
+                EntrypointOptions {
+                    fee: options,
+                    ..EntrypointOptions::default()
+                },
+                &node_info,
+                &Bn254Poseidon2,
+            )
+            .unwrap();
+        assert_eq!(request.auth_witnesses.len(), 2);
+        let entrypoint = request.args_of_calls.last().unwrap();
+        // Fee payload's `is_fee_payer`, then `cancellable`.
+        assert_eq!(entrypoint.values[entrypoint.values.len() - 2], Fr::zero());
+
+        let public = FeePaymentMethod::PublicFpc { fpc_address, asset };
+        let options = public
+            .fee_options(&wallet, settings, &node_info, &Bn254Poseidon2)
+            .unwrap();
+        assert!(options.auth_witnesses.is_empty());
+        assert_eq!(options.calls.len(), 2);
+        assert_eq!(options.calls[0].to, Fr::from(AUTH_REGISTRY_ADDRESS));
+        assert!(options.calls.iter().all(|call| call.is_public));
+    }
+}
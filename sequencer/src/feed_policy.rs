@@ -0,0 +1,377 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::alerts::{Alert, AlertSink};
+use crate::feeds::FeedUpdate;
+use crate::fields::Fr;
+use crate::twap::Twap;
+
+/// When a feed's price is pushed on chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeedPolicy {
+    /// Pushed at least this often, even when the price holds.
+    pub heartbeat: Duration,
+    /// Pushed early when the price moves more than this, in basis points.
+    pub deviation_bps: u64,
+    /// An on-chain value older than this raises a `StalenessAlert`.
+    pub staleness: Duration,
+}
+
+impl Default for FeedPolicy {
+    fn default() -> Self {
+        FeedPolicy {
+            heartbeat: Duration::from_secs(3600),
+            deviation_bps: 50,
+            staleness: Duration::from_secs(7200),
+        }
+    }
+}
+
+impl FeedPolicy {
+    /// `heartbeat=<secs>,deviation_bps=<n>,staleness=<secs>`; settings left
+    /// out keep `base`'s.
+    pub fn parse(spec: &str, base: FeedPolicy) -> Result<Self, String> {
+        let mut policy = base;
+        for setting in spec.split(',').filter(|s| !s.trim().is_empty()) {
+            let (name, value) = setting
+                .split_once('=')
+                .ok_or_else(|| format!("Feed policy setting must be `name=value`: {}", setting))?;
+            let number: u64 = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid {}: {}", name.trim(), value))?;
+            match name.trim() {
+                "heartbeat" => policy.heartbeat = Duration::from_secs(number),
+                "deviation_bps" => policy.deviation_bps = number,
+                "staleness" => policy.staleness = Duration::from_secs(number),
+                other => return Err(format!("Unknown feed policy setting: {}", other)),
+            }
+        }
+        Ok(policy)
+    }
+}
+
+/// The policy of every feed: a default, overridden per feed id.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FeedPolicies {
+    pub default: FeedPolicy,
+    pub feeds: HashMap<Fr, FeedPolicy>,
+}
+
+impl FeedPolicies {
+    /// `feeds` is `<feed id>:<policy>` entries separated by `;`, each
+    /// starting from the default policy.
+    pub fn parse(default: Option<&str>, feeds: Option<&str>) -> Result<Self, String> {
+        let default = FeedPolicy::parse(default.unwrap_or_default(), FeedPolicy::default())?;
+        let feeds = feeds
+            .unwrap_or_default()
+            .split(';')
+            .filter(|entry| !entry.trim().is_empty())
+            .map(|entry| {
+                let (id, spec) = entry.split_once(':').ok_or_else(|| {
+                    format!("Feed policy must be `<feed id>:<policy>`: {}", entry)
+                })?;
+                Ok((Fr::try_from(id)?, FeedPolicy::parse(spec, default)?))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(FeedPolicies { default, feeds })
+    }
+
+    /// Reads `FEED_POLICY` (the default) and `FEED_POLICIES` (per feed);
+    /// `None` when neither is set.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let default = env::var("FEED_POLICY").ok();
+        let feeds = env::var("FEED_POLICIES").ok();
+        if default.is_none() && feeds.is_none() {
+            return Ok(None);
+        }
+        Self::parse(default.as_deref(), feeds.as_deref()).map(Some)
+    }
+
+    pub fn policy(&self, feed_id: &Fr) -> &FeedPolicy {
+        self.feeds.get(feed_id).unwrap_or(&self.default)
+    }
+}
+
+/// Why a feed is due.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateReason {
+    /// Nothing was pushed for the feed yet.
+    Initial,
+    Heartbeat,
+    /// The price moved this many basis points.
+    Deviation(u64),
+}
+
+/// A feed whose on-chain value outlived its staleness window.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StalenessAlert {
+    pub feed_id: Fr,
+    /// When the on-chain value was observed (unix seconds).
+    pub updated_at: u64,
+    pub age_secs: u64,
+    pub staleness_secs: u64,
+}
+
+impl fmt::Display for StalenessAlert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Feed {} was last updated {}s ago (allowed {}s)",
+            self.feed_id.to_hex(),
+            self.age_secs,
+            self.staleness_secs
+        )
+    }
+}
+
+/// Decides which feeds to push under their `FeedPolicies` and watches the
+/// pushed values for staleness.
+#[derive(Debug)]
+pub struct FeedScheduler {
+    policies: FeedPolicies,
+    on_chain: HashMap<Fr, FeedUpdate>,
+    // Feeds already alerted for, until their next update.
+    stale: HashSet<Fr>,
+    sinks: Vec<Arc<dyn AlertSink>>,
+    alerts: u64,
+    twap: Option<Twap>,
+}
+
+impl FeedScheduler {
+    pub fn new(policies: FeedPolicies) -> Self {
+        FeedScheduler {
+            policies,
+            on_chain: HashMap::new(),
+            stale: HashSet::new(),
+            sinks: vec![],
+            alerts: 0,
+            twap: None,
+        }
+    }
+
+    pub fn with_alert_sink(mut self, sink: Arc<dyn AlertSink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    /// Pushes each feed's time-weighted average instead of its spot price;
+    /// see `smooth`.
+    pub fn with_twap(mut self, twap: Twap) -> Self {
+        self.twap = Some(twap);
+        self
+    }
+
+    /// The spot and average prices, for metrics.
+    pub fn twap(&self) -> Option<&Twap> {
+        self.twap.as_ref()
+    }
+
+    pub fn policies(&self) -> &FeedPolicies {
+        &self.policies
+    }
+
+    /// Alerts raised so far, for metrics.
+    pub fn alerts_raised(&self) -> u64 {
+        self.alerts
+    }
+
+    /// The feed's value as last pushed or read from chain.
+    pub fn on_chain(&self, feed_id: &Fr) -> Option<&FeedUpdate> {
+        self.on_chain.get(feed_id)
+    }
+
+    /// Which of `observations` should be pushed at `now` (unix seconds).
+    pub fn due(&self, observations: &[FeedUpdate], now: u64) -> Vec<(FeedUpdate, UpdateReason)> {
+        observations
+            .iter()
+            .filter_map(|observed| {
+                let reason = self.reason(observed, now)?;
+                Some((observed.clone(), reason))
+            })
+            .collect()
+    }
+
+    fn reason(&self, observed: &FeedUpdate, now: u64) -> Option<UpdateReason> {
+        let Some(last) = self.on_chain.get(&observed.feed_id) else {
+            return Some(UpdateReason::Initial);
+        };
+        let policy = self.policies.policy(&observed.feed_id);
+        let bps = deviation_bps(last.price, observed.price);
+        if bps > policy.deviation_bps {
+            Some(UpdateReason::Deviation(bps))
+        } else if now.saturating_sub(last.timestamp) >= policy.heartbeat.as_secs() {
+            Some(UpdateReason::Heartbeat)
+        } else {
+            None
+        }
+    }
+
+    /// With a TWAP, records `observations` as samples and returns them
+    /// priced at their average at `now`; without, returns them as they are.
+    pub fn smooth(
+        &mut self,
+        observations: &[FeedUpdate],
+        now: u64,
+    ) -> Result<Vec<FeedUpdate>, String> {
+        let Some(twap) = &mut self.twap else {
+            return Ok(observations.to_vec());
+        };
+        twap.record(observations, now)?;
+        Ok(observations
+            .iter()
+            .map(|observed| FeedUpdate {
+                price: twap
+                    .average(&observed.feed_id, now)
+                    .unwrap_or(observed.price),
+                ..observed.clone()
+            })
+            .collect())
+    }
+
+    /// Records values now on chain: pushed by us, or read at startup.
+    pub fn record(&mut self, updates: &[FeedUpdate]) {
+        for update in updates {
+            self.stale.remove(&update.feed_id);
+            self.on_chain.insert(update.feed_id.clone(), update.clone());
+        }
+    }
+
+    /// Alerts every sink about feeds whose on-chain value is older than
+    /// their staleness window at `now`. A feed alerts once per stale spell.
+    pub fn check_staleness(&mut self, now: u64) -> Vec<StalenessAlert> {
+        let mut alerts: Vec<StalenessAlert> = self
+            .on_chain
+            .values()
+            .filter(|update| !self.stale.contains(&update.feed_id))
+            .filter_map(|update| {
+                let staleness = self.policies.policy(&update.feed_id).staleness.as_secs();
+                let age = now.saturating_sub(update.timestamp);
+                (age > staleness).then(|| StalenessAlert {
+                    feed_id: update.feed_id.clone(),
+                    updated_at: update.timestamp,
+                    age_secs: age,
+                    staleness_secs: staleness,
+                })
+            })
+            .collect();
+        alerts.sort_by(|a, b| a.feed_id.cmp(&b.feed_id));
+        for alert in &alerts {
+            self.stale.insert(alert.feed_id.clone());
+            let alert = Alert::StaleFeed(alert.clone());
+            for sink in &self.sinks {
+                sink.alert(&alert);
+            }
+        }
+        self.alerts += alerts.len() as u64;
+        alerts
+    }
+}
+
+/// How far `to` is from `from`, in basis points of `from`. Any move away
+/// from zero counts as unbounded.
+fn deviation_bps(from: u128, to: u128) -> u64 {
+    let diff = from.abs_diff(to);
+    if diff == 0 {
+        return 0;
+    }
+    if from == 0 {
+        return u64::MAX;
+    }
+    diff.checked_mul(10_000)
+        .map(|scaled| scaled / from)
+        .and_then(|bps| u64::try_from(bps).ok())
+        .unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::tests::Collect;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn update(feed: u8, price: u128, timestamp: u64) -> FeedUpdate {
+        FeedUpdate {
+            feed_id: Fr::from(feed),
+            price,
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn test_policy_parsing() {
+        let policies = FeedPolicies::parse(
+            Some("heartbeat=600"),
+            Some("7:deviation_bps=10; 0x08:heartbeat=60,staleness=120"),
+        )
+        .unwrap();
+        assert_eq!(policies.default.heartbeat, Duration::from_secs(600));
+        assert_eq!(policies.default.deviation_bps, 50);
+        let seven = policies.policy(&Fr::from(7u8));
+        assert_eq!((seven.heartbeat.as_secs(), seven.deviation_bps), (600, 10));
+        assert_eq!(
+            policies.policy(&Fr::from(8u8)).staleness,
+            Duration::from_secs(120)
+        );
+        assert_eq!(policies.policy(&Fr::from(9u8)), &policies.default);
+
+        assert!(FeedPolicies::parse(Some("heartbeat"), None).is_err());
+        assert!(FeedPolicies::parse(Some("cadence=5"), None).is_err());
+        assert!(FeedPolicies::parse(None, Some("7=heartbeat=5")).is_err());
+    }
+
+    #[test]
+    fn test_pushes_on_heartbeat_or_deviation() {
+        let policies = FeedPolicies::parse(Some("heartbeat=600,deviation_bps=50"), None).unwrap();
+        let mut scheduler = FeedScheduler::new(policies);
+        let first = scheduler.due(&[update(1, 10_000, NOW)], NOW);
+        assert_eq!(first, [(update(1, 10_000, NOW), UpdateReason::Initial)]);
+        scheduler.record(&[update(1, 10_000, NOW)]);
+
+        // 0.5% is within the threshold; 0.51% is not.
+        assert!(scheduler
+            .due(&[update(1, 10_050, NOW + 60)], NOW + 60)
+            .is_empty());
+        assert_eq!(
+            scheduler.due(&[update(1, 9_949, NOW + 60)], NOW + 60)[0].1,
+            UpdateReason::Deviation(51)
+        );
+        assert_eq!(
+            scheduler.due(&[update(1, 10_000, NOW + 600)], NOW + 600)[0].1,
+            UpdateReason::Heartbeat
+        );
+
+        assert_eq!(deviation_bps(0, 1), u64::MAX);
+        assert_eq!(deviation_bps(1, u128::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn test_stale_feeds_alert_once_until_updated() {
+        let policies = FeedPolicies::parse(None, Some("2:staleness=60")).unwrap();
+        let sink = Arc::new(Collect::default());
+        let mut scheduler = FeedScheduler::new(policies).with_alert_sink(sink.clone());
+        scheduler.record(&[update(1, 5, NOW), update(2, 5, NOW)]);
+
+        assert!(scheduler.check_staleness(NOW + 60).is_empty());
+        let alerts = scheduler.check_staleness(NOW + 61);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].feed_id, Fr::from(2u8));
+        assert_eq!(alerts[0].age_secs, 61);
+        assert!(scheduler.check_staleness(NOW + 120).is_empty());
+        assert_eq!(
+            *sink.0.lock().unwrap(),
+            [Alert::StaleFeed(alerts[0].clone())]
+        );
+
+        scheduler.record(&[update(2, 6, NOW + 130)]);
+        assert!(scheduler.check_staleness(NOW + 190).is_empty());
+        assert_eq!(scheduler.check_staleness(NOW + 191).len(), 1);
+        assert_eq!(scheduler.alerts_raised(), 2);
+    }
+}
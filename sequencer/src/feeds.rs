@@ -0,0 +1,382 @@
+use serde_json::{json, Value};
+use tracing::Instrument;
+
+use crate::aztec_rpc_client::AztecRpcClient;
+use crate::contract::{Contract, SimulateOptions};
+use crate::encoder::{AbiType, ArgValue};
+use crate::feed_policy::FeedScheduler;
+use crate::fields::Fr;
+use crate::fixed_point::{Decimal, FixedPoint};
+use crate::pxe_api::PxeApi;
+
+const SET_FIELD: &str = "set_just_field";
+const GET_FIELD: &str = "get_just_field";
+const SET_FEED: &str = "set_field_in_map";
+const READ_FEED: &str = "read_field_in_map";
+const SET_FEEDS: &str = "set_feeds";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeedUpdate {
+    pub feed_id: Fr,
+    pub price: u128,
+    pub timestamp: u64,
+}
+
+impl FeedUpdate {
+    /// An update for a price as fetched, scaled by `fixed` to the integer
+    /// the contract stores.
+    pub fn from_decimal(
+        feed_id: Fr,
+        price: &Decimal,
+        fixed: &FixedPoint,
+        timestamp: u64,
+    ) -> Result<Self, String> {
+        Ok(FeedUpdate {
+            feed_id,
+            price: fixed.to_scaled(price)?,
+            timestamp,
+        })
+    }
+
+    fn to_arg(&self) -> Value {
+        json!({
+            "feed_id": self.feed_id.0.to_string(),
+            "price": self.price.to_string(),
+            "timestamp": self.timestamp,
+        })
+    }
+}
+
+/// Typed calls into the price-feed contract (`contract/src/main.nr`), where
+/// each feed is a slot of `field_in_map` keyed by feed id.
+pub struct FeedContract<'a, P: ?Sized = AztecRpcClient> {
+    contract: Contract<'a, P>,
+}
+
+impl<'a, P: PxeApi + ?Sized> FeedContract<'a, P> {
+    pub fn new(contract: Contract<'a, P>) -> Self {
+        FeedContract { contract }
+    }
+
+    pub fn contract(&self) -> &Contract<'a, P> {
+        &self.contract
+    }
+
+    pub async fn set_field(&self, value: Fr) -> Result<String, Box<dyn std::error::Error>> {
+        self.contract.method(SET_FIELD, vec![value])?.send().await
+    }
+
+    pub async fn get_field(&self) -> Result<Fr, Box<dyn std::error::Error>> {
+        self.read(GET_FIELD, vec![]).await
+    }
+
+    pub async fn set_feed(
+        &self,
+        feed_id: Fr,
+        price: Fr,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        self.contract
+            .method(SET_FEED, vec![feed_id, price])?
+            .send()
+            .await
+    }
+
+    pub async fn read_feed(&self, feed_id: Fr) -> Result<Fr, Box<dyn std::error::Error>> {
+        self.read(READ_FEED, vec![feed_id.into()]).await
+    }
+
+    /// Sends `updates` in one `set_feeds` tx. The contract takes a fixed-size
+    /// array of `{ feed_id, price, timestamp }`, so short batches are padded
+    /// with zeroed entries and oversized ones are rejected.
+    pub async fn set_feeds(
+        &self,
+        updates: &[FeedUpdate],
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let args = self.set_feeds_args(updates)?;
+        self.contract.method(SET_FEEDS, args)?.send().await
+    }
+
+    /// Sends `updates` in as few `set_feeds` txs as the contract's batch
+    /// size allows (see `batches`), one after the other, and returns their
+    /// hashes. On a failed send the batches before it stay sent.
+    pub async fn set_feeds_batched(
+        &self,
+        updates: &[FeedUpdate],
+    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut tx_hashes = vec![];
+        for batch in self.batches(updates)? {
+            tx_hashes.push(self.set_feeds(&batch).await?);
+        }
+        Ok(tx_hashes)
+    }
+
+    /// Groups `updates` into `set_feeds` calls of at most the ABI's array
+    /// length. A feed updated more than once keeps only its latest update,
+    /// so no slot goes to a price that is already outdated.
+    pub fn batches(&self, updates: &[FeedUpdate]) -> Result<Vec<Vec<FeedUpdate>>, String> {
+        let capacity = self.batch_capacity()?;
+        let mut latest: Vec<FeedUpdate> = Vec::with_capacity(updates.len());
+        for update in updates {
+            match latest.iter_mut().find(|u| u.feed_id == update.feed_id) {
+                Some(kept) if kept.timestamp <= update.timestamp => *kept = update.clone(),
+                Some(_) => {}
+                None => latest.push(update.clone()),
+            }
+        }
+        Ok(latest.chunks(capacity).map(<[_]>::to_vec).collect())
+    }
+
+    /// Checks `scheduler` for stale feeds, then sends the `observations`
+    /// its policies say are due at `now`, batched into as few `set_feeds`
+    /// txs as fit. With a TWAP on the scheduler the averages are pushed
+    /// rather than the spot prices. Empty when nothing was due.
+    pub async fn update_feeds(
+        &self,
+        scheduler: &mut FeedScheduler,
+        observations: &[FeedUpdate],
+        now: u64,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        // Before sending, so failing sends still end in an alarm.
+        scheduler.check_staleness(now);
+        let observations = scheduler.smooth(observations, now)?;
+        let due: Vec<FeedUpdate> = scheduler
+            .due(&observations, now)
+            .into_iter()
+            .map(|(update, _)| update)
+            .collect();
+        // Recorded batch by batch, so a failure part way leaves the sent
+        // ones known to be on chain.
+        let span = tracing::info_span!("feeds.update", due = due.len());
+        let mut tx_hashes = vec![];
+        for batch in self.batches(&due)? {
+            tx_hashes.push(self.set_feeds(&batch).instrument(span.clone()).await?);
+            scheduler.record(&batch);
+        }
+        Ok(tx_hashes)
+    }
+
+    /// How many updates one `set_feeds` call takes: its array parameter's
+    /// length.
+    pub fn batch_capacity(&self) -> Result<usize, String> {
+        let interaction = self.contract.method(SET_FEEDS, Vec::<ArgValue>::new())?;
+        match interaction.parameters() {
+            [param] => match &param.abi_type {
+                AbiType::Array { length: 0, .. } => {
+                    Err(format!("`{}` takes an empty array", SET_FEEDS))
+                }
+                AbiType::Array { length, .. } => Ok(*length),
+                other => Err(format!("`{}` takes {}, not an array", SET_FEEDS, other)),
+            },
+            params => Err(format!(
+                "`{}` takes {} parameters, expected a single array",
+                SET_FEEDS,
+                params.len()
+            )),
+        }
+    }
+
+    fn set_feeds_args(&self, updates: &[FeedUpdate]) -> Result<Vec<Value>, String> {
+        let capacity = self.batch_capacity()?;
+        if updates.len() > capacity {
+            return Err(format!(
+                "`{}` accepts at most {} updates, got {}",
+                SET_FEEDS,
+                capacity,
+                updates.len()
+            ));
+        }
+
+        let empty = FeedUpdate {
+            feed_id: Fr::zero(),
+            price: 0,
+            timestamp: 0,
+        };
+        let batch: Vec<Value> = updates
+            .iter()
+            .chain(std::iter::repeat(&empty))
+            .take(capacity)
+            .map(FeedUpdate::to_arg)
+            .collect();
+        Ok(vec![Value::Array(batch)])
+    }
+
+    async fn read(
+        &self,
+        function: &str,
+        args: Vec<ArgValue>,
+    ) -> Result<Fr, Box<dyn std::error::Error>> {
+        let simulation = self
+            .contract
+            .method(function, args)?
+            .simulate_result(SimulateOptions::default())
+            .await?;
+        simulation
+            .return_fields()?
+            .into_iter()
+            .next()
+            .ok_or_else(|| format!("`{}` returned no value", function).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aztec_rpc_client::AztecRpcClient;
+    use crate::feed_policy::FeedPolicies;
+    use crate::testing::{fixtures, MockPxe};
+    use crate::tx_request::DEFAULT_ORIGIN;
+
+    const ADDRESS: &str = "0x12d8f70092c1d4b2bf3ddd60af8e47c1a10d90f3f31fe4c874d4b91f58442ede";
+
+    #[tokio::test]
+    async fn test_read_feed_decodes_public_return_value() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond(
+            "pxe_simulateTx",
+            json!({ "publicOutput": { "publicReturnValues": [{ "values": ["0xd6"], "hash": "0x00" }] } }),
+        );
+        let pxe = mock.client();
+        let feeds = FeedContract::new(Contract::at(
+            &pxe,
+            DEFAULT_ORIGIN,
+            ADDRESS,
+            fixtures().artifact.clone(),
+        ));
+
+        assert_eq!(
+            feeds.read_feed(Fr::from(1u8)).await.unwrap(),
+            Fr::from(214u8)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_field_reads_recorded_simulation() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond("pxe_simulateTx", fixtures().simulate_get.clone());
+        let pxe = mock.client();
+        let feeds = FeedContract::new(Contract::at(
+            &pxe,
+            DEFAULT_ORIGIN,
+            ADDRESS,
+            fixtures().artifact.clone(),
+        ));
+
+        assert_eq!(feeds.get_field().await.unwrap(), Fr::from(700u16));
+    }
+
+    #[tokio::test]
+    async fn test_update_feeds_sends_only_due_feeds() {
+        let mock = MockPxe::start().await.unwrap();
+        fixtures().serve_send(&mock);
+        let pxe = mock.client();
+        let feeds = FeedContract::new(Contract::at(
+            &pxe,
+            DEFAULT_ORIGIN,
+            ADDRESS,
+            fixtures().artifact.clone(),
+        ));
+        let policies = FeedPolicies::parse(Some("heartbeat=600,deviation_bps=100"), None).unwrap();
+        let mut scheduler = FeedScheduler::new(policies);
+        let observe = |price: u128, timestamp: u64| {
+            [FeedUpdate {
+                feed_id: Fr::from(7u8),
+                price,
+                timestamp,
+            }]
+        };
+
+        let sent = feeds
+            .update_feeds(&mut scheduler, &observe(1_000, 100), 100)
+            .await
+            .unwrap();
+        assert_eq!(sent, [fixtures().tx_hash.clone()]);
+        let held = feeds
+            .update_feeds(&mut scheduler, &observe(1_005, 160), 160)
+            .await
+            .unwrap();
+        assert!(held.is_empty());
+        assert_eq!(scheduler.on_chain(&Fr::from(7u8)).unwrap().price, 1_000);
+
+        let sends = mock
+            .requests()
+            .iter()
+            .filter(|r| r["method"] == "pxe_sendTx")
+            .count();
+        assert_eq!(sends, 1);
+    }
+
+    #[test]
+    fn test_set_feeds_pads_batch_to_abi_length() {
+        let pxe = AztecRpcClient::new("http://localhost:0", None);
+        let feeds = FeedContract::new(Contract::at(
+            &pxe,
+            DEFAULT_ORIGIN,
+            ADDRESS,
+            fixtures().artifact.clone(),
+        ));
+        let update = FeedUpdate::from_decimal(
+            Fr::from(7u8),
+            &"1000.0".parse().unwrap(),
+            &FixedPoint::new(18).unwrap(),
+            1_700_000_000,
+        )
+        .unwrap();
+
+        let args = feeds.set_feeds_args(std::slice::from_ref(&update)).unwrap();
+        let batch = args[0].as_array().unwrap();
+        assert_eq!(batch.len(), 3);
+        assert_eq!(batch[0]["price"], json!("1000000000000000000000"));
+        assert_eq!(batch[2]["feed_id"], json!("0"));
+
+        let interaction = feeds.contract().method(SET_FEEDS, args).unwrap();
+        assert_eq!(interaction.encode_args().unwrap().len(), 9);
+
+        let err = feeds.set_feeds_args(&vec![update; 4]).unwrap_err();
+        assert!(err.contains("at most 3"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_set_feeds_batched_packs_updates_by_capacity() {
+        let mock = MockPxe::start().await.unwrap();
+        fixtures().serve_send(&mock);
+        let pxe = mock.client();
+        let feeds = FeedContract::new(Contract::at(
+            &pxe,
+            DEFAULT_ORIGIN,
+            ADDRESS,
+            fixtures().artifact.clone(),
+        ));
+        let update = |feed: u8, price: u128, timestamp: u64| FeedUpdate {
+            feed_id: Fr::from(feed),
+            price,
+            timestamp,
+        };
+        let updates = [
+            update(1, 10, 5),
+            update(2, 20, 5),
+            update(1, 11, 6),
+            update(3, 30, 5),
+            update(4, 40, 5),
+            update(2, 19, 4),
+        ];
+
+        let batches = feeds.batches(&updates).unwrap();
+        assert_eq!(feeds.batch_capacity().unwrap(), 3);
+        assert_eq!(
+            batches,
+            [
+                vec![update(1, 11, 6), update(2, 20, 5), update(3, 30, 5)],
+                vec![update(4, 40, 5)],
+            ]
+        );
+
+        let tx_hashes = feeds.set_feeds_batched(&updates).await.unwrap();
+        assert_eq!(tx_hashes.len(), 2);
+        let sends = mock
+            .requests()
+            .iter()
+            .filter(|r| r["method"] == "pxe_sendTx")
+            .count();
+        assert_eq!(sends, 2);
+    }
+}
@@ -0,0 +1,359 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::env;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::fields::Fr;
+use crate::state::StateStore;
+use crate::tx_request::{Gas, GasFees};
+
+const SPENDS_KEY: &str = "fees/spends";
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+
+/// Fee limits in fee juice base units; `None` means unlimited.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeeLimits {
+    #[serde(deserialize_with = "fee_amount")]
+    pub max_tx_fee: Option<u128>,
+    #[serde(deserialize_with = "fee_amount")]
+    pub hourly_cap: Option<u128>,
+    #[serde(deserialize_with = "fee_amount")]
+    pub daily_cap: Option<u128>,
+}
+
+// An integer, or a decimal or hex string for amounts past what config
+// formats such as TOML hold in an integer.
+fn fee_amount<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<u128>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Amount {
+        Integer(u64),
+        String(String),
+    }
+    match Amount::deserialize(deserializer)? {
+        Amount::Integer(fee) => Ok(Some(fee as u128)),
+        Amount::String(fee) => Fr::try_from(fee.as_str())
+            .ok()
+            .and_then(|fee| fee.to_u128())
+            .map(Some)
+            .ok_or_else(|| serde::de::Error::custom(format!("Invalid fee amount: {}", fee))),
+    }
+}
+
+impl FeeLimits {
+    /// Reads `FEE_MAX_PER_TX`, `FEE_HOURLY_CAP` and `FEE_DAILY_CAP`; `None`
+    /// when none of them is set.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let limit = |name: &str| match env::var(name) {
+            Ok(value) => Fr::try_from(value.as_str())
+                .ok()
+                .and_then(|fee| fee.to_u128())
+                .map(Some)
+                .ok_or_else(|| format!("Invalid {}: {}", name, value)),
+            Err(_) => Ok(None),
+        };
+        let limits = FeeLimits {
+            max_tx_fee: limit("FEE_MAX_PER_TX")?,
+            hourly_cap: limit("FEE_HOURLY_CAP")?,
+            daily_cap: limit("FEE_DAILY_CAP")?,
+        };
+        Ok((limits != FeeLimits::default()).then_some(limits))
+    }
+}
+
+/// The most a tx using `gas` can cost at `fees` per unit of gas.
+pub fn max_fee(gas: Gas, fees: &GasFees) -> u128 {
+    let cost =
+        |gas: u64, fee: &Fr| (gas as u128).saturating_mul(fee.to_u128().unwrap_or(u128::MAX));
+    cost(gas.da_gas, &fees.fee_per_da_gas).saturating_add(cost(gas.l2_gas, &fees.fee_per_l2_gas))
+}
+
+#[derive(Debug)]
+pub enum FeeBudgetError {
+    TxFeeTooHigh {
+        fee: u128,
+        max: u128,
+    },
+    CapExceeded {
+        window: &'static str,
+        spent: u128,
+        fee: u128,
+        cap: u128,
+    },
+    Store(String),
+}
+
+impl fmt::Display for FeeBudgetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeeBudgetError::TxFeeTooHigh { fee, max } => {
+                write!(f, "Tx fee up to {} exceeds the per-tx max of {}", fee, max)
+            }
+            FeeBudgetError::CapExceeded {
+                window,
+                spent,
+                fee,
+                cap,
+            } => write!(
+                f,
+                "Tx fee up to {} would exceed the {} cap of {} ({} already spent)",
+                fee, window, cap, spent
+            ),
+            FeeBudgetError::Store(e) => write!(f, "Fee budget state: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for FeeBudgetError {}
+
+// A fee counted against the caps: the estimate while the tx is in flight,
+// the receipt's `transactionFee` once it has one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Spend {
+    id: u64,
+    tx_hash: Option<String>,
+    at: u64,
+    fee: Fr,
+    settled: bool,
+}
+
+impl Spend {
+    fn fee(&self) -> u128 {
+        self.fee.to_u128().unwrap_or(u128::MAX)
+    }
+}
+
+/// Keeps an unattended sequencer within its fee limits. Every send reserves
+/// its worst-case fee first and is refused if that would break a limit; the
+/// reservation is replaced by the actual fee when a receipt for the tx is
+/// fetched. Spends of the last day are kept in the `StateStore`.
+#[derive(Debug)]
+pub struct FeeBudget {
+    limits: FeeLimits,
+    store: Arc<StateStore>,
+    refusals: AtomicU64,
+    // Serializes the read-check-write of the spend list.
+    lock: Mutex<()>,
+}
+
+impl FeeBudget {
+    pub fn new(limits: FeeLimits, store: Arc<StateStore>) -> Self {
+        FeeBudget {
+            limits,
+            store,
+            refusals: AtomicU64::new(0),
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn limits(&self) -> &FeeLimits {
+        &self.limits
+    }
+
+    /// How many sends have been refused since startup.
+    pub fn refusals(&self) -> u64 {
+        self.refusals.load(Ordering::Relaxed)
+    }
+
+    /// Fees counted since `since` (unix seconds), settled or not.
+    pub fn spent_since(&self, since: u64) -> Result<u128, String> {
+        Ok(spent_since(&self.spends()?, since))
+    }
+
+    /// Reserves `fee` at `now` (unix seconds) and returns the reservation id,
+    /// or refuses the send; sends raise `Alert::FeeBudgetExceeded` for it.
+    pub fn reserve(&self, fee: u128, now: u64) -> Result<u64, FeeBudgetError> {
+        let _guard = self.lock.lock().unwrap();
+        let mut spends = self.spends().map_err(FeeBudgetError::Store)?;
+        spends.retain(|spend| spend.at + DAY > now);
+
+        if let Err(e) = self.admit(&spends, fee, now) {
+            self.refusals.fetch_add(1, Ordering::Relaxed);
+            return Err(e);
+        }
+
+        let id = spends.iter().map(|spend| spend.id + 1).max().unwrap_or(0);
+        spends.push(Spend {
+            id,
+            tx_hash: None,
+            at: now,
+            fee: Fr::from(fee),
+            settled: false,
+        });
+        self.store
+            .put(SPENDS_KEY, &spends)
+            .map_err(FeeBudgetError::Store)?;
+        Ok(id)
+    }
+
+    /// Ties a reservation to the tx that was sent for it.
+    pub fn assign(&self, id: u64, tx_hash: &str) -> Result<(), String> {
+        self.update(|spends| {
+            if let Some(spend) = spends.iter_mut().find(|spend| spend.id == id) {
+                spend.tx_hash = Some(tx_hash.to_lowercase());
+            }
+        })
+    }
+
+    /// Drops a reservation whose tx was never sent.
+    pub fn release(&self, id: u64) -> Result<(), String> {
+        self.update(|spends| spends.retain(|spend| spend.id != id))
+    }
+
+    /// Replaces the estimate for the receipt's tx with its `transactionFee`.
+    /// Receipts without a fee (pending or dropped txs) are ignored.
+    pub fn settle(&self, receipt: &Value) -> Result<(), String> {
+        let (Some(tx_hash), Some(fee)) = (
+            receipt["txHash"].as_str(),
+            receipt.get("transactionFee").filter(|fee| !fee.is_null()),
+        ) else {
+            return Ok(());
+        };
+        let fee: Fr = serde_json::from_value(fee.clone())
+            .map_err(|e| format!("Invalid transactionFee in receipt: {}", e))?;
+        let tx_hash = tx_hash.to_lowercase();
+
+        self.update(|spends| {
+            if let Some(spend) = spends
+                .iter_mut()
+                .find(|spend| spend.tx_hash.as_deref() == Some(tx_hash.as_str()))
+            {
+                spend.fee = fee;
+                spend.settled = true;
+            }
+        })
+    }
+
+    fn admit(&self, spends: &[Spend], fee: u128, now: u64) -> Result<(), FeeBudgetError> {
+        if let Some(max) = self.limits.max_tx_fee {
+            if fee > max {
+                return Err(FeeBudgetError::TxFeeTooHigh { fee, max });
+            }
+        }
+        for (window, length, cap) in [
+            ("hourly", HOUR, self.limits.hourly_cap),
+            ("daily", DAY, self.limits.daily_cap),
+        ] {
+            let Some(cap) = cap else { continue };
+            let spent = spent_since(spends, now.saturating_sub(length));
+            if spent.saturating_add(fee) > cap {
+                return Err(FeeBudgetError::CapExceeded {
+                    window,
+                    spent,
+                    fee,
+                    cap,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn spends(&self) -> Result<Vec<Spend>, String> {
+        Ok(self.store.get(SPENDS_KEY)?.unwrap_or_default())
+    }
+
+    fn update(&self, change: impl FnOnce(&mut Vec<Spend>)) -> Result<(), String> {
+        let _guard = self.lock.lock().unwrap();
+        let mut spends = self.spends()?;
+        change(&mut spends);
+        self.store.put(SPENDS_KEY, &spends)
+    }
+}
+
+fn spent_since(spends: &[Spend], since: u64) -> u128 {
+    spends
+        .iter()
+        .filter(|spend| spend.at > since)
+        .fold(0u128, |total, spend| total.saturating_add(spend.fee()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn budget(limits: FeeLimits) -> FeeBudget {
+        FeeBudget::new(limits, Arc::new(StateStore::in_memory()))
+    }
+
+    #[test]
+    fn test_max_fee() {
+        let fees = GasFees {
+            fee_per_da_gas: Fr::from(2u8),
+            fee_per_l2_gas: Fr::from(10u8),
+        };
+        let gas = Gas {
+            da_gas: 100,
+            l2_gas: 30,
+        };
+        assert_eq!(max_fee(gas, &fees), 500);
+    }
+
+    #[test]
+    fn test_enforces_per_tx_and_period_caps() {
+        let budget = budget(FeeLimits {
+            max_tx_fee: Some(100),
+            hourly_cap: Some(150),
+            daily_cap: Some(220),
+        });
+
+        let too_high = budget.reserve(101, NOW).unwrap_err();
+        assert!(matches!(too_high, FeeBudgetError::TxFeeTooHigh { .. }));
+
+        budget.reserve(100, NOW).unwrap();
+        let hourly = budget.reserve(60, NOW + 10).unwrap_err();
+        assert!(matches!(
+            hourly,
+            FeeBudgetError::CapExceeded {
+                window: "hourly",
+                ..
+            }
+        ));
+
+        // An hour later only the daily cap is left to hit.
+        budget.reserve(100, NOW + HOUR).unwrap();
+        let daily = budget.reserve(40, NOW + HOUR + 10).unwrap_err();
+        assert!(matches!(
+            daily,
+            FeeBudgetError::CapExceeded {
+                window: "daily",
+                ..
+            }
+        ));
+        assert_eq!(budget.refusals(), 3);
+
+        // Spends older than a day no longer count.
+        budget.reserve(100, NOW + DAY).unwrap();
+    }
+
+    #[test]
+    fn test_receipts_replace_estimates() {
+        let budget = budget(FeeLimits {
+            hourly_cap: Some(1000),
+            ..Default::default()
+        });
+        let sent = budget.reserve(900, NOW).unwrap();
+        budget.assign(sent, "0xABC").unwrap();
+        let failed = budget.reserve(50, NOW).unwrap();
+        budget.release(failed).unwrap();
+        assert!(budget.reserve(200, NOW).is_err());
+
+        budget
+            .settle(&json!({ "txHash": "0xabc", "status": "pending" }))
+            .unwrap();
+        assert_eq!(budget.spent_since(0).unwrap(), 900);
+
+        budget
+            .settle(&json!({ "txHash": "0xabc", "status": "success", "transactionFee": "0x78" }))
+            .unwrap();
+        assert_eq!(budget.spent_since(0).unwrap(), 120);
+        budget.reserve(200, NOW).unwrap();
+    }
+}
@@ -0,0 +1,259 @@
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+
+use crate::aztec_rpc_client::AztecRpcClient;
+use crate::contract::{Contract, SimulateOptions};
+use crate::encoder::load_contract_artifact;
+use crate::secret::Zeroizing;
+use crate::tx_request::DEFAULT_ORIGIN;
+use crate::wallet::WalletConfig;
+
+/// An RPC client with its own runtime, for callers outside Rust. Made by
+/// `aztec_client_new`, released by `aztec_client_free`.
+pub struct AztecClient {
+    runtime: Runtime,
+    pxe: AztecRpcClient,
+    from: String,
+}
+
+// No `Debug`: it would print `secret_key`.
+#[derive(Deserialize)]
+struct ClientConfig {
+    url: String,
+    #[serde(default)]
+    namespace: Option<String>,
+    #[serde(default)]
+    from: Option<String>,
+    /// `schnorr` or `ecdsa`, as `ACCOUNT_KIND`.
+    #[serde(default)]
+    account_kind: Option<String>,
+    /// Signs txs from `from`; without it every call fails for want of a
+    /// wallet. Wiped from memory once the wallet is built.
+    #[serde(default)]
+    secret_key: Option<Zeroizing<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CallFunction {
+    contract: String,
+    /// Path to the contract's artifact JSON.
+    artifact: String,
+    function: String,
+    #[serde(default)]
+    args: Vec<Value>,
+    /// Sends a tx instead of simulating.
+    #[serde(default)]
+    send: bool,
+    #[serde(default)]
+    from: Option<String>,
+}
+
+/// Makes a client from `{"url", "namespace"?, "from"?, "account_kind"?,
+/// "secret_key"?}`. Returns null on failure, and then sets `*error` (when
+/// not null) to a message to release with `aztec_string_free`.
+///
+/// # Safety
+/// `config` must be a NUL-terminated string; `error` null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn aztec_client_new(
+    config: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut AztecClient {
+    let client = panic::catch_unwind(AssertUnwindSafe(|| {
+        let config = read(config)?;
+        let config: ClientConfig =
+            serde_json::from_str(config).map_err(|e| format!("Invalid client config: {}", e))?;
+        let runtime = Runtime::new().map_err(|e| format!("Cannot start runtime: {}", e))?;
+        let from = config.from.unwrap_or_else(|| DEFAULT_ORIGIN.to_string());
+        let mut pxe = AztecRpcClient::new(config.url, config.namespace);
+        if let Some(secret_key) = config.secret_key {
+            let wallet = WalletConfig::parse(config.account_kind.as_deref(), &from, &secret_key)?;
+            pxe = pxe.with_wallet(Arc::from(wallet.into_wallet(None)?));
+        }
+        Ok(AztecClient { runtime, pxe, from })
+    }))
+    .unwrap_or_else(|_| Err("Panicked".to_string()));
+    match client {
+        Ok(client) => Box::into_raw(Box::new(client)),
+        Err(e) => {
+            if !error.is_null() {
+                *error = to_c(e);
+            }
+            ptr::null_mut()
+        }
+    }
+}
+
+/// # Safety
+/// `client` must come from `aztec_client_new` and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn aztec_client_free(client: *mut AztecClient) {
+    if !client.is_null() {
+        drop(Box::from_raw(client));
+    }
+}
+
+/// Simulates (or with `"send": true` sends) a call given as `{"contract",
+/// "artifact", "function", "args"?, "send"?, "from"?}`. Answers
+/// `{"result": ...}`, the tx hash when sending, or `{"error": "..."}`.
+///
+/// # Safety
+/// `client` must come from `aztec_client_new`; `request` must be a
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn aztec_call_function(
+    client: *const AztecClient,
+    request: *const c_char,
+) -> *mut c_char {
+    respond(client, request, |client, request| {
+        let call: CallFunction =
+            serde_json::from_str(request).map_err(|e| format!("Invalid call: {}", e))?;
+        let artifact = load_contract_artifact(&call.artifact).map_err(|e| e.to_string())?;
+        let from = call.from.unwrap_or_else(|| client.from.clone());
+        let contract = Contract::at(&client.pxe, from, call.contract, Arc::new(artifact));
+        let interaction = contract.method(&call.function, call.args)?;
+        client.runtime.block_on(async {
+            let result = if call.send {
+                interaction.send().await.map(Value::from)
+            } else {
+                interaction.simulate(SimulateOptions::default()).await
+            };
+            result.map_err(|e| e.to_string())
+        })
+    })
+}
+
+/// The receipt of the tx `tx_hash`, as `{"result": receipt}` or
+/// `{"error": "..."}`.
+///
+/// # Safety
+/// `client` must come from `aztec_client_new`; `tx_hash` must be a
+/// NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn aztec_get_receipt(
+    client: *const AztecClient,
+    tx_hash: *const c_char,
+) -> *mut c_char {
+    respond(client, tx_hash, |client, tx_hash| {
+        client
+            .runtime
+            .block_on(client.pxe.get_tx_receipt(tx_hash))
+            .map_err(|e| e.to_string())
+    })
+}
+
+/// Releases a string returned by this library.
+///
+/// # Safety
+/// `s` must come from this library and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn aztec_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+// Runs `f` on the decoded input and renders its outcome as the JSON string
+// the caller owns. Panics are caught so they never unwind into C.
+unsafe fn respond(
+    client: *const AztecClient,
+    input: *const c_char,
+    f: impl FnOnce(&AztecClient, &str) -> Result<Value, String>,
+) -> *mut c_char {
+    let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+        let client = client.as_ref().ok_or("Client is null")?;
+        f(client, read(input)?)
+    }))
+    .unwrap_or_else(|_| Err("Panicked".to_string()));
+    let body = match outcome {
+        Ok(result) => json!({ "result": result }),
+        Err(error) => json!({ "error": error }),
+    };
+    to_c(body.to_string())
+}
+
+unsafe fn read<'a>(s: *const c_char) -> Result<&'a str, String> {
+    if s.is_null() {
+        return Err("Input is null".to_string());
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| "Input is not UTF-8".to_string())
+}
+
+fn to_c(s: String) -> *mut c_char {
+    CString::new(s)
+        .unwrap_or_else(|_| c"Output contains NUL".into())
+        .into_raw()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{fixtures, MockPxe};
+
+    fn take(s: *mut c_char) -> String {
+        let text = unsafe { CStr::from_ptr(s) }.to_str().unwrap().to_string();
+        unsafe { aztec_string_free(s) };
+        text
+    }
+
+    fn take_json(s: *mut c_char) -> Value {
+        serde_json::from_str(&take(s)).unwrap()
+    }
+
+    #[test]
+    fn test_c_api_simulates_and_reads_receipts() {
+        let server = Runtime::new().unwrap();
+        let mock = server.block_on(MockPxe::start()).unwrap();
+        mock.respond("pxe_simulateTx", fixtures().simulate_get.clone());
+        mock.respond("pxe_getTxReceipt", json!({ "status": "success" }));
+
+        let mut error = ptr::null_mut();
+        let bad = CString::new("{}").unwrap();
+        assert!(unsafe { aztec_client_new(bad.as_ptr(), &mut error) }.is_null());
+        assert!(take(error).contains("url"));
+
+        let config = json!({
+            "url": mock.url(),
+            "namespace": "pxe",
+            "account_kind": "ecdsa",
+            "secret_key": "0x0101010101010101010101010101010101010101010101010101010101010101",
+        })
+        .to_string();
+        let config = CString::new(config).unwrap();
+        let client = unsafe { aztec_client_new(config.as_ptr(), ptr::null_mut()) };
+        assert!(!client.is_null());
+
+        let artifact = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/feed_contract.json"
+        );
+        let call = |function: &str| {
+            let request = json!({
+                "contract": "0x0a",
+                "artifact": artifact,
+                "function": function,
+            });
+            let request = CString::new(request.to_string()).unwrap();
+            take_json(unsafe { aztec_call_function(client, request.as_ptr()) })
+        };
+        assert!(!call("get_just_field")["result"].is_null());
+        assert!(call("no_such_function")["error"]
+            .as_str()
+            .unwrap()
+            .contains("no_such_function"));
+
+        let tx_hash = CString::new("0x01").unwrap();
+        let receipt = take_json(unsafe { aztec_get_receipt(client, tx_hash.as_ptr()) });
+        assert_eq!(receipt, json!({ "result": { "status": "success" } }));
+        assert_eq!(mock.requests().last().unwrap()["params"], json!(["0x01"]));
+
+        unsafe { aztec_client_free(client) };
+    }
+}
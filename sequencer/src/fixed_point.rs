@@ -0,0 +1,289 @@
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::fields::Fr;
+
+/// A decimal number exactly as written, such as a price from an exchange
+/// API: `digits × 10^exponent`, so nothing is lost to binary floats.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decimal {
+    negative: bool,
+    digits: String,
+    exponent: i64,
+}
+
+impl Decimal {
+    pub fn is_zero(&self) -> bool {
+        self.digits.bytes().all(|d| d == b'0')
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = String;
+
+    /// Takes `12`, `-0.5`, `.25`, `1.5e-3` and the like.
+    fn from_str(s: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid decimal '{}'", s);
+        let trimmed = s.trim();
+        let (negative, unsigned) = match trimmed.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+        };
+        let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse().map_err(|_| invalid())?),
+            None => (unsigned, 0i64),
+        };
+        let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let all_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if integer.len() + fraction.len() == 0 || !all_digits(integer) || !all_digits(fraction) {
+            return Err(invalid());
+        }
+        let exponent = i64::try_from(fraction.len())
+            .ok()
+            .and_then(|len| exponent.checked_sub(len))
+            .ok_or_else(invalid)?;
+        Ok(Decimal {
+            negative,
+            digits: format!("{}{}", integer, fraction),
+            exponent,
+        })
+    }
+}
+
+impl TryFrom<f64> for Decimal {
+    type Error = String;
+
+    /// Goes through the shortest string that reads back as `value`, so
+    /// `0.1` becomes exactly `0.1` rather than its binary approximation.
+    fn try_from(value: f64) -> Result<Self, String> {
+        if !value.is_finite() {
+            return Err(format!("{} is not a decimal", value));
+        }
+        value.to_string().parse()
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.negative && !self.is_zero() {
+            "-"
+        } else {
+            ""
+        };
+        write!(f, "{}{}e{}", sign, self.digits, self.exponent)
+    }
+}
+
+/// How digits past a `FixedPoint`'s decimals are dropped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Rounding {
+    /// Truncated toward zero.
+    Down,
+    /// Halves round up: 0.5 → 1, 1.5 → 2.
+    #[default]
+    HalfUp,
+    /// Banker's rounding, halves to the even neighbour: 0.5 → 0, 1.5 → 2.
+    HalfEven,
+}
+
+impl FromStr for Rounding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "down" => Ok(Rounding::Down),
+            "half_up" => Ok(Rounding::HalfUp),
+            "half_even" | "bankers" => Ok(Rounding::HalfEven),
+            other => Err(format!("Unknown rounding '{}'", other)),
+        }
+    }
+}
+
+/// Converts decimal prices to the scaled `u128`s the feed contract stores
+/// (`price × 10^decimals`), and back for display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedPoint {
+    pub decimals: u32,
+    pub rounding: Rounding,
+}
+
+impl FixedPoint {
+    /// `u128` holds 38 full decimal digits.
+    pub const MAX_DECIMALS: u32 = 38;
+
+    pub fn new(decimals: u32) -> Result<Self, String> {
+        if decimals > Self::MAX_DECIMALS {
+            return Err(format!(
+                "At most {} decimals fit a u128, got {}",
+                Self::MAX_DECIMALS,
+                decimals
+            ));
+        }
+        Ok(FixedPoint {
+            decimals,
+            rounding: Rounding::default(),
+        })
+    }
+
+    pub fn with_decimals(self, decimals: u32) -> Result<Self, String> {
+        Ok(FixedPoint::new(decimals)?.with_rounding(self.rounding))
+    }
+
+    pub fn with_rounding(mut self, rounding: Rounding) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    /// `FEED_DECIMALS` (default 8) and `FEED_ROUNDING` (`down`, `half_up`
+    /// or `half_even`; default `half_up`).
+    pub fn from_env() -> Result<Self, String> {
+        let decimals = match env::var("FEED_DECIMALS") {
+            Ok(decimals) => decimals
+                .parse()
+                .map_err(|_| format!("Invalid FEED_DECIMALS: {}", decimals))?,
+            Err(_) => 8,
+        };
+        let rounding = match env::var("FEED_ROUNDING") {
+            Ok(rounding) => rounding.parse()?,
+            Err(_) => Rounding::default(),
+        };
+        Ok(FixedPoint::new(decimals)?.with_rounding(rounding))
+    }
+
+    /// `value × 10^decimals`, rounded. Fails on negative values and on
+    /// results past `u128::MAX`.
+    pub fn to_scaled(&self, value: &Decimal) -> Result<u128, String> {
+        if value.is_zero() {
+            return Ok(0);
+        }
+        if value.negative {
+            return Err(format!("Negative value {} has no fixed-point form", value));
+        }
+        let overflow = || format!("{} × 10^{} overflows a u128", value, self.decimals);
+        let digits = value.digits.trim_start_matches('0');
+        let shift = value.exponent.saturating_add(i64::from(self.decimals));
+
+        let (integer, remainder) = if shift >= 0 {
+            // Anything longer than u128's 39 digits can't fit, so the
+            // padding stays small however large the exponent.
+            if (digits.len() as i64).saturating_add(shift) > 39 {
+                return Err(overflow());
+            }
+            (format!("{}{}", digits, "0".repeat(shift as usize)), "")
+        } else {
+            let cut = (digits.len() as i64).saturating_add(shift);
+            if cut <= 0 {
+                // Every digit is past the last decimal; only a leading
+                // digit right after it can round up.
+                let first = if cut == 0 { digits } else { "" };
+                (String::new(), first)
+            } else {
+                let (integer, remainder) = digits.split_at(cut as usize);
+                (integer.to_string(), remainder)
+            }
+        };
+        let scaled = match integer.as_str() {
+            "" => 0,
+            integer => integer.parse::<u128>().map_err(|_| overflow())?,
+        };
+
+        let mut rest = remainder.bytes();
+        let round_up = match (self.rounding, rest.next()) {
+            (Rounding::Down, _) | (_, None) => false,
+            (_, Some(d)) if d > b'5' => true,
+            (_, Some(d)) if d < b'5' => false,
+            (Rounding::HalfUp, _) => true,
+            (Rounding::HalfEven, _) => rest.any(|d| d != b'0') || scaled % 2 == 1,
+        };
+        if round_up {
+            scaled.checked_add(1).ok_or_else(overflow)
+        } else {
+            Ok(scaled)
+        }
+    }
+
+    /// `to_scaled` as a field element.
+    pub fn to_fr(&self, value: &Decimal) -> Result<Fr, String> {
+        self.to_scaled(value).map(Fr::from)
+    }
+
+    /// Parses and scales `value` in one go.
+    pub fn parse(&self, value: &str) -> Result<u128, String> {
+        self.to_scaled(&value.parse()?)
+    }
+
+    /// The decimal a scaled value stands for, with all its decimals:
+    /// 1250 at 2 decimals is `12.50`.
+    pub fn format(&self, scaled: u128) -> String {
+        if self.decimals == 0 {
+            return scaled.to_string();
+        }
+        let digits = format!("{:0>width$}", scaled, width = self.decimals as usize + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - self.decimals as usize);
+        format!("{}.{}", integer, fraction)
+    }
+
+    /// `format` for a field element, which must fit a `u128`.
+    pub fn format_fr(&self, scaled: &Fr) -> Result<String, String> {
+        scaled
+            .to_u128()
+            .map(|scaled| self.format(scaled))
+            .ok_or_else(|| format!("{} is too large for a fixed-point value", scaled.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scales_and_rounds_decimals() {
+        let fixed = FixedPoint::new(2).unwrap();
+        assert_eq!(fixed.parse("12.5").unwrap(), 1250);
+        assert_eq!(fixed.parse("0.125").unwrap(), 13);
+        assert_eq!(fixed.parse("1.5e-3").unwrap(), 0);
+        assert_eq!(fixed.parse("0.005").unwrap(), 1);
+        assert_eq!(fixed.parse("3e2").unwrap(), 30_000);
+        assert_eq!(fixed.parse("-0.00").unwrap(), 0);
+        assert!(fixed.parse("-1").is_err());
+        assert!(fixed.parse("1.2.3").is_err());
+        assert!(fixed.parse("1e40").is_err());
+        assert!(fixed.parse("1e9223372036854775807").is_err());
+        assert_eq!(fixed.parse("1e-9223372036854775807").unwrap(), 0);
+        assert_eq!(
+            fixed.to_scaled(&Decimal::try_from(0.1).unwrap()).unwrap(),
+            10
+        );
+        assert!(Decimal::try_from(f64::NAN).is_err());
+
+        let bankers = fixed.with_rounding(Rounding::HalfEven);
+        assert_eq!(bankers.parse("0.125").unwrap(), 12);
+        assert_eq!(bankers.parse("0.135").unwrap(), 14);
+        assert_eq!(bankers.parse("0.1251").unwrap(), 13);
+        assert_eq!(bankers.parse("0.005").unwrap(), 0);
+        let down = fixed.with_rounding(Rounding::Down);
+        assert_eq!(down.parse("0.129").unwrap(), 12);
+
+        let max = FixedPoint::new(0).unwrap();
+        assert_eq!(max.parse(&u128::MAX.to_string()).unwrap(), u128::MAX);
+        assert!(max.parse(&format!("{}.5", u128::MAX)).is_err());
+        assert!(max
+            .parse("340282366920938463463374607431768211456")
+            .is_err());
+        assert!(FixedPoint::new(39).is_err());
+    }
+
+    #[test]
+    fn test_formats_scaled_values() {
+        let fixed = FixedPoint::new(8).unwrap();
+        assert_eq!(fixed.format(1_250_000_000), "12.50000000");
+        assert_eq!(fixed.format(5), "0.00000005");
+        assert_eq!(FixedPoint::new(0).unwrap().format(42), "42");
+        let price = fixed.to_fr(&"2034.17".parse().unwrap()).unwrap();
+        assert_eq!(fixed.format_fr(&price).unwrap(), "2034.17000000");
+        assert!(fixed
+            .format_fr(&Fr::try_from("0x1000000000000000000000000000000000").unwrap())
+            .is_err());
+    }
+}
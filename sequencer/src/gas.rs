@@ -0,0 +1,312 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Arc, Mutex};
+
+use crate::encoder::FunctionSelector;
+use crate::state::StateStore;
+use crate::tx_request::Gas;
+
+const PROFILES_KEY: &str = "gas/profiles";
+const PENDING_KEY: &str = "gas/pending";
+
+/// How a `GasProfiler` turns past usage into limits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GasProfilerConfig {
+    /// Added on top of the 95th percentile, in percent.
+    pub margin_percent: u64,
+    /// Samples needed before a function gets its own limits.
+    pub min_samples: usize,
+    /// Samples kept per function; older ones are dropped first.
+    pub max_samples: usize,
+}
+
+impl Default for GasProfilerConfig {
+    fn default() -> Self {
+        GasProfilerConfig {
+            margin_percent: 20,
+            min_samples: 3,
+            max_samples: 100,
+        }
+    }
+}
+
+impl GasProfilerConfig {
+    /// Reads `GAS_PROFILE_MARGIN` (percent) and `GAS_PROFILE_MIN_SAMPLES`;
+    /// `None` when neither is set.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let margin = env::var("GAS_PROFILE_MARGIN").ok();
+        let min_samples = env::var("GAS_PROFILE_MIN_SAMPLES").ok();
+        if margin.is_none() && min_samples.is_none() {
+            return Ok(None);
+        }
+        let mut config = GasProfilerConfig::default();
+        if let Some(margin) = margin {
+            config.margin_percent = margin
+                .parse()
+                .map_err(|_| format!("Invalid GAS_PROFILE_MARGIN: {}", margin))?;
+        }
+        if let Some(min_samples) = min_samples {
+            config.min_samples = min_samples
+                .parse()
+                .map_err(|_| format!("Invalid GAS_PROFILE_MIN_SAMPLES: {}", min_samples))?;
+        }
+        Ok(Some(config))
+    }
+}
+
+// A sent tx whose gas is counted once its receipt says it succeeded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Pending {
+    function: String,
+    gas: Gas,
+}
+
+/// Learns the gas each (contract, function selector) actually uses and
+/// suggests limits for its next calls: the 95th percentile plus a margin,
+/// never above the request's own limits. Receipts carry no gas, so a send
+/// records the gas its simulation used and a `success` receipt for the tx
+/// confirms it; reverted and dropped txs are forgotten. Samples are kept in
+/// the `StateStore`.
+#[derive(Debug)]
+pub struct GasProfiler {
+    config: GasProfilerConfig,
+    store: Arc<StateStore>,
+    lock: Mutex<()>,
+}
+
+impl GasProfiler {
+    pub fn new(config: GasProfilerConfig, store: Arc<StateStore>) -> Self {
+        GasProfiler {
+            config,
+            store,
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn config(&self) -> &GasProfilerConfig {
+        &self.config
+    }
+
+    /// Remembers that `tx_hash` calls `selector` on `contract` and simulated
+    /// to `gas`.
+    pub fn track(
+        &self,
+        tx_hash: &str,
+        contract: &str,
+        selector: &FunctionSelector,
+        gas: Gas,
+    ) -> Result<(), String> {
+        let _guard = self.lock.lock().unwrap();
+        let mut pending = self.pending()?;
+        pending.insert(
+            tx_hash.to_lowercase(),
+            Pending {
+                function: function_key(contract, selector),
+                gas,
+            },
+        );
+        self.store.put(PENDING_KEY, &pending)
+    }
+
+    /// Turns the receipt's tracked tx into a sample if it succeeded, or
+    /// drops it if it reverted or was dropped. Pending receipts and txs that
+    /// were never tracked are ignored.
+    pub fn record(&self, receipt: &Value) -> Result<(), String> {
+        let (Some(tx_hash), Some(status)) =
+            (receipt["txHash"].as_str(), receipt["status"].as_str())
+        else {
+            return Ok(());
+        };
+        if status == "pending" {
+            return Ok(());
+        }
+        let _guard = self.lock.lock().unwrap();
+        let mut pending = self.pending()?;
+        let Some(tx) = pending.remove(&tx_hash.to_lowercase()) else {
+            return Ok(());
+        };
+        if status == "success" {
+            let mut profiles = self.profiles()?;
+            let samples = profiles.entry(tx.function).or_default();
+            samples.push(tx.gas);
+            let excess = samples.len().saturating_sub(self.config.max_samples);
+            samples.drain(..excess);
+            self.store.put(PROFILES_KEY, &profiles)?;
+        }
+        self.store.put(PENDING_KEY, &pending)
+    }
+
+    /// Limits for the next call, or `None` until enough calls succeeded.
+    /// Each dimension is capped at `max`.
+    pub fn suggest(
+        &self,
+        contract: &str,
+        selector: &FunctionSelector,
+        max: Gas,
+    ) -> Result<Option<Gas>, String> {
+        let profiles = self.profiles()?;
+        let samples = match profiles.get(&function_key(contract, selector)) {
+            Some(samples) if samples.len() >= self.config.min_samples.max(1) => samples,
+            _ => return Ok(None),
+        };
+        let limit = |gas: Vec<u64>, max: u64| {
+            let p95 = percentile(gas, 95);
+            let margin = p95.saturating_mul(self.config.margin_percent) / 100;
+            p95.saturating_add(margin).min(max)
+        };
+        Ok(Some(Gas {
+            da_gas: limit(samples.iter().map(|g| g.da_gas).collect(), max.da_gas),
+            l2_gas: limit(samples.iter().map(|g| g.l2_gas).collect(), max.l2_gas),
+        }))
+    }
+
+    /// Successful calls recorded for the function.
+    pub fn samples(&self, contract: &str, selector: &FunctionSelector) -> Result<Vec<Gas>, String> {
+        let mut profiles = self.profiles()?;
+        Ok(profiles
+            .remove(&function_key(contract, selector))
+            .unwrap_or_default())
+    }
+
+    fn profiles(&self) -> Result<HashMap<String, Vec<Gas>>, String> {
+        Ok(self.store.get(PROFILES_KEY)?.unwrap_or_default())
+    }
+
+    fn pending(&self) -> Result<HashMap<String, Pending>, String> {
+        Ok(self.store.get(PENDING_KEY)?.unwrap_or_default())
+    }
+}
+
+fn function_key(contract: &str, selector: &FunctionSelector) -> String {
+    format!("{}:{}", contract.to_lowercase(), selector.0)
+}
+
+/// Nearest-rank percentile of a non-empty list.
+fn percentile(mut values: Vec<u64>, p: usize) -> u64 {
+    values.sort_unstable();
+    let rank = (values.len() * p).div_ceil(100).max(1);
+    values[rank - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const CONTRACT: &str = "0x0C";
+    const MAX: Gas = Gas {
+        da_gas: 1_000_000_000,
+        l2_gas: 1_000_000_000,
+    };
+
+    fn selector() -> FunctionSelector {
+        FunctionSelector("27e740b2".to_string())
+    }
+
+    fn profiler(min_samples: usize) -> GasProfiler {
+        let config = GasProfilerConfig {
+            min_samples,
+            ..Default::default()
+        };
+        GasProfiler::new(config, Arc::new(StateStore::in_memory()))
+    }
+
+    fn succeed(profiler: &GasProfiler, tx_hash: &str, l2_gas: u64) {
+        let gas = Gas {
+            da_gas: 1000,
+            l2_gas,
+        };
+        profiler.track(tx_hash, CONTRACT, &selector(), gas).unwrap();
+        profiler
+            .record(&json!({ "txHash": tx_hash, "status": "success" }))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_percentile() {
+        assert_eq!(percentile(vec![7], 95), 7);
+        assert_eq!(percentile((1..=100).rev().collect(), 95), 95);
+        assert_eq!(percentile(vec![10, 30, 20], 95), 30);
+    }
+
+    #[test]
+    fn test_suggests_p95_plus_margin_once_warm() {
+        let profiler = profiler(3);
+        succeed(&profiler, "0x01", 30_000);
+        succeed(&profiler, "0x02", 32_000);
+        assert_eq!(profiler.suggest("0x0c", &selector(), MAX), Ok(None));
+
+        succeed(&profiler, "0x03", 31_000);
+        assert_eq!(
+            profiler.suggest("0x0c", &selector(), MAX),
+            Ok(Some(Gas {
+                da_gas: 1200,
+                l2_gas: 38_400,
+            }))
+        );
+
+        let tight = Gas {
+            da_gas: 500,
+            l2_gas: 40_000,
+        };
+        assert_eq!(
+            profiler.suggest(CONTRACT, &selector(), tight),
+            Ok(Some(Gas {
+                da_gas: 500,
+                l2_gas: 38_400,
+            }))
+        );
+        let other = FunctionSelector("00000001".to_string());
+        assert_eq!(profiler.suggest(CONTRACT, &other, MAX), Ok(None));
+    }
+
+    #[test]
+    fn test_only_successful_receipts_become_samples() {
+        let profiler = profiler(1);
+        let gas = Gas {
+            da_gas: 1,
+            l2_gas: 1,
+        };
+        profiler.track("0xAA", CONTRACT, &selector(), gas).unwrap();
+        profiler
+            .record(&json!({ "txHash": "0xaa", "status": "pending" }))
+            .unwrap();
+        profiler
+            .record(&json!({ "txHash": "0xaa", "status": "app_logic_reverted" }))
+            .unwrap();
+        // Already forgotten, so a late success receipt adds nothing.
+        profiler
+            .record(&json!({ "txHash": "0xaa", "status": "success" }))
+            .unwrap();
+        assert!(profiler.samples(CONTRACT, &selector()).unwrap().is_empty());
+
+        succeed(&profiler, "0xbb", 5);
+        assert_eq!(profiler.samples(CONTRACT, &selector()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_keeps_only_recent_samples() {
+        let config = GasProfilerConfig {
+            min_samples: 1,
+            max_samples: 2,
+            ..Default::default()
+        };
+        let profiler = GasProfiler::new(config, Arc::new(StateStore::in_memory()));
+        succeed(&profiler, "0x01", 90_000);
+        succeed(&profiler, "0x02", 10_000);
+        succeed(&profiler, "0x03", 10_000);
+
+        let samples = profiler.samples(CONTRACT, &selector()).unwrap();
+        assert!(samples.iter().all(|gas| gas.l2_gas == 10_000));
+        assert_eq!(
+            profiler
+                .suggest(CONTRACT, &selector(), MAX)
+                .unwrap()
+                .unwrap()
+                .l2_gas,
+            12_000
+        );
+    }
+}
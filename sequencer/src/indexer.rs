@@ -0,0 +1,480 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::sleep;
+
+use crate::aztec_rpc_client::AztecRpcClient;
+use crate::block::L2Block;
+use crate::fields::Fr;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS blocks (number INTEGER PRIMARY KEY);
+CREATE TABLE IF NOT EXISTS block_times (number INTEGER PRIMARY KEY, timestamp INTEGER NOT NULL);
+CREATE TABLE IF NOT EXISTS txs (
+    tx_hash TEXT PRIMARY KEY,
+    block INTEGER NOT NULL,
+    fee TEXT NOT NULL,
+    reverted INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS tx_senders (tx_hash TEXT PRIMARY KEY, sender TEXT NOT NULL);
+CREATE TABLE IF NOT EXISTS feed_writes (
+    block INTEGER NOT NULL,
+    tx_hash TEXT NOT NULL,
+    feed_id TEXT NOT NULL,
+    value TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS feed_writes_by_feed ON feed_writes (feed_id, block);
+CREATE TABLE IF NOT EXISTS public_logs (
+    block INTEGER NOT NULL,
+    tx_hash TEXT NOT NULL,
+    contract TEXT NOT NULL,
+    fields TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS public_logs_by_contract ON public_logs (contract, block);
+";
+
+/// What the indexer keeps. Public data writes only carry the siloed leaf
+/// slot, so feeds are tracked by the leaf slot holding their value (what
+/// aztec.js' `computePublicDataTreeLeafSlot` gives for the feed's map slot).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IndexerConfig {
+    /// Contracts whose public logs are kept.
+    pub contracts: Vec<Fr>,
+    /// Feed id to leaf slot.
+    pub feed_slots: HashMap<Fr, Fr>,
+    /// First block to index on an empty database.
+    pub from_block: u64,
+}
+
+impl IndexerConfig {
+    /// Reads `INDEXER_CONTRACTS` (comma-separated addresses),
+    /// `INDEXER_FEED_SLOTS` (comma-separated `feed_id:leaf_slot` pairs) and
+    /// `INDEXER_FROM_BLOCK`.
+    pub fn from_env() -> Result<Self, String> {
+        let list = |name: &str| env::var(name).unwrap_or_default();
+        let field = |s: &str| Fr::try_from(s.trim());
+
+        let contracts = list("INDEXER_CONTRACTS")
+            .split(',')
+            .filter(|s| !s.trim().is_empty())
+            .map(field)
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("Invalid INDEXER_CONTRACTS: {}", e))?;
+        let feed_slots = list("INDEXER_FEED_SLOTS")
+            .split(',')
+            .filter(|s| !s.trim().is_empty())
+            .map(|pair| {
+                let (feed_id, slot) = pair
+                    .split_once(':')
+                    .ok_or_else(|| format!("expected feed_id:leaf_slot, got {}", pair))?;
+                Ok((field(feed_id)?, field(slot)?))
+            })
+            .collect::<Result<_, String>>()
+            .map_err(|e| format!("Invalid INDEXER_FEED_SLOTS: {}", e))?;
+        let from_block = match env::var("INDEXER_FROM_BLOCK") {
+            Ok(n) => n
+                .parse()
+                .map_err(|_| format!("Invalid INDEXER_FROM_BLOCK: {}", n))?,
+            Err(_) => 1,
+        };
+
+        Ok(IndexerConfig {
+            contracts,
+            feed_slots,
+            from_block,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedWrite {
+    pub block: u64,
+    pub tx_hash: String,
+    pub value: Fr,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedTx {
+    pub tx_hash: String,
+    pub block: u64,
+    pub fee: Fr,
+    pub reverted: bool,
+}
+
+/// Follows new blocks and keeps the activity of the configured contracts in
+/// SQLite: tracked feed writes, public logs, and the txs that made them.
+///
+/// Tx effects don't name the sender, so `txs_by_sender` only knows the
+/// senders passed to `record_sender` (our own sends, typically).
+pub struct Indexer {
+    node: AztecRpcClient,
+    config: IndexerConfig,
+    interval: Duration,
+    db: Mutex<Connection>,
+}
+
+impl Indexer {
+    /// Opens (or creates) the database at `path`.
+    pub fn open(
+        node: AztecRpcClient,
+        config: IndexerConfig,
+        interval: Duration,
+        path: &str,
+    ) -> Result<Self, String> {
+        let db = Connection::open(path).map_err(|e| format!("Cannot open {}: {}", path, e))?;
+        Self::with_connection(node, config, interval, db)
+    }
+
+    pub fn in_memory(
+        node: AztecRpcClient,
+        config: IndexerConfig,
+        interval: Duration,
+    ) -> Result<Self, String> {
+        let db = Connection::open_in_memory().map_err(|e| e.to_string())?;
+        Self::with_connection(node, config, interval, db)
+    }
+
+    fn with_connection(
+        node: AztecRpcClient,
+        config: IndexerConfig,
+        interval: Duration,
+        db: Connection,
+    ) -> Result<Self, String> {
+        db.execute_batch(SCHEMA).map_err(|e| e.to_string())?;
+        Ok(Indexer {
+            node,
+            config,
+            interval,
+            db: Mutex::new(db),
+        })
+    }
+
+    pub fn last_indexed_block(&self) -> Result<Option<u64>, String> {
+        self.db
+            .lock()
+            .unwrap()
+            .query_row("SELECT MAX(number) FROM blocks", [], |row| row.get(0))
+            .map_err(|e| e.to_string())
+    }
+
+    /// Indexes every block the node has that isn't indexed yet; returns how
+    /// many were added.
+    pub async fn poll(&self) -> Result<usize, Box<dyn std::error::Error>> {
+        let latest = self.node.get_block_number().await?;
+        let mut next = match self.last_indexed_block()? {
+            Some(last) => last + 1,
+            None => self.config.from_block,
+        };
+        let mut indexed = 0;
+        while next <= latest {
+            let Some(block) = self.node.get_block(next).await? else {
+                break;
+            };
+            self.index_block(&block)?;
+            next += 1;
+            indexed += 1;
+        }
+        Ok(indexed)
+    }
+
+    pub async fn run(self: Arc<Self>) {
+        loop {
+            match self.poll().await {
+                Ok(0) => {}
+                Ok(n) => println!("Indexer: indexed {} block(s)", n),
+                Err(e) => println!("Indexer poll failed: {}", e),
+            }
+            sleep(self.interval).await;
+        }
+    }
+
+    /// Stores the block's activity for the configured contracts. Indexing a
+    /// block twice is a no-op.
+    pub fn index_block(&self, block: &L2Block) -> Result<(), String> {
+        let number = block
+            .number()
+            .ok_or_else(|| "Block number does not fit in u64".to_string())?;
+        let feeds_by_slot: HashMap<&Fr, &Fr> = self
+            .config
+            .feed_slots
+            .iter()
+            .map(|(feed_id, slot)| (slot, feed_id))
+            .collect();
+
+        let mut db = self.db.lock().unwrap();
+        let tx = db.transaction().map_err(|e| e.to_string())?;
+        let inserted = tx
+            .execute(
+                "INSERT OR IGNORE INTO blocks (number) VALUES (?1)",
+                params![number],
+            )
+            .map_err(|e| e.to_string())?;
+        if inserted == 0 {
+            return Ok(());
+        }
+        if let Some(timestamp) = block.header.global_variables.timestamp.to_u64() {
+            tx.execute(
+                "INSERT INTO block_times (number, timestamp) VALUES (?1, ?2)",
+                params![number, timestamp],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+
+        for effect in block.tx_effects() {
+            let tx_hash = effect.tx_hash.to_lowercase();
+            let mut touched = false;
+            for write in &effect.public_data_writes {
+                let Some(feed_id) = feeds_by_slot.get(&write.leaf_slot) else {
+                    continue;
+                };
+                tx.execute(
+                    "INSERT INTO feed_writes (block, tx_hash, feed_id, value) VALUES (?1, ?2, ?3, ?4)",
+                    params![number, tx_hash, feed_id.to_hex(), write.value.to_hex()],
+                )
+                .map_err(|e| e.to_string())?;
+                touched = true;
+            }
+            for log in &effect.public_logs {
+                if !self.config.contracts.contains(&log.contract_address) {
+                    continue;
+                }
+                let fields = serde_json::to_string(&log.fields).map_err(|e| e.to_string())?;
+                tx.execute(
+                    "INSERT INTO public_logs (block, tx_hash, contract, fields) VALUES (?1, ?2, ?3, ?4)",
+                    params![number, tx_hash, log.contract_address.to_hex(), fields],
+                )
+                .map_err(|e| e.to_string())?;
+                touched = true;
+            }
+            if touched {
+                tx.execute(
+                    "INSERT OR REPLACE INTO txs (tx_hash, block, fee, reverted) VALUES (?1, ?2, ?3, ?4)",
+                    params![
+                        tx_hash,
+                        number,
+                        effect.transaction_fee.to_hex(),
+                        effect.reverted()
+                    ],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    /// Remembers who sent `tx_hash`, before or after it is indexed.
+    pub fn record_sender(&self, tx_hash: &str, sender: &str) -> Result<(), String> {
+        self.db
+            .lock()
+            .unwrap()
+            .execute(
+                "INSERT OR REPLACE INTO tx_senders (tx_hash, sender) VALUES (?1, ?2)",
+                params![tx_hash.to_lowercase(), sender.to_lowercase()],
+            )
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Every indexed write to the feed, oldest first.
+    pub fn history_of(&self, feed_id: &Fr) -> Result<Vec<FeedWrite>, String> {
+        let db = self.db.lock().unwrap();
+        let mut statement = db
+            .prepare(
+                "SELECT block, tx_hash, value FROM feed_writes WHERE feed_id = ?1 ORDER BY block, rowid",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = statement
+            .query_map(params![feed_id.to_hex()], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get::<_, String>(2)?))
+            })
+            .map_err(|e| e.to_string())?;
+        rows.map(|row| {
+            let (block, tx_hash, value) = row.map_err(|e| e.to_string())?;
+            Ok(FeedWrite {
+                block,
+                tx_hash,
+                value: Fr::try_from(value.as_str())?,
+            })
+        })
+        .collect()
+    }
+
+    /// Indexed txs sent by `sender`, oldest first.
+    pub fn txs_by_sender(&self, sender: &str) -> Result<Vec<IndexedTx>, String> {
+        let db = self.db.lock().unwrap();
+        let mut statement = db
+            .prepare(
+                "SELECT txs.tx_hash, block, fee, reverted FROM txs
+                 JOIN tx_senders ON tx_senders.tx_hash = txs.tx_hash
+                 WHERE sender = ?1 ORDER BY block",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = statement
+            .query_map(params![sender.to_lowercase()], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get(3)?,
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+        rows.map(|row| {
+            let (tx_hash, block, fee, reverted) = row.map_err(|e| e.to_string())?;
+            Ok(IndexedTx {
+                tx_hash,
+                block,
+                fee: Fr::try_from(fee.as_str())?,
+                reverted,
+            })
+        })
+        .collect()
+    }
+
+    /// The fields of `contract`'s indexed public logs, oldest first.
+    pub fn public_logs_of(&self, contract: &Fr) -> Result<Vec<(u64, Vec<Fr>)>, String> {
+        let db = self.db.lock().unwrap();
+        let mut statement = db
+            .prepare(
+                "SELECT block, fields FROM public_logs WHERE contract = ?1 ORDER BY block, rowid",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = statement
+            .query_map(params![contract.to_hex()], |row| {
+                Ok((row.get(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(|e| e.to_string())?;
+        rows.map(|row| {
+            let (block, fields) = row.map_err(|e| e.to_string())?;
+            Ok((
+                block,
+                serde_json::from_str(&fields).map_err(|e| e.to_string())?,
+            ))
+        })
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::fixtures::{block_json, tx_effect_json};
+    use crate::testing::MockPxe;
+    use serde_json::{json, Value};
+
+    fn effect(tx_hash: &str, writes: Value, logs: Value) -> Value {
+        let mut effect = tx_effect_json();
+        effect["txHash"] = json!(tx_hash);
+        effect["publicDataWrites"] = writes;
+        effect["publicLogs"] = logs;
+        effect
+    }
+
+    fn indexer(node: AztecRpcClient) -> Indexer {
+        let config = IndexerConfig {
+            contracts: vec![Fr::from(0xc0u8)],
+            feed_slots: HashMap::from([(Fr::from(1u8), Fr::from(0x51u8))]),
+            from_block: 1,
+        };
+        Indexer::in_memory(node, config, Duration::from_millis(10)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_follows_blocks_and_indexes_feed_activity() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond("node_getBlockNumber", json!(2));
+        mock.respond(
+            "node_getBlock",
+            block_json(
+                1,
+                vec![
+                    effect(
+                        "0xAA",
+                        json!([{ "leafSlot": "0x51", "value": "0x2a" }]),
+                        json!([{ "contractAddress": "0xc0", "fields": ["0x01"] }]),
+                    ),
+                    // Touches neither a tracked slot nor a tracked contract.
+                    effect(
+                        "0xbb",
+                        json!([{ "leafSlot": "0x52", "value": "0x01" }]),
+                        json!([{ "contractAddress": "0xc1", "fields": [] }]),
+                    ),
+                ],
+            ),
+        );
+        mock.respond(
+            "node_getBlock",
+            block_json(
+                2,
+                vec![effect(
+                    "0xcc",
+                    json!([{ "leafSlot": "0x51", "value": "0x2b" }]),
+                    json!([]),
+                )],
+            ),
+        );
+        let indexer = indexer(AztecRpcClient::new(mock.url(), Some("node".to_string())));
+        indexer.record_sender("0xaa", "0xSENDER").unwrap();
+
+        assert_eq!(indexer.poll().await.unwrap(), 2);
+        assert_eq!(indexer.last_indexed_block().unwrap(), Some(2));
+        assert_eq!(indexer.poll().await.unwrap(), 0);
+
+        let history = indexer.history_of(&Fr::from(1u8)).unwrap();
+        assert_eq!(
+            history,
+            vec![
+                FeedWrite {
+                    block: 1,
+                    tx_hash: "0xaa".to_string(),
+                    value: Fr::from(42u8),
+                },
+                FeedWrite {
+                    block: 2,
+                    tx_hash: "0xcc".to_string(),
+                    value: Fr::from(43u8),
+                },
+            ]
+        );
+        assert_eq!(
+            indexer.txs_by_sender("0xsender").unwrap(),
+            vec![IndexedTx {
+                tx_hash: "0xaa".to_string(),
+                block: 1,
+                fee: Fr::from(0x0c8du64),
+                reverted: false,
+            }]
+        );
+        assert_eq!(
+            indexer.public_logs_of(&Fr::from(0xc0u8)).unwrap(),
+            vec![(1, vec![Fr::from(1u8)])]
+        );
+        assert!(indexer
+            .public_logs_of(&Fr::from(0xc1u8))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_indexing_a_block_twice_is_a_no_op() {
+        let indexer = indexer(AztecRpcClient::new("http://127.0.0.1:1", None));
+        let block: L2Block = serde_json::from_value(block_json(
+            3,
+            vec![effect(
+                "0xaa",
+                json!([{ "leafSlot": "0x51", "value": "0x2a" }]),
+                json!([]),
+            )],
+        ))
+        .unwrap();
+
+        indexer.index_block(&block).unwrap();
+        indexer.index_block(&block).unwrap();
+        assert_eq!(indexer.history_of(&Fr::from(1u8)).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,326 @@
+use serde::Serialize;
+use std::fmt::Write;
+
+use crate::encoder::{AbiType, ContractArtifact, FunctionAbi, FunctionSelector};
+
+/// What a contract exposes, read off its artifact: the CLI inspector prints
+/// it and the bridge serves it as `GET /contracts/{address}/interface`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ArtifactReport {
+    pub name: String,
+    pub functions: Vec<FunctionReport>,
+    pub storage: Vec<StorageReport>,
+    pub notes: Vec<NoteReport>,
+    pub events: Vec<EventReport>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FunctionReport {
+    pub name: String,
+    /// `private`, `public` or `utility`.
+    pub function_type: String,
+    pub is_static: bool,
+    pub is_initializer: bool,
+    pub selector: String,
+    pub parameters: Vec<ParameterReport>,
+    pub return_types: Vec<String>,
+    pub flattened_size: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParameterReport {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub abi_type: String,
+    pub flattened_size: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StorageReport {
+    pub name: String,
+    pub slot: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub typ: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NoteReport {
+    pub name: String,
+    pub id: String,
+    #[serde(rename = "type")]
+    pub typ: String,
+    pub fields: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EventReport {
+    pub name: String,
+    pub fields: Vec<ParameterReport>,
+}
+
+impl FunctionReport {
+    fn from_abi(f: &FunctionAbi) -> Self {
+        let parameters: Vec<ParameterReport> = f
+            .parameters
+            .iter()
+            .map(|p| ParameterReport::new(&p.name, &p.abi_type))
+            .collect();
+        FunctionReport {
+            name: f.name.clone(),
+            function_type: f.function_type.clone(),
+            is_static: f.isStatic,
+            is_initializer: f.isInitializer,
+            selector: format!(
+                "0x{}",
+                FunctionSelector::from_name_and_parameters(&f.name, &f.parameters).0
+            ),
+            return_types: f.return_types.iter().map(AbiType::to_string).collect(),
+            flattened_size: parameters.iter().map(|p| p.flattened_size).sum(),
+            parameters,
+        }
+    }
+}
+
+impl ParameterReport {
+    fn new(name: &str, abi_type: &AbiType) -> Self {
+        ParameterReport {
+            name: name.to_string(),
+            abi_type: abi_type.to_string(),
+            flattened_size: abi_type.flattened_size(),
+        }
+    }
+}
+
+impl ArtifactReport {
+    /// Covers `functions` and then whichever `nonDispatchPublicFunctions`
+    /// they don't already list.
+    pub fn from_artifact(artifact: &ContractArtifact) -> Self {
+        let mut functions: Vec<FunctionReport> = artifact
+            .functions
+            .iter()
+            .map(|f| FunctionReport::from_abi(&f.to_abi()))
+            .collect();
+        for f in &artifact.non_dispatch_public_functions {
+            if !functions.iter().any(|listed| listed.name == f.name) {
+                functions.push(FunctionReport::from_abi(f));
+            }
+        }
+
+        let mut storage: Vec<StorageReport> = artifact
+            .storage_layout
+            .iter()
+            .map(|(name, layout)| StorageReport {
+                name: name.clone(),
+                slot: layout.slot.clone(),
+                typ: layout.typ.clone(),
+            })
+            .collect();
+        storage.sort_by(|a, b| a.slot.cmp(&b.slot).then_with(|| a.name.cmp(&b.name)));
+
+        let mut notes: Vec<NoteReport> = artifact
+            .notes
+            .iter()
+            .map(|(name, note)| {
+                let mut fields: Vec<_> = note.fields.iter().collect();
+                fields.sort_by_key(|f| f.index);
+                NoteReport {
+                    name: name.clone(),
+                    id: note.id.clone(),
+                    typ: note.typ.clone(),
+                    fields: fields
+                        .into_iter()
+                        .map(|f| {
+                            if f.nullable {
+                                format!("{}?", f.name)
+                            } else {
+                                f.name.clone()
+                            }
+                        })
+                        .collect(),
+                }
+            })
+            .collect();
+        notes.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let events = artifact
+            .events()
+            .iter()
+            .filter_map(|event| match event {
+                AbiType::Struct { path, fields } => Some(EventReport {
+                    name: path.clone(),
+                    fields: fields
+                        .iter()
+                        .map(|f| ParameterReport::new(&f.name, &f.field_type))
+                        .collect(),
+                }),
+                _ => None,
+            })
+            .collect();
+
+        ArtifactReport {
+            name: artifact.name.clone(),
+            functions,
+            storage,
+            notes,
+            events,
+        }
+    }
+
+    pub fn to_table(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "Contract {}", self.name);
+
+        let _ = writeln!(out, "\nFunctions");
+        let width = self
+            .functions
+            .iter()
+            .map(|f| f.name.len())
+            .max()
+            .unwrap_or(0)
+            .max(4);
+        let _ = writeln!(
+            out,
+            "  {:<10}  {:<width$}  {:<9}  {:>5}  PARAMETERS",
+            "SELECTOR", "NAME", "TYPE", "SIZE"
+        );
+        for f in &self.functions {
+            let mut params = f
+                .parameters
+                .iter()
+                .map(|p| format!("{}: {}", p.name, p.abi_type))
+                .collect::<Vec<_>>()
+                .join(", ");
+            if !f.return_types.is_empty() {
+                let _ = write!(params, " -> {}", f.return_types.join(", "));
+            }
+            let _ = writeln!(
+                out,
+                "  {:<10}  {:<width$}  {:<9}  {:>5}  {}",
+                f.selector, f.name, f.function_type, f.flattened_size, params
+            );
+        }
+
+        if !self.storage.is_empty() {
+            let _ = writeln!(out, "\nStorage");
+            for s in &self.storage {
+                match &s.typ {
+                    Some(typ) => {
+                        let _ = writeln!(out, "  {:<6}  {}: {}", s.slot, s.name, typ);
+                    }
+                    None => {
+                        let _ = writeln!(out, "  {:<6}  {}", s.slot, s.name);
+                    }
+                }
+            }
+        }
+
+        if !self.notes.is_empty() {
+            let _ = writeln!(out, "\nNotes");
+            for n in &self.notes {
+                let _ = writeln!(
+                    out,
+                    "  {}  {} ({})  [{}]",
+                    n.id,
+                    n.name,
+                    n.typ,
+                    n.fields.join(", ")
+                );
+            }
+        }
+
+        if !self.events.is_empty() {
+            let _ = writeln!(out, "\nEvents");
+            for e in &self.events {
+                let fields = e
+                    .fields
+                    .iter()
+                    .map(|f| format!("{}: {}", f.name, f.abi_type))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let _ = writeln!(out, "  {}  [{}]", e.name, fields);
+            }
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn artifact() -> ContractArtifact {
+        serde_json::from_value(json!({
+            "name": "Main",
+            "functions": [{
+                "name": "set_feeds",
+                "parameters": [
+                    { "name": "ids", "type": { "kind": "array", "length": 4, "type": { "kind": "field" } } },
+                    { "name": "price", "type": { "kind": "struct", "path": "Price", "fields": [
+                        { "name": "value", "type": { "kind": "integer", "sign": "unsigned", "width": 128 } },
+                        { "name": "live", "type": { "kind": "boolean" } },
+                    ] } },
+                ],
+                "bytecode": "",
+                "debugSymbols": "",
+                "functionType": "public",
+            }, {
+                "name": "get_just_field",
+                "parameters": [],
+                "returnTypes": [{ "kind": "field" }],
+                "isStatic": true,
+                "bytecode": "",
+                "debugSymbols": "",
+                "functionType": "public",
+            }],
+            "nonDispatchPublicFunctions": [],
+            "storageLayout": {
+                "just_field": { "slot": "0x02", "type": "PublicMutable<Field>" },
+                "field_in_map": { "slot": "0x01" },
+            },
+            "notes": { "ValueNote": { "id": "0x01", "type": "ValueNote", "fields": [
+                { "name": "owner", "index": 1, "nullable": false },
+                { "name": "value", "index": 0, "nullable": false },
+            ] } },
+            "fileMap": {},
+            "outputs": { "structs": { "events": [{ "kind": "struct", "path": "Main::PriceUpdated", "fields": [
+                { "name": "feed_id", "type": { "kind": "field" } },
+                { "name": "price", "type": { "kind": "integer", "sign": "unsigned", "width": 128 } },
+            ] }] } },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_report_lists_selectors_sizes_and_layout() {
+        let report = ArtifactReport::from_artifact(&artifact());
+        let function = &report.functions[0];
+
+        assert_eq!(function.selector.len(), 10);
+        assert_eq!(function.parameters[0].abi_type, "field[4]");
+        assert_eq!(function.flattened_size, 6);
+        assert_eq!(report.storage[0].name, "field_in_map");
+        assert_eq!(report.notes[0].fields, vec!["value", "owner"]);
+
+        let getter = &report.functions[1];
+        assert!(getter.is_static);
+        assert_eq!(getter.return_types, vec!["field"]);
+        assert_eq!(
+            report.storage[1].typ.as_deref(),
+            Some("PublicMutable<Field>")
+        );
+        assert_eq!(report.events[0].name, "Main::PriceUpdated");
+        assert_eq!(report.events[0].fields[1].abi_type, "u128");
+    }
+
+    #[test]
+    fn test_table_mentions_every_function() {
+        let table = ArtifactReport::from_artifact(&artifact()).to_table();
+        assert!(table.contains("set_feeds"));
+        assert!(table.contains("ids: field[4]"));
+        assert!(table.contains("0x02    just_field: PublicMutable<Field>"));
+        assert!(table.contains("-> field"));
+        assert!(table.contains("Main::PriceUpdated  [feed_id: field, price: u128]"));
+    }
+}
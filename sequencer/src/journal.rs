@@ -0,0 +1,361 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::AztecError;
+use crate::pxe_api::{decode, PxeApi};
+use crate::state::StateStore;
+
+const PREFIX: &str = "journal/";
+
+/// A proven tx and what it was sent to do.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    pub contract: String,
+    pub function: String,
+    pub from: String,
+    /// The tx as `sendTx` takes it.
+    pub tx: Value,
+    /// Set once `sendTx` answered.
+    pub tx_hash: Option<String>,
+    /// Set by `TxJournal::cancel`: the tx is no longer wanted and is never
+    /// sent again, though it may still be mined if the PXE already has it.
+    #[serde(default)]
+    pub cancelled: bool,
+}
+
+/// What `TxJournal::recover` did with one entry.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recovery {
+    /// Never acknowledged, so sent again; the PXE took it as `tx_hash`.
+    Resubmitted { id: String, tx_hash: String },
+    /// Sent and still pending; kept until its receipt settles.
+    Pending { id: String, tx_hash: String },
+    /// Sent and mined, reverted or otherwise final; forgotten.
+    Settled {
+        id: String,
+        tx_hash: String,
+        status: String,
+    },
+    /// The PXE refused it again, typically because it was already mined
+    /// and its nullifiers exist; forgotten.
+    Refused { id: String, error: String },
+    /// Cancelled, and never sent or since dropped, so not sent again;
+    /// forgotten.
+    Cancelled { id: String },
+}
+
+impl fmt::Display for Recovery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Recovery::Resubmitted { id, tx_hash } => {
+                write!(f, "Journaled tx {} resubmitted as {}", id, tx_hash)
+            }
+            Recovery::Pending { id, tx_hash } => {
+                write!(f, "Journaled tx {} ({}) is still pending", id, tx_hash)
+            }
+            Recovery::Settled {
+                id,
+                tx_hash,
+                status,
+            } => write!(f, "Journaled tx {} ({}) settled: {}", id, tx_hash, status),
+            Recovery::Refused { id, error } => {
+                write!(f, "Journaled tx {} refused on resubmission: {}", id, error)
+            }
+            Recovery::Cancelled { id } => write!(f, "Journaled tx {} cancelled", id),
+        }
+    }
+}
+
+/// Proven txs kept in the `StateStore` from just before `sendTx` until
+/// their receipt is final, so a crash between proving and sending doesn't
+/// lose the proof. `recover` runs at startup to finish what was in flight.
+#[derive(Debug)]
+pub struct TxJournal {
+    store: Arc<StateStore>,
+}
+
+impl TxJournal {
+    pub fn new(store: Arc<StateStore>) -> Self {
+        TxJournal { store }
+    }
+
+    /// Journals `entry` and returns its id; ids sort in journaling order.
+    pub fn record(&self, entry: &JournalEntry) -> Result<String, String> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let mut id = format!("{:024}", nanos);
+        while self.get(&id)?.is_some() {
+            id.push('0');
+        }
+        self.store.put(&key(&id), entry)?;
+        Ok(id)
+    }
+
+    /// Notes that `sendTx` accepted entry `id` as `tx_hash`.
+    pub fn sent(&self, id: &str, tx_hash: &str) -> Result<(), String> {
+        let Some(mut entry) = self.get(id)? else {
+            return Ok(());
+        };
+        entry.tx_hash = Some(tx_hash.to_string());
+        self.store.put(&key(id), &entry)
+    }
+
+    /// Forgets entry `id`, e.g. when the PXE refused it outright.
+    pub fn discard(&self, id: &str) -> Result<(), String> {
+        self.store.remove(&key(id))
+    }
+
+    pub fn get(&self, id: &str) -> Result<Option<JournalEntry>, String> {
+        self.store.get(&key(id))
+    }
+
+    /// Every entry with its id, oldest first.
+    pub fn entries(&self) -> Result<Vec<(String, JournalEntry)>, String> {
+        self.store
+            .keys(PREFIX)
+            .into_iter()
+            .filter_map(|key| {
+                let id = key[PREFIX.len()..].to_string();
+                self.get(&id).transpose().map(|entry| Ok((id, entry?)))
+            })
+            .collect()
+    }
+
+    /// Entries sent, or about to be, from `address` and not cancelled.
+    pub fn pending(&self, address: &str) -> Result<Vec<(String, JournalEntry)>, String> {
+        let mut entries = self.entries()?;
+        entries.retain(|(_, entry)| !entry.cancelled && entry.from.eq_ignore_ascii_case(address));
+        Ok(entries)
+    }
+
+    /// Marks the entry with id or tx hash `tx` cancelled and returns its id.
+    /// Aztec has no nonces to replace a tx by, so a cancelled tx the PXE
+    /// already holds can still be mined; it is only never resubmitted, and
+    /// forgotten once its receipt is final or it is dropped.
+    pub fn cancel(&self, tx: &str) -> Result<String, String> {
+        let (id, mut entry) = self
+            .entries()?
+            .into_iter()
+            .find(|(id, entry)| {
+                id == tx
+                    || entry
+                        .tx_hash
+                        .as_ref()
+                        .is_some_and(|hash| hash.eq_ignore_ascii_case(tx))
+            })
+            .ok_or_else(|| format!("No journaled tx {}", tx))?;
+        entry.cancelled = true;
+        self.store.put(&key(&id), &entry)?;
+        Ok(id)
+    }
+
+    /// Forgets the receipt's tx once its status is final, or once it is
+    /// dropped if it was cancelled.
+    pub fn settle(&self, receipt: &Value) -> Result<(), String> {
+        let (Some(tx_hash), Some(status)) =
+            (receipt["txHash"].as_str(), receipt["status"].as_str())
+        else {
+            return Ok(());
+        };
+        if status == "pending" {
+            return Ok(());
+        }
+        for (id, entry) in self.entries()? {
+            let matches = entry
+                .tx_hash
+                .is_some_and(|hash| hash.eq_ignore_ascii_case(tx_hash));
+            if matches && (status != "dropped" || entry.cancelled) {
+                self.discard(&id)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Finishes what a previous run left in flight. Entries `sendTx` never
+    /// acknowledged, and those the PXE has since dropped, are sent again
+    /// unless cancelled; the rest are checked against their receipts.
+    pub async fn recover<P: PxeApi + ?Sized>(&self, pxe: &P) -> Result<Vec<Recovery>, AztecError> {
+        let mut recovered = vec![];
+        for (id, entry) in self.entries().map_err(AztecError::State)? {
+            if let Some(tx_hash) = entry.tx_hash.clone() {
+                let receipt = pxe.get_tx_receipt(&tx_hash).await?;
+                let status = receipt["status"].as_str().unwrap_or_default().to_string();
+                match status.as_str() {
+                    "pending" => {
+                        recovered.push(Recovery::Pending { id, tx_hash });
+                        continue;
+                    }
+                    "dropped" => {}
+                    _ => {
+                        self.discard(&id).map_err(AztecError::State)?;
+                        recovered.push(Recovery::Settled {
+                            id,
+                            tx_hash,
+                            status,
+                        });
+                        continue;
+                    }
+                }
+            }
+            if entry.cancelled {
+                self.discard(&id).map_err(AztecError::State)?;
+                recovered.push(Recovery::Cancelled { id });
+                continue;
+            }
+            match pxe.call("sendTx", vec![entry.tx.clone()]).await {
+                Ok(result) => {
+                    let tx_hash: String = decode(result)?;
+                    self.sent(&id, &tx_hash).map_err(AztecError::State)?;
+                    recovered.push(Recovery::Resubmitted { id, tx_hash });
+                }
+                Err(AztecError::Rpc { error, .. }) => {
+                    self.discard(&id).map_err(AztecError::State)?;
+                    let error = error["message"]
+                        .as_str()
+                        .map_or_else(|| error.to_string(), str::to_string);
+                    recovered.push(Recovery::Refused { id, error });
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(recovered)
+    }
+}
+
+fn key(id: &str) -> String {
+    format!("{}{}", PREFIX, id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aztec_rpc_client::AztecRpcClient;
+    use crate::testing::MockPxe;
+    use serde_json::json;
+
+    fn entry(tx: u8, tx_hash: Option<&str>) -> JournalEntry {
+        JournalEntry {
+            contract: "0x0a".to_string(),
+            function: "set_just_field".to_string(),
+            from: "0x0b".to_string(),
+            tx: json!({ "data": tx }),
+            tx_hash: tx_hash.map(str::to_string),
+            cancelled: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recover_resubmits_and_reconciles() {
+        let journal = TxJournal::new(Arc::new(StateStore::in_memory()));
+        let unsent = journal.record(&entry(1, None)).unwrap();
+        let pending = journal.record(&entry(2, Some("0xaa"))).unwrap();
+        let mined = journal.record(&entry(3, Some("0xbb"))).unwrap();
+        let dropped = journal.record(&entry(4, Some("0xcc"))).unwrap();
+        assert!(unsent < pending && pending < mined);
+
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond("pxe_getTxReceipt", json!({ "status": "pending" }));
+        mock.respond("pxe_getTxReceipt", json!({ "status": "success" }));
+        mock.respond("pxe_getTxReceipt", json!({ "status": "dropped" }));
+        mock.respond("pxe_sendTx", json!("0x01"));
+        mock.respond_with_envelope(
+            "pxe_sendTx",
+            json!({ "jsonrpc": "2.0", "id": 1, "error": { "message": "Existing nullifier" } }),
+        );
+        let pxe = AztecRpcClient::new(mock.url(), Some("pxe".to_string()));
+
+        let recovered = journal.recover(&pxe).await.unwrap();
+        assert_eq!(
+            recovered,
+            [
+                Recovery::Resubmitted {
+                    id: unsent.clone(),
+                    tx_hash: "0x01".to_string()
+                },
+                Recovery::Pending {
+                    id: pending.clone(),
+                    tx_hash: "0xaa".to_string()
+                },
+                Recovery::Settled {
+                    id: mined,
+                    tx_hash: "0xbb".to_string(),
+                    status: "success".to_string()
+                },
+                Recovery::Refused {
+                    id: dropped,
+                    error: "Existing nullifier".to_string()
+                },
+            ]
+        );
+        let sends: Vec<_> = mock
+            .requests()
+            .into_iter()
+            .filter(|r| r["method"] == "pxe_sendTx")
+            .map(|r| r["params"][0]["data"].clone())
+            .collect();
+        assert_eq!(sends, [json!(1), json!(4)]);
+
+        let ids: Vec<_> = journal
+            .entries()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.0)
+            .collect();
+        assert_eq!(ids, [unsent.clone(), pending]);
+        journal
+            .settle(&json!({ "txHash": "0x01", "status": "success" }))
+            .unwrap();
+        assert_eq!(journal.get(&unsent).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_txs_are_never_resubmitted() {
+        let journal = TxJournal::new(Arc::new(StateStore::in_memory()));
+        let unsent = journal.record(&entry(1, None)).unwrap();
+        let dropped = journal.record(&entry(2, Some("0xAA"))).unwrap();
+        let kept = journal.record(&entry(3, Some("0xbb"))).unwrap();
+        assert_eq!(journal.pending("0x0B").unwrap().len(), 3);
+
+        assert_eq!(journal.cancel(&unsent).unwrap(), unsent);
+        assert_eq!(journal.cancel("0xaa").unwrap(), dropped);
+        assert!(journal.cancel("0xcc").is_err());
+        let pending: Vec<_> = journal
+            .pending("0x0b")
+            .unwrap()
+            .into_iter()
+            .map(|e| e.0)
+            .collect();
+        assert_eq!(pending, [kept.as_str()]);
+
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond("pxe_getTxReceipt", json!({ "status": "dropped" }));
+        mock.respond("pxe_getTxReceipt", json!({ "status": "pending" }));
+        let pxe = AztecRpcClient::new(mock.url(), Some("pxe".to_string()));
+        let recovered = journal.recover(&pxe).await.unwrap();
+        assert_eq!(
+            recovered,
+            [
+                Recovery::Cancelled { id: unsent },
+                Recovery::Cancelled { id: dropped },
+                Recovery::Pending {
+                    id: kept.clone(),
+                    tx_hash: "0xbb".to_string()
+                },
+            ]
+        );
+        assert!(mock.requests().iter().all(|r| r["method"] != "pxe_sendTx"));
+
+        // A cancelled tx dropped while running is forgotten at once.
+        journal.cancel("0xbb").unwrap();
+        journal
+            .settle(&json!({ "txHash": "0xbb", "status": "dropped" }))
+            .unwrap();
+        assert_eq!(journal.get(&kept).unwrap(), None);
+    }
+}
@@ -0,0 +1,477 @@
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::fields::Fr;
+use crate::secret::{Secret, Zeroizing};
+use crate::signing::SchnorrKeyPair;
+use crate::wallet::{AccountKind, EcdsaAccountWallet, WalletConfig};
+
+const VERSION: u32 = 2;
+
+/// Argon2id costs for new keys. The default is OWASP's recommendation:
+/// 19 MiB, two passes, one lane. Costs above `KdfParams::MAX` are refused,
+/// so a crafted key file cannot make unlocking take unbounded memory or time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub passes: u32,
+    pub lanes: u32,
+}
+
+impl KdfParams {
+    pub const MAX: KdfParams = KdfParams {
+        memory_kib: 1 << 20,
+        passes: 16,
+        lanes: 16,
+    };
+
+    fn check(&self) -> Result<(), String> {
+        let max = Self::MAX;
+        if self.memory_kib > max.memory_kib || self.passes > max.passes || self.lanes > max.lanes {
+            return Err(format!(
+                "Argon2 parameters exceed the limit of {} KiB, {} passes and {} lanes",
+                max.memory_kib, max.passes, max.lanes
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams {
+            memory_kib: Params::DEFAULT_M_COST,
+            passes: Params::DEFAULT_T_COST,
+            lanes: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+/// A key as it sits on disk. Everything but `ciphertext` is public; the
+/// cipher also authenticates the kind and address, so they cannot be
+/// swapped undetected.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct KeyFile {
+    version: u32,
+    name: String,
+    kind: AccountKind,
+    address: String,
+    kdf: KdfParams,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+// Read ahead of the rest, so files of other versions get a clear error.
+#[derive(Deserialize)]
+struct FileVersion {
+    version: u32,
+}
+
+/// What `list` shows about a key, without unlocking it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KeyInfo {
+    pub name: String,
+    pub kind: AccountKind,
+    pub address: String,
+}
+
+/// Account secret keys in `<dir>/<name>.json`, encrypted under a passphrase:
+/// Argon2id derives an AES-256-GCM key, with the kind and address as
+/// associated data.
+#[derive(Debug, Clone)]
+pub struct Keystore {
+    dir: PathBuf,
+    kdf: KdfParams,
+}
+
+impl Keystore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Keystore {
+            dir: dir.into(),
+            kdf: KdfParams::default(),
+        }
+    }
+
+    /// `KEYSTORE_DIR`, or `keystore` in the working directory.
+    pub fn from_env() -> Self {
+        Self::new(env::var("KEYSTORE_DIR").unwrap_or_else(|_| "keystore".to_string()))
+    }
+
+    /// Argon2id costs for keys written from now on.
+    pub fn with_kdf(mut self, kdf: KdfParams) -> Self {
+        self.kdf = kdf;
+        self
+    }
+
+    /// Generates a fresh secret for `kind` and stores it under `name`.
+    pub fn add(
+        &self,
+        name: &str,
+        kind: AccountKind,
+        address: &str,
+        passphrase: &str,
+    ) -> Result<KeyInfo, String> {
+        let secret_key = loop {
+            let candidate = Zeroizing::new(format!("0x{}", hex::encode(random::<32>()?)));
+            if check_secret(kind, &candidate).is_ok() {
+                break candidate;
+            }
+        };
+        self.import(name, kind, address, &secret_key, passphrase)
+    }
+
+    /// Stores an existing secret under `name`; names are never overwritten.
+    pub fn import(
+        &self,
+        name: &str,
+        kind: AccountKind,
+        address: &str,
+        secret_key: &str,
+        passphrase: &str,
+    ) -> Result<KeyInfo, String> {
+        check_name(name)?;
+        Fr::try_from(address).map_err(|e| format!("Invalid account address: {}", e))?;
+        let secret_key = Zeroizing::new(secret_key.trim().to_lowercase());
+        check_secret(kind, &secret_key)?;
+
+        let salt = random::<16>()?;
+        let nonce = random::<12>()?;
+        let key = derive_key(passphrase, &salt, &self.kdf)?;
+        let ciphertext = Aes256Gcm::new(&(*key).into())
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: secret_key.as_bytes(),
+                    aad: &associated_data(kind, address),
+                },
+            )
+            .map_err(|_| format!("Cannot encrypt key '{}'", name))?;
+        let file = KeyFile {
+            version: VERSION,
+            name: name.to_string(),
+            kind,
+            address: address.to_string(),
+            kdf: self.kdf,
+            salt: hex::encode(salt),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        };
+        self.write(&file)?;
+        Ok(info(&file))
+    }
+
+    /// Decrypts the secret stored under `name`.
+    pub fn export(&self, name: &str, passphrase: &str) -> Result<Secret<String>, String> {
+        let file = self.read(name)?;
+        let salt = decode(&file.salt, "salt")?;
+        let ciphertext = decode(&file.ciphertext, "ciphertext")?;
+        let nonce = decode(&file.nonce, "nonce")?;
+        if nonce.len() != 12 {
+            return Err(format!("Key '{}' has a bad nonce", name));
+        }
+        let key = derive_key(passphrase, &salt, &file.kdf)?;
+        let plaintext = Aes256Gcm::new(&(*key).into())
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: &associated_data(file.kind, &file.address),
+                },
+            )
+            .map_err(|_| {
+                format!(
+                    "Wrong passphrase for key '{}', or the file was altered",
+                    name
+                )
+            })?;
+        String::from_utf8(plaintext).map(Secret::new).map_err(|e| {
+            drop(Zeroizing::new(e.into_bytes()));
+            format!("Key '{}' does not decrypt", name)
+        })
+    }
+
+    /// The stored keys, by name. An absent directory holds no keys.
+    pub fn list(&self) -> Result<Vec<KeyInfo>, String> {
+        let read_dir = match fs::read_dir(&self.dir) {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(format!("Cannot read {}: {}", self.dir.display(), e)),
+        };
+        let mut keys = vec![];
+        for dir_entry in read_dir.flatten() {
+            let path = dir_entry.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+                    keys.push(info(&self.read(name)?));
+                }
+            }
+        }
+        keys.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(keys)
+    }
+
+    /// Unlocks `name` into the config `WalletConfig::into_wallet` builds
+    /// the account wallet from.
+    pub fn wallet_config(&self, name: &str, passphrase: &str) -> Result<WalletConfig, String> {
+        let file = self.read(name)?;
+        Ok(WalletConfig {
+            kind: file.kind,
+            address: file.address,
+            secret_key: self.export(name, passphrase)?,
+            registration: None,
+        })
+    }
+
+    /// `wallet_config` for the key stored for `address`, if there is one.
+    pub fn wallet_config_for(
+        &self,
+        address: &str,
+        passphrase: &str,
+    ) -> Result<Option<WalletConfig>, String> {
+        let address = Fr::try_from(address).map_err(|e| format!("Invalid address: {}", e))?;
+        let Some(key) = self
+            .list()?
+            .into_iter()
+            .find(|key| Fr::try_from(key.address.as_str()).is_ok_and(|a| a == address))
+        else {
+            return Ok(None);
+        };
+        self.wallet_config(&key.name, passphrase).map(Some)
+    }
+
+    fn read(&self, name: &str) -> Result<KeyFile, String> {
+        check_name(name)?;
+        let path = self.dir.join(format!("{}.json", name));
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| format!("Cannot read key '{}' ({}): {}", name, path.display(), e))?;
+        let malformed =
+            |e: serde_json::Error| format!("Key file {} is malformed: {}", path.display(), e);
+        let FileVersion { version } = serde_json::from_str(&contents).map_err(malformed)?;
+        if version != VERSION {
+            return Err(format!(
+                "Key file {} has unsupported version {}",
+                path.display(),
+                version
+            ));
+        }
+        serde_json::from_str(&contents).map_err(malformed)
+    }
+
+    fn write(&self, file: &KeyFile) -> Result<(), String> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Cannot create {}: {}", self.dir.display(), e))?;
+        let path = self.dir.join(format!("{}.json", file.name));
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut out = options.open(&path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::AlreadyExists => format!("Key '{}' already exists", file.name),
+            _ => format!("Cannot write {}: {}", path.display(), e),
+        })?;
+        let contents = serde_json::to_string_pretty(file).map_err(|e| e.to_string())?;
+        out.write_all(contents.as_bytes())
+            .map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+    }
+}
+
+fn info(file: &KeyFile) -> KeyInfo {
+    KeyInfo {
+        name: file.name.clone(),
+        kind: file.kind,
+        address: file.address.clone(),
+    }
+}
+
+// Names become file names, so they stay within the keystore directory.
+fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!("Invalid key name '{}'", name));
+    }
+    Ok(())
+}
+
+// The same checks `WalletConfig::into_wallet` makes, so a stored key always
+// opens into a wallet.
+fn check_secret(kind: AccountKind, secret_key: &str) -> Result<(), String> {
+    match kind {
+        AccountKind::Ecdsa => EcdsaAccountWallet::new("0x00", secret_key).map(|_| ()),
+        AccountKind::Schnorr => {
+            let secret =
+                Fr::try_from(secret_key).map_err(|e| format!("Invalid secret key: {}", e))?;
+            SchnorrKeyPair::from_secret(&secret.0).map(|_| ())
+        }
+    }
+}
+
+fn random<const N: usize>() -> Result<[u8; N], String> {
+    let mut bytes = [0u8; N];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("No randomness available: {}", e))?;
+    Ok(bytes)
+}
+
+fn decode(value: &str, field: &str) -> Result<Vec<u8>, String> {
+    hex::decode(value).map_err(|e| format!("Key file has a bad {}: {}", field, e))
+}
+
+fn derive_key(
+    passphrase: &str,
+    salt: &[u8],
+    kdf: &KdfParams,
+) -> Result<Zeroizing<[u8; 32]>, String> {
+    kdf.check()?;
+    let params = Params::new(kdf.memory_kib, kdf.passes, kdf.lanes, Some(32))
+        .map_err(|e| format!("Invalid Argon2 parameters: {}", e))?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut *key)
+        .map_err(|e| format!("Cannot derive key: {}", e))?;
+    Ok(key)
+}
+
+// The kind and address, length-prefixed so neither can bleed into the other.
+fn associated_data(kind: AccountKind, address: &str) -> Vec<u8> {
+    let kind: &[u8] = match kind {
+        AccountKind::Schnorr => b"schnorr",
+        AccountKind::Ecdsa => b"ecdsa",
+    };
+    let mut data = vec![];
+    for part in [kind, address.as_bytes()] {
+        data.extend((part.len() as u32).to_be_bytes());
+        data.extend(part);
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const ADDRESS: &str = "0x0a";
+    const SECRET: &str = "0x0101010101010101010101010101010101010101010101010101010101010101";
+
+    fn keystore(test: &str) -> Keystore {
+        let dir = env::temp_dir().join(format!("keystore-{}-{}", test, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        Keystore::new(dir).with_kdf(KdfParams {
+            memory_kib: 8,
+            passes: 1,
+            lanes: 1,
+        })
+    }
+
+    #[test]
+    fn test_import_export_and_list() {
+        let keystore = keystore("roundtrip");
+        assert_eq!(keystore.list().unwrap(), []);
+        keystore
+            .import("alice", AccountKind::Ecdsa, ADDRESS, SECRET, "hunter2")
+            .unwrap();
+        let bob = keystore
+            .add("bob", AccountKind::Schnorr, "0x0b", "correct horse")
+            .unwrap();
+        assert_eq!(bob.kind, AccountKind::Schnorr);
+
+        let names: Vec<_> = keystore
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|k| k.name)
+            .collect();
+        assert_eq!(names, ["alice", "bob"]);
+        assert_eq!(
+            keystore.export("alice", "hunter2").unwrap().expose(),
+            SECRET
+        );
+        let contents = fs::read_to_string(keystore.dir.join("alice.json")).unwrap();
+        assert!(!contents.contains(&SECRET[2..]));
+        assert_eq!(keystore.read("alice").unwrap().version, VERSION);
+
+        let err = keystore.export("alice", "hunter3").unwrap_err();
+        assert!(err.contains("Wrong passphrase"), "{}", err);
+        assert!(keystore
+            .import("alice", AccountKind::Ecdsa, ADDRESS, SECRET, "x")
+            .unwrap_err()
+            .contains("already exists"));
+        assert!(keystore
+            .import("../up", AccountKind::Ecdsa, ADDRESS, SECRET, "x")
+            .is_err());
+        assert!(keystore
+            .import("zero", AccountKind::Ecdsa, ADDRESS, "0x00", "x")
+            .is_err());
+
+        // Relabelling the account breaks the tag.
+        let mut file = keystore.read("alice").unwrap();
+        file.address = "0x0c".to_string();
+        file.name = "mallory".to_string();
+        keystore.write(&file).unwrap();
+        assert!(keystore.export("mallory", "hunter2").is_err());
+
+        fs::remove_dir_all(&keystore.dir).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_other_versions_and_excessive_costs() {
+        let keystore = keystore("limits");
+        keystore
+            .import("alice", AccountKind::Ecdsa, ADDRESS, SECRET, "hunter2")
+            .unwrap();
+        let mut file = keystore.read("alice").unwrap();
+
+        let mut legacy = serde_json::to_value(&file).unwrap();
+        legacy["version"] = json!(1);
+        fs::write(keystore.dir.join("old.json"), legacy.to_string()).unwrap();
+        let err = keystore.export("old", "hunter2").unwrap_err();
+        assert!(err.contains("unsupported version 1"), "{}", err);
+
+        file.name = "greedy".to_string();
+        file.kdf.memory_kib = KdfParams::MAX.memory_kib + 1;
+        keystore.write(&file).unwrap();
+        let err = keystore.export("greedy", "hunter2").unwrap_err();
+        assert!(err.contains("exceed the limit"), "{}", err);
+        assert!(keystore
+            .clone()
+            .with_kdf(KdfParams {
+                passes: KdfParams::MAX.passes + 1,
+                ..KdfParams::default()
+            })
+            .import("bob", AccountKind::Ecdsa, ADDRESS, SECRET, "x")
+            .is_err());
+
+        fs::remove_dir_all(&keystore.dir).unwrap();
+    }
+
+    #[test]
+    fn test_unlocks_into_a_wallet() {
+        let keystore = keystore("wallet");
+        keystore
+            .add("signer", AccountKind::Schnorr, ADDRESS, "pass")
+            .unwrap();
+        let config = keystore.wallet_config("signer", "pass").unwrap();
+        assert_eq!(config.kind, AccountKind::Schnorr);
+        let wallet = config.into_wallet(None).unwrap();
+        assert_eq!(wallet.address(), ADDRESS);
+        assert!(keystore.wallet_config("signer", "wrong").is_err());
+
+        let found = keystore.wallet_config_for("0x000a", "pass").unwrap();
+        assert_eq!(found.unwrap().address, ADDRESS);
+        assert!(keystore
+            .wallet_config_for("0x0b", "pass")
+            .unwrap()
+            .is_none());
+
+        fs::remove_dir_all(&keystore.dir).unwrap();
+    }
+}
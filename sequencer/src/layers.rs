@@ -0,0 +1,418 @@
+use futures_util::future::BoxFuture;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+use crate::aztec_rpc_client::SUBMITTING_METHODS;
+use crate::error::AztecError;
+use crate::testing::{RpcExchange, RpcRecorder};
+
+/// One JSON-RPC round trip: a request envelope in, the reply envelope out.
+/// The transports (`HttpTransport`, `ws_transport::WsTransport`) implement
+/// it, and layers wrap them to retry, pace, measure or record calls. `AztecRpcClient` checks and decodes the reply.
+pub trait RpcService: fmt::Debug + Send + Sync {
+    fn call(&self, request: Value) -> BoxFuture<'_, Result<Value, AztecError>>;
+}
+
+/// Wraps a service in another, as tower's `Layer` does; see
+/// `AztecRpcClientBuilder::layer`.
+pub trait Layer: fmt::Debug + Send + Sync {
+    fn layer(&self, inner: Arc<dyn RpcService>) -> Arc<dyn RpcService>;
+}
+
+/// The request's method, namespace included.
+fn method(request: &Value) -> String {
+    request["method"].as_str().unwrap_or_default().to_string()
+}
+
+/// POSTs each request to the PXE. Anything that isn't a JSON reply is a
+/// `Transport` error.
+#[derive(Debug, Clone)]
+pub struct HttpTransport {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl HttpTransport {
+    pub fn new(url: impl Into<String>, client: reqwest::Client) -> Self {
+        HttpTransport {
+            url: url.into(),
+            client,
+        }
+    }
+}
+
+impl RpcService for HttpTransport {
+    fn call(&self, request: Value) -> BoxFuture<'_, Result<Value, AztecError>> {
+        Box::pin(async move {
+            let response = self.client.post(&self.url).json(&request).send().await?;
+            let text = response.text().await?;
+            Ok(serde_json::from_str(&text)?)
+        })
+    }
+}
+
+/// Retries `Transport` errors, waiting `backoff` and then twice as long each
+/// time. `proveTx` and `sendTx` are never retried, since the first attempt
+/// may have reached the PXE.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryLayer {
+    attempts: u32,
+    backoff: Duration,
+}
+
+impl RetryLayer {
+    /// `attempts` counts the first call too.
+    pub fn new(attempts: u32, backoff: Duration) -> Self {
+        RetryLayer {
+            attempts: attempts.max(1),
+            backoff,
+        }
+    }
+}
+
+impl Layer for RetryLayer {
+    fn layer(&self, inner: Arc<dyn RpcService>) -> Arc<dyn RpcService> {
+        Arc::new(Retry {
+            inner,
+            config: *self,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Retry {
+    inner: Arc<dyn RpcService>,
+    config: RetryLayer,
+}
+
+impl RpcService for Retry {
+    fn call(&self, request: Value) -> BoxFuture<'_, Result<Value, AztecError>> {
+        Box::pin(async move {
+            let method = method(&request);
+            let submits = SUBMITTING_METHODS
+                .iter()
+                .any(|m| method.rsplit('_').next() == Some(*m));
+            let attempts = if submits { 1 } else { self.config.attempts };
+            let mut backoff = self.config.backoff;
+            let mut attempt = 1;
+            loop {
+                match self.inner.call(request.clone()).await {
+                    Err(AztecError::Transport(e)) if attempt < attempts => {
+                        tracing::debug!("Retrying {} after attempt {}: {}", method, attempt, e);
+                        sleep(backoff).await;
+                        backoff *= 2;
+                        attempt += 1;
+                    }
+                    result => return result,
+                }
+            }
+        })
+    }
+}
+
+/// Starts at most `per_second` calls a second, evenly spaced, across every
+/// clone of the client the layer was added to.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitLayer {
+    interval: Duration,
+}
+
+impl RateLimitLayer {
+    pub fn new(per_second: u32) -> Self {
+        RateLimitLayer {
+            interval: Duration::from_secs(1) / per_second.max(1),
+        }
+    }
+}
+
+impl Layer for RateLimitLayer {
+    fn layer(&self, inner: Arc<dyn RpcService>) -> Arc<dyn RpcService> {
+        Arc::new(RateLimit {
+            inner,
+            interval: self.interval,
+            next: Mutex::new(Instant::now()),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct RateLimit {
+    inner: Arc<dyn RpcService>,
+    interval: Duration,
+    /// When the next call may start.
+    next: Mutex<Instant>,
+}
+
+impl RpcService for RateLimit {
+    fn call(&self, request: Value) -> BoxFuture<'_, Result<Value, AztecError>> {
+        Box::pin(async move {
+            let start = {
+                let mut next = self.next.lock().expect("rate limit lock poisoned");
+                let start = (*next).max(Instant::now());
+                *next = start + self.interval;
+                start
+            };
+            tokio::time::sleep_until(start.into()).await;
+            self.inner.call(request).await
+        })
+    }
+}
+
+/// Calls, failures and time spent for one method.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MethodStats {
+    pub calls: u64,
+    /// Calls that failed or got a JSON-RPC error back.
+    pub errors: u64,
+    pub total_time: Duration,
+}
+
+/// Per-method stats a `MetricsLayer` collects. Share one between clients to
+/// aggregate them.
+#[derive(Debug, Default)]
+pub struct RpcMetrics {
+    methods: Mutex<BTreeMap<String, MethodStats>>,
+}
+
+impl RpcMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stats so far, by method (namespace included).
+    pub fn snapshot(&self) -> BTreeMap<String, MethodStats> {
+        self.methods.lock().expect("metrics lock poisoned").clone()
+    }
+
+    fn observe(&self, method: String, elapsed: Duration, failed: bool) {
+        let mut methods = self.methods.lock().expect("metrics lock poisoned");
+        let stats = methods.entry(method).or_default();
+        stats.calls += 1;
+        stats.errors += failed as u64;
+        stats.total_time += elapsed;
+    }
+}
+
+/// Counts calls into an `RpcMetrics`.
+#[derive(Debug, Clone)]
+pub struct MetricsLayer {
+    metrics: Arc<RpcMetrics>,
+}
+
+impl MetricsLayer {
+    pub fn new(metrics: Arc<RpcMetrics>) -> Self {
+        MetricsLayer { metrics }
+    }
+}
+
+impl Layer for MetricsLayer {
+    fn layer(&self, inner: Arc<dyn RpcService>) -> Arc<dyn RpcService> {
+        Arc::new(Metrics {
+            inner,
+            metrics: self.metrics.clone(),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Metrics {
+    inner: Arc<dyn RpcService>,
+    metrics: Arc<RpcMetrics>,
+}
+
+impl RpcService for Metrics {
+    fn call(&self, request: Value) -> BoxFuture<'_, Result<Value, AztecError>> {
+        Box::pin(async move {
+            let method = method(&request);
+            let started = Instant::now();
+            let result = self.inner.call(request).await;
+            let failed = match &result {
+                Ok(reply) => !reply["error"].is_null(),
+                Err(_) => true,
+            };
+            self.metrics.observe(method, started.elapsed(), failed);
+            result
+        })
+    }
+}
+
+/// Logs each call's method, id and duration as a `tracing` debug event.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TraceLayer;
+
+impl Layer for TraceLayer {
+    fn layer(&self, inner: Arc<dyn RpcService>) -> Arc<dyn RpcService> {
+        Arc::new(Trace { inner })
+    }
+}
+
+#[derive(Debug)]
+struct Trace {
+    inner: Arc<dyn RpcService>,
+}
+
+impl RpcService for Trace {
+    fn call(&self, request: Value) -> BoxFuture<'_, Result<Value, AztecError>> {
+        Box::pin(async move {
+            let method = method(&request);
+            let id = request["id"].clone();
+            let started = Instant::now();
+            let result = self.inner.call(request).await;
+            let elapsed_ms = started.elapsed().as_millis() as u64;
+            match &result {
+                Ok(_) => tracing::debug!(%method, %id, elapsed_ms, "PXE call"),
+                Err(e) => tracing::debug!(%method, %id, elapsed_ms, error = %e, "PXE call failed"),
+            }
+            result
+        })
+    }
+}
+
+/// Pushes every answered call into an `RpcRecorder`, for replay.
+#[derive(Debug, Clone)]
+pub struct RecordLayer {
+    recorder: RpcRecorder,
+}
+
+impl RecordLayer {
+    pub fn new(recorder: RpcRecorder) -> Self {
+        RecordLayer { recorder }
+    }
+}
+
+impl Layer for RecordLayer {
+    fn layer(&self, inner: Arc<dyn RpcService>) -> Arc<dyn RpcService> {
+        Arc::new(Record {
+            inner,
+            recorder: self.recorder.clone(),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Record {
+    inner: Arc<dyn RpcService>,
+    recorder: RpcRecorder,
+}
+
+impl RpcService for Record {
+    fn call(&self, request: Value) -> BoxFuture<'_, Result<Value, AztecError>> {
+        Box::pin(async move {
+            let response = self.inner.call(request.clone()).await?;
+            self.recorder.record(RpcExchange {
+                method: method(&request),
+                request,
+                response: response.clone(),
+            });
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aztec_rpc_client::AztecRpcClient;
+    use crate::testing::MockPxe;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Fails with a transport error until `failures` calls have been made.
+    #[derive(Debug)]
+    struct Flaky {
+        failures: u32,
+        calls: AtomicU32,
+    }
+
+    impl RpcService for Flaky {
+        fn call(&self, request: Value) -> BoxFuture<'_, Result<Value, AztecError>> {
+            let call = self.calls.fetch_add(1, Ordering::Relaxed);
+            Box::pin(async move {
+                if call < self.failures {
+                    return Err(AztecError::Transport("connection reset".to_string()));
+                }
+                Ok(json!({ "jsonrpc": "2.0", "id": request["id"], "result": 7 }))
+            })
+        }
+    }
+
+    fn flaky(failures: u32) -> Arc<Flaky> {
+        Arc::new(Flaky {
+            failures,
+            calls: AtomicU32::new(0),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_retry_stops_at_attempts_and_skips_submissions() {
+        let request = |method: &str| json!({ "jsonrpc": "2.0", "id": 1, "method": method });
+        let retry = RetryLayer::new(3, Duration::ZERO);
+
+        let inner = flaky(2);
+        let service = retry.layer(inner.clone());
+        assert_eq!(
+            service.call(request("pxe_getBlockNumber")).await.unwrap()["result"],
+            7
+        );
+        assert_eq!(inner.calls.load(Ordering::Relaxed), 3);
+
+        let inner = flaky(3);
+        let service = retry.layer(inner.clone());
+        assert!(service.call(request("pxe_getBlockNumber")).await.is_err());
+        assert_eq!(inner.calls.load(Ordering::Relaxed), 3);
+
+        let inner = flaky(1);
+        let service = retry.layer(inner.clone());
+        assert!(service.call(request("pxe_sendTx")).await.is_err());
+        assert_eq!(inner.calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_builder_layers_wrap_the_transport() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond("pxe_getBlockNumber", json!(7));
+        mock.respond_with_envelope(
+            "pxe_getContracts",
+            json!({ "jsonrpc": "2.0", "id": 2, "error": { "code": -32601 } }),
+        );
+        let metrics = Arc::new(RpcMetrics::new());
+        let recorder = RpcRecorder::new();
+        let pxe = AztecRpcClient::builder(mock.url())
+            .namespace("pxe")
+            .layer(MetricsLayer::new(metrics.clone()))
+            .layer(TraceLayer)
+            .layer(RateLimitLayer::new(20))
+            .layer(RecordLayer::new(recorder.clone()))
+            .build()
+            .unwrap();
+
+        let started = Instant::now();
+        assert_eq!(pxe.get_block_number().await.unwrap(), 7);
+        assert!(pxe.get_contracts().await.is_err());
+        assert_eq!(pxe.get_block_number().await.unwrap(), 7);
+        assert!(started.elapsed() >= Duration::from_millis(100));
+
+        let stats = metrics.snapshot();
+        assert_eq!(stats["pxe_getBlockNumber"].calls, 2);
+        assert_eq!(stats["pxe_getBlockNumber"].errors, 0);
+        assert_eq!(stats["pxe_getContracts"].errors, 1);
+        let recorded: Vec<String> = recorder
+            .trace()
+            .exchanges
+            .into_iter()
+            .map(|e| e.method)
+            .collect();
+        assert_eq!(
+            recorded,
+            [
+                "pxe_getBlockNumber",
+                "pxe_getContracts",
+                "pxe_getBlockNumber"
+            ]
+        );
+    }
+}
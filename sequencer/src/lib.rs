@@ -0,0 +1,60 @@
+pub use aztec_core::{curves, encoder, fields, tx_request};
+
+pub mod alerts;
+#[cfg(feature = "indexer")]
+pub mod audit;
+pub mod authwit;
+pub mod aztec_rpc_client;
+pub mod block;
+pub mod bridge;
+pub mod call_args;
+pub mod complete_address;
+pub mod contract;
+pub mod contracts;
+pub mod debug_info;
+pub mod deploy;
+pub mod error;
+pub mod fee_payment;
+pub mod feed_policy;
+pub mod feeds;
+pub mod fees;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod fixed_point;
+pub mod gas;
+#[cfg(feature = "indexer")]
+pub mod indexer;
+pub mod inspect;
+pub mod journal;
+pub mod keystore;
+pub mod layers;
+pub mod logging;
+pub mod merkle;
+pub mod networks;
+pub mod node_client;
+pub mod notes;
+pub mod outbox;
+pub mod pedersen;
+pub mod private_logs;
+pub mod pxe_api;
+pub mod remote_signer;
+pub mod secret;
+pub mod senders;
+pub mod signing;
+pub mod simulation_cache;
+pub mod simulation_error;
+pub mod state;
+pub mod storage;
+pub mod supervisor;
+#[cfg(feature = "otel")]
+pub mod telemetry;
+pub mod testing;
+pub mod timings;
+pub mod trees;
+pub mod twap;
+#[cfg(unix)]
+pub mod unix_transport;
+pub mod version;
+pub mod wallet;
+pub mod watcher;
+pub mod ws_transport;
@@ -0,0 +1,26 @@
+use tracing::level_filters::LevelFilter;
+use tracing::Subscriber;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+/// `tracing` events at info and above, on stderr: the failures the bridge
+/// recovers from, such as an idempotency key it could not record, which an
+/// operator still has to see.
+pub fn stderr_layer<S>() -> impl Layer<S>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_filter(LevelFilter::INFO)
+}
+
+/// Installs `stderr_layer` as the global `tracing` subscriber.
+pub fn init() -> Result<(), String> {
+    tracing_subscriber::registry()
+        .with(stderr_layer())
+        .try_init()
+        .map_err(|e| format!("Cannot install the log subscriber: {}", e))
+}
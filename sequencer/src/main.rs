@@ -1,42 +1,507 @@
-use encoder::load_contract_artifact;
-use num_bigint::BigUint;
+use sequencer::alerts;
+use sequencer::aztec_rpc_client::{setup_sandbox, AztecRpcClient};
+use sequencer::bridge::{self, ArtifactRegistry, BenchConfig, BridgeConfig};
+use sequencer::call_args::{load_args_file, prompt_args, scale_fixed_args};
+use sequencer::contract::{ConfirmSend, Contract, SimulateOptions, TxPreview};
+use sequencer::encoder::{get_function_artifact, load_contract_artifact};
+use sequencer::feeds::FeedContract;
+use sequencer::fees::{FeeBudget, FeeLimits};
+use sequencer::fields::Fr;
+use sequencer::fixed_point::FixedPoint;
+use sequencer::gas::{GasProfiler, GasProfilerConfig};
+use sequencer::inspect::ArtifactReport;
+use sequencer::journal::TxJournal;
+use sequencer::keystore::Keystore;
+use sequencer::networks::Networks;
+use sequencer::secret::Zeroizing;
+use sequencer::senders::{SenderPool, SenderPoolConfig};
+use sequencer::simulation_cache::SimulationCache;
+use sequencer::state::StateStore;
+use sequencer::supervisor::Supervisor;
+use sequencer::timings::{CallTimings, Stage};
+use sequencer::wallet::{AccountKind, WalletConfig};
 use serde_json::Value;
+use std::env;
+use std::io::{BufRead, Write};
+use std::sync::Arc;
 
-mod aztec_rpc_client;
-mod fields;
-mod encoder;
-use aztec_rpc_client::{setup_sandbox, AztecRpcClient};
+const DEMO_CONTRACT: &str = "0x12d8f70092c1d4b2bf3ddd60af8e47c1a10d90f3f31fe4c874d4b91f58442ede";
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let pxe = setup_sandbox().await?;
-    println!("Hello, world!");
+    #[cfg(feature = "otel")]
+    let telemetry = sequencer::telemetry::Telemetry::from_env("sequencer")?;
+    #[cfg(not(feature = "otel"))]
+    sequencer::logging::init()?;
+    let result = run().await;
+    #[cfg(feature = "otel")]
+    if let Some(telemetry) = telemetry {
+        telemetry.shutdown()?;
+    }
+    result
+}
+
+async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    // `--dry-run` anywhere: simulate every send instead of proving/sending it.
+    let dry_run = args.iter().any(|a| a == "--dry-run");
+    // `--confirm`: show each tx and ask before it is proven and sent.
+    let confirm = args.iter().any(|a| a == "--confirm");
+    args.retain(|a| a != "--dry-run" && a != "--confirm");
+    // `--network <name>`: a profile from `NETWORKS_CONFIG` instead of `PXE_URL`.
+    let network = match args.iter().position(|a| a == "--network") {
+        Some(i) if i + 1 < args.len() => Some(args.drain(i..i + 2).nth(1).unwrap()),
+        Some(_) => return Err("--network needs a network name".into()),
+        None => None,
+    };
+    if args.first().map(String::as_str) == Some("artifact") {
+        return artifact_command(&args[1..]);
+    }
+    if args.first().map(String::as_str) == Some("keys") {
+        return keys_command(&args[1..]);
+    }
+    if args.first().map(String::as_str) == Some("tx") {
+        return tx_command(&args[1..]);
+    }
+    if args.first().map(String::as_str) == Some("audit") {
+        return audit_command(&args[1..]);
+    }
+    if args.first().map(String::as_str) == Some("stats") {
+        return stats_command();
+    }
+    if args.first().map(String::as_str) == Some("bench") {
+        return bench_command(&args[1..]).await;
+    }
+
+    let (pxe, network_fees) = match &network {
+        Some(name) => (
+            AztecRpcClient::for_network(name).await?,
+            Some(Networks::from_env()?.get(name)?.fees),
+        ),
+        None => (setup_sandbox().await?, None),
+    };
+    let mut pxe = pxe
+        .with_dry_run(dry_run)
+        .with_alert_sink(alerts::from_env());
+    if confirm {
+        pxe = pxe.with_send_confirmation(Arc::new(StdinConfirm));
+    }
+    let fee_limits = FeeLimits::from_env()?
+        .or(network_fees)
+        .filter(|limits| *limits != FeeLimits::default());
+    if let Some(limits) = fee_limits {
+        println!("Fee budget: {:?}", limits);
+        let store = match env::var("FEE_STATE_PATH") {
+            Ok(path) => StateStore::open(path)?,
+            Err(_) => StateStore::in_memory(),
+        };
+        pxe = pxe.with_fee_budget(Arc::new(FeeBudget::new(limits, Arc::new(store))));
+    }
+    if let Some(config) = GasProfilerConfig::from_env()? {
+        println!("Gas profiling: {:?}", config);
+        let store = match env::var("GAS_PROFILE_PATH") {
+            Ok(path) => StateStore::open(path)?,
+            Err(_) => StateStore::in_memory(),
+        };
+        pxe = pxe.with_gas_profiler(Arc::new(GasProfiler::new(config, Arc::new(store))));
+    }
+    let store = match env::var("CALL_TIMINGS_PATH") {
+        Ok(path) => StateStore::open(path)?,
+        Err(_) => StateStore::in_memory(),
+    };
+    pxe = pxe.with_call_timings(Arc::new(CallTimings::new(Arc::new(store))));
+    if let Some(config) = SenderPoolConfig::from_env()? {
+        println!("Sending from {} accounts", config.accounts.len());
+        pxe = pxe.with_sender_pool(Arc::new(SenderPool::new(config)));
+    }
+    if let Some(cache) = SimulationCache::from_env()? {
+        pxe = pxe.with_simulation_cache(Arc::new(cache));
+    }
+    // A wrong origin address only shows up later, as a failed simulation.
+    if let Some(wallet) = WalletConfig::from_env()? {
+        if let Some(account) = wallet.ensure_registered(&pxe).await? {
+            println!(
+                "Account {} is registered with the PXE",
+                account.address.to_hex()
+            );
+        }
+        pxe = pxe.with_wallet(Arc::from(wallet.into_wallet(None)?));
+    }
+    // Pool accounts sign with their keystore keys. One that nothing signs
+    // for is refused here rather than on the first send it is picked for.
+    if let Some(pool) = pxe.sender_pool().cloned() {
+        let keystore = Keystore::from_env();
+        for account in &pool.config().accounts {
+            if pxe.wallet(&account.address).is_some() {
+                continue;
+            }
+            let passphrase = env::var("KEYSTORE_PASSPHRASE").map_err(|_| {
+                format!(
+                    "KEYSTORE_PASSPHRASE is not set, so sender pool account {} has no wallet",
+                    account.address
+                )
+            })?;
+            let wallet = keystore
+                .wallet_config_for(&account.address, &passphrase)?
+                .ok_or_else(|| {
+                    format!(
+                        "Sender pool account {} has no wallet: import its key into the keystore",
+                        account.address
+                    )
+                })?;
+            pxe = pxe.with_wallet(Arc::from(wallet.into_wallet(None)?));
+        }
+    }
+    if let Ok(path) = env::var("TX_JOURNAL_PATH") {
+        let journal = Arc::new(TxJournal::new(Arc::new(StateStore::open(path)?)));
+        for recovery in journal.recover(&pxe).await? {
+            println!("{}", recovery);
+        }
+        pxe = pxe.with_tx_journal(journal);
+    }
+    #[cfg_attr(not(feature = "indexer"), allow(unused_mut))]
+    let mut supervisor = Supervisor::new();
+    #[cfg(feature = "indexer")]
+    if let Ok(path) = env::var("INDEXER_DB_PATH") {
+        use sequencer::indexer::{Indexer, IndexerConfig};
+        use sequencer::supervisor::RestartPolicy;
+        // Blocks come from the node; the PXE only proxies some of its methods.
+        let node = match sequencer::node_client::AztecNodeClient::from_env()? {
+            Some(node) => node.rpc().clone(),
+            None => pxe.clone(),
+        };
+        let interval = std::time::Duration::from_secs(5);
+        let indexer = Indexer::open(node, IndexerConfig::from_env()?, interval, &path)?;
+        println!("Indexing feed activity into {}", path);
+        let indexer = Arc::new(indexer);
+        supervisor.add("Indexer", RestartPolicy::default(), move || {
+            let indexer = indexer.clone();
+            async move {
+                indexer.run().await;
+                Ok(())
+            }
+        });
+    }
+    if dry_run {
+        println!("Dry run: transactions are simulated, never sent");
+    }
+    if args.first().map(String::as_str) == Some("bridge") {
+        return bridge::run(BridgeConfig::from_env()?, pxe, supervisor).await;
+    }
+    // The other commands finish on their own; tasks only run alongside.
+    tokio::spawn(async move {
+        if let Err(e) = supervisor.run(std::future::pending()).await {
+            println!("{}", e);
+        }
+    });
+    if args.first().map(String::as_str) == Some("call") {
+        return call_command(&pxe, BridgeConfig::from_env()?, &args[1..]).await;
+    }
+
     let block = pxe.get_block_number().await?;
     println!("Current PXE block: {}", block);
-    // let contract_metadata = pxe.get_contract_metadata().await?;
-    // println!("contract_metadata: {:x?}", contract_metadata);
 
-    println!("===============================");
-    println!("===============================");
-    println!("===============================");
+    feeds_demo(&pxe, BridgeConfig::from_env()?).await
+}
 
-    let artifact = load_contract_artifact("contract-Main.json");
-    println!("artifact: {:x?}", artifact);
+/// Drives the deployed `Main` contract the way the TypeScript `deploy.ts`
+/// script does. Reuses the bridge's `ARTIFACT_DIR`, `DEFAULT_CONTRACT` and
+/// `SENDER_ADDRESS` settings.
+async fn feeds_demo(
+    pxe: &AztecRpcClient,
+    config: BridgeConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let address = config
+        .default_contract
+        .clone()
+        .unwrap_or_else(|| DEMO_CONTRACT.to_string());
+    let artifact = ArtifactRegistry::new(&config.artifact_dir).resolve(&address)?;
+    let feeds = FeedContract::new(Contract::at(pxe, config.sender.clone(), address, artifact));
 
-    let just_field = 1u32;
-    // let _contract_metadata = pxe
-    //     .send_tx_set_feeds(
-    //         "0x154307e2c5e6b146106ad12642a7a1abef01990b0bc68b21c0de67267a705344",
-    //         "0x12d8f70092c1d4b2bf3ddd60af8e47c1a10d90f3f31fe4c874d4b91f58442ede",
-    //         "set_just_field",
-    //         vec![Value::String(just_field.to_string())],
-    //     )
-    //     .await?;
+    let sent = if pxe.dry_run() {
+        "would have updated"
+    } else {
+        "sent"
+    };
 
+    let tx_hash = feeds.set_field(Fr::from(214u8)).await?;
+    println!("set_just_field(214) {}: {}", sent, tx_hash);
+    println!("just_field: {}", feeds.get_field().await?.0);
 
-    // TODO: Get contract artifact
-    // TODO: Get function artifact
+    let feed_id = Fr::from(1u8);
+    let tx_hash = feeds.set_feed(feed_id.clone(), Fr::from(42u8)).await?;
+    println!("set_field_in_map(1, 42) {}: {}", sent, tx_hash);
+    println!("feed 1: {}", feeds.read_feed(feed_id).await?.0);
 
+    Ok(())
+}
+
+/// Calls any function of a contract in the bridge's `ARTIFACT_DIR`: static
+/// ones are simulated and print their return values, others are sent. With
+/// neither `--args` nor `--args-file`, each argument is asked for. Each
+/// `--fixed <name>` argument or struct field is given as a decimal and
+/// scaled by `--decimals` (or `FEED_DECIMALS`).
+async fn call_command(
+    pxe: &AztecRpcClient,
+    config: BridgeConfig,
+    args: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let usage = "usage: sequencer call <function> [--contract <address>] \
+                 [--args '<json array>' | --args-file <args.json>] \
+                 [--fixed <name>]... [--decimals <n>]";
+    let (mut function, mut contract, mut json_args, mut args_file) = (None, None, None, None);
+    let (mut fixed_names, mut decimals) = (vec![], None);
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--contract" => contract = Some(rest.next().ok_or(usage)?.clone()),
+            "--args" => json_args = Some(rest.next().ok_or(usage)?),
+            "--args-file" => args_file = Some(rest.next().ok_or(usage)?),
+            "--fixed" => fixed_names.push(rest.next().ok_or(usage)?.clone()),
+            "--decimals" => decimals = Some(rest.next().ok_or(usage)?.parse()?),
+            name if function.is_none() && !name.starts_with("--") => function = Some(name),
+            _ => return Err(usage.into()),
+        }
+    }
+    let function = function.ok_or(usage)?;
+
+    let address = contract
+        .or(config.default_contract)
+        .unwrap_or_else(|| DEMO_CONTRACT.to_string());
+    let artifact = ArtifactRegistry::new(&config.artifact_dir).resolve(&address)?;
+    let abi = get_function_artifact(&artifact, function)?.to_abi();
+    let call_args: Vec<Value> = match (json_args, args_file) {
+        (Some(json), None) => serde_json::from_str(json)?,
+        (None, Some(path)) => load_args_file(path, &abi.parameters)?,
+        (None, None) => prompt_args(&abi.parameters, prompt, |e| eprintln!("  {}", e))?,
+        _ => return Err("--args and --args-file can't be combined".into()),
+    };
+    let call_args = if fixed_names.is_empty() {
+        call_args
+    } else {
+        let fixed = match decimals {
+            Some(decimals) => FixedPoint::from_env()?.with_decimals(decimals)?,
+            None => FixedPoint::from_env()?,
+        };
+        scale_fixed_args(&abi.parameters, call_args, &fixed_names, &fixed)?
+    };
+
+    let contract = Contract::at(pxe, config.sender, address, artifact);
+    let interaction = contract.method(function, call_args)?;
+    if abi.isStatic {
+        let simulation = interaction
+            .simulate_result(SimulateOptions::default())
+            .await?;
+        for value in simulation.returns()? {
+            println!("{}", value);
+        }
+    } else {
+        println!("{} sent: {}", abi.name, interaction.send().await?);
+    }
 
     Ok(())
 }
+
+fn artifact_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let usage = "usage: sequencer artifact inspect <artifact.json> [--json]";
+    let (Some("inspect"), Some(path)) = (args.first().map(String::as_str), args.get(1)) else {
+        return Err(usage.into());
+    };
+
+    let report = ArtifactReport::from_artifact(&load_contract_artifact(path)?);
+    if args[2..].iter().any(|a| a == "--json") {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{}", report.to_table());
+    }
+
+    Ok(())
+}
+
+fn keys_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let usage = "usage: sequencer keys add|import <name> <address> [--kind schnorr|ecdsa] \
+                 | export <name> | list";
+    let keystore = Keystore::from_env();
+    let mut kind = AccountKind::Schnorr;
+    let mut positional = vec![];
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--kind" => kind = AccountKind::parse(Some(rest.next().ok_or(usage)?))?,
+            arg => positional.push(arg),
+        }
+    }
+
+    match positional.as_slice() {
+        ["add", name, address] => {
+            let key = keystore.add(name, kind, address, &passphrase()?)?;
+            println!(
+                "Created {:?} key {} for {}",
+                key.kind, key.name, key.address
+            );
+        }
+        ["import", name, address] => {
+            let secret_key = Zeroizing::new(prompt("Secret key: ")?);
+            let key = keystore.import(name, kind, address, &secret_key, &passphrase()?)?;
+            println!(
+                "Imported {:?} key {} for {}",
+                key.kind, key.name, key.address
+            );
+        }
+        ["export", name] => println!("{}", keystore.export(name, &passphrase()?)?.expose()),
+        ["list"] => {
+            for key in keystore.list()? {
+                println!("{}\t{:?}\t{}", key.name, key.kind, key.address);
+            }
+        }
+        _ => return Err(usage.into()),
+    }
+
+    Ok(())
+}
+
+// Works on the `TX_JOURNAL_PATH` journal only; the next run with the journal
+// reconciles cancelled txs against their receipts.
+fn tx_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let usage = "usage: sequencer tx list [<account>] | cancel <tx hash or journal id>";
+    let path = env::var("TX_JOURNAL_PATH").map_err(|_| "tx needs TX_JOURNAL_PATH")?;
+    let journal = TxJournal::new(Arc::new(StateStore::open(path)?));
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["list"] | ["list", _] => {
+            let entries = match args.get(1) {
+                Some(account) => journal.pending(account)?,
+                None => journal.entries()?,
+            };
+            for (id, entry) in entries {
+                println!(
+                    "{}\t{}\t{}.{}\t{}{}",
+                    id,
+                    entry.from,
+                    entry.contract,
+                    entry.function,
+                    entry.tx_hash.as_deref().unwrap_or("unsent"),
+                    if entry.cancelled { "\tcancelled" } else { "" }
+                );
+            }
+        }
+        ["cancel", tx] => {
+            let id = journal.cancel(tx)?;
+            println!("Cancelled journaled tx {}; it won't be resubmitted", id);
+        }
+        _ => return Err(usage.into()),
+    }
+    Ok(())
+}
+
+// Reads the `INDEXER_DB_PATH` database, so it covers whatever a running
+// indexer has seen; `--from`/`--to` are inclusive unix timestamps.
+#[cfg(feature = "indexer")]
+fn audit_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    use sequencer::audit::{export, AuditFormat, AuditLog};
+    let usage =
+        "usage: sequencer audit export [--from <unix time>] [--to <unix time>] [--format csv|jsonl]";
+    let (Some("export"), rest) = (args.first().map(String::as_str), args.get(1..)) else {
+        return Err(usage.into());
+    };
+    let (mut from, mut to, mut format) = (None, None, AuditFormat::default());
+    let time = |flag: &str, value: &str| {
+        value
+            .parse::<u64>()
+            .map_err(|_| format!("Invalid {}: {}", flag, value))
+    };
+    let mut rest = rest.unwrap_or_default().iter();
+    while let Some(flag) = rest.next() {
+        let value = rest.next().ok_or(usage)?;
+        match flag.as_str() {
+            "--from" => from = Some(time(flag, value)?),
+            "--to" => to = Some(time(flag, value)?),
+            "--format" => format = value.parse()?,
+            _ => return Err(usage.into()),
+        }
+    }
+    let path = env::var("INDEXER_DB_PATH").map_err(|_| "audit needs INDEXER_DB_PATH")?;
+    let records = AuditLog::open(&path)?.records(from, to)?;
+    export(&records, format, &mut std::io::stdout().lock())?;
+    Ok(())
+}
+
+#[cfg(not(feature = "indexer"))]
+fn audit_command(_args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    Err("audit needs the indexer; this binary was built without the `indexer` feature".into())
+}
+
+// Summarises the `CALL_TIMINGS_PATH` timings, slowest functions first within
+// each stage; percentiles are bucket bounds, so upper estimates.
+fn stats_command() -> Result<(), Box<dyn std::error::Error>> {
+    let path = env::var("CALL_TIMINGS_PATH").map_err(|_| "stats needs CALL_TIMINGS_PATH")?;
+    let mut timings = CallTimings::new(Arc::new(StateStore::open(path)?)).snapshot()?;
+    timings.sort_by_key(|t| (t.stage, std::cmp::Reverse(t.histogram.mean())));
+    let ms = |d: Option<std::time::Duration>| d.map_or(0, |d| d.as_millis());
+    println!("function\tcontract\tselector\tstage\tcalls\tmean ms\tp50 ms\tp95 ms\tmax ms");
+    for t in &timings {
+        let h = &t.histogram;
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            t.function,
+            t.contract,
+            t.selector,
+            t.stage,
+            h.count,
+            ms(h.mean()),
+            ms(h.percentile(50)),
+            ms(h.percentile(95)),
+            h.max_ms
+        );
+    }
+    if !timings.iter().any(|t| t.stage == Stage::Prove) {
+        println!("No proofs timed yet");
+    }
+    Ok(())
+}
+
+// `bench ws ...`: load the bridge; needs no PXE of its own.
+async fn bench_command(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(("ws", options)) = args.split_first().map(|(kind, rest)| (kind.as_str(), rest)) else {
+        return Err(
+            "usage: bench ws [url] [--connections N] [--requests M] [--sets PERCENT] \
+                    [--rate PER_SECOND] [--timeout SECONDS] [--contract ADDRESS] \
+                    [--get FUNCTION] [--set FUNCTION] [--value JSON]"
+                .into(),
+        );
+    };
+    let config = BenchConfig::parse(options)?;
+    println!(
+        "Sending {} requests over {} connections to {}",
+        config.requests, config.connections, config.url
+    );
+    print!("{}", config.run().await);
+    Ok(())
+}
+
+#[derive(Debug)]
+struct StdinConfirm;
+
+impl ConfirmSend for StdinConfirm {
+    fn confirm(&self, preview: &TxPreview) -> bool {
+        print!("{}", preview);
+        prompt("Send this transaction? [y/N] ")
+            .is_ok_and(|answer| matches!(answer.trim(), "y" | "Y" | "yes"))
+    }
+}
+
+// `KEYSTORE_PASSPHRASE`, else asked for on stdin.
+fn passphrase() -> Result<Zeroizing<String>, Box<dyn std::error::Error>> {
+    match env::var("KEYSTORE_PASSPHRASE") {
+        Ok(passphrase) => Ok(Zeroizing::new(passphrase)),
+        Err(_) => prompt("Passphrase: ").map(Zeroizing::new),
+    }
+}
+
+fn prompt(label: &str) -> Result<String, Box<dyn std::error::Error>> {
+    eprint!("{}", label);
+    std::io::stderr().flush()?;
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line)?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
@@ -0,0 +1,503 @@
+use serde_json::Value;
+
+use crate::block::{AppendOnlyTreeSnapshot, BlockHeader};
+use crate::fields::Fr;
+use crate::node_client::{AztecNodeClient, BlockParam, MerkleTreeId};
+use crate::notes::Poseidon2;
+
+/// Hashes `leaf` up `sibling_path` to the root, taking the left or right
+/// branch by the bits of `index` from the bottom.
+pub fn compute_root<H: Poseidon2>(hasher: &H, leaf: &Fr, index: u64, sibling_path: &[Fr]) -> Fr {
+    sibling_path
+        .iter()
+        .enumerate()
+        .fold(leaf.clone(), |node, (level, sibling)| {
+            if (index >> level) & 1 == 0 {
+                hasher.hash(&[node, sibling.clone()])
+            } else {
+                hasher.hash(&[sibling.clone(), node])
+            }
+        })
+}
+
+/// Checks that `leaf` sits at `index` in the tree `snapshot` describes.
+pub fn verify_membership<H: Poseidon2>(
+    hasher: &H,
+    leaf: &Fr,
+    index: u64,
+    sibling_path: &[Fr],
+    snapshot: &AppendOnlyTreeSnapshot,
+) -> Result<(), String> {
+    if index >= snapshot.next_available_leaf_index {
+        return Err(format!(
+            "Leaf index {} is past the tree's {} leaves",
+            index, snapshot.next_available_leaf_index
+        ));
+    }
+    if sibling_path.len() < 64 && index >> sibling_path.len() != 0 {
+        return Err(format!(
+            "Leaf index {} does not fit a tree of height {}",
+            index,
+            sibling_path.len()
+        ));
+    }
+    let root = compute_root(hasher, leaf, index, sibling_path);
+    if root != snapshot.root {
+        return Err(format!(
+            "Proof gives root {}, expected {}",
+            root.to_hex(),
+            snapshot.root.to_hex()
+        ));
+    }
+    Ok(())
+}
+
+impl MerkleTreeId {
+    /// The tree as of `header`. For the archive that is the tree before the
+    /// header's block was added.
+    pub fn snapshot(self, header: &BlockHeader) -> &AppendOnlyTreeSnapshot {
+        match self {
+            MerkleTreeId::NullifierTree => &header.state.partial.nullifier_tree,
+            MerkleTreeId::NoteHashTree => &header.state.partial.note_hash_tree,
+            MerkleTreeId::PublicDataTree => &header.state.partial.public_data_tree,
+            MerkleTreeId::L1ToL2MessageTree => &header.state.l1_to_l2_message_tree,
+            MerkleTreeId::Archive => &header.last_archive,
+        }
+    }
+}
+
+/// A nullifier tree leaf: the nullifier and the next larger one in the tree,
+/// so a "low" leaf also proves a nullifier is absent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NullifierLeaf {
+    pub nullifier: Fr,
+    pub next_nullifier: Fr,
+    pub next_index: u64,
+}
+
+impl NullifierLeaf {
+    pub fn hash<H: Poseidon2>(&self, hasher: &H) -> Fr {
+        hasher.hash(&[
+            self.nullifier.clone(),
+            self.next_nullifier.clone(),
+            Fr::from(self.next_index),
+        ])
+    }
+}
+
+/// A public data tree leaf, linked by slot like the nullifier tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicDataLeaf {
+    pub slot: Fr,
+    pub value: Fr,
+    pub next_slot: Fr,
+    pub next_index: u64,
+}
+
+impl PublicDataLeaf {
+    pub fn hash<H: Poseidon2>(&self, hasher: &H) -> Fr {
+        hasher.hash(&[
+            self.slot.clone(),
+            self.value.clone(),
+            Fr::from(self.next_index),
+            self.next_slot.clone(),
+        ])
+    }
+}
+
+/// An indexed tree leaf with its position and sibling path, as the node's
+/// `get*MembershipWitness` and `getPublicDataWitness` return it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeafWitness<L> {
+    pub index: u64,
+    pub leaf: L,
+    pub sibling_path: Vec<Fr>,
+}
+
+impl LeafWitness<NullifierLeaf> {
+    pub fn from_json(witness: &Value) -> Result<Self, String> {
+        let preimage = &witness["leafPreimage"];
+        Ok(LeafWitness {
+            index: index(&witness["index"])?,
+            leaf: NullifierLeaf {
+                nullifier: field(preimage, &["nullifier", "leaf.nullifier"])?,
+                next_nullifier: field(preimage, &["nextNullifier", "nextKey"])?,
+                next_index: index(&preimage["nextIndex"])?,
+            },
+            sibling_path: sibling_path(&witness["siblingPath"])?,
+        })
+    }
+}
+
+impl LeafWitness<PublicDataLeaf> {
+    pub fn from_json(witness: &Value) -> Result<Self, String> {
+        let preimage = &witness["leafPreimage"];
+        Ok(LeafWitness {
+            index: index(&witness["index"])?,
+            leaf: PublicDataLeaf {
+                slot: field(preimage, &["slot", "leaf.slot"])?,
+                value: field(preimage, &["value", "leaf.value"])?,
+                next_slot: field(preimage, &["nextSlot", "nextKey"])?,
+                next_index: index(&preimage["nextIndex"])?,
+            },
+            sibling_path: sibling_path(&witness["siblingPath"])?,
+        })
+    }
+}
+
+/// `key` lies strictly between the low leaf and its successor; a successor
+/// of zero means the low leaf is the largest in the tree.
+pub(crate) fn in_gap(key: &Fr, low: &Fr, next: &Fr) -> bool {
+    low < key && (next.is_zero() || key < next)
+}
+
+/// The first of `keys` (dotted paths into `value`) present, as a field.
+fn field(value: &Value, keys: &[&str]) -> Result<Fr, String> {
+    let found = keys.iter().find_map(|key| {
+        let found = key.split('.').fold(value, |value, part| &value[part]);
+        (!found.is_null()).then_some(found)
+    });
+    let found = found.ok_or_else(|| format!("Leaf preimage has no `{}`", keys[0]))?;
+    serde_json::from_value(found.clone()).map_err(|e| format!("Invalid `{}`: {}", keys[0], e))
+}
+
+pub(crate) fn index(value: &Value) -> Result<u64, String> {
+    let parsed = match value {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => Fr::try_from(s.as_str()).ok().and_then(|f| f.to_u64()),
+        _ => None,
+    };
+    parsed.ok_or_else(|| format!("Invalid leaf index: {}", value))
+}
+
+/// Sibling paths come as a list of fields or as the hex of their buffer
+/// form (a 4-byte length, then 32 bytes per node).
+pub fn sibling_path(value: &Value) -> Result<Vec<Fr>, String> {
+    match value {
+        Value::Array(_) => serde_json::from_value(value.clone())
+            .map_err(|e| format!("Invalid sibling path: {}", e)),
+        Value::String(hex) => {
+            let bytes = hex::decode(hex.trim_start_matches("0x"))
+                .map_err(|e| format!("Invalid sibling path: {}", e))?;
+            let nodes = match bytes.len() % 32 {
+                0 => &bytes[..],
+                4 => &bytes[4..],
+                _ => return Err(format!("Sibling path has {} bytes", bytes.len())),
+            };
+            nodes.chunks(32).map(Fr::from_be_bytes).collect()
+        }
+        _ => Err(format!("Invalid sibling path: {}", value)),
+    }
+}
+
+/// Node reads checked against the block header's tree roots, so a lying or
+/// lagging node is caught instead of trusted. Every read is pinned to one
+/// block, so the header and the proof describe the same state.
+pub struct VerifiedReader<'a, H> {
+    node: &'a AztecNodeClient,
+    hasher: H,
+}
+
+impl<'a, H: Poseidon2> VerifiedReader<'a, H> {
+    pub fn new(node: &'a AztecNodeClient, hasher: H) -> Self {
+        VerifiedReader { node, hasher }
+    }
+
+    async fn header(&self, block: u64) -> Result<BlockHeader, Box<dyn std::error::Error>> {
+        let header = self
+            .node
+            .get_block_header(BlockParam::Number(block))
+            .await?;
+        Ok(header.ok_or_else(|| format!("Node has no block {}", block))?)
+    }
+
+    /// Checks that `note_hash` is leaf `index` of the note hash tree.
+    pub async fn verify_note_hash(
+        &self,
+        block: u64,
+        index: u64,
+        note_hash: &Fr,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let header = self.header(block).await?;
+        let path = self
+            .node
+            .get_note_hash_sibling_path(BlockParam::Number(block), index)
+            .await?;
+        let snapshot = MerkleTreeId::NoteHashTree.snapshot(&header);
+        Ok(verify_membership(
+            &self.hasher,
+            note_hash,
+            index,
+            &path,
+            snapshot,
+        )?)
+    }
+
+    /// Whether `nullifier` is in the tree: proven by its own leaf, or its
+    /// absence by the low leaf whose gap it falls in.
+    pub async fn nullifier_exists(
+        &self,
+        block: u64,
+        nullifier: &Fr,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let header = self.header(block).await?;
+        let snapshot = MerkleTreeId::NullifierTree.snapshot(&header);
+        let at = BlockParam::Number(block);
+        if let Some(witness) = self
+            .node
+            .get_nullifier_membership_witness(at, nullifier)
+            .await?
+        {
+            if &witness.leaf.nullifier != nullifier {
+                return Err("Node returned a witness for another nullifier".into());
+            }
+            self.verify_leaf(&witness, witness.leaf.hash(&self.hasher), snapshot)?;
+            return Ok(true);
+        }
+        let low = self
+            .node
+            .get_low_nullifier_membership_witness(at, nullifier)
+            .await?
+            .ok_or("Node has no low nullifier witness")?;
+        if !in_gap(nullifier, &low.leaf.nullifier, &low.leaf.next_nullifier) {
+            return Err("Low nullifier does not bound the nullifier".into());
+        }
+        self.verify_leaf(&low, low.leaf.hash(&self.hasher), snapshot)?;
+        Ok(false)
+    }
+
+    /// The value at `leaf_slot` (a contract's slot siloed with its address),
+    /// zero when the slot was never written.
+    pub async fn public_data(
+        &self,
+        block: u64,
+        leaf_slot: &Fr,
+    ) -> Result<Fr, Box<dyn std::error::Error>> {
+        let header = self.header(block).await?;
+        let witness = self
+            .node
+            .get_public_data_witness(BlockParam::Number(block), leaf_slot)
+            .await?
+            .ok_or("Node has no public data witness")?;
+        let leaf = &witness.leaf;
+        let value = if &leaf.slot == leaf_slot {
+            leaf.value.clone()
+        } else if in_gap(leaf_slot, &leaf.slot, &leaf.next_slot) {
+            Fr::zero()
+        } else {
+            return Err("Public data witness neither holds nor bounds the slot".into());
+        };
+        let snapshot = MerkleTreeId::PublicDataTree.snapshot(&header);
+        self.verify_leaf(&witness, leaf.hash(&self.hasher), snapshot)?;
+        Ok(value)
+    }
+
+    /// Checks that block `number`'s hash is leaf `number` of the archive
+    /// block `at` was built on, so `at` must come after `number`.
+    pub async fn verify_block_hash(
+        &self,
+        at: u64,
+        number: u64,
+        block_hash: &Fr,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if number >= at {
+            return Err(
+                format!("Block {} is not in the archive before block {}", number, at).into(),
+            );
+        }
+        let header = self.header(at).await?;
+        // The header's archive is the tree as of the previous block.
+        let path = self
+            .node
+            .get_archive_sibling_path(BlockParam::Number(at - 1), number)
+            .await?;
+        let snapshot = MerkleTreeId::Archive.snapshot(&header);
+        Ok(verify_membership(
+            &self.hasher,
+            block_hash,
+            number,
+            &path,
+            snapshot,
+        )?)
+    }
+
+    fn verify_leaf<L>(
+        &self,
+        witness: &LeafWitness<L>,
+        leaf_hash: Fr,
+        snapshot: &AppendOnlyTreeSnapshot,
+    ) -> Result<(), String> {
+        verify_membership(
+            &self.hasher,
+            &leaf_hash,
+            witness.index,
+            &witness.sibling_path,
+            snapshot,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::fixtures::block_json;
+    use crate::notes::Bn254Poseidon2;
+    use crate::testing::MockPxe;
+    use crate::trees::{AppendOnlyTree, IndexedTree};
+    use serde_json::json;
+
+    fn snapshot(root: &Fr) -> AppendOnlyTreeSnapshot {
+        AppendOnlyTreeSnapshot {
+            root: root.clone(),
+            next_available_leaf_index: 5,
+        }
+    }
+
+    #[test]
+    fn test_verifies_sibling_paths() {
+        let leaves: Vec<Fr> = (1..=8u64).map(Fr::from).collect();
+        let mut tree = AppendOnlyTree::new(Bn254Poseidon2, 3).unwrap();
+        for leaf in &leaves {
+            tree.append(leaf.clone()).unwrap();
+        }
+        let root = tree.root();
+        let paths: Vec<Vec<Fr>> = (0..8).map(|i| tree.sibling_path(i).unwrap()).collect();
+        let fields =
+            |hex: &[&str]| -> Vec<Fr> { hex.iter().map(|h| Fr::try_from(*h).unwrap()).collect() };
+        assert_eq!(
+            root,
+            fields(&["0x05d7e5aaddb74c086c24617065e8c97dea94b86fdae0eab7b498249e0dfee2a8"])[0]
+        );
+        assert_eq!(
+            paths[2],
+            fields(&[
+                "0x04",
+                "0x038682aa1cb5ae4e0a3f13da432a95c77c5c111f6f030faf9cad641ce1ed7383",
+                "0x232400b3cca0da78d26295f345d21e9bf8949238bee02b285140ebf183119982",
+            ])
+        );
+        // Above a lone leaf sit empty subtrees; one level up that is
+        // poseidon2([0, 0]), the node's well-known empty hash.
+        let mut lone = AppendOnlyTree::new(Bn254Poseidon2, 2).unwrap();
+        lone.append(Fr::from(1u8)).unwrap();
+        assert_eq!(
+            lone.sibling_path(0).unwrap(),
+            fields(&[
+                "0x00",
+                "0x0b63a53787021a4a962a452c2921b3663aff1ffd8d5510540f8e659e782956f1",
+            ])
+        );
+        for i in 0..5 {
+            verify_membership(
+                &Bn254Poseidon2,
+                &leaves[i],
+                i as u64,
+                &paths[i],
+                &snapshot(&root),
+            )
+            .unwrap();
+        }
+        assert!(
+            verify_membership(&Bn254Poseidon2, &leaves[2], 3, &paths[2], &snapshot(&root)).is_err()
+        );
+        assert!(verify_membership(
+            &Bn254Poseidon2,
+            &Fr::from(9u8),
+            2,
+            &paths[2],
+            &snapshot(&root)
+        )
+        .is_err());
+        // Past the tree's filled leaves, even though the path checks out.
+        assert!(
+            verify_membership(&Bn254Poseidon2, &leaves[6], 6, &paths[6], &snapshot(&root)).is_err()
+        );
+        assert!(
+            verify_membership(&Bn254Poseidon2, &leaves[1], 9, &paths[1], &snapshot(&root)).is_err()
+        );
+
+        let mut buffer = vec![0, 0, 0, 3];
+        buffer.extend(paths[0].iter().flat_map(|f| f.to_be_bytes()));
+        let hex = json!(format!("0x{}", hex::encode(buffer)));
+        assert_eq!(sibling_path(&hex).unwrap(), paths[0]);
+        assert_eq!(sibling_path(&json!(paths[0])).unwrap(), paths[0]);
+        assert!(sibling_path(&json!("0x0102")).is_err());
+    }
+
+    fn public_leaf(slot: u64, value: u64, next_slot: u64, next_index: u64) -> PublicDataLeaf {
+        PublicDataLeaf {
+            slot: Fr::from(slot),
+            value: Fr::from(value),
+            next_slot: Fr::from(next_slot),
+            next_index,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reads_are_checked_against_the_header() {
+        let mut public = IndexedTree::new(Bn254Poseidon2, 2).unwrap();
+        public.insert(public_leaf(10, 42, 0, 0)).unwrap();
+        let public_path = public.find(&Fr::from(10u8)).unwrap().sibling_path;
+        let public_root = public.root();
+        let mut nullifiers = IndexedTree::new(Bn254Poseidon2, 2).unwrap();
+        nullifiers
+            .insert(NullifierLeaf {
+                nullifier: Fr::from(5u8),
+                next_nullifier: Fr::zero(),
+                next_index: 0,
+            })
+            .unwrap();
+        let nullifier_path = nullifiers.find(&Fr::from(5u8)).unwrap().sibling_path;
+        let nullifier_root = nullifiers.root();
+
+        let mut header = block_json(9, vec![])["header"].clone();
+        header["state"]["partial"]["publicDataTree"] =
+            json!({ "root": public_root, "nextAvailableLeafIndex": 2 });
+        header["state"]["partial"]["nullifierTree"] =
+            json!({ "root": nullifier_root, "nextAvailableLeafIndex": 2 });
+        let mut buffer = vec![0, 0, 0, 2];
+        buffer.extend(public_path.iter().flat_map(|f| f.to_be_bytes()));
+        let public_witness = json!({
+            "index": "1",
+            "leafPreimage": { "slot": "0x0a", "value": "0x2a", "nextSlot": "0x00", "nextIndex": "0" },
+            "siblingPath": format!("0x{}", hex::encode(buffer)),
+        });
+        let mut lying = public_witness.clone();
+        lying["leafPreimage"]["value"] = json!("0x2b");
+        let nullifier_witness = json!({
+            "index": 1,
+            "leafPreimage": { "leaf": { "nullifier": "0x05" }, "nextKey": "0x00", "nextIndex": 0 },
+            "siblingPath": nullifier_path,
+        });
+
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond("node_getBlockHeader", header);
+        mock.respond("node_getPublicDataWitness", public_witness.clone());
+        mock.respond("node_getPublicDataWitness", public_witness);
+        mock.respond("node_getPublicDataWitness", lying);
+        mock.respond(
+            "node_getNullifierMembershipWitness",
+            nullifier_witness.clone(),
+        );
+        mock.respond("node_getNullifierMembershipWitness", Value::Null);
+        mock.respond("node_getLowNullifierMembershipWitness", nullifier_witness);
+        let node = AztecNodeClient::new(mock.url());
+        let reader = VerifiedReader::new(&node, Bn254Poseidon2);
+
+        assert_eq!(
+            reader.public_data(9, &Fr::from(10u8)).await.unwrap(),
+            Fr::from(42u8)
+        );
+        assert_eq!(
+            reader.public_data(9, &Fr::from(12u8)).await.unwrap(),
+            Fr::zero()
+        );
+        let err = reader.public_data(9, &Fr::from(10u8)).await.unwrap_err();
+        assert!(err.to_string().contains("expected"), "{}", err);
+
+        assert!(reader.nullifier_exists(9, &Fr::from(5u8)).await.unwrap());
+        assert!(!reader.nullifier_exists(9, &Fr::from(7u8)).await.unwrap());
+        // The low leaf (5, next 0) cannot prove 3 absent.
+        assert!(reader.nullifier_exists(9, &Fr::from(3u8)).await.is_err());
+    }
+}
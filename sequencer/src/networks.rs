@@ -0,0 +1,156 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use crate::fees::FeeLimits;
+use crate::tx_request::NodeInfo;
+
+/// Where `Networks::from_env` looks when `NETWORKS_CONFIG` is unset.
+pub const DEFAULT_NETWORKS_CONFIG: &str = "networks.toml";
+
+/// One environment the binary can target, from a `[networks.<name>]` table:
+///
+/// ```toml
+/// [networks.testnet]
+/// pxe_url = "https://pxe.testnet.example"
+/// l1_chain_id = 11155111
+/// fees = { max_tx_fee = 1000000000 }
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkProfile {
+    pub pxe_url: String,
+    /// The L1 chain the PXE's node must report, if checked.
+    pub l1_chain_id: Option<u64>,
+    /// The rollup version the PXE's node must report, if checked.
+    pub rollup_version: Option<u64>,
+    /// Fee budget for sends, unless `FEE_*` variables override it.
+    #[serde(default)]
+    pub fees: FeeLimits,
+}
+
+impl NetworkProfile {
+    /// The local sandbox, which is there even without a config file.
+    pub fn sandbox() -> Self {
+        NetworkProfile {
+            pxe_url: "http://localhost:8080".to_string(),
+            l1_chain_id: Some(31337),
+            rollup_version: None,
+            fees: FeeLimits::default(),
+        }
+    }
+
+    /// Whether `node` is on the chain this profile expects.
+    pub fn check(&self, node: &NodeInfo) -> Result<(), String> {
+        let expect = |what: &str, expected: Option<u64>, found: u64| match expected {
+            Some(expected) if expected != found => Err(format!(
+                "PXE at {} reports {} {}, expected {}",
+                self.pxe_url, what, found, expected
+            )),
+            _ => Ok(()),
+        };
+        expect("L1 chain id", self.l1_chain_id, node.l1_chain_id)?;
+        expect("rollup version", self.rollup_version, node.rollup_version)
+    }
+}
+
+/// The named network profiles of a config file.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct Networks {
+    #[serde(default)]
+    networks: BTreeMap<String, NetworkProfile>,
+}
+
+impl Networks {
+    pub fn parse(toml: &str) -> Result<Self, String> {
+        toml::from_str(toml).map_err(|e| format!("Invalid network config: {}", e))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        Self::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Reads the file named by `NETWORKS_CONFIG`, else `networks.toml` if it
+    /// exists; without either there are no profiles but the sandbox.
+    pub fn from_env() -> Result<Self, String> {
+        match env::var("NETWORKS_CONFIG") {
+            Ok(path) => Self::load(path),
+            Err(_) if Path::new(DEFAULT_NETWORKS_CONFIG).exists() => {
+                Self::load(DEFAULT_NETWORKS_CONFIG)
+            }
+            Err(_) => Ok(Networks::default()),
+        }
+    }
+
+    /// The profile called `name`. `sandbox` falls back to the local sandbox
+    /// when the config doesn't define it.
+    pub fn get(&self, name: &str) -> Result<NetworkProfile, String> {
+        match self.networks.get(name) {
+            Some(profile) => Ok(profile.clone()),
+            None if name == "sandbox" => Ok(NetworkProfile::sandbox()),
+            None => Err(format!(
+                "Unknown network '{}' (configured: {})",
+                name,
+                self.names().join(", ")
+            )),
+        }
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.networks.keys().map(String::as_str).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles_by_name() {
+        let networks = Networks::parse(
+            r#"
+            [networks.testnet]
+            pxe_url = "https://pxe.testnet.example"
+            l1_chain_id = 11155111
+            rollup_version = 7
+            fees = { max_tx_fee = 1000, daily_cap = "0x1000000000000000000" }
+
+            [networks.local]
+            pxe_url = "unix:///run/pxe.sock"
+            "#,
+        )
+        .unwrap();
+
+        let testnet = networks.get("testnet").unwrap();
+        assert_eq!(testnet.fees.max_tx_fee, Some(1000));
+        assert_eq!(testnet.fees.hourly_cap, None);
+        assert_eq!(testnet.fees.daily_cap, Some(1 << 72));
+        assert_eq!(networks.get("local").unwrap().l1_chain_id, None);
+        assert_eq!(networks.get("sandbox").unwrap(), NetworkProfile::sandbox());
+        assert!(networks
+            .get("mainnet")
+            .unwrap_err()
+            .contains("local, testnet"));
+        assert!(Networks::parse("[networks.x]\npxe_url = \"a\"\nchain = 1").is_err());
+
+        let node = NodeInfo {
+            node_version: String::new(),
+            l1_chain_id: 11155111,
+            rollup_version: 7,
+        };
+        assert!(testnet.check(&node).is_ok());
+        let node = NodeInfo {
+            rollup_version: 8,
+            ..node
+        };
+        assert!(testnet
+            .check(&node)
+            .unwrap_err()
+            .contains("rollup version 8"));
+    }
+}
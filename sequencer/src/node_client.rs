@@ -0,0 +1,340 @@
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::{json, Value};
+use std::env;
+
+use crate::aztec_rpc_client::{AztecRpcClient, HttpClientConfig};
+use crate::block::{BlockHeader, IndexedTxEffect, L2Block, StateReference};
+use crate::error::AztecError;
+use crate::fields::Fr;
+use crate::merkle::{sibling_path, LeafWitness, NullifierLeaf, PublicDataLeaf};
+use crate::outbox::MessageWitness;
+use crate::private_logs::PrivateLog;
+
+/// Which block a node read is made against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockParam {
+    Latest,
+    Number(u64),
+}
+
+impl Serialize for BlockParam {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            BlockParam::Latest => serializer.serialize_str("latest"),
+            BlockParam::Number(number) => serializer.serialize_u64(*number),
+        }
+    }
+}
+
+/// The node's world state trees, numbered as `MerkleTreeId`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MerkleTreeId {
+    NullifierTree = 0,
+    NoteHashTree = 1,
+    PublicDataTree = 2,
+    L1ToL2MessageTree = 3,
+    Archive = 4,
+}
+
+/// A log found by `getLogsByTags`, with the tx that emitted it.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxScopedLog {
+    pub tx_hash: String,
+    pub block_number: u64,
+    /// The private or public log, in the node's JSON form.
+    pub log: Value,
+}
+
+/// Reads chain data from an Aztec node (`node_` methods) rather than a PXE:
+/// blocks, tx effects, public storage and world state. Shares the PXE
+/// client's transport and `AztecError`s.
+#[derive(Debug, Clone)]
+pub struct AztecNodeClient {
+    rpc: AztecRpcClient,
+}
+
+impl AztecNodeClient {
+    pub fn new(url: impl Into<String>) -> Self {
+        AztecNodeClient {
+            rpc: AztecRpcClient::new(url, Some("node".to_string())),
+        }
+    }
+
+    /// A node client with a tuned HTTP client; see `HttpClientConfig`.
+    pub fn with_http_config(
+        url: impl Into<String>,
+        http: HttpClientConfig,
+    ) -> Result<Self, String> {
+        let rpc = AztecRpcClient::builder(url)
+            .namespace("node")
+            .http_config(http)
+            .build()?;
+        Ok(AztecNodeClient { rpc })
+    }
+
+    /// Reads `AZTEC_NODE_URL`, with the same `PXE_HTTP_*` settings as the
+    /// PXE client; `None` when no URL is set.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Ok(url) = env::var("AZTEC_NODE_URL") else {
+            return Ok(None);
+        };
+        let http = HttpClientConfig::from_env()?.unwrap_or_default();
+        Self::with_http_config(url, http).map(Some)
+    }
+
+    /// The underlying JSON-RPC client, for node methods without a wrapper.
+    pub fn rpc(&self) -> &AztecRpcClient {
+        &self.rpc
+    }
+
+    pub async fn get_block_number(&self) -> Result<u64, AztecError> {
+        self.rpc.get_block_number().await
+    }
+
+    /// `None` for blocks the node doesn't have yet.
+    pub async fn get_block(&self, number: u64) -> Result<Option<L2Block>, AztecError> {
+        self.rpc.get_block(number).await
+    }
+
+    /// `None` for blocks the node doesn't have yet.
+    pub async fn get_block_header(
+        &self,
+        block: BlockParam,
+    ) -> Result<Option<BlockHeader>, AztecError> {
+        self.rpc.request("getBlockHeader", vec![json!(block)]).await
+    }
+
+    /// The tree roots after `block`.
+    pub async fn get_world_state_roots(
+        &self,
+        block: BlockParam,
+    ) -> Result<Option<StateReference>, AztecError> {
+        Ok(self
+            .get_block_header(block)
+            .await?
+            .map(|header| header.state))
+    }
+
+    /// `None` until the tx is mined.
+    pub async fn get_tx_effect(
+        &self,
+        tx_hash: &str,
+    ) -> Result<Option<IndexedTxEffect>, AztecError> {
+        self.rpc.get_tx_effect(tx_hash).await
+    }
+
+    pub async fn get_public_storage_at(
+        &self,
+        block: BlockParam,
+        contract: &str,
+        slot: &Fr,
+    ) -> Result<Fr, AztecError> {
+        self.rpc
+            .request(
+                "getPublicStorageAt",
+                vec![json!(block), json!(contract), json!(slot)],
+            )
+            .await
+    }
+
+    /// Each leaf's index in `tree` as of `block`, `None` for leaves not in
+    /// it. Nodes answer with bare indexes or `{ data, l2BlockNumber, .. }`.
+    pub async fn find_leaves_indexes(
+        &self,
+        block: BlockParam,
+        tree: MerkleTreeId,
+        leaves: &[Fr],
+    ) -> Result<Vec<Option<u64>>, AztecError> {
+        let indexes: Vec<Value> = self
+            .rpc
+            .request(
+                "findLeavesIndexes",
+                vec![json!(block), json!(tree as u8), json!(leaves)],
+            )
+            .await?;
+        indexes.iter().map(leaf_index).collect()
+    }
+
+    /// The logs carrying each tag, one list per tag in order.
+    pub async fn get_logs_by_tags(&self, tags: &[Fr]) -> Result<Vec<Vec<TxScopedLog>>, AztecError> {
+        self.rpc.request("getLogsByTags", vec![json!(tags)]).await
+    }
+
+    /// The note hash tree path of leaf `index`, as of `block`.
+    pub async fn get_note_hash_sibling_path(
+        &self,
+        block: BlockParam,
+        index: u64,
+    ) -> Result<Vec<Fr>, AztecError> {
+        let path: Value = self
+            .rpc
+            .request("getNoteHashSiblingPath", vec![json!(block), json!(index)])
+            .await?;
+        sibling_path(&path).map_err(AztecError::Transport)
+    }
+
+    /// The archive path of block `number`'s hash, as of `block`.
+    pub async fn get_archive_sibling_path(
+        &self,
+        block: BlockParam,
+        number: u64,
+    ) -> Result<Vec<Fr>, AztecError> {
+        let path: Value = self
+            .rpc
+            .request("getArchiveSiblingPath", vec![json!(block), json!(number)])
+            .await?;
+        sibling_path(&path).map_err(AztecError::Transport)
+    }
+
+    /// `None` when `nullifier` is not in the tree.
+    pub async fn get_nullifier_membership_witness(
+        &self,
+        block: BlockParam,
+        nullifier: &Fr,
+    ) -> Result<Option<LeafWitness<NullifierLeaf>>, AztecError> {
+        self.witness("getNullifierMembershipWitness", block, nullifier)
+            .await?
+            .map(|w| LeafWitness::<NullifierLeaf>::from_json(&w).map_err(AztecError::Transport))
+            .transpose()
+    }
+
+    /// The leaf with the largest nullifier below `nullifier`.
+    pub async fn get_low_nullifier_membership_witness(
+        &self,
+        block: BlockParam,
+        nullifier: &Fr,
+    ) -> Result<Option<LeafWitness<NullifierLeaf>>, AztecError> {
+        self.witness("getLowNullifierMembershipWitness", block, nullifier)
+            .await?
+            .map(|w| LeafWitness::<NullifierLeaf>::from_json(&w).map_err(AztecError::Transport))
+            .transpose()
+    }
+
+    /// The leaf holding `leaf_slot`, or the low leaf below it when the slot
+    /// was never written.
+    pub async fn get_public_data_witness(
+        &self,
+        block: BlockParam,
+        leaf_slot: &Fr,
+    ) -> Result<Option<LeafWitness<PublicDataLeaf>>, AztecError> {
+        self.witness("getPublicDataWitness", block, leaf_slot)
+            .await?
+            .map(|w| LeafWitness::<PublicDataLeaf>::from_json(&w).map_err(AztecError::Transport))
+            .transpose()
+    }
+
+    /// Where `message` (its hash) sits in block `number`'s out hash tree;
+    /// `None` when the block sent no such message.
+    pub async fn get_l2_to_l1_membership_witness(
+        &self,
+        number: u64,
+        message: &Fr,
+    ) -> Result<Option<MessageWitness>, AztecError> {
+        let witness: Option<Value> = self
+            .rpc
+            .request(
+                "getL2ToL1MembershipWitness",
+                vec![json!(number), json!(message)],
+            )
+            .await?;
+        witness
+            .map(|w| MessageWitness::from_json(number, &w).map_err(AztecError::Transport))
+            .transpose()
+    }
+
+    async fn witness(
+        &self,
+        method: &str,
+        block: BlockParam,
+        key: &Fr,
+    ) -> Result<Option<Value>, AztecError> {
+        self.rpc
+            .request(method, vec![json!(block), json!(key)])
+            .await
+    }
+
+    /// Up to `limit` private logs from block `from` onwards.
+    pub async fn get_private_logs(
+        &self,
+        from: u64,
+        limit: u64,
+    ) -> Result<Vec<PrivateLog>, AztecError> {
+        self.rpc.get_private_logs(from, limit).await
+    }
+}
+
+fn leaf_index(index: &Value) -> Result<Option<u64>, AztecError> {
+    let invalid = || AztecError::Transport(format!("Invalid leaf index from node: {}", index));
+    match index.get("data").unwrap_or(index) {
+        Value::Null => Ok(None),
+        Value::Number(n) => n.as_u64().map(Some).ok_or_else(invalid),
+        Value::String(s) => Fr::try_from(s.as_str())
+            .ok()
+            .and_then(|f| f.to_u64())
+            .map(Some)
+            .ok_or_else(invalid),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::fixtures::block_json;
+    use crate::testing::MockPxe;
+
+    #[tokio::test]
+    async fn test_node_methods_use_the_node_namespace() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond(
+            "node_getBlockHeader",
+            block_json(9, vec![])["header"].clone(),
+        );
+        mock.respond("node_getPublicStorageAt", json!(Fr::from(214u8)));
+        mock.respond(
+            "node_findLeavesIndexes",
+            json!([5, null, "0x0c", { "data": "7", "l2BlockNumber": 9 }]),
+        );
+        mock.respond(
+            "node_getLogsByTags",
+            json!([[{ "txHash": "0x1a", "blockNumber": 9, "log": { "fields": ["0x01"] } }], []]),
+        );
+        let node = AztecNodeClient::new(mock.url());
+
+        let roots = node
+            .get_world_state_roots(BlockParam::Latest)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(roots.partial.nullifier_tree.next_available_leaf_index, 128);
+        let value = node
+            .get_public_storage_at(BlockParam::Number(9), "0x0a", &Fr::from(2u8))
+            .await
+            .unwrap();
+        assert_eq!(value, Fr::from(214u8));
+        let indexes = node
+            .find_leaves_indexes(
+                BlockParam::Latest,
+                MerkleTreeId::NullifierTree,
+                &[Fr::from(1u8), Fr::from(2u8), Fr::from(3u8), Fr::from(4u8)],
+            )
+            .await
+            .unwrap();
+        assert_eq!(indexes, [Some(5), None, Some(12), Some(7)]);
+        let logs = node
+            .get_logs_by_tags(&[Fr::from(1u8), Fr::from(2u8)])
+            .await
+            .unwrap();
+        assert_eq!(logs[0][0].tx_hash, "0x1a");
+        assert!(logs[1].is_empty());
+
+        let requests = mock.requests();
+        assert_eq!(requests[0]["params"], json!(["latest"]));
+        assert_eq!(requests[1]["params"][0], json!(9));
+        assert_eq!(requests[2]["params"][1], json!(0));
+
+        assert!(leaf_index(&json!([1])).is_err());
+        assert!(leaf_index(&json!(-1)).is_err());
+    }
+}
@@ -0,0 +1,326 @@
+use serde_json::Value;
+
+use crate::contract::TxEffects;
+use crate::encoder::ContractNote;
+use crate::fields::Fr;
+
+pub use aztec_core::poseidon2::{generator_index, Bn254Poseidon2, Poseidon2};
+
+/// A note's packed fields (in the artifact's field order) and the storage
+/// slot it lives in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotePreimage {
+    pub fields: Vec<Fr>,
+    pub storage_slot: Fr,
+}
+
+impl NotePreimage {
+    /// Packs a JSON object keyed by the note's field names, e.g.
+    /// `{"value": "0x2a", "owner": "0x..."}` for a `ValueNote`.
+    pub fn from_json(
+        note: &ContractNote,
+        values: &Value,
+        storage_slot: Fr,
+    ) -> Result<Self, String> {
+        let mut fields: Vec<_> = note.fields.iter().collect();
+        fields.sort_by_key(|f| f.index);
+
+        let fields = fields
+            .into_iter()
+            .map(|field| match &values[&field.name] {
+                Value::Null if field.nullable => Ok(Fr::zero()),
+                Value::Null => Err(format!("{} is missing `{}`", note.typ, field.name)),
+                value => serde_json::from_value(value.clone())
+                    .map_err(|e| format!("Invalid `{}` for {}: {}", field.name, note.typ, e)),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(NotePreimage {
+            fields,
+            storage_slot,
+        })
+    }
+}
+
+/// What a note turns into once a tx emits it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmittedNote {
+    pub note_hash: Fr,
+    pub siloed_note_hash: Fr,
+    /// The value that ends up in the note hash tree (and in the tx effect's
+    /// `noteHashes`).
+    pub unique_note_hash: Fr,
+}
+
+/// Note hash and nullifier derivation, following aztec-nr: a note hash is
+/// siloed with the contract address, then made unique with a nonce derived
+/// from the tx's first nullifier and the note's index among its note hashes.
+pub struct NoteHasher<H> {
+    hasher: H,
+}
+
+impl Default for NoteHasher<Bn254Poseidon2> {
+    fn default() -> Self {
+        NoteHasher::new(Bn254Poseidon2)
+    }
+}
+
+impl<H: Poseidon2> NoteHasher<H> {
+    pub fn new(hasher: H) -> Self {
+        NoteHasher { hasher }
+    }
+
+    pub fn note_hash(&self, note: &NotePreimage) -> Fr {
+        let mut inputs = note.fields.clone();
+        inputs.push(note.storage_slot.clone());
+        self.hasher
+            .hash_with_separator(&inputs, generator_index::NOTE_HASH)
+    }
+
+    pub fn silo_note_hash(&self, contract: &Fr, note_hash: &Fr) -> Fr {
+        self.hasher.hash_with_separator(
+            &[contract.clone(), note_hash.clone()],
+            generator_index::SILOED_NOTE_HASH,
+        )
+    }
+
+    pub fn note_hash_nonce(&self, first_nullifier: &Fr, index_in_tx: u64) -> Fr {
+        self.hasher.hash_with_separator(
+            &[first_nullifier.clone(), Fr::from(index_in_tx)],
+            generator_index::NOTE_HASH_NONCE,
+        )
+    }
+
+    pub fn unique_note_hash(&self, nonce: &Fr, siloed_note_hash: &Fr) -> Fr {
+        self.hasher.hash_with_separator(
+            &[nonce.clone(), siloed_note_hash.clone()],
+            generator_index::UNIQUE_NOTE_HASH,
+        )
+    }
+
+    /// Hashes for the notes `contract` emits in a tx whose first nullifier
+    /// is `first_nullifier`, in emission order.
+    pub fn emitted(
+        &self,
+        contract: &Fr,
+        first_nullifier: &Fr,
+        notes: &[NotePreimage],
+    ) -> Vec<EmittedNote> {
+        notes
+            .iter()
+            .enumerate()
+            .map(|(index, note)| {
+                let note_hash = self.note_hash(note);
+                let siloed_note_hash = self.silo_note_hash(contract, &note_hash);
+                let nonce = self.note_hash_nonce(first_nullifier, index as u64);
+                EmittedNote {
+                    unique_note_hash: self.unique_note_hash(&nonce, &siloed_note_hash),
+                    note_hash,
+                    siloed_note_hash,
+                }
+            })
+            .collect()
+    }
+
+    /// Where `note` sits in the tx effect's `noteHashes`, if `contract`
+    /// emitted it in that tx. The first nullifier is the tx's own.
+    pub fn find_in(
+        &self,
+        effects: &TxEffects,
+        contract: &Fr,
+        note: &NotePreimage,
+    ) -> Option<usize> {
+        let first_nullifier = effects.nullifiers.first()?;
+        let siloed_note_hash = self.silo_note_hash(contract, &self.note_hash(note));
+        effects
+            .note_hashes
+            .iter()
+            .enumerate()
+            .position(|(index, unique)| {
+                let nonce = self.note_hash_nonce(first_nullifier, index as u64);
+                *unique == self.unique_note_hash(&nonce, &siloed_note_hash)
+            })
+    }
+
+    /// The nullifier the owner emits when consuming the note:
+    /// `unique_note_hash` with their app-siloed nullifier secret key.
+    pub fn nullifier(&self, unique_note_hash: &Fr, app_nullifier_secret: &Fr) -> Fr {
+        self.hasher.hash_with_separator(
+            &[unique_note_hash.clone(), app_nullifier_secret.clone()],
+            generator_index::NOTE_NULLIFIER,
+        )
+    }
+
+    /// The nullifier as it appears in the tx effect.
+    pub fn silo_nullifier(&self, contract: &Fr, nullifier: &Fr) -> Fr {
+        self.hasher.hash_with_separator(
+            &[contract.clone(), nullifier.clone()],
+            generator_index::OUTER_NULLIFIER,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::cell::RefCell;
+
+    /// Records every call and returns `separator * 1000 + call number`, so
+    /// tests can check what was hashed and in which order.
+    #[derive(Default)]
+    struct Recorder {
+        calls: RefCell<Vec<(u32, Vec<Fr>)>>,
+    }
+
+    impl Poseidon2 for &Recorder {
+        fn hash_with_separator(&self, inputs: &[Fr], separator: u32) -> Fr {
+            let mut calls = self.calls.borrow_mut();
+            calls.push((separator, inputs.to_vec()));
+            Fr::from(separator as u64 * 1000 + calls.len() as u64)
+        }
+
+        fn hash(&self, inputs: &[Fr]) -> Fr {
+            self.hash_with_separator(inputs, 0)
+        }
+    }
+
+    fn value_note() -> ContractNote {
+        serde_json::from_value(json!({ "id": "0x01", "type": "ValueNote", "fields": [
+            { "name": "owner", "index": 1, "nullable": false },
+            { "name": "value", "index": 0, "nullable": false },
+            { "name": "memo", "index": 2, "nullable": true },
+        ] }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_preimage_follows_field_indices() {
+        let note = NotePreimage::from_json(
+            &value_note(),
+            &json!({ "owner": "0x0b", "value": 42 }),
+            Fr::from(2u8),
+        )
+        .unwrap();
+        assert_eq!(
+            note.fields,
+            vec![Fr::from(42u8), Fr::from(11u8), Fr::zero()]
+        );
+
+        let missing = NotePreimage::from_json(&value_note(), &json!({ "value": 1 }), Fr::zero());
+        assert!(missing.unwrap_err().contains("owner"));
+    }
+
+    #[test]
+    fn test_emitted_notes_are_siloed_then_made_unique() {
+        let recorder = Recorder::default();
+        let hasher = NoteHasher::new(&recorder);
+        let note = NotePreimage {
+            fields: vec![Fr::from(42u8)],
+            storage_slot: Fr::from(2u8),
+        };
+
+        let emitted = hasher.emitted(&Fr::from(0xc0u8), &Fr::from(0xf1u8), &[note]);
+        assert_eq!(
+            emitted,
+            vec![EmittedNote {
+                note_hash: Fr::from(1001u64),
+                siloed_note_hash: Fr::from(4002u64),
+                unique_note_hash: Fr::from(3004u64),
+            }]
+        );
+        assert_eq!(
+            *recorder.calls.borrow(),
+            vec![
+                (1, vec![Fr::from(42u8), Fr::from(2u8)]),
+                (4, vec![Fr::from(0xc0u8), Fr::from(1001u64)]),
+                (2, vec![Fr::from(0xf1u8), Fr::zero()]),
+                (3, vec![Fr::from(2003u64), Fr::from(4002u64)]),
+            ]
+        );
+    }
+
+    /// Sums the inputs plus the separator, so equal inputs hash equally.
+    struct Sum;
+
+    impl Poseidon2 for Sum {
+        fn hash_with_separator(&self, inputs: &[Fr], separator: u32) -> Fr {
+            let sum: u64 = inputs.iter().map(|f| f.to_u128().unwrap() as u64).sum();
+            Fr::from(sum + separator as u64)
+        }
+
+        fn hash(&self, inputs: &[Fr]) -> Fr {
+            self.hash_with_separator(inputs, 0)
+        }
+    }
+
+    #[test]
+    fn test_find_note_in_tx_effects() {
+        let hasher = NoteHasher::new(Sum);
+        let contract = Fr::from(100u8);
+        let first_nullifier = Fr::from(50u8);
+        let note = |value: u8| NotePreimage {
+            fields: vec![Fr::from(value)],
+            storage_slot: Fr::from(1u8),
+        };
+        let emitted = hasher.emitted(&contract, &first_nullifier, &[note(7), note(9)]);
+        let effects = TxEffects {
+            note_hashes: emitted.iter().map(|n| n.unique_note_hash.clone()).collect(),
+            nullifiers: vec![first_nullifier],
+            ..Default::default()
+        };
+
+        assert_eq!(hasher.find_in(&effects, &contract, &note(9)), Some(1));
+        assert_eq!(hasher.find_in(&effects, &contract, &note(8)), None);
+        assert_eq!(hasher.find_in(&effects, &Fr::from(1u8), &note(7)), None);
+    }
+
+    #[test]
+    fn test_native_hashes() {
+        let fr = |hex: &str| Fr::try_from(hex).unwrap();
+        let hasher = NoteHasher::default();
+        let note = NotePreimage {
+            fields: vec![Fr::from(42u8)],
+            storage_slot: Fr::from(2u8),
+        };
+
+        let [emitted] = &hasher.emitted(&Fr::from(0xc0u8), &Fr::from(0xf1u8), &[note])[..] else {
+            panic!("expected one note");
+        };
+        assert_eq!(
+            emitted.note_hash,
+            fr("0x199316090a1f3fcf3afb44cb73a32cd75af1c42e7cf713e29367fccc1f4e49f8")
+        );
+        assert_eq!(
+            emitted.siloed_note_hash,
+            fr("0x2c324c2da80b12388248169c0ca4974efccb9896120fe7cbf76bbb8276809bd0")
+        );
+        assert_eq!(
+            emitted.unique_note_hash,
+            fr("0x10b7bbf6c9ad8ac090e333d9cbec6c042864028d91ab2e710e624361fe62cca8")
+        );
+        let nullifier = hasher.nullifier(&emitted.unique_note_hash, &Fr::from(9u8));
+        assert_eq!(
+            nullifier,
+            fr("0x0644d5417cc43023c7b12fd47d4011385d52c2f055bcd0ae6cfb07e52fbd3867")
+        );
+        assert_eq!(
+            hasher.silo_nullifier(&Fr::from(0xc0u8), &nullifier),
+            fr("0x2bcb5314a19d00059c53d5d64b1e2905b491f069aef3ce341a8f6b75c1376559")
+        );
+    }
+
+    #[test]
+    fn test_nullifier_is_siloed_with_the_contract() {
+        let recorder = Recorder::default();
+        let hasher = NoteHasher::new(&recorder);
+
+        let nullifier = hasher.nullifier(&Fr::from(7u8), &Fr::from(9u8));
+        let siloed = hasher.silo_nullifier(&Fr::from(0xc0u8), &nullifier);
+        assert_eq!(siloed, Fr::from(7002u64));
+        assert_eq!(
+            recorder.calls.borrow()[0],
+            (53, vec![Fr::from(7u8), Fr::from(9u8)])
+        );
+    }
+}
@@ -0,0 +1,162 @@
+use num_bigint::BigUint;
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::block::TxEffect;
+use crate::error::AztecError;
+use crate::fields::Fr;
+use crate::merkle::{index, sibling_path};
+use crate::node_client::AztecNodeClient;
+
+/// A message a contract sends to L1, before it is hashed into its tx effect.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct L2ToL1Message {
+    /// The L2 contract that sent it.
+    pub sender: Fr,
+    /// The L1 address that may consume it.
+    pub recipient: Fr,
+    pub content: Fr,
+}
+
+impl L2ToL1Message {
+    /// The hash a tx effect lists and the L1 outbox consumes: the SHA-256 of
+    /// sender, rollup version, recipient, chain id and content, as a field.
+    pub fn hash(&self, rollup_version: u64, chain_id: u64) -> Fr {
+        sha256_to_field(&[
+            self.sender.clone(),
+            Fr::from(rollup_version),
+            self.recipient.clone(),
+            Fr::from(chain_id),
+            self.content.clone(),
+        ])
+    }
+
+    /// Where this message is among `effect`'s messages, if it sent it.
+    pub fn find_in(&self, effect: &TxEffect, rollup_version: u64, chain_id: u64) -> Option<usize> {
+        let hash = self.hash(rollup_version, chain_id);
+        effect.l2_to_l1_msgs.iter().position(|m| *m == hash)
+    }
+}
+
+/// SHA-256 of the fields' 32-byte forms, with the first byte dropped so the
+/// digest fits a field.
+pub fn sha256_to_field(inputs: &[Fr]) -> Fr {
+    let mut sha = Sha256::new();
+    for input in inputs {
+        sha.update(input.to_be_bytes());
+    }
+    Fr::from_biguint(BigUint::from_bytes_be(&sha.finalize()[..31]))
+}
+
+/// Where a message sits in its block's out hash tree, for an L1 relayer to
+/// consume it from the outbox.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageWitness {
+    pub l2_block_number: u64,
+    pub leaf_index: u64,
+    pub sibling_path: Vec<Fr>,
+}
+
+impl MessageWitness {
+    /// The node returns `[leafIndex, siblingPath]`, or an object with those
+    /// keys.
+    pub fn from_json(l2_block_number: u64, witness: &Value) -> Result<Self, String> {
+        let (leaf_index, path) = match witness {
+            Value::Array(pair) if pair.len() == 2 => (&pair[0], &pair[1]),
+            _ => (&witness["leafIndex"], &witness["siblingPath"]),
+        };
+        Ok(MessageWitness {
+            l2_block_number,
+            leaf_index: index(leaf_index)?,
+            sibling_path: sibling_path(path)?,
+        })
+    }
+
+    /// The out hash tree root `message` proves, hashing pairs up the path
+    /// with `sha256_to_field`. The outbox checks against this root.
+    pub fn root(&self, message: &Fr) -> Fr {
+        self.sibling_path
+            .iter()
+            .enumerate()
+            .fold(message.clone(), |node, (level, sibling)| {
+                if (self.leaf_index >> level) & 1 == 0 {
+                    sha256_to_field(&[node, sibling.clone()])
+                } else {
+                    sha256_to_field(&[sibling.clone(), node])
+                }
+            })
+    }
+}
+
+/// The witness for `message` sent by tx `tx_hash`: `None` until the tx is
+/// mined, and an error if the tx didn't send it.
+pub async fn message_witness(
+    node: &AztecNodeClient,
+    tx_hash: &str,
+    message: &Fr,
+) -> Result<Option<MessageWitness>, AztecError> {
+    let Some(effect) = node.get_tx_effect(tx_hash).await? else {
+        return Ok(None);
+    };
+    if !effect.data.l2_to_l1_msgs.contains(message) {
+        return Err(AztecError::State(format!(
+            "Tx {} sent no L2 to L1 message {}",
+            tx_hash,
+            message.to_hex()
+        )));
+    }
+    node.get_l2_to_l1_membership_witness(effect.l2_block_number, message)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::fixtures::tx_effect_json;
+    use crate::testing::MockPxe;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_message_hash_is_found_and_proven() {
+        let message = L2ToL1Message {
+            sender: Fr::from(0x0au8),
+            recipient: Fr::try_from("0x00000000000000000000000000000000000000ee").unwrap(),
+            content: Fr::from(214u8),
+        };
+        let hash = message.hash(1, 31337);
+        assert_eq!(hash.to_be_bytes()[0], 0);
+        assert_ne!(hash, message.hash(2, 31337));
+
+        let mut effect = tx_effect_json();
+        effect["l2ToL1Msgs"] = json!([Fr::from(1u8), hash]);
+        let effect: TxEffect = serde_json::from_value(effect).unwrap();
+        assert_eq!(message.find_in(&effect, 1, 31337), Some(1));
+        assert_eq!(message.find_in(&effect, 1, 1), None);
+
+        let sibling = Fr::from(1u8);
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond(
+            "node_getTxEffect",
+            json!({ "l2BlockNumber": 9, "l2BlockHash": "0x09", "data": effect }),
+        );
+        mock.respond("node_getL2ToL1MembershipWitness", json!(["0x1", [sibling]]));
+        let node = AztecNodeClient::new(mock.url());
+
+        let witness = message_witness(&node, &effect.tx_hash, &hash)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(witness.l2_block_number, 9);
+        assert_eq!(witness.leaf_index, 1);
+        assert_eq!(
+            witness.root(&hash),
+            sha256_to_field(&[sibling, hash.clone()])
+        );
+        assert_eq!(mock.requests()[1]["params"], json!([9, hash]));
+        assert!(message_witness(&node, &effect.tx_hash, &Fr::from(2u8))
+            .await
+            .is_err());
+    }
+}
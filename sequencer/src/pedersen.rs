@@ -0,0 +1,125 @@
+use num_bigint::BigUint;
+
+use crate::curves::grumpkin::{AffinePoint, ProjectivePoint};
+use crate::fields::Fr;
+
+/// The domain Noir's `pedersen_hash` and `pedersen_commitment` take their
+/// generators from.
+const DEFAULT_DOMAIN: &[u8] = b"DEFAULT_DOMAIN_SEPARATOR";
+/// The domain of the generator that commits to the number of inputs.
+const LENGTH_DOMAIN: &[u8] = b"pedersen_hash_length";
+
+/// Barretenberg's Pedersen hash (Noir's `std::hash::pedersen_hash`,
+/// aztec.js' `pedersenHash`): the x of `n·L + Σ xᵢ·Gᵢ` over Grumpkin.
+pub fn pedersen_hash(inputs: &[Fr]) -> Fr {
+    pedersen_hash_with_separator(inputs, 0)
+}
+
+/// `pedersen_hash` with the generators starting at index `separator`.
+pub fn pedersen_hash_with_separator(inputs: &[Fr], separator: u32) -> Fr {
+    let length = derive_generators(LENGTH_DOMAIN, 1, 0).remove(0);
+    length
+        .to_projective()
+        .mul(&BigUint::from(inputs.len()))
+        .add(&commit(inputs, separator))
+        .to_affine()
+        // Barretenberg normalizes the point at infinity to x = 0.
+        .map_or_else(Fr::zero, |point| point.x)
+}
+
+/// `Σ xᵢ·Gᵢ`, as Noir's `pedersen_commitment_with_separator`; `None` is the
+/// point at infinity.
+pub fn pedersen_commit(inputs: &[Fr], separator: u32) -> Option<AffinePoint> {
+    commit(inputs, separator).to_affine()
+}
+
+fn commit(inputs: &[Fr], separator: u32) -> ProjectivePoint {
+    derive_generators(DEFAULT_DOMAIN, inputs.len(), separator)
+        .iter()
+        .zip(inputs)
+        .fold(ProjectivePoint::infinity(), |sum, (generator, input)| {
+            sum.add(&generator.to_projective().mul(&input.0))
+        })
+}
+
+/// Barretenberg's `derive_generators`: generator `i` hashes
+/// `blake3(domain) || i as u32 BE || 28 zero bytes` to the curve.
+pub fn derive_generators(domain: &[u8], count: usize, start: u32) -> Vec<AffinePoint> {
+    let mut preimage = [0u8; 64];
+    preimage[..32].copy_from_slice(blake3::hash(domain).as_bytes());
+    (start..)
+        .take(count)
+        .map(|index| {
+            preimage[32..36].copy_from_slice(&index.to_be_bytes());
+            hash_to_curve(&preimage)
+        })
+        .collect()
+}
+
+/// Tries `x = blake3(seed || attempt || 0) · 2²⁵⁶ + blake3(seed || attempt
+/// || 1)` for attempts 0, 1, … until x is on the curve. The first hash's top
+/// bit picks y's parity.
+fn hash_to_curve(seed: &[u8]) -> AffinePoint {
+    let mut target = seed.to_vec();
+    target.extend([0, 0]);
+    let last = target.len() - 1;
+    (0..=u8::MAX)
+        .find_map(|attempt| {
+            target[last - 1] = attempt;
+            target[last] = 0;
+            let hi = blake3::hash(&target);
+            target[last] = 1;
+            let lo = blake3::hash(&target);
+            let wide = [hi.as_bytes().as_slice(), lo.as_bytes()].concat();
+            let x = Fr(BigUint::from_bytes_be(&wide) % Fr::modulus());
+            let point = AffinePoint::from_x(&x)?;
+            let odd = point.y.0.bit(0);
+            Some(if odd == (hi.as_bytes()[0] > 127) {
+                point
+            } else {
+                point.neg()
+            })
+        })
+        .expect("half of all x are on the curve, so some attempt succeeds")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fr(hex: &str) -> Fr {
+        Fr::try_from(hex).unwrap()
+    }
+
+    // Barretenberg's and aztec.js' own test vectors.
+    #[test]
+    fn test_matches_barretenberg() {
+        let generator = &derive_generators(DEFAULT_DOMAIN, 1, 0)[0];
+        assert_eq!(
+            generator,
+            &AffinePoint::new(
+                fr("0x083e7911d835097629f0067531fc15cafd79a89beecb39903f69572c636f4a5a"),
+                fr("0x1a7f5efaad7f315c25a918f30cc8d7333fccab7ad7c90f14de81bcc528f9935d"),
+            )
+            .unwrap()
+        );
+
+        let ones = [Fr::from(1u8), Fr::from(1u8)];
+        assert_eq!(
+            pedersen_hash(&ones),
+            fr("0x07ebfbf4df29888c6cd6dca13d4bb9d1a923013ddbbcbdc3378ab8845463297b")
+        );
+        assert_eq!(
+            pedersen_hash_with_separator(&ones, 5),
+            fr("0x1c446df60816b897cda124524e6b03f36df0cec333fad87617aab70d7861daa6")
+        );
+        assert_eq!(
+            pedersen_commit(&ones, 0).unwrap(),
+            AffinePoint::new(
+                fr("0x2f7a8f9a6c96926682205fb73ee43215bf13523c19d7afe36f12760266cdfe15"),
+                fr("0x01916b316adbbf0e10e39b18c1d24b33ec84b46daddf72f43878bcc92b6057e6"),
+            )
+            .unwrap()
+        );
+    }
+}
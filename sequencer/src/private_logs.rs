@@ -0,0 +1,278 @@
+use aes::cipher::block_padding::Pkcs7;
+use aes::cipher::{BlockDecryptMut, KeyIvInit};
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::curves::grumpkin::{self, is_positive, AffinePoint};
+use crate::encoder::{ContractArtifact, ContractNote};
+use crate::fields::Fr;
+use crate::notes::NotePreimage;
+use crate::secret::{Secret, Zeroizing};
+
+type Aes128CbcDec = cbc::Decryptor<aes::Aes128>;
+
+const GENERATOR_INDEX_SYMMETRIC_KEY: u8 = 22;
+const HEADER_LEN: usize = 16;
+
+/// A private log as the node returns it from `getPrivateLogs`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrivateLog {
+    pub fields: Vec<Fr>,
+}
+
+/// A note found in a private log addressed to us.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecryptedNote {
+    pub note_type_id: Fr,
+    pub preimage: NotePreimage,
+}
+
+impl DecryptedNote {
+    /// The artifact's definition of this note, if it declares one with
+    /// this id.
+    pub fn note_type<'a>(&self, artifact: &'a ContractArtifact) -> Option<&'a ContractNote> {
+        artifact
+            .notes
+            .values()
+            .find(|note| Fr::try_from(note.id.as_str()).ok().as_ref() == Some(&self.note_type_id))
+    }
+}
+
+/// Decrypts private logs with an account's incoming viewing key.
+///
+/// A log is `[tag, ephemeral public key x, ciphertext...]`. The sender
+/// encrypts to the account's address point, so the shared secret is
+/// `(preaddress + ivsk) * ephemeral public key`; its SHA-256 gives the
+/// AES-128-CBC key and IV. The ciphertext fields carry 31 bytes each: a
+/// 16-byte header holding the body length, then the body, whose plaintext
+/// is `[storage slot, note type id, packed note...]` as 32-byte fields.
+#[derive(Debug)]
+pub struct IncomingNoteDecryptor {
+    address_secret: Secret<[u8; 32]>,
+}
+
+impl IncomingNoteDecryptor {
+    /// `ivsk` is the account's incoming viewing secret key (a Grumpkin
+    /// scalar) and `preaddress` the hash of its public keys and partial
+    /// address.
+    pub fn new(ivsk: &BigUint, preaddress: &Fr) -> Self {
+        let order = grumpkin::order();
+        let sum = Zeroizing::new(Fr(&preaddress.0 + ivsk));
+        let secret = Zeroizing::new(Fr(&sum.0 % &order));
+        // Addresses only commit to x; senders take the point with the
+        // positive y, so the secret is negated when ours is the other one.
+        let address_secret = match grumpkin::mul(&AffinePoint::generator(), &secret.0) {
+            Some(point) if !is_positive(&point.y) => {
+                let negated = Zeroizing::new(Fr(&order - &secret.0));
+                Zeroizing::new(Fr(&negated.0 % &order))
+            }
+            _ => secret,
+        };
+        IncomingNoteDecryptor {
+            address_secret: Secret::new(address_secret.to_be_bytes()),
+        }
+    }
+
+    /// The account address these keys decrypt for.
+    pub fn address(&self) -> Fr {
+        grumpkin::mul(&AffinePoint::generator(), &self.scalar().0)
+            .map(|point| point.x)
+            .unwrap_or_else(Fr::zero)
+    }
+
+    /// The notes in `logs` that are addressed to us, in order.
+    pub fn decrypt_all(&self, logs: &[PrivateLog]) -> Vec<DecryptedNote> {
+        logs.iter()
+            .filter_map(|log| self.decrypt(&log.fields))
+            .collect()
+    }
+
+    /// `None` when the log isn't addressed to us (or isn't a note log).
+    pub fn decrypt(&self, log: &[Fr]) -> Option<DecryptedNote> {
+        let [_tag, ephemeral_x, ciphertext @ ..] = log else {
+            return None;
+        };
+        let ephemeral = AffinePoint::from_x(ephemeral_x)?;
+        let (key, iv) = symmetric_key(&grumpkin::mul(&ephemeral, &self.scalar().0)?);
+
+        let bytes: Vec<u8> = ciphertext
+            .iter()
+            .flat_map(|field| field.to_be_bytes()[1..].to_vec())
+            .collect();
+        let header = aes_decrypt(&key, &iv, bytes.get(..HEADER_LEN)?)?;
+        let body_len = u16::from_be_bytes(header.get(..2)?.try_into().ok()?) as usize;
+        let body = aes_decrypt(&key, &iv, bytes.get(HEADER_LEN..HEADER_LEN + body_len)?)?;
+
+        if body.len() % 32 != 0 {
+            return None;
+        }
+        let fields = body
+            .chunks(32)
+            .map(Fr::from_be_bytes)
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+        let [storage_slot, note_type_id, fields @ ..] = fields.as_slice() else {
+            return None;
+        };
+        Some(DecryptedNote {
+            note_type_id: note_type_id.clone(),
+            preimage: NotePreimage {
+                fields: fields.to_vec(),
+                storage_slot: storage_slot.clone(),
+            },
+        })
+    }
+
+    fn scalar(&self) -> Zeroizing<Fr> {
+        Zeroizing::new(Fr(BigUint::from_bytes_be(self.address_secret.expose())))
+    }
+}
+
+fn aes_decrypt(key: &[u8; 16], iv: &[u8; 16], ciphertext: &[u8]) -> Option<Vec<u8>> {
+    Aes128CbcDec::new(key.into(), iv.into())
+        .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+        .ok()
+}
+
+/// SHA-256 of the compressed shared secret (aztec-nr's `point_to_bytes`,
+/// which sets the sign bit for a positive y) and the symmetric key
+/// separator: the first half is the key, the second the IV.
+fn symmetric_key(shared_secret: &AffinePoint) -> ([u8; 16], [u8; 16]) {
+    let digest = Sha256::new()
+        .chain_update(shared_secret.compress())
+        .chain_update([GENERATOR_INDEX_SYMMETRIC_KEY])
+        .finalize();
+    let mut key = [0u8; 16];
+    let mut iv = [0u8; 16];
+    key.copy_from_slice(&digest[..16]);
+    iv.copy_from_slice(&digest[16..]);
+    (key, iv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes::cipher::BlockEncryptMut;
+    use serde_json::json;
+
+    type Aes128CbcEnc = cbc::Encryptor<aes::Aes128>;
+
+    // No log captured from a real node is checked in, so the sender side is
+    // written out from aztec-nr rather than from this module: the key is
+    // derived from `point_to_bytes` (x with the top bit set when y <= (p -
+    // 1) / 2) and the separator, not from `AffinePoint::compress`.
+    fn aztec_nr_symmetric_key(shared_secret: &AffinePoint) -> ([u8; 16], [u8; 16]) {
+        let mut bytes = [0u8; 33];
+        bytes[..32].copy_from_slice(&shared_secret.x.to_be_bytes());
+        if shared_secret.y.0 <= (Fr::modulus() - 1u32) / 2u32 {
+            bytes[0] += 128;
+        }
+        bytes[32] = 22;
+        let digest = Sha256::digest(bytes);
+        (
+            digest[..16].try_into().unwrap(),
+            digest[16..].try_into().unwrap(),
+        )
+    }
+
+    /// What a sender does to emit `plaintext` to `address`.
+    fn encrypt(address: &Fr, ephemeral_secret: u64, plaintext: &[Fr]) -> Vec<Fr> {
+        let order = grumpkin::order();
+        let g = AffinePoint::generator();
+        let mut ephemeral_secret = BigUint::from(ephemeral_secret);
+        let mut ephemeral = grumpkin::mul(&g, &ephemeral_secret).unwrap();
+        if !is_positive(&ephemeral.y) {
+            ephemeral_secret = &order - ephemeral_secret;
+            ephemeral = grumpkin::mul(&g, &ephemeral_secret).unwrap();
+        }
+        let address_point = AffinePoint::from_x(address).unwrap();
+        let (key, iv) =
+            aztec_nr_symmetric_key(&grumpkin::mul(&address_point, &ephemeral_secret).unwrap());
+
+        let encrypt = |bytes: &[u8]| {
+            Aes128CbcEnc::new(&key.into(), &iv.into()).encrypt_padded_vec_mut::<Pkcs7>(bytes)
+        };
+        let plaintext: Vec<u8> = plaintext.iter().flat_map(|f| f.to_be_bytes()).collect();
+        let body = encrypt(&plaintext);
+        let mut bytes = encrypt(&(body.len() as u16).to_be_bytes());
+        bytes.extend(body);
+        bytes.resize(bytes.len().div_ceil(31) * 31, 0);
+
+        let mut log = vec![Fr::from(0x7a6u64), ephemeral.x];
+        log.extend(
+            bytes
+                .chunks(31)
+                .map(|chunk| Fr::from_be_bytes(chunk).unwrap()),
+        );
+        log
+    }
+
+    #[test]
+    fn test_decrypts_notes_addressed_to_us() {
+        let ours = IncomingNoteDecryptor::new(&BigUint::from(0xabcdefu64), &Fr::from(42u8));
+        let theirs = IncomingNoteDecryptor::new(&BigUint::from(0x123456u64), &Fr::from(42u8));
+        let plaintext = [
+            Fr::from(2u8),
+            Fr::from(1u8),
+            Fr::from(100u8),
+            ours.address(),
+        ];
+
+        let logs = [
+            PrivateLog {
+                fields: encrypt(&ours.address(), 7, &plaintext),
+            },
+            PrivateLog {
+                fields: encrypt(&theirs.address(), 7, &plaintext),
+            },
+            PrivateLog {
+                fields: encrypt(&ours.address(), 1234567, &plaintext),
+            },
+        ];
+        let expected = DecryptedNote {
+            note_type_id: Fr::from(1u8),
+            preimage: NotePreimage {
+                fields: vec![Fr::from(100u8), ours.address()],
+                storage_slot: Fr::from(2u8),
+            },
+        };
+        assert_eq!(ours.decrypt_all(&logs), vec![expected.clone(), expected]);
+        assert_eq!(theirs.decrypt_all(&logs).len(), 1);
+        assert_eq!(ours.decrypt(&[Fr::from(1u8)]), None);
+    }
+
+    #[test]
+    fn test_symmetric_key_follows_the_sign_of_y() {
+        let g = AffinePoint::generator();
+        for point in [g.clone(), g.neg()] {
+            assert_eq!(symmetric_key(&point), aztec_nr_symmetric_key(&point));
+        }
+        assert_ne!(symmetric_key(&g), symmetric_key(&g.neg()));
+    }
+
+    #[test]
+    fn test_note_type_from_artifact() {
+        let artifact: ContractArtifact = serde_json::from_value(json!({
+            "name": "Token",
+            "functions": [],
+            "nonDispatchPublicFunctions": [],
+            "storageLayout": {},
+            "notes": { "ValueNote": { "id": "0x01", "type": "ValueNote", "fields": [] } },
+            "fileMap": {}
+        }))
+        .unwrap();
+        let note = |id: u8| DecryptedNote {
+            note_type_id: Fr::from(id),
+            preimage: NotePreimage {
+                fields: vec![],
+                storage_slot: Fr::zero(),
+            },
+        };
+        assert_eq!(
+            note(1).note_type(&artifact).map(|n| n.typ.as_str()),
+            Some("ValueNote")
+        );
+        assert!(note(2).note_type(&artifact).is_none());
+    }
+}
@@ -0,0 +1,482 @@
+use futures_util::future::BoxFuture;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::alerts::Alert;
+use crate::aztec_rpc_client::AztecRpcClient;
+use crate::block::L2Block;
+use crate::contract::ConfirmSend;
+use crate::error::AztecError;
+use crate::fees::FeeBudget;
+use crate::fields::{AztecAddress, Fr};
+use crate::gas::GasProfiler;
+use crate::journal::TxJournal;
+use crate::senders::SenderPool;
+use crate::simulation_cache::SimulationCache;
+use crate::timings::CallTimings;
+use crate::tx_request::NodeInfo;
+use crate::version::PayloadProfile;
+use crate::wallet::AccountWallet;
+
+/// The PXE as contracts, deployments, the feed updater and the bridge use
+/// it: its JSON-RPC methods, plus the client-side policies a send consults.
+/// `AztecRpcClient` is the real one; a failover pool or a wrapper that times
+/// or logs calls can stand in for it by implementing this.
+///
+/// Only `call` is required. The typed methods decode what it returns, and
+/// the policies default to off.
+pub trait PxeApi: Send + Sync {
+    /// Calls `method` (without the namespace prefix) and returns its result.
+    fn call<'a>(
+        &'a self,
+        method: &'a str,
+        params: Vec<Value>,
+    ) -> BoxFuture<'a, Result<Value, AztecError>>;
+
+    fn get_block_number(&self) -> BoxFuture<'_, Result<u64, AztecError>> {
+        Box::pin(async move { decode(self.call("getBlockNumber", vec![]).await?) })
+    }
+
+    /// The chain id and rollup version tx requests are built for.
+    fn get_node_info(&self) -> BoxFuture<'_, Result<NodeInfo, AztecError>> {
+        Box::pin(async move { decode(self.call("getNodeInfo", vec![]).await?) })
+    }
+
+    /// `None` for blocks the node doesn't have yet.
+    fn get_block(&self, number: u64) -> BoxFuture<'_, Result<Option<L2Block>, AztecError>> {
+        Box::pin(async move { decode(self.call("getBlock", vec![json!(number)]).await?) })
+    }
+
+    fn get_public_storage_at<'a>(
+        &'a self,
+        contract: &'a str,
+        slot: &'a Fr,
+    ) -> BoxFuture<'a, Result<Fr, AztecError>> {
+        Box::pin(async move {
+            let params = vec![json!(contract), json!(slot)];
+            decode(self.call("getPublicStorageAt", params).await?)
+        })
+    }
+
+    fn get_notes(&self, filter: Value) -> BoxFuture<'_, Result<Value, AztecError>> {
+        Box::pin(self.call("getNotes", vec![filter]))
+    }
+
+    fn get_tx_receipt<'a>(&'a self, tx_hash: &'a str) -> BoxFuture<'a, Result<Value, AztecError>> {
+        Box::pin(self.call("getTxReceipt", vec![json!(tx_hash)]))
+    }
+
+    fn get_contracts(&self) -> BoxFuture<'_, Result<Vec<String>, AztecError>> {
+        Box::pin(async move { decode(self.call("getContracts", vec![]).await?) })
+    }
+
+    /// Checks `address` before it goes into the request.
+    fn get_contract_metadata<'a>(
+        &'a self,
+        address: &'a AztecAddress,
+    ) -> BoxFuture<'a, Result<Value, AztecError>> {
+        Box::pin(async move {
+            address.validate().map_err(AztecError::Encoding)?;
+            self.call("getContractMetadata", vec![json!(address)]).await
+        })
+    }
+
+    fn get_contract_artifact<'a>(
+        &'a self,
+        class_id: &'a Fr,
+    ) -> BoxFuture<'a, Result<Value, AztecError>> {
+        Box::pin(self.call("getContractArtifact", vec![json!(class_id)]))
+    }
+
+    /// Registers a contract instance whose class's artifact the PXE already
+    /// has (aztec.js' `registerContract` without an artifact).
+    fn register_contract_instance(&self, instance: Value) -> BoxFuture<'_, Result<(), AztecError>> {
+        Box::pin(async move {
+            let params = vec![json!({ "instance": instance })];
+            self.call("registerContract", params).await.map(|_| ())
+        })
+    }
+
+    /// How tx requests are laid out for the PXE's release.
+    fn profile(&self) -> PayloadProfile {
+        PayloadProfile::default()
+    }
+
+    /// Whether sends should only simulate.
+    fn dry_run(&self) -> bool {
+        false
+    }
+
+    fn fee_budget(&self) -> Option<&Arc<FeeBudget>> {
+        None
+    }
+
+    fn gas_profiler(&self) -> Option<&Arc<GasProfiler>> {
+        None
+    }
+
+    fn tx_journal(&self) -> Option<&Arc<TxJournal>> {
+        None
+    }
+
+    fn simulation_cache(&self) -> Option<&Arc<SimulationCache>> {
+        None
+    }
+
+    fn sender_pool(&self) -> Option<&Arc<SenderPool>> {
+        None
+    }
+
+    fn call_timings(&self) -> Option<&Arc<CallTimings>> {
+        None
+    }
+
+    fn send_confirmation(&self) -> Option<&Arc<dyn ConfirmSend>> {
+        None
+    }
+
+    /// The wallet that signs for `address`'s account, if the client has one.
+    fn wallet(&self, _address: &str) -> Option<&Arc<dyn AccountWallet>> {
+        None
+    }
+
+    fn alert(&self, _alert: Alert) {}
+}
+
+/// A PXE that isn't there: every call fails. Stands in for one where
+/// interactions only `build` tx requests, with no network.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OfflinePxe;
+
+impl PxeApi for OfflinePxe {
+    fn call<'a>(
+        &'a self,
+        method: &'a str,
+        _params: Vec<Value>,
+    ) -> BoxFuture<'a, Result<Value, AztecError>> {
+        Box::pin(async move {
+            Err(AztecError::Transport(format!(
+                "Offline: there is no PXE to call {}",
+                method
+            )))
+        })
+    }
+}
+
+/// Decodes a `call` result as `T`.
+pub fn decode<T: DeserializeOwned>(result: Value) -> Result<T, AztecError> {
+    Ok(serde_json::from_value(result)?)
+}
+
+impl PxeApi for AztecRpcClient {
+    fn call<'a>(
+        &'a self,
+        method: &'a str,
+        params: Vec<Value>,
+    ) -> BoxFuture<'a, Result<Value, AztecError>> {
+        Box::pin(self.request(method, params))
+    }
+
+    fn get_block_number(&self) -> BoxFuture<'_, Result<u64, AztecError>> {
+        Box::pin(AztecRpcClient::get_block_number(self))
+    }
+
+    fn get_node_info(&self) -> BoxFuture<'_, Result<NodeInfo, AztecError>> {
+        Box::pin(AztecRpcClient::get_node_info(self))
+    }
+
+    fn get_block(&self, number: u64) -> BoxFuture<'_, Result<Option<L2Block>, AztecError>> {
+        Box::pin(AztecRpcClient::get_block(self, number))
+    }
+
+    fn get_public_storage_at<'a>(
+        &'a self,
+        contract: &'a str,
+        slot: &'a Fr,
+    ) -> BoxFuture<'a, Result<Fr, AztecError>> {
+        Box::pin(AztecRpcClient::get_public_storage_at(self, contract, slot))
+    }
+
+    fn get_notes(&self, filter: Value) -> BoxFuture<'_, Result<Value, AztecError>> {
+        Box::pin(AztecRpcClient::get_notes(self, filter))
+    }
+
+    // Settles the fee budget, gas profiler and sender pool on the way.
+    fn get_tx_receipt<'a>(&'a self, tx_hash: &'a str) -> BoxFuture<'a, Result<Value, AztecError>> {
+        Box::pin(AztecRpcClient::get_tx_receipt(self, tx_hash))
+    }
+
+    fn get_contracts(&self) -> BoxFuture<'_, Result<Vec<String>, AztecError>> {
+        Box::pin(AztecRpcClient::get_contracts(self))
+    }
+
+    fn get_contract_metadata<'a>(
+        &'a self,
+        address: &'a AztecAddress,
+    ) -> BoxFuture<'a, Result<Value, AztecError>> {
+        Box::pin(AztecRpcClient::get_contract_metadata(self, address))
+    }
+
+    fn get_contract_artifact<'a>(
+        &'a self,
+        class_id: &'a Fr,
+    ) -> BoxFuture<'a, Result<Value, AztecError>> {
+        Box::pin(AztecRpcClient::get_contract_artifact(self, class_id))
+    }
+
+    fn profile(&self) -> PayloadProfile {
+        AztecRpcClient::profile(self)
+    }
+
+    fn dry_run(&self) -> bool {
+        AztecRpcClient::dry_run(self)
+    }
+
+    fn fee_budget(&self) -> Option<&Arc<FeeBudget>> {
+        AztecRpcClient::fee_budget(self)
+    }
+
+    fn gas_profiler(&self) -> Option<&Arc<GasProfiler>> {
+        AztecRpcClient::gas_profiler(self)
+    }
+
+    fn tx_journal(&self) -> Option<&Arc<TxJournal>> {
+        AztecRpcClient::tx_journal(self)
+    }
+
+    fn simulation_cache(&self) -> Option<&Arc<SimulationCache>> {
+        AztecRpcClient::simulation_cache(self)
+    }
+
+    fn sender_pool(&self) -> Option<&Arc<SenderPool>> {
+        AztecRpcClient::sender_pool(self)
+    }
+
+    fn call_timings(&self) -> Option<&Arc<CallTimings>> {
+        AztecRpcClient::call_timings(self)
+    }
+
+    fn send_confirmation(&self) -> Option<&Arc<dyn ConfirmSend>> {
+        AztecRpcClient::send_confirmation(self)
+    }
+
+    fn wallet(&self, address: &str) -> Option<&Arc<dyn AccountWallet>> {
+        AztecRpcClient::wallet(self, address)
+    }
+
+    fn alert(&self, alert: Alert) {
+        AztecRpcClient::alert(self, alert)
+    }
+}
+
+impl<P: PxeApi + ?Sized> PxeApi for Arc<P> {
+    fn call<'a>(
+        &'a self,
+        method: &'a str,
+        params: Vec<Value>,
+    ) -> BoxFuture<'a, Result<Value, AztecError>> {
+        (**self).call(method, params)
+    }
+
+    fn get_block_number(&self) -> BoxFuture<'_, Result<u64, AztecError>> {
+        (**self).get_block_number()
+    }
+
+    fn get_node_info(&self) -> BoxFuture<'_, Result<NodeInfo, AztecError>> {
+        (**self).get_node_info()
+    }
+
+    fn get_block(&self, number: u64) -> BoxFuture<'_, Result<Option<L2Block>, AztecError>> {
+        (**self).get_block(number)
+    }
+
+    fn get_public_storage_at<'a>(
+        &'a self,
+        contract: &'a str,
+        slot: &'a Fr,
+    ) -> BoxFuture<'a, Result<Fr, AztecError>> {
+        (**self).get_public_storage_at(contract, slot)
+    }
+
+    fn get_notes(&self, filter: Value) -> BoxFuture<'_, Result<Value, AztecError>> {
+        (**self).get_notes(filter)
+    }
+
+    fn get_tx_receipt<'a>(&'a self, tx_hash: &'a str) -> BoxFuture<'a, Result<Value, AztecError>> {
+        (**self).get_tx_receipt(tx_hash)
+    }
+
+    fn get_contracts(&self) -> BoxFuture<'_, Result<Vec<String>, AztecError>> {
+        (**self).get_contracts()
+    }
+
+    fn get_contract_metadata<'a>(
+        &'a self,
+        address: &'a AztecAddress,
+    ) -> BoxFuture<'a, Result<Value, AztecError>> {
+        (**self).get_contract_metadata(address)
+    }
+
+    fn get_contract_artifact<'a>(
+        &'a self,
+        class_id: &'a Fr,
+    ) -> BoxFuture<'a, Result<Value, AztecError>> {
+        (**self).get_contract_artifact(class_id)
+    }
+
+    fn register_contract_instance(&self, instance: Value) -> BoxFuture<'_, Result<(), AztecError>> {
+        (**self).register_contract_instance(instance)
+    }
+
+    fn profile(&self) -> PayloadProfile {
+        (**self).profile()
+    }
+
+    fn dry_run(&self) -> bool {
+        (**self).dry_run()
+    }
+
+    fn fee_budget(&self) -> Option<&Arc<FeeBudget>> {
+        (**self).fee_budget()
+    }
+
+    fn gas_profiler(&self) -> Option<&Arc<GasProfiler>> {
+        (**self).gas_profiler()
+    }
+
+    fn tx_journal(&self) -> Option<&Arc<TxJournal>> {
+        (**self).tx_journal()
+    }
+
+    fn simulation_cache(&self) -> Option<&Arc<SimulationCache>> {
+        (**self).simulation_cache()
+    }
+
+    fn sender_pool(&self) -> Option<&Arc<SenderPool>> {
+        (**self).sender_pool()
+    }
+
+    fn call_timings(&self) -> Option<&Arc<CallTimings>> {
+        (**self).call_timings()
+    }
+
+    fn send_confirmation(&self) -> Option<&Arc<dyn ConfirmSend>> {
+        (**self).send_confirmation()
+    }
+
+    fn wallet(&self, address: &str) -> Option<&Arc<dyn AccountWallet>> {
+        (**self).wallet(address)
+    }
+
+    fn alert(&self, alert: Alert) {
+        (**self).alert(alert)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::Contract;
+    use crate::feeds::FeedContract;
+    use crate::testing::{fixtures, test_wallet, MockPxe};
+    use crate::tx_request::DEFAULT_ORIGIN;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    const ADDRESS: &str = "0x0a";
+
+    /// Answers every method from a table, without any HTTP.
+    struct Canned(HashMap<&'static str, Value>, Arc<dyn AccountWallet>);
+
+    impl PxeApi for Canned {
+        fn call<'a>(
+            &'a self,
+            method: &'a str,
+            _params: Vec<Value>,
+        ) -> BoxFuture<'a, Result<Value, AztecError>> {
+            let result = self.0.get(method).cloned().ok_or_else(|| AztecError::Rpc {
+                method: method.to_string(),
+                error: json!({ "message": "not canned" }),
+            });
+            Box::pin(async move { result })
+        }
+
+        fn wallet(&self, _address: &str) -> Option<&Arc<dyn AccountWallet>> {
+            Some(&self.1)
+        }
+    }
+
+    /// Counts calls by method, passing them on.
+    struct Counting<P> {
+        inner: P,
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl<P: PxeApi> PxeApi for Counting<P> {
+        fn call<'a>(
+            &'a self,
+            method: &'a str,
+            params: Vec<Value>,
+        ) -> BoxFuture<'a, Result<Value, AztecError>> {
+            self.calls.lock().unwrap().push(method.to_string());
+            self.inner.call(method, params)
+        }
+
+        fn profile(&self) -> PayloadProfile {
+            self.inner.profile()
+        }
+
+        fn wallet(&self, address: &str) -> Option<&Arc<dyn AccountWallet>> {
+            self.inner.wallet(address)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_contracts_run_on_any_pxe_api() {
+        let canned = Canned(
+            HashMap::from([
+                (
+                    "getNodeInfo",
+                    json!({ "nodeVersion": "0.87.2", "l1ChainId": 31337, "rollupVersion": 1 }),
+                ),
+                ("simulateTx", fixtures().simulate_get.clone()),
+            ]),
+            test_wallet(DEFAULT_ORIGIN),
+        );
+        let feeds = FeedContract::new(Contract::at(
+            &canned,
+            DEFAULT_ORIGIN,
+            ADDRESS,
+            fixtures().artifact.clone(),
+        ));
+        assert_eq!(feeds.get_field().await.unwrap(), Fr::from(700u16));
+        assert!(canned.get_block_number().await.is_err());
+
+        let mock = MockPxe::start().await.unwrap();
+        fixtures().serve_send(&mock);
+        let counting = Counting {
+            inner: mock.client(),
+            calls: Mutex::new(vec![]),
+        };
+        let dynamic: &dyn PxeApi = &counting;
+        let contract = Contract::at(
+            dynamic,
+            DEFAULT_ORIGIN,
+            ADDRESS,
+            fixtures().artifact.clone(),
+        );
+        let tx_hash = contract
+            .method("set_just_field", vec![json!(214)])
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(tx_hash, fixtures().tx_hash);
+        assert_eq!(
+            *counting.calls.lock().unwrap(),
+            ["getNodeInfo", "simulateTx", "proveTx", "sendTx"]
+        );
+    }
+}
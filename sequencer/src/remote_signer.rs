@@ -0,0 +1,337 @@
+use futures_util::future::BoxFuture;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+use std::env;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::fields::Fr;
+use crate::secret::Secret;
+use crate::tx_request::AuthWitness;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// What a digest is signed for. A signer only signs kinds it allows, so a
+/// leaked client credential cannot get arbitrary messages signed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MessageKind {
+    AuthWitness,
+    TxRequest,
+    Attestation,
+}
+
+impl MessageKind {
+    pub fn parse(kind: &str) -> Result<Self, String> {
+        serde_json::from_value(json!(kind.trim()))
+            .map_err(|_| format!("Unknown message kind: {}", kind))
+    }
+}
+
+/// A 64-byte signature, `r || s` or `s || e` depending on the signer's
+/// scheme; account contracts take either one byte per field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteSignature(pub [u8; 64]);
+
+impl RemoteSignature {
+    pub fn to_witness(&self) -> Vec<Fr> {
+        self.0.iter().map(|b| Fr::from(*b)).collect()
+    }
+}
+
+/// Signs digests with a key that never leaves the signer, such as an HSM or
+/// a signing service.
+pub trait RemoteSigner: Send + Sync {
+    fn sign<'a>(
+        &'a self,
+        kind: MessageKind,
+        digest: &'a [u8; 32],
+    ) -> BoxFuture<'a, Result<RemoteSignature, String>>;
+}
+
+/// Has `signer` sign `request_hash` into an auth witness. The signer applies
+/// its own scheme, e.g. SHA-256 before ECDSA.
+pub async fn remote_auth_witness(
+    signer: &dyn RemoteSigner,
+    request_hash: &Fr,
+) -> Result<AuthWitness, String> {
+    let signature = signer
+        .sign(MessageKind::AuthWitness, &request_hash.to_be_bytes())
+        .await?;
+    Ok(AuthWitness {
+        request_hash: request_hash.clone(),
+        witness: signature.to_witness(),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpSignerConfig {
+    pub url: String,
+    pub key_id: String,
+    /// Shared with the service; authenticates each request.
+    pub client_secret: Secret<String>,
+    pub allowed: Vec<MessageKind>,
+    pub timeout: Duration,
+}
+
+impl HttpSignerConfig {
+    pub fn parse(
+        url: &str,
+        key_id: &str,
+        client_secret: &str,
+        allowed: Option<&str>,
+        timeout_secs: Option<&str>,
+    ) -> Result<Self, String> {
+        let url = url.trim_end_matches('/');
+        let parsed = reqwest::Url::parse(url)
+            .map_err(|e| format!("Invalid remote signer URL {}: {}", url, e))?;
+        let local = matches!(parsed.host_str(), Some("127.0.0.1" | "localhost"));
+        if parsed.scheme() != "https" && !(parsed.scheme() == "http" && local) {
+            return Err(format!(
+                "Remote signer URL must be https (or local http): {}",
+                url
+            ));
+        }
+        if client_secret.is_empty() {
+            return Err("Remote signer client secret is empty".to_string());
+        }
+        let allowed = match allowed {
+            Some(allowed) => allowed
+                .split(',')
+                .filter(|kind| !kind.trim().is_empty())
+                .map(MessageKind::parse)
+                .collect::<Result<Vec<_>, _>>()?,
+            None => vec![MessageKind::AuthWitness],
+        };
+        let timeout_secs = match timeout_secs {
+            Some(secs) => secs
+                .parse()
+                .map_err(|_| format!("Invalid remote signer timeout: {}", secs))?,
+            None => 10,
+        };
+        Ok(HttpSignerConfig {
+            url: url.to_string(),
+            key_id: key_id.to_string(),
+            client_secret: client_secret.into(),
+            allowed,
+            timeout: Duration::from_secs(timeout_secs),
+        })
+    }
+
+    /// Reads `REMOTE_SIGNER_URL`, `REMOTE_SIGNER_KEY_ID`,
+    /// `REMOTE_SIGNER_SECRET`, `REMOTE_SIGNER_ALLOW` (comma-separated kinds,
+    /// default `authWitness`) and `REMOTE_SIGNER_TIMEOUT_SECS` (default 10);
+    /// `None` when no URL is set.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Ok(url) = env::var("REMOTE_SIGNER_URL") else {
+            return Ok(None);
+        };
+        let var = |name: &str| env::var(name).map_err(|_| format!("{} is not set", name));
+        Self::parse(
+            &url,
+            &var("REMOTE_SIGNER_KEY_ID")?,
+            &var("REMOTE_SIGNER_SECRET")?,
+            env::var("REMOTE_SIGNER_ALLOW").ok().as_deref(),
+            env::var("REMOTE_SIGNER_TIMEOUT_SECS").ok().as_deref(),
+        )
+        .map(Some)
+    }
+}
+
+/// Calls a signing service at `POST <url>/sign`. Each body carries a
+/// timestamp and nonce, and its HMAC-SHA256 under the client secret goes in
+/// `X-Signer-Auth`, so the service can reject forged or replayed requests.
+/// The service answers `{"signature": "0x<64 bytes>"}`.
+pub struct HttpRemoteSigner {
+    config: HttpSignerConfig,
+    client: reqwest::Client,
+}
+
+impl HttpRemoteSigner {
+    pub fn new(config: HttpSignerConfig) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .timeout(config.timeout)
+            .build()
+            .map_err(|e| format!("Cannot build remote signer client: {}", e))?;
+        Ok(HttpRemoteSigner { config, client })
+    }
+
+    async fn request(
+        &self,
+        kind: MessageKind,
+        digest: &[u8; 32],
+    ) -> Result<RemoteSignature, String> {
+        if !self.config.allowed.contains(&kind) {
+            return Err(format!("Remote signer is not allowed to sign {:?}", kind));
+        }
+        let mut nonce = [0u8; 16];
+        getrandom::getrandom(&mut nonce).map_err(|e| format!("No randomness available: {}", e))?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| e.to_string())?
+            .as_secs();
+        let body = json!({
+            "keyId": self.config.key_id,
+            "kind": kind,
+            "digest": format!("0x{}", hex::encode(digest)),
+            "timestamp": timestamp,
+            "nonce": hex::encode(nonce),
+        })
+        .to_string();
+
+        let response = self
+            .client
+            .post(format!("{}/sign", self.config.url))
+            .header("content-type", "application/json")
+            .header(
+                "X-Signer-Auth",
+                auth_tag(self.config.client_secret.expose(), &body),
+            )
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| format!("Remote signer unreachable: {}", e))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| format!("Remote signer response: {}", e))?;
+        if !status.is_success() {
+            return Err(format!("Remote signer refused ({}): {}", status, text));
+        }
+
+        let reply: serde_json::Value = serde_json::from_str(&text)
+            .map_err(|e| format!("Remote signer response is not JSON: {}", e))?;
+        let signature = reply["signature"]
+            .as_str()
+            .ok_or("Remote signer response has no signature")?;
+        let bytes = hex::decode(signature.trim_start_matches("0x"))
+            .map_err(|e| format!("Remote signature is not hex: {}", e))?;
+        let bytes: [u8; 64] = bytes.try_into().map_err(|bytes: Vec<u8>| {
+            format!("Remote signature has {} bytes, not 64", bytes.len())
+        })?;
+        Ok(RemoteSignature(bytes))
+    }
+}
+
+impl RemoteSigner for HttpRemoteSigner {
+    fn sign<'a>(
+        &'a self,
+        kind: MessageKind,
+        digest: &'a [u8; 32],
+    ) -> BoxFuture<'a, Result<RemoteSignature, String>> {
+        Box::pin(self.request(kind, digest))
+    }
+}
+
+/// The `X-Signer-Auth` value for `body`.
+pub fn auth_tag(client_secret: &str, body: &str) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(client_secret.as_bytes()).expect("HMAC takes any key length");
+    mac.update(body.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::post;
+    use axum::Router;
+    use k256::ecdsa::signature::hazmat::{PrehashSigner, PrehashVerifier};
+    use k256::ecdsa::{Signature, SigningKey};
+    use tokio::net::TcpListener;
+
+    const SECRET: &str = "shared-secret";
+
+    // A signing service holding an ECDSA key, checking the auth tag.
+    async fn start_service(key: SigningKey) -> String {
+        let app = Router::new().route(
+            "/sign",
+            post(move |headers: HeaderMap, body: String| async move {
+                let tag = headers["X-Signer-Auth"].to_str().unwrap();
+                if tag != auth_tag(SECRET, &body) {
+                    return (StatusCode::UNAUTHORIZED, "bad auth".to_string());
+                }
+                let request: serde_json::Value = serde_json::from_str(&body).unwrap();
+                assert_eq!(request["keyId"], "seq-1");
+                let digest = hex::decode(&request["digest"].as_str().unwrap()[2..]).unwrap();
+                let signature: Signature = key.sign_prehash(&digest).unwrap();
+                let reply =
+                    json!({ "signature": format!("0x{}", hex::encode(signature.to_bytes())) });
+                (StatusCode::OK, reply.to_string())
+            }),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_signs_through_the_service() {
+        let key = SigningKey::from_slice(&[7u8; 32]).unwrap();
+        let url = start_service(key.clone()).await;
+        let signer = HttpRemoteSigner::new(
+            HttpSignerConfig::parse(&url, "seq-1", SECRET, None, None).unwrap(),
+        )
+        .unwrap();
+
+        let request_hash = Fr::from(0x1234u64);
+        let witness = remote_auth_witness(&signer, &request_hash).await.unwrap();
+        assert_eq!(witness.witness.len(), 64);
+        let bytes: Vec<u8> = witness
+            .witness
+            .iter()
+            .map(|f| f.to_u64().unwrap() as u8)
+            .collect();
+        key.verifying_key()
+            .verify_prehash(
+                &request_hash.to_be_bytes(),
+                &Signature::from_slice(&bytes).unwrap(),
+            )
+            .unwrap();
+
+        let err = signer
+            .sign(MessageKind::TxRequest, &[0; 32])
+            .await
+            .unwrap_err();
+        assert!(err.contains("not allowed"), "{}", err);
+
+        let forged = HttpRemoteSigner::new(
+            HttpSignerConfig::parse(&url, "seq-1", "guess", None, None).unwrap(),
+        )
+        .unwrap();
+        let err = forged
+            .sign(MessageKind::AuthWitness, &[0; 32])
+            .await
+            .unwrap_err();
+        assert!(err.contains("401"), "{}", err);
+    }
+
+    #[test]
+    fn test_config_parsing() {
+        let config = HttpSignerConfig::parse(
+            "https://signer.internal/",
+            "seq-1",
+            SECRET,
+            Some("authWitness, attestation"),
+            Some("3"),
+        )
+        .unwrap();
+        assert_eq!(config.url, "https://signer.internal");
+        assert_eq!(
+            config.allowed,
+            [MessageKind::AuthWitness, MessageKind::Attestation]
+        );
+        assert_eq!(config.timeout, Duration::from_secs(3));
+
+        assert!(
+            HttpSignerConfig::parse("http://signer.internal", "k", SECRET, None, None).is_err()
+        );
+        assert!(HttpSignerConfig::parse("https://s", "k", "", None, None).is_err());
+        assert!(HttpSignerConfig::parse("https://s", "k", SECRET, Some("anything"), None).is_err());
+        assert!(HttpSignerConfig::parse("https://s", "k", SECRET, None, Some("soon")).is_err());
+    }
+}
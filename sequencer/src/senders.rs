@@ -0,0 +1,289 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::env;
+use std::sync::Mutex;
+
+use crate::fields::Fr;
+
+const DEFAULT_MAX_PENDING: usize = 4;
+
+/// An account the pool may send from, and the contracts it may call
+/// (`None` for any).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SenderAccount {
+    pub address: String,
+    pub contracts: Option<Vec<String>>,
+}
+
+impl SenderAccount {
+    fn may_call(&self, contract: &str) -> bool {
+        match &self.contracts {
+            Some(contracts) => contracts.iter().any(|c| c.eq_ignore_ascii_case(contract)),
+            None => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SenderPoolConfig {
+    pub accounts: Vec<SenderAccount>,
+    /// Unmined txs an account may have before the pool skips it.
+    pub max_pending: usize,
+}
+
+impl SenderPoolConfig {
+    /// Parses `0xaa,0xbb=0xc1|0xc2`: comma-separated accounts, each
+    /// optionally limited to `|`-separated contracts.
+    pub fn parse(accounts: &str, max_pending: Option<&str>) -> Result<Self, String> {
+        let accounts = accounts
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (address, contracts) = match entry.split_once('=') {
+                    Some((address, contracts)) => (address, Some(contracts)),
+                    None => (entry, None),
+                };
+                let address = checked_address(address)?;
+                let contracts = contracts
+                    .map(|contracts| contracts.split('|').map(checked_address).collect())
+                    .transpose()?;
+                Ok(SenderAccount { address, contracts })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        if accounts.is_empty() {
+            return Err("Sender pool has no accounts".to_string());
+        }
+        let max_pending = match max_pending {
+            Some(max) => max
+                .parse()
+                .ok()
+                .filter(|max| *max > 0)
+                .ok_or_else(|| format!("Invalid max pending txs per sender: {}", max))?,
+            None => DEFAULT_MAX_PENDING,
+        };
+        Ok(SenderPoolConfig {
+            accounts,
+            max_pending,
+        })
+    }
+
+    /// Reads `SENDER_POOL` and `SENDER_POOL_MAX_PENDING`; `None` when
+    /// `SENDER_POOL` is unset. Each account needs a wallet: the `ACCOUNT_*`
+    /// one, or its key in the keystore (`Keystore::wallet_config_for`).
+    pub fn from_env() -> Result<Option<Self>, String> {
+        match env::var("SENDER_POOL") {
+            Ok(accounts) => Self::parse(
+                &accounts,
+                env::var("SENDER_POOL_MAX_PENDING").ok().as_deref(),
+            )
+            .map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+fn checked_address(address: &str) -> Result<String, String> {
+    let address = address.trim();
+    Fr::try_from(address).map_err(|e| format!("Invalid address '{}': {}", address, e))?;
+    Ok(address.to_lowercase())
+}
+
+// Reservations by id: the account's index, and the tx hash once the send
+// went through (`None` while it is in flight).
+#[derive(Debug, Default)]
+struct PoolState {
+    next_id: u64,
+    pending: HashMap<u64, (usize, Option<String>)>,
+    // Where the next search starts, so equally loaded accounts take turns.
+    cursor: usize,
+}
+
+/// Spreads sends over several accounts so they don't queue behind one
+/// origin. Each send reserves the authorized account with the fewest
+/// unmined txs; a receipt for one of its txs frees the slot again.
+#[derive(Debug)]
+pub struct SenderPool {
+    config: SenderPoolConfig,
+    state: Mutex<PoolState>,
+}
+
+impl SenderPool {
+    pub fn new(config: SenderPoolConfig) -> Self {
+        SenderPool {
+            config,
+            state: Mutex::new(PoolState::default()),
+        }
+    }
+
+    pub fn config(&self) -> &SenderPoolConfig {
+        &self.config
+    }
+
+    /// Picks the account for a call to `contract` and returns the
+    /// reservation id with its address.
+    pub fn reserve(&self, contract: &str) -> Result<(u64, String), String> {
+        let mut state = self.state.lock().unwrap();
+        let accounts = &self.config.accounts;
+        let load = |index: usize, state: &PoolState| {
+            state.pending.values().filter(|(i, _)| *i == index).count()
+        };
+
+        let mut authorized = 0;
+        let mut best: Option<(usize, usize)> = None;
+        for offset in 0..accounts.len() {
+            let index = (state.cursor + offset) % accounts.len();
+            if !accounts[index].may_call(contract) {
+                continue;
+            }
+            authorized += 1;
+            let load = load(index, &state);
+            if load < self.config.max_pending && best.is_none_or(|(_, least)| load < least) {
+                best = Some((index, load));
+            }
+        }
+        let Some((index, _)) = best else {
+            return Err(match authorized {
+                0 => format!("No sender account may call {}", contract),
+                n => format!(
+                    "All {} sender accounts for {} have {} pending txs",
+                    n, contract, self.config.max_pending
+                ),
+            });
+        };
+
+        state.cursor = (index + 1) % accounts.len();
+        state.next_id += 1;
+        let id = state.next_id;
+        state.pending.insert(id, (index, None));
+        Ok((id, accounts[index].address.clone()))
+    }
+
+    /// Ties the reservation to the tx it sent.
+    pub fn assign(&self, id: u64, tx_hash: &str) {
+        if let Some((_, tx)) = self.state.lock().unwrap().pending.get_mut(&id) {
+            *tx = Some(tx_hash.to_lowercase());
+        }
+    }
+
+    /// Drops a reservation whose tx was never sent.
+    pub fn release(&self, id: u64) {
+        self.state.lock().unwrap().pending.remove(&id);
+    }
+
+    /// Frees the slot of the receipt's tx once it is mined, reverted or
+    /// dropped.
+    pub fn settle(&self, receipt: &Value) {
+        let (Some(tx_hash), Some(status)) =
+            (receipt["txHash"].as_str(), receipt["status"].as_str())
+        else {
+            return;
+        };
+        if status == "pending" {
+            return;
+        }
+        self.cancel(tx_hash);
+    }
+
+    /// Frees the slot of a tx given up on before its receipt is final.
+    pub fn cancel(&self, tx_hash: &str) {
+        let tx_hash = tx_hash.to_lowercase();
+        self.state
+            .lock()
+            .unwrap()
+            .pending
+            .retain(|_, (_, tx)| tx.as_deref() != Some(tx_hash.as_str()));
+    }
+
+    /// Reservations held by `address`, in flight or unmined.
+    pub fn pending(&self, address: &str) -> usize {
+        let state = self.state.lock().unwrap();
+        state
+            .pending
+            .values()
+            .filter(|(index, _)| {
+                self.config.accounts[*index]
+                    .address
+                    .eq_ignore_ascii_case(address)
+            })
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn pool(accounts: &str, max_pending: &str) -> SenderPool {
+        SenderPool::new(SenderPoolConfig::parse(accounts, Some(max_pending)).unwrap())
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config = SenderPoolConfig::parse(" 0xAA, 0xbb=0xc1|0xC2 ,", None).unwrap();
+        assert_eq!(config.max_pending, DEFAULT_MAX_PENDING);
+        assert_eq!(
+            config.accounts,
+            vec![
+                SenderAccount {
+                    address: "0xaa".to_string(),
+                    contracts: None,
+                },
+                SenderAccount {
+                    address: "0xbb".to_string(),
+                    contracts: Some(vec!["0xc1".to_string(), "0xc2".to_string()]),
+                },
+            ]
+        );
+
+        assert!(SenderPoolConfig::parse("", None).is_err());
+        assert!(SenderPoolConfig::parse("0xaa=nope", None).is_err());
+        assert!(SenderPoolConfig::parse("0xaa", Some("0")).is_err());
+    }
+
+    #[test]
+    fn test_rotates_between_idle_accounts() {
+        let pool = pool("0x01,0x02,0x03", "4");
+        let senders: Vec<String> = (0..4)
+            .map(|_| {
+                let (id, sender) = pool.reserve("0xc1").unwrap();
+                pool.release(id);
+                sender
+            })
+            .collect();
+        assert_eq!(senders, ["0x01", "0x02", "0x03", "0x01"]);
+    }
+
+    #[test]
+    fn test_prefers_least_loaded_and_frees_on_receipt() {
+        let pool = pool("0x01,0x02", "2");
+        let (a, first) = pool.reserve("0xc1").unwrap();
+        pool.assign(a, "0xAAA");
+        let (b, second) = pool.reserve("0xc1").unwrap();
+        pool.assign(b, "0xbbb");
+        let (_, third) = pool.reserve("0xc1").unwrap();
+        assert_eq!((first.as_str(), second.as_str()), ("0x01", "0x02"));
+        assert_eq!(pool.pending(&third), 2);
+
+        // 0x01 is full now; 0x02 has one slot left.
+        assert_eq!(pool.reserve("0xc1").unwrap().1, "0x02");
+        let full = pool.reserve("0xc1").unwrap_err();
+        assert!(full.contains("2 pending"), "{}", full);
+
+        pool.settle(&json!({ "txHash": "0xaaa", "status": "pending" }));
+        assert!(pool.reserve("0xc1").is_err());
+        pool.settle(&json!({ "txHash": "0xaaa", "status": "success" }));
+        assert_eq!(pool.reserve("0xc1").unwrap().1, "0x01");
+    }
+
+    #[test]
+    fn test_only_authorized_accounts_send() {
+        let pool = pool("0x01=0xc1,0x02=0xc2", "4");
+        for _ in 0..3 {
+            assert_eq!(pool.reserve("0xC2").unwrap().1, "0x02");
+        }
+        let err = pool.reserve("0xc3").unwrap_err();
+        assert!(err.contains("No sender account"), "{}", err);
+    }
+}
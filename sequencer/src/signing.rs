@@ -0,0 +1,298 @@
+use blake2::Blake2s256;
+use num_bigint::BigUint;
+use num_traits::Zero;
+use sha2::{Digest, Sha256};
+
+use crate::curves::grumpkin::{self, AffinePoint};
+use crate::fields::Fr;
+use crate::pedersen::pedersen_hash;
+use crate::secret::{Secret, Zeroizing};
+
+/// The challenge hash of a Schnorr signature. `PedersenBlake2s` is the one
+/// account contracts check.
+pub trait SchnorrChallenge {
+    fn challenge(&self, r_x: &Fr, public_key: &AffinePoint, message: &[u8; 32]) -> [u8; 32];
+}
+
+impl<H: SchnorrChallenge + ?Sized> SchnorrChallenge for Box<H> {
+    fn challenge(&self, r_x: &Fr, public_key: &AffinePoint, message: &[u8; 32]) -> [u8; 32] {
+        (**self).challenge(r_x, public_key, message)
+    }
+}
+
+/// The challenge Noir's `schnorr::verify_signature` recomputes:
+/// `blake2s(pedersen_hash([r_x, pk.x, pk.y]) || message)`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PedersenBlake2s;
+
+impl SchnorrChallenge for PedersenBlake2s {
+    fn challenge(&self, r_x: &Fr, public_key: &AffinePoint, message: &[u8; 32]) -> [u8; 32] {
+        let hash = pedersen_hash(&[r_x.clone(), public_key.x.clone(), public_key.y.clone()]);
+        Blake2s256::new()
+            .chain_update(hash.to_be_bytes())
+            .chain_update(message)
+            .finalize()
+            .into()
+    }
+}
+
+/// A signature as account contracts take it: `s || e`, 32 big-endian bytes
+/// each.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchnorrSignature {
+    pub s: [u8; 32],
+    pub e: [u8; 32],
+}
+
+impl SchnorrSignature {
+    pub fn to_bytes(&self) -> [u8; 64] {
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(&self.s);
+        bytes[32..].copy_from_slice(&self.e);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() != 64 {
+            return Err(format!(
+                "Schnorr signature needs 64 bytes, got {}",
+                bytes.len()
+            ));
+        }
+        let mut signature = SchnorrSignature {
+            s: [0; 32],
+            e: [0; 32],
+        };
+        signature.s.copy_from_slice(&bytes[..32]);
+        signature.e.copy_from_slice(&bytes[32..]);
+        Ok(signature)
+    }
+
+    /// One field per byte, the way a Schnorr account reads its auth witness.
+    pub fn to_witness(&self) -> Vec<Fr> {
+        self.to_bytes().iter().map(|b| Fr::from(*b)).collect()
+    }
+
+    pub fn from_witness(fields: &[Fr]) -> Result<Self, String> {
+        let bytes = fields
+            .iter()
+            .map(|field| {
+                u8::try_from(&field.0)
+                    .map_err(|_| format!("Witness field {} is not a byte", field.to_hex()))
+            })
+            .collect::<Result<Vec<u8>, String>>()?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+/// A Grumpkin secret key and its public key. The secret is held as
+/// big-endian bytes so it is cleared on drop.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SchnorrKeyPair {
+    secret: Secret<[u8; 32]>,
+    public_key: AffinePoint,
+}
+
+impl SchnorrKeyPair {
+    pub fn from_secret(secret: &BigUint) -> Result<Self, String> {
+        let secret = Zeroizing::new(Fr(secret % grumpkin::order()));
+        let public_key = grumpkin::mul(&AffinePoint::generator(), &secret.0)
+            .ok_or("Signing secret is zero modulo the curve order")?;
+        Ok(SchnorrKeyPair {
+            secret: Secret::new(secret.to_be_bytes()),
+            public_key,
+        })
+    }
+
+    pub fn public_key(&self) -> &AffinePoint {
+        &self.public_key
+    }
+}
+
+impl std::fmt::Debug for SchnorrKeyPair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SchnorrKeyPair")
+            .field("public_key", &self.public_key)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Aztec's Schnorr scheme over Grumpkin: `R = k·G`, `e = H(R.x, pk, m)`,
+/// `s = k − e·x`; a signature verifies when `s·G + e·pk` hashes back to `e`.
+pub struct Schnorr<H> {
+    hasher: H,
+}
+
+impl<H: SchnorrChallenge> Schnorr<H> {
+    pub fn new(hasher: H) -> Self {
+        Schnorr { hasher }
+    }
+
+    /// Signs a 32-byte message hash. The nonce is derived from the secret
+    /// and the message, so signing is deterministic and needs no RNG.
+    pub fn sign(&self, key: &SchnorrKeyPair, message_hash: &Fr) -> SchnorrSignature {
+        let order = grumpkin::order();
+        let secret = Zeroizing::new(Fr(BigUint::from_bytes_be(key.secret.expose())));
+        let message = message_hash.to_be_bytes();
+        let mut counter = 0u64;
+        loop {
+            let digest: Zeroizing<[u8; 32]> = Zeroizing::new(
+                Sha256::new()
+                    .chain_update(key.secret.expose())
+                    .chain_update(message)
+                    .chain_update(counter.to_be_bytes())
+                    .finalize()
+                    .into(),
+            );
+            counter += 1;
+            let nonce = Zeroizing::new(Fr(BigUint::from_bytes_be(&*digest) % &order));
+            let Some(r) = grumpkin::mul(&AffinePoint::generator(), &nonce.0) else {
+                continue;
+            };
+            let e = self.hasher.challenge(&r.x, key.public_key(), &message);
+            let e_scalar = BigUint::from_bytes_be(&e) % &order;
+            // e·x gives away the secret and n + k the nonce; only s is public.
+            let product = Zeroizing::new(Fr(e_scalar * &secret.0));
+            let e_secret = Zeroizing::new(Fr(&product.0 % &order));
+            let mut unreduced = Zeroizing::new(Fr(&order + &nonce.0));
+            unreduced.0 -= &e_secret.0;
+            let s = &unreduced.0 % &order;
+            if s.is_zero() {
+                continue;
+            }
+            return SchnorrSignature {
+                s: Fr(s).to_be_bytes(),
+                e,
+            };
+        }
+    }
+
+    /// `R = s·G + e·pk` must not be infinity, and hashing it with the key
+    /// and message must give `e` back.
+    pub fn verify(
+        &self,
+        public_key: &AffinePoint,
+        message_hash: &Fr,
+        signature: &SchnorrSignature,
+    ) -> Result<(), String> {
+        if !public_key.is_on_curve() {
+            return Err(format!(
+                "Public key ({}, {}) is not on Grumpkin",
+                public_key.x.to_hex(),
+                public_key.y.to_hex()
+            ));
+        }
+        let order = grumpkin::order();
+        let s = BigUint::from_bytes_be(&signature.s) % &order;
+        let e = BigUint::from_bytes_be(&signature.e) % &order;
+        let r = AffinePoint::generator()
+            .to_projective()
+            .mul(&s)
+            .add(&public_key.to_projective().mul(&e))
+            .to_affine()
+            .ok_or("Invalid signature: R is the point at infinity")?;
+
+        let message = message_hash.to_be_bytes();
+        if self.hasher.challenge(&r.x, public_key, &message) != signature.e {
+            return Err(format!(
+                "Invalid signature over {} for key {}",
+                message_hash.to_hex(),
+                public_key.x.to_hex()
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fr(hex: &str) -> Fr {
+        Fr::try_from(hex).unwrap()
+    }
+
+    // Computed with an independent model of barretenberg's Pedersen hash
+    // (checked against its published vectors) and Python's `blake2s`.
+    #[test]
+    fn test_challenge_is_pedersen_then_blake2s() {
+        let key = SchnorrKeyPair::from_secret(&BigUint::from(0xabcdefu64)).unwrap();
+        assert_eq!(
+            key.public_key(),
+            &AffinePoint::new(
+                fr("0x2f9ea3ceffb1c5758707ca48435d64ed1ff4567ae7b815cf12acc2b83a7ed908"),
+                fr("0x2e05a265a7fee987949e9d56adf499db4d46b5cd2492d8f0dfaba1a060f10f5d"),
+            )
+            .unwrap()
+        );
+        let r = grumpkin::mul(&AffinePoint::generator(), &BigUint::from(7u8)).unwrap();
+        assert_eq!(
+            r.x,
+            fr("0x0e602b9dd6a3e8d039a17f069add3f9c2a187a8f629a1de60a33a8067b9b2842")
+        );
+        assert_eq!(
+            pedersen_hash(&[
+                r.x.clone(),
+                key.public_key().x.clone(),
+                key.public_key().y.clone()
+            ]),
+            fr("0x233dcad9fc61979076dbc5b0b6967f585dfc90d67dca35161d1e57ed9e09d29f")
+        );
+        let e =
+            PedersenBlake2s.challenge(&r.x, key.public_key(), &Fr::from(0x1234u64).to_be_bytes());
+        assert_eq!(
+            hex::encode(e),
+            "92f9d34cdd4a66958d4f960fa0d9256d964366e340e0817bd063f296f0c0a333"
+        );
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let schnorr = Schnorr::new(PedersenBlake2s);
+        let key = SchnorrKeyPair::from_secret(&BigUint::from(0xabcdefu64)).unwrap();
+        let message = Fr::from(0x1234u64);
+        let signature = schnorr.sign(&key, &message);
+        assert_eq!(signature, schnorr.sign(&key, &message));
+        assert_eq!(
+            schnorr.verify(key.public_key(), &message, &signature),
+            Ok(())
+        );
+
+        assert!(schnorr
+            .verify(key.public_key(), &Fr::from(0x1235u64), &signature)
+            .is_err());
+        let other = SchnorrKeyPair::from_secret(&BigUint::from(7u8)).unwrap();
+        assert!(schnorr
+            .verify(other.public_key(), &message, &signature)
+            .is_err());
+        let mut tampered = signature.clone();
+        tampered.s[31] ^= 1;
+        assert!(schnorr
+            .verify(key.public_key(), &message, &tampered)
+            .is_err());
+
+        assert!(SchnorrKeyPair::from_secret(&grumpkin::order()).is_err());
+    }
+
+    #[test]
+    fn test_signature_encodings() {
+        let signature = Schnorr::new(PedersenBlake2s).sign(
+            &SchnorrKeyPair::from_secret(&BigUint::from(42u8)).unwrap(),
+            &Fr::from(99u8),
+        );
+        let bytes = signature.to_bytes();
+        assert_eq!(&bytes[..32], &signature.s);
+        assert_eq!(SchnorrSignature::from_bytes(&bytes), Ok(signature.clone()));
+        assert!(SchnorrSignature::from_bytes(&bytes[1..]).is_err());
+
+        let witness = signature.to_witness();
+        assert_eq!(witness.len(), 64);
+        assert_eq!(witness[63], Fr::from(signature.e[31]));
+        assert_eq!(SchnorrSignature::from_witness(&witness), Ok(signature));
+
+        let mut too_big = witness.clone();
+        too_big[0] = Fr::from(256u32);
+        assert!(SchnorrSignature::from_witness(&too_big).is_err());
+        assert!(SchnorrSignature::from_witness(&witness[..63]).is_err());
+    }
+}
@@ -0,0 +1,229 @@
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::env;
+use std::sync::Mutex;
+
+use crate::encoder::FunctionSelector;
+
+/// What a cached simulation is keyed by.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SimulationKey {
+    pub contract: String,
+    pub selector: FunctionSelector,
+    /// SHA-256 of the arguments, the sender and the simulate options, all of
+    /// which can change the result.
+    pub args_hash: [u8; 32],
+    pub block: u64,
+}
+
+impl SimulationKey {
+    pub fn new(contract: &str, selector: FunctionSelector, request: &Value, block: u64) -> Self {
+        let args_hash = Sha256::digest(request.to_string().as_bytes()).into();
+        SimulationKey {
+            contract: contract.to_lowercase(),
+            selector,
+            args_hash,
+            block,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    /// The latest block a `BlockWatcher` reported.
+    block: Option<u64>,
+    /// Bumped on every use; the entry with the lowest goes first.
+    clock: u64,
+    entries: HashMap<SimulationKey, (Value, u64)>,
+    hits: u64,
+    misses: u64,
+}
+
+/// Results of `ContractFunctionInteraction::simulate`, so identical view
+/// calls in the same block reach the PXE once. Holds at most `capacity`
+/// results, evicting the least recently used.
+///
+/// A `BlockWatcher` polling through the same client moves the cache to each
+/// new block, dropping older results. Without one, every simulation asks
+/// for the block number to key its result by.
+#[derive(Debug)]
+pub struct SimulationCache {
+    capacity: usize,
+    state: Mutex<CacheState>,
+}
+
+impl SimulationCache {
+    pub fn new(capacity: usize) -> Self {
+        SimulationCache {
+            capacity: capacity.max(1),
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    /// Reads `SIMULATION_CACHE_SIZE`; `None` when it is unset.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        match env::var("SIMULATION_CACHE_SIZE") {
+            Ok(size) => size
+                .parse()
+                .map(|size| Some(SimulationCache::new(size)))
+                .map_err(|_| format!("Invalid SIMULATION_CACHE_SIZE: {}", size)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// The block results are being cached for, once a watcher reported one.
+    pub fn block(&self) -> Option<u64> {
+        self.lock().block
+    }
+
+    /// Moves to block `number`, dropping results of earlier blocks.
+    pub fn new_block(&self, number: u64) {
+        let mut state = self.lock();
+        if state.block != Some(number) {
+            state.block = Some(number);
+            state.entries.retain(|key, _| key.block >= number);
+        }
+    }
+
+    pub fn get(&self, key: &SimulationKey) -> Option<Value> {
+        let mut state = self.lock();
+        state.clock += 1;
+        let clock = state.clock;
+        let found = state.entries.get_mut(key).map(|(value, used)| {
+            *used = clock;
+            value.clone()
+        });
+        match found {
+            Some(_) => state.hits += 1,
+            None => state.misses += 1,
+        }
+        found
+    }
+
+    pub fn insert(&self, key: SimulationKey, value: Value) {
+        let mut state = self.lock();
+        if state.block.is_some_and(|block| key.block < block) {
+            return;
+        }
+        if state.entries.len() >= self.capacity && !state.entries.contains_key(&key) {
+            let oldest = state
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                state.entries.remove(&oldest);
+            }
+        }
+        state.clock += 1;
+        let clock = state.clock;
+        state.entries.insert(key, (value, clock));
+    }
+
+    pub fn clear(&self) {
+        self.lock().entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Lookups answered from the cache and those that went to the PXE.
+    pub fn hits_and_misses(&self) -> (u64, u64) {
+        let state = self.lock();
+        (state.hits, state.misses)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheState> {
+        self.state.lock().expect("simulation cache lock poisoned")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::{Contract, SimulateOptions};
+    use crate::testing::{fixtures, MockPxe};
+    use crate::tx_request::DEFAULT_ORIGIN;
+    use crate::watcher::BlockWatcher;
+    use serde_json::json;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn key(arg: u8, block: u64) -> SimulationKey {
+        SimulationKey::new(
+            "0xAB",
+            FunctionSelector("0x01020304".to_string()),
+            &json!([arg]),
+            block,
+        )
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used_and_old_blocks() {
+        let cache = SimulationCache::new(2);
+        cache.insert(key(1, 5), json!(1));
+        cache.insert(key(2, 5), json!(2));
+        assert_eq!(cache.get(&key(1, 5)), Some(json!(1)));
+        cache.insert(key(3, 5), json!(3));
+        assert_eq!(cache.get(&key(2, 5)), None);
+        assert_eq!(cache.get(&key(1, 5)), Some(json!(1)));
+        assert_eq!(cache.hits_and_misses(), (2, 1));
+
+        cache.insert(key(1, 6), json!(10));
+        cache.new_block(6);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(&key(1, 6)), Some(json!(10)));
+        cache.insert(key(4, 5), json!(4));
+        assert_eq!(cache.get(&key(4, 5)), None);
+    }
+
+    #[tokio::test]
+    async fn test_simulate_hits_cache_until_the_watcher_sees_a_block() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond("pxe_getBlockNumber", json!(5));
+        mock.respond("pxe_simulateTx", fixtures().simulate_get.clone());
+        let cache = Arc::new(SimulationCache::new(8));
+        let pxe = mock.client().with_simulation_cache(cache.clone());
+        let contract = Contract::at(&pxe, DEFAULT_ORIGIN, "0x0a", fixtures().artifact.clone());
+        let get = contract
+            .method("get_just_field", Vec::<Value>::new())
+            .unwrap();
+        let simulations = || {
+            mock.requests()
+                .iter()
+                .filter(|r| r["method"] == "pxe_simulateTx")
+                .count()
+        };
+
+        let first = get.simulate(SimulateOptions::default()).await.unwrap();
+        assert_eq!(
+            get.simulate(SimulateOptions::default()).await.unwrap(),
+            first
+        );
+        assert_eq!(simulations(), 1);
+        let bypass = SimulateOptions {
+            bypass_cache: true,
+            ..SimulateOptions::default()
+        };
+        get.simulate(bypass).await.unwrap();
+        assert_eq!(simulations(), 2);
+
+        let watcher = BlockWatcher::new(pxe.clone(), Duration::from_secs(1));
+        watcher.poll().await.unwrap();
+        assert_eq!(cache.block(), Some(5));
+        // With the block known, a hit makes no PXE requests at all.
+        let requests = mock.requests().len();
+        get.simulate(SimulateOptions::default()).await.unwrap();
+        assert_eq!(mock.requests().len(), requests);
+
+        cache.new_block(6);
+        get.simulate(SimulateOptions::default()).await.unwrap();
+        assert_eq!(simulations(), 3);
+    }
+}
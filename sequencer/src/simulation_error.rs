@@ -0,0 +1,260 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::debug_info::source_locations;
+pub use crate::debug_info::SourceLocation;
+use crate::encoder::{get_function_artifact, ContractArtifact};
+use crate::error::AztecError;
+
+/// One function on the failing call path, outermost first. `locations` is
+/// the Noir call stack inside the innermost function, empty elsewhere or
+/// when the artifact has no debug symbols.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallFrame {
+    pub contract: String,
+    pub address: String,
+    pub function: String,
+    pub locations: Vec<SourceLocation>,
+}
+
+/// A failed `simulateTx`, from the `SimulationError` the PXE attaches to
+/// its JSON-RPC error, with names and source locations resolved from the
+/// contracts' artifacts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulationError {
+    pub message: String,
+    pub frames: Vec<CallFrame>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawSimulationError {
+    original_message: String,
+    #[serde(default)]
+    function_error_stack: Vec<FailingFunction>,
+    #[serde(default)]
+    noir_error_stack: Vec<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FailingFunction {
+    contract_address: String,
+    #[serde(default)]
+    contract_name: Option<String>,
+    #[serde(default)]
+    function_selector: Option<String>,
+    #[serde(default)]
+    function_name: Option<String>,
+}
+
+impl SimulationError {
+    /// `None` unless `error` is a PXE error carrying a simulation failure.
+    /// `artifacts` looks contracts up by address.
+    pub fn from_error(
+        error: &(dyn std::error::Error + 'static),
+        artifacts: impl Fn(&str) -> Option<Arc<ContractArtifact>>,
+    ) -> Option<Self> {
+        match error.downcast_ref::<AztecError>()? {
+            AztecError::Rpc { error, .. } => Self::from_rpc_error(error, artifacts),
+            AztecError::Simulation { failure, .. } => Some(failure.clone()),
+            _ => None,
+        }
+    }
+
+    /// Parses the JSON-RPC `error` object (or its `data`).
+    pub fn from_rpc_error(
+        error: &Value,
+        artifacts: impl Fn(&str) -> Option<Arc<ContractArtifact>>,
+    ) -> Option<Self> {
+        let data = if error["data"]["originalMessage"].is_string() {
+            &error["data"]
+        } else {
+            error
+        };
+        let raw: RawSimulationError = serde_json::from_value(data.clone()).ok()?;
+        let opcodes: Vec<String> = raw
+            .noir_error_stack
+            .iter()
+            .map(|opcode| match opcode {
+                Value::String(opcode) => opcode.clone(),
+                other => other.to_string(),
+            })
+            .collect();
+
+        let depth = raw.function_error_stack.len();
+        let frames = raw
+            .function_error_stack
+            .into_iter()
+            .enumerate()
+            .map(|(i, failing)| {
+                let artifact = artifacts(&failing.contract_address);
+                let function = artifact.as_deref().and_then(|artifact| {
+                    let key = failing
+                        .function_name
+                        .as_deref()
+                        .or(failing.function_selector.as_deref())?;
+                    get_function_artifact(artifact, key).ok()
+                });
+                let locations = match (&artifact, function) {
+                    (Some(artifact), Some(function)) if i + 1 == depth => {
+                        source_locations(artifact, function, &opcodes)
+                    }
+                    _ => vec![],
+                };
+                CallFrame {
+                    contract: failing
+                        .contract_name
+                        .or_else(|| artifact.as_ref().map(|a| a.name.clone()))
+                        .unwrap_or_else(|| "<unknown contract>".to_string()),
+                    function: function
+                        .map(|f| f.name.clone())
+                        .or(failing.function_name)
+                        .or(failing.function_selector)
+                        .unwrap_or_else(|| "<unknown function>".to_string()),
+                    address: failing.contract_address,
+                    locations,
+                }
+            })
+            .collect();
+
+        Some(SimulationError {
+            message: raw.original_message,
+            frames,
+        })
+    }
+
+    /// Where the innermost function failed, if its source is known.
+    pub fn location(&self) -> Option<&SourceLocation> {
+        self.frames.last()?.locations.last()
+    }
+
+    /// The failure as an indented call tree, innermost call last, followed
+    /// by the Noir source lines it went through.
+    pub fn render(&self) -> String {
+        let mut out = format!("Simulation failed: {}\n", self.message);
+        for (depth, frame) in self.frames.iter().enumerate() {
+            let indent = "   ".repeat(depth);
+            out.push_str(&format!(
+                "{}└─ {}.{} ({})\n",
+                indent, frame.contract, frame.function, frame.address
+            ));
+            for location in &frame.locations {
+                out.push_str(&format!(
+                    "{}     at {}  {}\n",
+                    indent, location, location.text
+                ));
+            }
+        }
+        out
+    }
+
+    /// Emits one `tracing` error event per frame, outermost first, with the
+    /// contract, function and innermost source location as fields.
+    pub fn trace(&self) {
+        for (depth, frame) in self.frames.iter().enumerate() {
+            let location = frame
+                .locations
+                .last()
+                .map(|l| l.to_string())
+                .unwrap_or_default();
+            tracing::error!(
+                depth,
+                contract = %frame.contract,
+                address = %frame.address,
+                function = %frame.function,
+                location = %location,
+                "{}",
+                self.message
+            );
+        }
+    }
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render())
+    }
+}
+
+impl std::error::Error for SimulationError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::debug_info::tests::{set_debug_symbols, SOURCE};
+    use crate::encoder::parse_contract_artifact;
+    use serde_json::json;
+
+    fn artifact() -> Arc<ContractArtifact> {
+        let artifact = json!({
+            "name": "Main",
+            "functions": [{
+                "name": "set_just_field",
+                "parameters": [{ "name": "value", "type": { "kind": "field" } }],
+                "bytecode": "",
+                "debugSymbols": set_debug_symbols(),
+                "functionType": "public",
+            }],
+            "nonDispatchPublicFunctions": [],
+            "storageLayout": {},
+            "notes": {},
+            "fileMap": { "3": { "path": "src/main.nr", "source": SOURCE } },
+        });
+        Arc::new(parse_contract_artifact(&artifact.to_string()).unwrap())
+    }
+
+    #[test]
+    fn test_renders_call_tree_with_source_locations() {
+        let error = json!({
+            "code": -32000,
+            "message": "Assertion failed: zero",
+            "data": {
+                "originalMessage": "Assertion failed: zero",
+                "functionErrorStack": [
+                    { "contractAddress": "0x0b", "functionName": "entrypoint" },
+                    { "contractAddress": "0x0a", "functionName": "set_just_field" },
+                ],
+                "noirErrorStack": ["0.7"],
+            },
+        });
+        let main = artifact();
+        let simulation = SimulationError::from_rpc_error(&error, |address| {
+            (address == "0x0a").then(|| main.clone())
+        })
+        .unwrap();
+
+        assert_eq!(simulation.frames.len(), 2);
+        assert_eq!(simulation.frames[0].contract, "<unknown contract>");
+        assert_eq!(simulation.frames[0].function, "entrypoint");
+        let failing = &simulation.frames[1];
+        assert_eq!(failing.contract, "Main");
+        assert_eq!(
+            failing.locations[1],
+            SourceLocation {
+                path: "src/main.nr".to_string(),
+                line: 2,
+                column: 5,
+                text: "assert(value != 0, \"zero\")".to_string(),
+            }
+        );
+
+        let rendered = simulation.render();
+        assert_eq!(
+            rendered,
+            "Simulation failed: Assertion failed: zero\n\
+             └─ <unknown contract>.entrypoint (0x0b)\n   \
+             └─ Main.set_just_field (0x0a)\n        \
+             at src/main.nr:1:1  fn set(value: Field)\n        \
+             at src/main.nr:2:5  assert(value != 0, \"zero\")\n"
+        );
+
+        let other = AztecError::Rpc {
+            method: "pxe_simulateTx".to_string(),
+            error: json!({ "code": -32000, "message": "boom" }),
+        };
+        assert!(SimulationError::from_error(&other, |_| None).is_none());
+    }
+}
@@ -0,0 +1,175 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Small key/value store for sequencer state that has to survive restarts.
+/// Everything lives in one JSON file that is rewritten (via a temp file and
+/// rename) on every change; without a path the store is memory-only.
+#[derive(Debug, Default)]
+pub struct StateStore {
+    path: Option<PathBuf>,
+    entries: Mutex<BTreeMap<String, Value>>,
+}
+
+impl StateStore {
+    pub fn in_memory() -> Self {
+        StateStore::default()
+    }
+
+    /// Loads `path` if it exists; it is created on the first write.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, String> {
+        let path = path.into();
+        let entries = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|e| format!("Corrupt state file {}: {}", path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(format!("Cannot read {}: {}", path.display(), e)),
+        };
+
+        Ok(StateStore {
+            path: Some(path),
+            entries: Mutex::new(entries),
+        })
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, String> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(key)
+            .map(|value| serde_json::from_value(value.clone()).map_err(|e| e.to_string()))
+            .transpose()
+    }
+
+    pub fn put<T: Serialize>(&self, key: &str, value: &T) -> Result<(), String> {
+        let value = serde_json::to_value(value).map_err(|e| e.to_string())?;
+        self.update(|entries| {
+            entries.insert(key.to_string(), value);
+            true
+        })
+    }
+
+    pub fn remove(&self, key: &str) -> Result<(), String> {
+        self.update(|entries| entries.remove(key).is_some())
+    }
+
+    /// Removes all of `keys` with a single write.
+    pub fn remove_all(&self, keys: &[String]) -> Result<(), String> {
+        self.update(|entries| {
+            let before = entries.len();
+            for key in keys {
+                entries.remove(key);
+            }
+            entries.len() != before
+        })
+    }
+
+    /// Keys starting with `prefix`, in sorted order.
+    pub fn keys(&self, prefix: &str) -> Vec<String> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .range(prefix.to_string()..)
+            .map(|(key, _)| key)
+            .take_while(|key| key.starts_with(prefix))
+            .cloned()
+            .collect()
+    }
+
+    /// Whether changes can still be saved: writes and removes a probe file
+    /// next to the store. Memory-only stores always can.
+    pub fn check_writable(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let probe = path.with_extension("probe");
+        fs::write(&probe, b"")
+            .and_then(|_| fs::remove_file(&probe))
+            .map_err(|e| format!("Cannot write next to {}: {}", path.display(), e))
+    }
+
+    // Applies `change` to a copy of the entries, which replaces them only
+    // once it is on disk, so a failed write changes nothing. `change` says
+    // whether it changed anything.
+    fn update(
+        &self,
+        change: impl FnOnce(&mut BTreeMap<String, Value>) -> bool,
+    ) -> Result<(), String> {
+        let mut entries = self.entries.lock().unwrap();
+        let mut updated = entries.clone();
+        if change(&mut updated) {
+            self.flush(&updated)?;
+            *entries = updated;
+        }
+        Ok(())
+    }
+
+    // The temp file is synced before the rename and the directory after it,
+    // so a crash leaves either the old state or the new one.
+    fn flush(&self, entries: &BTreeMap<String, Value>) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let contents = serde_json::to_vec_pretty(entries).map_err(|e| e.to_string())?;
+        let tmp = path.with_extension("tmp");
+        let write = || -> std::io::Result<()> {
+            let mut file = fs::File::create(&tmp)?;
+            file.write_all(&contents)?;
+            file.sync_all()?;
+            fs::rename(&tmp, path)?;
+            #[cfg(unix)]
+            {
+                let dir = match path.parent() {
+                    Some(dir) if !dir.as_os_str().is_empty() => dir,
+                    _ => Path::new("."),
+                };
+                fs::File::open(dir)?.sync_all()?;
+            }
+            Ok(())
+        };
+        write().map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_survive_reopen() {
+        let path = std::env::temp_dir().join(format!("state-store-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let store = StateStore::open(&path).unwrap();
+        store.put("approvals/b", &2u64).unwrap();
+        store.put("approvals/a", &1u64).unwrap();
+        store.put("other", &"x").unwrap();
+        store.remove("approvals/b").unwrap();
+
+        let reopened = StateStore::open(&path).unwrap();
+        assert_eq!(reopened.get::<u64>("approvals/a").unwrap(), Some(1));
+        assert_eq!(reopened.get::<u64>("approvals/b").unwrap(), None);
+        assert_eq!(reopened.keys("approvals/"), vec!["approvals/a"]);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_failed_write_leaves_entries_unchanged() {
+        let dir = std::env::temp_dir().join(format!("state-store-gone-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir(&dir).unwrap();
+        let store = StateStore::open(dir.join("state.json")).unwrap();
+        store.put("nonces/a", &1u64).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(store.put("nonces/b", &2u64).is_err());
+        assert!(store.remove("nonces/a").is_err());
+        assert!(store.remove_all(&["nonces/a".to_string()]).is_err());
+        assert_eq!(store.keys("nonces/"), vec!["nonces/a"]);
+        assert_eq!(store.get::<u64>("nonces/b").unwrap(), None);
+    }
+}
@@ -0,0 +1,200 @@
+use futures_util::stream::{self, StreamExt};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::aztec_rpc_client::AztecRpcClient;
+use crate::encoder::ContractArtifact;
+use crate::fields::Fr;
+use crate::notes::{Bn254Poseidon2, Poseidon2};
+
+/// aztec-nr's `derive_storage_slot_in_map`: `poseidon2_hash([slot, key])`.
+pub trait MapSlotHasher: Send + Sync {
+    fn map_slot(&self, slot: &Fr, key: &Fr) -> Fr;
+}
+
+impl MapSlotHasher for Bn254Poseidon2 {
+    fn map_slot(&self, slot: &Fr, key: &Fr) -> Fr {
+        self.hash(&[slot.clone(), key.clone()])
+    }
+}
+
+/// One public storage value: a variable of `contract`'s storage layout, or
+/// the entry under `key` when the variable is a map.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageRead {
+    pub contract: String,
+    pub variable: String,
+    pub key: Option<Fr>,
+}
+
+impl StorageRead {
+    pub fn new(contract: impl Into<String>, variable: impl Into<String>, key: Option<Fr>) -> Self {
+        StorageRead {
+            contract: contract.into(),
+            variable: variable.into(),
+            key,
+        }
+    }
+}
+
+/// Reads public storage by variable name, resolving slots from the
+/// registered contracts' storage layouts.
+pub struct StorageReader<'a> {
+    pxe: &'a AztecRpcClient,
+    contracts: HashMap<String, Arc<ContractArtifact>>,
+    map_hasher: Arc<dyn MapSlotHasher>,
+    concurrency: usize,
+}
+
+impl<'a> StorageReader<'a> {
+    pub fn new(pxe: &'a AztecRpcClient) -> Self {
+        StorageReader {
+            pxe,
+            contracts: HashMap::new(),
+            map_hasher: Arc::new(Bn254Poseidon2),
+            concurrency: 8,
+        }
+    }
+
+    pub fn with_contract(mut self, address: &str, artifact: Arc<ContractArtifact>) -> Self {
+        self.contracts.insert(address.to_lowercase(), artifact);
+        self
+    }
+
+    /// Replaces the Poseidon2 map entries' slots are derived with.
+    pub fn with_map_hasher(mut self, hasher: Arc<dyn MapSlotHasher>) -> Self {
+        self.map_hasher = hasher;
+        self
+    }
+
+    /// How many `getPublicStorageAt` calls `read_many` keeps in flight.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// The slot `read` is stored at.
+    pub fn slot(&self, read: &StorageRead) -> Result<Fr, String> {
+        let artifact = self
+            .contracts
+            .get(&read.contract.to_lowercase())
+            .ok_or_else(|| format!("No artifact registered for contract {}", read.contract))?;
+        let layout = artifact.storage_layout.get(&read.variable).ok_or_else(|| {
+            format!(
+                "{} has no storage variable `{}`",
+                artifact.name, read.variable
+            )
+        })?;
+        let slot = Fr::try_from(layout.slot.as_str())
+            .map_err(|e| format!("Bad slot for `{}`: {}", read.variable, e))?;
+        Ok(match &read.key {
+            None => slot,
+            Some(key) => self.map_hasher.map_slot(&slot, key),
+        })
+    }
+
+    pub async fn read(&self, read: &StorageRead) -> Result<Fr, String> {
+        let slot = self.slot(read)?;
+        self.pxe
+            .get_public_storage_at(&read.contract, &slot)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    /// Reads every entry, a bounded number at a time. Results come back in
+    /// the order of `reads`, each with its own error.
+    pub async fn read_many(&self, reads: Vec<StorageRead>) -> Vec<Result<Fr, String>> {
+        stream::iter(reads)
+            .map(|read| async move { self.read(&read).await })
+            .buffered(self.concurrency)
+            .collect()
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockPxe;
+    use serde_json::json;
+
+    const ADDRESS: &str = "0x0a";
+
+    fn artifact() -> Arc<ContractArtifact> {
+        Arc::new(
+            serde_json::from_value(json!({
+                "name": "Main",
+                "functions": [],
+                "nonDispatchPublicFunctions": [],
+                "storageLayout": {
+                    "just_field": { "slot": "0x02" },
+                    "field_in_map": { "slot": "0x01" },
+                },
+                "notes": {},
+                "fileMap": {},
+            }))
+            .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_read_many_keeps_order_and_per_entry_errors() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond("pxe_getPublicStorageAt", json!(Fr::from(214u8)));
+        let pxe = AztecRpcClient::new(mock.url(), Some("pxe".to_string()));
+        let reader = StorageReader::new(&pxe)
+            .with_contract(ADDRESS, artifact())
+            .with_concurrency(2);
+
+        let results = reader
+            .read_many(vec![
+                StorageRead::new(ADDRESS, "just_field", None),
+                StorageRead::new(ADDRESS, "missing", None),
+                StorageRead::new(ADDRESS, "field_in_map", Some(Fr::from(5u8))),
+                StorageRead::new("0x0b", "just_field", None),
+            ])
+            .await;
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0], Ok(Fr::from(214u8)));
+        assert!(results[1].as_ref().unwrap_err().contains("missing"));
+        assert_eq!(results[2], Ok(Fr::from(214u8)));
+        assert!(results[3].as_ref().unwrap_err().contains("0x0b"));
+
+        let slots: Vec<_> = mock
+            .requests()
+            .iter()
+            .map(|r| r["params"][1].clone())
+            .collect();
+        assert_eq!(slots.len(), 2);
+        assert!(slots.contains(&json!(Fr::from(2u8))));
+        assert!(slots.contains(&json!(
+            "0x1b6d87a05376f09ae962e5cfd48d056755e4061f72bd0bcd9c764b42c3cd90b6"
+        )));
+    }
+
+    struct Sum;
+
+    impl MapSlotHasher for Sum {
+        fn map_slot(&self, slot: &Fr, key: &Fr) -> Fr {
+            Fr::from_biguint(&slot.0 + &key.0)
+        }
+    }
+
+    #[test]
+    fn test_map_slots_hash_the_key_into_the_slot() {
+        let pxe = AztecRpcClient::new("http://localhost:0", None);
+        let reader = StorageReader::new(&pxe).with_contract("0x0A", artifact());
+        assert_eq!(
+            reader.slot(&StorageRead::new(ADDRESS, "just_field", None)),
+            Ok(Fr::from(2u8))
+        );
+        let entry = StorageRead::new(ADDRESS, "field_in_map", Some(Fr::from(1u8)));
+        assert_eq!(
+            reader.slot(&entry).unwrap(),
+            Fr::try_from("0x1df6080e5bf5cefb3e40daf91cfcc5a267781505471aa058c0b205986774f978")
+                .unwrap()
+        );
+        let reader = reader.with_map_hasher(Arc::new(Sum));
+        assert_eq!(reader.slot(&entry), Ok(Fr::from(2u8)));
+    }
+}
@@ -0,0 +1,214 @@
+use futures_util::future::BoxFuture;
+use futures_util::FutureExt;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tokio::time::sleep;
+
+/// When a supervised task is started again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Restart {
+    /// Never: the task failing shuts the supervisor down.
+    Never,
+    /// After it fails or panics; finishing is fine.
+    OnFailure,
+    /// Whenever it stops, failed or not.
+    Always,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestartPolicy {
+    pub restart: Restart,
+    /// Restarts allowed before stopping again is fatal.
+    pub max_restarts: u32,
+    /// The wait before the first restart, doubled for each one after, up to
+    /// `max_backoff`.
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        RestartPolicy {
+            restart: Restart::OnFailure,
+            max_restarts: 5,
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RestartPolicy {
+    pub fn never() -> Self {
+        RestartPolicy {
+            restart: Restart::Never,
+            ..RestartPolicy::default()
+        }
+    }
+
+    fn delay(&self, restarts: u32) -> Duration {
+        self.backoff
+            .saturating_mul(2u32.saturating_pow(restarts))
+            .min(self.max_backoff)
+    }
+}
+
+type Task = Box<dyn Fn() -> BoxFuture<'static, Result<(), String>> + Send + Sync>;
+
+/// Owns long-running tasks (servers, watchers, the indexer) and restarts
+/// them by their `RestartPolicy`. A task that stops for good ends `run`
+/// with its error, after the other tasks are aborted, so nothing keeps
+/// running half dead.
+#[derive(Default)]
+pub struct Supervisor {
+    tasks: Vec<(String, RestartPolicy, Task)>,
+}
+
+impl Supervisor {
+    pub fn new() -> Self {
+        Supervisor::default()
+    }
+
+    /// Adds a task; `task` makes a fresh future for each (re)start.
+    pub fn add<F, Fut>(&mut self, name: impl Into<String>, policy: RestartPolicy, task: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        self.tasks
+            .push((name.into(), policy, Box::new(move || task().boxed())));
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.tasks.iter().map(|(name, ..)| name.as_str()).collect()
+    }
+
+    /// Runs the tasks until `shutdown` resolves or they have all finished,
+    /// or until one fails for good, which is the error returned.
+    pub async fn run(self, shutdown: impl Future<Output = ()>) -> Result<(), String> {
+        let mut running = JoinSet::new();
+        for (name, policy, task) in self.tasks {
+            running.spawn(supervise(name, policy, task));
+        }
+        tokio::pin!(shutdown);
+        // Dropping `running` on return aborts whatever is left.
+        loop {
+            tokio::select! {
+                _ = &mut shutdown => return Ok(()),
+                joined = running.join_next() => match joined {
+                    None => return Ok(()),
+                    Some(Ok(Ok(()))) => {}
+                    Some(Ok(Err(e))) => return Err(e),
+                    Some(Err(e)) => return Err(format!("Supervised task lost: {}", e)),
+                },
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for Supervisor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Supervisor")
+            .field("tasks", &self.names())
+            .finish()
+    }
+}
+
+async fn supervise(name: String, policy: RestartPolicy, task: Task) -> Result<(), String> {
+    let mut restarts = 0;
+    loop {
+        let outcome = AssertUnwindSafe(task())
+            .catch_unwind()
+            .await
+            .unwrap_or_else(|_| Err("panicked".to_string()));
+        let stopped = match (outcome, policy.restart) {
+            (Ok(()), Restart::Never | Restart::OnFailure) => return Ok(()),
+            (Err(e), Restart::Never) => return Err(format!("{} failed: {}", name, e)),
+            (Ok(()), Restart::Always) => "stopped".to_string(),
+            (Err(e), _) => format!("failed: {}", e),
+        };
+        if restarts >= policy.max_restarts {
+            return Err(format!(
+                "{} {} after {} restart(s)",
+                name, stopped, restarts
+            ));
+        }
+        let delay = policy.delay(restarts);
+        println!("{} {}; restarting in {:?}", name, stopped, delay);
+        sleep(delay).await;
+        restarts += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::pending;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use tokio::sync::oneshot;
+
+    fn quick(restart: Restart, max_restarts: u32) -> RestartPolicy {
+        RestartPolicy {
+            restart,
+            max_restarts,
+            backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(4),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_restarts_failures_and_stops_on_a_fatal_one() {
+        assert_eq!(RestartPolicy::default().delay(10), Duration::from_secs(30));
+        let attempts = Arc::new(AtomicU32::new(0));
+        let flaky = attempts.clone();
+        let mut supervisor = Supervisor::new();
+        supervisor.add("flaky", quick(Restart::OnFailure, 3), move || {
+            let attempt = flaky.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if attempt < 2 {
+                    panic!("attempt {}", attempt);
+                }
+                Ok(())
+            }
+        });
+        supervisor.run(pending()).await.unwrap();
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        // The server is aborted once the watcher gives up.
+        let (dropped, server_gone) = oneshot::channel::<()>();
+        let dropped = Arc::new(dropped);
+        let mut supervisor = Supervisor::new();
+        supervisor.add("server", quick(Restart::Always, 0), move || {
+            let dropped = dropped.clone();
+            async move {
+                let _held = dropped;
+                pending::<()>().await;
+                Ok(())
+            }
+        });
+        supervisor.add("watcher", quick(Restart::OnFailure, 2), || async {
+            Err("node unreachable".to_string())
+        });
+        assert_eq!(supervisor.names(), ["server", "watcher"]);
+        let error = supervisor.run(pending()).await.unwrap_err();
+        assert_eq!(error, "watcher failed: node unreachable after 2 restart(s)");
+        assert!(server_gone.await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_aborts_running_tasks() {
+        let mut supervisor = Supervisor::new();
+        supervisor.add("idle", RestartPolicy::never(), || async {
+            pending::<()>().await;
+            Ok(())
+        });
+        let (stop, stopped) = oneshot::channel();
+        stop.send(()).unwrap();
+        let shutdown = async {
+            stopped.await.unwrap();
+        };
+        supervisor.run(shutdown).await.unwrap();
+    }
+}
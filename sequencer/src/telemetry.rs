@@ -0,0 +1,103 @@
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use std::collections::HashMap;
+use std::env;
+use tracing::level_filters::LevelFilter;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+use crate::logging;
+
+/// Exports the crate's `tracing` spans over OTLP/gRPC: a bridge request's
+/// `bridge.request` span with the `tx.send`, `pxe.call` and `tx.wait` spans
+/// under it. Spans still buffered are flushed by `shutdown`.
+pub struct Telemetry {
+    provider: SdkTracerProvider,
+}
+
+impl Telemetry {
+    /// Installs the exporter, next to `logging::stderr_layer`, as the global
+    /// `tracing` subscriber when `OTEL_EXPORTER_OTLP_ENDPOINT` is set, and
+    /// only the stderr log otherwise. The other `OTEL_EXPORTER_OTLP_*`
+    /// variables apply as usual; the service is `OTEL_SERVICE_NAME`, else
+    /// `service_name`. Call from within the Tokio runtime, which the
+    /// exporter sends on.
+    pub fn from_env(service_name: &str) -> Result<Option<Self>, String> {
+        if env::var("OTEL_EXPORTER_OTLP_ENDPOINT").is_err() {
+            logging::init()?;
+            return Ok(None);
+        }
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_tonic()
+            .build()
+            .map_err(|e| format!("Cannot set up OTLP export: {}", e))?;
+        let mut resource = Resource::builder();
+        if env::var("OTEL_SERVICE_NAME").is_err() {
+            resource = resource.with_service_name(service_name.to_string());
+        }
+        let provider = SdkTracerProvider::builder()
+            .with_resource(resource.build())
+            .with_batch_exporter(exporter)
+            .build();
+        let layer = tracing_opentelemetry::layer()
+            .with_tracer(provider.tracer("sequencer"))
+            .with_filter(LevelFilter::INFO);
+        tracing_subscriber::registry()
+            .with(logging::stderr_layer())
+            .with(layer)
+            .try_init()
+            .map_err(|e| format!("Cannot install the trace exporter: {}", e))?;
+        Ok(Some(Telemetry { provider }))
+    }
+
+    pub fn shutdown(self) -> Result<(), String> {
+        self.provider
+            .shutdown()
+            .map_err(|e| format!("Cannot flush traces: {}", e))
+    }
+}
+
+/// Makes `span` continue the trace in a W3C `traceparent`. Must be called
+/// before the span is first entered; malformed values leave it a new trace.
+pub fn set_parent(span: &Span, traceparent: &str) {
+    let carrier = HashMap::from([("traceparent".to_string(), traceparent.to_string())]);
+    let parent = TraceContextPropagator::new().extract(&carrier);
+    // Fails only without the OpenTelemetry layer, when nothing is exported.
+    let _ = span.set_parent(parent);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::trace::TraceContextExt;
+
+    #[test]
+    fn test_spans_continue_the_callers_trace() {
+        let provider = SdkTracerProvider::builder().build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("bridge.request");
+            set_parent(
+                &span,
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            );
+            let context = span.context();
+            let trace_id = context.span().span_context().trace_id();
+            assert_eq!(trace_id.to_string(), "4bf92f3577b34da6a3ce929d0e0e4736");
+
+            let child = span.in_scope(|| tracing::info_span!("pxe.call"));
+            assert_eq!(child.context().span().span_context().trace_id(), trace_id);
+
+            let fresh = tracing::info_span!("bridge.request");
+            set_parent(&fresh, "garbage");
+            assert_ne!(fresh.context().span().span_context().trace_id(), trace_id);
+        });
+    }
+}
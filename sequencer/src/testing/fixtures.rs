@@ -0,0 +1,126 @@
+use serde_json::Value;
+use std::sync::{Arc, OnceLock};
+
+use super::MockPxe;
+use crate::encoder::ContractArtifact;
+use crate::wallet::{AccountWallet, EcdsaAccountWallet};
+
+macro_rules! fixture {
+    ($name:literal) => {
+        include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/",
+            $name
+        ))
+    };
+}
+
+/// The feed contract (`contract/src/main.nr`) and what a PXE answers when it
+/// is driven, as checked in under `tests/fixtures/`. The artifact is generated
+/// from the compiled contract by `src/feed-fixture.ts`. Embedded at build
+/// time, so tests need no files at runtime.
+#[derive(Debug)]
+pub struct Fixtures {
+    /// Processed artifact JSON, as the bridge reads it from disk.
+    pub artifact_json: &'static str,
+    pub artifact: Arc<ContractArtifact>,
+    /// `pxe_simulateTx` result for `set_just_field`, with gas used.
+    pub simulate_set: Value,
+    /// `pxe_simulateTx` result for `get_just_field`, returning 700.
+    pub simulate_get: Value,
+    /// `pxe_proveTx` result.
+    pub prove: Value,
+    /// `pxe_sendTx` result: the tx hash.
+    pub tx_hash: String,
+}
+
+impl Fixtures {
+    /// Queues the simulate, prove and send responses for one `set_just_field`.
+    pub fn serve_send(&self, mock: &MockPxe) {
+        mock.respond("pxe_simulateTx", self.simulate_set.clone());
+        mock.respond("pxe_proveTx", self.prove.clone());
+        mock.respond("pxe_sendTx", Value::String(self.tx_hash.clone()));
+    }
+}
+
+/// A wallet for `address` with a fixed ECDSA key, so tests can send from it.
+pub fn test_wallet(address: &str) -> Arc<dyn AccountWallet> {
+    let secret = "0x0101010101010101010101010101010101010101010101010101010101010101";
+    Arc::new(EcdsaAccountWallet::new(address, secret).expect("valid test key"))
+}
+
+/// The shared, parsed fixtures.
+pub fn fixtures() -> &'static Fixtures {
+    static FIXTURES: OnceLock<Fixtures> = OnceLock::new();
+    FIXTURES.get_or_init(|| {
+        let json = |s: &str| serde_json::from_str::<Value>(s).expect("fixture is valid JSON");
+        let artifact_json = fixture!("feed_contract.json");
+        Fixtures {
+            artifact_json,
+            artifact: Arc::new(
+                serde_json::from_str(artifact_json).expect("fixture artifact parses"),
+            ),
+            simulate_set: json(fixture!("simulate_set_just_field.json")),
+            simulate_get: json(fixture!("simulate_get_just_field.json")),
+            prove: json(fixture!("prove_set_just_field.json")),
+            tx_hash: serde_json::from_str(fixture!("send_set_just_field.json"))
+                .expect("fixture tx hash is a string"),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoder::{get_function_artifact, ArgumentEncoder};
+    use crate::fields::Fr;
+    use serde_json::json;
+
+    #[test]
+    fn test_artifact_covers_contract_functions() {
+        let artifact = &fixtures().artifact;
+        for name in [
+            "constructor",
+            "set_just_field",
+            "get_just_field",
+            "set_field_in_map",
+            "read_field_in_map",
+            "set_feeds",
+        ] {
+            get_function_artifact(artifact, name).unwrap();
+        }
+        let slot = |name: &str| Fr::try_from(artifact.storage_layout[name].slot.as_str()).unwrap();
+        assert_eq!(slot("just_field"), Fr::from(2u8));
+        assert_eq!(slot("feeds"), Fr::from(3u8));
+        assert!(artifact
+            .file_map
+            .0
+            .values()
+            .any(|f| f.source.contains("fn set_feeds")));
+    }
+
+    #[test]
+    fn test_encodes_set_feeds_from_fixture_artifact() {
+        let artifact = &fixtures().artifact;
+        let abi = get_function_artifact(artifact, "set_feeds")
+            .unwrap()
+            .to_abi();
+        let price = 1_000_000_000_000_000_000_000u128;
+        let update = |id: u64| json!({ "feed_id": id, "price": price.to_string(), "timestamp": 1_700_000_000u64 });
+        let args = vec![json!([update(1), update(2), update(3)])];
+
+        let encoded = ArgumentEncoder::new(abi, args).encode().unwrap();
+        assert_eq!(encoded.len(), 9);
+        assert_eq!(encoded[0], Fr::from(1u8));
+        assert_eq!(encoded[1], Fr::from(price));
+        assert_eq!(encoded[2], Fr::from(1_700_000_000u64));
+        assert_eq!(encoded[6], Fr::from(3u8));
+
+        let abi = get_function_artifact(artifact, "set_feeds")
+            .unwrap()
+            .to_abi();
+        assert!(ArgumentEncoder::new(abi, vec![json!([update(1)])])
+            .encode()
+            .is_err());
+    }
+}
@@ -0,0 +1,233 @@
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use super::fixtures::test_wallet;
+use super::recorder::RpcTrace;
+use crate::aztec_rpc_client::AztecRpcClient;
+use crate::tx_request::DEFAULT_ORIGIN;
+
+#[derive(Debug, Default)]
+struct MockState {
+    responses: HashMap<String, VecDeque<Value>>,
+    delays: HashMap<String, Duration>,
+    received: Vec<Value>,
+    heads: Vec<String>,
+}
+
+impl MockState {
+    fn reply(&mut self, request: Value) -> Value {
+        let method = request["method"].as_str().unwrap_or_default().to_string();
+        let id = request["id"].clone();
+        self.received.push(request);
+
+        // The last queued response for a method keeps being served so that
+        // polling calls such as `getBlockNumber` don't need one entry per poll.
+        let envelope = match self.responses.get_mut(&method) {
+            Some(queue) if queue.len() > 1 => queue.pop_front(),
+            Some(queue) => queue.front().cloned(),
+            None => None,
+        };
+
+        match envelope {
+            Some(mut envelope) => {
+                envelope["id"] = id;
+                envelope
+            }
+            None if method.ends_with("_getNodeInfo") => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": { "nodeVersion": "0.87.2", "l1ChainId": 31337, "rollupVersion": 1 },
+            }),
+            None => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": -32601, "message": format!("MockPxe has no response for {}", method) },
+            }),
+        }
+    }
+}
+
+/// Minimal HTTP JSON-RPC server standing in for a PXE. Responses are queued
+/// per fully-qualified method name (`pxe_simulateTx`) and every request body
+/// is kept so tests can assert on what the client actually sent.
+/// `getNodeInfo` answers like a local sandbox unless a test queues its own.
+#[derive(Debug)]
+pub struct MockPxe {
+    url: String,
+    state: Arc<Mutex<MockState>>,
+    handle: JoinHandle<()>,
+}
+
+impl MockPxe {
+    pub async fn start() -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let state = Arc::new(Mutex::new(MockState::default()));
+        let handle = tokio::spawn(serve(listener, state.clone()));
+
+        Ok(MockPxe { url, state, handle })
+    }
+
+    /// Starts a mock that answers with the responses captured in `trace`, in
+    /// recorded order per method.
+    pub async fn from_trace(trace: &RpcTrace) -> std::io::Result<Self> {
+        let mock = Self::start().await?;
+        for exchange in &trace.exchanges {
+            mock.respond_with_envelope(&exchange.method, exchange.response.clone());
+        }
+        Ok(mock)
+    }
+
+    /// A client for this PXE that can send from `DEFAULT_ORIGIN`.
+    pub fn client(&self) -> AztecRpcClient {
+        AztecRpcClient::new(self.url(), Some("pxe".to_string()))
+            .with_wallet(test_wallet(DEFAULT_ORIGIN))
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn respond(&self, method: &str, result: Value) {
+        self.respond_with_envelope(
+            method,
+            json!({ "jsonrpc": "2.0", "id": 1, "result": result }),
+        );
+    }
+
+    pub fn respond_with_envelope(&self, method: &str, envelope: Value) {
+        self.lock()
+            .responses
+            .entry(method.to_string())
+            .or_default()
+            .push_back(envelope);
+    }
+
+    /// Holds every answer to `method` back for `delay`, like a slow prover.
+    pub fn delay(&self, method: &str, delay: Duration) {
+        self.lock().delays.insert(method.to_string(), delay);
+    }
+
+    pub fn requests(&self) -> Vec<Value> {
+        self.lock().received.clone()
+    }
+
+    /// The request line and headers of every request, lowercased.
+    pub fn request_heads(&self) -> Vec<String> {
+        self.lock().heads.clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().expect("mock pxe lock poisoned")
+    }
+}
+
+impl Drop for MockPxe {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+async fn serve(listener: TcpListener, state: Arc<Mutex<MockState>>) {
+    while let Ok((stream, _)) = listener.accept().await {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let _ = handle_connection(stream, state).await;
+        });
+    }
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    state: Arc<Mutex<MockState>>,
+) -> std::io::Result<()> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    let header_end = loop {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let headers = String::from_utf8_lossy(&buf[..header_end]).to_ascii_lowercase();
+    let content_length = headers
+        .lines()
+        .find_map(|line| line.strip_prefix("content-length:"))
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .unwrap_or(0);
+
+    while buf.len() < header_end + content_length {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+
+    let body = &buf[header_end..buf.len().min(header_end + content_length)];
+    let (reply, delay) = match serde_json::from_slice::<Value>(body) {
+        Ok(request) => {
+            let mut state = state.lock().expect("mock pxe lock poisoned");
+            state.heads.push(headers.clone());
+            let delay = request["method"]
+                .as_str()
+                .and_then(|method| state.delays.get(method).copied());
+            (state.reply(request), delay)
+        }
+        Err(err) => (
+            json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "error": { "code": -32700, "message": err.to_string() },
+            }),
+            None,
+        ),
+    };
+    if let Some(delay) = delay {
+        tokio::time::sleep(delay).await;
+    }
+
+    let payload = serde_json::to_vec(&reply)?;
+    let head = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        payload.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&payload).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aztec_rpc_client::AztecRpcClient;
+
+    #[tokio::test]
+    async fn test_mock_pxe_serves_queued_responses() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond("pxe_getBlockNumber", json!(12));
+
+        let pxe = AztecRpcClient::new(mock.url(), Some("pxe".to_string()));
+        assert_eq!(pxe.get_block_number().await.unwrap(), 12);
+        assert_eq!(pxe.get_block_number().await.unwrap(), 12);
+        assert_eq!(mock.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_mock_pxe_errors_on_unknown_method() {
+        let mock = MockPxe::start().await.unwrap();
+        let pxe = AztecRpcClient::new(mock.url(), Some("pxe".to_string()));
+        assert!(pxe.get_block_number().await.is_err());
+    }
+}
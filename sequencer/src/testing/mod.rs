@@ -0,0 +1,9 @@
+mod fixtures;
+mod mock_pxe;
+mod recorder;
+mod replay;
+
+pub use fixtures::{fixtures, test_wallet, Fixtures};
+pub use mock_pxe::MockPxe;
+pub use recorder::{RpcExchange, RpcRecorder, RpcTrace};
+pub use replay::{ReplayHarness, ReplayMismatch};
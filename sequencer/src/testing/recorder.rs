@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// One JSON-RPC round trip: the full payload we sent and the full envelope the
+/// PXE answered with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcExchange {
+    pub method: String,
+    pub request: Value,
+    pub response: Value,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RpcTrace {
+    pub exchanges: Vec<RpcExchange>,
+}
+
+impl RpcTrace {
+    /// Traces are stored as JSON lines, one exchange per line.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = fs::read_to_string(path)?;
+        let mut exchanges = Vec::new();
+        for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+            exchanges.push(serde_json::from_str(line)?);
+        }
+        Ok(RpcTrace { exchanges })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn std::error::Error>> {
+        let mut contents = String::new();
+        for exchange in &self.exchanges {
+            contents.push_str(&serde_json::to_string(exchange)?);
+            contents.push('\n');
+        }
+        fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+/// Shared sink that `AztecRpcClient` pushes every exchange into when recording
+/// is enabled. Clones share the same underlying trace.
+#[derive(Debug, Clone, Default)]
+pub struct RpcRecorder {
+    exchanges: Arc<Mutex<Vec<RpcExchange>>>,
+}
+
+impl RpcRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, exchange: RpcExchange) {
+        self.exchanges
+            .lock()
+            .expect("recorder lock poisoned")
+            .push(exchange);
+    }
+
+    pub fn trace(&self) -> RpcTrace {
+        RpcTrace {
+            exchanges: self
+                .exchanges
+                .lock()
+                .expect("recorder lock poisoned")
+                .clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_trace_round_trips_through_json_lines() {
+        let recorder = RpcRecorder::new();
+        recorder.record(RpcExchange {
+            method: "pxe_getBlockNumber".to_string(),
+            request: json!({ "jsonrpc": "2.0", "id": 1, "method": "pxe_getBlockNumber", "params": [] }),
+            response: json!({ "jsonrpc": "2.0", "id": 1, "result": 7 }),
+        });
+
+        let path = std::env::temp_dir().join(format!("trace-{}.jsonl", std::process::id()));
+        recorder.trace().save(&path).unwrap();
+        let loaded = RpcTrace::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, recorder.trace());
+    }
+}
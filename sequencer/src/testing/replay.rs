@@ -0,0 +1,312 @@
+use std::fmt;
+
+use super::mock_pxe::MockPxe;
+use super::recorder::RpcTrace;
+use crate::aztec_rpc_client::AztecRpcClient;
+use crate::fields::Fr;
+use crate::tx_request::TxExecutionRequest;
+use crate::wallet::entrypoint_nonces;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayMismatch {
+    pub index: usize,
+    pub method: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl fmt::Display for ReplayMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "request #{} ({}) differs from the recording\n  expected: {}\n  actual:   {}",
+            self.index, self.method, self.expected, self.actual
+        )
+    }
+}
+
+impl std::error::Error for ReplayMismatch {}
+
+/// Serves a recorded trace from a `MockPxe` and checks that the client sends
+/// exactly the same payloads, in the same order, as when the trace was taken.
+#[derive(Debug)]
+pub struct ReplayHarness {
+    trace: RpcTrace,
+    pxe: MockPxe,
+}
+
+impl ReplayHarness {
+    pub async fn start(trace: RpcTrace) -> std::io::Result<Self> {
+        let pxe = MockPxe::from_trace(&trace).await?;
+        Ok(ReplayHarness { trace, pxe })
+    }
+
+    pub fn client(&self) -> AztecRpcClient {
+        self.pxe.client()
+    }
+
+    /// The entrypoint nonces of the trace's first `simulateTx`, for
+    /// `ContractFunctionInteraction::with_nonces`: a send picks them at
+    /// random, so it is only replayed byte for byte with the recorded ones.
+    pub fn recorded_nonces(&self) -> Option<(Fr, Fr)> {
+        let simulate = self
+            .trace
+            .exchanges
+            .iter()
+            .find(|exchange| exchange.method == "pxe_simulateTx")?;
+        let request = TxExecutionRequest::from_json(simulate.request["params"][0].clone()).ok()?;
+        entrypoint_nonces(&request)
+    }
+
+    pub fn verify(&self) -> Result<(), ReplayMismatch> {
+        let received = self.pxe.requests();
+        let count = self.trace.exchanges.len().max(received.len());
+
+        for index in 0..count {
+            let expected = self.trace.exchanges.get(index);
+            let actual = received.get(index);
+
+            let expected_bytes = expected
+                .map(|e| serde_json::to_string(&e.request).unwrap_or_default())
+                .unwrap_or_else(|| "<nothing>".to_string());
+            let actual_bytes = actual
+                .map(|a| serde_json::to_string(a).unwrap_or_default())
+                .unwrap_or_else(|| "<nothing>".to_string());
+
+            if expected_bytes != actual_bytes {
+                let method = expected
+                    .map(|e| e.method.clone())
+                    .or_else(|| actual.and_then(|a| a["method"].as_str().map(str::to_string)))
+                    .unwrap_or_default();
+                return Err(ReplayMismatch {
+                    index,
+                    method,
+                    expected: expected_bytes,
+                    actual: actual_bytes,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::ContractFunctionInteraction;
+    use crate::encoder::{get_function_artifact, FunctionAbi};
+    use crate::notes::Bn254Poseidon2;
+    use crate::testing::{fixtures, MockPxe, RpcRecorder};
+    use crate::tx_request::DEFAULT_ORIGIN;
+    use crate::wallet::WalletConfig;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    const CONTRACT_ADDRESS: &str =
+        "0x12d8f70092c1d4b2bf3ddd60af8e47c1a10d90f3f31fe4c874d4b91f58442ede";
+
+    fn set_just_field_abi() -> FunctionAbi {
+        get_function_artifact(&fixtures().artifact, "set_just_field")
+            .unwrap()
+            .to_abi()
+    }
+
+    fn set_just_field(pxe: &AztecRpcClient) -> ContractFunctionInteraction<'_> {
+        ContractFunctionInteraction::new(
+            pxe,
+            DEFAULT_ORIGIN,
+            CONTRACT_ADDRESS,
+            set_just_field_abi(),
+            vec![json!(1)],
+        )
+    }
+
+    #[tokio::test]
+    async fn test_send_builds_the_request_from_the_interaction() {
+        let mock = MockPxe::start().await.unwrap();
+        fixtures().serve_send(&mock);
+        let pxe = mock.client();
+        let interaction = set_just_field(&pxe);
+
+        assert_eq!(interaction.send().await.unwrap(), fixtures().tx_hash);
+        let requests = mock.requests();
+        let methods: Vec<_> = requests.iter().map(|r| r["method"].clone()).collect();
+        assert_eq!(
+            methods,
+            [
+                "pxe_getNodeInfo",
+                "pxe_simulateTx",
+                "pxe_proveTx",
+                "pxe_sendTx"
+            ]
+        );
+        let tx_request = TxExecutionRequest::from_json(requests[1]["params"][0].clone()).unwrap();
+        assert_eq!(requests[2]["params"][0], requests[1]["params"][0]);
+        assert_eq!(tx_request.origin, Fr::try_from(DEFAULT_ORIGIN).unwrap());
+
+        let call = interaction.request().unwrap();
+        let calldata = call.hashed_args(&Bn254Poseidon2).unwrap();
+        assert_eq!(
+            calldata.values,
+            [call.selector_field().unwrap(), Fr::from(1u8)]
+        );
+        let entrypoint = tx_request
+            .args_of_calls
+            .iter()
+            .find(|args| args.hash == tx_request.first_call_args_hash)
+            .unwrap();
+        assert_eq!(
+            entrypoint.values[..5],
+            [
+                calldata.hash.clone(),
+                call.selector_field().unwrap(),
+                Fr::try_from(CONTRACT_ADDRESS).unwrap(),
+                Fr::from(1u8),
+                Fr::zero(),
+            ]
+        );
+        assert!(tx_request.args_of_calls.contains(&calldata));
+        assert_eq!(tx_request.auth_witnesses.len(), 1);
+    }
+
+    // Sends `set_just_field(1)` against `trace` with its recorded nonces and
+    // checks every payload, `simulateTx`, `proveTx` and `sendTx`, is the
+    // recorded one.
+    async fn replay_send(trace: RpcTrace) {
+        let harness = ReplayHarness::start(trace).await.unwrap();
+        let (app_nonce, fee_nonce) = harness.recorded_nonces().unwrap();
+        let pxe = harness.client();
+        set_just_field(&pxe)
+            .with_nonces(app_nonce, fee_nonce)
+            .send()
+            .await
+            .unwrap();
+        harness.verify().unwrap();
+    }
+
+    // The trace is taken from the mock PXE: one recorded against a sandbox
+    // goes through `replay_send` the same way.
+    #[tokio::test]
+    async fn test_send_replays_byte_for_byte() {
+        let mock = MockPxe::start().await.unwrap();
+        fixtures().serve_send(&mock);
+        let recorder = RpcRecorder::new();
+        let pxe = mock.client().with_recorder(recorder.clone());
+        set_just_field(&pxe).send().await.unwrap();
+
+        let trace = recorder.trace();
+        let methods: Vec<_> = trace.exchanges.iter().map(|e| e.method.as_str()).collect();
+        assert_eq!(
+            methods,
+            [
+                "pxe_getNodeInfo",
+                "pxe_simulateTx",
+                "pxe_proveTx",
+                "pxe_sendTx"
+            ]
+        );
+        replay_send(trace.clone()).await;
+
+        // Without the recorded nonces the entrypoint args differ.
+        let harness = ReplayHarness::start(trace).await.unwrap();
+        let replayed = harness.client();
+        set_just_field(&replayed).send().await.unwrap();
+        assert_eq!(harness.verify().unwrap_err().method, "pxe_simulateTx");
+    }
+
+    // Recorded against a 0.85-0.87 sandbox (the default payload profile) by
+    // `RPC_TRACE=traces/send_set_just_field.jsonl cargo run --example
+    // set_and_read`, signed by the `ACCOUNT_*` wallet this test signs with.
+    const SANDBOX_TRACE: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/traces/send_set_just_field.jsonl"
+    );
+
+    // Rebuilds the recorded send, origin, contract and argument alike, from
+    // its `simulateTx` request, and replays it with the recorded nonces.
+    #[tokio::test]
+    async fn test_send_replays_the_sandbox_trace() {
+        let Ok(trace) = RpcTrace::load(SANDBOX_TRACE) else {
+            eprintln!("No sandbox trace at {}, skipping", SANDBOX_TRACE);
+            return;
+        };
+        let simulate = trace
+            .exchanges
+            .iter()
+            .find(|exchange| exchange.method == "pxe_simulateTx")
+            .unwrap();
+        let request = TxExecutionRequest::from_json(simulate.request["params"][0].clone()).unwrap();
+        let args = |hash: &Fr| {
+            request
+                .args_of_calls
+                .iter()
+                .find(|args| &args.hash == hash)
+                .unwrap()
+        };
+        let entrypoint = args(&request.first_call_args_hash);
+        let calldata = args(&entrypoint.values[0]);
+        let wallet = WalletConfig::from_env()
+            .unwrap()
+            .expect("the ACCOUNT_* wallet the trace was signed with")
+            .into_wallet(None)
+            .unwrap();
+
+        let harness = ReplayHarness::start(trace.clone()).await.unwrap();
+        let (app_nonce, fee_nonce) = harness.recorded_nonces().unwrap();
+        let pxe = harness.client().with_wallet(Arc::from(wallet));
+        ContractFunctionInteraction::new(
+            &pxe,
+            request.origin.to_hex(),
+            entrypoint.values[2].to_hex(),
+            set_just_field_abi(),
+            vec![json!(calldata.values[1].0.to_string())],
+        )
+        .with_nonces(app_nonce, fee_nonce)
+        .send()
+        .await
+        .unwrap();
+        harness.verify().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay_round_trip() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond("pxe_getBlockNumber", json!(7));
+        mock.respond(
+            "pxe_getTxReceipt",
+            json!({ "txHash": "0x01", "status": "success" }),
+        );
+
+        let recorder = RpcRecorder::new();
+        let pxe = mock.client().with_recorder(recorder.clone());
+        pxe.get_node_info().await.unwrap();
+        pxe.get_block_number().await.unwrap();
+        pxe.get_tx_receipt("0x01").await.unwrap();
+
+        let harness = ReplayHarness::start(recorder.trace()).await.unwrap();
+        let replayed = harness.client();
+        assert_eq!(replayed.get_node_info().await.unwrap().l1_chain_id, 31337);
+        assert_eq!(replayed.get_block_number().await.unwrap(), 7);
+        replayed.get_tx_receipt("0x01").await.unwrap();
+
+        assert_eq!(recorder.trace().exchanges.len(), 3);
+        harness.verify().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_verify_reports_first_divergent_request() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond("pxe_getBlockNumber", json!(1));
+        let recorder = RpcRecorder::new();
+        let pxe = mock.client().with_recorder(recorder.clone());
+        pxe.get_block_number().await.unwrap();
+
+        let harness = ReplayHarness::start(recorder.trace()).await.unwrap();
+        let _ = harness.client().get_contracts().await;
+
+        let mismatch = harness.verify().unwrap_err();
+        assert_eq!(mismatch.index, 0);
+        assert_eq!(mismatch.method, "pxe_getBlockNumber");
+    }
+}
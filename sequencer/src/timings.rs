@@ -0,0 +1,255 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::encoder::FunctionSelector;
+use crate::state::StateStore;
+
+const KEY_PREFIX: &str = "timings/";
+
+/// Upper bounds of the histogram buckets, in milliseconds. Proofs take
+/// seconds to minutes; simulations and sends far less.
+pub const BUCKETS_MS: [u64; 12] = [
+    50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000, 20_000, 40_000, 80_000, 160_000,
+];
+
+/// The PXE round trips a send is made of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    /// `simulateTx`.
+    Simulate,
+    /// `proveTx`.
+    Prove,
+    /// `sendTx`.
+    Send,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Stage::Simulate => "simulate",
+            Stage::Prove => "prove",
+            Stage::Send => "send",
+        })
+    }
+}
+
+/// Durations counted into `BUCKETS_MS`, plus one bucket for anything
+/// slower.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Histogram {
+    pub counts: Vec<u64>,
+    pub count: u64,
+    pub sum_ms: u64,
+    pub max_ms: u64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Histogram {
+            counts: vec![0; BUCKETS_MS.len() + 1],
+            count: 0,
+            sum_ms: 0,
+            max_ms: 0,
+        }
+    }
+}
+
+impl Histogram {
+    pub fn observe(&mut self, elapsed: Duration) {
+        let ms = elapsed.as_millis().min(u64::MAX as u128) as u64;
+        let bucket = BUCKETS_MS.partition_point(|&bound| bound < ms);
+        self.counts[bucket] += 1;
+        self.count += 1;
+        self.sum_ms = self.sum_ms.saturating_add(ms);
+        self.max_ms = self.max_ms.max(ms);
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        (self.count > 0).then(|| Duration::from_millis(self.sum_ms / self.count))
+    }
+
+    /// An upper estimate of the `p`th percentile: the bound of the bucket
+    /// it falls in, or the slowest duration seen if that is lower.
+    pub fn percentile(&self, p: u64) -> Option<Duration> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = (self.count * p).div_ceil(100).max(1);
+        let mut seen = 0;
+        for (bucket, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let bound = BUCKETS_MS.get(bucket).copied().unwrap_or(u64::MAX);
+                return Some(Duration::from_millis(bound.min(self.max_ms)));
+            }
+        }
+        None
+    }
+}
+
+/// One function's durations at one stage.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallTiming {
+    pub contract: String,
+    pub selector: String,
+    /// The function's name, for reports; the selector is what's keyed on.
+    pub function: String,
+    pub stage: Stage,
+    pub histogram: Histogram,
+}
+
+/// How long the PXE takes to simulate, prove and send each (contract,
+/// function selector)'s txs, so expensive functions can be told apart
+/// from cheap ones. Kept in a `StateStore`, which `sequencer stats` reads.
+#[derive(Debug)]
+pub struct CallTimings {
+    store: Arc<StateStore>,
+    lock: Mutex<()>,
+}
+
+impl CallTimings {
+    pub fn new(store: Arc<StateStore>) -> Self {
+        CallTimings {
+            store,
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn observe(
+        &self,
+        contract: &str,
+        selector: &FunctionSelector,
+        function: &str,
+        stage: Stage,
+        elapsed: Duration,
+    ) -> Result<(), String> {
+        let contract = contract.to_lowercase();
+        let key = format!("{}{}:{}:{}", KEY_PREFIX, contract, selector.0, stage);
+        let _guard = self.lock.lock().unwrap();
+        let mut timing = self.store.get(&key)?.unwrap_or_else(|| CallTiming {
+            contract,
+            selector: selector.0.clone(),
+            function: function.to_string(),
+            stage,
+            histogram: Histogram::default(),
+        });
+        timing.histogram.observe(elapsed);
+        self.store.put(&key, &timing)
+    }
+
+    /// Every function timed so far, by contract, selector and stage.
+    pub fn snapshot(&self) -> Result<Vec<CallTiming>, String> {
+        let mut timings = vec![];
+        for key in self.store.keys(KEY_PREFIX) {
+            timings.extend(self.store.get::<CallTiming>(&key)?);
+        }
+        Ok(timings)
+    }
+
+    /// The histograms in the Prometheus text format, as
+    /// `sequencer_call_duration_seconds`.
+    pub fn prometheus(&self) -> Result<String, String> {
+        const NAME: &str = "sequencer_call_duration_seconds";
+        let mut out = format!(
+            "# HELP {} PXE time per contract function and stage.\n# TYPE {} histogram\n",
+            NAME, NAME
+        );
+        for timing in self.snapshot()? {
+            let labels = format!(
+                "contract=\"{}\",selector=\"{}\",function=\"{}\",stage=\"{}\"",
+                timing.contract,
+                timing.selector,
+                escape(&timing.function),
+                timing.stage
+            );
+            let histogram = &timing.histogram;
+            let mut cumulative = 0;
+            for (bound, count) in BUCKETS_MS.iter().zip(&histogram.counts) {
+                cumulative += count;
+                let le = *bound as f64 / 1000.0;
+                let _ = writeln!(
+                    out,
+                    "{}_bucket{{{},le=\"{}\"}} {}",
+                    NAME, labels, le, cumulative
+                );
+            }
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{},le=\"+Inf\"}} {}",
+                NAME, labels, histogram.count
+            );
+            let sum = histogram.sum_ms as f64 / 1000.0;
+            let _ = writeln!(out, "{}_sum{{{}}} {}", NAME, labels, sum);
+            let _ = writeln!(out, "{}_count{{{}}} {}", NAME, labels, histogram.count);
+        }
+        Ok(out)
+    }
+}
+
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histograms_per_function_and_stage() {
+        let timings = CallTimings::new(Arc::new(StateStore::in_memory()));
+        let selector = FunctionSelector("27e740b2".to_string());
+        for ms in [800, 1_200, 3_000, 200_000] {
+            let elapsed = Duration::from_millis(ms);
+            timings
+                .observe("0x0C", &selector, "set_feeds", Stage::Prove, elapsed)
+                .unwrap();
+        }
+        timings
+            .observe(
+                "0x0c",
+                &selector,
+                "set_feeds",
+                Stage::Send,
+                Duration::from_millis(40),
+            )
+            .unwrap();
+
+        let snapshot = timings.snapshot().unwrap();
+        assert_eq!(snapshot.len(), 2);
+        let prove = snapshot.iter().find(|t| t.stage == Stage::Prove).unwrap();
+        assert_eq!(prove.contract, "0x0c");
+        assert_eq!(prove.histogram.count, 4);
+        assert_eq!(prove.histogram.mean(), Some(Duration::from_millis(51_250)));
+        assert_eq!(
+            prove.histogram.percentile(50),
+            Some(Duration::from_millis(2_500))
+        );
+        assert_eq!(
+            prove.histogram.percentile(95),
+            Some(Duration::from_millis(200_000))
+        );
+        assert_eq!(Histogram::default().percentile(50), None);
+
+        let text = timings.prometheus().unwrap();
+        let labels =
+            "contract=\"0x0c\",selector=\"27e740b2\",function=\"set_feeds\",stage=\"prove\"";
+        assert!(text.contains(&format!(
+            "sequencer_call_duration_seconds_bucket{{{},le=\"2.5\"}} 2",
+            labels
+        )));
+        assert!(text.contains(&format!(
+            "sequencer_call_duration_seconds_bucket{{{},le=\"+Inf\"}} 4",
+            labels
+        )));
+        assert!(text.contains(&format!(
+            "sequencer_call_duration_seconds_sum{{{}}} 205",
+            labels
+        )));
+    }
+}
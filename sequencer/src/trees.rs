@@ -0,0 +1,491 @@
+use std::collections::BTreeMap;
+
+use crate::block::AppendOnlyTreeSnapshot;
+use crate::fields::Fr;
+use crate::merkle::{in_gap, verify_membership, LeafWitness, NullifierLeaf, PublicDataLeaf};
+use crate::notes::Poseidon2;
+
+/// The height of the node's note hash tree (and of its nullifier and
+/// public data trees).
+pub const NOTE_HASH_TREE_HEIGHT: usize = 40;
+
+/// A fixed-depth append-only Merkle tree, like the node's note hash tree
+/// and archive. Unfilled leaves are zero.
+pub struct AppendOnlyTree<H> {
+    hasher: H,
+    depth: usize,
+    // levels[0] holds the leaves; missing nodes are the empty subtree root.
+    levels: Vec<Vec<Fr>>,
+    zeros: Vec<Fr>,
+}
+
+impl<H: Poseidon2> AppendOnlyTree<H> {
+    pub fn new(hasher: H, depth: usize) -> Result<Self, String> {
+        if depth == 0 || depth > 63 {
+            return Err(format!("Tree depth must be 1 to 63, not {}", depth));
+        }
+        let mut zeros = vec![Fr::zero()];
+        for level in 0..depth {
+            let zero = zeros[level].clone();
+            zeros.push(hasher.hash(&[zero.clone(), zero]));
+        }
+        Ok(AppendOnlyTree {
+            hasher,
+            depth,
+            levels: vec![vec![]; depth + 1],
+            zeros,
+        })
+    }
+
+    pub fn hasher(&self) -> &H {
+        &self.hasher
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn size(&self) -> u64 {
+        self.levels[0].len() as u64
+    }
+
+    pub fn root(&self) -> Fr {
+        self.node(self.depth, 0)
+    }
+
+    /// The root and leaf count, as a block header records the tree.
+    pub fn snapshot(&self) -> AppendOnlyTreeSnapshot {
+        AppendOnlyTreeSnapshot {
+            root: self.root(),
+            next_available_leaf_index: self.size(),
+        }
+    }
+
+    pub fn leaf(&self, index: u64) -> Option<&Fr> {
+        self.levels[0].get(index as usize)
+    }
+
+    /// The first index holding `leaf`.
+    pub fn find(&self, leaf: &Fr) -> Option<u64> {
+        self.levels[0]
+            .iter()
+            .position(|l| l == leaf)
+            .map(|i| i as u64)
+    }
+
+    /// Adds `leaf` and returns its index.
+    pub fn append(&mut self, leaf: Fr) -> Result<u64, String> {
+        let index = self.size();
+        if index >> self.depth != 0 {
+            return Err(format!("Tree of depth {} is full", self.depth));
+        }
+        self.set(index, leaf);
+        Ok(index)
+    }
+
+    /// Replaces the leaf at `index`; indexed trees relink leaves in place.
+    pub fn update(&mut self, index: u64, leaf: Fr) -> Result<(), String> {
+        if index >= self.size() {
+            return Err(format!("No leaf at index {}", index));
+        }
+        self.set(index, leaf);
+        Ok(())
+    }
+
+    pub fn sibling_path(&self, index: u64) -> Result<Vec<Fr>, String> {
+        if index >= self.size() {
+            return Err(format!("No leaf at index {}", index));
+        }
+        Ok((0..self.depth)
+            .map(|level| self.node(level, (index >> level) ^ 1))
+            .collect())
+    }
+
+    fn node(&self, level: usize, index: u64) -> Fr {
+        self.levels[level]
+            .get(index as usize)
+            .cloned()
+            .unwrap_or_else(|| self.zeros[level].clone())
+    }
+
+    fn set(&mut self, index: u64, leaf: Fr) {
+        let mut node = leaf;
+        let mut index = index;
+        for level in 0..=self.depth {
+            let nodes = &mut self.levels[level];
+            match nodes.get_mut(index as usize) {
+                Some(slot) => *slot = node.clone(),
+                None => nodes.push(node.clone()),
+            }
+            if level == self.depth {
+                break;
+            }
+            let sibling = self.node(level, index ^ 1);
+            node = if index & 1 == 0 {
+                self.hasher.hash(&[node, sibling])
+            } else {
+                self.hasher.hash(&[sibling, node])
+            };
+            index >>= 1;
+        }
+    }
+}
+
+/// A leaf of an indexed tree: leaves stay in insertion order, but each
+/// points at the leaf with the next larger key.
+pub trait IndexedLeaf: Clone {
+    /// The leaf every indexed tree starts with, key zero.
+    fn zero() -> Self;
+    fn key(&self) -> &Fr;
+    fn next_key(&self) -> &Fr;
+    fn next_index(&self) -> u64;
+    fn link(&mut self, next_key: Fr, next_index: u64);
+    /// Folds a new leaf for an existing key into this one.
+    fn merge(&mut self, new: Self) -> Result<(), String>;
+    fn hash<H: Poseidon2>(&self, hasher: &H) -> Fr;
+}
+
+impl IndexedLeaf for NullifierLeaf {
+    fn zero() -> Self {
+        NullifierLeaf {
+            nullifier: Fr::zero(),
+            next_nullifier: Fr::zero(),
+            next_index: 0,
+        }
+    }
+
+    fn key(&self) -> &Fr {
+        &self.nullifier
+    }
+
+    fn next_key(&self) -> &Fr {
+        &self.next_nullifier
+    }
+
+    fn next_index(&self) -> u64 {
+        self.next_index
+    }
+
+    fn link(&mut self, next_key: Fr, next_index: u64) {
+        self.next_nullifier = next_key;
+        self.next_index = next_index;
+    }
+
+    fn merge(&mut self, new: Self) -> Result<(), String> {
+        Err(format!(
+            "Nullifier {} already exists",
+            new.nullifier.to_hex()
+        ))
+    }
+
+    fn hash<H: Poseidon2>(&self, hasher: &H) -> Fr {
+        NullifierLeaf::hash(self, hasher)
+    }
+}
+
+impl IndexedLeaf for PublicDataLeaf {
+    fn zero() -> Self {
+        PublicDataLeaf {
+            slot: Fr::zero(),
+            value: Fr::zero(),
+            next_slot: Fr::zero(),
+            next_index: 0,
+        }
+    }
+
+    fn key(&self) -> &Fr {
+        &self.slot
+    }
+
+    fn next_key(&self) -> &Fr {
+        &self.next_slot
+    }
+
+    fn next_index(&self) -> u64 {
+        self.next_index
+    }
+
+    fn link(&mut self, next_key: Fr, next_index: u64) {
+        self.next_slot = next_key;
+        self.next_index = next_index;
+    }
+
+    /// A write to a slot already in the tree overwrites its value.
+    fn merge(&mut self, new: Self) -> Result<(), String> {
+        self.value = new.value;
+        Ok(())
+    }
+
+    fn hash<H: Poseidon2>(&self, hasher: &H) -> Fr {
+        PublicDataLeaf::hash(self, hasher)
+    }
+}
+
+/// An indexed Merkle tree, like the node's nullifier and public data
+/// trees: it proves a key present with its leaf and absent with the "low"
+/// leaf whose key is the largest below it.
+pub struct IndexedTree<H, L> {
+    tree: AppendOnlyTree<H>,
+    leaves: Vec<L>,
+    keys: BTreeMap<Fr, u64>,
+}
+
+impl<H: Poseidon2, L: IndexedLeaf> IndexedTree<H, L> {
+    pub fn new(hasher: H, depth: usize) -> Result<Self, String> {
+        let mut indexed = IndexedTree {
+            tree: AppendOnlyTree::new(hasher, depth)?,
+            leaves: vec![],
+            keys: BTreeMap::new(),
+        };
+        let zero = L::zero();
+        indexed.tree.append(zero.hash(indexed.tree.hasher()))?;
+        indexed.keys.insert(zero.key().clone(), 0);
+        indexed.leaves.push(zero);
+        Ok(indexed)
+    }
+
+    pub fn root(&self) -> Fr {
+        self.tree.root()
+    }
+
+    pub fn snapshot(&self) -> AppendOnlyTreeSnapshot {
+        self.tree.snapshot()
+    }
+
+    pub fn leaf(&self, index: u64) -> Option<&L> {
+        self.leaves.get(index as usize)
+    }
+
+    /// Inserts `leaf` (its links are ignored) and returns its index. A key
+    /// already in the tree is merged into its leaf in place.
+    pub fn insert(&mut self, mut leaf: L) -> Result<u64, String> {
+        if let Some(&index) = self.keys.get(leaf.key()) {
+            self.leaves[index as usize].merge(leaf)?;
+            self.rehash(index)?;
+            return Ok(index);
+        }
+        let low = self.low_index(leaf.key());
+        let index = self.tree.size();
+        let low_leaf = &self.leaves[low as usize];
+        leaf.link(low_leaf.next_key().clone(), low_leaf.next_index());
+        self.tree.append(leaf.hash(self.tree.hasher()))?;
+        self.leaves[low as usize].link(leaf.key().clone(), index);
+        self.rehash(low)?;
+        self.keys.insert(leaf.key().clone(), index);
+        self.leaves.push(leaf);
+        Ok(index)
+    }
+
+    /// The leaf holding `key` with its proof, as `getNullifierMembershipWitness`
+    /// returns it.
+    pub fn find(&self, key: &Fr) -> Option<LeafWitness<L>> {
+        self.keys.get(key).map(|&index| self.witness(index))
+    }
+
+    /// The leaf whose gap `key` falls in, proving `key` absent; an error
+    /// when `key` is in the tree.
+    pub fn low_leaf(&self, key: &Fr) -> Result<LeafWitness<L>, String> {
+        if self.keys.contains_key(key) {
+            return Err(format!("{} is in the tree", key.to_hex()));
+        }
+        Ok(self.witness(self.low_index(key)))
+    }
+
+    fn witness(&self, index: u64) -> LeafWitness<L> {
+        LeafWitness {
+            index,
+            leaf: self.leaves[index as usize].clone(),
+            sibling_path: self
+                .tree
+                .sibling_path(index)
+                .expect("every leaf index is in the tree"),
+        }
+    }
+
+    // The zero leaf is below every other key, so there always is one.
+    fn low_index(&self, key: &Fr) -> u64 {
+        self.keys
+            .range(..key)
+            .next_back()
+            .map(|(_, &index)| index)
+            .unwrap_or(0)
+    }
+
+    fn rehash(&mut self, index: u64) -> Result<(), String> {
+        let hash = self.leaves[index as usize].hash(self.tree.hasher());
+        self.tree.update(index, hash)
+    }
+}
+
+/// Checks that `witness` proves `key` absent from the tree `snapshot`
+/// describes: its leaf is in the tree and `key` falls in the leaf's gap.
+pub fn verify_non_membership<H: Poseidon2, L: IndexedLeaf>(
+    hasher: &H,
+    key: &Fr,
+    witness: &LeafWitness<L>,
+    snapshot: &AppendOnlyTreeSnapshot,
+) -> Result<(), String> {
+    let leaf = &witness.leaf;
+    if !in_gap(key, leaf.key(), leaf.next_key()) {
+        return Err(format!(
+            "Low leaf {} does not bound {}",
+            leaf.key().to_hex(),
+            key.to_hex()
+        ));
+    }
+    verify_membership(
+        hasher,
+        &leaf.hash(hasher),
+        witness.index,
+        &witness.sibling_path,
+        snapshot,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::Bn254Poseidon2;
+
+    #[test]
+    fn test_append_only_tree_proves_its_leaves() {
+        let mut tree = AppendOnlyTree::new(Bn254Poseidon2, 3).unwrap();
+        let empty = tree.root();
+        assert_eq!(
+            empty,
+            AppendOnlyTree::new(Bn254Poseidon2, 3).unwrap().zeros[3]
+        );
+
+        for i in 1..=5u64 {
+            assert_eq!(tree.append(Fr::from(i * 11)).unwrap(), i - 1);
+        }
+        assert_eq!(
+            tree.root(),
+            Fr::try_from("0x0c7e9e40d698c6b235af4b139b210a38936f3e877446d66ca322e4695b9ceeac")
+                .unwrap()
+        );
+        assert_eq!(tree.snapshot().next_available_leaf_index, 5);
+        assert_eq!(tree.find(&Fr::from(33u8)), Some(2));
+        for i in 0..5 {
+            let path = tree.sibling_path(i).unwrap();
+            assert_eq!(path.len(), 3);
+            verify_membership(
+                &Bn254Poseidon2,
+                tree.leaf(i).unwrap(),
+                i,
+                &path,
+                &tree.snapshot(),
+            )
+            .unwrap();
+        }
+        assert!(tree.sibling_path(5).is_err());
+
+        let before = tree.root();
+        tree.update(1, Fr::from(7u8)).unwrap();
+        assert_ne!(tree.root(), before);
+        let path = tree.sibling_path(1).unwrap();
+        verify_membership(&Bn254Poseidon2, &Fr::from(7u8), 1, &path, &tree.snapshot()).unwrap();
+
+        let mut small = AppendOnlyTree::new(Bn254Poseidon2, 1).unwrap();
+        small.append(Fr::from(1u8)).unwrap();
+        small.append(Fr::from(2u8)).unwrap();
+        assert!(small.append(Fr::from(3u8)).is_err());
+        assert_eq!(
+            small.root(),
+            Bn254Poseidon2.hash(&[Fr::from(1u8), Fr::from(2u8)])
+        );
+        assert!(AppendOnlyTree::new(Bn254Poseidon2, 0).is_err());
+
+        let empty = AppendOnlyTree::new(Bn254Poseidon2, NOTE_HASH_TREE_HEIGHT).unwrap();
+        assert_eq!(
+            empty.zeros[1],
+            Fr::try_from("0x0b63a53787021a4a962a452c2921b3663aff1ffd8d5510540f8e659e782956f1")
+                .unwrap()
+        );
+        assert_eq!(
+            empty.root(),
+            Fr::try_from("0x1fd848aa69e1633722fe249a5b7f53b094f1c9cef9f5c694b073fd1cc5850dfb")
+                .unwrap()
+        );
+    }
+
+    fn nullifier(value: u64) -> NullifierLeaf {
+        NullifierLeaf {
+            nullifier: Fr::from(value),
+            ..IndexedLeaf::zero()
+        }
+    }
+
+    #[test]
+    fn test_indexed_tree_links_keys_in_order() {
+        let mut tree: IndexedTree<Bn254Poseidon2, NullifierLeaf> =
+            IndexedTree::new(Bn254Poseidon2, 4).unwrap();
+        for value in [10, 5, 20] {
+            tree.insert(nullifier(value)).unwrap();
+        }
+        assert!(tree.insert(nullifier(5)).is_err());
+
+        // Insertion order: 0, 10, 5, 20.
+        assert_eq!(tree.leaf(0).unwrap().next_nullifier, Fr::from(5u8));
+        assert_eq!(tree.leaf(2).unwrap().next_index, 1);
+        assert_eq!(tree.leaf(1).unwrap().next_nullifier, Fr::from(20u8));
+        assert!(tree.leaf(3).unwrap().next_nullifier.is_zero());
+
+        let snapshot = tree.snapshot();
+        assert_eq!(
+            snapshot.root,
+            Fr::try_from("0x06ff6be076078694538277bf27aeb80804005cc81be2b38471a450c423de76b9")
+                .unwrap()
+        );
+        for value in [5u64, 10, 20] {
+            let witness = tree.find(&Fr::from(value)).unwrap();
+            let leaf = witness.leaf.hash(&Bn254Poseidon2);
+            verify_membership(
+                &Bn254Poseidon2,
+                &leaf,
+                witness.index,
+                &witness.sibling_path,
+                &snapshot,
+            )
+            .unwrap();
+        }
+        let low = tree.low_leaf(&Fr::from(7u8)).unwrap();
+        assert_eq!(low.leaf.nullifier, Fr::from(5u8));
+        verify_non_membership(&Bn254Poseidon2, &Fr::from(7u8), &low, &snapshot).unwrap();
+        let above = tree.low_leaf(&Fr::from(30u8)).unwrap();
+        verify_non_membership(&Bn254Poseidon2, &Fr::from(30u8), &above, &snapshot).unwrap();
+        assert!(verify_non_membership(&Bn254Poseidon2, &Fr::from(12u8), &low, &snapshot).is_err());
+        assert!(tree.low_leaf(&Fr::from(10u8)).is_err());
+    }
+
+    #[test]
+    fn test_public_data_writes_overwrite_in_place() {
+        let mut tree: IndexedTree<Bn254Poseidon2, PublicDataLeaf> =
+            IndexedTree::new(Bn254Poseidon2, 3).unwrap();
+        let write = |slot: u64, value: u64| PublicDataLeaf {
+            slot: Fr::from(slot),
+            value: Fr::from(value),
+            ..IndexedLeaf::zero()
+        };
+        assert_eq!(tree.insert(write(3, 1)).unwrap(), 1);
+        let before = tree.root();
+        assert_eq!(tree.insert(write(3, 2)).unwrap(), 1);
+        assert_ne!(tree.root(), before);
+        assert_eq!(
+            tree.root(),
+            Fr::try_from("0x08d7e762738a5df85d824f4df3c49e758dbe675c60b7696f42911060bda89b06")
+                .unwrap()
+        );
+        assert_eq!(tree.snapshot().next_available_leaf_index, 2);
+        let witness = tree.find(&Fr::from(3u8)).unwrap();
+        assert_eq!(witness.leaf.value, Fr::from(2u8));
+        verify_membership(
+            &Bn254Poseidon2,
+            &witness.leaf.hash(&Bn254Poseidon2),
+            witness.index,
+            &witness.sibling_path,
+            &tree.snapshot(),
+        )
+        .unwrap();
+    }
+}
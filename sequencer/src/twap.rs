@@ -0,0 +1,208 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::feeds::FeedUpdate;
+use crate::fields::Fr;
+use crate::state::StateStore;
+
+const SAMPLES_KEY: &str = "feeds/twap";
+
+// One fetched price. Prices are kept as strings since the store's JSON
+// numbers stop at u64.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Sample {
+    timestamp: u64,
+    price: String,
+}
+
+/// A feed's latest fetched price next to its time-weighted average, for
+/// metrics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeedPrices {
+    pub spot: u128,
+    pub twap: u128,
+    /// Samples the average is taken over.
+    pub samples: usize,
+}
+
+/// Time-weighted average prices over a trailing `window`. Each fetched
+/// price counts for as long as it was the latest, so a burst of fetches
+/// weighs no more than one. Samples are snapshotted to the `StateStore`
+/// after every batch, so a restart keeps the window.
+#[derive(Debug)]
+pub struct Twap {
+    window: Duration,
+    store: Arc<StateStore>,
+    samples: BTreeMap<Fr, VecDeque<(u64, u128)>>,
+}
+
+impl Twap {
+    /// Picks up the samples `store` holds from an earlier run.
+    pub fn new(window: Duration, store: Arc<StateStore>) -> Result<Self, String> {
+        let snapshot: BTreeMap<Fr, Vec<Sample>> = store.get(SAMPLES_KEY)?.unwrap_or_default();
+        let samples = snapshot
+            .into_iter()
+            .map(|(feed_id, samples)| {
+                let samples = samples
+                    .into_iter()
+                    .map(|s| {
+                        let price = s.price.parse().map_err(|_| {
+                            format!("Invalid TWAP sample price in state: {}", s.price)
+                        })?;
+                        Ok((s.timestamp, price))
+                    })
+                    .collect::<Result<_, String>>()?;
+                Ok((feed_id, samples))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Twap {
+            window,
+            store,
+            samples,
+        })
+    }
+
+    /// Reads `FEED_TWAP_WINDOW` (seconds); `None` when it is unset, and
+    /// spot prices are pushed.
+    pub fn window_from_env() -> Result<Option<Duration>, String> {
+        match env::var("FEED_TWAP_WINDOW") {
+            Ok(secs) => secs
+                .parse()
+                .map(|secs| Some(Duration::from_secs(secs)))
+                .map_err(|_| format!("Invalid FEED_TWAP_WINDOW: {}", secs)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Adds `observations` as samples, forgets those that fell out of the
+    /// window at `now`, and snapshots the rest. Observations no newer than
+    /// a feed's last sample are ignored.
+    pub fn record(&mut self, observations: &[FeedUpdate], now: u64) -> Result<(), String> {
+        let start = now.saturating_sub(self.window.as_secs());
+        for observed in observations {
+            let samples = self.samples.entry(observed.feed_id.clone()).or_default();
+            if samples.back().is_none_or(|(t, _)| *t < observed.timestamp) {
+                samples.push_back((observed.timestamp, observed.price));
+            }
+        }
+        for samples in self.samples.values_mut() {
+            // The last sample before the window still sets its opening price.
+            while samples.get(1).is_some_and(|(t, _)| *t <= start) {
+                samples.pop_front();
+            }
+        }
+        self.snapshot()
+    }
+
+    /// The average over the window ending at `now`; `None` for feeds with
+    /// no samples.
+    pub fn average(&self, feed_id: &Fr, now: u64) -> Option<u128> {
+        let samples = self.samples.get(feed_id)?;
+        let start = now.saturating_sub(self.window.as_secs());
+        let mut weighted = 0u128;
+        let mut total = 0u64;
+        for (i, (timestamp, price)) in samples.iter().enumerate() {
+            let from = (*timestamp).max(start);
+            let to = samples.get(i + 1).map_or(now, |(next, _)| *next).min(now);
+            if to > from {
+                weighted = weighted.saturating_add(price.saturating_mul((to - from) as u128));
+                total += to - from;
+            }
+        }
+        match total {
+            0 => samples.back().map(|(_, price)| *price),
+            total => Some(weighted / total as u128),
+        }
+    }
+
+    pub fn prices(&self, feed_id: &Fr, now: u64) -> Option<FeedPrices> {
+        let samples = self.samples.get(feed_id)?;
+        Some(FeedPrices {
+            spot: samples.back()?.1,
+            twap: self.average(feed_id, now)?,
+            samples: samples.len(),
+        })
+    }
+
+    fn snapshot(&self) -> Result<(), String> {
+        let snapshot: BTreeMap<&Fr, Vec<Sample>> = self
+            .samples
+            .iter()
+            .map(|(feed_id, samples)| {
+                let samples = samples
+                    .iter()
+                    .map(|(timestamp, price)| Sample {
+                        timestamp: *timestamp,
+                        price: price.to_string(),
+                    })
+                    .collect();
+                (feed_id, samples)
+            })
+            .collect();
+        self.store.put(SAMPLES_KEY, &snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feed_policy::{FeedPolicies, FeedScheduler};
+
+    fn update(price: u128, timestamp: u64) -> FeedUpdate {
+        FeedUpdate {
+            feed_id: Fr::from(7u8),
+            price,
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn test_twap_weights_prices_by_time_and_survives_restart() {
+        let store = Arc::new(StateStore::in_memory());
+        let mut twap = Twap::new(Duration::from_secs(100), store.clone()).unwrap();
+        let feed = Fr::from(7u8);
+        twap.record(&[update(1_000, 0)], 0).unwrap();
+        assert_eq!(twap.average(&feed, 0), Some(1_000));
+        twap.record(&[update(2_000, 75)], 75).unwrap();
+        // A repeat of the last timestamp is ignored.
+        twap.record(&[update(9_000, 75)], 75).unwrap();
+
+        // 75s at 1000, 25s at 2000.
+        assert_eq!(twap.average(&feed, 100), Some(1_250));
+        // The window now opens at 50: 25s at 1000, 75s at 2000.
+        assert_eq!(twap.average(&feed, 150), Some(1_750));
+        assert_eq!(twap.average(&Fr::from(8u8), 150), None);
+
+        let restarted = Twap::new(Duration::from_secs(100), store).unwrap();
+        let prices = restarted.prices(&feed, 150).unwrap();
+        assert_eq!(
+            prices,
+            FeedPrices {
+                spot: 2_000,
+                twap: 1_750,
+                samples: 2
+            }
+        );
+
+        let mut scheduler = FeedScheduler::new(FeedPolicies::default()).with_twap(restarted);
+        let smoothed = scheduler.smooth(&[update(3_000, 175)], 175).unwrap();
+        // The window opens at 75: 100s at 2000, none yet at 3000.
+        assert_eq!(smoothed, [update(2_000, 175)]);
+        assert_eq!(
+            scheduler
+                .twap()
+                .unwrap()
+                .prices(&feed, 175)
+                .unwrap()
+                .samples,
+            2
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use futures_util::future::BoxFuture;
+use http_body_util::{BodyExt, Full};
+use hyper::body::Bytes;
+use hyper::client::conn::http1;
+use hyper::Request;
+use hyper_util::rt::TokioIo;
+use serde_json::Value;
+use std::path::PathBuf;
+use tokio::net::UnixStream;
+
+use crate::error::AztecError;
+use crate::layers::RpcService;
+
+/// URLs of this scheme name a Unix socket: `unix:///run/pxe.sock`.
+pub const UNIX_SCHEME: &str = "unix://";
+
+/// JSON-RPC over HTTP on a Unix domain socket, for a PXE on the same host.
+/// Each call opens its own connection, which is cheap on a local socket.
+/// `AztecRpcClient` picks it for `unix://` URLs.
+#[derive(Debug, Clone)]
+pub struct UnixTransport {
+    path: PathBuf,
+}
+
+impl UnixTransport {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        UnixTransport { path: path.into() }
+    }
+
+    /// `None` unless `url` is a `unix://` URL.
+    pub fn from_url(url: &str) -> Option<Self> {
+        url.strip_prefix(UNIX_SCHEME).map(UnixTransport::new)
+    }
+
+    async fn post(&self, request: Value) -> Result<Value, AztecError> {
+        let transport = |e: &dyn std::fmt::Display| {
+            AztecError::Transport(format!("{}: {}", self.path.display(), e))
+        };
+        let stream = UnixStream::connect(&self.path)
+            .await
+            .map_err(|e| transport(&e))?;
+        let (mut sender, connection) = http1::handshake(TokioIo::new(stream))
+            .await
+            .map_err(|e| transport(&e))?;
+        tokio::spawn(connection);
+
+        let request = Request::post("/")
+            .header("host", "localhost")
+            .header("content-type", "application/json")
+            .body(Full::new(Bytes::from(request.to_string())))
+            .map_err(|e| transport(&e))?;
+        let response = sender
+            .send_request(request)
+            .await
+            .map_err(|e| transport(&e))?;
+        let body = response
+            .into_body()
+            .collect()
+            .await
+            .map_err(|e| transport(&e))?
+            .to_bytes();
+        Ok(serde_json::from_slice(&body)?)
+    }
+}
+
+impl RpcService for UnixTransport {
+    fn call(&self, request: Value) -> BoxFuture<'_, Result<Value, AztecError>> {
+        Box::pin(self.post(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aztec_rpc_client::AztecRpcClient;
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::UnixListener;
+
+    #[tokio::test]
+    async fn test_unix_url_calls_over_the_socket() {
+        let path = std::env::temp_dir().join(format!("pxe-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 4096];
+            let n = stream.read(&mut request).await.unwrap();
+            let request = String::from_utf8_lossy(&request[..n]).to_string();
+            assert!(request.starts_with("POST / HTTP/1.1"), "{}", request);
+            assert!(request.contains("\"method\":\"pxe_getBlockNumber\""));
+            let body = json!({ "jsonrpc": "2.0", "id": 1, "result": 12 }).to_string();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        let url = format!("{}{}", UNIX_SCHEME, path.display());
+        let pxe = AztecRpcClient::new(url, Some("pxe".to_string()));
+        assert_eq!(pxe.get_block_number().await.unwrap(), 12);
+        assert!(matches!(
+            pxe.get_block_number().await.unwrap_err(),
+            AztecError::Transport(_)
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,249 @@
+use serde_json::{json, Value};
+use std::fmt;
+
+use crate::contract::SimulateOptions;
+
+/// An aztec-packages release as reported by `getPXEInfo` / `getNodeInfo`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProtocolVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl ProtocolVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        ProtocolVersion {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Accepts `0.86.0`, `v0.86.0` and pre-release tags such as
+    /// `0.86.0-alpha-testnet.2`; the tag is ignored.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let trimmed = input.trim().trim_start_matches('v');
+        let core = trimmed.split(['-', '+']).next().unwrap_or_default();
+        let parts: Vec<&str> = core.split('.').collect();
+        if parts.len() != 3 {
+            return Err(format!("Invalid aztec-packages version '{}'", input));
+        }
+
+        let number = |part: &str| {
+            part.parse::<u32>()
+                .map_err(|_| format!("Invalid aztec-packages version '{}'", input))
+        };
+        Ok(ProtocolVersion::new(
+            number(parts[0])?,
+            number(parts[1])?,
+            number(parts[2])?,
+        ))
+    }
+}
+
+impl fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Payload shapes that differ between supported sandbox releases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PayloadProfile {
+    /// 0.82.x – 0.84.x: no `capsules` on the tx request and `simulateTx`
+    /// takes `(txRequest, simulatePublic, msgSender, skipTxValidation)`.
+    Legacy,
+    /// 0.85.x – 0.87.x: the shape recorded from aztec.js in `tx_request`.
+    #[default]
+    Current,
+}
+
+/// Inclusive lower bound, exclusive upper bound.
+pub const COMPATIBILITY: &[(ProtocolVersion, ProtocolVersion, PayloadProfile)] = &[
+    (
+        ProtocolVersion::new(0, 82, 0),
+        ProtocolVersion::new(0, 85, 0),
+        PayloadProfile::Legacy,
+    ),
+    (
+        ProtocolVersion::new(0, 85, 0),
+        ProtocolVersion::new(0, 88, 0),
+        PayloadProfile::Current,
+    ),
+];
+
+impl PayloadProfile {
+    pub fn for_version(version: ProtocolVersion) -> Result<Self, String> {
+        COMPATIBILITY
+            .iter()
+            .find(|(min, max, _)| *min <= version && version < *max)
+            .map(|(_, _, profile)| *profile)
+            .ok_or_else(|| {
+                let (min, _, _) = COMPATIBILITY[0];
+                let (_, max, _) = COMPATIBILITY[COMPATIBILITY.len() - 1];
+                format!(
+                    "Unsupported aztec-packages version {} (supported: >= {}, < {})",
+                    version, min, max
+                )
+            })
+    }
+
+    /// Adapts a canonical (`Current`) tx request to this profile.
+    pub fn tx_request(&self, mut tx_request: Value) -> Value {
+        if *self == PayloadProfile::Legacy {
+            if let Some(object) = tx_request.as_object_mut() {
+                object.remove("capsules");
+            }
+        }
+        tx_request
+    }
+
+    /// Lays out `simulateTx` params. Trailing `scopes` is only sent when set,
+    /// and `skipFeeEnforcement` does not exist on `Legacy` PXEs.
+    pub fn simulate_params(&self, tx_request: Value, options: &SimulateOptions) -> Vec<Value> {
+        let mut params = vec![
+            self.tx_request(tx_request),
+            json!(options.simulate_public),
+            json!(options.msg_sender_override),
+            json!(options.skip_tx_validation),
+        ];
+        if *self == PayloadProfile::Current {
+            params.push(json!(options.skip_fee_enforcement));
+        }
+        if let Some(scopes) = &options.scopes {
+            params.push(json!(scopes));
+        }
+        params
+    }
+}
+
+/// Pulls the release string out of a `getPXEInfo` or `getNodeInfo` result.
+pub fn version_from_info(info: &Value) -> Option<&str> {
+    info.get("pxeVersion")
+        .or_else(|| info.get("nodeVersion"))
+        .and_then(Value::as_str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contract::ContractFunctionInteraction;
+    use crate::encoder::{AbiParameter, AbiType, FunctionAbi};
+    use crate::testing::MockPxe;
+    use crate::tx_request::DEFAULT_ORIGIN;
+
+    fn set_just_field_abi() -> FunctionAbi {
+        FunctionAbi {
+            name: "set_just_field".to_string(),
+            function_type: "public".to_string(),
+            isInternal: false,
+            isStatic: false,
+            isInitializer: false,
+            parameters: vec![AbiParameter {
+                name: "value".to_string(),
+                abi_type: AbiType::Field,
+            }],
+            return_types: vec![],
+            errorTypes: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_versions() {
+        assert_eq!(
+            ProtocolVersion::parse("0.86.0").unwrap(),
+            ProtocolVersion::new(0, 86, 0)
+        );
+        assert_eq!(
+            ProtocolVersion::parse("v0.83.1-alpha-testnet.2").unwrap(),
+            ProtocolVersion::new(0, 83, 1)
+        );
+        assert!(ProtocolVersion::parse("0.86").is_err());
+        assert!(ProtocolVersion::parse("latest").is_err());
+    }
+
+    #[test]
+    fn test_compatibility_matrix() {
+        let profile = |v: &str| PayloadProfile::for_version(ProtocolVersion::parse(v).unwrap());
+        assert_eq!(profile("0.82.0").unwrap(), PayloadProfile::Legacy);
+        assert_eq!(profile("0.84.9").unwrap(), PayloadProfile::Legacy);
+        assert_eq!(profile("0.85.0").unwrap(), PayloadProfile::Current);
+        assert_eq!(profile("0.87.2").unwrap(), PayloadProfile::Current);
+
+        let err = profile("0.90.0").unwrap_err();
+        assert!(err.contains("0.90.0"), "{}", err);
+        assert!(profile("0.81.0").is_err());
+    }
+
+    #[test]
+    fn test_legacy_profile_drops_capsules() {
+        let tx = json!({ "origin": "0x01", "capsules": [] });
+        let options = SimulateOptions::default();
+        let params = PayloadProfile::Legacy.simulate_params(tx.clone(), &options);
+        assert_eq!(params.len(), 4);
+        assert!(params[0].get("capsules").is_none());
+
+        let params = PayloadProfile::Current.simulate_params(tx.clone(), &options);
+        assert_eq!(params.len(), 5);
+        assert_eq!(params[0], tx);
+    }
+
+    #[test]
+    fn test_version_from_info_prefers_pxe_version() {
+        let info = json!({ "pxeVersion": "0.86.0", "nodeVersion": "0.85.0" });
+        assert_eq!(version_from_info(&info), Some("0.86.0"));
+        assert_eq!(
+            version_from_info(&json!({ "nodeVersion": "0.85.0" })),
+            Some("0.85.0")
+        );
+        assert_eq!(version_from_info(&json!({})), None);
+    }
+
+    #[tokio::test]
+    async fn test_negotiates_legacy_payloads_from_node_info() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond(
+            "pxe_getNodeInfo",
+            json!({ "nodeVersion": "0.83.2", "l1ChainId": 31337, "rollupVersion": 1 }),
+        );
+        mock.respond("pxe_simulateTx", json!({ "privateExecutionResult": {} }));
+
+        let mut pxe = mock.client();
+        let version = pxe.negotiate_version().await.unwrap();
+        assert_eq!(version, ProtocolVersion::new(0, 83, 2));
+        assert_eq!(pxe.profile(), PayloadProfile::Legacy);
+
+        ContractFunctionInteraction::new(
+            &pxe,
+            DEFAULT_ORIGIN,
+            DEFAULT_ORIGIN,
+            set_just_field_abi(),
+            vec![json!(1)],
+        )
+        .simulate(SimulateOptions::default())
+        .await
+        .unwrap();
+
+        let requests = mock.requests();
+        let simulate = requests
+            .iter()
+            .find(|r| r["method"] == "pxe_simulateTx")
+            .unwrap();
+        let params = simulate["params"].as_array().unwrap();
+        assert_eq!(params.len(), 4);
+        assert!(params[0].get("capsules").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_negotiation_rejects_unsupported_versions() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond("pxe_getPXEInfo", json!({ "pxeVersion": "0.99.0" }));
+
+        let mut pxe = mock.client();
+        let err = pxe.negotiate_version().await.unwrap_err().to_string();
+        assert!(err.contains("Unsupported"), "{}", err);
+        assert_eq!(pxe.profile(), PayloadProfile::Current);
+        assert_eq!(pxe.version(), None);
+    }
+}
@@ -0,0 +1,727 @@
+use k256::ecdsa::signature::hazmat::PrehashSigner;
+use k256::ecdsa::{Signature, SigningKey};
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::complete_address::{ensure_registered, CompleteAddress};
+use crate::encoder::FunctionSelector;
+use crate::fields::Fr;
+use crate::keystore::Keystore;
+use crate::notes::{generator_index, Poseidon2};
+use crate::pxe_api::PxeApi;
+use crate::secret::{Secret, Zeroizing};
+use crate::signing::{PedersenBlake2s, Schnorr, SchnorrChallenge, SchnorrKeyPair};
+use crate::tx_request::{AuthWitness, GasSettings, HashedValues, NodeInfo, TxExecutionRequest};
+
+/// An account that authorizes actions on its behalf, like aztec.js'
+/// `AccountWallet`: it turns a request hash into the auth witness its
+/// account contract checks.
+pub trait AccountWallet: Send + Sync {
+    fn address(&self) -> &str;
+    fn create_auth_witness(&self, request_hash: &Fr) -> Result<AuthWitness, String>;
+
+    /// Wraps `calls` in a call to this account's entrypoint, as aztec.js'
+    /// `DefaultAccountEntrypoint` does: the app and fee payloads become the
+    /// entrypoint's args, and their combined hash is signed into the
+    /// request's auth witness.
+    fn create_tx_execution_request(
+        &self,
+        calls: Vec<FunctionCall>,
+        options: EntrypointOptions,
+        node_info: &NodeInfo,
+        hasher: &dyn Poseidon2,
+    ) -> Result<TxExecutionRequest, String> {
+        let origin =
+            Fr::try_from(self.address()).map_err(|e| format!("Invalid account address: {}", e))?;
+        let nonce = match options.nonce {
+            Some(nonce) => nonce,
+            None => random_nonce()?,
+        };
+        let fee_nonce = match options.fee.nonce {
+            Some(nonce) => nonce,
+            None => random_nonce()?,
+        };
+        let app = EntrypointPayload::app(calls, nonce)?;
+        let fee = EntrypointPayload::fee(options.fee.calls, fee_nonce, options.fee.is_fee_payer)?;
+
+        let mut entrypoint_args = app.to_fields(hasher);
+        entrypoint_args.extend(fee.to_fields(hasher));
+        entrypoint_args.push(Fr::from(options.cancellable));
+        let entrypoint = HashedValues::from_args(hasher, entrypoint_args);
+        let witness = self.create_auth_witness(&combined_payload_hash(&app, &fee, hasher))?;
+
+        let mut args_of_calls = app.hashed_args(hasher);
+        args_of_calls.extend(fee.hashed_args(hasher));
+        Ok(TxExecutionRequest {
+            origin,
+            function_selector: FunctionSelector(ENTRYPOINT_SELECTOR.to_string()),
+            first_call_args_hash: entrypoint.hash.clone(),
+            tx_context: node_info.tx_context(options.fee.gas_settings),
+            args_of_calls: args_of_calls.into_iter().chain([entrypoint]).collect(),
+            auth_witnesses: [witness]
+                .into_iter()
+                .chain(options.fee.auth_witnesses)
+                .collect(),
+            capsules: vec![],
+        })
+    }
+}
+
+/// Selector of aztec-nr's account `entrypoint(app_payload, fee_payload,
+/// cancellable)`.
+pub const ENTRYPOINT_SELECTOR: &str = "27e740b2";
+
+/// How many calls an app payload and a fee payload hold; shorter lists are
+/// padded with `FunctionCall::empty()`.
+pub const APP_MAX_CALLS: usize = 4;
+pub const FEE_MAX_CALLS: usize = 2;
+
+// Fields per call in a payload's `to_fields`.
+const CALL_FIELDS: usize = 5;
+
+/// One call an account makes on its owner's behalf (aztec.js'
+/// `FunctionCall`). `args` are the encoded arguments; a public call hashes
+/// them after its selector, as the calldata the public VM reads.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionCall {
+    pub to: Fr,
+    pub selector: FunctionSelector,
+    pub args: Vec<Fr>,
+    pub is_public: bool,
+    pub is_static: bool,
+}
+
+impl FunctionCall {
+    /// The padding call: no target, selector or args, public.
+    pub fn empty() -> Self {
+        FunctionCall {
+            to: Fr::zero(),
+            selector: FunctionSelector("00000000".to_string()),
+            args: vec![],
+            is_public: true,
+            is_static: false,
+        }
+    }
+
+    /// The args as `argsOfCalls` lists them: public calls are hashed as
+    /// calldata, selector first.
+    pub fn hashed_args(&self, hasher: &dyn Poseidon2) -> Result<HashedValues, String> {
+        if !self.is_public {
+            return Ok(HashedValues::from_args(hasher, self.args.clone()));
+        }
+        let mut calldata = vec![self.selector_field()?];
+        calldata.extend(self.args.iter().cloned());
+        Ok(HashedValues::from_calldata(hasher, calldata))
+    }
+
+    pub(crate) fn selector_field(&self) -> Result<Fr, String> {
+        u32::from_str_radix(&self.selector.0, 16)
+            .map(Fr::from)
+            .map_err(|_| format!("Invalid function selector '{}'", self.selector.0))
+    }
+}
+
+/// How an entrypoint call pays its fee: the fee payload's calls and the
+/// auth witnesses they need. The default has the account pay from its own
+/// balance, with no fee calls; `FeePaymentMethod` builds the others.
+/// `nonce` is the fee payload's, random when unset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeeOptions {
+    pub gas_settings: GasSettings,
+    pub calls: Vec<FunctionCall>,
+    pub is_fee_payer: bool,
+    pub auth_witnesses: Vec<AuthWitness>,
+    pub nonce: Option<Fr>,
+}
+
+impl Default for FeeOptions {
+    fn default() -> Self {
+        FeeOptions {
+            gas_settings: GasSettings::default(),
+            calls: vec![],
+            is_fee_payer: true,
+            auth_witnesses: vec![],
+            nonce: None,
+        }
+    }
+}
+
+/// `nonce` is the app payload's, random when unset; a `cancellable` tx can
+/// be replaced by another with the same nonce.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EntrypointOptions {
+    pub fee: FeeOptions,
+    pub nonce: Option<Fr>,
+    pub cancellable: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PayloadKind {
+    App,
+    Fee { is_fee_payer: bool },
+}
+
+/// An entrypoint's app or fee payload: its calls, padded to the payload's
+/// size, and a nonce.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntrypointPayload {
+    function_calls: Vec<FunctionCall>,
+    nonce: Fr,
+    kind: PayloadKind,
+}
+
+impl EntrypointPayload {
+    pub fn app(calls: Vec<FunctionCall>, nonce: Fr) -> Result<Self, String> {
+        Self::new(calls, APP_MAX_CALLS, nonce, PayloadKind::App)
+    }
+
+    pub fn fee(calls: Vec<FunctionCall>, nonce: Fr, is_fee_payer: bool) -> Result<Self, String> {
+        Self::new(
+            calls,
+            FEE_MAX_CALLS,
+            nonce,
+            PayloadKind::Fee { is_fee_payer },
+        )
+    }
+
+    fn new(
+        mut calls: Vec<FunctionCall>,
+        max_calls: usize,
+        nonce: Fr,
+        kind: PayloadKind,
+    ) -> Result<Self, String> {
+        if calls.len() > max_calls {
+            return Err(format!(
+                "Payload holds at most {} calls, got {}",
+                max_calls,
+                calls.len()
+            ));
+        }
+        for call in &calls {
+            call.selector_field()?;
+        }
+        calls.resize_with(max_calls, FunctionCall::empty);
+        Ok(EntrypointPayload {
+            function_calls: calls,
+            nonce,
+            kind,
+        })
+    }
+
+    pub fn function_calls(&self) -> &[FunctionCall] {
+        &self.function_calls
+    }
+
+    pub fn nonce(&self) -> &Fr {
+        &self.nonce
+    }
+
+    /// Each call's args, as the request's `argsOfCalls` lists them.
+    pub fn hashed_args(&self, hasher: &dyn Poseidon2) -> Vec<HashedValues> {
+        self.function_calls
+            .iter()
+            // Selectors are checked in `new`.
+            .filter_map(|call| call.hashed_args(hasher).ok())
+            .collect()
+    }
+
+    /// `args_hash, selector, to, is_public, is_static` per call, then the
+    /// nonce and, for fee payloads, `is_fee_payer`.
+    pub fn to_fields(&self, hasher: &dyn Poseidon2) -> Vec<Fr> {
+        let mut fields = vec![];
+        for (call, args) in self.function_calls.iter().zip(self.hashed_args(hasher)) {
+            fields.extend([
+                args.hash,
+                // Checked in `new`.
+                call.selector_field().unwrap_or_else(|_| Fr::zero()),
+                call.to.clone(),
+                Fr::from(call.is_public),
+                Fr::from(call.is_static),
+            ]);
+        }
+        fields.push(self.nonce.clone());
+        if let PayloadKind::Fee { is_fee_payer } = self.kind {
+            fields.push(Fr::from(is_fee_payer));
+        }
+        fields
+    }
+
+    pub fn hash(&self, hasher: &dyn Poseidon2) -> Fr {
+        let separator = match self.kind {
+            PayloadKind::App => generator_index::SIGNATURE_PAYLOAD,
+            PayloadKind::Fee { .. } => generator_index::FEE_PAYLOAD,
+        };
+        hasher.hash_with_separator(&self.to_fields(hasher), separator)
+    }
+}
+
+/// What the account signs: both payloads' hashes, hashed together.
+pub fn combined_payload_hash(
+    app: &EntrypointPayload,
+    fee: &EntrypointPayload,
+    hasher: &dyn Poseidon2,
+) -> Fr {
+    hasher.hash_with_separator(
+        &[app.hash(hasher), fee.hash(hasher)],
+        generator_index::COMBINED_PAYLOAD,
+    )
+}
+
+/// The app and fee payload nonces of an entrypoint request, read back from
+/// the entrypoint's args: a recorded request is rebuilt exactly by passing
+/// them to `create_tx_execution_request`.
+pub fn entrypoint_nonces(request: &TxExecutionRequest) -> Option<(Fr, Fr)> {
+    let entrypoint = request
+        .args_of_calls
+        .iter()
+        .find(|args| args.hash == request.first_call_args_hash)?;
+    let app = APP_MAX_CALLS * CALL_FIELDS;
+    let fee = app + 1 + FEE_MAX_CALLS * CALL_FIELDS;
+    Some((
+        entrypoint.values.get(app)?.clone(),
+        entrypoint.values.get(fee)?.clone(),
+    ))
+}
+
+pub(crate) fn random_nonce() -> Result<Fr, String> {
+    let mut bytes = [0u8; 32];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("No randomness available: {}", e))?;
+    Ok(Fr::from_biguint(
+        BigUint::from_bytes_be(&bytes) % Fr::modulus(),
+    ))
+}
+
+/// A `SchnorrAccount`: Grumpkin key, witness is the 64 signature bytes.
+pub struct SchnorrAccountWallet<H> {
+    address: String,
+    key: SchnorrKeyPair,
+    schnorr: Schnorr<H>,
+}
+
+impl<H: SchnorrChallenge> SchnorrAccountWallet<H> {
+    pub fn new(address: impl Into<String>, key: SchnorrKeyPair, hasher: H) -> Self {
+        SchnorrAccountWallet {
+            address: address.into(),
+            key,
+            schnorr: Schnorr::new(hasher),
+        }
+    }
+
+    pub fn key(&self) -> &SchnorrKeyPair {
+        &self.key
+    }
+}
+
+impl<H: SchnorrChallenge + Send + Sync> AccountWallet for SchnorrAccountWallet<H> {
+    fn address(&self) -> &str {
+        &self.address
+    }
+
+    fn create_auth_witness(&self, request_hash: &Fr) -> Result<AuthWitness, String> {
+        Ok(AuthWitness {
+            request_hash: request_hash.clone(),
+            witness: self.schnorr.sign(&self.key, request_hash).to_witness(),
+        })
+    }
+}
+
+/// An `EcdsaKAccount`: secp256k1 key, so keys can live on hardware made for
+/// Ethereum. The contract checks `r || s` over `sha256(request hash)`, one
+/// byte per witness field.
+pub struct EcdsaAccountWallet {
+    address: String,
+    key: SigningKey,
+}
+
+impl EcdsaAccountWallet {
+    pub fn new(address: impl Into<String>, secret_key: &str) -> Result<Self, String> {
+        let bytes = Zeroizing::new(
+            hex::decode(secret_key.trim_start_matches("0x"))
+                .map_err(|e| format!("Invalid secret key: {}", e))?,
+        );
+        let key = SigningKey::from_slice(&bytes).map_err(|_| "Invalid secret key".to_string())?;
+        Ok(EcdsaAccountWallet {
+            address: address.into(),
+            key,
+        })
+    }
+
+    /// The public key's x and y, as the account contract stores them.
+    pub fn public_key(&self) -> ([u8; 32], [u8; 32]) {
+        let point = self.key.verifying_key().to_encoded_point(false);
+        let mut x = [0u8; 32];
+        let mut y = [0u8; 32];
+        x.copy_from_slice(point.x().expect("uncompressed point"));
+        y.copy_from_slice(point.y().expect("uncompressed point"));
+        (x, y)
+    }
+}
+
+impl AccountWallet for EcdsaAccountWallet {
+    fn address(&self) -> &str {
+        &self.address
+    }
+
+    fn create_auth_witness(&self, request_hash: &Fr) -> Result<AuthWitness, String> {
+        let digest = Sha256::digest(request_hash.to_be_bytes());
+        let signature: Signature = self
+            .key
+            .sign_prehash(&digest)
+            .map_err(|e| format!("Cannot sign: {}", e))?;
+        Ok(AuthWitness {
+            request_hash: request_hash.clone(),
+            witness: signature.to_bytes().iter().map(|b| Fr::from(*b)).collect(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AccountKind {
+    Schnorr,
+    Ecdsa,
+}
+
+impl AccountKind {
+    /// `schnorr`, or `ecdsa` and its aliases; `None` means Schnorr.
+    pub fn parse(kind: Option<&str>) -> Result<Self, String> {
+        match kind.map(|kind| kind.trim().to_lowercase()).as_deref() {
+            None | Some("schnorr") => Ok(AccountKind::Schnorr),
+            Some("ecdsa") | Some("ecdsa_k") | Some("secp256k1") => Ok(AccountKind::Ecdsa),
+            Some(other) => Err(format!("Unknown account kind: {}", other)),
+        }
+    }
+}
+
+/// What the PXE registers the account under: the secret key its master
+/// keys derive from (not the signing key) and its partial address.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountRegistration {
+    pub secret_key: Secret<String>,
+    pub partial_address: Fr,
+}
+
+impl AccountRegistration {
+    /// Reads `ACCOUNT_MASTER_SECRET_KEY` and `ACCOUNT_PARTIAL_ADDRESS`;
+    /// `None` when neither is set.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let secret_key = env::var("ACCOUNT_MASTER_SECRET_KEY").map(Zeroizing::new);
+        let partial_address = env::var("ACCOUNT_PARTIAL_ADDRESS");
+        match (secret_key, partial_address) {
+            (Err(_), Err(_)) => Ok(None),
+            (Ok(secret_key), Ok(partial_address)) => Ok(Some(AccountRegistration {
+                secret_key: secret_key.as_str().into(),
+                partial_address: Fr::try_from(partial_address.as_str())
+                    .map_err(|e| format!("Invalid ACCOUNT_PARTIAL_ADDRESS: {}", e))?,
+            })),
+            _ => Err(
+                "Set both ACCOUNT_MASTER_SECRET_KEY and ACCOUNT_PARTIAL_ADDRESS, or neither"
+                    .to_string(),
+            ),
+        }
+    }
+}
+
+/// The account wallets a client sends from, looked up by address.
+#[derive(Clone, Default)]
+pub struct Wallets(Vec<Arc<dyn AccountWallet>>);
+
+impl Wallets {
+    /// Adds `wallet`, replacing any other for its address.
+    pub fn insert(&mut self, wallet: Arc<dyn AccountWallet>) {
+        self.0
+            .retain(|w| !same_address(w.address(), wallet.address()));
+        self.0.push(wallet);
+    }
+
+    pub fn get(&self, address: &str) -> Option<&Arc<dyn AccountWallet>> {
+        self.0.iter().find(|w| same_address(w.address(), address))
+    }
+}
+
+impl fmt::Debug for Wallets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map(|w| w.address()))
+            .finish()
+    }
+}
+
+fn same_address(a: &str, b: &str) -> bool {
+    match (Fr::try_from(a), Fr::try_from(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a.eq_ignore_ascii_case(b),
+    }
+}
+
+/// Which account contract the wallet signs for, and with which key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalletConfig {
+    pub kind: AccountKind,
+    pub address: String,
+    pub secret_key: Secret<String>,
+    /// Lets `ensure_registered` check `address` against the PXE.
+    pub registration: Option<AccountRegistration>,
+}
+
+impl WalletConfig {
+    pub fn parse(kind: Option<&str>, address: &str, secret_key: &str) -> Result<Self, String> {
+        let kind = AccountKind::parse(kind)?;
+        Fr::try_from(address).map_err(|e| format!("Invalid account address: {}", e))?;
+        Ok(WalletConfig {
+            kind,
+            address: address.to_string(),
+            secret_key: secret_key.into(),
+            registration: None,
+        })
+    }
+
+    /// Reads `ACCOUNT_KIND` (`schnorr` or `ecdsa`), `ACCOUNT_ADDRESS` and
+    /// `ACCOUNT_SECRET_KEY`, or unlocks keystore key `ACCOUNT_KEY_NAME` with
+    /// `KEYSTORE_PASSPHRASE`; `None` when neither is set. Either way the
+    /// registration comes from `AccountRegistration::from_env`.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Some(mut config) = Self::signer_from_env()? else {
+            return Ok(None);
+        };
+        config.registration = AccountRegistration::from_env()?;
+        Ok(Some(config))
+    }
+
+    fn signer_from_env() -> Result<Option<Self>, String> {
+        let Ok(secret_key) = env::var("ACCOUNT_SECRET_KEY").map(Zeroizing::new) else {
+            let Ok(name) = env::var("ACCOUNT_KEY_NAME") else {
+                return Ok(None);
+            };
+            let passphrase = env::var("KEYSTORE_PASSPHRASE")
+                .map_err(|_| "KEYSTORE_PASSPHRASE is not set".to_string())?;
+            return Keystore::from_env()
+                .wallet_config(&name, &passphrase)
+                .map(Some);
+        };
+        let address =
+            env::var("ACCOUNT_ADDRESS").map_err(|_| "ACCOUNT_ADDRESS is not set".to_string())?;
+        Self::parse(
+            env::var("ACCOUNT_KIND").ok().as_deref(),
+            &address,
+            &secret_key,
+        )
+        .map(Some)
+    }
+
+    /// Makes sure the PXE has the account registered under `address` with
+    /// the registration's keys (see `complete_address::ensure_registered`).
+    /// `None` without a registration, when there is nothing to check.
+    pub async fn ensure_registered<P: PxeApi + ?Sized>(
+        &self,
+        pxe: &P,
+    ) -> Result<Option<CompleteAddress>, Box<dyn std::error::Error>> {
+        let Some(registration) = &self.registration else {
+            return Ok(None);
+        };
+        let address = Fr::try_from(self.address.as_str())?;
+        let secret_key = Fr::try_from(registration.secret_key.expose().as_str())
+            .map(Zeroizing::new)
+            .map_err(|e| format!("Invalid ACCOUNT_MASTER_SECRET_KEY: {}", e))?;
+        ensure_registered(pxe, &address, &secret_key, &registration.partial_address)
+            .await
+            .map(Some)
+    }
+
+    /// Schnorr wallets sign with `schnorr_hasher`, or `PedersenBlake2s`, the
+    /// challenge account contracts check, when it is `None`; ECDSA wallets
+    /// ignore it.
+    pub fn into_wallet(
+        self,
+        schnorr_hasher: Option<Box<dyn SchnorrChallenge + Send + Sync>>,
+    ) -> Result<Box<dyn AccountWallet>, String> {
+        match self.kind {
+            AccountKind::Ecdsa => Ok(Box::new(EcdsaAccountWallet::new(
+                self.address.clone(),
+                self.secret_key.expose(),
+            )?)),
+            AccountKind::Schnorr => {
+                let hasher = schnorr_hasher.unwrap_or_else(|| Box::new(PedersenBlake2s));
+                let secret = Fr::try_from(self.secret_key.expose().as_str())
+                    .map(Zeroizing::new)
+                    .map_err(|e| format!("Invalid secret key: {}", e))?;
+                let key = SchnorrKeyPair::from_secret(&secret.0)?;
+                Ok(Box::new(SchnorrAccountWallet::new(
+                    self.address.clone(),
+                    key,
+                    hasher,
+                )))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notes::Bn254Poseidon2;
+    use crate::signing::SchnorrSignature;
+    use k256::ecdsa::signature::hazmat::PrehashVerifier;
+
+    const ADDRESS: &str = "0x0a";
+
+    const SECRET: &str = "0x0101010101010101010101010101010101010101010101010101010101010101";
+
+    #[test]
+    fn test_ecdsa_witness_verifies_over_sha256() {
+        let wallet = EcdsaAccountWallet::new(ADDRESS, SECRET).unwrap();
+        let request_hash = Fr::from(0x1234u64);
+        let witness = wallet.create_auth_witness(&request_hash).unwrap();
+        assert_eq!(witness.request_hash, request_hash);
+        assert_eq!(witness.witness.len(), 64);
+
+        let bytes: Vec<u8> = witness
+            .witness
+            .iter()
+            .map(|field| field.to_u64().unwrap() as u8)
+            .collect();
+        let signature = Signature::from_slice(&bytes).unwrap();
+        assert!(signature.normalize_s().is_none(), "s must be low");
+        let digest = Sha256::digest(request_hash.to_be_bytes());
+        wallet
+            .key
+            .verifying_key()
+            .verify_prehash(&digest, &signature)
+            .unwrap();
+
+        let (x, y) = wallet.public_key();
+        let point = wallet.key.verifying_key().to_encoded_point(false);
+        assert_eq!(&point.as_bytes()[1..33], &x);
+        assert_eq!(&point.as_bytes()[33..], &y);
+    }
+
+    #[test]
+    fn test_config_selects_the_account_kind() {
+        let ecdsa = WalletConfig::parse(Some("ECDSA"), ADDRESS, SECRET).unwrap();
+        assert_eq!(ecdsa.kind, AccountKind::Ecdsa);
+        assert!(!format!("{:?}", ecdsa).contains(&SECRET[2..]));
+        let wallet = ecdsa.into_wallet(None).unwrap();
+        assert_eq!(wallet.address(), ADDRESS);
+
+        let schnorr = WalletConfig::parse(None, ADDRESS, "0x2a").unwrap();
+        assert_eq!(schnorr.kind, AccountKind::Schnorr);
+        let wallet = schnorr.into_wallet(None).unwrap();
+        let witness = wallet.create_auth_witness(&Fr::from(7u8)).unwrap();
+        assert_eq!(witness.witness.len(), 64);
+        let key = SchnorrKeyPair::from_secret(&BigUint::from(0x2au8)).unwrap();
+        let signature = SchnorrSignature::from_witness(&witness.witness).unwrap();
+        assert_eq!(
+            Schnorr::new(PedersenBlake2s).verify(key.public_key(), &Fr::from(7u8), &signature),
+            Ok(())
+        );
+
+        assert!(WalletConfig::parse(Some("rsa"), ADDRESS, SECRET).is_err());
+        assert!(WalletConfig::parse(None, "nope", SECRET).is_err());
+        assert!(WalletConfig::parse(Some("ecdsa"), ADDRESS, "0x00")
+            .unwrap()
+            .into_wallet(None)
+            .is_err());
+    }
+
+    /// The real hasher, except that the recorded private call's args were
+    /// redacted from the fixture: only their hash is genuine, so that hash
+    /// stands in for them.
+    struct Recorded(Fr);
+
+    impl Poseidon2 for Recorded {
+        fn hash_with_separator(&self, inputs: &[Fr], separator: u32) -> Fr {
+            if separator == generator_index::FUNCTION_ARGS
+                && inputs.len() == 16
+                && inputs.iter().all(Fr::is_zero)
+            {
+                return self.0.clone();
+            }
+            Bn254Poseidon2.hash_with_separator(inputs, separator)
+        }
+
+        fn hash(&self, inputs: &[Fr]) -> Fr {
+            Bn254Poseidon2.hash(inputs)
+        }
+    }
+
+    #[test]
+    fn test_entrypoint_request_matches_the_recorded_layout() {
+        use crate::tx_request::set_feeds_tx_request;
+
+        let recorded = TxExecutionRequest::from_json(set_feeds_tx_request(ADDRESS)).unwrap();
+        let hasher = Recorded(recorded.args_of_calls[0].hash.clone());
+        let calls = vec![
+            FunctionCall {
+                to: Fr::from(2u8),
+                selector: FunctionSelector::from_hex("0xc02957").unwrap(),
+                args: recorded.args_of_calls[0].values.clone(),
+                is_public: false,
+                is_static: false,
+            },
+            FunctionCall {
+                to: recorded.args_of_calls[6].values[7].clone(),
+                selector: FunctionSelector::from_hex("0x17f12888").unwrap(),
+                args: vec![],
+                is_public: true,
+                is_static: false,
+            },
+        ];
+        let node_info = NodeInfo {
+            node_version: String::new(),
+            l1_chain_id: 31337,
+            rollup_version: 0xb2da7e95,
+        };
+        let options = EntrypointOptions {
+            nonce: Some(recorded.args_of_calls[6].values[20].clone()),
+            ..EntrypointOptions::default()
+        };
+        let wallet = EcdsaAccountWallet::new(ADDRESS, SECRET).unwrap();
+        let request = wallet
+            .create_tx_execution_request(calls.clone(), options, &node_info, &hasher)
+            .unwrap();
+
+        assert_eq!(
+            entrypoint_nonces(&recorded),
+            Some((
+                recorded.args_of_calls[6].values[20].clone(),
+                recorded.args_of_calls[6].values[31].clone()
+            ))
+        );
+        assert_eq!(request.function_selector, recorded.function_selector);
+        assert_eq!(request.tx_context, recorded.tx_context);
+        // The public call and the padding calls hash as calldata.
+        assert_eq!(request.args_of_calls[..6], recorded.args_of_calls[..6]);
+
+        // Everything but the fee payload's random nonce is as recorded.
+        let entrypoint = &request.args_of_calls[6];
+        let mut expected = recorded.args_of_calls[6].clone();
+        assert_eq!(entrypoint.values[..31], expected.values[..31]);
+        assert_eq!(entrypoint.values[32..], expected.values[32..]);
+        assert_eq!(request.first_call_args_hash, entrypoint.hash);
+        expected.values[31] = entrypoint.values[31].clone();
+        assert_eq!(
+            entrypoint.hash,
+            HashedValues::from_args(&Bn254Poseidon2, expected.values).hash
+        );
+        assert_eq!(
+            HashedValues::from_args(&Bn254Poseidon2, recorded.args_of_calls[6].values.clone()),
+            recorded.args_of_calls[6]
+        );
+
+        // With the recorded fee nonce the payloads hash to what the account
+        // contract was asked to sign.
+        let app = EntrypointPayload::app(calls, entrypoint.values[20].clone()).unwrap();
+        let fee =
+            EntrypointPayload::fee(vec![], recorded.args_of_calls[6].values[31].clone(), true)
+                .unwrap();
+        assert_eq!(
+            combined_payload_hash(&app, &fee, &hasher),
+            recorded.auth_witnesses[0].request_hash
+        );
+        assert!(EntrypointPayload::app(vec![FunctionCall::empty(); 5], Fr::zero()).is_err());
+    }
+}
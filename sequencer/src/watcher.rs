@@ -0,0 +1,440 @@
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::sleep;
+
+use crate::fields::Fr;
+use crate::pxe_api::PxeApi;
+
+/// Chain state the watcher re-reads on every new block.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum WatchTarget {
+    PublicStorage {
+        contract: String,
+        slot: Fr,
+    },
+    /// Notes the PXE holds for `contract`, optionally narrowed to a storage
+    /// slot and to the given accounts.
+    Notes {
+        contract: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        storage_slot: Option<Fr>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        scopes: Vec<String>,
+    },
+}
+
+impl WatchTarget {
+    pub fn contract(&self) -> &str {
+        match self {
+            WatchTarget::PublicStorage { contract, .. } | WatchTarget::Notes { contract, .. } => {
+                contract
+            }
+        }
+    }
+
+    async fn read(&self, pxe: &dyn PxeApi) -> Result<Value, Box<dyn std::error::Error>> {
+        match self {
+            WatchTarget::PublicStorage { contract, slot } => {
+                Ok(json!(pxe.get_public_storage_at(contract, slot).await?))
+            }
+            WatchTarget::Notes {
+                contract,
+                storage_slot,
+                scopes,
+            } => {
+                let mut filter = json!({ "contractAddress": contract });
+                if let Some(slot) = storage_slot {
+                    filter["storageSlot"] = json!(slot);
+                }
+                if !scopes.is_empty() {
+                    filter["scopes"] = json!(scopes);
+                }
+                Ok(pxe.get_notes(filter).await?)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValueChange {
+    pub target: WatchTarget,
+    pub block: u64,
+    pub previous: Value,
+    pub current: Value,
+}
+
+/// A public log emitted by a contract passed to `BlockWatcher::watch_logs`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractLog {
+    pub block: u64,
+    pub tx_hash: String,
+    pub contract: Fr,
+    pub fields: Vec<Fr>,
+}
+
+/// Sent once per block the watcher sees, with the changes found in it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewBlock {
+    pub number: u64,
+    pub changes: Vec<ValueChange>,
+}
+
+type Callback = Arc<dyn Fn(ValueChange) -> BoxFuture<'static, ()> + Send + Sync>;
+
+// A watched target: how many watchers asked for it and the last value
+// read, `None` until the first read.
+struct Watched {
+    watchers: usize,
+    value: Option<Value>,
+}
+
+#[derive(Default)]
+struct WatchState {
+    last_block: Option<u64>,
+    values: HashMap<WatchTarget, Watched>,
+    /// Contracts whose public logs are fetched with each new block, with
+    /// how many watchers asked for each.
+    log_contracts: HashMap<Fr, usize>,
+}
+
+/// Polls `getBlockNumber` and re-reads every watched target when a new block
+/// shows up. Changes go to the `on_value_changed` callbacks and to every
+/// `subscribe`r (the bridge forwards them to WebSocket subscribers).
+///
+/// The first read of a target only records a baseline; it is not a change.
+/// A `SimulationCache` on the watcher's client moves to each new block.
+///
+/// For contracts passed to `watch_logs`, every block since the previous
+/// poll is fetched and the public logs of successful txs go to
+/// `subscribe_logs`.
+///
+/// Watches are counted: a target or contract is dropped once as many
+/// `unwatch` calls as `watch` calls have been made for it.
+pub struct BlockWatcher {
+    pxe: Box<dyn PxeApi>,
+    interval: Duration,
+    state: Mutex<WatchState>,
+    callbacks: Mutex<Vec<Callback>>,
+    changes: broadcast::Sender<ValueChange>,
+    blocks: broadcast::Sender<NewBlock>,
+    logs: broadcast::Sender<ContractLog>,
+}
+
+impl BlockWatcher {
+    pub fn new(pxe: impl PxeApi + 'static, interval: Duration) -> Self {
+        BlockWatcher {
+            pxe: Box::new(pxe),
+            interval,
+            state: Mutex::new(WatchState::default()),
+            callbacks: Mutex::new(Vec::new()),
+            changes: broadcast::channel(64).0,
+            blocks: broadcast::channel(64).0,
+            logs: broadcast::channel(256).0,
+        }
+    }
+
+    /// Returns `false` if the target was already watched.
+    pub fn watch(&self, target: WatchTarget) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let watched = state.values.entry(target).or_insert(Watched {
+            watchers: 0,
+            value: None,
+        });
+        watched.watchers += 1;
+        watched.watchers == 1
+    }
+
+    pub fn is_watching(&self, target: &WatchTarget) -> bool {
+        self.state.lock().unwrap().values.contains_key(target)
+    }
+
+    /// Returns `true` if that was the target's last watcher.
+    pub fn unwatch(&self, target: &WatchTarget) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let Some(watched) = state.values.get_mut(target) else {
+            return false;
+        };
+        watched.watchers -= 1;
+        if watched.watchers > 0 {
+            return false;
+        }
+        state.values.remove(target);
+        true
+    }
+
+    /// Returns `false` if the contract's logs were already watched.
+    pub fn watch_logs(&self, contract: Fr) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let watchers = state.log_contracts.entry(contract).or_insert(0);
+        *watchers += 1;
+        *watchers == 1
+    }
+
+    /// Returns `true` if that was the last watcher of the contract's logs.
+    pub fn unwatch_logs(&self, contract: &Fr) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let Some(watchers) = state.log_contracts.get_mut(contract) else {
+            return false;
+        };
+        *watchers -= 1;
+        if *watchers > 0 {
+            return false;
+        }
+        state.log_contracts.remove(contract);
+        true
+    }
+
+    pub fn on_value_changed<F, Fut>(&self, callback: F)
+    where
+        F: Fn(ValueChange) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let callback: Callback = Arc::new(move |change| Box::pin(callback(change)));
+        self.callbacks.lock().unwrap().push(callback);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ValueChange> {
+        self.changes.subscribe()
+    }
+
+    pub fn subscribe_blocks(&self) -> broadcast::Receiver<NewBlock> {
+        self.blocks.subscribe()
+    }
+
+    pub fn subscribe_logs(&self) -> broadcast::Receiver<ContractLog> {
+        self.logs.subscribe()
+    }
+
+    /// One polling round. Does nothing (and reads nothing) if the block
+    /// number hasn't moved since the last call.
+    pub async fn poll(&self) -> Result<Vec<ValueChange>, Box<dyn std::error::Error>> {
+        let block = self.pxe.get_block_number().await?;
+        let (previous_block, targets, log_contracts) = {
+            let mut state = self.state.lock().unwrap();
+            if state.last_block == Some(block) {
+                return Ok(vec![]);
+            }
+            let previous_block = state.last_block.replace(block);
+            let targets: Vec<WatchTarget> = state.values.keys().cloned().collect();
+            let log_contracts: HashSet<Fr> = state.log_contracts.keys().cloned().collect();
+            (previous_block, targets, log_contracts)
+        };
+        if let Some(cache) = self.pxe.simulation_cache() {
+            cache.new_block(block);
+        }
+
+        let mut changes = Vec::new();
+        for target in targets {
+            let current = target.read(&*self.pxe).await?;
+            // Unwatched while it was being read: nobody wants the change.
+            let previous = match self.state.lock().unwrap().values.get_mut(&target) {
+                Some(watched) => watched.value.replace(current.clone()),
+                None => continue,
+            };
+
+            match previous {
+                Some(previous) if previous != current => changes.push(ValueChange {
+                    target,
+                    block,
+                    previous,
+                    current,
+                }),
+                _ => {}
+            }
+        }
+
+        // Like targets, the first block seen is only a baseline.
+        if let Some(previous_block) = previous_block.filter(|_| !log_contracts.is_empty()) {
+            for number in previous_block + 1..=block {
+                self.send_logs(number, &log_contracts).await?;
+            }
+        }
+
+        let callbacks = self.callbacks.lock().unwrap().clone();
+        for change in &changes {
+            for callback in &callbacks {
+                callback(change.clone()).await;
+            }
+            // No receivers is fine: nobody has subscribed yet.
+            let _ = self.changes.send(change.clone());
+        }
+        let _ = self.blocks.send(NewBlock {
+            number: block,
+            changes: changes.clone(),
+        });
+        Ok(changes)
+    }
+
+    async fn send_logs(
+        &self,
+        number: u64,
+        contracts: &HashSet<Fr>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(block) = self.pxe.get_block(number).await? else {
+            return Ok(());
+        };
+        let effects = block.tx_effects().iter().filter(|e| !e.reverted());
+        for effect in effects {
+            for log in &effect.public_logs {
+                if contracts.contains(&log.contract_address) {
+                    let _ = self.logs.send(ContractLog {
+                        block: number,
+                        tx_hash: effect.tx_hash.clone(),
+                        contract: log.contract_address.clone(),
+                        fields: log.fields.clone(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn run(self: Arc<Self>) {
+        loop {
+            if let Err(e) = self.poll().await {
+                println!("Block watcher poll failed: {}", e);
+            }
+            sleep(self.interval).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aztec_rpc_client::AztecRpcClient;
+    use crate::testing::MockPxe;
+
+    const CONTRACT: &str = "0x12d8f70092c1d4b2bf3ddd60af8e47c1a10d90f3f31fe4c874d4b91f58442ede";
+
+    fn just_field() -> WatchTarget {
+        WatchTarget::PublicStorage {
+            contract: CONTRACT.to_string(),
+            slot: Fr::from(2u8),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reports_changes_only_on_new_blocks() {
+        let mock = MockPxe::start().await.unwrap();
+        for block in [1, 1, 2] {
+            mock.respond("pxe_getBlockNumber", json!(block));
+        }
+        for value in [Fr::from(700u16), Fr::from(214u8)] {
+            mock.respond("pxe_getPublicStorageAt", json!(value));
+        }
+
+        let watcher = BlockWatcher::new(
+            AztecRpcClient::new(mock.url(), Some("pxe".to_string())),
+            Duration::from_millis(10),
+        );
+        assert!(watcher.watch(just_field()));
+        assert!(!watcher.watch(just_field()));
+        assert!(!watcher.unwatch(&just_field()));
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        watcher.on_value_changed(move |change| {
+            let sink = sink.clone();
+            async move { sink.lock().unwrap().push(change) }
+        });
+        let mut subscriber = watcher.subscribe();
+        let mut blocks = watcher.subscribe_blocks();
+
+        assert!(watcher.poll().await.unwrap().is_empty());
+        assert!(watcher.poll().await.unwrap().is_empty());
+        let changes = watcher.poll().await.unwrap();
+
+        let expected = ValueChange {
+            target: just_field(),
+            block: 2,
+            previous: json!(Fr::from(700u16)),
+            current: json!(Fr::from(214u8)),
+        };
+        assert_eq!(changes, vec![expected.clone()]);
+        assert_eq!(*seen.lock().unwrap(), vec![expected.clone()]);
+        assert_eq!(subscriber.try_recv().unwrap(), expected);
+
+        let numbers: Vec<u64> = std::iter::from_fn(|| blocks.try_recv().ok())
+            .map(|block| block.number)
+            .collect();
+        assert_eq!(numbers, vec![1, 2]);
+
+        let storage_reads = mock
+            .requests()
+            .iter()
+            .filter(|r| r["method"] == "pxe_getPublicStorageAt")
+            .count();
+        assert_eq!(storage_reads, 2);
+    }
+
+    #[tokio::test]
+    async fn test_targets_are_read_until_their_last_watcher_leaves() {
+        let mock = MockPxe::start().await.unwrap();
+        for block in [1, 2] {
+            mock.respond("pxe_getBlockNumber", json!(block));
+        }
+        mock.respond("pxe_getPublicStorageAt", json!(Fr::from(700u16)));
+
+        let watcher = BlockWatcher::new(
+            AztecRpcClient::new(mock.url(), Some("pxe".to_string())),
+            Duration::from_millis(10),
+        );
+        let contract = Fr::try_from(CONTRACT).unwrap();
+        watcher.watch(just_field());
+        watcher.watch(just_field());
+        assert!(watcher.watch_logs(contract.clone()));
+        watcher.poll().await.unwrap();
+
+        assert!(!watcher.unwatch(&just_field()));
+        assert!(watcher.unwatch(&just_field()));
+        assert!(!watcher.unwatch(&just_field()));
+        assert!(watcher.unwatch_logs(&contract));
+        watcher.poll().await.unwrap();
+
+        let methods: Vec<_> = mock
+            .requests()
+            .iter()
+            .map(|r| r["method"].clone())
+            .collect();
+        assert_eq!(
+            methods,
+            [
+                "pxe_getBlockNumber",
+                "pxe_getPublicStorageAt",
+                "pxe_getBlockNumber"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_notes_filter_is_built_from_target() {
+        let mock = MockPxe::start().await.unwrap();
+        mock.respond("pxe_getBlockNumber", json!(5));
+        mock.respond("pxe_getNotes", json!([]));
+
+        let watcher = BlockWatcher::new(
+            AztecRpcClient::new(mock.url(), Some("pxe".to_string())),
+            Duration::from_millis(10),
+        );
+        watcher.watch(WatchTarget::Notes {
+            contract: CONTRACT.to_string(),
+            storage_slot: Some(Fr::from(1u8)),
+            scopes: vec![],
+        });
+        watcher.poll().await.unwrap();
+
+        let filter = &mock.requests()[1]["params"][0];
+        assert_eq!(
+            *filter,
+            json!({ "contractAddress": CONTRACT, "storageSlot": Fr::from(1u8) })
+        );
+    }
+}
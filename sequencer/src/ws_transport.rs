@@ -0,0 +1,158 @@
+use futures_util::future::BoxFuture;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::Value;
+use std::collections::HashMap;
+use tokio::net::TcpStream;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+use crate::error::AztecError;
+use crate::layers::RpcService;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+type Reply = oneshot::Sender<Result<Value, AztecError>>;
+
+/// JSON-RPC over one WebSocket connection, for PXEs that serve it. Calls
+/// don't wait for each other: replies are matched to requests by id, so
+/// any number can be in flight. Use it in place of HTTP with
+/// `AztecRpcClientBuilder::transport`.
+#[derive(Debug)]
+pub struct WsTransport {
+    requests: mpsc::UnboundedSender<(Value, Reply)>,
+    notifications: broadcast::Sender<Value>,
+}
+
+impl WsTransport {
+    pub async fn connect(url: &str) -> Result<Self, AztecError> {
+        let (socket, _) = connect_async(url)
+            .await
+            .map_err(|e| AztecError::Transport(e.to_string()))?;
+        let (requests, commands) = mpsc::unbounded_channel();
+        let (notifications, _) = broadcast::channel(64);
+        tokio::spawn(drive(socket, commands, notifications.clone()));
+        Ok(WsTransport {
+            requests,
+            notifications,
+        })
+    }
+
+    /// Messages the PXE pushes without a request (anything without an id),
+    /// from now on.
+    pub fn notifications(&self) -> broadcast::Receiver<Value> {
+        self.notifications.subscribe()
+    }
+}
+
+impl RpcService for WsTransport {
+    fn call(&self, request: Value) -> BoxFuture<'_, Result<Value, AztecError>> {
+        Box::pin(async move {
+            let closed = || AztecError::Transport("WebSocket connection is closed".to_string());
+            let (reply, response) = oneshot::channel();
+            self.requests.send((request, reply)).map_err(|_| closed())?;
+            response.await.map_err(|_| closed())?
+        })
+    }
+}
+
+// Owns the socket: sends requests and hands each reply to the caller whose
+// request carried its id. When the connection ends, every pending call
+// fails with the reason.
+async fn drive(
+    mut socket: Socket,
+    mut commands: mpsc::UnboundedReceiver<(Value, Reply)>,
+    notifications: broadcast::Sender<Value>,
+) {
+    let mut pending: HashMap<String, Reply> = HashMap::new();
+
+    let reason = loop {
+        tokio::select! {
+            command = commands.recv() => {
+                let Some((request, reply)) = command else {
+                    let _ = socket.close(None).await;
+                    return;
+                };
+                let id = request["id"].to_string();
+                if let Err(e) = socket.send(Message::Text(request.to_string())).await {
+                    let _ = reply.send(Err(AztecError::Transport(e.to_string())));
+                    break e.to_string();
+                }
+                pending.insert(id, reply);
+            }
+            message = socket.next() => match message {
+                Some(Ok(Message::Close(_))) | None => break "Connection closed by the PXE".to_string(),
+                Some(Err(e)) => break e.to_string(),
+                Some(Ok(Message::Text(text))) => {
+                    let reply: Value = match serde_json::from_str(&text) {
+                        Ok(reply) => reply,
+                        Err(e) => {
+                            tracing::warn!("Ignoring non-JSON WebSocket message: {}", e);
+                            continue;
+                        }
+                    };
+                    if reply["id"].is_null() {
+                        let _ = notifications.send(reply);
+                    } else if let Some(caller) = pending.remove(&reply["id"].to_string()) {
+                        let _ = caller.send(Ok(reply));
+                    } else {
+                        tracing::warn!("Ignoring reply to unknown request {}", reply["id"]);
+                    }
+                }
+                Some(Ok(_)) => {}
+            }
+        }
+    };
+
+    for (_, reply) in pending {
+        let _ = reply.send(Err(AztecError::Transport(reason.clone())));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aztec_rpc_client::AztecRpcClient;
+    use serde_json::json;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::accept_async;
+
+    #[tokio::test]
+    async fn test_concurrent_calls_share_one_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = accept_async(stream).await.unwrap();
+            let mut requests = vec![];
+            while requests.len() < 2 {
+                if let Some(Ok(Message::Text(text))) = socket.next().await {
+                    requests.push(serde_json::from_str::<Value>(&text).unwrap());
+                }
+            }
+            // Push, then answer out of order: each result is ten times its id.
+            let push = json!({ "jsonrpc": "2.0", "method": "pxe_blockAdded", "params": [9] });
+            socket.send(Message::Text(push.to_string())).await.unwrap();
+            for request in requests.iter().rev() {
+                let id = request["id"].as_u64().unwrap();
+                let reply = json!({ "jsonrpc": "2.0", "id": id, "result": id * 10 });
+                socket.send(Message::Text(reply.to_string())).await.unwrap();
+            }
+            socket.close(None).await.unwrap();
+        });
+
+        let transport = WsTransport::connect(&url).await.unwrap();
+        let mut notifications = transport.notifications();
+        let pxe = AztecRpcClient::builder(&url)
+            .namespace("pxe")
+            .transport(transport)
+            .build()
+            .unwrap();
+
+        let (first, second) = tokio::join!(pxe.get_block_number(), pxe.get_block_number());
+        assert_eq!((first.unwrap(), second.unwrap()), (10, 20));
+        assert_eq!(notifications.recv().await.unwrap()["params"], json!([9]));
+
+        let err = pxe.get_block_number().await.unwrap_err();
+        assert!(matches!(err, AztecError::Transport(_)), "{:?}", err);
+    }
+}
@@ -0,0 +1,126 @@
+//! Property tests for the argument encoder: random ABI type trees with
+//! matching random arguments must survive encode → decode unchanged, and
+//! mismatched arguments must fail with an error instead of a panic.
+
+use num_bigint::BigUint;
+use proptest::prelude::*;
+use serde_json::{Map, Value};
+
+use sequencer::encoder::{
+    decode_arguments, encode_arguments, AbiParameter, AbiStructField, AbiType, FunctionAbi,
+};
+
+fn abi_type() -> impl Strategy<Value = AbiType> {
+    let leaf = prop_oneof![
+        Just(AbiType::Field),
+        Just(AbiType::Boolean),
+        prop::sample::select(vec![8usize, 16, 32, 64, 128]).prop_map(|width| AbiType::Integer {
+            sign: "unsigned".to_string(),
+            width,
+        }),
+        (0usize..8).prop_map(|length| AbiType::String { length }),
+    ];
+    leaf.prop_recursive(3, 24, 4, |inner| {
+        prop_oneof![
+            (inner.clone(), 0usize..4).prop_map(|(ty, length)| AbiType::Array {
+                r#type: Box::new(ty),
+                length,
+            }),
+            prop::collection::vec(inner, 1..4).prop_map(|types| AbiType::Struct {
+                path: "Props::Struct".to_string(),
+                fields: types
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, field_type)| AbiStructField {
+                        name: format!("f{}", i),
+                        field_type,
+                    })
+                    .collect(),
+            }),
+        ]
+    })
+}
+
+/// Arguments for `ty` in the form `decode_arguments` returns them.
+fn value_for(ty: &AbiType) -> BoxedStrategy<Value> {
+    match ty {
+        // 31 bytes always stay below the field modulus.
+        AbiType::Field => any::<[u8; 31]>()
+            .prop_map(|bytes| Value::String(BigUint::from_bytes_be(&bytes).to_string()))
+            .boxed(),
+        AbiType::Boolean => any::<bool>().prop_map(Value::Bool).boxed(),
+        AbiType::Integer { width, .. } => {
+            let width = *width as u32;
+            any::<u128>()
+                .prop_map(move |v| {
+                    let v = if width < 128 { v % (1u128 << width) } else { v };
+                    Value::String(v.to_string())
+                })
+                .boxed()
+        }
+        AbiType::String { length } => prop::collection::vec(0x20u8..0x7f, 0..=*length)
+            .prop_map(|bytes| Value::String(String::from_utf8(bytes).unwrap()))
+            .boxed(),
+        AbiType::Array { r#type, length } => prop::collection::vec(value_for(r#type), *length)
+            .prop_map(Value::Array)
+            .boxed(),
+        AbiType::Struct { fields, .. } => {
+            let names: Vec<String> = fields.iter().map(|f| f.name.clone()).collect();
+            fields
+                .iter()
+                .map(|f| value_for(&f.field_type))
+                .collect::<Vec<_>>()
+                .prop_map(move |values| {
+                    Value::Object(names.iter().cloned().zip(values).collect::<Map<_, _>>())
+                })
+                .boxed()
+        }
+    }
+}
+
+fn function(types: Vec<AbiType>) -> FunctionAbi {
+    FunctionAbi {
+        name: "props".to_string(),
+        function_type: "public".to_string(),
+        isInternal: false,
+        isStatic: false,
+        isInitializer: false,
+        parameters: types
+            .into_iter()
+            .enumerate()
+            .map(|(i, abi_type)| AbiParameter {
+                name: format!("p{}", i),
+                abi_type,
+            })
+            .collect(),
+        return_types: vec![],
+        errorTypes: None,
+    }
+}
+
+fn call() -> impl Strategy<Value = (FunctionAbi, Vec<Value>)> {
+    prop::collection::vec(abi_type(), 0..4).prop_flat_map(|types| {
+        let values: Vec<_> = types.iter().map(value_for).collect();
+        (Just(function(types)), values)
+    })
+}
+
+proptest! {
+    #[test]
+    fn test_encode_then_decode_round_trips((abi, args) in call()) {
+        let parameters = abi.parameters.clone();
+        let fields = encode_arguments(abi, args.clone()).unwrap();
+
+        let size: usize = parameters.iter().map(|p| p.abi_type.flattened_size()).sum();
+        prop_assert_eq!(fields.len(), size);
+        prop_assert_eq!(decode_arguments(&parameters, &fields).unwrap(), args);
+    }
+
+    #[test]
+    fn test_mismatched_arguments_do_not_panic(
+        (abi, _) in call(),
+        (_, args) in call(),
+    ) {
+        let _ = encode_arguments(abi, args);
+    }
+}
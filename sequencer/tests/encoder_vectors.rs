@@ -0,0 +1,64 @@
+//! Compares `encode_arguments` with aztec.js' `encodeArguments` on the
+//! fixtures in `tests/vectors/` (regenerate them with
+//! `src/encoder-vectors.ts` at the repository root).
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+use sequencer::encoder::{encode_arguments, FunctionAbi};
+use sequencer::fields::Fr;
+
+#[derive(Deserialize)]
+struct Vector {
+    name: String,
+    abi: FunctionAbi,
+    args: Vec<Value>,
+    expected: Vec<Fr>,
+}
+
+fn vectors() -> Vec<Vector> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/vectors");
+    let mut paths: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            let contents = fs::read_to_string(path).unwrap();
+            serde_json::from_str(&contents)
+                .unwrap_or_else(|e| panic!("Bad vector {}: {}", path.display(), e))
+        })
+        .collect()
+}
+
+#[test]
+fn test_encoder_matches_aztec_js_vectors() {
+    let vectors = vectors();
+    assert!(!vectors.is_empty(), "no vectors in tests/vectors");
+
+    for vector in vectors {
+        let encoded = encode_arguments(vector.abi, vector.args)
+            .unwrap_or_else(|e| panic!("{}: {}", vector.name, e));
+        for (i, (actual, expected)) in encoded.iter().zip(&vector.expected).enumerate() {
+            assert_eq!(
+                actual.to_hex(),
+                expected.to_hex(),
+                "{}: field {} differs",
+                vector.name,
+                i
+            );
+        }
+        assert_eq!(
+            encoded.len(),
+            vector.expected.len(),
+            "{}: wrong number of fields",
+            vector.name
+        );
+    }
+}
@@ -0,0 +1,338 @@
+//! Pins the bridge's wire format: every protocol message variant, as JSON,
+//! against `tests/fixtures/protocol_messages.json`. A change in any shape
+//! fails until `protocol::VERSION` is bumped and the file is regenerated
+//! with `UPDATE_GOLDEN=1 cargo test --test protocol_golden`.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::fs;
+use std::path::PathBuf;
+
+use sequencer::bridge::protocol::{
+    ApprovalStatus, Approve, BridgeEvent, BridgeRequest, BridgeResponse, CallRequest,
+    ContractEvent, ErrorCode, ErrorResponse, EventSubscription, Feature, Framing, Hello,
+    InterfaceRequest, Negotiated, ReceiptRequest, SetAuth, StorageRequest, Subscribe, VERSION,
+};
+use sequencer::contract::TxEffects;
+use sequencer::fields::Fr;
+use sequencer::tx_request::Gas;
+use sequencer::watcher::{ValueChange, WatchTarget};
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Golden {
+    version: u32,
+    messages: BTreeMap<String, Value>,
+}
+
+fn golden_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/protocol_messages.json")
+}
+
+/// Decodes the golden JSON and checks it is the sample message again.
+type Check = Box<dyn Fn(&Value) -> Result<(), String>>;
+
+/// One sample message, checked against its golden JSON.
+struct Sample {
+    name: String,
+    json: Value,
+    check: Check,
+}
+
+fn sample<T>(name: &str, message: T) -> Sample
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug + 'static,
+{
+    let json = serde_json::to_value(&message).unwrap();
+    let check = move |golden: &Value| {
+        let decoded: T = serde_json::from_value(golden.clone()).map_err(|e| e.to_string())?;
+        if decoded != message {
+            return Err(format!("decodes to {:?}", decoded));
+        }
+        for framing in [Framing::Json, Framing::Cbor] {
+            let bytes = framing.encode(&message)?;
+            if framing.decode::<T>(&bytes)? != message {
+                return Err(format!("does not round-trip through {:?}", framing));
+            }
+        }
+        Ok(())
+    };
+    Sample {
+        name: name.to_string(),
+        json,
+        check: Box::new(check),
+    }
+}
+
+fn storage_target() -> WatchTarget {
+    WatchTarget::PublicStorage {
+        contract: "0x12".to_string(),
+        slot: Fr::from(2u8),
+    }
+}
+
+fn samples() -> Vec<Sample> {
+    let set = CallRequest {
+        contract: Some("0x12".to_string()),
+        function: Some("set_feeds".to_string()),
+        args: Some(vec![json!([1, 2, 3]), json!("214")]),
+        value: None,
+        force_refresh: false,
+        auth: Some(SetAuth {
+            operator: "0x0b".to_string(),
+            nonce: 7,
+            expiry: 1_700_000_000,
+            signature: "0x5151".to_string(),
+        }),
+        idempotency_key: Some("retry-7".to_string()),
+    };
+    let mut samples = vec![
+        sample(
+            "request/hello",
+            BridgeRequest::Hello(Hello {
+                framing: vec![Framing::Cbor, Framing::Json],
+                version: Some(2),
+                min_version: Some(1),
+                features: vec![
+                    Feature::BinaryFraming,
+                    Feature::Subscriptions,
+                    Feature::Auth,
+                ],
+            }),
+        ),
+        sample(
+            "request/hello_legacy",
+            BridgeRequest::Hello(Hello {
+                framing: vec![Framing::Json],
+                ..Hello::default()
+            }),
+        ),
+        sample("request/set", BridgeRequest::Set(set)),
+        sample(
+            "request/set_legacy",
+            BridgeRequest::Set(CallRequest {
+                value: Some(json!(214)),
+                ..CallRequest::default()
+            }),
+        ),
+        sample(
+            "request/get",
+            BridgeRequest::Get(CallRequest {
+                contract: Some("0x12".to_string()),
+                function: Some("read_field_in_map".to_string()),
+                args: Some(vec![json!(1)]),
+                force_refresh: true,
+                ..CallRequest::default()
+            }),
+        ),
+        sample(
+            "request/subscribe_target",
+            BridgeRequest::Subscribe(Subscribe::Target {
+                target: storage_target(),
+            }),
+        ),
+        sample(
+            "request/subscribe_events",
+            BridgeRequest::Subscribe(Subscribe::Events {
+                events: EventSubscription {
+                    contract: "0x12".to_string(),
+                    event: "PriceUpdated".to_string(),
+                },
+            }),
+        ),
+        sample(
+            "request/approve",
+            BridgeRequest::Approve(Approve {
+                id: "0x01".to_string(),
+                operator: "0x02".to_string(),
+                signature: "0x03".to_string(),
+            }),
+        ),
+        sample(
+            "request/storage",
+            BridgeRequest::Storage(StorageRequest {
+                contract: Some("0x12".to_string()),
+                variable: "just_field".to_string(),
+            }),
+        ),
+        sample(
+            "request/receipt",
+            BridgeRequest::Receipt(ReceiptRequest {
+                tx_hash: "0x04".to_string(),
+            }),
+        ),
+        sample(
+            "request/interface",
+            BridgeRequest::Interface(InterfaceRequest {
+                contract: Some("0x12".to_string()),
+            }),
+        ),
+        sample("response/sent", BridgeResponse::sent("0xabc".to_string())),
+        sample(
+            "response/dry_run",
+            BridgeResponse::would_send(TxEffects {
+                nullifiers: vec![Fr::from(1u8)],
+                gas_used: Some(Gas {
+                    da_gas: 1_024,
+                    l2_gas: 35_000,
+                }),
+                ..TxEffects::default()
+            }),
+        ),
+        sample(
+            "response/value",
+            BridgeResponse::value(json!({ "values": ["0x01", true, null] }), true),
+        ),
+        sample("response/ok", BridgeResponse::ok()),
+        sample(
+            "response/awaiting_approval",
+            BridgeResponse::awaiting_approval(ApprovalStatus {
+                id: "0x01".to_string(),
+                approvals: 1,
+                threshold: 2,
+            }),
+        ),
+        sample(
+            "response/welcome",
+            BridgeResponse::welcome(
+                Framing::Cbor,
+                Negotiated {
+                    version: 2,
+                    features: vec![Feature::BinaryFraming, Feature::Subscriptions],
+                },
+            ),
+        ),
+        sample(
+            "response/error_legacy",
+            BridgeResponse {
+                success: false,
+                error: Some("no such contract".to_string()),
+                code: Some(ErrorCode::NotFound),
+                ..BridgeResponse::default()
+            },
+        ),
+        sample(
+            "response/failed",
+            BridgeResponse::failed_with_details(
+                ErrorCode::PxeUnavailable,
+                "connection refused",
+                json!({ "url": "http://localhost:8080" }),
+            ),
+        ),
+        sample(
+            "event/value_changed",
+            BridgeResponse::push(BridgeEvent::ValueChanged(ValueChange {
+                target: storage_target(),
+                block: 7,
+                previous: json!(Fr::from(700u16)),
+                current: json!(Fr::from(214u8)),
+            })),
+        ),
+        sample(
+            "event/contract_event",
+            BridgeResponse::push(BridgeEvent::ContractEvent(ContractEvent {
+                contract: "0x12".to_string(),
+                event: "Main::PriceUpdated".to_string(),
+                block: 8,
+                tx_hash: "0x05".to_string(),
+                payload: json!({ "feed_id": "1", "price": "214" }),
+            })),
+        ),
+        sample("negotiated/legacy", Negotiated::legacy()),
+    ];
+    let codes = [
+        ErrorCode::InvalidRequest,
+        ErrorCode::Encoding,
+        ErrorCode::NotFound,
+        ErrorCode::Upstream,
+        ErrorCode::PxeUnavailable,
+        ErrorCode::TxReverted,
+        ErrorCode::Unauthorized,
+        ErrorCode::PayloadTooLarge,
+    ];
+    for code in codes {
+        let name = serde_json::to_value(code).unwrap();
+        samples.push(sample(
+            &format!("error/{}", name.as_str().unwrap()),
+            ErrorResponse::new(code, "message"),
+        ));
+    }
+    samples
+}
+
+#[test]
+fn test_protocol_messages_match_golden() {
+    let samples = samples();
+    let actions: Vec<String> = samples
+        .iter()
+        .filter_map(|s| s.json.get("action").and_then(Value::as_str))
+        .map(str::to_string)
+        .collect();
+    for action in [
+        "hello",
+        "set",
+        "get",
+        "subscribe",
+        "approve",
+        "storage",
+        "receipt",
+        "interface",
+    ] {
+        assert!(
+            actions.iter().any(|a| a == action),
+            "no sample for {}",
+            action
+        );
+    }
+    let current: BTreeMap<String, Value> = samples
+        .iter()
+        .map(|s| (s.name.clone(), s.json.clone()))
+        .collect();
+
+    let path = golden_path();
+    let golden: Option<Golden> = fs::read(&path)
+        .ok()
+        .map(|bytes| serde_json::from_slice(&bytes).unwrap());
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        if let Some(golden) = &golden {
+            assert!(
+                golden.messages == current || golden.version < VERSION,
+                "Message shapes changed; bump protocol::VERSION past {} first",
+                golden.version
+            );
+        }
+        let golden = Golden {
+            version: VERSION,
+            messages: current,
+        };
+        let mut text = serde_json::to_string_pretty(&golden).unwrap();
+        text.push('\n');
+        fs::write(&path, text).unwrap();
+        return;
+    }
+
+    let golden = golden.unwrap_or_else(|| panic!("{} is missing", path.display()));
+    for sample in &samples {
+        let recorded = golden
+            .messages
+            .get(&sample.name)
+            .unwrap_or_else(|| panic!("{} is new: regenerate with UPDATE_GOLDEN=1", sample.name));
+        assert_eq!(
+            &sample.json, recorded,
+            "{}'s wire format changed: bump protocol::VERSION and regenerate with UPDATE_GOLDEN=1",
+            sample.name
+        );
+        (sample.check)(recorded).unwrap_or_else(|e| panic!("{}: {}", sample.name, e));
+    }
+    assert_eq!(
+        golden.messages.len(),
+        samples.len(),
+        "a message was dropped: bump protocol::VERSION and regenerate with UPDATE_GOLDEN=1"
+    );
+    assert_eq!(
+        golden.version, VERSION,
+        "protocol::VERSION changed: regenerate with UPDATE_GOLDEN=1"
+    );
+}
@@ -0,0 +1,15 @@
+// Regenerates the `expected` outputs of the Rust encoder's test vectors
+// (sequencer/tests/vectors/*.json) with aztec.js: `yarn tsx src/encoder-vectors.ts`
+import { encodeArguments, type FunctionAbi } from "@aztec/stdlib/abi";
+import { readdirSync, readFileSync, writeFileSync } from "fs";
+import { join } from "path";
+
+const dir = join(import.meta.dirname, "../sequencer/tests/vectors");
+
+for (const file of readdirSync(dir).filter((f) => f.endsWith(".json"))) {
+    const path = join(dir, file);
+    const vector = JSON.parse(readFileSync(path, "utf8"));
+    vector.expected = encodeArguments(vector.abi as FunctionAbi, vector.args).map((f) => f.toString());
+    writeFileSync(path, JSON.stringify(vector, null, 2) + "\n");
+    console.log(`${file}: ${vector.expected.length} fields`);
+}
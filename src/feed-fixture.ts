@@ -0,0 +1,53 @@
+// Regenerates the feed contract fixture (sequencer/tests/fixtures/feed_contract.json) and
+// sequencer/contract-Main.json from the compiled contract:
+// `cd contract && aztec-nargo compile`, then `yarn tsx src/feed-fixture.ts`
+import { loadContractArtifact, type FunctionAbi } from "@aztec/stdlib/abi";
+import { copyFileSync, readFileSync, writeFileSync } from "fs";
+import { join } from "path";
+
+const compiled = join(import.meta.dirname, "../contract/target/contract-Main.json");
+const fixture = join(import.meta.dirname, "../sequencer/tests/fixtures/feed_contract.json");
+
+const artifact = loadContractArtifact(JSON.parse(readFileSync(compiled, "utf8")));
+
+// The bridge only needs the ABIs, so bytecode and debug symbols are left out.
+const strip = (f: FunctionAbi & { functionType: string }) => ({
+    name: f.name,
+    functionType: f.functionType,
+    ...(f.isInitializer && { isInitializer: true }),
+    ...(f.isStatic && { isStatic: true }),
+    parameters: f.parameters,
+    ...(f.returnTypes.length > 0 && { returnTypes: f.returnTypes }),
+    bytecode: "",
+    debugSymbols: "",
+});
+
+const functions = [...artifact.functions, ...artifact.nonDispatchPublicFunctions]
+    .filter((f) => f.name !== "public_dispatch" && f.functionType !== "utility")
+    .map(strip);
+
+// The compiled artifact has no storage types, so they are carried over from the current fixture.
+const previous = JSON.parse(readFileSync(fixture, "utf8")).storageLayout ?? {};
+const storageLayout = Object.fromEntries(
+    Object.entries(artifact.storageLayout).map(([name, { slot }]) => [
+        name,
+        { slot: slot.toString(), ...(previous[name]?.type && { type: previous[name].type }) },
+    ]),
+);
+
+const fileMap = Object.fromEntries(
+    Object.entries(artifact.fileMap)
+        .filter(([, file]) => file.path.endsWith("contract/src/main.nr"))
+        .map(([id, file]) => [id, { source: file.source, path: "contract/src/main.nr" }]),
+);
+
+writeFileSync(
+    fixture,
+    JSON.stringify(
+        { name: artifact.name, functions, nonDispatchPublicFunctions: [], storageLayout, notes: artifact.notes, fileMap },
+        null,
+        2,
+    ) + "\n",
+);
+copyFileSync(compiled, join(import.meta.dirname, "../sequencer/contract-Main.json"));
+console.log(`feed_contract.json: ${functions.map((f) => f.name).join(", ")}`);